@@ -0,0 +1,346 @@
+//! Retrieval quality evaluation (`demongrep bench eval`)
+//!
+//! Runs a labelled dataset through the same ranking as `demongrep search`
+//! and scores the results, so the effect of a ranking change, model or
+//! config setting is measured instead of eyeballed. Each dataset line is a
+//! JSON object:
+//!
+//! ```json
+//! {"query": "where are refresh tokens rotated", "expected": [{"path": "src/auth/token.rs", "start_line": 40, "end_line": 80}]}
+//! ```
+//!
+//! A result is relevant when it is in an expected file and, if the expected
+//! entry has a line range, overlaps it. Each expected entry counts once.
+
+use anyhow::{anyhow, Context, Result};
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+use crate::config::Config;
+use crate::database::DatabaseManager;
+use crate::embed::EmbeddingService;
+use crate::rerank::NeuralReranker;
+use crate::search::{rank_results, ParsedQuery};
+use crate::vectordb::{SearchResult, StoreOptions};
+
+/// Where a correct answer lives: a file, or lines of one (as shown in search results)
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct ExpectedLocation {
+    pub path: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub start_line: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub end_line: Option<usize>,
+}
+
+impl ExpectedLocation {
+    fn matches(&self, result: &SearchResult) -> bool {
+        if result.path.trim_start_matches("./") != self.path.trim_start_matches("./") {
+            return false;
+        }
+        let start = self.start_line.unwrap_or(0);
+        let end = self.end_line.unwrap_or(usize::MAX);
+        result.start_line <= end && start <= result.end_line
+    }
+}
+
+/// One labelled query
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct EvalCase {
+    pub query: String,
+    pub expected: Vec<ExpectedLocation>,
+}
+
+/// Parse a JSONL dataset; blank lines and lines starting with `#` are skipped
+pub fn parse_dataset(content: &str) -> Result<Vec<EvalCase>> {
+    content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+        .map(|(index, line)| {
+            let case: EvalCase =
+                serde_json::from_str(line).with_context(|| format!("Invalid dataset entry on line {}", index + 1))?;
+            if case.expected.is_empty() {
+                return Err(anyhow!("Line {} has no expected locations", index + 1));
+            }
+            Ok(case)
+        })
+        .collect()
+}
+
+/// Scores of one query's top k results
+#[derive(Debug, Clone, Serialize)]
+pub struct QueryScore {
+    pub query: String,
+    /// Fraction of expected locations found
+    pub recall: f64,
+    /// 1 / rank of the first relevant result (0 when none is in the top k)
+    pub reciprocal_rank: f64,
+    pub ndcg: f64,
+    /// Rank (1-based) of the first relevant result
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub first_hit: Option<usize>,
+    /// Expected locations not found in the top k
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub missed: Vec<ExpectedLocation>,
+}
+
+/// Score ranked results against the expected locations
+///
+/// Relevance is binary. A result only gains for the first expected location
+/// it matches that no higher-ranked result matched already, so repeated hits
+/// on one location don't inflate nDCG.
+pub fn score_query(case: &EvalCase, results: &[SearchResult], k: usize) -> QueryScore {
+    let mut found = vec![false; case.expected.len()];
+    let mut dcg = 0.0;
+    let mut first_hit = None;
+
+    for (index, result) in results.iter().take(k).enumerate() {
+        let rank = index + 1;
+        let new_match = case
+            .expected
+            .iter()
+            .enumerate()
+            .find(|(i, expected)| !found[*i] && expected.matches(result));
+        if let Some((i, _)) = new_match {
+            found[i] = true;
+            dcg += 1.0 / (rank as f64 + 1.0).log2();
+            first_hit.get_or_insert(rank);
+        }
+    }
+
+    let ideal: f64 = (1..=case.expected.len().min(k)).map(|rank| 1.0 / (rank as f64 + 1.0).log2()).sum();
+    let hits = found.iter().filter(|f| **f).count();
+
+    QueryScore {
+        query: case.query.clone(),
+        recall: hits as f64 / case.expected.len() as f64,
+        reciprocal_rank: first_hit.map(|rank| 1.0 / rank as f64).unwrap_or(0.0),
+        ndcg: if ideal > 0.0 { dcg / ideal } else { 0.0 },
+        first_hit,
+        missed: case
+            .expected
+            .iter()
+            .zip(&found)
+            .filter(|(_, found)| !**found)
+            .map(|(expected, _)| expected.clone())
+            .collect(),
+    }
+}
+
+/// Result of `demongrep bench eval`
+#[derive(Debug, Clone, Serialize)]
+pub struct EvalReport {
+    pub model: String,
+    pub queries: usize,
+    pub k: usize,
+    pub reranked: bool,
+    pub prefer_project: bool,
+    /// Mean recall@k
+    pub recall: f64,
+    /// Mean reciprocal rank within the top k
+    pub mrr: f64,
+    /// Mean nDCG@k
+    pub ndcg: f64,
+    pub results: Vec<QueryScore>,
+}
+
+/// Score every case against already loaded databases
+pub fn score_cases(
+    manager: &DatabaseManager,
+    embedding_service: &mut EmbeddingService,
+    cases: &[EvalCase],
+    k: usize,
+    mut reranker: Option<&mut NeuralReranker>,
+    prefer_project: bool,
+    config: &Config,
+) -> Result<Vec<QueryScore>> {
+    let mut results = Vec::with_capacity(cases.len());
+    for case in cases {
+        let query = ParsedQuery::parse(&case.query)?;
+        // Bypass the query cache so query timings include the model
+        let embedding = embedding_service.embed_query_uncached(&query.text)?;
+        let ranked = rank_results(
+            manager,
+            &query,
+            &embedding,
+            k,
+            reranker.as_deref_mut(),
+            config.search.rerank_top,
+            prefer_project,
+            config,
+        )?;
+        results.push(score_query(case, &ranked, k));
+    }
+    Ok(results)
+}
+
+/// Mean of one metric over all queries
+pub fn mean_score(results: &[QueryScore], metric: fn(&QueryScore) -> f64) -> f64 {
+    if results.is_empty() {
+        return 0.0;
+    }
+    results.iter().map(metric).sum::<f64>() / results.len() as f64
+}
+
+/// Run every case through the search ranking and score the top k
+pub fn evaluate(
+    path: Option<PathBuf>,
+    cases: &[EvalCase],
+    k: usize,
+    rerank: bool,
+    prefer_project: bool,
+    config: &Config,
+) -> Result<EvalReport> {
+    if cases.is_empty() {
+        return Err(anyhow!("No queries to evaluate"));
+    }
+    let k = k.max(1);
+
+    let manager = DatabaseManager::load_with_options(path, &StoreOptions::from_config(config))?;
+    let mut embedding_service = EmbeddingService::with_model(manager.model_type())?;
+    let mut reranker = if rerank { Some(NeuralReranker::new()?) } else { None };
+
+    let results = score_cases(&manager, &mut embedding_service, cases, k, reranker.as_mut(), prefer_project, config)?;
+
+    Ok(EvalReport {
+        model: manager.model_type().short_name().to_string(),
+        queries: results.len(),
+        k,
+        reranked: rerank,
+        prefer_project,
+        recall: mean_score(&results, |r| r.recall),
+        mrr: mean_score(&results, |r| r.reciprocal_rank),
+        ndcg: mean_score(&results, |r| r.ndcg),
+        results,
+    })
+}
+
+/// Run the evaluation and print the scores
+pub async fn eval(
+    dataset: PathBuf,
+    path: Option<PathBuf>,
+    k: usize,
+    rerank: bool,
+    prefer_project: bool,
+    json: bool,
+    config: &Config,
+) -> Result<()> {
+    if json {
+        crate::output::set_quiet(true);
+    }
+
+    let content = std::fs::read_to_string(&dataset)
+        .map_err(|e| anyhow!("Could not read {}: {}", dataset.display(), e))?;
+    let cases = parse_dataset(&content)?;
+    let report = evaluate(path, &cases, k, rerank, prefer_project, config)?;
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(());
+    }
+
+    println!("{}", "🎯 Retrieval quality".bright_cyan().bold());
+    println!("{}", "=".repeat(60));
+    println!(
+        "🔎 {} queries, top {} • model {}{}{}",
+        report.queries,
+        report.k,
+        report.model,
+        if report.reranked { " • reranked" } else { "" },
+        if report.prefer_project { " • prefer-project" } else { "" }
+    );
+    println!();
+    println!("{:<50} {:>7} {:>6} {:>6} {:>5}", "Query", "Recall", "RR", "nDCG", "Hit");
+    for result in &report.results {
+        let query: String = result.query.chars().take(48).collect();
+        let hit = result.first_hit.map(|r| r.to_string()).unwrap_or_else(|| "-".to_string());
+        let line = format!(
+            "{:<50} {:>6.0}% {:>6.2} {:>6.2} {:>5}",
+            query,
+            result.recall * 100.0,
+            result.reciprocal_rank,
+            result.ndcg,
+            hit
+        );
+        if result.first_hit.is_none() {
+            println!("{}", line.red());
+        } else {
+            println!("{}", line);
+        }
+    }
+    println!();
+    println!(
+        "{}",
+        format!(
+            "Recall@{}: {:.1}%   MRR: {:.3}   nDCG@{}: {:.3}",
+            report.k,
+            report.recall * 100.0,
+            report.mrr,
+            report.k,
+            report.ndcg
+        )
+        .bold()
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(path: &str, start_line: usize, end_line: usize) -> SearchResult {
+        SearchResult {
+            path: path.to_string(),
+            start_line,
+            end_line,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_parse_dataset() {
+        let cases = parse_dataset(
+            "# auth\n{\"query\": \"token rotation\", \"expected\": [{\"path\": \"src/auth.rs\", \"start_line\": 10, \"end_line\": 20}]}\n\n",
+        )
+        .unwrap();
+        assert_eq!(cases.len(), 1);
+        assert_eq!(cases[0].expected[0].start_line, Some(10));
+
+        assert!(parse_dataset("{\"query\": \"q\", \"expected\": []}").is_err());
+        assert!(parse_dataset("not json").is_err());
+    }
+
+    #[test]
+    fn test_score_query() {
+        let case = EvalCase {
+            query: "token rotation".to_string(),
+            expected: vec![
+                ExpectedLocation { path: "src/auth.rs".to_string(), start_line: Some(10), end_line: Some(20) },
+                ExpectedLocation { path: "src/session.rs".to_string(), start_line: None, end_line: None },
+            ],
+        };
+
+        // Right file, wrong lines; then a hit; then a repeat of the same location
+        let results = vec![result("src/auth.rs", 50, 60), result("./src/auth.rs", 15, 30), result("src/auth.rs", 18, 19)];
+        let score = score_query(&case, &results, 10);
+        assert_eq!(score.recall, 0.5);
+        assert_eq!(score.first_hit, Some(2));
+        assert_eq!(score.reciprocal_rank, 0.5);
+        let ideal = 1.0 + 1.0 / 3f64.log2();
+        assert!((score.ndcg - (1.0 / 3f64.log2()) / ideal).abs() < 1e-9);
+        assert_eq!(score.missed.len(), 1);
+        assert_eq!(score.missed[0].path, "src/session.rs");
+
+        // Both found at the top: perfect scores
+        let perfect = score_query(&case, &[result("src/session.rs", 0, 5), result("src/auth.rs", 10, 12)], 10);
+        assert_eq!((perfect.recall, perfect.reciprocal_rank), (1.0, 1.0));
+        assert!((perfect.ndcg - 1.0).abs() < 1e-9);
+
+        // Outside the top k nothing counts
+        let cut = score_query(&case, &results, 1);
+        assert_eq!((cut.recall, cut.reciprocal_rank, cut.ndcg), (0.0, 0.0, 0.0));
+    }
+}
@@ -1 +1,485 @@
-// Benchmarking framework and utilities
+//! Benchmarks run from the CLI (`demongrep bench ...`)
+//!
+//! `bench chunk` measures chunker throughput per language over a real tree,
+//! so regressions from new extractors or grammar upgrades show up before a
+//! release. File contents are read up front so only chunking is timed.
+//!
+//! `bench search` runs a query set through hybrid retrieval at several
+//! depths. Queries are embedded once, so only retrieval and fusion are timed.
+//! Accuracy is the overlap of each depth's top results with the deepest
+//! retrieval, plus the hit rate when the query file names expected paths.
+//!
+//! `bench eval` scores retrieval quality (recall@k, MRR, nDCG) against a
+//! labelled dataset, using the same ranking and settings as `search`.
+//! `bench models` runs that dataset against a scratch index per embedding
+//! model to compare accuracy, index time and index size.
+//!
+//! `bench index` times each phase of a full index into a scratch database
+//! and reports throughput and peak memory, for tracking regressions in CI.
+
+mod eval;
+mod models;
+mod pipeline;
+
+pub use eval::eval;
+pub use models::models;
+pub use pipeline::index;
+
+use anyhow::{anyhow, Result};
+use colored::Colorize;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use crate::chunker::{GrammarManager, SemanticChunker};
+use crate::config::Config;
+use crate::database::DatabaseManager;
+use crate::embed::EmbeddingService;
+use crate::file::{FileWalker, Language};
+use crate::fts::FtsStore;
+use crate::rerank::Fusion;
+use crate::search::{adaptive_depth, retrieve, Pipeline, QueryFilters, MAX_RETRIEVAL_DEPTH};
+use crate::vectordb::StoreOptions;
+
+/// Chunking throughput for one language
+#[derive(Debug, Clone, Serialize)]
+pub struct LanguageThroughput {
+    pub language: String,
+    pub files: usize,
+    pub bytes: usize,
+    /// Chunks produced per iteration
+    pub chunks: usize,
+    /// Time spent chunking, summed over all iterations
+    pub chunk_ms: f64,
+    pub files_per_sec: f64,
+    pub chunks_per_sec: f64,
+    pub mb_per_sec: f64,
+    /// Cold grammar load and parser setup (None for languages without a grammar)
+    pub grammar_load_ms: Option<f64>,
+}
+
+/// Result of `demongrep bench chunk`
+#[derive(Debug, Clone, Serialize)]
+pub struct ChunkBenchReport {
+    pub root: String,
+    pub iterations: usize,
+    /// Time to construct a `SemanticChunker`
+    pub chunker_init_ms: f64,
+    pub total_files: usize,
+    pub total_chunks: usize,
+    pub files_per_sec: f64,
+    pub chunks_per_sec: f64,
+    /// Slowest languages first
+    pub languages: Vec<LanguageThroughput>,
+}
+
+#[derive(Default)]
+struct Accumulator {
+    files: usize,
+    bytes: usize,
+    chunks: usize,
+    elapsed: Duration,
+}
+
+fn ms(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+fn per_sec(count: usize, elapsed: Duration) -> f64 {
+    let secs = elapsed.as_secs_f64();
+    if secs > 0.0 {
+        count as f64 / secs
+    } else {
+        0.0
+    }
+}
+
+/// Cold grammar load plus `set_language` on a fresh parser
+fn grammar_load_time(language: Language) -> Option<Duration> {
+    let start = Instant::now();
+    let grammar = GrammarManager::new().get_grammar(language)?;
+    tree_sitter::Parser::new().set_language(&grammar).ok()?;
+    Some(start.elapsed())
+}
+
+/// Chunk every file under `root` `iterations` times and report throughput per language
+pub fn bench_chunker(root: PathBuf, config: &Config, iterations: usize) -> Result<ChunkBenchReport> {
+    let iterations = iterations.max(1);
+
+    let walker = FileWalker::new(root.clone()).with_excludes(&config.indexing.excludes);
+    let (files, _stats) = walker.walk()?;
+    let sources: Vec<_> = files
+        .into_iter()
+        .filter_map(|file| {
+            let content = std::fs::read_to_string(&file.path).ok()?;
+            Some((file, content))
+        })
+        .collect();
+
+    let init_start = Instant::now();
+    let mut chunker = SemanticChunker::from_config(&config.indexing);
+    let chunker_init = init_start.elapsed();
+
+    let mut by_language: HashMap<Language, Accumulator> = HashMap::new();
+    for iteration in 0..iterations {
+        for (file, content) in &sources {
+            let start = Instant::now();
+            let chunks = chunker.chunk_semantic(file.language, &file.path, content)?;
+            let elapsed = start.elapsed();
+
+            let acc = by_language.entry(file.language).or_default();
+            acc.elapsed += elapsed;
+            if iteration == 0 {
+                acc.files += 1;
+                acc.bytes += content.len();
+                acc.chunks += chunks.len();
+            }
+        }
+    }
+
+    let mut languages: Vec<LanguageThroughput> = by_language
+        .into_iter()
+        .map(|(language, acc)| LanguageThroughput {
+            language: language.name().to_string(),
+            files: acc.files,
+            bytes: acc.bytes,
+            chunks: acc.chunks,
+            chunk_ms: ms(acc.elapsed),
+            files_per_sec: per_sec(acc.files * iterations, acc.elapsed),
+            chunks_per_sec: per_sec(acc.chunks * iterations, acc.elapsed),
+            mb_per_sec: per_sec(acc.bytes * iterations, acc.elapsed) / (1024.0 * 1024.0),
+            grammar_load_ms: grammar_load_time(language).map(ms),
+        })
+        .collect();
+    languages.sort_by(|a, b| b.chunk_ms.partial_cmp(&a.chunk_ms).unwrap_or(std::cmp::Ordering::Equal));
+
+    let total_files: usize = languages.iter().map(|l| l.files).sum();
+    let total_chunks: usize = languages.iter().map(|l| l.chunks).sum();
+    let total_elapsed = Duration::from_secs_f64(languages.iter().map(|l| l.chunk_ms).sum::<f64>() / 1000.0);
+
+    Ok(ChunkBenchReport {
+        root: root.display().to_string(),
+        iterations,
+        chunker_init_ms: ms(chunker_init),
+        total_files,
+        total_chunks,
+        files_per_sec: per_sec(total_files * iterations, total_elapsed),
+        chunks_per_sec: per_sec(total_chunks * iterations, total_elapsed),
+        languages,
+    })
+}
+
+/// Run the chunker benchmark and print the results
+pub async fn chunk(path: Option<PathBuf>, iterations: usize, json: bool, config: &Config) -> Result<()> {
+    let root = path.unwrap_or_else(|| PathBuf::from("."));
+    let report = bench_chunker(root, config, iterations)?;
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(());
+    }
+
+    println!("{}", "⏱️  Chunker benchmark".bright_cyan().bold());
+    println!("{}", "=".repeat(60));
+    println!("📂 Root: {}", report.root);
+    println!("🔁 Iterations: {}", report.iterations);
+    println!("🧰 Chunker init: {:.2} ms", report.chunker_init_ms);
+    println!();
+    println!(
+        "{:<12} {:>7} {:>8} {:>10} {:>11} {:>9} {:>10}",
+        "Language", "Files", "Chunks", "Files/s", "Chunks/s", "MB/s", "Grammar"
+    );
+    for lang in &report.languages {
+        let grammar = lang
+            .grammar_load_ms
+            .map(|ms| format!("{:.2} ms", ms))
+            .unwrap_or_else(|| "-".to_string());
+        println!(
+            "{:<12} {:>7} {:>8} {:>10.0} {:>11.0} {:>9.2} {:>10}",
+            lang.language, lang.files, lang.chunks, lang.files_per_sec, lang.chunks_per_sec, lang.mb_per_sec, grammar
+        );
+    }
+    println!();
+    println!(
+        "{} {} files, {} chunks — {:.0} files/s, {:.0} chunks/s",
+        "Total:".bold(),
+        report.total_files,
+        report.total_chunks,
+        report.files_per_sec,
+        report.chunks_per_sec
+    );
+
+    Ok(())
+}
+
+/// One benchmark query, with the path a correct answer lives in (if known)
+#[derive(Debug, Clone, PartialEq)]
+pub struct BenchQuery {
+    pub query: String,
+    pub expected: Option<String>,
+}
+
+/// Parse a query file: one query per line, optionally `query<TAB>expected/path`
+///
+/// Blank lines and lines starting with `#` are skipped.
+pub fn parse_queries(content: &str) -> Vec<BenchQuery> {
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| match line.split_once('\t') {
+            Some((query, expected)) => BenchQuery {
+                query: query.trim().to_string(),
+                expected: Some(expected.trim().to_string()).filter(|e| !e.is_empty()),
+            },
+            None => BenchQuery {
+                query: line.to_string(),
+                expected: None,
+            },
+        })
+        .collect()
+}
+
+/// Latency and accuracy of hybrid retrieval at one depth
+#[derive(Debug, Clone, Serialize)]
+pub struct DepthResult {
+    pub depth: usize,
+    /// Whether this is the depth `search` picks on its own
+    pub adaptive: bool,
+    pub mean_ms: f64,
+    pub max_ms: f64,
+    /// Mean fraction of the reference top results also found at this depth
+    pub overlap: f64,
+    /// Fraction of queries with an expected path found in the top results
+    pub hit_rate: Option<f64>,
+}
+
+/// Result of `demongrep bench search`
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchBenchReport {
+    pub database: String,
+    pub total_chunks: usize,
+    pub queries: usize,
+    pub max_results: usize,
+    pub iterations: usize,
+    /// Depth the overlap is measured against
+    pub reference_depth: usize,
+    pub depths: Vec<DepthResult>,
+}
+
+/// Run every query at each depth and compare against the deepest retrieval
+pub fn bench_retrieval_depths(
+    path: Option<PathBuf>,
+    queries: &[BenchQuery],
+    depths: &[usize],
+    max_results: usize,
+    iterations: usize,
+    fusion: Fusion,
+    store_options: &StoreOptions,
+) -> Result<SearchBenchReport> {
+    if queries.is_empty() {
+        return Err(anyhow!("No queries to run"));
+    }
+    let iterations = iterations.max(1);
+
+    let manager = DatabaseManager::load_with_options(path, store_options)?;
+    let database = &manager.databases()[0];
+    let store = database.store();
+    let fts_store = FtsStore::open_readonly(&database.path).ok();
+    let total_chunks = store.chunk_count()?;
+
+    let mut embedding_service = EmbeddingService::with_model(manager.model_type())?;
+    let embeddings = queries
+        .iter()
+        .map(|q| embedding_service.embed_query(&q.query))
+        .collect::<Result<Vec<_>>>()?;
+
+    let top_ids = |query: &str, embedding: &[f32], depth: usize| -> Result<Vec<u32>> {
+        let no_filters = QueryFilters::default();
+        let (_, fused) =
+            retrieve(store, fts_store.as_ref(), query, embedding, Pipeline::Hybrid, depth, fusion, &no_filters)?;
+        Ok(fused.into_iter().take(max_results).map(|f| f.chunk_id).collect())
+    };
+
+    let reference_depth = MAX_RETRIEVAL_DEPTH.min(total_chunks.max(1));
+    let references = queries
+        .iter()
+        .zip(&embeddings)
+        .map(|(q, embedding)| top_ids(&q.query, embedding, reference_depth))
+        .collect::<Result<Vec<_>>>()?;
+
+    let adaptive = adaptive_depth(max_results, total_chunks);
+    let mut all_depths: Vec<usize> = depths.iter().copied().filter(|d| *d > 0).collect();
+    all_depths.push(adaptive);
+    all_depths.sort_unstable();
+    all_depths.dedup();
+
+    let labelled = queries.iter().filter(|q| q.expected.is_some()).count();
+    let mut results = Vec::with_capacity(all_depths.len());
+
+    for depth in all_depths {
+        let mut total = Duration::ZERO;
+        let mut slowest = Duration::ZERO;
+        let mut overlap = 0.0;
+        let mut hits = 0;
+
+        for ((q, embedding), reference) in queries.iter().zip(&embeddings).zip(&references) {
+            let mut ids = Vec::new();
+            for _ in 0..iterations {
+                let start = Instant::now();
+                ids = top_ids(&q.query, embedding, depth)?;
+                let elapsed = start.elapsed();
+                total += elapsed;
+                slowest = slowest.max(elapsed);
+            }
+
+            if !reference.is_empty() {
+                let reference: HashSet<&u32> = reference.iter().collect();
+                let shared = ids.iter().filter(|id| reference.contains(id)).count();
+                overlap += shared as f64 / reference.len() as f64;
+            } else {
+                overlap += 1.0;
+            }
+
+            if let Some(ref expected) = q.expected {
+                if contains_path(store, &ids, expected)? {
+                    hits += 1;
+                }
+            }
+        }
+
+        results.push(DepthResult {
+            depth,
+            adaptive: depth == adaptive,
+            mean_ms: ms(total) / (queries.len() * iterations) as f64,
+            max_ms: ms(slowest),
+            overlap: overlap / queries.len() as f64,
+            hit_rate: (labelled > 0).then(|| hits as f64 / labelled as f64),
+        });
+    }
+
+    Ok(SearchBenchReport {
+        database: database.path.display().to_string(),
+        total_chunks,
+        queries: queries.len(),
+        max_results,
+        iterations,
+        reference_depth,
+        depths: results,
+    })
+}
+
+/// Whether any of the chunks lives in a file whose path contains `expected`
+fn contains_path(store: &crate::vectordb::VectorStore, ids: &[u32], expected: &str) -> Result<bool> {
+    for id in ids {
+        if let Some(chunk) = store.get_chunk(*id)? {
+            if chunk.path.contains(expected) {
+                return Ok(true);
+            }
+        }
+    }
+    Ok(false)
+}
+
+/// Run the retrieval depth benchmark and print the results
+pub async fn search(
+    queries: PathBuf,
+    path: Option<PathBuf>,
+    depths: Vec<usize>,
+    max_results: usize,
+    iterations: usize,
+    json: bool,
+    config: &Config,
+) -> Result<()> {
+    if json {
+        crate::output::set_quiet(true);
+    }
+
+    let content = std::fs::read_to_string(&queries)
+        .map_err(|e| anyhow!("Could not read {}: {}", queries.display(), e))?;
+    let queries = parse_queries(&content);
+    let report = bench_retrieval_depths(
+        path,
+        &queries,
+        &depths,
+        max_results,
+        iterations,
+        config.search.hybrid_fusion(),
+        &StoreOptions::from_config(config),
+    )?;
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(());
+    }
+
+    println!("{}", "⏱️  Retrieval depth benchmark".bright_cyan().bold());
+    println!("{}", "=".repeat(60));
+    println!("💾 Database: {} ({} chunks)", report.database, report.total_chunks);
+    println!("🔎 Queries: {} × {} iterations, top {}", report.queries, report.iterations, report.max_results);
+    println!("📏 Overlap measured against depth {}", report.reference_depth);
+    println!();
+    println!("{:<14} {:>10} {:>10} {:>9} {:>8}", "Depth", "Mean ms", "Max ms", "Overlap", "Hit");
+    for result in &report.depths {
+        let label = if result.adaptive {
+            format!("{} (auto)", result.depth)
+        } else {
+            result.depth.to_string()
+        };
+        let hit = result
+            .hit_rate
+            .map(|h| format!("{:.0}%", h * 100.0))
+            .unwrap_or_else(|| "-".to_string());
+        println!(
+            "{:<14} {:>10.2} {:>10.2} {:>8.0}% {:>8}",
+            label,
+            result.mean_ms,
+            result.max_ms,
+            result.overlap * 100.0,
+            hit
+        );
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bench_chunker_counts_per_language() {
+        // Non-hidden prefix: the walker skips dot-directories
+        let dir = tempfile::Builder::new().prefix("bench").tempdir().unwrap();
+        std::fs::write(dir.path().join("lib.rs"), "fn a() {}\n\nfn b() {}\n").unwrap();
+        std::fs::write(dir.path().join("app.py"), "def a():\n    pass\n").unwrap();
+
+        let report = bench_chunker(dir.path().to_path_buf(), &Config::default(), 2).unwrap();
+        assert_eq!(report.iterations, 2);
+        assert_eq!(report.total_files, 2);
+        assert!(report.total_chunks >= 2);
+
+        let rust = report.languages.iter().find(|l| l.language == Language::Rust.name()).unwrap();
+        assert_eq!(rust.files, 1);
+        assert!(rust.grammar_load_ms.is_some());
+    }
+
+    #[test]
+    fn test_parse_queries() {
+        let queries = parse_queries("# comment\nauth middleware\tsrc/server\n\n  retry logic  \n");
+        assert_eq!(
+            queries,
+            vec![
+                BenchQuery {
+                    query: "auth middleware".to_string(),
+                    expected: Some("src/server".to_string()),
+                },
+                BenchQuery {
+                    query: "retry logic".to_string(),
+                    expected: None,
+                },
+            ]
+        );
+    }
+}
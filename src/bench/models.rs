@@ -0,0 +1,341 @@
+//! Embedding model comparison (`demongrep bench models`)
+//!
+//! Indexes the same sample of a project once per model into a scratch
+//! database, then scores each with the `bench eval` dataset, so picking a
+//! model comes down to a table of accuracy vs index time vs index size
+//! measured on the user's own code. The sample always includes every file
+//! the dataset expects results in; the rest is spread evenly over the tree.
+
+use anyhow::{anyhow, Result};
+use colored::Colorize;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use super::eval::{mean_score, parse_dataset, score_cases, EvalCase, QueryScore};
+use super::ms;
+use crate::chunker::{Chunk, SemanticChunker};
+use crate::config::Config;
+use crate::database::DatabaseManagerBuilder;
+use crate::embed::{EmbeddingService, ModelType};
+use crate::file::{FileInfo, FileWalker};
+use crate::fts::FtsStore;
+use crate::index::dir_size;
+use crate::info_print;
+use crate::rerank::NeuralReranker;
+use crate::vectordb::{StoreOptions, VectorStore};
+
+/// Path relative to the project root, with forward slashes
+fn relative_path(root: &Path, path: &Path) -> String {
+    let relative = path.strip_prefix(root).unwrap_or(path);
+    relative.to_string_lossy().replace('\\', "/")
+}
+
+/// Pick at most `max_files` files (0 = all), always keeping the `required` ones
+///
+/// The remaining slots are filled evenly across the walk order, so the sample
+/// covers the whole tree instead of its first directories.
+pub fn sample_files(files: Vec<FileInfo>, root: &Path, required: &HashSet<String>, max_files: usize) -> Vec<FileInfo> {
+    if max_files == 0 || files.len() <= max_files {
+        return files;
+    }
+
+    let (mut sample, rest): (Vec<FileInfo>, Vec<FileInfo>) = files
+        .into_iter()
+        .partition(|file| required.contains(&relative_path(root, &file.path)));
+
+    let slots = max_files.saturating_sub(sample.len());
+    if slots > 0 && !rest.is_empty() {
+        let step = rest.len() as f64 / slots.min(rest.len()) as f64;
+        let mut next = 0.0;
+        for (index, file) in rest.into_iter().enumerate() {
+            if index as f64 >= next && sample.len() < max_files.max(required.len()) {
+                sample.push(file);
+                next += step;
+            }
+        }
+    }
+    sample
+}
+
+/// Results for one model
+#[derive(Debug, Clone, Serialize)]
+pub struct ModelResult {
+    pub model: String,
+    pub dimensions: usize,
+    /// Model download and load time
+    pub load_ms: f64,
+    /// Embedding plus vector and full-text index build time
+    pub index_ms: f64,
+    pub index_bytes: u64,
+    pub recall: f64,
+    pub mrr: f64,
+    pub ndcg: f64,
+    /// Mean query embedding plus ranking time
+    pub query_ms: f64,
+    pub results: Vec<QueryScore>,
+}
+
+/// Result of `demongrep bench models`
+#[derive(Debug, Clone, Serialize)]
+pub struct ModelBenchReport {
+    pub root: String,
+    pub files: usize,
+    pub chunks: usize,
+    pub queries: usize,
+    pub k: usize,
+    pub reranked: bool,
+    pub models: Vec<ModelResult>,
+}
+
+/// Build a scratch index of `chunks` with one model and score the dataset against it
+#[allow(clippy::too_many_arguments)]
+fn bench_model(
+    model_type: ModelType,
+    chunks: &[Chunk],
+    cases: &[EvalCase],
+    k: usize,
+    reranker: Option<&mut NeuralReranker>,
+    prefer_project: bool,
+    config: &Config,
+) -> Result<ModelResult> {
+    let scratch = tempfile::tempdir()?;
+    let db_path = scratch.path().join("db");
+
+    let start = Instant::now();
+    let mut embedding_service = EmbeddingService::with_model(model_type)?;
+    let load_ms = ms(start.elapsed());
+
+    let start = Instant::now();
+    let embedded = embedding_service.embed_chunks(chunks.to_vec())?;
+    {
+        let mut store = VectorStore::with_options(&db_path, model_type.dimensions(), &StoreOptions::from_config(config))?;
+        let ids = store.insert_chunks_with_ids(embedded.clone())?;
+        store.build_index()?;
+        store.save_db_metadata(model_type.name(), model_type.dimensions(), true)?;
+
+        let mut fts_store = FtsStore::with_analyzer(&db_path, &config.fts)?;
+        for (chunk, id) in embedded.iter().zip(&ids) {
+            fts_store.add_chunk(
+                *id,
+                &chunk.chunk.content,
+                &chunk.chunk.path,
+                chunk.chunk.name.as_deref(),
+                chunk.chunk.signature.as_deref(),
+                chunk.chunk.docstring.as_deref(),
+                &format!("{:?}", chunk.chunk.kind),
+                &chunk.chunk.string_literals,
+                &chunk.chunk.tags,
+                chunk.chunk.package.as_deref(),
+            )?;
+        }
+        fts_store.commit()?;
+    }
+    let index_ms = ms(start.elapsed());
+    let index_bytes = dir_size(&db_path);
+
+    let manager = DatabaseManagerBuilder::new()
+        .add_database(db_path)
+        .with_model_type(model_type)
+        .with_dimensions(model_type.dimensions())
+        .with_options(StoreOptions::from_config(config))
+        .build()?;
+
+    let start = Instant::now();
+    let results = score_cases(&manager, &mut embedding_service, cases, k, reranker, prefer_project, config)?;
+    let query_ms = ms(start.elapsed()) / cases.len().max(1) as f64;
+
+    Ok(ModelResult {
+        model: model_type.short_name().to_string(),
+        dimensions: model_type.dimensions(),
+        load_ms,
+        index_ms,
+        index_bytes,
+        recall: mean_score(&results, |r| r.recall),
+        mrr: mean_score(&results, |r| r.reciprocal_rank),
+        ndcg: mean_score(&results, |r| r.ndcg),
+        query_ms,
+        results,
+    })
+}
+
+/// Index a sample of `root` with each model and score the dataset against each index
+#[allow(clippy::too_many_arguments)]
+pub fn bench_models(
+    root: PathBuf,
+    models: &[ModelType],
+    cases: &[EvalCase],
+    max_files: usize,
+    k: usize,
+    rerank: bool,
+    prefer_project: bool,
+    config: &Config,
+) -> Result<ModelBenchReport> {
+    if models.is_empty() {
+        return Err(anyhow!("No models to compare"));
+    }
+    if cases.is_empty() {
+        return Err(anyhow!("No queries to evaluate"));
+    }
+    let k = k.max(1);
+    let root = root.canonicalize()?;
+
+    let walker = FileWalker::new(root.clone()).with_excludes(&config.indexing.excludes);
+    let (files, _stats) = walker.walk()?;
+    let required: HashSet<String> = cases
+        .iter()
+        .flat_map(|case| &case.expected)
+        .map(|expected| expected.path.trim_start_matches("./").to_string())
+        .collect();
+    let files = sample_files(files, &root, &required, max_files);
+
+    // Chunk once with paths relative to the root, as the dataset writes them
+    let mut chunker = SemanticChunker::from_config(&config.indexing);
+    let mut chunks = Vec::new();
+    for file in &files {
+        let Ok(content) = std::fs::read_to_string(&file.path) else {
+            continue;
+        };
+        let relative = PathBuf::from(relative_path(&root, &file.path));
+        chunks.extend(chunker.chunk_semantic(file.language, &relative, &content)?);
+    }
+    if chunks.is_empty() {
+        return Err(anyhow!("No chunks to index under {}", root.display()));
+    }
+
+    // The reranker doesn't depend on the embedding model, so load it once
+    let mut reranker = if rerank { Some(NeuralReranker::new()?) } else { None };
+
+    let mut results = Vec::with_capacity(models.len());
+    for &model_type in models {
+        info_print!("🧠 Indexing {} chunks with {}...", chunks.len(), model_type.short_name());
+        results.push(bench_model(model_type, &chunks, cases, k, reranker.as_mut(), prefer_project, config)?);
+    }
+
+    Ok(ModelBenchReport {
+        root: root.display().to_string(),
+        files: files.len(),
+        chunks: chunks.len(),
+        queries: cases.len(),
+        k,
+        reranked: rerank,
+        models: results,
+    })
+}
+
+/// Parse a comma-separated model list such as `minilm-l6-q,bge-small,jina-code`
+pub fn parse_models(models: &[String]) -> Result<Vec<ModelType>> {
+    models
+        .iter()
+        .map(|name| {
+            ModelType::from_str(name.trim()).ok_or_else(|| {
+                let available: Vec<&str> = ModelType::all().iter().map(|m| m.short_name()).collect();
+                anyhow!("Unknown model '{}'. Available: {}", name.trim(), available.join(", "))
+            })
+        })
+        .collect()
+}
+
+/// Run the model comparison and print the table
+#[allow(clippy::too_many_arguments)]
+pub async fn models(
+    models: Vec<String>,
+    dataset: PathBuf,
+    path: Option<PathBuf>,
+    max_files: usize,
+    k: usize,
+    rerank: bool,
+    prefer_project: bool,
+    json: bool,
+    config: &Config,
+) -> Result<()> {
+    if json {
+        crate::output::set_quiet(true);
+    }
+
+    let models = parse_models(&models)?;
+    let content = std::fs::read_to_string(&dataset)
+        .map_err(|e| anyhow!("Could not read {}: {}", dataset.display(), e))?;
+    let cases = parse_dataset(&content)?;
+    let root = path.unwrap_or_else(|| PathBuf::from("."));
+    let report = bench_models(root, &models, &cases, max_files, k, rerank, prefer_project, config)?;
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(());
+    }
+
+    println!("{}", "🧠 Model comparison".bright_cyan().bold());
+    println!("{}", "=".repeat(60));
+    println!("📂 Sample: {} files, {} chunks", report.files, report.chunks);
+    println!(
+        "🔎 Queries: {}, top {}{}",
+        report.queries,
+        report.k,
+        if report.reranked { " • reranked" } else { "" }
+    );
+    println!();
+    println!(
+        "{:<18} {:>5} {:>8} {:>6} {:>6} {:>10} {:>9} {:>9}",
+        "Model", "Dims", "Recall", "MRR", "nDCG", "Index s", "Size MB", "Query ms"
+    );
+    let best = report.models.iter().map(|m| m.ndcg).fold(f64::MIN, f64::max);
+    for model in &report.models {
+        let line = format!(
+            "{:<18} {:>5} {:>7.1}% {:>6.3} {:>6.3} {:>10.1} {:>9.1} {:>9.1}",
+            model.model,
+            model.dimensions,
+            model.recall * 100.0,
+            model.mrr,
+            model.ndcg,
+            model.index_ms / 1000.0,
+            model.index_bytes as f64 / (1024.0 * 1024.0),
+            model.query_ms
+        );
+        if model.ndcg == best {
+            println!("{}", line.green());
+        } else {
+            println!("{}", line);
+        }
+    }
+    println!();
+    println!("Index time excludes model download and load. Use one with `demongrep index --model <name>`.");
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file::Language;
+
+    fn file(path: &str) -> FileInfo {
+        FileInfo {
+            path: PathBuf::from("/repo").join(path),
+            language: Language::Rust,
+            size: 0,
+        }
+    }
+
+    #[test]
+    fn test_sample_files() {
+        let files: Vec<FileInfo> = (0..10).map(|i| file(&format!("src/f{}.rs", i))).collect();
+        let required: HashSet<String> = ["src/f9.rs".to_string()].into_iter().collect();
+
+        let sample = sample_files(files.clone(), Path::new("/repo"), &required, 4);
+        let names: Vec<String> = sample.iter().map(|f| relative_path(Path::new("/repo"), &f.path)).collect();
+        assert_eq!(names, ["src/f9.rs", "src/f0.rs", "src/f3.rs", "src/f6.rs"]);
+
+        // 0 and a limit above the file count keep everything
+        assert_eq!(sample_files(files.clone(), Path::new("/repo"), &required, 0).len(), 10);
+        assert_eq!(sample_files(files, Path::new("/repo"), &required, 20).len(), 10);
+    }
+
+    #[test]
+    fn test_parse_models() {
+        let models = parse_models(&["minilm-l6-q".to_string(), " bge-small".to_string()]).unwrap();
+        assert_eq!(models, [ModelType::AllMiniLML6V2Q, ModelType::BGESmallENV15]);
+        assert!(parse_models(&["no-such-model".to_string()]).is_err());
+    }
+}
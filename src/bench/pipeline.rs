@@ -0,0 +1,276 @@
+//! Indexing throughput benchmark (`demongrep bench index`)
+//!
+//! Runs the full indexing pipeline over a project into a scratch database
+//! and times each phase: discovery, chunking, model load, embedding, vector
+//! storage and the full-text index. Each phase also reports peak RSS. On
+//! Linux the peak is reset between phases, so it is that phase's own peak;
+//! elsewhere it is the process peak so far.
+
+use anyhow::{anyhow, Result};
+use colored::Colorize;
+use serde::Serialize;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use super::{ms, per_sec};
+use crate::chunker::SemanticChunker;
+use crate::config::Config;
+use crate::embed::{EmbeddingService, ModelType};
+use crate::file::FileWalker;
+use crate::fts::FtsStore;
+use crate::index::dir_size;
+use crate::info_print;
+use crate::vectordb::{StoreOptions, VectorStore};
+
+/// Peak resident set size from `/proc/self/status` (the `VmHWM` line)
+fn parse_vm_hwm(status: &str) -> Option<u64> {
+    let line = status.lines().find(|line| line.starts_with("VmHWM:"))?;
+    let kb: u64 = line.trim_start_matches("VmHWM:").trim().trim_end_matches("kB").trim().parse().ok()?;
+    Some(kb * 1024)
+}
+
+/// Peak resident set size of this process in bytes
+#[cfg(target_os = "linux")]
+fn peak_rss() -> Option<u64> {
+    parse_vm_hwm(&std::fs::read_to_string("/proc/self/status").ok()?)
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+fn peak_rss() -> Option<u64> {
+    // SAFETY: getrusage only writes into the struct we pass
+    let usage = unsafe {
+        let mut usage: libc::rusage = std::mem::zeroed();
+        if libc::getrusage(libc::RUSAGE_SELF, &mut usage) != 0 {
+            return None;
+        }
+        usage
+    };
+    // ru_maxrss is bytes on macOS and kilobytes on the BSDs
+    let max = usage.ru_maxrss as u64;
+    Some(if cfg!(target_os = "macos") { max } else { max * 1024 })
+}
+
+#[cfg(not(unix))]
+fn peak_rss() -> Option<u64> {
+    None
+}
+
+/// Reset the peak RSS so the next reading covers only what follows
+///
+/// Returns whether the reset worked (Linux only).
+fn reset_peak_rss() -> bool {
+    cfg!(target_os = "linux") && std::fs::write("/proc/self/clear_refs", "5").is_ok()
+}
+
+/// Timing and memory for one pipeline phase
+#[derive(Debug, Clone, Serialize)]
+pub struct PhaseResult {
+    pub phase: String,
+    pub ms: f64,
+    /// Files, chunks or embeddings handled, depending on the phase
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub items: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub items_per_sec: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub peak_rss_bytes: Option<u64>,
+}
+
+/// Result of `demongrep bench index`
+#[derive(Debug, Clone, Serialize)]
+pub struct IndexBenchReport {
+    pub root: String,
+    pub model: String,
+    pub files: usize,
+    pub bytes: u64,
+    pub chunks: usize,
+    pub total_ms: f64,
+    pub files_per_sec: f64,
+    pub chunks_per_sec: f64,
+    pub embeddings_per_sec: f64,
+    /// Whether each phase's peak RSS is its own (false: process peak so far)
+    pub per_phase_rss: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub peak_rss_bytes: Option<u64>,
+    pub index_bytes: u64,
+    pub phases: Vec<PhaseResult>,
+}
+
+/// Collects phase results, resetting the peak RSS before each phase
+struct Phases {
+    results: Vec<PhaseResult>,
+    per_phase_rss: bool,
+    start: Instant,
+}
+
+impl Phases {
+    fn new() -> Self {
+        Self {
+            results: Vec::new(),
+            per_phase_rss: reset_peak_rss(),
+            start: Instant::now(),
+        }
+    }
+
+    fn finish(&mut self, phase: &str, items: Option<usize>) -> Duration {
+        let elapsed = self.start.elapsed();
+        self.results.push(PhaseResult {
+            phase: phase.to_string(),
+            ms: ms(elapsed),
+            items,
+            items_per_sec: items.map(|n| per_sec(n, elapsed)),
+            peak_rss_bytes: peak_rss(),
+        });
+        info_print!("   {} done in {:?}", phase, elapsed);
+        reset_peak_rss();
+        self.start = Instant::now();
+        elapsed
+    }
+}
+
+/// Index `root` into a scratch database with `model_type`, timing every phase
+pub fn bench_indexing(root: PathBuf, model_type: ModelType, config: &Config) -> Result<IndexBenchReport> {
+    let scratch = tempfile::tempdir()?;
+    let db_path = scratch.path().join("db");
+    let mut phases = Phases::new();
+
+    let walker = FileWalker::new(root.clone()).with_excludes(&config.indexing.excludes);
+    let (files, _stats) = walker.walk()?;
+    let discovery = phases.finish("discovery", Some(files.len()));
+    if files.is_empty() {
+        return Err(anyhow!("No indexable files under {}", root.display()));
+    }
+
+    let mut chunker = SemanticChunker::from_config(&config.indexing);
+    let mut chunks = Vec::new();
+    let mut bytes = 0u64;
+    for file in &files {
+        let Ok(content) = std::fs::read_to_string(&file.path) else {
+            continue;
+        };
+        bytes += content.len() as u64;
+        chunks.extend(chunker.chunk_semantic(file.language, &file.path, &content)?);
+    }
+    let chunking = phases.finish("chunking", Some(files.len()));
+    let chunk_count = chunks.len();
+
+    let mut embedding_service = EmbeddingService::with_model(model_type)?;
+    phases.finish("model load", None);
+
+    let embedded = embedding_service.embed_chunks(chunks)?;
+    let embedding = phases.finish("embedding", Some(embedded.len()));
+
+    let mut store = VectorStore::with_options(&db_path, model_type.dimensions(), &StoreOptions::from_config(config))?;
+    let ids = store.insert_chunks_with_ids(embedded.clone())?;
+    store.build_index()?;
+    store.save_db_metadata(model_type.name(), model_type.dimensions(), true)?;
+    drop(store);
+    let storage = phases.finish("vector storage", Some(ids.len()));
+
+    let mut fts_store = FtsStore::with_analyzer(&db_path, &config.fts)?;
+    for (chunk, id) in embedded.iter().zip(&ids) {
+        fts_store.add_chunk(
+            *id,
+            &chunk.chunk.content,
+            &chunk.chunk.path,
+            chunk.chunk.name.as_deref(),
+            chunk.chunk.signature.as_deref(),
+            chunk.chunk.docstring.as_deref(),
+            &format!("{:?}", chunk.chunk.kind),
+            &chunk.chunk.string_literals,
+            &chunk.chunk.tags,
+            chunk.chunk.package.as_deref(),
+        )?;
+    }
+    fts_store.commit()?;
+    drop(fts_store);
+    let fts = phases.finish("full-text index", Some(ids.len()));
+
+    // Model load is left out of the throughput figures: it is a one-off cost
+    let pipeline = discovery + chunking + embedding + storage + fts;
+    let total: f64 = phases.results.iter().map(|p| p.ms).sum();
+    let peak = phases.results.iter().filter_map(|p| p.peak_rss_bytes).max();
+
+    Ok(IndexBenchReport {
+        root: root.display().to_string(),
+        model: model_type.short_name().to_string(),
+        files: files.len(),
+        bytes,
+        chunks: chunk_count,
+        total_ms: total,
+        files_per_sec: per_sec(files.len(), pipeline),
+        chunks_per_sec: per_sec(chunk_count, pipeline),
+        embeddings_per_sec: per_sec(embedded.len(), embedding),
+        per_phase_rss: phases.per_phase_rss,
+        peak_rss_bytes: peak,
+        index_bytes: dir_size(&db_path),
+        phases: phases.results,
+    })
+}
+
+fn mb(bytes: u64) -> f64 {
+    bytes as f64 / (1024.0 * 1024.0)
+}
+
+/// Run the indexing benchmark and print the results
+pub async fn index(path: Option<PathBuf>, model: Option<ModelType>, json: bool, config: &Config) -> Result<()> {
+    if json {
+        crate::output::set_quiet(true);
+    }
+
+    let root = path.unwrap_or_else(|| PathBuf::from("."));
+    let model_type = model.unwrap_or_default();
+    info_print!("🚀 Indexing {} with {} into a scratch database...", root.display(), model_type.short_name());
+    let report = bench_indexing(root, model_type, config)?;
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(());
+    }
+
+    println!();
+    println!("{}", "⏱️  Indexing benchmark".bright_cyan().bold());
+    println!("{}", "=".repeat(60));
+    println!("📂 Root: {}", report.root);
+    println!("🧠 Model: {}", report.model);
+    println!(
+        "📄 {} files ({:.2} MB), {} chunks, index {:.2} MB",
+        report.files,
+        mb(report.bytes),
+        report.chunks,
+        mb(report.index_bytes)
+    );
+    println!();
+    println!("{:<18} {:>11} {:>9} {:>11} {:>12}", "Phase", "Time ms", "Items", "Items/s", "Peak RSS MB");
+    for phase in &report.phases {
+        let items = phase.items.map(|n| n.to_string()).unwrap_or_else(|| "-".to_string());
+        let rate = phase.items_per_sec.map(|r| format!("{:.0}", r)).unwrap_or_else(|| "-".to_string());
+        let rss = phase.peak_rss_bytes.map(|b| format!("{:.1}", mb(b))).unwrap_or_else(|| "-".to_string());
+        println!("{:<18} {:>11.1} {:>9} {:>11} {:>12}", phase.phase, phase.ms, items, rate, rss);
+    }
+    if !report.per_phase_rss {
+        println!("{}", "Peak RSS is the process peak so far, not per phase".dimmed());
+    }
+    println!();
+    println!(
+        "{} {:.0} files/s, {:.0} chunks/s, {:.0} embeddings/s",
+        "Throughput:".bold(),
+        report.files_per_sec,
+        report.chunks_per_sec,
+        report.embeddings_per_sec
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_vm_hwm() {
+        let status = "Name:\tdemongrep\nVmPeak:\t  912344 kB\nVmHWM:\t   51200 kB\nVmRSS:\t   40000 kB\n";
+        assert_eq!(parse_vm_hwm(status), Some(51200 * 1024));
+        assert_eq!(parse_vm_hwm("Name:\tdemongrep\n"), None);
+    }
+}
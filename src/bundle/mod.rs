@@ -0,0 +1,317 @@
+//! Portable index bundles
+//!
+//! `demongrep export` packs a database (LMDB store, tantivy index, file
+//! metadata and metadata.json) into a single `.tar.zst` archive, and
+//! `demongrep import` unpacks it for another checkout of the same project.
+//! CI can build the index once and developers download the result.
+
+use anyhow::{anyhow, Result};
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+use crate::cache::FileMetaStore;
+use crate::index::{get_index_db_path, get_search_db_paths, rebuild_fts, WriteLock};
+use crate::vectordb::VectorStore;
+
+/// Bumped when the archive layout changes
+const BUNDLE_FORMAT_VERSION: u32 = 1;
+
+/// Name of the manifest entry at the root of the archive
+const MANIFEST_NAME: &str = "manifest.json";
+
+/// Directory inside the archive holding the database files
+const DB_DIR: &str = "db";
+
+/// Files that must not be copied verbatim (data.mdb is snapshotted instead)
+const SKIPPED_FILES: &[&str] = &["data.mdb", "lock.mdb", crate::index::LOCK_FILE, crate::server::ADDRESS_FILE];
+
+/// zstd compression level for exports
+const ZSTD_LEVEL: i32 = 10;
+
+/// Description of a bundle, stored as `manifest.json`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BundleManifest {
+    pub format_version: u32,
+    pub demongrep_version: String,
+    pub model: String,
+    pub dimensions: usize,
+    /// Canonical project root on the machine that built the index
+    pub source_root: String,
+    pub created_at: String,
+    pub total_chunks: usize,
+    pub total_files: usize,
+}
+
+/// Read model name and dimensions from a database's metadata.json
+fn read_model_metadata(db_path: &Path) -> Result<(String, usize)> {
+    let content = std::fs::read_to_string(db_path.join("metadata.json"))
+        .map_err(|_| anyhow!("Database at {} has no metadata.json", db_path.display()))?;
+    let json: serde_json::Value = serde_json::from_str(&content)?;
+
+    let model = json
+        .get("model_short_name")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow!("metadata.json is missing model_short_name"))?
+        .to_string();
+    let dimensions = json
+        .get("dimensions")
+        .and_then(|v| v.as_u64())
+        .ok_or_else(|| anyhow!("metadata.json is missing dimensions"))? as usize;
+
+    Ok((model, dimensions))
+}
+
+/// Write a bundle for the database at `db_path` to `output`
+pub fn write_bundle(db_path: &Path, source_root: &Path, output: &Path) -> Result<BundleManifest> {
+    let (model, dimensions) = read_model_metadata(db_path)?;
+    let store = VectorStore::new(db_path, dimensions)?;
+    let stats = store.stats()?;
+
+    let manifest = BundleManifest {
+        format_version: BUNDLE_FORMAT_VERSION,
+        demongrep_version: env!("CARGO_PKG_VERSION").to_string(),
+        model,
+        dimensions,
+        source_root: source_root.to_string_lossy().to_string(),
+        created_at: chrono::Utc::now().to_rfc3339(),
+        total_chunks: stats.total_chunks,
+        total_files: stats.total_files,
+    };
+
+    // Snapshot LMDB instead of copying data.mdb, so a concurrent writer can't tear it
+    let staging = tempfile::tempdir()?;
+    let snapshot_path = staging.path().join("data.mdb");
+    store.snapshot_to(&snapshot_path)?;
+    drop(store);
+
+    let file = File::create(output)?;
+    let encoder = zstd::Encoder::new(file, ZSTD_LEVEL)?;
+    let mut builder = tar::Builder::new(encoder);
+
+    let manifest_json = serde_json::to_vec_pretty(&manifest)?;
+    let mut header = tar::Header::new_gnu();
+    header.set_size(manifest_json.len() as u64);
+    header.set_mode(0o644);
+    header.set_cksum();
+    builder.append_data(&mut header, MANIFEST_NAME, manifest_json.as_slice())?;
+
+    builder.append_path_with_name(&snapshot_path, Path::new(DB_DIR).join("data.mdb"))?;
+
+    for entry in std::fs::read_dir(db_path)? {
+        let entry = entry?;
+        let name = entry.file_name();
+        if SKIPPED_FILES.iter().any(|skipped| name == *skipped) {
+            continue;
+        }
+
+        let archive_path = Path::new(DB_DIR).join(&name);
+        if entry.file_type()?.is_dir() {
+            builder.append_dir_all(&archive_path, entry.path())?;
+        } else {
+            builder.append_path_with_name(entry.path(), &archive_path)?;
+        }
+    }
+
+    builder.into_inner()?.finish()?;
+    Ok(manifest)
+}
+
+/// Unpack a bundle into `target_db`, rebasing absolute paths onto `project_root`
+///
+/// The bundle is unpacked, opened and rebased beside the target first, and
+/// only then swapped in, so a corrupt or mismatched archive leaves an
+/// existing database at `target_db` as it was.
+pub fn read_bundle(archive: &Path, target_db: &Path, project_root: &Path) -> Result<BundleManifest> {
+    let parent = target_db
+        .parent()
+        .ok_or_else(|| anyhow!("Invalid database path: {}", target_db.display()))?;
+    std::fs::create_dir_all(parent)?;
+
+    // Unpack next to the target so the final move is a cheap rename
+    let staging = tempfile::tempdir_in(parent)?;
+    let decoder = zstd::Decoder::new(File::open(archive)?)?;
+    tar::Archive::new(decoder).unpack(staging.path())?;
+
+    let manifest: BundleManifest = serde_json::from_str(
+        &std::fs::read_to_string(staging.path().join(MANIFEST_NAME))
+            .map_err(|_| anyhow!("{} is not a demongrep bundle (no manifest)", archive.display()))?,
+    )?;
+    if manifest.format_version > BUNDLE_FORMAT_VERSION {
+        return Err(anyhow!(
+            "Bundle format v{} is newer than this demongrep supports (v{}); upgrade demongrep",
+            manifest.format_version,
+            BUNDLE_FORMAT_VERSION
+        ));
+    }
+
+    let staged_db = staging.path().join(DB_DIR);
+    if !staged_db.is_dir() {
+        return Err(anyhow!("{} is not a demongrep bundle (no database)", archive.display()));
+    }
+
+    // Opening the store checks its format; absolute paths from the build
+    // machine point nowhere here, and the full-text index holds them too
+    let mut store = VectorStore::new(&staged_db, manifest.dimensions)?;
+    let new_root = project_root.to_string_lossy().to_string();
+    if manifest.source_root != new_root {
+        store.rebase_paths(&manifest.source_root, &new_root)?;
+        rebuild_fts(&staged_db, &store)?;
+
+        let mut file_meta = FileMetaStore::load_or_create(&staged_db, &manifest.model, manifest.dimensions)?;
+        if file_meta.rebase_paths(&manifest.source_root, &new_root) > 0 {
+            file_meta.save(&staged_db)?;
+        }
+    }
+    drop(store);
+
+    // Keep the old database until the new one is in place; the staging
+    // directory removes it afterwards
+    if target_db.exists() {
+        let previous = staging.path().join("previous");
+        std::fs::rename(target_db, &previous)?;
+        if let Err(e) = std::fs::rename(&staged_db, target_db) {
+            std::fs::rename(&previous, target_db)?;
+            return Err(e.into());
+        }
+    } else {
+        std::fs::rename(&staged_db, target_db)?;
+    }
+
+    Ok(manifest)
+}
+
+/// Export the index for a project to a `.tar.zst` archive
+pub async fn export(output: PathBuf, path: Option<PathBuf>) -> Result<()> {
+    let project_root = path.clone().unwrap_or_else(|| PathBuf::from(".")).canonicalize()?;
+    let db_paths = get_search_db_paths(path)?;
+
+    let db_path = match db_paths.first() {
+        Some(p) => p,
+        None => {
+            println!("{}", "❌ No database found!".red());
+            println!("   Run {} first", "demongrep index".bright_cyan());
+            return Ok(());
+        }
+    };
+
+    println!("{}", "📦 Exporting index".bright_cyan().bold());
+    println!("{}", "=".repeat(60));
+    println!("💾 Database: {}", db_path.display());
+
+    let manifest = write_bundle(db_path, &project_root, &output)?;
+    let size = std::fs::metadata(&output)?.len();
+
+    println!("✅ Wrote {}", output.display());
+    println!("   Model: {} ({} dims)", manifest.model, manifest.dimensions);
+    println!("   Chunks: {} from {} files", manifest.total_chunks, manifest.total_files);
+    println!("   Archive size: {:.2} MB", size as f64 / (1024.0 * 1024.0));
+
+    Ok(())
+}
+
+/// Import an index archive for a project
+pub async fn import(archive: PathBuf, path: Option<PathBuf>, global: bool, force: bool) -> Result<()> {
+    let project_root = path.unwrap_or_else(|| PathBuf::from(".")).canonicalize()?;
+
+    let target_db = get_index_db_path(Some(project_root.clone()), global)?;
+
+    // Only the database the bundle replaces is locked, and only swapped out once the bundle checks out
+    let _write_lock = if target_db.exists() {
+        if !force {
+            println!("{}", "⚠️  A database already exists for this project:".yellow());
+            println!("   {}", target_db.display());
+            println!("\n   Use {} to replace it", "--force".bright_cyan());
+            return Err(anyhow!("Database already exists"));
+        }
+        Some(WriteLock::acquire(&target_db, "import")?)
+    } else {
+        None
+    };
+
+    println!("{}", "📥 Importing index".bright_cyan().bold());
+    println!("{}", "=".repeat(60));
+    println!("📂 Project: {}", project_root.display());
+    println!("💾 Database: {}", target_db.display());
+
+    let manifest = read_bundle(&archive, &target_db, &project_root)?;
+
+    println!("✅ Imported {} chunks from {} files", manifest.total_chunks, manifest.total_files);
+    println!("   Model: {} ({} dims)", manifest.model, manifest.dimensions);
+    println!("   Built: {} (demongrep {})", manifest.created_at, manifest.demongrep_version);
+    println!(
+        "\n   Run {} to pick up local changes since the bundle was built",
+        "demongrep index".bright_cyan()
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunker::{Chunk, ChunkKind};
+    use crate::embed::EmbeddedChunk;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_bundle_roundtrip_rebases_paths() {
+        let source = tempdir().unwrap();
+        let source_db = source.path().join(".demongrep.db");
+        let source_file = format!("{}/src/lib.rs", source.path().display());
+
+        let mut store = VectorStore::new(&source_db, 4).unwrap();
+        store
+            .insert_chunks(vec![EmbeddedChunk::new(
+                Chunk::new("fn a() {}".to_string(), 0, 1, ChunkKind::Function, source_file),
+                vec![1.0, 0.0, 0.0, 0.0],
+            )])
+            .unwrap();
+        store.build_index().unwrap();
+        drop(store);
+        std::fs::write(
+            source_db.join("metadata.json"),
+            r#"{"model_short_name": "minilm-l6-q", "dimensions": 4}"#,
+        )
+        .unwrap();
+
+        let archive = source.path().join("index.tar.zst");
+        let manifest = write_bundle(&source_db, source.path(), &archive).unwrap();
+        assert_eq!(manifest.total_chunks, 1);
+
+        let target = tempdir().unwrap();
+        let target_db = target.path().join(".demongrep.db");
+        read_bundle(&archive, &target_db, target.path()).unwrap();
+
+        let store = VectorStore::new(&target_db, 4).unwrap();
+        assert_eq!(store.stats().unwrap().total_chunks, 1);
+        let chunk = store.get_chunk(0).unwrap().unwrap();
+        assert_eq!(chunk.path, format!("{}/src/lib.rs", target.path().display()));
+        assert!(target_db.join("metadata.json").exists());
+    }
+
+    #[test]
+    fn test_rejects_non_bundle() {
+        let dir = tempdir().unwrap();
+        let archive = dir.path().join("bogus.tar.zst");
+        let encoder = zstd::Encoder::new(File::create(&archive).unwrap(), 1).unwrap();
+        tar::Builder::new(encoder).into_inner().unwrap().finish().unwrap();
+
+        let result = read_bundle(&archive, &dir.path().join(".demongrep.db"), dir.path());
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_failed_import_keeps_existing_database() {
+        let dir = tempdir().unwrap();
+        let target_db = dir.path().join(".demongrep.db");
+        std::fs::create_dir_all(&target_db).unwrap();
+        std::fs::write(target_db.join("metadata.json"), "{}").unwrap();
+
+        let archive = dir.path().join("bogus.tar.zst");
+        std::fs::write(&archive, b"not an archive").unwrap();
+        assert!(read_bundle(&archive, &target_db, dir.path()).is_err());
+        assert!(target_db.join("metadata.json").exists());
+    }
+}
@@ -6,6 +6,8 @@ use std::fs;
 use std::path::Path;
 use std::time::SystemTime;
 
+use crate::vectordb::rebase_path;
+
 /// Metadata for a single indexed file
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FileMeta {
@@ -78,6 +80,20 @@ impl FileMetaStore {
         }
     }
 
+    /// Load the store saved in a database directory as-is, if there is one
+    ///
+    /// Unlike `load_or_create`, a store built with another model is returned
+    /// unchanged rather than reset.
+    pub fn load(db_path: &Path) -> Result<Option<Self>> {
+        let meta_path = db_path.join(Self::FILENAME);
+        if !meta_path.exists() {
+            return Ok(None);
+        }
+        let content = fs::read_to_string(&meta_path)?;
+        let store = serde_json::from_str(&content).map_err(|e| anyhow!("Failed to parse file metadata: {}", e))?;
+        Ok(Some(store))
+    }
+
     /// Save to database directory
     pub fn save(&self, db_path: &Path) -> Result<()> {
         let meta_path = db_path.join(Self::FILENAME);
@@ -86,6 +102,25 @@ impl FileMetaStore {
         Ok(())
     }
 
+    /// Rewrite tracked paths under the directory `from` to be under `to`
+    ///
+    /// Returns the number of paths rewritten.
+    pub fn rebase_paths(&mut self, from: &str, to: &str) -> usize {
+        let moved: Vec<(String, String)> = self
+            .files
+            .keys()
+            .filter_map(|path| rebase_path(path, from, to).map(|new_path| (path.clone(), new_path)))
+            .collect();
+
+        for (path, new_path) in &moved {
+            if let Some(meta) = self.files.remove(path) {
+                self.files.insert(new_path.clone(), meta);
+            }
+        }
+
+        moved.len()
+    }
+
     /// Compute SHA256 hash of file content
     pub fn compute_hash(path: &Path) -> Result<String> {
         let content = fs::read(path)?;
@@ -160,6 +195,11 @@ impl FileMetaStore {
         self.files.keys()
     }
 
+    /// Chunk IDs recorded for each tracked file
+    pub fn tracked_chunk_ids(&self) -> impl Iterator<Item = (&String, &[u32])> {
+        self.files.iter().map(|(path, meta)| (path, meta.chunk_ids.as_slice()))
+    }
+
     /// Find files that were deleted (exist in store but not on disk)
     pub fn find_deleted_files(&self) -> Vec<(String, Vec<u32>)> {
         self.files
@@ -9,10 +9,14 @@ mod fallback;
 mod dedup;
 mod extractor;
 mod semantic;
+mod simhash;
+mod tags;
 
 pub use semantic::SemanticChunker;
-pub use parser::{CodeParser, ParsedCode};
-pub use grammar::{GrammarManager, GrammarStats};
+pub use parser::CodeParser;
+pub use grammar::GrammarManager;
+pub use simhash::{is_near_duplicate, simhash};
+pub use tags::PathTags;
 
 /// Default number of context lines before/after a chunk
 pub const DEFAULT_CONTEXT_LINES: usize = 3;
@@ -45,6 +49,9 @@ pub struct Chunk {
     /// Extracted docstring/documentation comment
     pub docstring: Option<String>,
 
+    /// Name of the defined symbol (e.g., "handle_request")
+    pub name: Option<String>,
+
     /// Whether this chunk is complete (not split)
     pub is_complete: bool,
 
@@ -62,6 +69,15 @@ pub struct Chunk {
 
     /// Extracted string literals for better search (e.g., "API-VERSION", "2")
     pub string_literals: Vec<String>,
+
+    /// Team notes attached with `demongrep note`, embedded with the chunk
+    pub notes: Vec<String>,
+
+    /// Tags from `indexing.tags` that the file's path matches
+    pub tags: Vec<String>,
+
+    /// Package whose manifest is nearest above the file, by its declared name
+    pub package: Option<String>,
 }
 
 impl Chunk {
@@ -84,12 +100,16 @@ impl Chunk {
             path,
             signature: None,
             docstring: None,
+            name: None,
             is_complete: true,
             split_index: None,
             hash,
             context_prev: None,
             context_next: None,
             string_literals: Vec::new(),
+            notes: Vec::new(),
+            tags: Vec::new(),
+            package: None,
         }
     }
 
@@ -115,6 +135,18 @@ impl Chunk {
         self.content.len()
     }
 
+    /// Symbol name from stored breadcrumbs ("File: a.rs > Impl: Pool > Method: acquire" -> "acquire")
+    ///
+    /// For chunks read back from the database, which keep the breadcrumbs
+    /// but not the name. None for gap chunks, whose last breadcrumb is the file.
+    pub fn name_from_context(context: &str) -> Option<&str> {
+        let last = context.rsplit(" > ").next()?;
+        if last.starts_with("File: ") {
+            return None;
+        }
+        last.split_once(": ").map(|(_, name)| name)
+    }
+
     /// Extract string literals from content for better search
     /// Extracts strings from common patterns like "string", 'string', `string`
     pub fn extract_string_literals(content: &str) -> Vec<String> {
@@ -150,7 +182,7 @@ impl Chunk {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum ChunkKind {
     Function,      // Standalone function
     Class,         // Class definition (non-Rust languages)
@@ -165,10 +197,81 @@ pub enum ChunkKind {
     Const,         // Constant
     Static,        // Static variable
     Block,         // Gap/unstructured code
+    Imports,       // Gap made up mostly of imports/use declarations
+    ModuleDocs,    // Module-level documentation (//!, /*!, module docstrings)
     Anchor,        // File-level summary chunk
     Other,         // Catch-all
 }
 
+impl ChunkKind {
+    /// Kind named in a query filter (`kind:fn`), by short or full name
+    pub fn from_name(name: &str) -> Option<Self> {
+        let kind = match name.to_lowercase().as_str() {
+            "fn" | "func" | "function" => Self::Function,
+            "class" => Self::Class,
+            "method" => Self::Method,
+            "struct" => Self::Struct,
+            "enum" => Self::Enum,
+            "trait" => Self::Trait,
+            "interface" => Self::Interface,
+            "impl" => Self::Impl,
+            "mod" | "module" => Self::Mod,
+            "type" | "typealias" => Self::TypeAlias,
+            "const" | "constant" => Self::Const,
+            "static" => Self::Static,
+            "block" => Self::Block,
+            "import" | "imports" => Self::Imports,
+            "docs" | "moduledocs" => Self::ModuleDocs,
+            "anchor" => Self::Anchor,
+            "other" => Self::Other,
+            _ => return None,
+        };
+        Some(kind)
+    }
+}
+
+/// Count chunks per kind, most common first
+pub fn count_by_kind(chunks: &[Chunk]) -> Vec<(ChunkKind, usize)> {
+    let mut counts: std::collections::HashMap<ChunkKind, usize> = std::collections::HashMap::new();
+    for chunk in chunks {
+        *counts.entry(chunk.kind).or_default() += 1;
+    }
+
+    let mut counts: Vec<_> = counts.into_iter().collect();
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| format!("{:?}", a.0).cmp(&format!("{:?}", b.0))));
+    counts
+}
+
+/// Position in `chunks` of each chunk's enclosing definition (a method's impl block or class)
+///
+/// The parent is a chunk of the same file whose breadcrumbs are the child's
+/// without its own label and whose lines include the child's first line; of
+/// a split parent, that is the part the child starts in. Top-level
+/// definitions and gaps have no parent.
+pub fn parent_indices<'a>(chunks: impl IntoIterator<Item = &'a Chunk>) -> Vec<Option<usize>> {
+    let chunks: Vec<&Chunk> = chunks.into_iter().collect();
+    let mut by_context: std::collections::HashMap<(&str, &[String]), Vec<usize>> = std::collections::HashMap::new();
+    for (i, chunk) in chunks.iter().enumerate() {
+        // Definitions are at least "File: ... > Kind: name"
+        if chunk.context.len() >= 2 {
+            by_context.entry((chunk.path.as_str(), chunk.context.as_slice())).or_default().push(i);
+        }
+    }
+    chunks
+        .iter()
+        .map(|child| {
+            let n = child.context.len();
+            if n < 3 {
+                return None;
+            }
+            by_context.get(&(child.path.as_str(), &child.context[..n - 1]))?.iter().copied().find(|&i| {
+                let parent = chunks[i];
+                parent.start_line <= child.start_line && child.start_line < parent.end_line
+            })
+        })
+        .collect()
+}
+
 /// Trait for chunking strategies
 pub trait Chunker: Send + Sync {
     /// Chunk a file into semantic pieces
@@ -204,6 +307,34 @@ mod tests {
         assert_eq!(literals.len(), 5);
     }
 
+    #[test]
+    fn test_name_from_context() {
+        assert_eq!(Chunk::name_from_context("File: src/pool.rs > Impl: Pool > Method: acquire"), Some("acquire"));
+        assert_eq!(Chunk::name_from_context("File: src/lib.rs > Function: main"), Some("main"));
+        assert_eq!(Chunk::name_from_context("File: src/lib.rs"), None);
+        assert_eq!(Chunk::name_from_context("Block"), None);
+    }
+
+    #[test]
+    fn test_parent_indices() {
+        let chunk = |context: &[&str], start_line, end_line| {
+            let mut chunk = Chunk::new(String::new(), start_line, end_line, ChunkKind::Function, "a.rs".to_string());
+            chunk.context = context.iter().map(|crumb| crumb.to_string()).collect();
+            chunk
+        };
+        let chunks = vec![
+            chunk(&["File: a.rs"], 0, 2),
+            chunk(&["File: a.rs", "Impl: Pool"], 2, 40),
+            chunk(&["File: a.rs", "Impl: Pool", "Method: acquire"], 5, 20),
+            chunk(&["File: a.rs", "Impl: Pool", "Method: release"], 21, 39),
+            chunk(&["File: a.rs", "Function: main"], 41, 50),
+            // A second impl of the same type
+            chunk(&["File: a.rs", "Impl: Pool"], 51, 60),
+            chunk(&["File: a.rs", "Impl: Pool", "Method: drop"], 52, 59),
+        ];
+        assert_eq!(parent_indices(&chunks), [None, None, Some(1), Some(1), None, None, Some(5)]);
+    }
+
     #[test]
     fn test_extract_string_literals_with_escapes() {
         let code = "let msg = \"Hello \\\"World\\\"!\";";
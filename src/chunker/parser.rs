@@ -1,7 +1,8 @@
 use crate::file::Language;
 use anyhow::{anyhow, Result};
-use tree_sitter::{Node, Parser, Tree};
+use tree_sitter::{Node, Parser, Point, Tree};
 
+use super::extractor::get_extractor;
 use super::grammar::GrammarManager;
 
 /// Wrapper around tree-sitter parser with language support
@@ -91,6 +92,35 @@ impl ParsedCode {
         self.root_node().has_error()
     }
 
+    /// Rows of the smallest definition enclosing rows `start_row..end_row`
+    ///
+    /// Returns the definition's first row and the row after its last, or
+    /// None when the rows are outside any definition.
+    pub fn enclosing_definition(&self, start_row: usize, end_row: usize) -> Option<(usize, usize)> {
+        let lines: Vec<&str> = self.source.lines().collect();
+        let last_row = end_row.saturating_sub(1).max(start_row).min(lines.len().checked_sub(1)?);
+        let start_row = start_row.min(last_row);
+
+        // Span the code on the rows, not the indentation and trailing whitespace around it
+        let indent = |line: &str| line.len() - line.trim_start().len();
+        let start = Point::new(start_row, indent(lines[start_row]));
+        let end = Point::new(last_row, lines[last_row].trim_end().len());
+
+        let extractor = get_extractor(self.language);
+        let is_definition = |node: Node| match &extractor {
+            Some(extractor) => extractor.is_definition(node),
+            None => is_definition_node(node),
+        };
+
+        let mut node = self.root_node().descendant_for_point_range(start, end)?;
+        loop {
+            if is_definition(node) {
+                return Some((node.start_position().row, node.end_position().row + 1));
+            }
+            node = node.parent()?;
+        }
+    }
+
     /// Walk the tree and find all nodes of a given type
     ///
     /// Note: This returns node IDs that can be used to access nodes via the tree
@@ -181,6 +211,19 @@ fn main() {
         assert!(!parsed.has_errors());
     }
 
+    #[test]
+    fn test_enclosing_definition() {
+        let mut parser = CodeParser::new();
+        let source = "use std::io;\n\nimpl Server {\n    fn handle(&self) {\n        let a = 1;\n        let b = 2;\n    }\n}\n";
+        let parsed = parser.parse(Language::Rust, source).unwrap();
+
+        // The middle of a method expands to the whole method
+        assert_eq!(parsed.enclosing_definition(4, 5), Some((3, 7)));
+        // A range spanning the impl's methods expands to the impl
+        assert_eq!(parsed.enclosing_definition(2, 8), Some((2, 8)));
+        assert_eq!(parsed.enclosing_definition(0, 1), None);
+    }
+
     #[test]
     fn test_parse_python_code() {
         let mut parser = CodeParser::new();
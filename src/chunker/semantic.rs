@@ -1,7 +1,8 @@
-use super::{Chunk, ChunkKind, Chunker, DEFAULT_CONTEXT_LINES};
+use super::{Chunk, ChunkKind, Chunker, PathTags, DEFAULT_CONTEXT_LINES};
 use crate::chunker::extractor::{get_extractor, LanguageExtractor};
 use crate::chunker::parser::CodeParser;
-use crate::file::Language;
+use crate::config::{ContextSource, GapStrategy, IndexingConfig};
+use crate::file::{Language, PackageFinder};
 use anyhow::Result;
 use std::path::Path;
 use tree_sitter::Node;
@@ -13,6 +14,30 @@ pub struct SemanticChunker {
     max_chunk_chars: usize,
     overlap_lines: usize,
     context_lines: usize,
+    context_source: ContextSource,
+    gap_strategy: GapStrategy,
+    gap_stats: GapStats,
+    tags: PathTags,
+    packages: PackageFinder,
+}
+
+/// What happened to gap chunks across all files chunked so far
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct GapStats {
+    /// Gaps indexed as their own chunks
+    pub kept: usize,
+    /// Gaps attached to a neighbouring definition
+    pub merged: usize,
+    /// Gaps skipped
+    pub dropped: usize,
+}
+
+impl std::ops::AddAssign for GapStats {
+    fn add_assign(&mut self, other: Self) {
+        self.kept += other.kept;
+        self.merged += other.merged;
+        self.dropped += other.dropped;
+    }
 }
 
 impl SemanticChunker {
@@ -23,21 +48,80 @@ impl SemanticChunker {
             max_chunk_chars,
             overlap_lines,
             context_lines: DEFAULT_CONTEXT_LINES,
+            context_source: ContextSource::Lines,
+            gap_strategy: GapStrategy::Keep,
+            gap_stats: GapStats::default(),
+            tags: PathTags::default(),
+            packages: PackageFinder::default(),
         }
     }
 
+    /// Create a chunker using the chunk sizes, gap strategy, context source and tags from the indexing config
+    pub fn from_config(config: &IndexingConfig) -> Self {
+        Self::new(config.max_chunk_lines, config.max_chunk_chars, config.overlap_lines)
+            .with_gap_strategy(config.gap_strategy)
+            .with_context_source(config.context_source)
+            .with_tags(PathTags::new(&config.tags))
+    }
+
+    /// Set the tags chunks get by path
+    pub fn with_tags(mut self, tags: PathTags) -> Self {
+        self.tags = tags;
+        self
+    }
+
+    /// Match tag patterns relative to `root`, the directory being indexed, and look for package manifests up to it
+    pub fn with_project_root(mut self, root: &Path) -> Self {
+        self.tags = self.tags.with_root(root);
+        self.packages = self.packages.with_root(root);
+        self
+    }
+
+    /// Set where context_prev/context_next come from
+    pub fn with_context_source(mut self, source: ContextSource) -> Self {
+        self.context_source = source;
+        self
+    }
+
+    /// Set how code between definitions is handled
+    pub fn with_gap_strategy(mut self, strategy: GapStrategy) -> Self {
+        self.gap_strategy = strategy;
+        self
+    }
+
+    /// Gap chunks kept, merged and dropped by this chunker so far
+    pub fn gap_stats(&self) -> GapStats {
+        self.gap_stats
+    }
+
     /// Set the number of context lines to extract before/after each chunk
     pub fn with_context_lines(mut self, lines: usize) -> Self {
         self.context_lines = lines;
         self
     }
 
-    /// Chunk a file using semantic analysis
+    /// Chunk a file using semantic analysis, tagging the chunks by its path and package
     pub fn chunk_semantic(
         &mut self,
         language: Language,
         path: &Path,
         content: &str,
+    ) -> Result<Vec<Chunk>> {
+        let mut chunks = self.chunk_untagged(language, path, content)?;
+        let tags = self.tags.tags(path);
+        let package = self.packages.package_of(path);
+        for chunk in &mut chunks {
+            chunk.tags = tags.clone();
+            chunk.package = package.clone();
+        }
+        Ok(chunks)
+    }
+
+    fn chunk_untagged(
+        &mut self,
+        language: Language,
+        path: &Path,
+        content: &str,
     ) -> Result<Vec<Chunk>> {
         // 1. Check if we have an extractor for this language
         let extractor = match get_extractor(language) {
@@ -65,17 +149,21 @@ impl SemanticChunker {
             &mut gap_tracker,
         );
 
-        // 4. Extract gap chunks (code between definitions)
-        let gap_chunks = gap_tracker.extract_gaps(path);
-
-        // 5. Combine and sort all chunks by position
+        // 4. Add gap chunks (code between definitions) per the gap strategy
         let mut all_chunks = definition_chunks;
-        all_chunks.extend(gap_chunks);
+        self.gap_stats += gap_tracker.apply(path, self.gap_strategy, &mut all_chunks);
+
+        // 5. Sort all chunks by position
         all_chunks.sort_by_key(|c| c.start_line);
 
-        // 6. Populate context windows (lines before/after each chunk)
-        let source_lines: Vec<&str> = content.lines().collect();
-        self.populate_context_windows(&mut all_chunks, &source_lines);
+        // 6. Populate context windows (lines or sibling chunks before/after each chunk)
+        match self.context_source {
+            ContextSource::Lines => {
+                let source_lines: Vec<&str> = content.lines().collect();
+                self.populate_context_windows(&mut all_chunks, &source_lines);
+            }
+            ContextSource::Siblings => self.populate_sibling_context(&mut all_chunks),
+        }
 
         // 7. Split oversized chunks
         let final_chunks = all_chunks
@@ -119,6 +207,51 @@ impl SemanticChunker {
         }
     }
 
+    /// Use the neighbouring chunks at the same nesting level as context_prev/context_next
+    ///
+    /// Expects `chunks` sorted by start line.
+    fn populate_sibling_context(&self, chunks: &mut [Chunk]) {
+        if self.context_lines == 0 {
+            return;
+        }
+
+        // Definitions carry their own label last; gaps only have the file breadcrumb
+        let parents: Vec<Vec<String>> = chunks
+            .iter()
+            .map(|c| match c.context.len() {
+                0 | 1 => c.context.clone(),
+                n => c.context[..n - 1].to_vec(),
+            })
+            .collect();
+        let summaries: Vec<String> = chunks.iter().map(|c| self.sibling_summary(c)).collect();
+
+        for i in 0..chunks.len() {
+            let prev = (0..i)
+                .rev()
+                .find(|&j| parents[j] == parents[i] && chunks[j].end_line <= chunks[i].start_line);
+            let next = (i + 1..chunks.len())
+                .find(|&j| parents[j] == parents[i] && chunks[j].start_line >= chunks[i].end_line);
+
+            chunks[i].context_prev = prev.map(|j| summaries[j].clone());
+            chunks[i].context_next = next.map(|j| summaries[j].clone());
+        }
+    }
+
+    /// Signature plus the first few lines of a chunk
+    fn sibling_summary(&self, chunk: &Chunk) -> String {
+        let mut lines = chunk.content.lines();
+        let mut summary: Vec<&str> = lines.by_ref().take(self.context_lines).collect();
+        if lines.next().is_some() {
+            summary.push("...");
+        }
+        let head = summary.join("\n");
+
+        match &chunk.signature {
+            Some(sig) if !head.contains(sig.as_str()) => format!("{}\n{}", sig, head),
+            _ => head,
+        }
+    }
+
     /// Recursively visit AST nodes and extract chunks
     fn visit_node(
         &self,
@@ -176,6 +309,7 @@ impl SemanticChunker {
             chunk.context = new_context.clone();
             chunk.signature = signature;
             chunk.docstring = docstring;
+            chunk.name = name;
             chunk.string_literals = Chunk::extract_string_literals(&content);
 
             chunks.push(chunk);
@@ -262,6 +396,8 @@ impl SemanticChunker {
                 } else {
                     None
                 };
+                // Only the first part holds the definition itself
+                split_chunk.name = if split_index == 0 { chunk.name.clone() } else { None };
                 split_chunk.is_complete = false;
                 split_chunk.split_index = Some(split_index);
 
@@ -301,7 +437,11 @@ impl Chunker for SemanticChunker {
             self.max_chunk_lines,
             self.max_chunk_chars,
             self.overlap_lines,
-        );
+        )
+        .with_gap_strategy(self.gap_strategy)
+        .with_context_source(self.context_source)
+        .with_tags(self.tags.clone());
+        temp_chunker.packages = self.packages.clone();
 
         temp_chunker.chunk_semantic(language, path, content)
     }
@@ -399,6 +539,71 @@ impl<'a> GapTracker<'a> {
         gaps
     }
 
+    /// Add this file's gaps to `chunks` according to `strategy`
+    fn apply(&self, path: &Path, strategy: GapStrategy, chunks: &mut Vec<Chunk>) -> GapStats {
+        let gaps = self.extract_gaps(path);
+        let mut stats = GapStats::default();
+
+        // A file without definitions is all gap; keep it rather than lose the file
+        let strategy = if chunks.is_empty() { GapStrategy::Keep } else { strategy };
+
+        match strategy {
+            GapStrategy::Keep => {
+                stats.kept = gaps.len();
+                chunks.extend(gaps);
+            }
+            GapStrategy::Drop => stats.dropped = gaps.len(),
+            GapStrategy::Merge => {
+                for gap in gaps {
+                    match Self::merge_target(chunks, &gap) {
+                        Some(idx) => {
+                            Self::merge_into(&mut chunks[idx], gap);
+                            stats.merged += 1;
+                        }
+                        None => {
+                            chunks.push(gap);
+                            stats.kept += 1;
+                        }
+                    }
+                }
+            }
+        }
+
+        stats
+    }
+
+    /// Outermost definition starting right after the gap, else the one ending before it
+    fn merge_target(chunks: &[Chunk], gap: &Chunk) -> Option<usize> {
+        let following = chunks
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| c.start_line >= gap.end_line)
+            .min_by_key(|(_, c)| (c.start_line, std::cmp::Reverse(c.end_line)))
+            .map(|(i, _)| i);
+
+        following.or_else(|| {
+            chunks
+                .iter()
+                .enumerate()
+                .filter(|(_, c)| c.end_line <= gap.start_line)
+                .max_by_key(|(_, c)| (c.end_line, std::cmp::Reverse(c.start_line)))
+                .map(|(i, _)| i)
+        })
+    }
+
+    /// Prepend (or append, for trailing gaps) the gap's lines to a definition
+    fn merge_into(chunk: &mut Chunk, gap: Chunk) {
+        if gap.end_line <= chunk.start_line {
+            chunk.content = format!("{}\n{}", gap.content, chunk.content);
+            chunk.start_line = gap.start_line;
+        } else {
+            chunk.content = format!("{}\n{}", chunk.content, gap.content);
+            chunk.end_line = gap.end_line;
+        }
+        chunk.hash = Chunk::compute_hash(&chunk.content);
+        chunk.string_literals.extend(gap.string_literals);
+    }
+
     /// Classify what kind of gap this is
     fn classify_gap(content: &str) -> ChunkKind {
         let trimmed = content.trim();
@@ -410,17 +615,23 @@ impl<'a> GapTracker<'a> {
                 line.starts_with("import ") ||
                 line.starts_with("from ") ||
                 line.starts_with("use ") ||
+                line.starts_with("pub use ") ||
+                line.starts_with("using ") ||
                 line.starts_with("#include")
             })
             .count();
 
         if import_count > trimmed.lines().count() / 2 {
-            return ChunkKind::Block; // Could add ChunkKind::Imports later
+            return ChunkKind::Imports;
         }
 
-        // Check if it's module-level docs
-        if trimmed.starts_with("//!") || trimmed.starts_with("/*!") {
-            return ChunkKind::Block; // Could add ChunkKind::ModuleDocs later
+        // Check if it's module-level docs (Rust inner docs, Python module docstring)
+        if trimmed.starts_with("//!")
+            || trimmed.starts_with("/*!")
+            || trimmed.starts_with("\"\"\"")
+            || trimmed.starts_with("'''")
+        {
+            return ChunkKind::ModuleDocs;
         }
 
         ChunkKind::Block
@@ -439,6 +650,41 @@ mod tests {
         assert_eq!(chunker.overlap_lines, 10);
     }
 
+    const GAP_SOURCE: &str = "use std::io;\nuse std::fs;\n\nfn read() {\n    todo!()\n}\n\nconst LIMIT: usize = 3;\nstatic mut COUNT: usize = 0;\n";
+
+    fn chunk_with_gaps(strategy: GapStrategy) -> (Vec<Chunk>, GapStats) {
+        let mut chunker = SemanticChunker::new(100, 2000, 10).with_gap_strategy(strategy);
+        let chunks = chunker
+            .chunk_semantic(Language::Rust, Path::new("gaps.rs"), GAP_SOURCE)
+            .unwrap();
+        (chunks, chunker.gap_stats())
+    }
+
+    #[test]
+    fn test_gap_strategy_keep() {
+        let (chunks, stats) = chunk_with_gaps(GapStrategy::Keep);
+        assert!(stats.kept >= 1);
+        assert!(chunks.iter().any(|c| c.kind == ChunkKind::Imports && c.content.contains("use std::io")));
+    }
+
+    #[test]
+    fn test_gap_strategy_merge() {
+        let (chunks, stats) = chunk_with_gaps(GapStrategy::Merge);
+        assert!(stats.merged >= 1);
+        assert_eq!(stats.kept, 0);
+        let read = chunks.iter().find(|c| c.content.contains("fn read")).unwrap();
+        assert!(read.content.starts_with("use std::io;"));
+        assert_eq!(read.start_line, 0);
+        assert!(chunks.iter().all(|c| c.kind != ChunkKind::Imports));
+    }
+
+    #[test]
+    fn test_gap_strategy_drop() {
+        let (chunks, stats) = chunk_with_gaps(GapStrategy::Drop);
+        assert!(stats.dropped >= 1);
+        assert!(chunks.iter().all(|c| !c.content.contains("use std::io")));
+    }
+
     #[test]
     fn test_chunk_rust_code() {
         let mut chunker = SemanticChunker::new(100, 2000, 10);
@@ -512,6 +758,31 @@ class Calculator:
         assert!(!chunks_with_docs.is_empty(), "Should have chunks with docstrings");
     }
 
+    #[test]
+    fn test_sibling_context() {
+        let mut chunker = SemanticChunker::new(100, 2000, 10).with_context_source(ContextSource::Siblings);
+        let code = "fn first() {\n    let a = 1;\n    let b = 2;\n    let c = 3;\n    a + b + c;\n}\n\nfn second() {\n    todo!()\n}\n";
+
+        let chunks = chunker.chunk_semantic(Language::Rust, Path::new("s.rs"), code).unwrap();
+        let first = chunks.iter().find(|c| c.content.starts_with("fn first")).unwrap();
+        let second = chunks.iter().find(|c| c.content.starts_with("fn second")).unwrap();
+
+        let prev = second.context_prev.as_deref().unwrap();
+        assert!(prev.starts_with("fn first() {"));
+        assert!(prev.ends_with("..."));
+        assert!(first.context_prev.is_none());
+        assert!(first.context_next.as_deref().unwrap().starts_with("fn second() {"));
+    }
+
+    #[test]
+    fn test_classify_gap() {
+        assert_eq!(GapTracker::classify_gap("use std::io;\nuse std::fs;"), ChunkKind::Imports);
+        assert_eq!(GapTracker::classify_gap("import os\nfrom typing import List"), ChunkKind::Imports);
+        assert_eq!(GapTracker::classify_gap("//! Parses config files"), ChunkKind::ModuleDocs);
+        assert_eq!(GapTracker::classify_gap("\"\"\"Helpers for auth.\"\"\""), ChunkKind::ModuleDocs);
+        assert_eq!(GapTracker::classify_gap("let x = 1;\nprintln!(\"{}\", x);"), ChunkKind::Block);
+    }
+
     #[test]
     fn test_chunk_unsupported_language() {
         let mut chunker = SemanticChunker::new(100, 2000, 10);
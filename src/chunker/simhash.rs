@@ -0,0 +1,88 @@
+//! Near-duplicate fingerprints for chunks
+//!
+//! The content hash only catches byte-identical chunks. Vendored copies,
+//! generated clients and copy-pasted helpers usually differ in a comment,
+//! a name or whitespace, so search also needs to know when two chunks are
+//! *nearly* the same. A 64-bit SimHash over token trigrams gives chunks
+//! that share most of their code fingerprints a few bits apart.
+
+/// Tokens per shingle
+const SHINGLE_SIZE: usize = 3;
+
+/// Fingerprints at most this many bits apart are near-duplicates
+pub const NEAR_DUPLICATE_BITS: u32 = 3;
+
+/// 64-bit FNV-1a over the tokens of a shingle
+///
+/// Fingerprints are stored in the index, so the hash must not change
+/// between builds (unlike std's `DefaultHasher`).
+fn fnv1a(tokens: &[&str]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for token in tokens {
+        for byte in token.bytes().chain(std::iter::once(0xff)) {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+    hash
+}
+
+/// SimHash fingerprint of a chunk's content (0 for content without tokens)
+///
+/// Only identifiers, keywords and literals count; whitespace and
+/// punctuation are ignored.
+pub fn simhash(content: &str) -> u64 {
+    let tokens: Vec<&str> = content
+        .split(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .filter(|token| !token.is_empty())
+        .collect();
+    if tokens.is_empty() {
+        return 0;
+    }
+
+    let mut counts = [0i32; 64];
+    for shingle in tokens.windows(SHINGLE_SIZE.min(tokens.len())) {
+        let hash = fnv1a(shingle);
+        for (bit, count) in counts.iter_mut().enumerate() {
+            if (hash >> bit) & 1 == 1 {
+                *count += 1;
+            } else {
+                *count -= 1;
+            }
+        }
+    }
+
+    counts
+        .iter()
+        .enumerate()
+        .filter(|(_, &count)| count > 0)
+        .fold(0, |fingerprint, (bit, _)| fingerprint | (1 << bit))
+}
+
+/// Whether two fingerprints mark near-duplicate content (0 is unknown)
+pub fn is_near_duplicate(a: u64, b: u64) -> bool {
+    a != 0 && b != 0 && (a ^ b).count_ones() <= NEAR_DUPLICATE_BITS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_simhash_near_duplicates() {
+        let original = "pub fn retry<F: Fn() -> Result<()>>(attempts: usize, f: F) -> Result<()> {\n    \
+            for attempt in 0..attempts {\n        if f().is_ok() { return Ok(()); }\n        \
+            sleep(backoff(attempt));\n    }\n    Err(anyhow!(\"gave up after {} attempts\", attempts))\n}";
+        // The same function reformatted, as a vendored copy might be
+        let reformatted = original.replace("\n    ", "\n  ").replace("{ return", "{\n return");
+        // A copy with a provenance comment
+        let vendored = format!("// Vendored from retry-rs\n{}", original);
+        let unrelated = "fn parse_config(path: &Path) -> Config {\n    toml::from_str(&read(path)).unwrap()\n}";
+
+        assert_eq!(simhash(original), simhash(&reformatted));
+        assert!(is_near_duplicate(simhash(original), simhash(&vendored)));
+        assert!(!is_near_duplicate(simhash(original), simhash(unrelated)));
+        assert_eq!(simhash("{ }"), 0);
+        assert!(!is_near_duplicate(0, 0));
+    }
+}
@@ -0,0 +1,85 @@
+//! Tags for chunks by path (`indexing.tags`)
+//!
+//! A project's structure is often more than its directory tree: API
+//! handlers live next to internal helpers, generated clients sit among
+//! hand-written code. `indexing.tags` names such groups with gitignore-style
+//! patterns, every chunk is stored with the tags its path matches, and
+//! `search --tag api --not-tag generated` filters on them. Tags are fixed at
+//! index time, so changing the patterns takes a reindex.
+
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use std::collections::BTreeMap;
+use std::path::{Component, Path, PathBuf};
+use tracing::warn;
+
+/// Compiled `indexing.tags` patterns
+#[derive(Clone, Default)]
+pub struct PathTags {
+    /// Each tag with a matcher for its patterns
+    rules: Vec<(String, Gitignore)>,
+    /// Paths are matched relative to this directory
+    root: Option<PathBuf>,
+}
+
+impl PathTags {
+    /// Compile the patterns, skipping tags with an invalid one
+    pub fn new(tags: &BTreeMap<String, Vec<String>>) -> Self {
+        let mut rules = Vec::new();
+        for (tag, patterns) in tags {
+            let mut builder = GitignoreBuilder::new("");
+            let added = patterns.iter().try_for_each(|pattern| builder.add_line(None, pattern).map(|_| ()));
+            match added.and_then(|_| builder.build()) {
+                Ok(matcher) => rules.push((tag.clone(), matcher)),
+                Err(e) => warn!("Invalid pattern for tag '{}': {}", tag, e),
+            }
+        }
+        Self { rules, root: None }
+    }
+
+    /// Match paths relative to `root`, the directory being indexed
+    pub fn with_root(mut self, root: &Path) -> Self {
+        self.root = Some(root.to_path_buf());
+        self
+    }
+
+    /// Tags of a file, in name order
+    pub fn tags(&self, path: &Path) -> Vec<String> {
+        if self.rules.is_empty() {
+            return Vec::new();
+        }
+        let relative = self.root.as_deref().and_then(|root| path.strip_prefix(root).ok()).unwrap_or(path);
+        let relative: PathBuf = relative.components().filter(|c| *c != Component::CurDir).collect();
+        // Outside the project: patterns are relative to it
+        if relative.has_root() {
+            return Vec::new();
+        }
+        self.rules
+            .iter()
+            .filter(|(_, matcher)| matcher.matched_path_or_any_parents(&relative, false).is_ignore())
+            .map(|(tag, _)| tag.clone())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_path_tags() {
+        let config = BTreeMap::from([
+            ("api".to_string(), vec!["src/api/**".to_string()]),
+            ("generated".to_string(), vec!["**/*.pb.go".to_string(), "gen/".to_string()]),
+            ("tests".to_string(), vec!["**/tests/**".to_string()]),
+        ]);
+        let tags = PathTags::new(&config);
+        assert_eq!(tags.tags(Path::new("./src/api/user.rs")), ["api"]);
+        assert_eq!(tags.tags(Path::new("src/api/user.pb.go")), ["api", "generated"]);
+        assert_eq!(tags.tags(Path::new("gen/client/mod.rs")), ["generated"]);
+        assert!(tags.tags(Path::new("src/api_v2/user.rs")).is_empty());
+
+        let tags = tags.with_root(Path::new("/work/project"));
+        assert_eq!(tags.tags(Path::new("/work/project/crates/core/tests/pool.rs")), ["tests"]);
+        assert!(tags.tags(Path::new("/elsewhere/src/api/user.rs")).is_empty());
+    }
+}
@@ -1,13 +1,496 @@
-use anyhow::Result;
+//! Installation and index health checks
+//!
+//! `demongrep doctor` prints a checklist for humans; `--json` emits the same
+//! report for editor extensions and CI. The process exits with status 1 when
+//! any critical check fails. `--bundle` also writes a diagnostic bundle (see
+//! `crate::diagnostics`) containing the report. `--repair` fixes what the
+//! integrity check can rebuild from stored chunk metadata (see
+//! `crate::index::check_integrity`). The report also lists every global
+//! store with its size, and `--prune` deletes the orphaned ones.
 
-pub async fn run() -> Result<()> {
-    println!("🔍 Checking demongrep installation...");
+use anyhow::{anyhow, Result};
+use colored::Colorize;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
 
-    // TODO: Check installation health
-    // - Model paths
-    // - Database integrity
-    // - Dependencies
+use crate::config::{Config, Device};
+use crate::diagnostics::DiagnosticBundle;
+use crate::embed::{is_model_cached, model_cache_dir, ModelType};
+use crate::fts::FtsStore;
+use crate::index::{
+    check_integrity, check_store, get_search_db_paths, global_stores, prune_global_stores, repair, GlobalStore,
+    StoreInventory,
+};
+use crate::vectordb::VectorStore;
 
-    println!("✅ All checks passed!");
+/// Chunks sampled for the self-recall check on each database
+const DOCTOR_SAMPLES: usize = 20;
+
+/// Outcome of a single check
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckStatus {
+    Pass,
+    Warn,
+    Fail,
+}
+
+/// One line of the doctor report
+#[derive(Debug, Clone, Serialize)]
+pub struct Check {
+    pub name: String,
+    pub status: CheckStatus,
+    /// A failing critical check makes `doctor` exit non-zero
+    pub critical: bool,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub remediation: Option<String>,
+}
+
+impl Check {
+    fn pass(name: &str, message: impl Into<String>) -> Self {
+        Self {
+            name: name.to_string(),
+            status: CheckStatus::Pass,
+            critical: false,
+            message: message.into(),
+            remediation: None,
+        }
+    }
+
+    fn warn(name: &str, message: impl Into<String>, remediation: impl Into<String>) -> Self {
+        Self {
+            name: name.to_string(),
+            status: CheckStatus::Warn,
+            critical: false,
+            message: message.into(),
+            remediation: Some(remediation.into()),
+        }
+    }
+
+    fn fail(name: &str, message: impl Into<String>, remediation: impl Into<String>) -> Self {
+        Self {
+            name: name.to_string(),
+            status: CheckStatus::Fail,
+            critical: true,
+            message: message.into(),
+            remediation: Some(remediation.into()),
+        }
+    }
+}
+
+/// Full doctor report, serialized as-is for `--json`
+#[derive(Debug, Clone, Serialize)]
+pub struct DoctorReport {
+    pub version: String,
+    /// Worst status across all checks
+    pub status: CheckStatus,
+    pub checks: Vec<Check>,
+    /// Global stores under ~/.demongrep/stores, largest first
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub stores: Vec<GlobalStore>,
+    /// Path of the diagnostic bundle, when `--bundle` was given
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bundle: Option<String>,
+}
+
+impl DoctorReport {
+    pub fn new(checks: Vec<Check>) -> Self {
+        let status = checks
+            .iter()
+            .map(|c| c.status)
+            .max()
+            .unwrap_or(CheckStatus::Pass);
+        Self {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            status,
+            checks,
+            stores: Vec::new(),
+            bundle: None,
+        }
+    }
+
+    /// Whether any critical check failed
+    pub fn has_critical_failure(&self) -> bool {
+        self.checks
+            .iter()
+            .any(|c| c.critical && c.status == CheckStatus::Fail)
+    }
+}
+
+pub async fn run(path: Option<PathBuf>, json: bool, bundle: bool, repair: bool, prune: bool) -> Result<()> {
+    if json {
+        // Keep stdout clean for the JSON report
+        crate::output::set_quiet(true);
+    }
+
+    let project_root = path.clone().unwrap_or_else(|| PathBuf::from("."));
+    let mut checks = collect_checks(&project_root, path, repair);
+    let mut inventory = global_stores().unwrap_or_default();
+    if prune {
+        checks.push(prune_stores(&inventory));
+        inventory = global_stores().unwrap_or_default();
+    } else {
+        checks.push(check_stores(&inventory));
+    }
+    let mut report = DoctorReport::new(checks);
+    report.stores = inventory.stores;
+
+    if bundle {
+        let extra = serde_json::to_value(&report)?;
+        let bundle_path = DiagnosticBundle::collect(None, Some(extra)).write()?;
+        report.bundle = Some(bundle_path.to_string_lossy().to_string());
+    }
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        print_report(&report);
+    }
+
+    if report.has_critical_failure() {
+        std::process::exit(1);
+    }
     Ok(())
 }
+
+fn collect_checks(project_root: &Path, path: Option<PathBuf>, repair: bool) -> Vec<Check> {
+    let mut checks = Vec::new();
+
+    let config = match Config::load_for(project_root) {
+        Ok(config) => {
+            checks.push(Check::pass("config", "Configuration loaded"));
+            config
+        }
+        Err(e) => {
+            checks.push(Check::fail(
+                "config",
+                format!("Invalid configuration: {}", e),
+                "Fix the setting in .demongrep.toml or ~/.demongrep/config.toml",
+            ));
+            Config::default()
+        }
+    };
+
+    let model = config.index_model_type();
+    checks.push(check_model(&config, model));
+    checks.push(check_device(config.embedding.device));
+    checks.push(check_home());
+    checks.extend(check_databases(path, model, repair));
+
+    checks
+}
+
+fn check_model(config: &Config, model: Option<ModelType>) -> Check {
+    let Some(model) = model else {
+        return Check::fail(
+            "model",
+            format!("Unknown model '{}'", config.index_model_name()),
+            "Pick a model from `demongrep --help` and set it in [embedding] model",
+        );
+    };
+
+    if is_model_cached(model) {
+        Check::pass("model", format!("{} is downloaded", model.short_name()))
+    } else {
+        Check::warn(
+            "model",
+            format!(
+                "{} is not in the model cache ({})",
+                model.short_name(),
+                model_cache_dir().display()
+            ),
+            "It will be downloaded on first use; run `demongrep index` while online",
+        )
+    }
+}
+
+fn check_device(device: Device) -> Check {
+    match device {
+        Device::Cpu => Check::pass("device", "Using CPU"),
+        Device::Cuda if cfg!(feature = "cuda") => Check::pass("device", "Using CUDA"),
+        Device::Cuda => Check::warn(
+            "device",
+            "CUDA requested but this build lacks the `cuda` feature; falling back to CPU",
+            "Rebuild with `cargo install demongrep --features cuda` or set device = \"cpu\"",
+        ),
+        Device::DirectML => Check::warn(
+            "device",
+            "DirectML is not supported by this build; falling back to CPU",
+            "Set [embedding] device = \"cpu\"",
+        ),
+    }
+}
+
+fn check_home() -> Check {
+    let Some(home) = dirs::home_dir() else {
+        return Check::warn(
+            "home",
+            "Could not determine home directory",
+            "Set $HOME; global databases and user config are unavailable without it",
+        );
+    };
+
+    let dir = home.join(".demongrep");
+    match std::fs::create_dir_all(&dir).and_then(|_| tempfile::tempfile_in(&dir)) {
+        Ok(_) => Check::pass("home", format!("{} is writable", dir.display())),
+        Err(e) => Check::warn(
+            "home",
+            format!("{} is not writable: {}", dir.display(), e),
+            "Fix permissions on ~/.demongrep to use --global indexes",
+        ),
+    }
+}
+
+fn check_databases(path: Option<PathBuf>, config_model: Option<ModelType>, repair: bool) -> Vec<Check> {
+    let db_paths = match get_search_db_paths(path) {
+        Ok(paths) => paths,
+        Err(e) => {
+            return vec![Check::fail(
+                "database",
+                format!("Could not resolve database paths: {}", e),
+                "Check that the project path exists",
+            )]
+        }
+    };
+
+    if db_paths.is_empty() {
+        return vec![Check::warn(
+            "database",
+            "No index found for this project",
+            "Run `demongrep index`",
+        )];
+    }
+
+    let mut checks = Vec::new();
+    for db_path in &db_paths {
+        // Integrity first, so a repaired FTS index passes the checks below
+        let integrity = db_model(db_path).and_then(|model| check_db_integrity(db_path, model, repair));
+        if let Ok(check) = integrity {
+            checks.push(check);
+        }
+
+        checks.push(match check_database(db_path, config_model) {
+            Ok(check) => check,
+            Err(e) => Check::fail(
+                "database",
+                format!("{}: {}", db_path.display(), e),
+                "Run `demongrep clear` then `demongrep index` to rebuild it",
+            ),
+        });
+    }
+    checks
+}
+
+/// Model a database was built with, from its metadata.json
+fn db_model(db_path: &Path) -> Result<ModelType> {
+    let content = std::fs::read_to_string(db_path.join("metadata.json"))
+        .map_err(|_| anyhow!("metadata.json is missing"))?;
+    let json: serde_json::Value = serde_json::from_str(&content)?;
+
+    let model_name = json
+        .get("model_short_name")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow!("metadata.json is missing model_short_name"))?;
+    ModelType::from_str(model_name).ok_or_else(|| anyhow!("built with unknown model '{}'", model_name))
+}
+
+/// Cross-check chunk IDs across the stores, repairing them first if asked
+fn check_db_integrity(db_path: &Path, model: ModelType, repair_stores: bool) -> Result<Check> {
+    let mut store = VectorStore::new(db_path, model.dimensions())?;
+    let report = check_integrity(db_path, &store)?;
+    if report.is_ok() {
+        return Ok(Check::pass(
+            "integrity",
+            format!("{}: chunks, vectors, FTS and file metadata agree", db_path.display()),
+        ));
+    }
+
+    let rebuild = "Run `demongrep clear` then `demongrep index` to rebuild it";
+    if !repair_stores {
+        let remediation = if report.repairable() { "Run `demongrep doctor --repair`" } else { rebuild };
+        return Ok(Check::fail(
+            "integrity",
+            format!("{}: {}", db_path.display(), report.problems().join("; ")),
+            remediation,
+        ));
+    }
+
+    let actions = repair(db_path, &mut store, &report)?;
+    let after = check_integrity(db_path, &store)?;
+    let done = if actions.is_empty() { "nothing repairable".to_string() } else { actions.join("; ") };
+    if after.is_ok() {
+        Ok(Check::pass("integrity", format!("{}: repaired ({})", db_path.display(), done)))
+    } else {
+        Ok(Check::fail(
+            "integrity",
+            format!("{}: {} (after repair: {})", db_path.display(), after.problems().join("; "), done),
+            rebuild,
+        ))
+    }
+}
+
+fn check_database(db_path: &Path, config_model: Option<ModelType>) -> Result<Check> {
+    let db_model = db_model(db_path)?;
+
+    let store = VectorStore::new(db_path, db_model.dimensions())?;
+    let fts_documents = FtsStore::open_readonly(db_path)
+        .and_then(|fts| fts.stats())
+        .map(|stats| stats.num_documents)
+        .ok();
+    let report = check_store(&store, fts_documents, DOCTOR_SAMPLES)?;
+
+    let problems = report.problems();
+    if !problems.is_empty() {
+        return Err(anyhow!(problems.join("; ")));
+    }
+
+    if let Some(config_model) = config_model.filter(|m| *m != db_model) {
+        return Ok(Check::warn(
+            "database",
+            format!(
+                "{}: built with {} but config selects {}",
+                db_path.display(),
+                db_model.short_name(),
+                config_model.short_name()
+            ),
+            "Run `demongrep clear` then `demongrep index` to re-embed with the configured model",
+        ));
+    }
+
+    Ok(Check::pass(
+        "database",
+        format!("{}: {} chunks, {}", db_path.display(), report.total_chunks, db_model.short_name()),
+    ))
+}
+
+fn mb(bytes: u64) -> f64 {
+    bytes as f64 / (1024.0 * 1024.0)
+}
+
+fn check_stores(inventory: &StoreInventory) -> Check {
+    let orphaned = inventory.orphaned().count();
+    let stale = inventory.stale_entries.len();
+    if orphaned == 0 && stale == 0 {
+        return Check::pass(
+            "stores",
+            format!("{} global stores, {:.1} MB", inventory.stores.len(), mb(inventory.total_bytes())),
+        );
+    }
+
+    Check::warn(
+        "stores",
+        format!(
+            "{} orphaned global stores ({:.1} MB), {} stale registry entries",
+            orphaned,
+            mb(inventory.orphaned_bytes()),
+            stale
+        ),
+        "Run `demongrep doctor --prune` to delete them",
+    )
+}
+
+fn prune_stores(inventory: &StoreInventory) -> Check {
+    match prune_global_stores(inventory) {
+        Ok(summary) => Check::pass(
+            "stores",
+            format!(
+                "Pruned {} orphaned stores ({:.1} MB freed) and {} registry entries",
+                summary.stores_removed,
+                mb(summary.bytes_freed),
+                summary.entries_removed
+            ),
+        ),
+        Err(e) => Check::warn(
+            "stores",
+            format!("Pruning failed: {}", e),
+            "Check permissions on ~/.demongrep/stores and ~/.demongrep/projects.json",
+        ),
+    }
+}
+
+fn print_stores(stores: &[GlobalStore]) {
+    println!();
+    println!("{}", "💾 Global stores".bright_cyan());
+    println!("{:>10}  {:<12} {:<12} Project", "Size MB", "Indexed", "Status");
+    for store in stores {
+        // RFC 3339 timestamps start with the date
+        let indexed = store.indexed_at.as_deref().and_then(|t| t.get(..10)).unwrap_or("-");
+        // Pad before coloring so escape codes don't throw off the columns
+        let status = match (&store.project, store.project_exists) {
+            (None, _) => format!("{:<12}", "unregistered").yellow(),
+            (Some(_), false) => format!("{:<12}", "orphaned").yellow(),
+            (Some(_), true) => format!("{:<12}", "ok").green(),
+        };
+        let project = match (&store.project, &store.store) {
+            (Some(project), Some(name)) => format!("{} ({})", project.display(), name),
+            (Some(project), None) => project.display().to_string(),
+            (None, _) => store.path.display().to_string(),
+        };
+        println!("{:>10.1}  {:<12} {} {}", mb(store.size_bytes), indexed, status, project);
+    }
+}
+
+fn print_report(report: &DoctorReport) {
+    println!("{}", "🔍 Checking demongrep installation...".bright_cyan().bold());
+    println!("{}", "=".repeat(60));
+
+    for check in &report.checks {
+        let icon = match check.status {
+            CheckStatus::Pass => "✅",
+            CheckStatus::Warn => "⚠️ ",
+            CheckStatus::Fail => "❌",
+        };
+        println!("{} {:<10} {}", icon, check.name, check.message);
+        if let Some(ref remediation) = check.remediation {
+            println!("   {} {}", "→".dimmed(), remediation);
+        }
+    }
+
+    if !report.stores.is_empty() {
+        print_stores(&report.stores);
+    }
+
+    println!();
+    match report.status {
+        CheckStatus::Pass => println!("{}", "✅ All checks passed!".green()),
+        CheckStatus::Warn => println!("{}", "⚠️  Checks passed with warnings".yellow()),
+        CheckStatus::Fail => println!("{}", "❌ Some checks failed".red()),
+    }
+
+    if let Some(ref bundle) = report.bundle {
+        println!("\n📦 Diagnostic bundle written to {}", bundle);
+        println!("   Attach it when filing an issue");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_status_and_exit() {
+        let report = DoctorReport::new(vec![
+            Check::pass("config", "ok"),
+            Check::warn("model", "not cached", "download"),
+        ]);
+        assert_eq!(report.status, CheckStatus::Warn);
+        assert!(!report.has_critical_failure());
+
+        let report = DoctorReport::new(vec![
+            Check::pass("config", "ok"),
+            Check::fail("database", "corrupt", "rebuild"),
+        ]);
+        assert_eq!(report.status, CheckStatus::Fail);
+        assert!(report.has_critical_failure());
+    }
+
+    #[test]
+    fn test_report_json_shape() {
+        let report = DoctorReport::new(vec![Check::pass("config", "ok")]);
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["status"], "pass");
+        assert_eq!(json["checks"][0]["critical"], false);
+        assert!(json["checks"][0].get("remediation").is_none());
+    }
+}
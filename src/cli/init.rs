@@ -0,0 +1,399 @@
+//! First-run setup wizard (`demongrep init`)
+//!
+//! Detects the languages in a repository, recommends an embedding model and
+//! profile, writes `.demongrep.toml` and `.demongrepignore`, optionally installs
+//! git hooks and an MCP config, and kicks off the initial index.
+
+use anyhow::Result;
+use colored::Colorize;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use crate::config::{Config, PROJECT_CONFIG_FILE};
+use crate::embed::ModelType;
+use crate::file::{FileWalker, Language, WalkStats};
+
+/// Marker used to recognise hook lines we installed
+const HOOK_MARKER: &str = "# demongrep: keep index fresh";
+
+/// Git hooks that trigger a background re-index
+const GIT_HOOKS: &[&str] = &["post-commit", "post-merge", "post-checkout"];
+
+/// Indexing profile recommended by the wizard
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Profile {
+    /// Smallest quantized model, for very large repositories
+    Fast,
+    /// Good default for most repositories
+    Balanced,
+    /// Code-specific model plus reranking, for small code-heavy repositories
+    Quality,
+}
+
+impl Profile {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Profile::Fast => "fast",
+            Profile::Balanced => "balanced",
+            Profile::Quality => "quality",
+        }
+    }
+
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "fast" => Some(Profile::Fast),
+            "balanced" => Some(Profile::Balanced),
+            "quality" => Some(Profile::Quality),
+            _ => None,
+        }
+    }
+
+    /// Embedding model used by this profile
+    pub fn model(&self) -> ModelType {
+        match self {
+            Profile::Fast => ModelType::AllMiniLML6V2Q,
+            Profile::Balanced => ModelType::BGESmallENV15Q,
+            Profile::Quality => ModelType::JinaEmbeddingsV2BaseCode,
+        }
+    }
+
+    /// Whether neural reranking is enabled by default
+    pub fn rerank(&self) -> bool {
+        matches!(self, Profile::Quality)
+    }
+
+    fn description(&self) -> &'static str {
+        match self {
+            Profile::Fast => "smallest model, quickest indexing",
+            Profile::Balanced => "good quality at a modest cost",
+            Profile::Quality => "code-specific model with reranking",
+        }
+    }
+}
+
+/// Recommend a profile based on repository size and language mix
+pub fn recommend_profile(stats: &WalkStats) -> Profile {
+    let code_files: usize = stats
+        .files_by_language
+        .iter()
+        .filter(|(lang, _)| lang.supports_tree_sitter())
+        .map(|(_, count)| *count)
+        .sum();
+
+    if stats.indexable_files > 5_000 {
+        Profile::Fast
+    } else if stats.indexable_files <= 1_000 && code_files * 2 >= stats.indexable_files && code_files > 0 {
+        Profile::Quality
+    } else {
+        Profile::Balanced
+    }
+}
+
+/// Run the setup wizard
+pub async fn run(
+    path: Option<PathBuf>,
+    yes: bool,
+    hooks: bool,
+    mcp: bool,
+    no_index: bool,
+    model_override: Option<ModelType>,
+) -> Result<()> {
+    let root = path.unwrap_or_else(|| PathBuf::from(".")).canonicalize()?;
+
+    println!("{}", "🧙 Demongrep Setup".bright_cyan().bold());
+    println!("{}", "=".repeat(60));
+    println!("📂 Project: {}", root.display());
+
+    // Detect languages
+    println!("\n{}", "🔍 Detecting languages...".bright_cyan());
+    let walker = FileWalker::new(root.clone());
+    let (_files, stats) = walker.walk()?;
+
+    if stats.indexable_files == 0 {
+        println!("{}", "No indexable files found - nothing to set up.".yellow());
+        return Ok(());
+    }
+
+    let mut langs: Vec<(&Language, &usize)> = stats.files_by_language.iter().collect();
+    langs.sort_by(|a, b| b.1.cmp(a.1));
+    for (lang, count) in langs.iter().take(5) {
+        println!("   {:<12} {} files", lang.name(), count);
+    }
+    println!("   Total: {} files ({:.2} MB)", stats.indexable_files, stats.total_size_mb());
+
+    // Recommend profile and model
+    let recommended = recommend_profile(&stats);
+    println!(
+        "\n💡 Recommended profile: {} ({})",
+        recommended.name().bright_green(),
+        recommended.description()
+    );
+
+    let profile = if yes {
+        recommended
+    } else {
+        let answer = prompt(&format!("Profile [fast/balanced/quality] ({}): ", recommended.name()))?;
+        if answer.is_empty() {
+            recommended
+        } else {
+            Profile::from_str(&answer).unwrap_or_else(|| {
+                println!("{}", format!("Unknown profile '{}', using {}", answer, recommended.name()).yellow());
+                recommended
+            })
+        }
+    };
+
+    let model = model_override.unwrap_or_else(|| profile.model());
+    println!("🧠 Model: {} ({} dims)", model.name(), model.dimensions());
+
+    // Write .demongrep.toml
+    let config_path = root.join(PROJECT_CONFIG_FILE);
+    if config_path.exists() && !yes && !confirm(&format!("{} exists. Overwrite?", PROJECT_CONFIG_FILE), false)? {
+        println!("   Keeping existing {}", PROJECT_CONFIG_FILE);
+    } else {
+        std::fs::write(&config_path, render_project_config(profile, model))?;
+        println!("{}", format!("✅ Wrote {}", PROJECT_CONFIG_FILE).green());
+    }
+
+    // Write .demongrepignore (never overwrite user patterns)
+    let ignore_path = root.join(".demongrepignore");
+    if ignore_path.exists() {
+        println!("   Keeping existing .demongrepignore");
+    } else {
+        std::fs::write(&ignore_path, render_ignore_file(&stats))?;
+        println!("{}", "✅ Wrote .demongrepignore".green());
+    }
+
+    // Optional git hooks
+    let install_hooks = hooks || (!yes && root.join(".git").is_dir()
+        && confirm("Install git hooks to re-index after commits and checkouts?", false)?);
+    if install_hooks {
+        match install_git_hooks(&root) {
+            Ok(installed) if installed > 0 => println!("{}", format!("✅ Installed {} git hook(s)", installed).green()),
+            Ok(_) => println!("   Git hooks already installed"),
+            Err(e) => eprintln!("{}", format!("⚠️  Could not install git hooks: {}", e).yellow()),
+        }
+    }
+
+    // Optional MCP config
+    let install_mcp = mcp || (!yes && confirm("Write .mcp.json so MCP clients can use demongrep?", false)?);
+    if install_mcp {
+        write_mcp_config(&root)?;
+        println!("{}", "✅ Wrote .mcp.json".green());
+    }
+
+    // Initial index
+    if no_index {
+        println!("\nRun {} when you're ready to index", "demongrep index".bright_cyan());
+        return Ok(());
+    }
+
+    if !yes && !confirm("Start the initial index now?", true)? {
+        println!("\nRun {} when you're ready to index", "demongrep index".bright_cyan());
+        return Ok(());
+    }
+
+    // Reload so the index picks up the config we just wrote
+    let config = Config::load_for(&root)?;
+
+    println!();
+    let options = crate::index::IndexOptions { model: Some(model), ..Default::default() };
+    crate::index::index(Some(root), options, &config).await
+}
+
+/// Render the contents of `.demongrep.toml` for a profile
+fn render_project_config(profile: Profile, model: ModelType) -> String {
+    format!(
+        "# demongrep project configuration\n\
+         # Generated by `demongrep init` (profile: {profile})\n\
+         \n\
+         [embedding]\n\
+         model = \"{model}\"\n\
+         \n\
+         [indexing]\n\
+         max_chunk_lines = 100\n\
+         max_chunk_chars = 2000\n\
+         overlap_lines = 10\n\
+         \n\
+         [search]\n\
+         rerank = {rerank}\n\
+         rrf_k = 20.0\n",
+        profile = profile.name(),
+        model = model.short_name(),
+        rerank = profile.rerank(),
+    )
+}
+
+/// Render `.demongrepignore` with patterns suited to the detected languages
+fn render_ignore_file(stats: &WalkStats) -> String {
+    let has = |lang: Language| stats.files_by_language.contains_key(&lang);
+
+    let mut lines = vec![
+        "# demongrep ignore patterns (gitignore syntax)".to_string(),
+        "# Generated by `demongrep init`".to_string(),
+        String::new(),
+        "# Lock files".to_string(),
+        "package-lock.json".to_string(),
+        "pnpm-lock.yaml".to_string(),
+    ];
+
+    if has(Language::JavaScript) || has(Language::TypeScript) {
+        lines.push(String::new());
+        lines.push("# Bundled and minified JavaScript".to_string());
+        lines.push("*.min.js".to_string());
+        lines.push("*.bundle.js".to_string());
+        lines.push("*.d.ts".to_string());
+    }
+
+    if has(Language::Python) {
+        lines.push(String::new());
+        lines.push("# Generated Python".to_string());
+        lines.push("*_pb2.py".to_string());
+        lines.push("*_pb2_grpc.py".to_string());
+    }
+
+    if has(Language::Go) {
+        lines.push(String::new());
+        lines.push("# Generated Go".to_string());
+        lines.push("*.pb.go".to_string());
+    }
+
+    if has(Language::CSharp) {
+        lines.push(String::new());
+        lines.push("# Generated C#".to_string());
+        lines.push("*.Designer.cs".to_string());
+        lines.push("**/obj/**".to_string());
+    }
+
+    lines.push(String::new());
+    lines.join("\n")
+}
+
+/// Install post-commit/merge/checkout hooks that re-index in the background
+///
+/// Returns the number of hooks that were created or extended.
+fn install_git_hooks(root: &Path) -> Result<usize> {
+    let hooks_dir = root.join(".git").join("hooks");
+    if !hooks_dir.is_dir() {
+        return Err(anyhow::anyhow!("{} is not a git repository", root.display()));
+    }
+
+    let hook_line = format!(
+        "{}\ndemongrep index -q \"$(git rev-parse --show-toplevel)\" >/dev/null 2>&1 &\n",
+        HOOK_MARKER
+    );
+
+    let mut installed = 0;
+    for hook in GIT_HOOKS {
+        let hook_path = hooks_dir.join(hook);
+
+        let content = if hook_path.exists() {
+            let existing = std::fs::read_to_string(&hook_path)?;
+            if existing.contains(HOOK_MARKER) {
+                continue;
+            }
+            format!("{}\n{}", existing.trim_end(), hook_line)
+        } else {
+            format!("#!/bin/sh\n{}", hook_line)
+        };
+
+        std::fs::write(&hook_path, content)?;
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&hook_path, std::fs::Permissions::from_mode(0o755))?;
+        }
+
+        installed += 1;
+    }
+
+    Ok(installed)
+}
+
+/// Add a demongrep entry to the project's `.mcp.json`, preserving other servers
+fn write_mcp_config(root: &Path) -> Result<()> {
+    let mcp_path = root.join(".mcp.json");
+
+    let mut config: serde_json::Value = if mcp_path.exists() {
+        serde_json::from_str(&std::fs::read_to_string(&mcp_path)?)?
+    } else {
+        serde_json::json!({})
+    };
+
+    let command = std::env::current_exe()
+        .map(|p| p.display().to_string())
+        .unwrap_or_else(|_| "demongrep".to_string());
+
+    let servers = config
+        .as_object_mut()
+        .ok_or_else(|| anyhow::anyhow!(".mcp.json is not a JSON object"))?
+        .entry("mcpServers")
+        .or_insert_with(|| serde_json::json!({}));
+
+    servers["demongrep"] = serde_json::json!({
+        "command": command,
+        "args": ["mcp", root.display().to_string()],
+    });
+
+    std::fs::write(&mcp_path, serde_json::to_string_pretty(&config)?)?;
+    Ok(())
+}
+
+/// Read a line of input after printing a prompt
+fn prompt(question: &str) -> Result<String> {
+    print!("{}", question);
+    io::stdout().flush()?;
+
+    let mut input = String::new();
+    io::stdin().read_line(&mut input)?;
+    Ok(input.trim().to_string())
+}
+
+/// Ask a yes/no question, returning `default` on empty input
+fn confirm(question: &str, default: bool) -> Result<bool> {
+    let hint = if default { "(Y/n)" } else { "(y/N)" };
+    let answer = prompt(&format!("{} {}: ", question, hint))?;
+
+    if answer.is_empty() {
+        Ok(default)
+    } else {
+        Ok(answer.eq_ignore_ascii_case("y") || answer.eq_ignore_ascii_case("yes"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats_with(langs: &[(Language, usize)]) -> WalkStats {
+        let mut stats = WalkStats::new();
+        for (lang, count) in langs {
+            stats.files_by_language.insert(*lang, *count);
+            stats.indexable_files += count;
+        }
+        stats
+    }
+
+    #[test]
+    fn test_recommend_profile() {
+        assert_eq!(recommend_profile(&stats_with(&[(Language::Rust, 200)])), Profile::Quality);
+        assert_eq!(recommend_profile(&stats_with(&[(Language::Markdown, 200)])), Profile::Balanced);
+        assert_eq!(recommend_profile(&stats_with(&[(Language::Python, 8_000)])), Profile::Fast);
+    }
+
+    #[test]
+    fn test_project_config_roundtrip() {
+        let rendered = render_project_config(Profile::Quality, Profile::Quality.model());
+        let value: toml::Value = toml::from_str(&rendered).unwrap();
+        assert_eq!(value["embedding"]["model"].as_str(), Some("jina-code"));
+        assert_eq!(value["search"]["rerank"].as_bool(), Some(true));
+    }
+
+    #[test]
+    fn test_ignore_file_languages() {
+        let rendered = render_ignore_file(&stats_with(&[(Language::Go, 10)]));
+        assert!(rendered.contains("*.pb.go"));
+        assert!(!rendered.contains("*.min.js"));
+    }
+}
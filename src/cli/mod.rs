@@ -1,8 +1,11 @@
-use anyhow::Result;
+use anyhow::{bail, Result};
 use clap::{Parser, Subcommand};
 use std::path::PathBuf;
 
+use crate::config::{Config, FusionMethod, UriScheme};
 use crate::embed::ModelType;
+use crate::search::{CountBy, OutputFormat, SearchScope};
+use pager::Pager;
 
 /// Fast, local semantic code search powered by Rust
 #[derive(Parser, Debug)]
@@ -20,10 +23,22 @@ pub struct Cli {
     #[arg(short, long, global = true)]
     pub quiet: bool,
 
-    /// Override default store name
+    /// Disable colors and syntax highlighting (NO_COLOR is honored too)
+    #[arg(long, global = true)]
+    pub no_color: bool,
+
+    /// Don't page long output through $PAGER / less
+    #[arg(long, global = true)]
+    pub no_pager: bool,
+
+    /// Use a named store with its own database (e.g. "docs" vs "code")
     #[arg(long, global = true)]
     pub store: Option<String>,
 
+    /// Only use locally cached models, never download (DEMONGREP_OFFLINE=1 too)
+    #[arg(long, global = true)]
+    pub offline: bool,
+
     /// Embedding model to use (e.g., bge-small, minilm-l6-q, jina-code)
     /// Available: minilm-l6, minilm-l6-q, minilm-l12, minilm-l12-q, paraphrase-minilm,
     ///            bge-small, bge-small-q, bge-base, nomic-v1, nomic-v1.5, nomic-v1.5-q,
@@ -36,16 +51,25 @@ pub struct Cli {
 pub enum Commands {
     /// Search the codebase using natural language
     Search {
-        /// Search query (e.g., "where do we handle authentication?")
-        query: String,
+        /// Search query (e.g., "where do we handle authentication?"), or - to read it from stdin
+        #[arg(required_unless_present_any = ["queries", "queries_file"])]
+        query: Option<String>,
 
-        /// Maximum total results to return
-        #[arg(short = 'm', long, default_value = "25")]
-        max_results: usize,
+        /// Another query to search in the same run (repeatable; results are grouped by query)
+        #[arg(short = 'e', long = "query", value_name = "QUERY", conflicts_with = "all_projects")]
+        queries: Vec<String>,
+
+        /// Search every query in a file, one per line ("-" for stdin; # starts a comment)
+        #[arg(long, value_name = "FILE", conflicts_with = "all_projects")]
+        queries_file: Option<PathBuf>,
+
+        /// Maximum total results to return (default 25, or search.max_results from config)
+        #[arg(short = 'm', long)]
+        max_results: Option<usize>,
 
-        /// Maximum matches to show per file
-        #[arg(long, default_value = "1")]
-        per_file: usize,
+        /// Maximum matches to show per file (default 1, or search.per_file from config)
+        #[arg(long)]
+        per_file: Option<usize>,
 
         /// Show full chunk content instead of snippets
         #[arg(short, long)]
@@ -55,6 +79,10 @@ pub enum Commands {
         #[arg(long)]
         scores: bool,
 
+        /// Show how each result was scored: vector and text ranks, RRF, boosts, reranker
+        #[arg(long)]
+        explain: bool,
+
         /// Show file paths only (like grep -l)
         #[arg(long)]
         compact: bool,
@@ -63,10 +91,27 @@ pub enum Commands {
         #[arg(short, long)]
         sync: bool,
 
+        /// Re-index changed files only if the index is behind the working tree
+        #[arg(long, conflicts_with = "sync")]
+        auto_sync: bool,
+
         /// Output JSON for agents
         #[arg(long)]
         json: bool,
 
+        /// Output format: text, json, jsonl (one result per line), markdown (fenced code with file/line headers),
+        /// sarif, or context (one token-budgeted block for an LLM prompt)
+        #[arg(long, value_enum, conflicts_with_all = ["json", "compact"])]
+        format: Option<OutputFormat>,
+
+        /// Token budget for --format context (default 6000)
+        #[arg(long, value_name = "N")]
+        budget_tokens: Option<usize>,
+
+        /// Leave out results once their code would exceed N LLM tokens in total
+        #[arg(long, value_name = "N", conflicts_with = "watch")]
+        max_total_tokens: Option<usize>,
+
         /// Path to search in (defaults to current directory)
         #[arg(long)]
         path: Option<PathBuf>,
@@ -75,21 +120,230 @@ pub enum Commands {
         #[arg(long)]
         vector_only: bool,
 
-        /// RRF k parameter for score fusion (default 20)
-        #[arg(long, default_value = "20")]
-        rrf_k: f32,
+        /// Pick lexical, hybrid or vector search from the shape of the query
+        #[arg(long, conflicts_with = "vector_only")]
+        auto: bool,
+
+        /// Search the full-text (BM25) index only, without loading the embedding model
+        #[arg(long, conflicts_with_all = ["vector_only", "auto", "queries", "queries_file", "watch"])]
+        text_only: bool,
+
+        /// How hybrid search fuses vector and text results: rrf (by rank) or weighted (by normalized score)
+        #[arg(long, value_enum)]
+        fusion: Option<FusionMethod>,
+
+        /// RRF k parameter for score fusion (default 20, or search.rrf_k from config)
+        #[arg(long)]
+        rrf_k: Option<f32>,
+
+        /// Vector weight for --fusion weighted, 0 to 1; BM25 gets the rest (default 0.5, or search.alpha)
+        #[arg(long)]
+        alpha: Option<f32>,
+
+        /// Candidates fetched by each retriever in hybrid mode (default: adaptive to index size and limit)
+        #[arg(long)]
+        retrieval_depth: Option<usize>,
+
+        /// Score the query against every stored vector instead of the approximate index
+        #[arg(long, conflicts_with_all = ["text_only", "ephemeral", "remote"])]
+        exact: bool,
+
+        /// Candidates the approximate search explores per result and tree (default 15, or search.search_quality)
+        #[arg(long, value_name = "N", conflicts_with_all = ["text_only", "exact", "ephemeral", "remote"])]
+        search_quality: Option<usize>,
 
         /// Enable neural reranking for better accuracy (uses Jina Reranker)
         #[arg(long)]
         rerank: bool,
 
-        /// Number of top results to rerank (default 50)
-        #[arg(long, default_value = "50")]
-        rerank_top: usize,
+        /// Disable neural reranking even if enabled in config
+        #[arg(long, conflicts_with = "rerank")]
+        no_rerank: bool,
+
+        /// Number of top results to rerank (default 50, or search.rerank_top from config)
+        #[arg(long)]
+        rerank_top: Option<usize>,
 
         /// Filter results to files under this path (e.g., "src/")
         #[arg(long)]
         filter_path: Option<String>,
+
+        /// Only show chunks with this tag from indexing.tags (repeatable; any of them)
+        #[arg(long = "tag", value_name = "TAG", conflicts_with_all = ["queries", "queries_file", "watch"])]
+        tags: Vec<String>,
+
+        /// Leave out chunks with this tag (repeatable)
+        #[arg(long = "not-tag", value_name = "TAG", conflicts_with_all = ["queries", "queries_file", "watch"])]
+        not_tags: Vec<String>,
+
+        /// Only show chunks from this package, by the name in its Cargo.toml, package.json, go.mod or pyproject.toml (repeatable; any of them)
+        #[arg(long = "package", value_name = "NAME", conflicts_with_all = ["queries", "queries_file", "watch"])]
+        packages: Vec<String>,
+
+        /// Down-rank vendored dependencies and declaration-only chunks in favor of project code
+        #[arg(long)]
+        prefer_project: bool,
+
+        /// Boost results from recently modified files
+        #[arg(long)]
+        boost_recent: bool,
+
+        /// Show near-duplicate copies from other files as separate results
+        #[arg(long)]
+        keep_duplicates: bool,
+
+        /// Show the complete enclosing function or class of each result
+        #[arg(long)]
+        expand: bool,
+
+        /// Also show the definitions of what the top results call (2), and what those call (3)
+        #[arg(
+            long,
+            value_name = "N",
+            default_value_t = 1,
+            conflicts_with_all = ["queries", "queries_file", "all_projects", "watch", "ephemeral"]
+        )]
+        hops: usize,
+
+        /// Link result paths in the terminal to files or an editor
+        #[arg(long, value_enum)]
+        uri_scheme: Option<UriScheme>,
+
+        /// Open result N in $EDITOR at its line (without N, pick from a list)
+        #[arg(long, value_name = "N", conflicts_with_all = ["queries", "queries_file"])]
+        open: Option<Option<usize>>,
+
+        /// Print how many results there are and in how many files instead of the results
+        #[arg(long, conflicts_with_all = ["queries", "queries_file", "watch", "ephemeral", "open"])]
+        count: bool,
+
+        /// Print result counts per file, language or chunk kind instead of the results
+        #[arg(long, value_enum, value_name = "BY", conflicts_with_all = ["queries", "queries_file", "watch", "ephemeral", "open"])]
+        count_by: Option<CountBy>,
+
+        /// Leave out import-only chunks (same as search.imports_weight = 0)
+        #[arg(long)]
+        no_imports: bool,
+
+        /// Search every project registered in ~/.demongrep/projects.json
+        #[arg(long, conflicts_with_all = ["path", "sync"])]
+        all_projects: bool,
+
+        /// Keep running and print how the results change whenever the index changes
+        #[arg(long, conflicts_with_all = ["json", "format", "compact", "all_projects", "sync", "queries", "queries_file"])]
+        watch: bool,
+
+        /// With --watch, also re-run the query every N seconds
+        #[arg(long, requires = "watch")]
+        interval: Option<u64>,
+
+        /// Search only the results of the previous search, ranked by this query
+        #[arg(long, conflicts_with_all = ["queries", "queries_file", "watch", "sync"])]
+        refine: bool,
+
+        /// Rank files by their summaries first, then search chunks in the best files only (for broad questions)
+        #[arg(long, conflicts_with_all = ["text_only", "queries", "queries_file", "watch", "ephemeral"])]
+        coarse_to_fine: bool,
+
+        /// Files a coarse-to-fine search ranks chunks in (default 10, or search.top_files from config)
+        #[arg(long, value_name = "N")]
+        top_files: Option<usize>,
+
+        /// Show the impl block or class around each matching method instead of the method
+        #[arg(long, conflicts_with_all = ["queries", "queries_file", "watch", "ephemeral"])]
+        return_parents: bool,
+
+        /// Show results from files changed since indexing as the files are now, not as indexed
+        #[arg(long, conflicts_with_all = ["queries", "queries_file", "watch", "ephemeral"])]
+        live: bool,
+
+        /// Search code, or commit messages embedded with `index --with-history`
+        #[arg(
+            long = "in",
+            value_enum,
+            value_name = "WHAT",
+            default_value = "code",
+            conflicts_with_all = ["queries", "queries_file", "watch", "ephemeral", "refine", "all_projects", "remote"]
+        )]
+        scope: SearchScope,
+
+        /// Search a directory without an index: chunk and embed it in memory (small directories only)
+        #[arg(
+            long,
+            conflicts_with_all = ["queries", "queries_file", "all_projects", "watch", "text_only", "sync", "auto_sync", "refine"]
+        )]
+        ephemeral: bool,
+
+        /// Send the query to a running `demongrep serve` instead of loading the model (without URL, the project's server)
+        #[arg(
+            long,
+            value_name = "URL",
+            conflicts_with_all = [
+                "queries", "queries_file", "watch", "ephemeral", "all_projects", "sync", "auto_sync", "explain",
+                "open", "count", "count_by", "refine", "coarse_to_fine", "return_parents", "live", "hops",
+                "text_only", "vector_only", "auto", "expand"
+            ]
+        )]
+        remote: Option<Option<String>>,
+    },
+
+    /// Print the top search results as a context block to paste into an LLM
+    Ask {
+        /// Question or search query
+        query: String,
+
+        /// Token budget for the context block
+        #[arg(short, long, default_value = "4000")]
+        budget: usize,
+
+        /// Maximum results to consider (default 25, or search.max_results from config)
+        #[arg(short = 'm', long)]
+        max_results: Option<usize>,
+
+        /// Only use results from files under this path (e.g., "src/")
+        #[arg(long)]
+        filter_path: Option<String>,
+
+        /// Enable neural reranking for better accuracy (uses Jina Reranker)
+        #[arg(long)]
+        rerank: bool,
+
+        /// Down-rank vendored dependencies and declaration-only chunks in favor of project code
+        #[arg(long)]
+        prefer_project: bool,
+
+        /// Path to the project (defaults to current directory)
+        #[arg(long)]
+        path: Option<PathBuf>,
+    },
+
+    /// Search inside a single file (a semantic Ctrl-F for large files)
+    Within {
+        /// File to search in
+        file: PathBuf,
+
+        /// Search query
+        query: String,
+
+        /// Maximum results to return
+        #[arg(short = 'm', long, default_value = "10")]
+        max_results: usize,
+
+        /// Show full chunk content instead of snippets
+        #[arg(short, long)]
+        content: bool,
+
+        /// Show relevance scores
+        #[arg(long)]
+        scores: bool,
+
+        /// Output JSON for agents
+        #[arg(long)]
+        json: bool,
+
+        /// Path to the project (defaults to current directory)
+        #[arg(long)]
+        path: Option<PathBuf>,
     },
 
     /// Index the repository
@@ -108,13 +362,53 @@ pub enum Commands {
         /// Index to global database in home directory instead of local .demongrep.db
         #[arg(short = 'g', long)]
         global: bool,
+
+        /// Verify the index after indexing (self-recall and FTS consistency checks)
+        #[arg(long)]
+        verify: bool,
+
+        /// Number of chunks to sample during verification
+        #[arg(long, default_value = "50")]
+        verify_samples: usize,
+
+        /// Also embed the last N commit messages, searched with `search --in history`
+        #[arg(long, value_name = "N")]
+        with_history: Option<usize>,
+
+        /// Index the files of this git revision (tag, branch or commit) instead of the working tree
+        #[arg(long, value_name = "REF")]
+        rev: Option<String>,
+
+        /// Trees in the approximate vector index: more raise recall but take longer to build (default: by index size, or indexing.trees)
+        #[arg(long, value_name = "N")]
+        trees: Option<usize>,
+
+        /// Split a new vector index into N shards, built and searched independently (default 1, or indexing.shards)
+        #[arg(long, value_name = "N")]
+        shards: Option<usize>,
     },
 
     /// Run a background server with live file watching
     Serve {
-        /// Port to listen on
-        #[arg(short, long, default_value = "4444")]
-        port: u16,
+        /// Port to listen on (default 4444, or serve.port from config)
+        #[arg(short, long)]
+        port: Option<u16>,
+
+        /// Interface to bind (default 127.0.0.1, or serve.host from config)
+        #[arg(long)]
+        host: Option<String>,
+
+        /// PEM certificate chain to serve HTTPS
+        #[arg(long, requires = "tls_key")]
+        tls_cert: Option<PathBuf>,
+
+        /// PEM private key to serve HTTPS
+        #[arg(long, requires = "tls_cert")]
+        tls_key: Option<PathBuf>,
+
+        /// Serve on this Unix domain socket instead of a TCP port (or serve.socket from config)
+        #[arg(long, value_name = "PATH", conflicts_with_all = ["port", "host", "tls_cert"])]
+        socket: Option<PathBuf>,
 
         /// Path to serve (defaults to current directory)
         path: Option<PathBuf>,
@@ -127,6 +421,16 @@ pub enum Commands {
     Stats {
         /// Path to show stats for (defaults to current directory)
         path: Option<PathBuf>,
+
+        /// Show chunk size distribution (chars and estimated tokens)
+        #[arg(long)]
+        chunks: bool,
+    },
+
+    /// Merge full-text index segments, purge deleted documents and fold pending vector changes into the index
+    Optimize {
+        /// Path to optimize (defaults to current directory)
+        path: Option<PathBuf>,
     },
 
     /// Clear the vector database
@@ -143,14 +447,142 @@ pub enum Commands {
         project: Option<String>,
     },
 
-    /// Check installation health
-    Doctor,
+    /// Export the index to a portable archive (.tar.zst)
+    Export {
+        /// Archive to write (e.g. index.tar.zst)
+        output: PathBuf,
+
+        /// Project whose index to export (defaults to current directory)
+        path: Option<PathBuf>,
+    },
+
+    /// Export chunk embeddings with their metadata for analysis (Parquet, NumPy or JSON Lines)
+    ExportEmbeddings {
+        /// Project whose embeddings to export (defaults to current directory)
+        path: Option<PathBuf>,
+
+        /// File format
+        #[arg(long, value_enum, default_value = "parquet")]
+        format: crate::dataset::EmbeddingFormat,
+
+        /// File to write (default: embeddings.parquet, .npy or .jsonl)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Write the definitions found while parsing to a SCIP index
+    Scip {
+        /// Project to parse (defaults to current directory)
+        path: Option<PathBuf>,
+
+        /// File to write
+        #[arg(short, long, default_value = "index.scip")]
+        output: PathBuf,
+    },
+
+    /// Import an index archive created by `demongrep export`
+    Import {
+        /// Archive to read
+        archive: PathBuf,
+
+        /// Project to import into (defaults to current directory)
+        path: Option<PathBuf>,
+
+        /// Import into the global database in home directory instead of local .demongrep.db
+        #[arg(short = 'g', long)]
+        global: bool,
+
+        /// Replace an existing database
+        #[arg(short, long)]
+        force: bool,
+    },
+
+    /// Run performance benchmarks
+    Bench {
+        #[command(subcommand)]
+        command: BenchCommands,
+    },
+
+    /// Keep the index up to date from a background process (start, stop, status)
+    Daemon {
+        #[command(subcommand)]
+        command: DaemonCommands,
+    },
+
+    /// Attach a team note to the indexed code at a file location
+    Note {
+        /// Location as <path>:<line>, with the line as shown in search results
+        location: String,
+
+        /// Note text (omit to list the notes on that code)
+        message: Option<String>,
+
+        /// Remove every note on that code
+        #[arg(long, conflicts_with = "message")]
+        clear: bool,
+
+        /// Path to the project (defaults to current directory)
+        #[arg(long)]
+        path: Option<PathBuf>,
+    },
+
+    /// Check installation and index health
+    Doctor {
+        /// Path to the project (defaults to current directory)
+        path: Option<PathBuf>,
+
+        /// Output the report as JSON (exit code 1 if a critical check fails)
+        #[arg(long)]
+        json: bool,
+
+        /// Also write a diagnostic bundle to attach to bug reports
+        #[arg(long)]
+        bundle: bool,
 
-    /// Download embedding models
+        /// Rebuild the FTS index from stored chunks and prune dangling vectors
+        #[arg(long)]
+        repair: bool,
+
+        /// Delete global stores whose project is gone, and stale registry entries
+        #[arg(long)]
+        prune: bool,
+    },
+
+    /// Set up demongrep for a project (detect languages, write config, index)
+    Init {
+        /// Path to the project (defaults to current directory)
+        path: Option<PathBuf>,
+
+        /// Accept recommended defaults without prompting
+        #[arg(short = 'y', long)]
+        yes: bool,
+
+        /// Install git hooks that re-index after commits and checkouts
+        #[arg(long)]
+        hooks: bool,
+
+        /// Write a .mcp.json entry for the demongrep MCP server
+        #[arg(long)]
+        mcp: bool,
+
+        /// Skip the initial index
+        #[arg(long)]
+        no_index: bool,
+    },
+
+    /// Download the embedding model and reranker for offline use
     Setup {
-        /// Model to download (defaults to mxbai-embed-xsmall-v1)
+        /// Embedding model to download (defaults to the configured model)
         #[arg(long)]
         model: Option<String>,
+
+        /// Skip downloading the neural reranker
+        #[arg(long)]
+        no_reranker: bool,
+
+        /// List all models with their download status and size on disk
+        #[arg(long, conflicts_with_all = ["model", "no_reranker"])]
+        list: bool,
     },
 
     /// Start MCP server for Claude Code integration
@@ -160,6 +592,153 @@ pub enum Commands {
     },
 }
 
+#[derive(Subcommand, Debug)]
+pub enum BenchCommands {
+    /// Measure chunker throughput per language over a directory
+    Chunk {
+        /// Path to benchmark (defaults to current directory)
+        path: Option<PathBuf>,
+
+        /// Number of passes over the files
+        #[arg(short = 'n', long, default_value = "3")]
+        iterations: usize,
+
+        /// Output results as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Measure indexing throughput and peak memory per pipeline phase
+    Index {
+        /// Path to benchmark (defaults to current directory)
+        path: Option<PathBuf>,
+
+        /// Output results as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Measure latency and accuracy of hybrid search at several retrieval depths
+    Search {
+        /// File with one query per line, optionally followed by a tab and the expected path
+        queries: PathBuf,
+
+        /// Path to the project (defaults to current directory)
+        #[arg(long)]
+        path: Option<PathBuf>,
+
+        /// Retrieval depths to compare, comma-separated (adaptive depth is always included)
+        #[arg(long, value_delimiter = ',', default_values_t = [50, 100, 200, 400])]
+        depths: Vec<usize>,
+
+        /// Results per query that accuracy is measured on
+        #[arg(short = 'm', long, default_value = "10")]
+        max_results: usize,
+
+        /// Number of timed runs per query and depth
+        #[arg(short = 'n', long, default_value = "3")]
+        iterations: usize,
+
+        /// Output results as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Score retrieval quality (recall@k, MRR, nDCG) against a labelled dataset
+    Eval {
+        /// JSONL file with one {"query", "expected": [{"path", "start_line", "end_line"}]} per line
+        #[arg(long)]
+        dataset: PathBuf,
+
+        /// Path to the project (defaults to current directory)
+        #[arg(long)]
+        path: Option<PathBuf>,
+
+        /// Number of top results scored per query
+        #[arg(short = 'k', long, default_value = "10")]
+        k: usize,
+
+        /// Enable neural reranking
+        #[arg(long)]
+        rerank: bool,
+
+        /// Down-rank vendored dependencies and declaration-only chunks in favor of project code
+        #[arg(long)]
+        prefer_project: bool,
+
+        /// Output results as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Compare embedding models by accuracy, index time and index size on a sample of the project
+    Models {
+        /// Models to compare, comma-separated (e.g. minilm-l6-q,bge-small,jina-code)
+        #[arg(long, value_delimiter = ',', required = true)]
+        models: Vec<String>,
+
+        /// JSONL dataset in the `bench eval` format
+        #[arg(long)]
+        dataset: PathBuf,
+
+        /// Path to the project (defaults to current directory)
+        #[arg(long)]
+        path: Option<PathBuf>,
+
+        /// Files to index per model (0 = all); files named in the dataset are always included
+        #[arg(long, default_value = "500")]
+        max_files: usize,
+
+        /// Number of top results scored per query
+        #[arg(short = 'k', long, default_value = "10")]
+        k: usize,
+
+        /// Enable neural reranking
+        #[arg(long)]
+        rerank: bool,
+
+        /// Down-rank vendored dependencies and declaration-only chunks in favor of project code
+        #[arg(long)]
+        prefer_project: bool,
+
+        /// Output results as JSON
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum DaemonCommands {
+    /// Start watching a project in a detached background process
+    Start {
+        /// Path to the project (defaults to current directory)
+        path: Option<PathBuf>,
+    },
+
+    /// Stop the project's background daemon
+    Stop {
+        /// Path to the project (defaults to current directory)
+        path: Option<PathBuf>,
+    },
+
+    /// Show whether a daemon is running for the project
+    Status {
+        /// Path to the project (defaults to current directory)
+        path: Option<PathBuf>,
+
+        /// Output status as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Watch and re-index in the foreground (what `daemon start` launches)
+    #[command(hide = true)]
+    Run {
+        /// Path to the project (defaults to current directory)
+        path: Option<PathBuf>,
+    },
+}
+
 pub async fn run() -> Result<()> {
     let cli = Cli::parse();
 
@@ -177,44 +756,355 @@ pub async fn run() -> Result<()> {
     if cli.quiet {
         crate::output::set_quiet(true);
     }
+    if cli.no_color {
+        colored::control::set_override(false);
+    }
+
+    // Select the named store before any database paths are resolved
+    if let Some(ref store) = cli.store {
+        crate::index::set_store(store)?;
+    }
+
+    // Load config for the project this command targets (CLI > project > user > defaults)
+    let project_path = match &cli.command {
+        Commands::Search { path, .. }
+        | Commands::Index { path, .. }
+        | Commands::Serve { path, .. }
+        | Commands::Stats { path, .. }
+        | Commands::Clear { path, .. }
+        | Commands::Export { path, .. }
+        | Commands::Import { path, .. }
+        | Commands::Scip { path, .. }
+        | Commands::ExportEmbeddings { path, .. }
+        | Commands::Init { path, .. }
+        | Commands::Doctor { path, .. }
+        | Commands::Note { path, .. }
+        | Commands::Within { path, .. }
+        | Commands::Ask { path, .. }
+        | Commands::Bench { command: BenchCommands::Chunk { path, .. } }
+        | Commands::Bench { command: BenchCommands::Search { path, .. } }
+        | Commands::Bench { command: BenchCommands::Index { path, .. } }
+        | Commands::Bench { command: BenchCommands::Eval { path, .. } }
+        | Commands::Bench { command: BenchCommands::Models { path, .. } }
+        | Commands::Daemon {
+            command:
+                DaemonCommands::Start { path }
+                | DaemonCommands::Stop { path }
+                | DaemonCommands::Status { path, .. }
+                | DaemonCommands::Run { path },
+        }
+        | Commands::Mcp { path } => path.clone(),
+        _ => None,
+    };
+    let project_root = project_path.unwrap_or_else(|| PathBuf::from("."));
+    crate::diagnostics::set_project_root(project_root.clone());
+    let config = match Config::load_for(&project_root) {
+        Ok(config) => config,
+        // doctor reports a broken config as a failed check instead of aborting
+        Err(_) if matches!(cli.command, Commands::Doctor { .. }) => Config::default(),
+        Err(e) => return Err(e),
+    };
+    crate::embed::set_device(config.embedding.device);
+    crate::embed::set_offline(cli.offline);
+    if config.embedding.query_cache {
+        if let Err(e) = crate::embed::init_query_cache(&config.data_dir.join("query_cache")) {
+            tracing::debug!("Query cache unavailable: {}", e);
+        }
+    }
 
+    // Config values are defaults; explicit CLI flags always win
+    let config_model = config.index_model_type();
+    if config_model.is_none() {
+        crate::warn_print!(
+            "Unknown model '{}' in config, falling back to default",
+            config.index_model_name()
+        );
+    }
+
+    let verbose = cli.verbose;
     match cli.command {
         Commands::Search {
             query,
+            queries,
+            queries_file,
             max_results,
             per_file,
             content,
             scores,
+            explain,
             compact,
             sync,
+            auto_sync,
             json,
+            format,
+            budget_tokens,
+            max_total_tokens,
             path,
             vector_only,
+            auto,
+            text_only,
+            fusion,
             rrf_k,
+            alpha,
+            retrieval_depth,
+            exact,
+            search_quality,
             rerank,
+            no_rerank,
             rerank_top,
             filter_path,
+            tags,
+            not_tags,
+            packages,
+            prefer_project,
+            boost_recent,
+            keep_duplicates,
+            expand,
+            hops,
+            uri_scheme,
+            open,
+            count,
+            count_by,
+            no_imports,
+            all_projects,
+            watch,
+            interval,
+            refine,
+            coarse_to_fine,
+            top_files,
+            return_parents,
+            live,
+            scope,
+            ephemeral,
+            remote,
         } => {
-            // Auto-enable quiet mode for JSON output
-            if json {
+            let mut config = config;
+            if no_imports {
+                config.search.imports_weight = 0.0;
+            }
+            if let Some(fusion) = fusion {
+                config.search.fusion = fusion;
+            }
+            if let Some(rrf_k) = rrf_k {
+                config.search.rrf_k = rrf_k;
+            }
+            if let Some(alpha) = alpha {
+                if !(0.0..=1.0).contains(&alpha) {
+                    bail!("--alpha must be between 0 and 1");
+                }
+                config.search.alpha = alpha;
+            }
+            if top_files == Some(0) {
+                bail!("--top-files must be greater than 0");
+            }
+            if exact {
+                config.search.exact_threshold = usize::MAX;
+            }
+            if let Some(quality) = search_quality {
+                if quality == 0 {
+                    bail!("--search-quality must be greater than 0");
+                }
+                config.search.search_quality = quality;
+            }
+
+            // `search -` reads the query, e.g. an error message or stack trace, from stdin
+            let query = match query {
+                Some(query) if query == "-" => Some(crate::search::read_stdin_query()?),
+                query => query,
+            };
+
+            // With -e or --queries-file, all queries are searched in one batch
+            let batch = !queries.is_empty() || queries_file.is_some();
+            let mut queries: Vec<String> = query.iter().cloned().chain(queries).collect();
+            if let Some(ref file) = queries_file {
+                queries.extend(crate::search::read_queries_file(file)?);
+            }
+            let query = query.unwrap_or_default();
+
+            if watch {
+                return crate::search::watch_search(
+                    &query,
+                    max_results.unwrap_or(config.search.max_results),
+                    path,
+                    filter_path,
+                    model_type,
+                    vector_only || config.search.vector_only,
+                    (auto || config.search.auto) && !vector_only,
+                    retrieval_depth.or(config.search.retrieval_depth),
+                    config.search.hybrid_fusion(),
+                    (rerank || config.search.rerank) && !no_rerank,
+                    rerank_top.unwrap_or(config.search.rerank_top),
+                    prefer_project || config.search.prefer_project,
+                    interval.filter(|&secs| secs > 0).map(std::time::Duration::from_secs),
+                    &config,
+                )
+                .await;
+            }
+
+            let format = if json { OutputFormat::Json } else { format.unwrap_or(OutputFormat::Text) };
+            if budget_tokens.is_some() && format != OutputFormat::Context {
+                bail!("--budget-tokens only applies to --format context");
+            }
+            let budget_tokens = budget_tokens.unwrap_or(crate::search::DEFAULT_BUDGET_TOKENS);
+            if let Some(tag) = tags.iter().chain(&not_tags).find(|tag| !config.indexing.tags.contains_key(*tag)) {
+                crate::warn_print!("⚠️  No entry in [indexing.tags] defines the tag \"{}\"", tag);
+            }
+
+            // Auto-enable quiet mode for JSON and Markdown output
+            if format != OutputFormat::Text {
                 crate::output::set_quiet(true);
             }
+            if format == OutputFormat::Text {
+                crate::search::enable_hyperlinks(uri_scheme.unwrap_or(config.search.uri_scheme));
+            }
+            // The editor and the prompt need the terminal
+            let paged = format == OutputFormat::Text && open.is_none() && !cli.no_pager;
+            let _pager = paged.then(Pager::start).flatten();
+            if scope == SearchScope::History {
+                return crate::search::history_search(
+                    &query,
+                    max_results.unwrap_or(config.search.max_results),
+                    content || config.search.content,
+                    scores || config.search.scores,
+                    format,
+                    path,
+                )
+                .await;
+            }
+            if ephemeral {
+                return crate::search::ephemeral_search(
+                    &query,
+                    max_results.unwrap_or(config.search.max_results),
+                    content || config.search.content,
+                    scores || config.search.scores,
+                    format,
+                    path,
+                    filter_path,
+                    tags,
+                    not_tags,
+                    packages,
+                    model_type.or(config_model).unwrap_or_default(),
+                    config.search.collapse_duplicates && !keep_duplicates,
+                    budget_tokens,
+                    max_total_tokens,
+                    &config,
+                )
+                .await;
+            }
+            // search.auto_daemon sends searches a server can answer to a search daemon
+            let local_only = batch
+                || sync
+                || auto_sync
+                || explain
+                || open.is_some()
+                || count
+                || count_by.is_some()
+                || refine
+                || coarse_to_fine
+                || return_parents
+                || live
+                || hops > 1
+                || text_only
+                || vector_only
+                || auto
+                || expand
+                || all_projects
+                || format == OutputFormat::Sarif
+                || !crate::search::ParsedQuery::parse(&query)?.filters.is_empty();
+            let remote = match remote {
+                Some(url) => Some(url),
+                None if config.search.auto_daemon && !local_only => {
+                    match crate::daemon::ensure_search_daemon(path.clone(), &config).await {
+                        Ok(url) => url.map(Some),
+                        Err(e) => {
+                            crate::warn_print!("⚠️  {:#}; searching locally", e);
+                            None
+                        }
+                    }
+                }
+                None => None,
+            };
+            if let Some(url) = remote {
+                let rerank = if rerank { Some(true) } else { no_rerank.then_some(false) };
+                return crate::search::remote_search(
+                    &query,
+                    url,
+                    max_results.unwrap_or(config.search.max_results),
+                    content || config.search.content,
+                    scores || config.search.scores,
+                    format,
+                    path,
+                    filter_path,
+                    tags,
+                    not_tags,
+                    packages,
+                    rerank,
+                    budget_tokens,
+                    max_total_tokens,
+                    &config,
+                )
+                .await;
+            }
+            if batch {
+                config.search.retrieval_depth = retrieval_depth.or(config.search.retrieval_depth);
+                return crate::search::batch_search(
+                    &queries,
+                    max_results.unwrap_or(config.search.max_results),
+                    content || config.search.content,
+                    scores || config.search.scores,
+                    format,
+                    path,
+                    filter_path,
+                    (rerank || config.search.rerank) && !no_rerank,
+                    rerank_top.unwrap_or(config.search.rerank_top),
+                    prefer_project || config.search.prefer_project,
+                    config.search.collapse_duplicates && !keep_duplicates,
+                    max_total_tokens,
+                    &config,
+                )
+                .await;
+            }
             crate::search::search(
                 &query,
-                max_results,
-                per_file,
-                content,
-                scores,
-                compact,
-                sync,
-                json,
+                max_results.unwrap_or(config.search.max_results),
+                per_file.unwrap_or(config.search.per_file),
+                content || config.search.content,
+                scores || config.search.scores,
+                compact || config.search.compact,
+                (sync || config.search.sync) && !all_projects,
+                format,
                 path,
                 filter_path,
+                tags,
+                not_tags,
+                packages,
                 model_type,
-                vector_only,
-                rrf_k,
-                rerank,
-                rerank_top,
+                vector_only || config.search.vector_only,
+                (auto || config.search.auto) && !vector_only,
+                text_only,
+                verbose,
+                retrieval_depth.or(config.search.retrieval_depth),
+                config.search.hybrid_fusion(),
+                (rerank || config.search.rerank) && !no_rerank,
+                rerank_top.unwrap_or(config.search.rerank_top),
+                prefer_project || config.search.prefer_project,
+                all_projects,
+                (auto_sync || config.search.auto_sync) && !all_projects,
+                boost_recent || config.search.boost_recent,
+                config.search.collapse_duplicates && !keep_duplicates,
+                expand,
+                explain,
+                open,
+                count_by.map(Some).or(count.then_some(None)),
+                refine,
+                coarse_to_fine || config.search.coarse_to_fine,
+                top_files.unwrap_or(config.search.top_files),
+                return_parents,
+                live,
+                budget_tokens,
+                max_total_tokens,
+                hops,
+                &config,
             )
             .await
         }
@@ -223,16 +1113,149 @@ pub async fn run() -> Result<()> {
             dry_run,
             force,
             global,
-        } => crate::index::index(path, dry_run, force, global, model_type).await,
-        Commands::Serve { port, path } => crate::server::serve(port, path).await,
+            verify,
+            verify_samples,
+            with_history,
+            rev,
+            trees,
+            shards,
+        } => {
+            if with_history == Some(0) {
+                bail!("--with-history must be greater than 0");
+            }
+            if trees == Some(0) {
+                bail!("--trees must be greater than 0");
+            }
+            let mut config = config;
+            if trees.is_some() {
+                config.indexing.trees = trees;
+            }
+            if let Some(shards) = shards {
+                if !(1..=crate::vectordb::MAX_SHARDS).contains(&shards) {
+                    bail!("--shards must be between 1 and {}", crate::vectordb::MAX_SHARDS);
+                }
+                config.indexing.shards = shards;
+            }
+            let options = crate::index::IndexOptions {
+                dry_run,
+                force,
+                global: global || config.index.global,
+                model: model_type.or(config_model),
+                verify_samples: (verify || config.index.verify).then_some(verify_samples),
+                history: with_history,
+                rev,
+            };
+            crate::index::index(path, options, &config).await
+        }
+        Commands::Serve { port, host, tls_cert, tls_key, socket, path } => {
+            // An explicit --host or --port picks TCP over a configured socket
+            let socket = socket.or_else(|| config.serve.socket.clone()).filter(|_| host.is_none() && port.is_none());
+            let host = host.unwrap_or_else(|| config.serve.host.clone());
+            let tls = crate::server::TlsFiles::resolve(
+                tls_cert.or_else(|| config.serve.tls_cert.clone()),
+                tls_key.or_else(|| config.serve.tls_key.clone()),
+            )?;
+            if socket.is_some() && tls.is_some() {
+                bail!("A Unix socket is served without TLS; leave out the certificate or the socket");
+            }
+            crate::server::serve(&host, port.unwrap_or(config.serve.port), tls, socket, path, &config).await
+        }
         Commands::List => crate::index::list().await,
-        Commands::Stats { path } => crate::index::stats(path).await,
+        Commands::Export { output, path } => crate::bundle::export(output, path).await,
+        Commands::Scip { path, output } => crate::scip::export_scip(path, output, &config).await,
+        Commands::ExportEmbeddings { path, format, output } => {
+            crate::dataset::export_embeddings(path, format, output).await
+        }
+        Commands::Import {
+            archive,
+            path,
+            global,
+            force,
+        } => crate::bundle::import(archive, path, global, force).await,
+        Commands::Stats { path, chunks } => crate::index::stats(path, chunks).await,
+        Commands::Optimize { path } => crate::index::optimize(path, &config).await,
         Commands::Clear { path, yes, project } => crate::index::clear(path, yes, project).await,
-        Commands::Doctor => crate::cli::doctor::run().await,
-        Commands::Setup { model } => crate::cli::setup::run(model).await,
-        Commands::Mcp { path } => crate::mcp::run_mcp_server(path).await,
+        Commands::Bench { command } => match command {
+            BenchCommands::Chunk { path, iterations, json } => {
+                crate::bench::chunk(path, iterations, json, &config).await
+            }
+            BenchCommands::Index { path, json } => {
+                crate::bench::index(path, model_type.or(config_model), json, &config).await
+            }
+            BenchCommands::Search { queries, path, depths, max_results, iterations, json } => {
+                crate::bench::search(queries, path, depths, max_results, iterations, json, &config).await
+            }
+            BenchCommands::Eval { dataset, path, k, rerank, prefer_project, json } => {
+                crate::bench::eval(
+                    dataset,
+                    path,
+                    k,
+                    rerank || config.search.rerank,
+                    prefer_project || config.search.prefer_project,
+                    json,
+                    &config,
+                )
+                .await
+            }
+            BenchCommands::Models { models, dataset, path, max_files, k, rerank, prefer_project, json } => {
+                crate::bench::models(
+                    models,
+                    dataset,
+                    path,
+                    max_files,
+                    k,
+                    rerank || config.search.rerank,
+                    prefer_project || config.search.prefer_project,
+                    json,
+                    &config,
+                )
+                .await
+            }
+        },
+        Commands::Ask { query, budget, max_results, filter_path, rerank, prefer_project, path } => {
+            crate::search::ask(
+                &query,
+                budget,
+                max_results.unwrap_or(config.search.max_results),
+                filter_path,
+                rerank || config.search.rerank,
+                config.search.rerank_top,
+                prefer_project || config.search.prefer_project,
+                path,
+                &config,
+            )
+            .await
+        }
+        Commands::Within { file, query, max_results, content, scores, json, path } => {
+            if !json {
+                crate::search::enable_hyperlinks(config.search.uri_scheme);
+            }
+            let _pager = (!json && !cli.no_pager).then(Pager::start).flatten();
+            crate::search::within(file, &query, max_results, content, scores || config.search.scores, json, path, &config)
+                .await
+        }
+        Commands::Note { location, message, clear, path } => {
+            crate::cli::note::run(location, message, clear, path, &config).await
+        }
+        Commands::Doctor { path, json, bundle, repair, prune } => {
+            crate::cli::doctor::run(path, json, bundle, repair, prune).await
+        }
+        Commands::Init { path, yes, hooks, mcp, no_index } => {
+            crate::cli::init::run(path, yes, hooks, mcp, no_index, model_type).await
+        }
+        Commands::Setup { model, no_reranker, list } => crate::cli::setup::run(model, no_reranker, list, &config).await,
+        Commands::Daemon { command } => match command {
+            DaemonCommands::Start { path } => crate::daemon::start(path, &config).await,
+            DaemonCommands::Stop { path } => crate::daemon::stop(path, &config).await,
+            DaemonCommands::Status { path, json } => crate::daemon::status(path, json, &config).await,
+            DaemonCommands::Run { path } => crate::daemon::run(path, &config).await,
+        },
+        Commands::Mcp { path } => crate::mcp::run_mcp_server(path, &config).await,
     }
 }
 
 mod doctor;
+mod init;
+mod note;
+mod pager;
 mod setup;
@@ -0,0 +1,172 @@
+//! Team notes on indexed code (`demongrep note`)
+//!
+//! A note is stored in the index next to the chunk it describes, keyed by the
+//! chunk's content hash. Search output shows it under matching results, and
+//! it is embedded with the chunk, so a later query phrased like the note
+//! finds that code. The chunk's file is reindexed right away so a new note
+//! takes effect immediately.
+
+use anyhow::{anyhow, bail, Result};
+use colored::Colorize;
+use std::path::{Path, PathBuf};
+
+use crate::chunker::SemanticChunker;
+use crate::config::Config;
+use crate::database::DatabaseManager;
+use crate::embed::EmbeddingService;
+use crate::file::{FileInfo, Language};
+use crate::index::{apply_changes, PendingChanges};
+use crate::vectordb::ChunkNote;
+
+/// Split `<path>:<line>` into its parts
+fn parse_location(location: &str) -> Result<(&str, usize)> {
+    let (path, line) = location
+        .rsplit_once(':')
+        .ok_or_else(|| anyhow!("Expected <path>:<line>, got '{}'", location))?;
+    let line = line
+        .parse()
+        .map_err(|_| anyhow!("Invalid line number '{}' in '{}'", line, location))?;
+    if path.is_empty() {
+        bail!("Expected <path>:<line>, got '{}'", location);
+    }
+    Ok((path, line))
+}
+
+/// Who is writing the note: git's user.name, falling back to the login name
+fn note_author() -> Option<String> {
+    std::process::Command::new("git")
+        .args(["config", "user.name"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+        .filter(|name| !name.is_empty())
+        .or_else(|| std::env::var("USER").or_else(|_| std::env::var("USERNAME")).ok())
+}
+
+pub async fn run(
+    location: String,
+    message: Option<String>,
+    clear: bool,
+    path: Option<PathBuf>,
+    config: &Config,
+) -> Result<()> {
+    let (file, line) = parse_location(&location)?;
+    let mut db_manager = DatabaseManager::load(path)?;
+
+    // The first database (local before global) with a chunk covering the line
+    let mut found = None;
+    for (index, database) in db_manager.databases().iter().enumerate() {
+        if let Some((_, chunk)) = database.store().find_chunk_at(file, line)? {
+            found = Some((index, chunk));
+            break;
+        }
+    }
+    let Some((index, chunk)) = found else {
+        bail!(
+            "No indexed chunk covers {}:{}. Run `demongrep index` if the file is new or changed",
+            file,
+            line
+        );
+    };
+
+    let heading = format!(
+        "{}:{}-{} {}",
+        chunk.path,
+        chunk.start_line,
+        chunk.end_line,
+        chunk.signature.as_deref().unwrap_or(&chunk.kind)
+    );
+
+    let model_type = db_manager.model_type();
+    let database = &mut db_manager.databases_mut()[index];
+
+    let Some(text) = message else {
+        if clear {
+            let removed = database.store_mut().remove_notes(&chunk.hash)?;
+            println!("🗑️  Removed {} note(s) from {}", removed, heading.bright_green());
+            if removed > 0 {
+                reindex_file(database, &chunk.path, model_type, config)?;
+            }
+            return Ok(());
+        }
+
+        let notes = database.store().get_notes(&chunk.hash)?;
+        println!("📝 {}", heading.bright_green());
+        if notes.is_empty() {
+            println!("   {}", "No notes".dimmed());
+        }
+        for note in notes {
+            let created = chrono::DateTime::from_timestamp(note.created_at as i64, 0)
+                .map(|t| t.format("%Y-%m-%d").to_string())
+                .unwrap_or_default();
+            let by = note.author.map(|a| format!("{}, ", a)).unwrap_or_default();
+            println!("   • {} {}", note.text, format!("({}{})", by, created).dimmed());
+        }
+        return Ok(());
+    };
+
+    let text = text.trim().to_string();
+    if text.is_empty() {
+        bail!("Note text is empty");
+    }
+    let note = ChunkNote {
+        text,
+        author: note_author(),
+        created_at: chrono::Utc::now().timestamp() as u64,
+    };
+    let count = database.store_mut().add_note(&chunk.hash, note)?;
+    println!("📝 Added note to {} ({} total)", heading.bright_green(), count);
+
+    reindex_file(database, &chunk.path, model_type, config)
+}
+
+/// Re-embed a file's chunks so the notes on them are part of their vectors
+fn reindex_file(
+    database: &mut crate::database::Database,
+    file_path: &str,
+    model_type: crate::embed::ModelType,
+    config: &Config,
+) -> Result<()> {
+    let path = Path::new(file_path);
+    if !path.exists() {
+        crate::warn_print!(
+            "{}",
+            format!("⚠️  {} is not on disk here; the note applies when it is next indexed", file_path).yellow()
+        );
+        return Ok(());
+    }
+
+    crate::info_print!("{}", "🔄 Re-embedding the file with its notes...".dimmed());
+    let changes = PendingChanges {
+        changed: vec![(
+            FileInfo {
+                path: path.to_path_buf(),
+                language: Language::from_path(path),
+                size: std::fs::metadata(path)?.len(),
+            },
+            database.store().file_chunk_ids(file_path)?,
+        )],
+        deleted: vec![],
+    };
+
+    let mut embedding_service = EmbeddingService::with_model(model_type)?;
+    let mut chunker = SemanticChunker::from_config(&config.indexing);
+    let db_path = database.path.clone();
+    apply_changes(database.store_mut(), &db_path, &changes, &mut embedding_service, &mut chunker)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_location() {
+        assert_eq!(parse_location("src/auth.rs:42").unwrap(), ("src/auth.rs", 42));
+        assert_eq!(parse_location("C:/repo/main.rs:7").unwrap(), ("C:/repo/main.rs", 7));
+        assert!(parse_location("src/auth.rs").is_err());
+        assert!(parse_location("src/auth.rs:x").is_err());
+        assert!(parse_location(":3").is_err());
+    }
+}
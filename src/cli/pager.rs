@@ -0,0 +1,122 @@
+//! Page long terminal output through `$PAGER`, like git does
+//!
+//! While a `Pager` is alive, stdout is redirected into the pager's stdin.
+//! `LESS=FRX` is set unless the user has their own `LESS`, so less exits
+//! right away when the output fits on one screen, keeps colors, and leaves
+//! the output on screen afterwards. Paging only happens when stdout is a
+//! terminal, and not at all on Windows.
+
+use std::io::IsTerminal;
+
+/// Pager command: `$DEMONGREP_PAGER`, then `$PAGER`, then `less`
+///
+/// An empty value or `cat` means no pager.
+fn pager_command() -> Option<String> {
+    let command = std::env::var("DEMONGREP_PAGER")
+        .or_else(|_| std::env::var("PAGER"))
+        .unwrap_or_else(|_| "less".to_string());
+    let command = command.trim();
+    (!command.is_empty() && command != "cat").then(|| command.to_string())
+}
+
+/// Redirects stdout into a pager until dropped
+pub struct Pager {
+    #[cfg(unix)]
+    child: std::process::Child,
+    #[cfg(unix)]
+    saved_stdout: std::os::fd::OwnedFd,
+}
+
+impl Pager {
+    /// Start the pager, or None when stdout is not a terminal or no pager is configured
+    pub fn start() -> Option<Self> {
+        if !std::io::stdout().is_terminal() {
+            return None;
+        }
+        Self::spawn(&pager_command()?)
+    }
+
+    #[cfg(unix)]
+    fn spawn(command: &str) -> Option<Self> {
+        use std::io::Write;
+        use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+        use std::process::{Command, Stdio};
+
+        let mut pager = Command::new("sh");
+        pager.args(["-c", command]).stdin(Stdio::piped());
+        if std::env::var_os("LESS").is_none() {
+            pager.env("LESS", "FRX");
+        }
+        let mut child = pager.spawn().ok()?;
+        let stdin = child.stdin.take()?;
+
+        let _ = std::io::stdout().flush();
+        // SAFETY: plain fd duplication; the duplicate is owned by `saved_stdout`
+        let saved_stdout = unsafe {
+            let saved = libc::dup(libc::STDOUT_FILENO);
+            if saved < 0 || libc::dup2(stdin.as_raw_fd(), libc::STDOUT_FILENO) < 0 {
+                if saved >= 0 {
+                    libc::close(saved);
+                }
+                let _ = child.kill();
+                return None;
+            }
+            OwnedFd::from_raw_fd(saved)
+        };
+        // stdout now holds the only write end of the pipe
+        drop(stdin);
+
+        // Quitting the pager early should end us quietly, as it does git
+        // SAFETY: restoring the default disposition of a signal
+        unsafe {
+            libc::signal(libc::SIGPIPE, libc::SIG_DFL);
+        }
+
+        Some(Self { child, saved_stdout })
+    }
+
+    #[cfg(not(unix))]
+    fn spawn(_command: &str) -> Option<Self> {
+        None
+    }
+}
+
+#[cfg(unix)]
+impl Drop for Pager {
+    fn drop(&mut self) {
+        use std::io::Write;
+        use std::os::fd::AsRawFd;
+
+        let _ = std::io::stdout().flush();
+        // Point stdout back at the terminal, which closes the pipe so the pager
+        // sees the end of the output; then wait until the user quits it
+        // SAFETY: both fds are open for the duration of the call
+        unsafe {
+            libc::dup2(self.saved_stdout.as_raw_fd(), libc::STDOUT_FILENO);
+        }
+        let _ = self.child.wait();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pager_command() {
+        std::env::remove_var("DEMONGREP_PAGER");
+        std::env::set_var("PAGER", "more");
+        assert_eq!(pager_command().as_deref(), Some("more"));
+
+        std::env::set_var("DEMONGREP_PAGER", "less -S");
+        assert_eq!(pager_command().as_deref(), Some("less -S"));
+
+        std::env::set_var("DEMONGREP_PAGER", "cat");
+        assert_eq!(pager_command(), None);
+
+        std::env::set_var("DEMONGREP_PAGER", "");
+        assert_eq!(pager_command(), None);
+        std::env::remove_var("DEMONGREP_PAGER");
+        std::env::remove_var("PAGER");
+    }
+}
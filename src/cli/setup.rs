@@ -1,12 +1,197 @@
-use anyhow::Result;
+//! Model download and cache management (`demongrep setup`)
+//!
+//! Downloads the embedding model and the neural reranker ahead of time, so
+//! indexing and `--rerank` work offline afterwards, then verifies the files
+//! on disk. Models come from the Hugging Face hub, whose cache names each
+//! large (LFS) file after the SHA-256 of its content; those are re-hashed to
+//! catch truncated or corrupted downloads. Small files are named after git
+//! blob IDs and are only checked for being complete.
 
-pub async fn run(model: Option<String>) -> Result<()> {
-    let model_name = model.unwrap_or_else(|| "mxbai-embed-xsmall-v1".to_string());
+use anyhow::{anyhow, Result};
+use colored::Colorize;
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
 
-    println!("📦 Downloading embedding model: {}", model_name);
+use crate::config::Config;
+use crate::embed::{model_code, model_repo_dir, EmbeddingService, ModelType};
+use crate::index::dir_size;
+use crate::rerank::{reranker_model_code, NeuralReranker, DEFAULT_RERANKER};
 
-    // TODO: Download model from HuggingFace Hub
+/// Result of checking one model's cached files
+#[derive(Debug, Default)]
+struct BlobCheck {
+    /// Files whose SHA-256 matched their name
+    verified: usize,
+    /// Files that can't be checked by name (git blob IDs)
+    unchecked: usize,
+    /// Files that failed the check or were never finished
+    corrupt: Vec<PathBuf>,
+}
+
+fn is_sha256_name(name: &str) -> bool {
+    name.len() == 64 && name.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+fn sha256_file(path: &Path) -> Result<String> {
+    let mut file = std::fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Check every blob in a cached repo against its content hash
+fn verify_blobs(repo_dir: &Path) -> Result<BlobCheck> {
+    let mut check = BlobCheck::default();
+    let blobs = repo_dir.join("blobs");
+    if !blobs.is_dir() {
+        return Ok(check);
+    }
+
+    for entry in std::fs::read_dir(&blobs)? {
+        let path = entry?.path();
+        let name = path.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+        if name.ends_with(".incomplete") || name.ends_with(".lock") {
+            check.corrupt.push(path);
+        } else if is_sha256_name(name) {
+            if sha256_file(&path)? == name.to_ascii_lowercase() {
+                check.verified += 1;
+            } else {
+                check.corrupt.push(path);
+            }
+        } else {
+            check.unchecked += 1;
+        }
+    }
+    Ok(check)
+}
+
+/// Verify a downloaded repo and print the outcome
+fn report_verification(label: &str, repo_dir: &Path) -> Result<()> {
+    let check = verify_blobs(repo_dir)?;
+    if check.corrupt.is_empty() {
+        println!(
+            "   ✅ {} verified ({} checksummed, {} small files, {:.1} MB)",
+            label,
+            check.verified,
+            check.unchecked,
+            dir_size(repo_dir) as f64 / (1024.0 * 1024.0)
+        );
+        return Ok(());
+    }
+
+    println!("   ❌ {} has {} corrupt or incomplete files:", label, check.corrupt.len());
+    for path in &check.corrupt {
+        println!("      {}", path.display());
+    }
+    Err(anyhow!(
+        "{} failed verification; delete {} and run `demongrep setup` again",
+        label,
+        repo_dir.display()
+    ))
+}
+
+/// Download the embedding model and reranker, or list the model cache
+pub async fn run(model: Option<String>, no_reranker: bool, list: bool, config: &Config) -> Result<()> {
+    if list {
+        list_models(config);
+        return Ok(());
+    }
+
+    let model_type = match model {
+        Some(name) => ModelType::from_str(&name).ok_or_else(|| anyhow!("Unknown model '{}'", name))?,
+        None => config.index_model_type().unwrap_or_default(),
+    };
+
+    println!("{}", "📦 Demongrep Setup".bright_cyan().bold());
+    println!("{}", "=".repeat(60));
 
-    println!("✅ Setup complete!");
+    println!("📥 Embedding model: {}", model_type.name());
+    EmbeddingService::with_model(model_type)?;
+    if let Some(code) = model_code(model_type) {
+        report_verification(model_type.short_name(), &model_repo_dir(&code))?;
+    }
+
+    if !no_reranker {
+        println!("📥 Reranker: {}", DEFAULT_RERANKER);
+        NeuralReranker::new()?;
+        if let Some(code) = reranker_model_code(&DEFAULT_RERANKER) {
+            report_verification("reranker", &model_repo_dir(&code))?;
+        }
+    }
+
+    println!("\n{}", "✅ Setup complete! Models are cached for offline use.".green());
     Ok(())
 }
+
+/// One row of `setup --list`
+fn list_row(name: &str, kind: &str, dims: &str, code: Option<String>, current: bool) {
+    let repo_dir = code.map(|code| model_repo_dir(&code)).filter(|dir| dir.is_dir());
+    let size = repo_dir
+        .as_ref()
+        .map(|dir| format!("{:.1}", dir_size(dir) as f64 / (1024.0 * 1024.0)))
+        .unwrap_or_else(|| "-".to_string());
+    let status = if repo_dir.is_some() {
+        format!("{:<15}", "downloaded").green()
+    } else {
+        format!("{:<15}", "not downloaded").dimmed()
+    };
+    let marker = if current { "*" } else { " " };
+    println!("{} {:<18} {:<10} {:>5} {:>9} {}", marker, name, kind, dims, size, status);
+}
+
+fn list_models(config: &Config) {
+    let current = config.index_model_type().unwrap_or_default();
+
+    println!("{}", "📦 Models".bright_cyan().bold());
+    println!("{}", "=".repeat(60));
+    println!("  {:<18} {:<10} {:>5} {:>9} Status", "Name", "Kind", "Dims", "Size MB");
+    for &model_type in ModelType::all() {
+        list_row(
+            model_type.short_name(),
+            "embedding",
+            &model_type.dimensions().to_string(),
+            model_code(model_type),
+            model_type == current,
+        );
+    }
+    list_row(
+        "jina-reranker",
+        "reranker",
+        "-",
+        reranker_model_code(&DEFAULT_RERANKER),
+        config.search.rerank,
+    );
+    println!();
+    println!("* configured for this project. Cache: {}", crate::embed::model_cache_dir().display());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_verify_blobs() {
+        let repo = tempdir().unwrap();
+        let blobs = repo.path().join("blobs");
+        std::fs::create_dir_all(&blobs).unwrap();
+
+        let weights = b"model weights";
+        let good = format!("{:x}", Sha256::digest(weights));
+        std::fs::write(blobs.join(&good), weights).unwrap();
+        // Named for other content: corrupt
+        std::fs::write(blobs.join("a".repeat(64)), weights).unwrap();
+        // Git blob ID of a small config file: can't be checked by name
+        std::fs::write(blobs.join("0123456789abcdef0123456789abcdef01234567"), "{}").unwrap();
+        std::fs::write(blobs.join(format!("{}.incomplete", "b".repeat(64))), "partial").unwrap();
+
+        let check = verify_blobs(repo.path()).unwrap();
+        assert_eq!(check.verified, 1);
+        assert_eq!(check.unchecked, 1);
+        assert_eq!(check.corrupt.len(), 2);
+
+        // A repo that was never downloaded has nothing to check
+        let empty = verify_blobs(&repo.path().join("missing")).unwrap();
+        assert_eq!((empty.verified, empty.corrupt.len()), (0, 0));
+    }
+}
@@ -1,9 +1,32 @@
-use anyhow::Result;
+//! Configuration loading
+//!
+//! Settings are layered: built-in defaults, then the user config at
+//! `~/.demongrep/config.toml`, then the project's `.demongrep.toml`, then
+//! `.demongrep.<store>.toml` when a named store is selected with `--store`.
+//! CLI flags are applied on top of the loaded config by the caller.
+
+use anyhow::{anyhow, Result};
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::embed::ModelType;
+
+/// File name of the user-wide config inside the data directory
+pub const USER_CONFIG_FILE: &str = "config.toml";
+
+/// File name of the per-project config in the project root
+pub const PROJECT_CONFIG_FILE: &str = ".demongrep.toml";
+
+/// Settings honored only in the user config, as `(section, key)`
+///
+/// A project's config files come with the repository, so a setting that
+/// runs commands must not be taken from a repository that was just cloned.
+const USER_ONLY_KEYS: &[(&str, &str)] = &[("search", "transform_command")];
 
 /// Global configuration for demongrep
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Config {
     /// Root directory for demongrep data
     pub data_dir: PathBuf,
@@ -16,11 +39,27 @@ pub struct Config {
 
     /// Indexing configuration
     pub indexing: IndexingConfig,
+
+    /// Full-text index analyzers (fixed per index when it is created)
+    pub fts: FtsConfig,
+
+    /// Search configuration and `demongrep search` defaults
+    pub search: SearchConfig,
+
+    /// `demongrep index` defaults
+    pub index: IndexDefaults,
+
+    /// `demongrep serve` defaults
+    pub serve: ServeDefaults,
+
+    /// MCP server configuration
+    pub mcp: McpConfig,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct EmbeddingConfig {
-    /// Model name (e.g., "mxbai-embed-xsmall-v1")
+    /// Model short name (e.g., "minilm-l6-q", "jina-code")
     pub model: String,
 
     /// Device to use (cpu, cuda, directml)
@@ -31,9 +70,13 @@ pub struct EmbeddingConfig {
 
     /// Cache size in MB
     pub cache_size_mb: usize,
+
+    /// Keep query embeddings on disk so repeated queries skip the model
+    pub query_cache: bool,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum Device {
     Cpu,
     Cuda,
@@ -41,6 +84,7 @@ pub enum Device {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct VectorDbConfig {
     /// Vector database backend
     pub backend: VectorDbType,
@@ -57,6 +101,7 @@ pub enum VectorDbType {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct IndexingConfig {
     /// Maximum chunk size in lines
     pub max_chunk_lines: usize,
@@ -69,13 +114,396 @@ pub struct IndexingConfig {
 
     /// Number of parallel workers
     pub workers: usize,
+
+    /// Extra ignore patterns (gitignore syntax) applied on top of ignore files
+    pub excludes: Vec<String>,
+
+    /// What to do with code between definitions (imports, stray statements)
+    pub gap_strategy: GapStrategy,
+
+    /// Where context_prev/context_next come from
+    pub context_source: ContextSource,
+
+    /// Tags for chunks by path: tag name to gitignore-style patterns (`search --tag`)
+    pub tags: BTreeMap<String, Vec<String>>,
+
+    /// Also embed a summary of each file (path, symbols, docstrings) for `search --coarse-to-fine`
+    pub file_summaries: bool,
+
+    /// Trees in the approximate vector index (default: chosen by index size)
+    pub trees: Option<usize>,
+
+    /// Vector index shards for new indexes, each built and searched on its own
+    pub shards: usize,
+}
+
+/// How the full-text index splits and normalizes text
+///
+/// The choice is recorded with the index, so changing it only affects
+/// indexes built afterwards.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FtsConfig {
+    /// Reduce English words to their stem ("parsing" matches "parse")
+    pub stemming: bool,
+
+    /// Match regardless of case
+    pub lowercase: bool,
+
+    /// Fold accented characters to ASCII ("café" matches "cafe")
+    pub ascii_folding: bool,
+
+    /// Index signatures as trigrams, so part of an identifier matches
+    pub identifier_ngrams: bool,
+}
+
+/// Source of the context shown before/after each chunk
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ContextSource {
+    /// The raw lines immediately before/after the chunk
+    Lines,
+    /// Signature and first lines of the neighbouring chunk at the same nesting level
+    Siblings,
+}
+
+/// Link target for result paths in terminal output
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum UriScheme {
+    /// file:///path/to/file
+    #[default]
+    File,
+    /// vscode://file/path/to/file:line
+    Vscode,
+    /// No links
+    None,
+}
+
+/// How hybrid search combines vector and full-text results
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum FusionMethod {
+    /// Reciprocal Rank Fusion over the two rankings
+    #[default]
+    Rrf,
+    /// Blend of min-max normalized scores, weighted by alpha
+    Weighted,
+}
+
+/// Handling of gap chunks (code outside any definition)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GapStrategy {
+    /// Index each gap as its own chunk
+    Keep,
+    /// Attach each gap to the following definition (or the preceding one at end of file)
+    Merge,
+    /// Skip gaps entirely
+    Drop,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SearchConfig {
+    /// Fusion of vector and full-text results in hybrid search
+    pub fusion: FusionMethod,
+
+    /// RRF k parameter for score fusion
+    pub rrf_k: f32,
+
+    /// Weight of the vector score in weighted fusion (0-1, BM25 gets the rest)
+    pub alpha: f32,
+
+    /// Enable neural reranking by default
+    pub rerank: bool,
+
+    /// Number of top results to rerank
+    pub rerank_top: usize,
+
+    /// Seconds without a query before `serve` and `mcp` unload the reranker (0 = keep it loaded)
+    pub reranker_idle_secs: u64,
+
+    /// Maximum total results to return
+    pub max_results: usize,
+
+    /// Maximum matches to show per file
+    pub per_file: usize,
+
+    /// Show full chunk content by default
+    pub content: bool,
+
+    /// Show relevance scores by default
+    pub scores: bool,
+
+    /// Show file paths only by default
+    pub compact: bool,
+
+    /// Re-index changed files before every search
+    pub sync: bool,
+
+    /// Re-index changed files before a search only when the index is stale
+    pub auto_sync: bool,
+
+    /// Use vector-only search by default
+    pub vector_only: bool,
+
+    /// Let the query classifier pick lexical, hybrid or vector search
+    pub auto: bool,
+
+    /// Candidates fetched by each retriever in hybrid/lexical mode (None = adaptive)
+    pub retrieval_depth: Option<usize>,
+
+    /// Score multiplier for Imports chunks (1.0 = no down-ranking)
+    pub imports_weight: f32,
+
+    /// Score multiplier for ModuleDocs chunks on module-overview queries
+    pub module_docs_boost: f32,
+
+    /// Down-rank dependency code and declaration-only chunks by default
+    pub prefer_project: bool,
+
+    /// Boost results from recently modified files by default
+    pub boost_recent: bool,
+
+    /// Extra weight for a file modified just now with boost_recent (0.25 = ×1.25)
+    pub recency_boost: f32,
+
+    /// Age in days at which boost_recent gives half its extra weight
+    pub recency_half_life_days: f32,
+
+    /// Fold near-duplicate results from other files into the best-ranked copy
+    pub collapse_duplicates: bool,
+
+    /// Link target for result paths in the terminal ("file", "vscode" or "none")
+    pub uri_scheme: UriScheme,
+
+    /// Shell command that rewrites the result set (JSON on stdin and stdout)
+    pub transform_command: Option<String>,
+
+    /// Score multipliers by path pattern (gitignore syntax), e.g. "**/tests/**" = 0.5
+    pub path_weights: BTreeMap<String, f32>,
+
+    /// Rank files by their summaries first, then search chunks in the best files only
+    pub coarse_to_fine: bool,
+
+    /// Files whose chunks a coarse-to-fine search ranks
+    pub top_files: usize,
+
+    /// Also embed the lines of the top results to find their best-matching lines
+    pub embed_match_lines: bool,
+
+    /// Start a search daemon on the first search and send later searches to it
+    pub auto_daemon: bool,
+
+    /// Indexes with at most this many chunks are searched exactly instead of approximately (0 = never)
+    pub exact_threshold: usize,
+
+    /// Candidates an approximate search explores, per result and tree
+    pub search_quality: usize,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IndexDefaults {
+    /// Model for `demongrep index` (overrides embedding.model)
+    pub model: Option<String>,
+
+    /// Index into the global database by default
+    pub global: bool,
+
+    /// Run the verification pass after indexing
+    pub verify: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ServeDefaults {
+    /// Port for `demongrep serve`
+    pub port: u16,
+
+    /// Interface to bind (use 0.0.0.0 for team use, together with an API key)
+    pub host: String,
+
+    /// Bearer token required on every request (overridden by DEMONGREP_API_KEY)
+    pub api_key: Option<String>,
+
+    /// PEM certificate chain for HTTPS (requires tls_key)
+    pub tls_cert: Option<PathBuf>,
+
+    /// PEM private key for HTTPS (requires tls_cert)
+    pub tls_key: Option<PathBuf>,
+
+    /// Unix domain socket to serve on instead of host and port
+    pub socket: Option<PathBuf>,
+
+    /// Requests per minute allowed from one client IP (0 = no limit)
+    pub rate_limit_per_minute: u32,
+
+    /// Requests a client may send in a burst before the per-minute rate applies
+    pub rate_limit_burst: u32,
+
+    /// Longest accepted search query, in characters
+    pub max_query_chars: usize,
+
+    /// Upper bound on the `limit` a search request may ask for
+    pub max_limit: usize,
+}
+
+impl ServeDefaults {
+    /// API key from DEMONGREP_API_KEY, falling back to the config value
+    pub fn resolved_api_key(&self) -> Option<String> {
+        std::env::var("DEMONGREP_API_KEY")
+            .ok()
+            .or_else(|| self.api_key.clone())
+            .filter(|key| !key.is_empty())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct McpConfig {
+    /// What to do when the index is older than the working tree
+    pub refresh: RefreshPolicy,
+
+    /// Auto-sync only when at most this many files changed
+    pub auto_sync_max_files: usize,
+
+    /// Minimum seconds between staleness checks (0 = check on every request)
+    pub check_interval_secs: u64,
+}
+
+/// How the MCP server handles a stale index
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RefreshPolicy {
+    /// Never check for staleness
+    Off,
+    /// Check and add a warning to tool output
+    Warn,
+    /// Re-index small change sets before answering, warn about large ones
+    Auto,
 }
 
 impl Config {
-    /// Load configuration from default location or create default
+    /// Load configuration for the current directory
     pub fn load() -> Result<Self> {
-        // TODO: Load from ~/.demongrep/config.toml
-        Ok(Self::default())
+        Self::load_for(&std::env::current_dir()?)
+    }
+
+    /// Load configuration for a project, merging user and project config files
+    ///
+    /// Precedence (highest first): store `.demongrep.<store>.toml`, project
+    /// `.demongrep.toml`, user `~/.demongrep/config.toml`, built-in defaults.
+    pub fn load_for(project_root: &Path) -> Result<Self> {
+        Self::load_layers(Self::user_config_path().as_deref(), project_root)
+    }
+
+    /// Merge the given user config file and the project's config files over the defaults
+    ///
+    /// Split from `load_for` so tests don't depend on the real `~/.demongrep/config.toml`.
+    fn load_layers(user_config: Option<&Path>, project_root: &Path) -> Result<Self> {
+        let mut merged = toml::Value::try_from(Self::default())?;
+
+        let project_layers = [
+            Some(project_root.join(PROJECT_CONFIG_FILE)),
+            crate::index::store_name().map(|name| project_root.join(format!(".demongrep.{}.toml", name))),
+        ];
+        let layers = std::iter::once((user_config.map(Path::to_path_buf), true))
+            .chain(project_layers.into_iter().map(|path| (path, false)));
+
+        for (path, is_user) in layers {
+            let Some(path) = path.filter(|path| path.exists()) else {
+                continue;
+            };
+            let content = std::fs::read_to_string(&path)?;
+            let layer: toml::Value = toml::from_str(&content)
+                .map_err(|e| anyhow!("Failed to parse {}: {}", path.display(), e))?;
+            if !is_user {
+                reject_user_only_keys(&layer, &path)?;
+            }
+            merge_values(&mut merged, layer);
+        }
+
+        let config: Config = merged
+            .try_into()
+            .map_err(|e| anyhow!("Invalid configuration: {}", e))?;
+        config.validate()?;
+
+        Ok(config)
+    }
+
+    /// Path of the user-wide config file (`~/.demongrep/config.toml`)
+    pub fn user_config_path() -> Option<PathBuf> {
+        dirs::home_dir().map(|home| home.join(".demongrep").join(USER_CONFIG_FILE))
+    }
+
+    /// Resolve the configured embedding model
+    ///
+    /// Returns None if the configured name is not a known model.
+    pub fn model_type(&self) -> Option<ModelType> {
+        ModelType::from_str(&self.embedding.model)
+    }
+
+    /// Model name `demongrep index` should use (index.model, then embedding.model)
+    pub fn index_model_name(&self) -> &str {
+        self.index.model.as_deref().unwrap_or(&self.embedding.model)
+    }
+
+    /// Resolve the model `demongrep index` should use
+    ///
+    /// Returns None if the configured name is not a known model.
+    pub fn index_model_type(&self) -> Option<ModelType> {
+        ModelType::from_str(self.index_model_name())
+    }
+
+    /// Check values that would otherwise cause panics or nonsense chunking
+    pub fn validate(&self) -> Result<()> {
+        if self.indexing.max_chunk_lines == 0 {
+            return Err(anyhow!("indexing.max_chunk_lines must be greater than 0"));
+        }
+        if self.indexing.overlap_lines >= self.indexing.max_chunk_lines {
+            return Err(anyhow!(
+                "indexing.overlap_lines ({}) must be smaller than indexing.max_chunk_lines ({})",
+                self.indexing.overlap_lines,
+                self.indexing.max_chunk_lines
+            ));
+        }
+        if self.indexing.trees == Some(0) {
+            return Err(anyhow!("indexing.trees must be greater than 0"));
+        }
+        if self.search.search_quality == 0 {
+            return Err(anyhow!("search.search_quality must be greater than 0"));
+        }
+        if !(1..=crate::vectordb::MAX_SHARDS).contains(&self.indexing.shards) {
+            return Err(anyhow!("indexing.shards must be between 1 and {}", crate::vectordb::MAX_SHARDS));
+        }
+        if self.search.rrf_k < 0.0 {
+            return Err(anyhow!("search.rrf_k must not be negative"));
+        }
+        if !(0.0..=1.0).contains(&self.search.alpha) {
+            return Err(anyhow!("search.alpha must be between 0 and 1"));
+        }
+        if self.search.retrieval_depth == Some(0) {
+            return Err(anyhow!("search.retrieval_depth must be greater than 0"));
+        }
+        if self.search.top_files == 0 {
+            return Err(anyhow!("search.top_files must be greater than 0"));
+        }
+        if let Some((pattern, weight)) = self.search.path_weights.iter().find(|(_, &weight)| weight <= 0.0) {
+            return Err(anyhow!(
+                "search.path_weights: weight {} for \"{}\" must be positive (use indexing.excludes to leave files out)",
+                weight,
+                pattern
+            ));
+        }
+        if let Some(tag) = self.indexing.tags.keys().find(|tag| !is_tag_name(tag)) {
+            return Err(anyhow!(
+                "indexing.tags: \"{}\" is not a valid tag (use letters, digits, '-', '_' and '.')",
+                tag
+            ));
+        }
+        Ok(())
     }
 
     /// Get the data directory, creating it if necessary
@@ -87,28 +515,361 @@ impl Config {
     }
 }
 
+/// Whether `tag` can name a tag: non-empty, without spaces, commas or other punctuation
+pub fn is_tag_name(tag: &str) -> bool {
+    !tag.is_empty() && tag.chars().all(|c| c.is_alphanumeric() || matches!(c, '-' | '_' | '.'))
+}
+
+/// Fail if a project config file sets one of `USER_ONLY_KEYS`
+fn reject_user_only_keys(layer: &toml::Value, path: &Path) -> Result<()> {
+    for (section, key) in USER_ONLY_KEYS {
+        if layer.get(section).and_then(|table| table.get(key)).is_some() {
+            return Err(anyhow!(
+                "{}: {}.{} can only be set in the user config ({})",
+                path.display(),
+                section,
+                key,
+                Config::user_config_path()
+                    .map(|path| path.display().to_string())
+                    .unwrap_or_else(|| format!("~/.demongrep/{}", USER_CONFIG_FILE)),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Recursively merge `overlay` into `base`, with overlay values winning
+fn merge_values(base: &mut toml::Value, overlay: toml::Value) {
+    match (base, overlay) {
+        (toml::Value::Table(base), toml::Value::Table(overlay)) => {
+            for (key, value) in overlay {
+                match base.get_mut(&key) {
+                    Some(existing) => merge_values(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
 impl Default for Config {
     fn default() -> Self {
         let home = dirs::home_dir().expect("Could not find home directory");
 
         Self {
             data_dir: home.join(".demongrep"),
-            embedding: EmbeddingConfig {
-                model: "mxbai-embed-xsmall-v1".to_string(),
-                device: Device::Cpu,
-                batch_size: 32,
-                cache_size_mb: 512,
-            },
-            vectordb: VectorDbConfig {
-                backend: VectorDbType::LanceDb,
-                connection: "data/vectordb".to_string(),
-            },
-            indexing: IndexingConfig {
-                max_chunk_lines: 75,
-                max_chunk_chars: 2000,
-                overlap_lines: 10,
-                workers: num_cpus::get(),
-            },
+            embedding: EmbeddingConfig::default(),
+            vectordb: VectorDbConfig::default(),
+            indexing: IndexingConfig::default(),
+            fts: FtsConfig::default(),
+            search: SearchConfig::default(),
+            index: IndexDefaults::default(),
+            serve: ServeDefaults::default(),
+            mcp: McpConfig::default(),
+        }
+    }
+}
+
+impl Default for EmbeddingConfig {
+    fn default() -> Self {
+        Self {
+            model: ModelType::default().short_name().to_string(),
+            device: Device::Cpu,
+            batch_size: 32,
+            cache_size_mb: 512,
+            query_cache: true,
+        }
+    }
+}
+
+impl Default for VectorDbConfig {
+    fn default() -> Self {
+        Self {
+            backend: VectorDbType::LanceDb,
+            connection: "data/vectordb".to_string(),
         }
     }
 }
+
+impl Default for IndexingConfig {
+    fn default() -> Self {
+        Self {
+            max_chunk_lines: 100,
+            max_chunk_chars: 2000,
+            overlap_lines: 10,
+            workers: num_cpus::get(),
+            excludes: Vec::new(),
+            gap_strategy: GapStrategy::Keep,
+            context_source: ContextSource::Lines,
+            tags: BTreeMap::new(),
+            file_summaries: true,
+            trees: None,
+            shards: 1,
+        }
+    }
+}
+
+impl Default for FtsConfig {
+    fn default() -> Self {
+        Self {
+            stemming: false,
+            lowercase: true,
+            ascii_folding: false,
+            identifier_ngrams: false,
+        }
+    }
+}
+
+impl SearchConfig {
+    /// The configured fusion with its parameter
+    pub fn hybrid_fusion(&self) -> crate::rerank::Fusion {
+        match self.fusion {
+            FusionMethod::Rrf => crate::rerank::Fusion::Rrf { k: self.rrf_k },
+            FusionMethod::Weighted => crate::rerank::Fusion::Weighted { alpha: self.alpha },
+        }
+    }
+}
+
+impl Default for SearchConfig {
+    fn default() -> Self {
+        Self {
+            fusion: FusionMethod::Rrf,
+            rrf_k: crate::rerank::DEFAULT_RRF_K,
+            alpha: crate::rerank::DEFAULT_FUSION_ALPHA,
+            rerank: false,
+            rerank_top: 50,
+            reranker_idle_secs: 600,
+            max_results: 25,
+            per_file: 1,
+            content: false,
+            scores: false,
+            compact: false,
+            sync: false,
+            auto_sync: false,
+            vector_only: false,
+            auto: false,
+            retrieval_depth: None,
+            imports_weight: crate::rerank::DEFAULT_IMPORTS_WEIGHT,
+            module_docs_boost: crate::rerank::DEFAULT_MODULE_DOCS_BOOST,
+            prefer_project: false,
+            boost_recent: false,
+            recency_boost: crate::rerank::DEFAULT_RECENCY_BOOST,
+            recency_half_life_days: crate::rerank::DEFAULT_RECENCY_HALF_LIFE_DAYS,
+            collapse_duplicates: true,
+            uri_scheme: UriScheme::File,
+            transform_command: None,
+            path_weights: BTreeMap::new(),
+            coarse_to_fine: false,
+            top_files: 10,
+            embed_match_lines: false,
+            auto_daemon: false,
+            exact_threshold: crate::vectordb::DEFAULT_EXACT_THRESHOLD,
+            search_quality: crate::vectordb::DEFAULT_SEARCH_QUALITY,
+        }
+    }
+}
+
+impl Default for ServeDefaults {
+    fn default() -> Self {
+        Self {
+            port: 4444,
+            host: "127.0.0.1".to_string(),
+            api_key: None,
+            tls_cert: None,
+            tls_key: None,
+            socket: None,
+            rate_limit_per_minute: 120,
+            rate_limit_burst: 20,
+            max_query_chars: 1000,
+            max_limit: 100,
+        }
+    }
+}
+
+impl Default for McpConfig {
+    fn default() -> Self {
+        Self {
+            refresh: RefreshPolicy::Auto,
+            auto_sync_max_files: 20,
+            check_interval_secs: 2,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_defaults_without_files() {
+        let dir = tempdir().unwrap();
+        let config = Config::load_layers(None, dir.path()).unwrap();
+        assert_eq!(config.indexing.max_chunk_lines, 100);
+        assert_eq!(config.model_type(), Some(ModelType::default()));
+    }
+
+    #[test]
+    fn test_project_config_overrides_defaults() {
+        let dir = tempdir().unwrap();
+        std::fs::write(
+            dir.path().join(PROJECT_CONFIG_FILE),
+            "[embedding]\nmodel = \"jina-code\"\ndevice = \"cuda\"\n\n[indexing]\nmax_chunk_lines = 60\nexcludes = [\"*.gen.rs\"]\n\n[search]\nrrf_k = 40.0\n",
+        )
+        .unwrap();
+
+        let config = Config::load_layers(None, dir.path()).unwrap();
+        assert_eq!(config.model_type(), Some(ModelType::JinaEmbeddingsV2BaseCode));
+        assert_eq!(config.embedding.device, Device::Cuda);
+        assert_eq!(config.indexing.max_chunk_lines, 60);
+        // Untouched keys keep their defaults
+        assert_eq!(config.indexing.max_chunk_chars, 2000);
+        assert_eq!(config.indexing.excludes, vec!["*.gen.rs".to_string()]);
+        assert_eq!(config.search.rrf_k, 40.0);
+    }
+
+    #[test]
+    fn test_project_config_overrides_user_config() {
+        let dir = tempdir().unwrap();
+        let user = dir.path().join(USER_CONFIG_FILE);
+        std::fs::write(&user, "[search]\nmax_results = 10\nscores = true\n").unwrap();
+        std::fs::write(dir.path().join(PROJECT_CONFIG_FILE), "[search]\nmax_results = 5\n").unwrap();
+
+        let config = Config::load_layers(Some(&user), dir.path()).unwrap();
+        assert_eq!(config.search.max_results, 5);
+        assert!(config.search.scores);
+    }
+
+    #[test]
+    fn test_user_only_keys() {
+        let dir = tempdir().unwrap();
+        let user = dir.path().join(USER_CONFIG_FILE);
+        std::fs::write(&user, "[search]\ntransform_command = \"cat\"\n").unwrap();
+        let config = Config::load_layers(Some(&user), dir.path()).unwrap();
+        assert_eq!(config.search.transform_command.as_deref(), Some("cat"));
+
+        std::fs::write(dir.path().join(PROJECT_CONFIG_FILE), "[search]\ntransform_command = \"rm -rf ~\"\n").unwrap();
+        let err = Config::load_layers(Some(&user), dir.path()).unwrap_err();
+        assert!(err.to_string().contains("search.transform_command can only be set in the user config"));
+    }
+
+    #[test]
+    fn test_command_defaults() {
+        let dir = tempdir().unwrap();
+        std::fs::write(
+            dir.path().join(PROJECT_CONFIG_FILE),
+            "[search]\nrerank = true\nmax_results = 10\n\n[index]\nmodel = \"jina-code\"\n",
+        )
+        .unwrap();
+
+        let config = Config::load_layers(None, dir.path()).unwrap();
+        assert!(config.search.rerank);
+        assert_eq!(config.search.max_results, 10);
+        assert_eq!(config.search.per_file, 1);
+        assert_eq!(config.index_model_type(), Some(ModelType::JinaEmbeddingsV2BaseCode));
+        // embedding.model is untouched and still used by other commands
+        assert_eq!(config.model_type(), Some(ModelType::default()));
+        assert_eq!(config.serve.port, 4444);
+        assert_eq!(config.serve.host, "127.0.0.1");
+        assert!(config.serve.api_key.is_none());
+    }
+
+    #[test]
+    fn test_gap_strategy() {
+        let dir = tempdir().unwrap();
+        std::fs::write(
+            dir.path().join(PROJECT_CONFIG_FILE),
+            "[indexing]\ngap_strategy = \"merge\"\n",
+        )
+        .unwrap();
+
+        let config = Config::load_layers(None, dir.path()).unwrap();
+        assert_eq!(config.indexing.gap_strategy, GapStrategy::Merge);
+        assert_eq!(Config::default().indexing.gap_strategy, GapStrategy::Keep);
+        assert_eq!(config.indexing.context_source, ContextSource::Lines);
+    }
+
+    #[test]
+    fn test_mcp_refresh_policy() {
+        let dir = tempdir().unwrap();
+        std::fs::write(
+            dir.path().join(PROJECT_CONFIG_FILE),
+            "[mcp]\nrefresh = \"warn\"\n",
+        )
+        .unwrap();
+
+        let config = Config::load_layers(None, dir.path()).unwrap();
+        assert_eq!(config.mcp.refresh, RefreshPolicy::Warn);
+        assert_eq!(config.mcp.auto_sync_max_files, 20);
+    }
+
+    #[test]
+    fn test_path_weights() {
+        let dir = tempdir().unwrap();
+        std::fs::write(
+            dir.path().join(PROJECT_CONFIG_FILE),
+            "[search.path_weights]\n\"src/**\" = 1.2\n\"**/tests/**\" = 0.5\n",
+        )
+        .unwrap();
+        let config = Config::load_layers(None, dir.path()).unwrap();
+        assert_eq!(config.search.path_weights.get("src/**"), Some(&1.2));
+        assert_eq!(config.search.path_weights.get("**/tests/**"), Some(&0.5));
+
+        std::fs::write(dir.path().join(PROJECT_CONFIG_FILE), "[search.path_weights]\n\"gen/**\" = 0\n").unwrap();
+        assert!(Config::load_layers(None, dir.path()).is_err());
+    }
+
+    #[test]
+    fn test_tags() {
+        let dir = tempdir().unwrap();
+        std::fs::write(
+            dir.path().join(PROJECT_CONFIG_FILE),
+            "[indexing.tags]\ngenerated = [\"**/*.pb.go\", \"gen/**\"]\napi = [\"src/api/**\"]\n",
+        )
+        .unwrap();
+        let config = Config::load_layers(None, dir.path()).unwrap();
+        assert_eq!(config.indexing.tags["generated"], ["**/*.pb.go", "gen/**"]);
+        assert_eq!(config.indexing.tags["api"], ["src/api/**"]);
+
+        std::fs::write(dir.path().join(PROJECT_CONFIG_FILE), "[indexing.tags]\n\"public api\" = [\"api/**\"]\n").unwrap();
+        assert!(Config::load_layers(None, dir.path()).is_err());
+    }
+
+    #[test]
+    fn test_fusion() {
+        let dir = tempdir().unwrap();
+        std::fs::write(dir.path().join(PROJECT_CONFIG_FILE), "[search]\nfusion = \"weighted\"\nalpha = 0.7\n").unwrap();
+        let config = Config::load_layers(None, dir.path()).unwrap();
+        assert_eq!(config.search.hybrid_fusion(), crate::rerank::Fusion::Weighted { alpha: 0.7 });
+        assert_eq!(Config::default().search.hybrid_fusion(), crate::rerank::Fusion::Rrf { k: 20.0 });
+
+        std::fs::write(dir.path().join(PROJECT_CONFIG_FILE), "[search]\nalpha = 1.5\n").unwrap();
+        assert!(Config::load_layers(None, dir.path()).is_err());
+    }
+
+    #[test]
+    fn test_invalid_overlap_rejected() {
+        let dir = tempdir().unwrap();
+        std::fs::write(
+            dir.path().join(PROJECT_CONFIG_FILE),
+            "[indexing]\nmax_chunk_lines = 10\noverlap_lines = 10\n",
+        )
+        .unwrap();
+
+        assert!(Config::load_layers(None, dir.path()).is_err());
+    }
+
+    #[test]
+    fn test_merge_values() {
+        let mut base: toml::Value = toml::from_str("[a]\nx = 1\ny = 2\n").unwrap();
+        let overlay: toml::Value = toml::from_str("[a]\ny = 3\n[b]\nz = 4\n").unwrap();
+        merge_values(&mut base, overlay);
+
+        assert_eq!(base["a"]["x"].as_integer(), Some(1));
+        assert_eq!(base["a"]["y"].as_integer(), Some(3));
+        assert_eq!(base["b"]["z"].as_integer(), Some(4));
+    }
+}
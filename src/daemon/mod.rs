@@ -0,0 +1,427 @@
+//! Background index updates (`demongrep daemon start|stop|status`)
+//!
+//! `start` launches a detached copy of demongrep running `daemon run`, which
+//! watches the project and re-indexes changed files as `serve` does, without
+//! the HTTP server. Each project (and named store) gets a pidfile and a log
+//! in `<data_dir>/daemons`; `stop` and `status` find the daemon through the
+//! pidfile.
+//!
+//! With `search.auto_daemon`, the first `search` starts a search daemon
+//! instead: `serve` on a Unix socket next to the pidfile, which watches the
+//! project like `daemon run` and also keeps the model loaded for searches.
+//! `stop` and `status` treat it like any other daemon.
+
+use anyhow::{anyhow, Result};
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::time::{Duration, Instant};
+
+use crate::chunker::SemanticChunker;
+use crate::config::Config;
+use crate::database::DatabaseManager;
+use crate::embed::EmbeddingService;
+use crate::index::{
+    apply_changes, get_search_db_paths, is_process_running, pending_changes, record_fingerprint, store_dir_name,
+    store_name, WriteLock,
+};
+use crate::server::ServerAddress;
+use crate::vectordb::StoreOptions;
+use crate::watch::FileWatcher;
+
+/// How long `stop` waits for the daemon to exit
+const STOP_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long `search` waits for a search daemon to load the model and answer
+const READY_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Contents of a daemon's pidfile
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DaemonInfo {
+    pub pid: u32,
+    pub project: PathBuf,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub store: Option<String>,
+    /// RFC 3339
+    pub started_at: String,
+    pub log: PathBuf,
+    /// Socket a search daemon (`search.auto_daemon`) answers searches on
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub socket: Option<PathBuf>,
+}
+
+/// Pidfile and log paths for the daemon of `root` in the selected store
+fn daemon_paths(root: &Path, config: &Config) -> Result<(PathBuf, PathBuf)> {
+    let dir = config.data_dir()?.join("daemons");
+    std::fs::create_dir_all(&dir)?;
+    let name = store_dir_name(root);
+    Ok((dir.join(format!("{}.pid", name)), dir.join(format!("{}.log", name))))
+}
+
+fn read_pidfile(path: &Path) -> Option<DaemonInfo> {
+    serde_json::from_str(&std::fs::read_to_string(path).ok()?).ok()
+}
+
+fn project_root(path: Option<PathBuf>) -> Result<PathBuf> {
+    let root = path.unwrap_or_else(|| PathBuf::from("."));
+    root.canonicalize()
+        .map_err(|e| anyhow!("Cannot access {}: {}", root.display(), e))
+}
+
+#[cfg(unix)]
+fn terminate(pid: u32) -> Result<()> {
+    // SAFETY: sends SIGTERM to the pid recorded by `start`
+    if unsafe { libc::kill(pid as libc::pid_t, libc::SIGTERM) } != 0 {
+        return Err(std::io::Error::last_os_error().into());
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn terminate(pid: u32) -> Result<()> {
+    let status = Command::new("taskkill").args(["/PID", &pid.to_string(), "/F"]).status()?;
+    if !status.success() {
+        return Err(anyhow!("taskkill failed for pid {}", pid));
+    }
+    Ok(())
+}
+
+/// Launch `demongrep <args> <root>` detached from this terminal, logging to `log`
+fn spawn_detached(root: &Path, log: &Path, args: &[&str]) -> Result<Child> {
+    let log_file = std::fs::OpenOptions::new().create(true).append(true).open(log)?;
+
+    let mut command = Command::new(std::env::current_exe()?);
+    command.arg("--no-color");
+    if let Some(store) = store_name() {
+        command.args(["--store", store]);
+    }
+    if crate::embed::is_offline() {
+        command.arg("--offline");
+    }
+    command
+        .args(args)
+        .arg(root)
+        .stdin(Stdio::null())
+        .stdout(log_file.try_clone()?)
+        .stderr(log_file);
+
+    // Keep Ctrl-C in this terminal from reaching the daemon
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        command.process_group(0);
+    }
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        const DETACHED_PROCESS: u32 = 0x0000_0008;
+        const CREATE_NEW_PROCESS_GROUP: u32 = 0x0000_0200;
+        command.creation_flags(DETACHED_PROCESS | CREATE_NEW_PROCESS_GROUP);
+    }
+
+    Ok(command.spawn()?)
+}
+
+/// Start a background daemon for the project
+pub async fn start(path: Option<PathBuf>, config: &Config) -> Result<()> {
+    let root = project_root(path)?;
+    let (pidfile, log) = daemon_paths(&root, config)?;
+
+    if let Some(info) = read_pidfile(&pidfile).filter(|info| is_process_running(info.pid)) {
+        println!("{}", format!("Daemon already running for {} (pid {})", root.display(), info.pid).yellow());
+        return Ok(());
+    }
+    // Fail here rather than in the background
+    let db_paths = get_search_db_paths(Some(root.clone()))?;
+    if db_paths.is_empty() {
+        return Err(anyhow!("No index for {}; run `demongrep index` first", root.display()));
+    }
+    for db_path in &db_paths {
+        if let Some(holder) = WriteLock::holder(db_path) {
+            return Err(anyhow!(
+                "Database {} is being written by `demongrep {}` (pid {}); stop it first",
+                db_path.display(),
+                holder.command,
+                holder.pid
+            ));
+        }
+    }
+
+    let mut child = spawn_detached(&root, &log, &["daemon", "run"])?;
+    let info = DaemonInfo {
+        pid: child.id(),
+        project: root.clone(),
+        store: store_name().map(|s| s.to_string()),
+        started_at: chrono::Utc::now().to_rfc3339(),
+        log: log.clone(),
+        socket: None,
+    };
+    std::fs::write(&pidfile, serde_json::to_string_pretty(&info)?)?;
+
+    // Catch a daemon that exits straight away (broken index, model missing offline)
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    if let Some(status) = child.try_wait()? {
+        let _ = std::fs::remove_file(&pidfile);
+        return Err(anyhow!("Daemon exited on startup ({}); see {}", status, log.display()));
+    }
+
+    println!("{}", format!("✅ Daemon started for {} (pid {})", root.display(), info.pid).green());
+    println!("   Log: {}", log.display());
+    println!("   Stop it with `demongrep daemon stop`");
+    Ok(())
+}
+
+/// Stop the project's background daemon
+pub async fn stop(path: Option<PathBuf>, config: &Config) -> Result<()> {
+    let root = project_root(path)?;
+    let (pidfile, _) = daemon_paths(&root, config)?;
+
+    let Some(info) = read_pidfile(&pidfile) else {
+        println!("No daemon running for {}", root.display());
+        return Ok(());
+    };
+
+    if is_process_running(info.pid) {
+        terminate(info.pid)?;
+        let deadline = Instant::now() + STOP_TIMEOUT;
+        while is_process_running(info.pid) && Instant::now() < deadline {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        if is_process_running(info.pid) {
+            return Err(anyhow!("Daemon (pid {}) did not exit within {:?}", info.pid, STOP_TIMEOUT));
+        }
+        println!("{}", format!("✅ Stopped daemon for {} (pid {})", root.display(), info.pid).green());
+    } else {
+        println!("Daemon for {} was not running; removed its stale pidfile", root.display());
+    }
+
+    std::fs::remove_file(&pidfile)?;
+    Ok(())
+}
+
+/// Last `n` lines of a file
+fn tail_lines(path: &Path, n: usize) -> Vec<String> {
+    let content = std::fs::read_to_string(path).unwrap_or_default();
+    let lines: Vec<&str> = content.lines().collect();
+    lines[lines.len().saturating_sub(n)..].iter().map(|s| s.to_string()).collect()
+}
+
+/// Report whether a daemon is running for the project
+pub async fn status(path: Option<PathBuf>, json: bool, config: &Config) -> Result<()> {
+    let root = project_root(path)?;
+    let (pidfile, _) = daemon_paths(&root, config)?;
+    let info = read_pidfile(&pidfile);
+    let running = info.as_ref().map(|info| is_process_running(info.pid)).unwrap_or(false);
+
+    if json {
+        let report = serde_json::json!({
+            "project": root,
+            "running": running,
+            "daemon": info,
+        });
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(());
+    }
+
+    match info {
+        Some(info) if running => {
+            println!("{} for {}", "● Daemon running".green(), root.display());
+            println!("   PID:     {}", info.pid);
+            println!("   Started: {}", info.started_at);
+            println!("   Log:     {}", info.log.display());
+            if let Some(ref socket) = info.socket {
+                println!("   Serving: {}", socket.display());
+            }
+            let recent = tail_lines(&info.log, 5);
+            if !recent.is_empty() {
+                println!();
+                for line in recent {
+                    println!("   {}", line.dimmed());
+                }
+            }
+        }
+        Some(info) => println!(
+            "{} for {} (stale pidfile for pid {}; `demongrep daemon stop` removes it)",
+            "○ No daemon running".dimmed(),
+            root.display(),
+            info.pid
+        ),
+        None => println!("{} for {}", "○ No daemon running".dimmed(), root.display()),
+    }
+    Ok(())
+}
+
+/// URL of a server announced in one of `db_paths`
+fn announced_server(db_paths: &[PathBuf]) -> Option<String> {
+    db_paths.iter().find_map(|db_path| ServerAddress::find(db_path)).map(|address| address.url)
+}
+
+/// Wait until a starting search daemon announces its URL in one of `db_paths`
+async fn wait_until_ready(db_paths: &[PathBuf], mut exited: impl FnMut() -> bool, log: &Path) -> Result<String> {
+    let deadline = Instant::now() + READY_TIMEOUT;
+    loop {
+        if let Some(url) = announced_server(db_paths) {
+            return Ok(url);
+        }
+        if exited() {
+            return Err(anyhow!("Search daemon exited on startup; see {}", log.display()));
+        }
+        if Instant::now() >= deadline {
+            return Err(anyhow!("Search daemon did not answer within {:?}; see {}", READY_TIMEOUT, log.display()));
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+}
+
+/// URL of a server answering searches for the project, started as a daemon if none runs (`search.auto_daemon`)
+///
+/// None when a search can't be routed: the project has no index, another
+/// writer such as a `daemon start` daemon holds it, or the platform has no
+/// Unix sockets.
+pub async fn ensure_search_daemon(path: Option<PathBuf>, config: &Config) -> Result<Option<String>> {
+    if cfg!(not(unix)) {
+        return Ok(None);
+    }
+    let root = project_root(path)?;
+    let db_paths = get_search_db_paths(Some(root.clone()))?;
+    if db_paths.is_empty() {
+        return Ok(None);
+    }
+    if let Some(url) = announced_server(&db_paths) {
+        return Ok(Some(url));
+    }
+
+    // Started by another search and still loading the model
+    let (pidfile, log) = daemon_paths(&root, config)?;
+    if let Some(info) = read_pidfile(&pidfile).filter(|info| is_process_running(info.pid)) {
+        if info.socket.is_none() {
+            return Ok(None);
+        }
+        return wait_until_ready(&db_paths, || !is_process_running(info.pid), &info.log).await.map(Some);
+    }
+    if db_paths.iter().any(|db_path| WriteLock::holder(db_path).is_some()) {
+        return Ok(None);
+    }
+
+    let socket = pidfile.with_extension("sock");
+    let mut child = spawn_detached(&root, &log, &["serve", "--socket", &socket.to_string_lossy()])?;
+    let info = DaemonInfo {
+        pid: child.id(),
+        project: root.clone(),
+        store: store_name().map(|s| s.to_string()),
+        started_at: chrono::Utc::now().to_rfc3339(),
+        log: log.clone(),
+        socket: Some(socket),
+    };
+    std::fs::write(&pidfile, serde_json::to_string_pretty(&info)?)?;
+    crate::warn_print!(
+        "{}",
+        format!(
+            "⏳ Starting a search daemon for {} (pid {}); later searches skip loading the model",
+            root.display(),
+            info.pid
+        )
+        .dimmed()
+    );
+
+    let ready = wait_until_ready(&db_paths, || child.try_wait().ok().flatten().is_some(), &log).await;
+    if ready.is_err() && child.try_wait().ok().flatten().is_some() {
+        let _ = std::fs::remove_file(&pidfile);
+    }
+    ready.map(Some)
+}
+
+fn log(message: &str) {
+    println!("[{}] {}", chrono::Local::now().format("%Y-%m-%d %H:%M:%S"), message);
+}
+
+/// Re-index changed files in every database of the project
+///
+/// The embedding model is loaded on the first change and then kept.
+fn sync_all(
+    manager: &mut DatabaseManager,
+    root: &Path,
+    embedding_service: &mut Option<EmbeddingService>,
+    chunker: &mut SemanticChunker,
+    config: &Config,
+) -> Result<usize> {
+    let model_type = manager.model_type();
+    let mut synced = 0;
+
+    for database in manager.databases_mut() {
+        let changes = pending_changes(database.store(), root, &config.indexing)?;
+        if changes.is_empty() {
+            continue;
+        }
+        if embedding_service.is_none() {
+            *embedding_service = Some(EmbeddingService::with_model(model_type)?);
+        }
+        let service = embedding_service.as_mut().unwrap();
+
+        let db_path = database.path.clone();
+        let chunks = apply_changes(database.store_mut(), &db_path, &changes, service, chunker)?;
+        record_fingerprint(root, &db_path)?;
+
+        for (file, _) in &changes.changed {
+            log(&format!("  📝 {}", file.path.strip_prefix(root).unwrap_or(&file.path).display()));
+        }
+        for (path, _) in &changes.deleted {
+            log(&format!("  🗑️  {}", path.strip_prefix(root).unwrap_or(path).display()));
+        }
+        log(&format!("Re-indexed {} file(s), {} chunk(s) in {}", changes.file_count(), chunks, db_path.display()));
+        synced += changes.file_count();
+    }
+
+    Ok(synced)
+}
+
+/// Watch the project and keep its index current (the process `start` launches)
+pub async fn run(path: Option<PathBuf>, config: &Config) -> Result<()> {
+    // Store and model loading chatter has no place in the log
+    crate::output::set_quiet(true);
+
+    let root = project_root(path)?;
+    let mut manager = DatabaseManager::load_with_options(Some(root.clone()), &StoreOptions::from_config(config))?;
+    // Held until the daemon is stopped
+    let _write_locks = manager
+        .databases()
+        .iter()
+        .map(|database| WriteLock::acquire(&database.path, "daemon"))
+        .collect::<Result<Vec<_>>>()?;
+    let mut chunker = SemanticChunker::from_config(&config.indexing).with_project_root(&root);
+    let mut embedding_service = None;
+    log(&format!("Watching {} (pid {})", root.display(), std::process::id()));
+
+    // Catch up on changes made while no daemon was running
+    sync_all(&mut manager, &root, &mut embedding_service, &mut chunker, config)?;
+
+    let mut watcher = FileWatcher::new(root.clone());
+    watcher.start(300)?;
+
+    loop {
+        if watcher.poll_events().is_empty() {
+            tokio::time::sleep(Duration::from_millis(500)).await;
+            continue;
+        }
+        if let Err(e) = sync_all(&mut manager, &root, &mut embedding_service, &mut chunker, config) {
+            log(&format!("❌ Sync failed: {:#}", e));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_tail_lines() {
+        let dir = tempdir().unwrap();
+        let log = dir.path().join("daemon.log");
+        std::fs::write(&log, "one\ntwo\nthree\n").unwrap();
+        assert_eq!(tail_lines(&log, 2), ["two", "three"]);
+        assert_eq!(tail_lines(&log, 10).len(), 3);
+        assert!(tail_lines(&dir.path().join("missing.log"), 5).is_empty());
+    }
+}
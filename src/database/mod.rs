@@ -8,7 +8,8 @@ use std::path::PathBuf;
 
 use crate::embed::ModelType;
 use crate::index::get_search_db_paths;
-use crate::vectordb::{SearchResult, VectorStore};
+use crate::search::{vector_search, QueryFilters};
+use crate::vectordb::{SearchResult, StoreOptions, VectorStore};
 
 /// Type of database (local or global)
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -37,8 +38,8 @@ pub struct Database {
 
 impl Database {
     /// Create a new database instance
-    pub fn new(path: PathBuf, db_type: DatabaseType, dimensions: usize) -> Result<Self> {
-        let store = VectorStore::new(&path, dimensions)?;
+    pub fn new(path: PathBuf, db_type: DatabaseType, dimensions: usize, options: &StoreOptions) -> Result<Self> {
+        let store = VectorStore::with_options(&path, dimensions, options)?;
         Ok(Self {
             path,
             db_type,
@@ -55,6 +56,22 @@ impl Database {
     pub fn store_mut(&mut self) -> &mut VectorStore {
         &mut self.store
     }
+
+    /// When this database was last indexed (RFC 3339, from metadata.json)
+    pub fn indexed_at(&self) -> Option<String> {
+        let content = std::fs::read_to_string(self.path.join("metadata.json")).ok()?;
+        let json: serde_json::Value = serde_json::from_str(&content).ok()?;
+        json.get("indexed_at")?.as_str().map(|s| s.to_string())
+    }
+
+    /// Git revision this database is pinned to with `index --rev`, and its commit
+    pub fn revision(&self) -> Option<(String, String)> {
+        let content = std::fs::read_to_string(self.path.join("metadata.json")).ok()?;
+        let json: serde_json::Value = serde_json::from_str(&content).ok()?;
+        let rev = json.get("git_rev")?.as_str()?.to_string();
+        let commit = json.get("git_head").and_then(|v| v.as_str()).unwrap_or_default().to_string();
+        Some((rev, commit))
+    }
 }
 
 /// Combined statistics from all databases
@@ -80,6 +97,11 @@ pub struct DatabaseManager {
 impl DatabaseManager {
     /// Load all available databases for a given path
     pub fn load(path: Option<PathBuf>) -> Result<Self> {
+        Self::load_with_options(path, &StoreOptions::default())
+    }
+
+    /// Load all available databases for a given path, searched as `options` says
+    pub fn load_with_options(path: Option<PathBuf>, options: &StoreOptions) -> Result<Self> {
         let db_paths = get_search_db_paths(path)?;
 
         if db_paths.is_empty() {
@@ -93,13 +115,13 @@ impl DatabaseManager {
         // Load all databases
         let mut databases = Vec::new();
         for db_path in db_paths {
-            let db_type = if db_path.ends_with(".demongrep.db") {
+            let db_type = if crate::index::is_local_db(&db_path) {
                 DatabaseType::Local
             } else {
                 DatabaseType::Global
             };
 
-            match Database::new(db_path.clone(), db_type, dimensions) {
+            match Database::new(db_path.clone(), db_type, dimensions, options) {
                 Ok(db) => databases.push(db),
                 Err(e) => {
                     eprintln!(
@@ -175,10 +197,21 @@ impl DatabaseManager {
 
     /// Search across all databases
     pub fn search_all(&self, query_embedding: &[f32], limit: usize) -> Result<Vec<SearchResult>> {
+        self.search_all_filtered(query_embedding, limit, &QueryFilters::default())
+    }
+
+    /// Search across all databases, keeping only chunks that pass `filters`
+    pub fn search_all_filtered(
+        &self,
+        query_embedding: &[f32],
+        limit: usize,
+        filters: &QueryFilters,
+    ) -> Result<Vec<SearchResult>> {
         let mut all_results = Vec::new();
 
         for database in &self.databases {
-            match database.store.search(query_embedding, limit) {
+            let results = vector_search(&database.store, query_embedding, limit, filters);
+            match results {
                 Ok(mut results) => {
                     all_results.append(&mut results);
                 }
@@ -277,6 +310,7 @@ pub struct DatabaseManagerBuilder {
     db_paths: Vec<PathBuf>,
     model_type: Option<ModelType>,
     dimensions: Option<usize>,
+    options: StoreOptions,
 }
 
 impl DatabaseManagerBuilder {
@@ -285,6 +319,7 @@ impl DatabaseManagerBuilder {
             db_paths: Vec::new(),
             model_type: None,
             dimensions: None,
+            options: StoreOptions::default(),
         }
     }
 
@@ -303,6 +338,11 @@ impl DatabaseManagerBuilder {
         self
     }
 
+    pub fn with_options(mut self, options: StoreOptions) -> Self {
+        self.options = options;
+        self
+    }
+
     pub fn build(self) -> Result<DatabaseManager> {
         if self.db_paths.is_empty() {
             return Err(anyhow!("No database paths specified"));
@@ -319,13 +359,13 @@ impl DatabaseManagerBuilder {
         // Load all databases
         let mut databases = Vec::new();
         for db_path in self.db_paths {
-            let db_type = if db_path.ends_with(".demongrep.db") {
+            let db_type = if crate::index::is_local_db(&db_path) {
                 DatabaseType::Local
             } else {
                 DatabaseType::Global
             };
 
-            match Database::new(db_path.clone(), db_type, dimensions) {
+            match Database::new(db_path.clone(), db_type, dimensions, &self.options) {
                 Ok(db) => databases.push(db),
                 Err(e) => {
                     eprintln!(
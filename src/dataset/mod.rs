@@ -0,0 +1,275 @@
+//! Embedding export for analysis (`demongrep export-embeddings`)
+//!
+//! The index already holds an embedding for every chunk, so clustering,
+//! UMAP plots or retrieval experiments can run on the corpus without
+//! embedding it again. Each row is one chunk: its ID, path, lines, kind,
+//! signature, tags, package and content, and the raw vector.
+//!
+//! - `parquet`: one file, the vector as a fixed-size list of float32
+//! - `npy`: a NumPy float32 matrix with a row per chunk, and the other
+//!   columns in a JSON Lines file beside it (same name, `.jsonl`), line for row
+//! - `jsonl`: one JSON object per chunk, vector included
+
+use anyhow::{anyhow, Result};
+use arrow_array::builder::{FixedSizeListBuilder, Float32Builder, ListBuilder, StringBuilder};
+use arrow_array::{ArrayRef, RecordBatch, StringArray, UInt32Array, UInt64Array};
+use arrow_schema::{DataType, Field, Schema, SchemaRef};
+use colored::Colorize;
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+use serde::Serialize;
+use std::fs::File;
+use std::io::{BufWriter, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Instant;
+
+use crate::index::{get_search_db_paths, recorded_dimensions};
+use crate::vectordb::{ChunkMetadata, VectorStore};
+
+/// File format of `export-embeddings`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum EmbeddingFormat {
+    /// Apache Parquet, vectors as a fixed-size list column
+    Parquet,
+    /// NumPy .npy matrix, with metadata in a .jsonl file beside it
+    Npy,
+    /// JSON Lines, one chunk with its vector per line
+    Jsonl,
+}
+
+impl EmbeddingFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            Self::Parquet => "parquet",
+            Self::Npy => "npy",
+            Self::Jsonl => "jsonl",
+        }
+    }
+}
+
+/// Rows per Parquet row group, so memory stays bounded on large stores
+const BATCH_ROWS: usize = 8192;
+
+/// Bytes reserved for the .npy header: room for any row count, and a multiple of 64 as NumPy aligns data
+const NPY_HEADER_LEN: usize = 128;
+
+/// One exported chunk
+#[derive(Serialize)]
+struct Row {
+    id: u32,
+    path: String,
+    start_line: usize,
+    end_line: usize,
+    kind: String,
+    signature: Option<String>,
+    tags: Vec<String>,
+    package: Option<String>,
+    content: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    embedding: Option<Vec<f32>>,
+}
+
+impl Row {
+    fn new(id: u32, meta: ChunkMetadata, embedding: Option<Vec<f32>>) -> Self {
+        Self {
+            id,
+            path: meta.path,
+            start_line: meta.start_line,
+            end_line: meta.end_line,
+            kind: meta.kind,
+            signature: meta.signature,
+            tags: meta.tags,
+            package: meta.package,
+            content: meta.content,
+            embedding,
+        }
+    }
+}
+
+fn parquet_schema(dimensions: usize) -> SchemaRef {
+    let float = Arc::new(Field::new("item", DataType::Float32, true));
+    let string = Arc::new(Field::new("item", DataType::Utf8, true));
+    Arc::new(Schema::new(vec![
+        Field::new("id", DataType::UInt32, false),
+        Field::new("path", DataType::Utf8, false),
+        Field::new("start_line", DataType::UInt64, false),
+        Field::new("end_line", DataType::UInt64, false),
+        Field::new("kind", DataType::Utf8, false),
+        Field::new("signature", DataType::Utf8, true),
+        Field::new("tags", DataType::List(string), false),
+        Field::new("package", DataType::Utf8, true),
+        Field::new("content", DataType::Utf8, false),
+        Field::new("embedding", DataType::FixedSizeList(float, dimensions as i32), false),
+    ]))
+}
+
+/// Columns of `rows` as an Arrow record batch
+fn record_batch(schema: &SchemaRef, rows: &[Row], dimensions: usize) -> Result<RecordBatch> {
+    let mut tags = ListBuilder::new(StringBuilder::new());
+    let mut embeddings = FixedSizeListBuilder::with_capacity(
+        Float32Builder::with_capacity(rows.len() * dimensions),
+        dimensions as i32,
+        rows.len(),
+    );
+    for row in rows {
+        for tag in &row.tags {
+            tags.values().append_value(tag);
+        }
+        tags.append(true);
+        embeddings.values().append_slice(row.embedding.as_deref().unwrap_or_default());
+        embeddings.append(true);
+    }
+
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(UInt32Array::from_iter_values(rows.iter().map(|row| row.id))),
+        Arc::new(StringArray::from_iter_values(rows.iter().map(|row| &row.path))),
+        Arc::new(UInt64Array::from_iter_values(rows.iter().map(|row| row.start_line as u64))),
+        Arc::new(UInt64Array::from_iter_values(rows.iter().map(|row| row.end_line as u64))),
+        Arc::new(StringArray::from_iter_values(rows.iter().map(|row| &row.kind))),
+        Arc::new(StringArray::from_iter(rows.iter().map(|row| row.signature.as_deref()))),
+        Arc::new(tags.finish()),
+        Arc::new(StringArray::from_iter(rows.iter().map(|row| row.package.as_deref()))),
+        Arc::new(StringArray::from_iter_values(rows.iter().map(|row| &row.content))),
+        Arc::new(embeddings.finish()),
+    ];
+    Ok(RecordBatch::try_new(schema.clone(), columns)?)
+}
+
+fn write_parquet(store: &VectorStore, output: &Path, dimensions: usize) -> Result<usize> {
+    let schema = parquet_schema(dimensions);
+    let properties = WriterProperties::builder().set_compression(Compression::SNAPPY).build();
+    let mut writer = ArrowWriter::try_new(File::create(output)?, schema.clone(), Some(properties))?;
+
+    let mut rows = Vec::with_capacity(BATCH_ROWS);
+    let mut written = 0;
+    store.for_each_vector(|id, meta, vector| {
+        rows.push(Row::new(id, meta, Some(vector)));
+        if rows.len() == BATCH_ROWS {
+            writer.write(&record_batch(&schema, &rows, dimensions)?)?;
+            written += rows.len();
+            rows.clear();
+        }
+        Ok(())
+    })?;
+    if !rows.is_empty() {
+        writer.write(&record_batch(&schema, &rows, dimensions)?)?;
+        written += rows.len();
+    }
+    writer.close()?;
+    Ok(written)
+}
+
+/// `.npy` header of a little-endian float32 matrix, padded to `NPY_HEADER_LEN` bytes
+fn npy_header(rows: usize, columns: usize) -> Vec<u8> {
+    let dict = format!("{{'descr': '<f4', 'fortran_order': False, 'shape': ({}, {}), }}", rows, columns);
+    let mut header = b"\x93NUMPY\x01\x00".to_vec();
+    header.extend_from_slice(&((NPY_HEADER_LEN - 10) as u16).to_le_bytes());
+    header.extend_from_slice(dict.as_bytes());
+    header.resize(NPY_HEADER_LEN - 1, b' ');
+    header.push(b'\n');
+    header
+}
+
+/// Write the vectors to `output` and the other columns to `metadata`, returning the row count
+fn write_npy(store: &VectorStore, output: &Path, metadata: &Path, dimensions: usize) -> Result<usize> {
+    let mut matrix = BufWriter::new(File::create(output)?);
+    let mut lines = BufWriter::new(File::create(metadata)?);
+    // The row count is only known at the end; the header is rewritten then
+    matrix.write_all(&npy_header(0, dimensions))?;
+
+    let mut rows = 0;
+    store.for_each_vector(|id, meta, vector| {
+        for value in &vector {
+            matrix.write_all(&value.to_le_bytes())?;
+        }
+        writeln!(lines, "{}", serde_json::to_string(&Row::new(id, meta, None))?)?;
+        rows += 1;
+        Ok(())
+    })?;
+    lines.flush()?;
+
+    let mut file = matrix.into_inner().map_err(|e| anyhow!("Could not write {}: {}", output.display(), e))?;
+    file.seek(SeekFrom::Start(0))?;
+    file.write_all(&npy_header(rows, dimensions))?;
+    Ok(rows)
+}
+
+fn write_jsonl(store: &VectorStore, output: &Path) -> Result<usize> {
+    let mut lines = BufWriter::new(File::create(output)?);
+    let mut rows = 0;
+    store.for_each_vector(|id, meta, vector| {
+        writeln!(lines, "{}", serde_json::to_string(&Row::new(id, meta, Some(vector)))?)?;
+        rows += 1;
+        Ok(())
+    })?;
+    lines.flush()?;
+    Ok(rows)
+}
+
+/// Export the chunk embeddings of the project's index
+pub async fn export_embeddings(path: Option<PathBuf>, format: EmbeddingFormat, output: Option<PathBuf>) -> Result<()> {
+    let db_paths = get_search_db_paths(path)?;
+    let Some(db_path) = db_paths.first() else {
+        println!("{}", "❌ No database found!".red());
+        println!("   Run {} first", "demongrep index".bright_cyan());
+        return Ok(());
+    };
+    let dimensions = recorded_dimensions(db_path)
+        .ok_or_else(|| anyhow!("{} has no metadata.json; run `demongrep index` first", db_path.display()))?;
+    let output = output.unwrap_or_else(|| PathBuf::from(format!("embeddings.{}", format.extension())));
+
+    println!("{}", "📤 Exporting embeddings".bright_cyan().bold());
+    println!("{}", "=".repeat(60));
+    println!("💾 Database: {}", db_path.display());
+
+    let start = Instant::now();
+    let store = VectorStore::new(db_path, dimensions)?;
+    let rows = match format {
+        EmbeddingFormat::Parquet => write_parquet(&store, &output, dimensions)?,
+        EmbeddingFormat::Npy => {
+            let metadata = output.with_extension("jsonl");
+            let rows = write_npy(&store, &output, &metadata, dimensions)?;
+            println!("   Chunk metadata: {}", metadata.display());
+            rows
+        }
+        EmbeddingFormat::Jsonl => write_jsonl(&store, &output)?,
+    };
+
+    println!("✅ Wrote {} embeddings ({} dims) to {}", rows, dimensions, output.display());
+    println!("   Took {:?}", start.elapsed());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_npy_header() {
+        let header = npy_header(12345, 384);
+        assert_eq!(header.len(), NPY_HEADER_LEN);
+        assert!(header.starts_with(b"\x93NUMPY\x01\x00"));
+        assert_eq!(u16::from_le_bytes([header[8], header[9]]) as usize, NPY_HEADER_LEN - 10);
+        let dict = String::from_utf8_lossy(&header[10..]);
+        assert!(dict.starts_with("{'descr': '<f4', 'fortran_order': False, 'shape': (12345, 384), }"));
+        assert!(dict.ends_with(" \n"));
+    }
+
+    #[test]
+    fn test_record_batch() {
+        let meta = |path: &str, tags: &[&str]| ChunkMetadata {
+            path: path.to_string(),
+            kind: "Function".to_string(),
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            ..Default::default()
+        };
+        let rows = vec![
+            Row::new(3, meta("src/auth.rs", &["api"]), Some(vec![0.5, 0.5])),
+            Row::new(8, meta("src/db.rs", &[]), Some(vec![1.0, 0.0])),
+        ];
+        let batch = record_batch(&parquet_schema(2), &rows, 2).unwrap();
+        assert_eq!((batch.num_rows(), batch.num_columns()), (2, 10));
+    }
+}
@@ -0,0 +1,340 @@
+//! Local crash reports
+//!
+//! A panic hook writes a diagnostic bundle (version, platform, config with
+//! secrets redacted, recent log lines, database stats) to
+//! `~/.demongrep/crashes/` and prints its path, so users have something to
+//! attach to an issue. `demongrep doctor --bundle` writes the same bundle on
+//! demand.
+
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::panic::PanicHookInfo;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+use crate::config::Config;
+use crate::index::get_search_db_paths;
+
+/// Number of recent log lines kept in memory for bundles
+const LOG_CAPACITY: usize = 200;
+
+/// Config and argument names containing any of these are redacted
+const SECRET_MARKERS: &[&str] = &["key", "token", "secret", "password"];
+
+/// Recent complete log lines, and the line currently being written
+struct LogBuffer {
+    lines: VecDeque<String>,
+    partial: Vec<u8>,
+}
+
+impl LogBuffer {
+    fn push_line(&mut self, line: &[u8]) {
+        if self.lines.len() == LOG_CAPACITY {
+            self.lines.pop_front();
+        }
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        self.lines.push_back(String::from_utf8_lossy(line).into_owned());
+    }
+}
+
+static LOG_LINES: Mutex<LogBuffer> = Mutex::new(LogBuffer { lines: VecDeque::new(), partial: Vec::new() });
+
+/// Project the current command targets (set once by the CLI)
+static PROJECT_ROOT: OnceLock<PathBuf> = OnceLock::new();
+
+/// Record the project root so bundles can include its config and databases
+pub fn set_project_root(root: PathBuf) {
+    let _ = PROJECT_ROOT.set(root);
+}
+
+/// `tracing` writer that keeps the last log lines in memory
+#[derive(Debug, Default, Clone, Copy)]
+pub struct LogCapture;
+
+impl std::io::Write for LogCapture {
+    // A line usually arrives over several writes, so only a newline completes it
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let mut log = LOG_LINES.lock().unwrap_or_else(|e| e.into_inner());
+        let mut rest = buf;
+        while let Some(end) = rest.iter().position(|&b| b == b'\n') {
+            let mut line = std::mem::take(&mut log.partial);
+            line.extend_from_slice(&rest[..end]);
+            log.push_line(&line);
+            rest = &rest[end + 1..];
+        }
+        log.partial.extend_from_slice(rest);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        let mut log = LOG_LINES.lock().unwrap_or_else(|e| e.into_inner());
+        if !log.partial.is_empty() {
+            let line = std::mem::take(&mut log.partial);
+            log.push_line(&line);
+        }
+        Ok(())
+    }
+}
+
+fn recent_logs() -> Vec<String> {
+    LOG_LINES
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .lines
+        .iter()
+        .cloned()
+        .collect()
+}
+
+/// Where and why the process panicked
+#[derive(Debug, Clone, Serialize)]
+pub struct PanicDetails {
+    pub message: String,
+    pub location: Option<String>,
+    pub backtrace: String,
+}
+
+impl PanicDetails {
+    fn from_hook(info: &PanicHookInfo<'_>) -> Self {
+        let message = if let Some(s) = info.payload().downcast_ref::<&str>() {
+            s.to_string()
+        } else if let Some(s) = info.payload().downcast_ref::<String>() {
+            s.clone()
+        } else {
+            "<non-string panic payload>".to_string()
+        };
+
+        Self {
+            message,
+            location: info.location().map(|l| format!("{}:{}:{}", l.file(), l.line(), l.column())),
+            backtrace: std::backtrace::Backtrace::force_capture().to_string(),
+        }
+    }
+}
+
+/// On-disk size and metadata.json of one database
+#[derive(Debug, Clone, Serialize)]
+pub struct DatabaseSnapshot {
+    pub path: String,
+    pub size_bytes: u64,
+    pub metadata: Option<serde_json::Value>,
+}
+
+/// Everything written to a diagnostic bundle
+#[derive(Debug, Clone, Serialize)]
+pub struct DiagnosticBundle {
+    pub version: String,
+    pub created_at: String,
+    pub os: String,
+    pub arch: String,
+    pub args: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub panic: Option<PanicDetails>,
+    pub project_root: Option<String>,
+    /// Effective config with secrets redacted (or the load error)
+    pub config: serde_json::Value,
+    pub databases: Vec<DatabaseSnapshot>,
+    pub recent_logs: Vec<String>,
+    /// Command-specific details (e.g. the doctor report)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub extra: Option<serde_json::Value>,
+}
+
+impl DiagnosticBundle {
+    /// Gather a bundle for the current process
+    ///
+    /// Only reads files: databases are not opened, since the crashing
+    /// process may still hold them.
+    pub fn collect(panic: Option<PanicDetails>, extra: Option<serde_json::Value>) -> Self {
+        let project_root = PROJECT_ROOT.get().cloned().unwrap_or_else(|| PathBuf::from("."));
+
+        let config = match Config::load_for(&project_root).and_then(|c| Ok(toml::Value::try_from(c)?)) {
+            Ok(mut value) => {
+                redact(&mut value);
+                serde_json::to_value(value).unwrap_or_default()
+            }
+            Err(e) => serde_json::json!({ "error": e.to_string() }),
+        };
+
+        let databases = get_search_db_paths(Some(project_root.clone()))
+            .unwrap_or_default()
+            .into_iter()
+            .map(|db_path| snapshot_database(&db_path))
+            .collect();
+
+        Self {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            created_at: chrono::Utc::now().to_rfc3339(),
+            os: std::env::consts::OS.to_string(),
+            arch: std::env::consts::ARCH.to_string(),
+            args: redact_args(std::env::args().collect()),
+            panic,
+            project_root: project_root
+                .canonicalize()
+                .ok()
+                .map(|p| p.to_string_lossy().to_string()),
+            config,
+            databases,
+            recent_logs: recent_logs(),
+            extra,
+        }
+    }
+
+    /// Write the bundle as JSON into the crash directory, returning its path
+    pub fn write(&self) -> Result<PathBuf> {
+        let dir = crash_dir();
+        std::fs::create_dir_all(&dir)?;
+
+        let file_name = format!(
+            "demongrep-{}-{}.json",
+            chrono::Utc::now().format("%Y%m%dT%H%M%SZ"),
+            std::process::id()
+        );
+        let path = dir.join(file_name);
+        std::fs::write(&path, serde_json::to_vec_pretty(self)?)?;
+        Ok(path)
+    }
+}
+
+/// Directory bundles are written to (falls back to the temp dir without a home)
+pub fn crash_dir() -> PathBuf {
+    dirs::home_dir()
+        .map(|home| home.join(".demongrep").join("crashes"))
+        .unwrap_or_else(|| std::env::temp_dir().join("demongrep-crashes"))
+}
+
+/// Install a panic hook that writes a bundle before the default panic output
+pub fn install_panic_hook() {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let bundle = DiagnosticBundle::collect(Some(PanicDetails::from_hook(info)), None);
+        match bundle.write() {
+            Ok(path) => eprintln!(
+                "\n💥 demongrep crashed. A diagnostic report was written to:\n   {}\n   Please attach it when filing an issue.\n",
+                path.display()
+            ),
+            Err(e) => eprintln!("\n💥 demongrep crashed (could not write diagnostic report: {})\n", e),
+        }
+        default_hook(info);
+    }));
+}
+
+fn snapshot_database(db_path: &Path) -> DatabaseSnapshot {
+    let metadata = std::fs::read_to_string(db_path.join("metadata.json"))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok());
+
+    DatabaseSnapshot {
+        path: db_path.to_string_lossy().to_string(),
+        size_bytes: dir_size(db_path),
+        metadata,
+    }
+}
+
+fn dir_size(path: &Path) -> u64 {
+    let Ok(entries) = std::fs::read_dir(path) else {
+        return 0;
+    };
+    entries
+        .flatten()
+        .map(|entry| match entry.file_type() {
+            Ok(t) if t.is_dir() => dir_size(&entry.path()),
+            _ => entry.metadata().map(|m| m.len()).unwrap_or(0),
+        })
+        .sum()
+}
+
+fn is_secret_name(name: &str) -> bool {
+    let name = name.to_lowercase();
+    SECRET_MARKERS.iter().any(|marker| name.contains(marker))
+}
+
+/// Replace values of secret-looking keys, recursively
+fn redact(value: &mut toml::Value) {
+    match value {
+        toml::Value::Table(table) => {
+            for (key, value) in table.iter_mut() {
+                if is_secret_name(key) && !value.is_table() {
+                    *value = toml::Value::String("<redacted>".to_string());
+                } else {
+                    redact(value);
+                }
+            }
+        }
+        toml::Value::Array(items) => items.iter_mut().for_each(redact),
+        _ => {}
+    }
+}
+
+/// Redact values passed to secret-looking flags (`--api-key X`, `--token=X`)
+fn redact_args(args: Vec<String>) -> Vec<String> {
+    let mut redacted = Vec::with_capacity(args.len());
+    let mut redact_next = false;
+
+    for arg in args {
+        if redact_next {
+            redacted.push("<redacted>".to_string());
+            redact_next = false;
+            continue;
+        }
+        match arg.strip_prefix("--").and_then(|flag| flag.split_once('=')) {
+            Some((flag, _)) if is_secret_name(flag) => redacted.push(format!("--{}=<redacted>", flag)),
+            _ => {
+                redact_next = arg.starts_with("--") && is_secret_name(&arg);
+                redacted.push(arg);
+            }
+        }
+    }
+
+    redacted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact_config() {
+        let mut value: toml::Value = toml::from_str(
+            r#"
+            [server]
+            api_keys = ["abc"]
+            port = 4444
+            "#,
+        )
+        .unwrap();
+        redact(&mut value);
+        assert_eq!(value["server"]["api_keys"].as_str(), Some("<redacted>"));
+        assert_eq!(value["server"]["port"].as_integer(), Some(4444));
+    }
+
+    #[test]
+    fn test_redact_args() {
+        let args = ["demongrep", "serve", "--api-key", "abc", "--token=xyz", "--port", "80"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        assert_eq!(
+            redact_args(args),
+            ["demongrep", "serve", "--api-key", "<redacted>", "--token=<redacted>", "--port", "80"]
+        );
+    }
+
+    #[test]
+    fn test_log_capture_is_bounded() {
+        use std::io::Write;
+        for i in 0..LOG_CAPACITY + 10 {
+            writeln!(LogCapture, "line {}", i).unwrap();
+        }
+        let logs = recent_logs();
+        assert_eq!(logs.len(), LOG_CAPACITY);
+        assert_eq!(logs.last().map(|s| s.as_str()), Some(format!("line {}", LOG_CAPACITY + 9).as_str()));
+
+        write!(LogCapture, "no ").unwrap();
+        write!(LogCapture, "newline").unwrap();
+        assert_eq!(recent_logs().last().map(|s| s.as_str()), Some(format!("line {}", LOG_CAPACITY + 9).as_str()));
+        LogCapture.flush().unwrap();
+        assert_eq!(recent_logs().last().map(|s| s.as_str()), Some("no newline"));
+    }
+}
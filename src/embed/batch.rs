@@ -1,7 +1,11 @@
 use super::embedder::FastEmbedder;
 use crate::chunker::Chunk;
 use anyhow::Result;
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, OnceLock};
+use tiktoken_rs::CoreBPE;
+
+/// Input length (in tokens) the embedding models truncate to (fastembed's default `max_length`)
+pub const MAX_EMBED_TOKENS: usize = 512;
 
 /// Statistics for embedding operations
 #[derive(Debug, Clone, Default)]
@@ -94,7 +98,7 @@ impl BatchEmbedder {
         }
 
         let total = chunks.len();
-        println!("📊 Embedding {} chunks (batch size: {})...", total, self.batch_size);
+        crate::info_print!("📊 Embedding {} chunks (batch size: {})...", total, self.batch_size);
 
         let start = std::time::Instant::now();
         let mut embedded_chunks = Vec::with_capacity(total);
@@ -104,7 +108,7 @@ impl BatchEmbedder {
             let batch_start = batch_idx * self.batch_size;
             let batch_end = (batch_start + chunk_batch.len()).min(total);
 
-            println!(
+            crate::info_print!(
                 "   Batch {}/{}: chunks {}-{}",
                 batch_idx + 1,
                 (total + self.batch_size - 1) / self.batch_size,
@@ -115,7 +119,7 @@ impl BatchEmbedder {
             // Prepare texts for embedding
             let texts: Vec<String> = chunk_batch
                 .iter()
-                .map(|chunk| self.prepare_text(chunk))
+                .map(embedding_text)
                 .collect();
 
             // Generate embeddings
@@ -128,7 +132,7 @@ impl BatchEmbedder {
         }
 
         let elapsed = start.elapsed();
-        println!(
+        crate::info_print!(
             "✅ Embedded {} chunks in {:.2}s ({:.1} chunks/sec)",
             total,
             elapsed.as_secs_f32(),
@@ -140,47 +144,11 @@ impl BatchEmbedder {
 
     /// Embed a single chunk
     pub fn embed_chunk(&mut self, chunk: Chunk) -> Result<EmbeddedChunk> {
-        let text = self.prepare_text(&chunk);
+        let text = embedding_text(&chunk);
         let embedding = self.embedder.lock().unwrap().embed_one(&text)?;
         Ok(EmbeddedChunk::new(chunk, embedding))
     }
 
-    /// Prepare chunk text for embedding
-    ///
-    /// Combines different chunk metadata for better embeddings:
-    /// - Context breadcrumbs
-    /// - Signature (if available)
-    /// - Docstring (if available)
-    /// - Content
-    fn prepare_text(&self, chunk: &Chunk) -> String {
-        let mut parts = Vec::new();
-
-        // Add context breadcrumbs (e.g., "File: main.rs > Class: Server")
-        if !chunk.context.is_empty() {
-            let context = chunk.context.join(" > ");
-            parts.push(format!("Context: {}", context));
-        }
-
-        // Add signature if available (e.g., "fn process(data: Vec<T>) -> Result<T>")
-        if let Some(sig) = &chunk.signature {
-            parts.push(format!("Signature: {}", sig));
-        }
-
-        // Add docstring if available
-        if let Some(doc) = &chunk.docstring {
-            // Clean up docstring
-            let cleaned = clean_docstring(doc);
-            if !cleaned.is_empty() {
-                parts.push(format!("Documentation: {}", cleaned));
-            }
-        }
-
-        // Add main content
-        parts.push(format!("Code:\n{}", chunk.content));
-
-        parts.join("\n")
-    }
-
     /// Get embedding dimensions
     pub fn dimensions(&self) -> usize {
         self.embedder.lock().unwrap().dimensions()
@@ -193,8 +161,87 @@ impl BatchEmbedder {
     }
 }
 
+/// Prepare chunk text for embedding
+///
+/// Combines different chunk metadata for better embeddings:
+/// - Context breadcrumbs
+/// - Signature (if available)
+/// - Docstring (if available)
+/// - Team notes (if any)
+/// - Content
+pub fn embedding_text(chunk: &Chunk) -> String {
+    let mut parts = Vec::new();
+
+    // Add context breadcrumbs (e.g., "File: main.rs > Class: Server")
+    if !chunk.context.is_empty() {
+        let context = chunk.context.join(" > ");
+        parts.push(format!("Context: {}", context));
+    }
+
+    // Add signature if available (e.g., "fn process(data: Vec<T>) -> Result<T>")
+    if let Some(sig) = &chunk.signature {
+        parts.push(format!("Signature: {}", sig));
+    }
+
+    // Add docstring if available
+    if let Some(doc) = &chunk.docstring {
+        // Clean up docstring
+        let cleaned = clean_docstring(doc);
+        if !cleaned.is_empty() {
+            parts.push(format!("Documentation: {}", cleaned));
+        }
+    }
+
+    // Add team notes so searches phrased like the note find the chunk
+    if !chunk.notes.is_empty() {
+        parts.push(format!("Notes: {}", chunk.notes.join(" | ")));
+    }
+
+    // Add main content
+    parts.push(format!("Code:\n{}", chunk.content));
+
+    parts.join("\n")
+}
+
+/// Rough WordPiece token count for `text`
+///
+/// Each run of letters/digits counts one token per 4 characters, every other
+/// non-whitespace character counts as one, plus the two special tokens. Close
+/// enough to tell tiny and truncated chunks apart without loading a tokenizer.
+pub fn estimate_tokens(text: &str) -> usize {
+    let mut tokens = 2;
+    let mut word_len: usize = 0;
+
+    for c in text.chars() {
+        if c.is_alphanumeric() {
+            word_len += 1;
+            continue;
+        }
+        tokens += word_len.div_ceil(4);
+        word_len = 0;
+        if !c.is_whitespace() {
+            tokens += 1;
+        }
+    }
+
+    tokens + word_len.div_ceil(4)
+}
+
+/// Tokens `text` takes in an LLM prompt, counted with the cl100k_base BPE
+///
+/// Unlike `estimate_tokens`, which sizes the embedding model's input, this
+/// is for budgeting what agents paste into prompts. Falls back to the
+/// estimate if the encoding can't be loaded.
+pub fn count_tokens(text: &str) -> usize {
+    static BPE: OnceLock<Option<CoreBPE>> = OnceLock::new();
+    match BPE.get_or_init(|| tiktoken_rs::cl100k_base().ok()) {
+        Some(bpe) => bpe.encode_with_special_tokens(text).len(),
+        None => estimate_tokens(text),
+    }
+}
+
 /// Clean docstring by removing comment markers
-fn clean_docstring(doc: &str) -> String {
+pub fn clean_docstring(doc: &str) -> String {
     // First handle triple-quoted strings and JSDoc as special cases
     let cleaned = if let Some(stripped) = doc.strip_prefix("\"\"\"").and_then(|s| s.strip_suffix("\"\"\"")) {
         stripped
@@ -262,6 +309,16 @@ mod tests {
         assert_eq!(stats.chunks_per_second(), 80.0);
     }
 
+    #[test]
+    fn test_estimate_tokens() {
+        assert_eq!(estimate_tokens(""), 2);
+        // "fn" + "main" + "(" + ")" + "{" + "}"
+        assert_eq!(estimate_tokens("fn main() {}"), 8);
+        // 11-character identifier splits into three pieces
+        assert_eq!(estimate_tokens("getUserById"), 5);
+        assert_eq!(count_tokens("fn validate_token() {}"), 5);
+    }
+
     #[test]
     fn test_clean_docstring() {
         let rust_doc = "/// This is a doc comment\n/// with multiple lines";
@@ -275,14 +332,7 @@ mod tests {
     }
 
     #[test]
-    fn test_prepare_text() {
-        let embedder = Arc::new(Mutex::new(FastEmbedder::new().unwrap_or_else(|_| {
-            // For tests, create a mock if real embedder fails
-            panic!("Cannot create embedder in test");
-        })));
-
-        let batch = BatchEmbedder::new(embedder);
-
+    fn test_embedding_text() {
         let mut chunk = Chunk::new(
             "fn test() { println!(\"test\"); }".to_string(),
             0,
@@ -294,12 +344,16 @@ mod tests {
         chunk.signature = Some("fn test()".to_string());
         chunk.docstring = Some("/// Test function".to_string());
 
-        let text = batch.prepare_text(&chunk);
+        let text = embedding_text(&chunk);
 
         assert!(text.contains("Context: File: test.rs > Function: test"));
         assert!(text.contains("Signature: fn test()"));
         assert!(text.contains("Documentation: Test function"));
         assert!(text.contains("Code:"));
+        assert!(!text.contains("Notes:"));
+
+        chunk.notes = vec!["Used by the nightly export".to_string()];
+        assert!(embedding_text(&chunk).contains("Notes: Used by the nightly export"));
     }
 
     #[test]
@@ -4,10 +4,20 @@ use anyhow::Result;
 use dashmap::DashMap;
 use std::sync::atomic::{AtomicUsize, Ordering};
 
+/// Key of a chunk's embedding: its content hash, extended with its notes
+fn cache_key(chunk: &Chunk) -> String {
+    if chunk.notes.is_empty() {
+        chunk.hash.clone()
+    } else {
+        format!("{}+{}", chunk.hash, Chunk::compute_hash(&chunk.notes.join("\n")))
+    }
+}
+
 /// Cache for embeddings keyed by chunk hash
 ///
 /// Uses DashMap for concurrent access without locks.
-/// Chunks are identified by their SHA-256 content hash.
+/// Chunks are identified by their SHA-256 content hash, plus their notes
+/// when they have any (notes change the embedded text).
 pub struct EmbeddingCache {
     cache: DashMap<String, Vec<f32>>,
     hits: AtomicUsize,
@@ -26,7 +36,7 @@ impl EmbeddingCache {
 
     /// Get embedding from cache if available
     pub fn get(&self, chunk: &Chunk) -> Option<Vec<f32>> {
-        if let Some(embedding) = self.cache.get(&chunk.hash) {
+        if let Some(embedding) = self.cache.get(&cache_key(chunk)) {
             self.hits.fetch_add(1, Ordering::Relaxed);
             Some(embedding.clone())
         } else {
@@ -37,18 +47,18 @@ impl EmbeddingCache {
 
     /// Store embedding in cache
     pub fn put(&self, chunk: &Chunk, embedding: Vec<f32>) {
-        self.cache.insert(chunk.hash.clone(), embedding);
+        self.cache.insert(cache_key(chunk), embedding);
     }
 
     /// Store an embedded chunk
     pub fn put_embedded(&self, embedded: &EmbeddedChunk) {
         self.cache
-            .insert(embedded.chunk.hash.clone(), embedded.embedding.clone());
+            .insert(cache_key(&embedded.chunk), embedded.embedding.clone());
     }
 
     /// Check if cache contains embedding for chunk
     pub fn contains(&self, chunk: &Chunk) -> bool {
-        self.cache.contains_key(&chunk.hash)
+        self.cache.contains_key(&cache_key(chunk))
     }
 
     /// Get cache statistics
@@ -133,7 +143,7 @@ impl CachedBatchEmbedder {
         let mut cache_indices = Vec::new();
 
         // Check cache first
-        println!("🔍 Checking cache for {} chunks...", total);
+        crate::info_print!("🔍 Checking cache for {} chunks...", total);
         for (idx, chunk) in chunks.iter().enumerate() {
             if let Some(embedding) = self.cache.get(chunk) {
                 embedded_chunks.push(EmbeddedChunk::new(chunk.clone(), embedding));
@@ -146,7 +156,7 @@ impl CachedBatchEmbedder {
         let cached_count = embedded_chunks.len();
         let to_embed_count = chunks_to_embed.len();
 
-        println!(
+        crate::info_print!(
             "   ✅ Found {} in cache, embedding {} new chunks",
             cached_count, to_embed_count
         );
@@ -167,7 +177,7 @@ impl CachedBatchEmbedder {
         // (Note: Current implementation maintains order naturally due to how we build the vec)
 
         let stats = self.cache.stats();
-        println!(
+        crate::info_print!(
             "📊 Cache stats: {} entries, {:.1}% hit rate",
             stats.size,
             stats.hit_rate() * 100.0
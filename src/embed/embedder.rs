@@ -1,10 +1,87 @@
+use crate::config::Device;
 use crate::info_print;
 use anyhow::{anyhow, Result};
 use fastembed::{EmbeddingModel as FastEmbedModel, InitOptions, TextEmbedding};
 use ort::execution_providers::CPUExecutionProvider;
+use std::sync::OnceLock;
+
+/// Execution device for embedders created in this process (set once from config)
+static DEVICE: OnceLock<Device> = OnceLock::new();
+
+/// Set the execution device used by newly created embedders
+pub fn set_device(device: Device) {
+    let _ = DEVICE.set(device);
+}
+
+/// Get the configured execution device (defaults to CPU)
+pub fn device() -> Device {
+    DEVICE.get().copied().unwrap_or(Device::Cpu)
+}
+
+/// Whether model downloads are disabled (set once from `--offline`)
+static OFFLINE: OnceLock<bool> = OnceLock::new();
+
+/// Only load models from the local cache, never download
+pub fn set_offline(offline: bool) {
+    let _ = OFFLINE.set(offline);
+}
+
+/// Whether offline mode is on (`--offline` or `DEMONGREP_OFFLINE=1`)
+pub fn is_offline() -> bool {
+    OFFLINE.get().copied().unwrap_or(false)
+        || std::env::var("DEMONGREP_OFFLINE")
+            .map(|v| !matches!(v.trim().to_ascii_lowercase().as_str(), "" | "0" | "false" | "no"))
+            .unwrap_or(false)
+}
+
+/// Directory fastembed downloads models into (honours FASTEMBED_CACHE_DIR)
+pub fn model_cache_dir() -> std::path::PathBuf {
+    std::env::var("FASTEMBED_CACHE_DIR")
+        .map(std::path::PathBuf::from)
+        .unwrap_or_else(|_| std::path::PathBuf::from(".fastembed_cache"))
+}
+
+/// Cache directory of a Hugging Face repo (e.g. "BAAI/bge-small-en-v1.5")
+pub fn model_repo_dir(model_code: &str) -> std::path::PathBuf {
+    // hf-hub layout: models--<org>--<name>
+    model_cache_dir().join(format!("models--{}", model_code.replace('/', "--")))
+}
+
+/// Hugging Face repo an embedding model is downloaded from
+pub fn model_code(model_type: ModelType) -> Option<String> {
+    TextEmbedding::get_model_info(&model_type.to_fastembed_model())
+        .ok()
+        .map(|info| info.model_code.clone())
+}
+
+/// Whether a snapshot of a cached repo contains `file` (e.g. "onnx/model.onnx")
+pub fn is_repo_file_cached(model_code: &str, file: &str) -> bool {
+    std::fs::read_dir(model_repo_dir(model_code).join("snapshots"))
+        .map(|entries| entries.filter_map(|e| e.ok()).any(|e| e.path().join(file).is_file()))
+        .unwrap_or(false)
+}
+
+/// Whether a model's files are already in the local model cache
+pub fn is_model_cached(model_type: ModelType) -> bool {
+    TextEmbedding::get_model_info(&model_type.to_fastembed_model())
+        .map(|info| is_repo_file_cached(&info.model_code, &info.model_file))
+        .unwrap_or(false)
+}
+
+/// Error for a model that offline mode can't load, saying how to fetch it
+pub fn offline_error(what: &str, setup_args: &str) -> anyhow::Error {
+    anyhow!(
+        "Offline mode: {} is not in the model cache ({}).\n\
+         Pre-download it on a connected machine with `demongrep setup{}`, then copy the cache\n\
+         directory here or point FASTEMBED_CACHE_DIR at it.",
+        what,
+        model_cache_dir().display(),
+        setup_args
+    )
+}
 
 /// Available embedding models
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum ModelType {
     // === MiniLM Family ===
     /// All-MiniLM-L6-v2 - 384 dimensions, fast and efficient
@@ -218,18 +295,42 @@ impl FastEmbedder {
 
     /// Create a new embedder with specified model
     pub fn with_model(model_type: ModelType) -> Result<Self> {
+        if is_offline() && !is_model_cached(model_type) {
+            return Err(offline_error(
+                &format!("embedding model '{}'", model_type.short_name()),
+                &format!(" --model {} --no-reranker", model_type.short_name()),
+            ));
+        }
+
         info_print!("📦 Loading embedding model: {}", model_type.name());
         info_print!("   Dimensions: {}", model_type.dimensions());
 
-        // Use CPU execution provider with arena allocator for better memory performance
-        let cpu_ep = CPUExecutionProvider::default()
-            .with_arena_allocator(true)
-            .build();
+        let mut providers = Vec::new();
+        match device() {
+            Device::Cpu => {}
+            Device::Cuda => {
+                #[cfg(feature = "cuda")]
+                providers.push(ort::execution_providers::CUDAExecutionProvider::default().build());
+                #[cfg(not(feature = "cuda"))]
+                info_print!("⚠️  CUDA requested but this build lacks the `cuda` feature, using CPU");
+            }
+            Device::DirectML => {
+                info_print!("⚠️  DirectML is not supported by this build, using CPU");
+            }
+        }
+
+        // CPU execution provider with arena allocator for better memory performance
+        // (always registered last as the fallback)
+        providers.push(
+            CPUExecutionProvider::default()
+                .with_arena_allocator(true)
+                .build(),
+        );
 
         let model = TextEmbedding::try_new(
             InitOptions::new(model_type.to_fastembed_model())
                 .with_show_download_progress(true)
-                .with_execution_providers(vec![cpu_ep])
+                .with_execution_providers(providers)
         )
             .map_err(|e| anyhow!("Failed to initialize embedding model: {}", e))?;
 
@@ -1,10 +1,17 @@
 mod embedder;
 mod batch;
 mod cache;
-
-pub use embedder::{FastEmbedder, ModelType};
-pub use batch::{BatchEmbedder, EmbeddedChunk};
+mod query_cache;
+
+pub use embedder::{
+    is_model_cached, is_offline, is_repo_file_cached, model_cache_dir, model_code, model_repo_dir,
+    offline_error, set_device, set_offline, FastEmbedder, ModelType,
+};
+pub use batch::{
+    clean_docstring, count_tokens, embedding_text, estimate_tokens, BatchEmbedder, EmbeddedChunk, MAX_EMBED_TOKENS,
+};
 pub use cache::{CachedBatchEmbedder, CacheStats};
+pub use query_cache::{init_query_cache, query_cache};
 
 use anyhow::Result;
 use std::sync::{Arc, Mutex};
@@ -44,8 +51,57 @@ impl EmbeddingService {
         self.cached_embedder.embed_chunk(chunk)
     }
 
-    /// Embed query text
+    /// Embed query text, going through the persistent query cache when it is open
     pub fn embed_query(&mut self, query: &str) -> Result<Vec<f32>> {
+        let Some(cache) = query_cache() else {
+            return self.embed_query_uncached(query);
+        };
+        if let Some(embedding) = cache.get(self.model_type, query) {
+            return Ok(embedding);
+        }
+        let embedding = self.embed_query_uncached(query)?;
+        if let Err(e) = cache.put(self.model_type, query, &embedding) {
+            tracing::debug!("Query cache write failed: {}", e);
+        }
+        Ok(embedding)
+    }
+
+    /// Embed several queries, running the model once for all that aren't cached
+    pub fn embed_queries(&mut self, queries: &[String]) -> Result<Vec<Vec<f32>>> {
+        let cache = query_cache();
+        let mut embeddings: Vec<Option<Vec<f32>>> = queries
+            .iter()
+            .map(|query| cache.and_then(|cache| cache.get(self.model_type, query)))
+            .collect();
+
+        let missing: Vec<String> = queries
+            .iter()
+            .zip(&embeddings)
+            .filter(|(_, embedding)| embedding.is_none())
+            .map(|(query, _)| query.clone())
+            .collect();
+        if !missing.is_empty() {
+            let embedder_arc = &self.cached_embedder.batch_embedder.embedder;
+            let computed = embedder_arc.lock().unwrap().embed_batch(missing.clone())?;
+            let mut computed = missing.iter().zip(computed);
+            for slot in embeddings.iter_mut().filter(|embedding| embedding.is_none()) {
+                let Some((query, embedding)) = computed.next() else {
+                    anyhow::bail!("Fewer query embeddings than queries");
+                };
+                if let Some(cache) = cache {
+                    if let Err(e) = cache.put(self.model_type, query, &embedding) {
+                        tracing::debug!("Query cache write failed: {}", e);
+                    }
+                }
+                *slot = Some(embedding);
+            }
+        }
+
+        Ok(embeddings.into_iter().flatten().collect())
+    }
+
+    /// Embed query text with the model, bypassing the query cache
+    pub fn embed_query_uncached(&mut self, query: &str) -> Result<Vec<f32>> {
         // Access the batch embedder's embedder via mutex
         let embedder_arc = &self.cached_embedder.batch_embedder.embedder;
         embedder_arc.lock().unwrap().embed_one(query)
@@ -0,0 +1,184 @@
+//! Persistent query embedding cache
+//!
+//! Agents repeat the same queries a lot, and each repeat used to load the
+//! model and run inference again. Query embeddings are kept in a small LMDB
+//! table under the data directory, keyed by model and a hash of the query,
+//! so a repeated query skips the model entirely. When the table fills up it
+//! is cleared and starts over.
+
+use anyhow::Result;
+use heed::types::{Bytes, Str};
+use heed::{Database, Env, EnvOpenOptions};
+use sha2::{Digest, Sha256};
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::OnceLock;
+
+use super::cache::CacheStats;
+use super::ModelType;
+
+/// LMDB map size: roughly 40k 384-dim or 15k 1024-dim embeddings
+const MAP_SIZE: usize = 64 * 1024 * 1024;
+
+/// The process-wide cache (set once from config)
+static QUERY_CACHE: OnceLock<QueryCache> = OnceLock::new();
+
+/// Open the query cache in `dir` for this process (later calls are ignored)
+pub fn init_query_cache(dir: &Path) -> Result<()> {
+    if QUERY_CACHE.get().is_none() {
+        let _ = QUERY_CACHE.set(QueryCache::open(dir)?);
+    }
+    Ok(())
+}
+
+/// The process-wide query cache, if it was opened
+pub fn query_cache() -> Option<&'static QueryCache> {
+    QUERY_CACHE.get()
+}
+
+/// Key of a query's embedding (LMDB keys are capped at 511 bytes, so hash the query)
+fn cache_key(model_type: ModelType, query: &str) -> String {
+    format!("{}:{:x}", model_type.short_name(), Sha256::digest(query.as_bytes()))
+}
+
+fn encode(embedding: &[f32]) -> Vec<u8> {
+    embedding.iter().flat_map(|v| v.to_le_bytes()).collect()
+}
+
+fn decode(bytes: &[u8]) -> Vec<f32> {
+    bytes
+        .chunks_exact(4)
+        .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect()
+}
+
+/// Query embeddings stored on disk, keyed by (model, query)
+pub struct QueryCache {
+    env: Env,
+    db: Database<Str, Bytes>,
+    hits: AtomicUsize,
+    misses: AtomicUsize,
+}
+
+impl QueryCache {
+    /// Open or create the cache in `dir`
+    pub fn open(dir: &Path) -> Result<Self> {
+        std::fs::create_dir_all(dir)?;
+        let env = unsafe { EnvOpenOptions::new().map_size(MAP_SIZE).max_dbs(1).open(dir)? };
+
+        let mut wtxn = env.write_txn()?;
+        let db: Database<Str, Bytes> = env.create_database(&mut wtxn, Some("queries"))?;
+        wtxn.commit()?;
+
+        Ok(Self {
+            env,
+            db,
+            hits: AtomicUsize::new(0),
+            misses: AtomicUsize::new(0),
+        })
+    }
+
+    fn lookup(&self, model_type: ModelType, query: &str) -> Result<Option<Vec<f32>>> {
+        let rtxn = self.env.read_txn()?;
+        let embedding = self
+            .db
+            .get(&rtxn, &cache_key(model_type, query))?
+            // A model with a different size under the same name is a miss
+            .filter(|bytes| bytes.len() == model_type.dimensions() * 4)
+            .map(decode);
+        Ok(embedding)
+    }
+
+    /// Get a cached query embedding, counting the hit or miss
+    pub fn get(&self, model_type: ModelType, query: &str) -> Option<Vec<f32>> {
+        let embedding = self.lookup(model_type, query).ok().flatten();
+        let counter = if embedding.is_some() { &self.hits } else { &self.misses };
+        counter.fetch_add(1, Ordering::Relaxed);
+        embedding
+    }
+
+    fn insert(&self, key: &str, bytes: &[u8]) -> heed::Result<()> {
+        let mut wtxn = self.env.write_txn()?;
+        self.db.put(&mut wtxn, key, bytes)?;
+        wtxn.commit()
+    }
+
+    /// Store a query embedding, clearing the cache first if it is full
+    pub fn put(&self, model_type: ModelType, query: &str, embedding: &[f32]) -> Result<()> {
+        let key = cache_key(model_type, query);
+        let bytes = encode(embedding);
+        match self.insert(&key, &bytes) {
+            Err(heed::Error::Mdb(heed::MdbError::MapFull)) => {
+                self.clear()?;
+                self.insert(&key, &bytes)?;
+            }
+            result => result?,
+        }
+        Ok(())
+    }
+
+    /// Remove every cached embedding
+    pub fn clear(&self) -> Result<()> {
+        let mut wtxn = self.env.write_txn()?;
+        self.db.clear(&mut wtxn)?;
+        wtxn.commit()?;
+        Ok(())
+    }
+
+    /// Number of cached queries
+    pub fn len(&self) -> usize {
+        self.env
+            .read_txn()
+            .and_then(|rtxn| self.db.len(&rtxn))
+            .unwrap_or(0) as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Hits and misses in this process
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            size: self.len(),
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_query_cache_roundtrip() {
+        let dir = tempdir().unwrap();
+        let cache = QueryCache::open(dir.path()).unwrap();
+        let model = ModelType::AllMiniLML6V2Q;
+        let embedding: Vec<f32> = (0..model.dimensions()).map(|i| i as f32 / 100.0).collect();
+
+        assert!(cache.get(model, "find auth code").is_none());
+        cache.put(model, "find auth code", &embedding).unwrap();
+        assert_eq!(cache.get(model, "find auth code"), Some(embedding.clone()));
+        // Same query, different model: separate entry
+        assert!(cache.get(ModelType::BGESmallENV15, "find auth code").is_none());
+
+        let stats = cache.stats();
+        assert_eq!((stats.size, stats.hits, stats.misses), (1, 1, 2));
+
+        // Survives reopening
+        drop(cache);
+        let cache = QueryCache::open(dir.path()).unwrap();
+        assert_eq!(cache.get(model, "find auth code"), Some(embedding));
+        cache.clear().unwrap();
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn test_long_queries_fit_in_a_key() {
+        let key = cache_key(ModelType::default(), &"x".repeat(10_000));
+        assert!(key.len() < 100);
+    }
+}
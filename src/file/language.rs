@@ -28,6 +28,31 @@ pub enum Language {
 }
 
 impl Language {
+    /// Every known language except `Unknown`
+    pub const ALL: &'static [Language] = &[
+        Self::Rust,
+        Self::Python,
+        Self::JavaScript,
+        Self::TypeScript,
+        Self::Go,
+        Self::Java,
+        Self::C,
+        Self::Cpp,
+        Self::CSharp,
+        Self::Ruby,
+        Self::Php,
+        Self::Swift,
+        Self::Kotlin,
+        Self::Shell,
+        Self::Markdown,
+        Self::Json,
+        Self::Yaml,
+        Self::Toml,
+        Self::Sql,
+        Self::Html,
+        Self::Css,
+    ];
+
     /// Detect language from file extension
     pub fn from_path(path: &Path) -> Self {
         let extension = path
@@ -67,6 +92,44 @@ impl Language {
         }
     }
 
+    /// File extensions detected as this language (the inverse of `from_extension`)
+    pub fn extensions(&self) -> &'static [&'static str] {
+        match self {
+            Self::Rust => &["rs"],
+            Self::Python => &["py", "pyw", "pyi"],
+            Self::JavaScript => &["js", "mjs", "cjs"],
+            Self::TypeScript => &["ts", "mts", "cts", "tsx", "jsx"],
+            Self::Go => &["go"],
+            Self::Java => &["java"],
+            Self::C => &["c", "h"],
+            Self::Cpp => &["cpp", "cc", "cxx", "hpp", "hxx"],
+            Self::CSharp => &["cs"],
+            Self::Ruby => &["rb", "rake"],
+            Self::Php => &["php"],
+            Self::Swift => &["swift"],
+            Self::Kotlin => &["kt", "kts"],
+            Self::Shell => &["sh", "bash", "zsh"],
+            Self::Markdown => &["md", "markdown", "txt"],
+            Self::Json => &["json"],
+            Self::Yaml => &["yaml", "yml"],
+            Self::Toml => &["toml"],
+            Self::Sql => &["sql"],
+            Self::Html => &["html", "htm"],
+            Self::Css => &["css", "scss", "sass", "less"],
+            Self::Unknown => &[],
+        }
+    }
+
+    /// Language named in a query filter (`lang:rust`): a name, fence tag or extension
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name = name.to_lowercase();
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|lang| lang.name().to_lowercase() == name || lang.fence_tag() == name)
+            .or_else(|| Some(Self::from_extension(&name)).filter(|lang| *lang != Self::Unknown))
+    }
+
     /// Check if this language is supported for semantic chunking
     pub fn supports_tree_sitter(&self) -> bool {
         matches!(
@@ -118,6 +181,34 @@ impl Language {
             Self::Unknown => "Unknown",
         }
     }
+
+    /// Info string for a Markdown code fence (empty when unknown)
+    pub fn fence_tag(&self) -> &'static str {
+        match self {
+            Self::Rust => "rust",
+            Self::Python => "python",
+            Self::JavaScript => "javascript",
+            Self::TypeScript => "typescript",
+            Self::Go => "go",
+            Self::Java => "java",
+            Self::C => "c",
+            Self::Cpp => "cpp",
+            Self::CSharp => "csharp",
+            Self::Ruby => "ruby",
+            Self::Php => "php",
+            Self::Swift => "swift",
+            Self::Kotlin => "kotlin",
+            Self::Shell => "bash",
+            Self::Markdown => "markdown",
+            Self::Json => "json",
+            Self::Yaml => "yaml",
+            Self::Toml => "toml",
+            Self::Sql => "sql",
+            Self::Html => "html",
+            Self::Css => "css",
+            Self::Unknown => "",
+        }
+    }
 }
 
 #[cfg(test)]
@@ -156,6 +247,21 @@ mod tests {
         assert!(!Language::Json.supports_tree_sitter());
     }
 
+    #[test]
+    fn test_from_name() {
+        assert_eq!(Language::from_name("Rust"), Some(Language::Rust));
+        assert_eq!(Language::from_name("py"), Some(Language::Python));
+        assert_eq!(Language::from_name("c++"), Some(Language::Cpp));
+        assert_eq!(Language::from_name("csharp"), Some(Language::CSharp));
+        assert_eq!(Language::from_name("klingon"), None);
+
+        for &lang in Language::ALL {
+            for ext in lang.extensions() {
+                assert_eq!(Language::from_extension(ext), lang);
+            }
+        }
+    }
+
     #[test]
     fn test_indexable() {
         assert!(Language::Rust.is_indexable());
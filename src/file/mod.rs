@@ -1,4 +1,5 @@
 use anyhow::Result;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
 use ignore::WalkBuilder;
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
@@ -6,9 +7,11 @@ use tracing::{debug, info, warn};
 
 mod binary;
 mod language;
+mod packages;
 
 pub use binary::is_binary_file;
 pub use language::Language;
+pub use packages::PackageFinder;
 
 /// Information about a discovered file
 #[derive(Debug, Clone)]
@@ -71,6 +74,7 @@ pub struct FileWalker {
     root: PathBuf,
     respect_gitignore: bool,
     include_hidden: bool,
+    excludes: Option<Gitignore>,
 }
 
 impl FileWalker {
@@ -79,9 +83,26 @@ impl FileWalker {
             root: root.into(),
             respect_gitignore: true,
             include_hidden: false,
+            excludes: None,
         }
     }
 
+    /// Add extra ignore patterns (gitignore syntax), e.g. from config `indexing.excludes`
+    pub fn with_excludes(mut self, patterns: &[String]) -> Self {
+        if patterns.is_empty() {
+            return self;
+        }
+
+        let mut builder = GitignoreBuilder::new(&self.root);
+        for pattern in patterns {
+            if let Err(e) = builder.add_line(None, pattern) {
+                warn!("Invalid exclude pattern '{}': {}", pattern, e);
+            }
+        }
+        self.excludes = builder.build().ok();
+        self
+    }
+
     /// Set whether to respect .gitignore files (default: true)
     pub fn respect_gitignore(mut self, respect: bool) -> Self {
         self.respect_gitignore = respect;
@@ -174,6 +195,14 @@ impl FileWalker {
             return true;
         }
 
+        // Check configured exclude patterns
+        if let Some(ref excludes) = self.excludes {
+            let relative = path.strip_prefix(&self.root).unwrap_or(path);
+            if excludes.matched_path_or_any_parents(relative, false).is_ignore() {
+                return true;
+            }
+        }
+
         // Check if file is binary
         is_binary_file(path)
     }
@@ -259,6 +288,23 @@ mod tests {
         assert_eq!(stats.files_by_language.get(&Language::JavaScript), Some(&1));
     }
 
+    #[test]
+    fn test_config_excludes() {
+        let dir = TempDir::new().unwrap();
+
+        fs::create_dir(dir.path().join("generated")).unwrap();
+        fs::write(dir.path().join("generated").join("api.rs"), "fn api() {}").unwrap();
+        fs::write(dir.path().join("schema.gen.rs"), "fn gen() {}").unwrap();
+        fs::write(dir.path().join("main.rs"), "fn main() {}").unwrap();
+
+        let walker = FileWalker::new(dir.path())
+            .with_excludes(&["generated/".to_string(), "*.gen.rs".to_string()]);
+        let (files, _) = walker.walk().unwrap();
+
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].path.file_name().unwrap(), "main.rs");
+    }
+
     #[test]
     fn test_excluded_directories() {
         let dir = TempDir::new().unwrap();
@@ -0,0 +1,126 @@
+//! Package detection for monorepos (`search --package`)
+//!
+//! A file belongs to the package whose manifest is in its nearest ancestor
+//! directory: `Cargo.toml`, `package.json`, `go.mod` or `pyproject.toml`.
+//! The package name is the one the manifest declares, so `--package api`
+//! means the same thing as in the package manager. Manifests without a name
+//! (a Cargo workspace root, a private `package.json` stub) are skipped and
+//! the search goes on upwards.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Manifest files, in the order they are checked within one directory
+pub const MANIFESTS: &[&str] = &["Cargo.toml", "package.json", "go.mod", "pyproject.toml"];
+
+/// Package name declared by the manifest `file_name` with contents `text`
+pub fn manifest_name(file_name: &str, text: &str) -> Option<String> {
+    let name = match file_name {
+        "Cargo.toml" => {
+            let manifest: toml::Value = toml::from_str(text).ok()?;
+            manifest.get("package")?.get("name")?.as_str()?.to_string()
+        }
+        "pyproject.toml" => {
+            let manifest: toml::Value = toml::from_str(text).ok()?;
+            let poetry = || manifest.get("tool")?.get("poetry")?.get("name");
+            manifest.get("project").and_then(|project| project.get("name")).or_else(poetry)?.as_str()?.to_string()
+        }
+        "package.json" => {
+            let manifest: serde_json::Value = serde_json::from_str(text).ok()?;
+            manifest.get("name")?.as_str()?.to_string()
+        }
+        "go.mod" => {
+            let line = text.lines().map(str::trim).find(|line| line.starts_with("module "))?;
+            let module = line["module ".len()..].split("//").next()?.trim();
+            module.trim_matches('"').to_string()
+        }
+        _ => return None,
+    };
+    (!name.is_empty()).then_some(name)
+}
+
+/// Finds the package of files, reading each directory's manifests once
+#[derive(Clone, Default)]
+pub struct PackageFinder {
+    /// Directories above this one are not searched
+    root: Option<PathBuf>,
+    /// Package declared in each directory looked at so far
+    declared: HashMap<PathBuf, Option<String>>,
+}
+
+impl PackageFinder {
+    /// Stop looking for manifests at `root`, the directory being indexed
+    pub fn with_root(mut self, root: &Path) -> Self {
+        self.root = Some(root.to_path_buf());
+        self
+    }
+
+    /// Package declared in `dir` itself
+    fn declared_in(&mut self, dir: &Path) -> Option<String> {
+        if let Some(name) = self.declared.get(dir) {
+            return name.clone();
+        }
+        let name = MANIFESTS.iter().find_map(|manifest| {
+            let text = std::fs::read_to_string(dir.join(manifest)).ok()?;
+            manifest_name(manifest, &text)
+        });
+        self.declared.insert(dir.to_path_buf(), name.clone());
+        name
+    }
+
+    /// Name of the package `path` belongs to, if any
+    pub fn package_of(&mut self, path: &Path) -> Option<String> {
+        let mut dir = path.parent();
+        while let Some(current) = dir {
+            // "" is the working directory, already covered by "."
+            if current.as_os_str().is_empty() {
+                break;
+            }
+            if let Some(name) = self.declared_in(current) {
+                return Some(name);
+            }
+            if self.root.as_deref() == Some(current) {
+                break;
+            }
+            dir = current.parent();
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_manifest_name() {
+        assert_eq!(manifest_name("Cargo.toml", "[package]\nname = \"core\"\n").as_deref(), Some("core"));
+        assert_eq!(manifest_name("Cargo.toml", "[workspace]\nmembers = [\"core\"]\n"), None);
+        assert_eq!(manifest_name("package.json", r#"{"name": "@acme/web", "private": true}"#).as_deref(), Some("@acme/web"));
+        assert_eq!(
+            manifest_name("go.mod", "// gateway\nmodule github.com/acme/gateway // main module\n\ngo 1.22\n").as_deref(),
+            Some("github.com/acme/gateway")
+        );
+        assert_eq!(manifest_name("pyproject.toml", "[project]\nname = \"ml-tools\"\n").as_deref(), Some("ml-tools"));
+        assert_eq!(manifest_name("pyproject.toml", "[tool.poetry]\nname = \"legacy\"\n").as_deref(), Some("legacy"));
+        assert_eq!(manifest_name("package.json", "{ not json"), None);
+    }
+
+    #[test]
+    fn test_package_of() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        std::fs::write(root.join("Cargo.toml"), "[workspace]\nmembers = [\"crates/*\"]\n").unwrap();
+        std::fs::create_dir_all(root.join("crates/core/src/pool")).unwrap();
+        std::fs::write(root.join("crates/core/Cargo.toml"), "[package]\nname = \"core\"\n").unwrap();
+        std::fs::create_dir_all(root.join("web/src")).unwrap();
+        std::fs::write(root.join("web/package.json"), r#"{"name": "web"}"#).unwrap();
+
+        let mut finder = PackageFinder::default().with_root(root);
+        assert_eq!(finder.package_of(&root.join("crates/core/src/pool/mod.rs")).as_deref(), Some("core"));
+        assert_eq!(finder.package_of(&root.join("web/src/app.ts")).as_deref(), Some("web"));
+        // The workspace manifest declares no package
+        assert_eq!(finder.package_of(&root.join("build.rs")), None);
+    }
+}
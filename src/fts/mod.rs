@@ -5,4 +5,4 @@
 
 mod tantivy_store;
 
-pub use tantivy_store::{FtsStore, FtsResult};
+pub use tantivy_store::{escape_regex, FtsFilter, FtsResult, FtsStore};
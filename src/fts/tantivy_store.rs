@@ -1,17 +1,56 @@
 //! Tantivy-based full-text search store
 //!
 //! Provides BM25 full-text search for hybrid search with RRF fusion.
+//!
+//! Text is split and normalized by the analyzer `[fts]` in the config
+//! describes. The options are written to `fts/analyzer.json` when the index
+//! is created and read back whenever it is opened, so queries are analyzed
+//! the way the documents were. Indexes without the file use tantivy's
+//! default tokenizer, which is the default analyzer.
 
 use anyhow::{anyhow, Result};
+use serde::Serialize;
 use std::path::Path;
+use tantivy::tokenizer::{
+    AsciiFoldingFilter, Language, LowerCaser, NgramTokenizer, RemoveLongFilter, SimpleTokenizer, Stemmer,
+    TextAnalyzer,
+};
 use tantivy::{
-    collector::TopDocs,
+    collector::{DocSetCollector, TopDocs},
     directory::MmapDirectory,
-    query::QueryParser,
-    schema::{Field, Schema, STORED, STRING, TEXT, NumericOptions, Value},
+    query::{AllQuery, BooleanQuery, Occur, Query, QueryParser, RegexQuery, TermQuery},
+    schema::{
+        Facet, FacetOptions, Field, IndexRecordOption, NumericOptions, Schema, TextFieldIndexing, TextOptions, Value,
+        STORED, STRING,
+    },
     Index, IndexReader, IndexWriter, IndexSettings, TantivyDocument, Term,
 };
 
+use crate::config::FtsConfig;
+
+/// File in the index directory recording the analyzer options
+const ANALYZER_FILE: &str = "analyzer.json";
+
+/// Tokenizer for content, signatures and string literals
+const TEXT_TOKENIZER: &str = "demongrep";
+
+/// Tokenizer for signatures with `identifier_ngrams`
+const NGRAM_TOKENIZER: &str = "demongrep_ngram";
+
+/// Segment count above which `merge_if_fragmented` merges
+///
+/// Every commit adds at least one segment, and a query visits each one, so
+/// an index updated file by file (a watched project) slows down over time.
+pub const MAX_SEGMENTS: usize = 8;
+
+/// Weight of docstring matches relative to code matches
+///
+/// Doc comments state what code is for in the words a query uses, so a
+/// chunk whose docs match ranks above one that only mentions the terms in
+/// its body. The text rank is what fusion sees, so the boost carries over
+/// into hybrid results.
+pub const DOCSTRING_BOOST: f32 = 2.0;
+
 /// Result from FTS search
 #[derive(Debug, Clone)]
 pub struct FtsResult {
@@ -21,6 +60,102 @@ pub struct FtsResult {
     pub score: f32,
 }
 
+/// Restrictions on the chunks a search may return
+///
+/// A chunk must have one of `kinds` (when there are any), for every group
+/// in `path_patterns` a path matching one of the group's regular
+/// expressions, a path under the directory `under`, when `chunk_ids` is
+/// set, one of those IDs, one of `tags` (when there are any), none of
+/// `not_tags` and one of `packages` (when there are any).
+#[derive(Debug, Clone, Default)]
+pub struct FtsFilter {
+    pub kinds: Vec<String>,
+    pub path_patterns: Vec<Vec<String>>,
+    pub under: Option<String>,
+    pub chunk_ids: Option<Vec<u32>>,
+    pub tags: Vec<String>,
+    pub not_tags: Vec<String>,
+    pub packages: Vec<String>,
+}
+
+impl FtsFilter {
+    pub fn is_empty(&self) -> bool {
+        self.kinds.is_empty()
+            && self.path_patterns.is_empty()
+            && self.under.is_none()
+            && self.chunk_ids.is_none()
+            && self.tags.is_empty()
+            && self.not_tags.is_empty()
+            && self.packages.is_empty()
+    }
+}
+
+/// Escape the characters the regex syntax treats as special
+pub fn escape_regex(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if r"\.+*?()|[]{}^$#&-~".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Facet of a path's components (`./src/api/user.rs` -> `/src/api/user.rs`)
+///
+/// Tantivy indexes every ancestor of a facet, so a term for `/src/api`
+/// matches all files under `src/api`.
+fn path_facet(path: &str) -> Facet {
+    Facet::from_path(path.split('/').filter(|part| !part.is_empty() && *part != "."))
+}
+
+/// Indexing options for a text field analyzed by `tokenizer`
+fn text_options(tokenizer: &str) -> TextOptions {
+    TextOptions::default().set_indexing_options(
+        TextFieldIndexing::default()
+            .set_tokenizer(tokenizer)
+            .set_index_option(IndexRecordOption::WithFreqsAndPositions),
+    )
+}
+
+/// Register the tokenizers the schema refers to, configured by `options`
+fn register_analyzers(index: &Index, options: &FtsConfig) -> Result<()> {
+    let mut text = TextAnalyzer::builder(SimpleTokenizer::default()).filter_dynamic(RemoveLongFilter::limit(40));
+    let mut ngrams = TextAnalyzer::builder(NgramTokenizer::new(3, 3, false)?).dynamic();
+    if options.lowercase {
+        text = text.filter_dynamic(LowerCaser);
+        ngrams = ngrams.filter_dynamic(LowerCaser);
+    }
+    if options.ascii_folding {
+        text = text.filter_dynamic(AsciiFoldingFilter);
+        ngrams = ngrams.filter_dynamic(AsciiFoldingFilter);
+    }
+    if options.stemming {
+        text = text.filter_dynamic(Stemmer::new(Language::English));
+    }
+    index.tokenizers().register(TEXT_TOKENIZER, text.build());
+    index.tokenizers().register(NGRAM_TOKENIZER, ngrams.build());
+    Ok(())
+}
+
+/// Analyzer options recorded for the index at `fts_path`
+///
+/// Indexes from before the options were recorded use the defaults.
+fn recorded_analyzer(fts_path: &Path) -> Result<FtsConfig> {
+    let path = fts_path.join(ANALYZER_FILE);
+    if !path.exists() {
+        return Ok(FtsConfig::default());
+    }
+    serde_json::from_str(&std::fs::read_to_string(&path)?)
+        .map_err(|e| anyhow!("Invalid analyzer options in {:?}: {}", path, e))
+}
+
+/// The name fields, if the index has them
+fn name_fields(schema: &Schema) -> Option<(Field, Field)> {
+    Some((schema.get_field("name").ok()?, schema.get_field("name_lower").ok()?))
+}
+
 /// Full-text search store using Tantivy
 pub struct FtsStore {
     index: Index,
@@ -35,13 +170,36 @@ pub struct FtsStore {
     signature_field: Field,
     kind_field: Field,
     string_literals_field: Field,
+    /// Path components as a facet, for directory filters (None in old indexes)
+    dirs_field: Option<Field>,
+    /// Doc comments (None in old indexes)
+    docstring_field: Option<Field>,
+    /// Symbol name as written, and lowercased (None in old indexes)
+    name_fields: Option<(Field, Field)>,
+    /// Tags from `indexing.tags`, one term each (None in old indexes)
+    tags_field: Option<Field>,
+    /// Package the chunk's file belongs to (None in old indexes)
+    package_field: Option<Field>,
+    /// Analyzer options the index was built with
+    analyzer: FtsConfig,
 }
 
 impl FtsStore {
-    /// Create or open an FTS index at the given path
+    /// Create or open an FTS index at the given path, with the default analyzer
     pub fn new(db_path: &Path) -> Result<Self> {
+        Self::with_analyzer(db_path, &FtsConfig::default())
+    }
+
+    /// Create an FTS index analyzed as `options` says, or open an existing one
+    ///
+    /// An existing index keeps the analyzer it was created with; compare
+    /// `analyzer()` to `options` to find out whether it differs.
+    pub fn with_analyzer(db_path: &Path, options: &FtsConfig) -> Result<Self> {
         let fts_path = db_path.join("fts");
         std::fs::create_dir_all(&fts_path)?;
+        let exists = fts_path.join("meta.json").exists();
+        let analyzer = if exists { recorded_analyzer(&fts_path)? } else { options.clone() };
+        let signature_tokenizer = if analyzer.identifier_ngrams { NGRAM_TOKENIZER } else { TEXT_TOKENIZER };
 
         // Build schema
         let mut schema_builder = Schema::builder();
@@ -53,29 +211,56 @@ impl FtsStore {
         );
 
         // Content - full text indexed for BM25 search
-        let content_field = schema_builder.add_text_field("content", TEXT);
+        let content_field = schema_builder.add_text_field("content", text_options(TEXT_TOKENIZER));
 
         // Path - stored and string indexed for filtering
         let path_field = schema_builder.add_text_field("path", STRING | STORED);
 
         // Signature - indexed for function/method name search
-        let signature_field = schema_builder.add_text_field("signature", TEXT);
+        let signature_field = schema_builder.add_text_field("signature", text_options(signature_tokenizer));
 
         // Kind - stored for filtering (function, class, etc)
         let kind_field = schema_builder.add_text_field("kind", STRING | STORED);
 
         // String literals - indexed for literal value search
-        let string_literals_field = schema_builder.add_text_field("string_literals", TEXT);
+        let string_literals_field =
+            schema_builder.add_text_field("string_literals", text_options(TEXT_TOKENIZER));
+
+        // Path components - facet for filtering by directory
+        schema_builder.add_facet_field("dirs", FacetOptions::default());
+
+        // Docstring - indexed separately so its matches can be boosted
+        schema_builder.add_text_field("docstring", text_options(TEXT_TOKENIZER));
+
+        // Symbol name - untokenized, as written and lowercased, for exact and prefix lookups
+        schema_builder.add_text_field("name", STRING | STORED);
+        schema_builder.add_text_field("name_lower", STRING);
+
+        // Tags - untokenized, one value per tag, for --tag filters
+        schema_builder.add_text_field("tags", STRING);
+
+        // Package name - untokenized, for --package filters
+        schema_builder.add_text_field("package", STRING);
 
         let schema = schema_builder.build();
 
         // Open or create index
-        let index = if fts_path.join("meta.json").exists() {
+        let index = if exists {
             Index::open_in_dir(&fts_path)?
         } else {
             let dir = MmapDirectory::open(&fts_path)?;
-            Index::create(dir, schema.clone(), IndexSettings::default())?
+            let index = Index::create(dir, schema.clone(), IndexSettings::default())?;
+            std::fs::write(fts_path.join(ANALYZER_FILE), serde_json::to_string_pretty(&analyzer)?)?;
+            index
         };
+        register_analyzers(&index, &analyzer)?;
+
+        // Indexes created before these fields were added don't have them
+        let dirs_field = index.schema().get_field("dirs").ok();
+        let docstring_field = index.schema().get_field("docstring").ok();
+        let name_fields = name_fields(&index.schema());
+        let tags_field = index.schema().get_field("tags").ok();
+        let package_field = index.schema().get_field("package").ok();
 
         // Create reader for searching
         let reader = index.reader()?;
@@ -91,6 +276,12 @@ impl FtsStore {
             signature_field,
             kind_field,
             string_literals_field,
+            dirs_field,
+            docstring_field,
+            name_fields,
+            tags_field,
+            package_field,
+            analyzer,
         })
     }
 
@@ -103,6 +294,8 @@ impl FtsStore {
         }
 
         let index = Index::open_in_dir(&fts_path)?;
+        let analyzer = recorded_analyzer(&fts_path)?;
+        register_analyzers(&index, &analyzer)?;
         let schema = index.schema();
 
         let chunk_id_field = schema.get_field("chunk_id")
@@ -120,6 +313,11 @@ impl FtsStore {
                 // For backward compatibility with old indexes
                 schema.get_field("content").unwrap()
             });
+        let dirs_field = schema.get_field("dirs").ok();
+        let docstring_field = schema.get_field("docstring").ok();
+        let name_fields = name_fields(&schema);
+        let tags_field = schema.get_field("tags").ok();
+        let package_field = schema.get_field("package").ok();
 
         let reader = index.reader()?;
 
@@ -134,9 +332,20 @@ impl FtsStore {
             signature_field,
             kind_field,
             string_literals_field,
+            dirs_field,
+            docstring_field,
+            name_fields,
+            tags_field,
+            package_field,
+            analyzer,
         })
     }
 
+    /// Analyzer options the index was built with
+    pub fn analyzer(&self) -> &FtsConfig {
+        &self.analyzer
+    }
+
     /// Ensure writer is initialized for indexing
     fn ensure_writer(&mut self) -> Result<()> {
         if self.writer.is_none() {
@@ -148,14 +357,19 @@ impl FtsStore {
     }
 
     /// Add a chunk to the FTS index
+    #[allow(clippy::too_many_arguments)]
     pub fn add_chunk(
         &mut self,
         chunk_id: u32,
         content: &str,
         path: &str,
+        name: Option<&str>,
         signature: Option<&str>,
+        docstring: Option<&str>,
         kind: &str,
         string_literals: &[String],
+        tags: &[String],
+        package: Option<&str>,
     ) -> Result<()> {
         self.ensure_writer()?;
 
@@ -166,6 +380,11 @@ impl FtsStore {
         let signature_field = self.signature_field;
         let kind_field = self.kind_field;
         let string_literals_field = self.string_literals_field;
+        let dirs_field = self.dirs_field;
+        let docstring_field = self.docstring_field;
+        let name_fields = self.name_fields;
+        let tags_field = self.tags_field;
+        let package_field = self.package_field;
 
         let writer = self.writer.as_mut().unwrap();
 
@@ -174,11 +393,33 @@ impl FtsStore {
         doc.add_text(content_field, content);
         doc.add_text(path_field, path);
         doc.add_text(kind_field, kind);
+        if let Some(dirs_field) = dirs_field {
+            doc.add_facet(dirs_field, path_facet(path));
+        }
 
         if let Some(sig) = signature {
             doc.add_text(signature_field, sig);
         }
 
+        if let (Some(docstring), Some(docstring_field)) = (docstring, docstring_field) {
+            doc.add_text(docstring_field, docstring);
+        }
+
+        if let (Some(name), Some((name_field, name_lower_field))) = (name, name_fields) {
+            doc.add_text(name_field, name);
+            doc.add_text(name_lower_field, name.to_lowercase());
+        }
+
+        if let Some(tags_field) = tags_field {
+            for tag in tags {
+                doc.add_text(tags_field, tag);
+            }
+        }
+
+        if let (Some(package), Some(package_field)) = (package, package_field) {
+            doc.add_text(package_field, package);
+        }
+
         // Add string literals as a space-separated field for better search
         if !string_literals.is_empty() {
             let literals_text = string_literals.join(" ");
@@ -221,13 +462,160 @@ impl FtsStore {
 
     /// Search using BM25
     pub fn search(&self, query: &str, limit: usize) -> Result<Vec<FtsResult>> {
-        let searcher = self.reader.searcher();
+        let parsed_query = self.parse_query(query)?;
+        self.run_query(&*parsed_query, limit)
+    }
 
-        // Parse query against content, signature, and string_literals fields
-        let mut query_parser = QueryParser::for_index(
-            &self.index,
-            vec![self.content_field, self.signature_field, self.string_literals_field],
+    /// Search using BM25, restricted to chunks of one file
+    ///
+    /// `path` must match the indexed path exactly.
+    pub fn search_in_path(&self, query: &str, path: &str, limit: usize) -> Result<Vec<FtsResult>> {
+        let path_query = TermQuery::new(
+            Term::from_field_text(self.path_field, path),
+            IndexRecordOption::Basic,
         );
+        let query = BooleanQuery::new(vec![
+            (Occur::Must, self.parse_query(query)?),
+            (Occur::Must, Box::new(path_query)),
+        ]);
+        self.run_query(&query, limit)
+    }
+
+    /// Search using BM25, returning only chunks that pass `filter`
+    ///
+    /// Indexes without the path facet ignore `filter.under`; callers filter
+    /// those results by path afterwards.
+    pub fn search_filtered(&self, query: &str, filter: &FtsFilter, limit: usize) -> Result<Vec<FtsResult>> {
+        if filter.is_empty() {
+            return self.search(query, limit);
+        }
+        self.run_filtered(self.parse_query(query)?, filter, limit)
+    }
+
+    /// Chunks defining a symbol named `name` that pass `filter`
+    ///
+    /// Names matching case and all are listed first, then those matching
+    /// only when lowercased. Empty for indexes without the name fields.
+    pub fn search_name(&self, name: &str, filter: &FtsFilter, limit: usize) -> Result<Vec<FtsResult>> {
+        let Some((name_field, name_lower_field)) = self.name_fields else {
+            return Ok(Vec::new());
+        };
+        let term_query = |term: Term| Box::new(TermQuery::new(term, IndexRecordOption::Basic)) as Box<dyn Query>;
+
+        let mut results = self.run_filtered(term_query(Term::from_field_text(name_field, name)), filter, limit)?;
+        let lowered = Term::from_field_text(name_lower_field, &name.to_lowercase());
+        for result in self.run_filtered(term_query(lowered), filter, limit)? {
+            if results.len() < limit && !results.iter().any(|r| r.chunk_id == result.chunk_id) {
+                results.push(result);
+            }
+        }
+        Ok(results)
+    }
+
+    /// Chunks defining a symbol whose name starts with `prefix`, ignoring case
+    pub fn search_name_prefix(&self, prefix: &str, filter: &FtsFilter, limit: usize) -> Result<Vec<FtsResult>> {
+        let Some((_, name_lower_field)) = self.name_fields else {
+            return Ok(Vec::new());
+        };
+        let pattern = format!("{}.*", escape_regex(&prefix.to_lowercase()));
+        let query = RegexQuery::from_pattern(&pattern, name_lower_field)
+            .map_err(|e| anyhow!("Invalid name prefix '{}': {}", prefix, e))?;
+        self.run_filtered(Box::new(query), filter, limit)
+    }
+
+    /// Run `query`, restricted to chunks that pass `filter`
+    fn run_filtered(&self, query: Box<dyn Query>, filter: &FtsFilter, limit: usize) -> Result<Vec<FtsResult>> {
+        let mut clauses: Vec<(Occur, Box<dyn Query>)> = vec![(Occur::Must, query)];
+        if !filter.kinds.is_empty() {
+            let kinds: Vec<(Occur, Box<dyn Query>)> = filter
+                .kinds
+                .iter()
+                .map(|kind| {
+                    let term = Term::from_field_text(self.kind_field, kind);
+                    (Occur::Should, Box::new(TermQuery::new(term, IndexRecordOption::Basic)) as Box<dyn Query>)
+                })
+                .collect();
+            clauses.push((Occur::Must, Box::new(BooleanQuery::new(kinds))));
+        }
+        for group in &filter.path_patterns {
+            let mut paths: Vec<(Occur, Box<dyn Query>)> = Vec::with_capacity(group.len());
+            for pattern in group {
+                let regex = RegexQuery::from_pattern(pattern, self.path_field)
+                    .map_err(|e| anyhow!("Invalid path pattern '{}': {}", pattern, e))?;
+                paths.push((Occur::Should, Box::new(regex)));
+            }
+            clauses.push((Occur::Must, Box::new(BooleanQuery::new(paths))));
+        }
+        if let (Some(dir), Some(dirs_field)) = (&filter.under, self.dirs_field) {
+            let term = Term::from_facet(dirs_field, &path_facet(dir));
+            clauses.push((Occur::Must, Box::new(TermQuery::new(term, IndexRecordOption::Basic))));
+        }
+        if let Some(ref chunk_ids) = filter.chunk_ids {
+            let ids: Vec<(Occur, Box<dyn Query>)> = chunk_ids
+                .iter()
+                .map(|&id| {
+                    let term = Term::from_field_u64(self.chunk_id_field, u64::from(id));
+                    (Occur::Should, Box::new(TermQuery::new(term, IndexRecordOption::Basic)) as Box<dyn Query>)
+                })
+                .collect();
+            clauses.push((Occur::Must, Box::new(BooleanQuery::new(ids))));
+        }
+        match self.tags_field {
+            Some(tags_field) => {
+                let tag_query = |tag: &String| {
+                    Box::new(TermQuery::new(Term::from_field_text(tags_field, tag), IndexRecordOption::Basic))
+                        as Box<dyn Query>
+                };
+                if !filter.tags.is_empty() {
+                    let tags = filter.tags.iter().map(|tag| (Occur::Should, tag_query(tag))).collect();
+                    clauses.push((Occur::Must, Box::new(BooleanQuery::new(tags))));
+                }
+                clauses.extend(filter.not_tags.iter().map(|tag| (Occur::MustNot, tag_query(tag))));
+            }
+            // Nothing in an old index has tags
+            None if !filter.tags.is_empty() => return Ok(Vec::new()),
+            None => {}
+        }
+        if !filter.packages.is_empty() {
+            // Nothing in an old index has a package
+            let Some(package_field) = self.package_field else {
+                return Ok(Vec::new());
+            };
+            let packages = filter
+                .packages
+                .iter()
+                .map(|package| {
+                    let term = Term::from_field_text(package_field, package);
+                    (Occur::Should, Box::new(TermQuery::new(term, IndexRecordOption::Basic)) as Box<dyn Query>)
+                })
+                .collect();
+            clauses.push((Occur::Must, Box::new(BooleanQuery::new(packages))));
+        }
+        self.run_query(&BooleanQuery::new(clauses), limit)
+    }
+
+    /// Search signatures only (function, method and type declarations)
+    ///
+    /// A cheap, precise lookup by name: bodies, comments and string literals
+    /// that merely mention the name don't match.
+    pub fn search_signatures(&self, query: &str, limit: usize) -> Result<Vec<FtsResult>> {
+        let parsed_query = self.parse_query_in(query, vec![self.signature_field])?;
+        self.run_query(&*parsed_query, limit)
+    }
+
+    /// Parse a user query against the content, signature, string literal and docstring fields
+    fn parse_query(&self, query: &str) -> Result<Box<dyn Query>> {
+        let mut fields = vec![self.content_field, self.signature_field, self.string_literals_field];
+        fields.extend(self.docstring_field);
+        self.parse_query_in(query, fields)
+    }
+
+    /// Parse a user query against the given fields
+    fn parse_query_in(&self, query: &str, fields: Vec<Field>) -> Result<Box<dyn Query>> {
+        let mut query_parser = QueryParser::for_index(&self.index, fields);
+        if let Some(docstring_field) = self.docstring_field {
+            query_parser.set_field_boost(docstring_field, DOCSTRING_BOOST);
+        }
         
         // Set conjunction mode (AND) by default for multi-term queries
         // This makes "embedding model" require BOTH terms to be present
@@ -244,8 +632,13 @@ impl FtsStore {
             }
         };
 
-        // Execute search
-        let top_docs = searcher.search(&parsed_query, &TopDocs::with_limit(limit))?;
+        Ok(parsed_query)
+    }
+
+    /// Execute a query and map the top documents to chunk IDs
+    fn run_query(&self, query: &dyn Query, limit: usize) -> Result<Vec<FtsResult>> {
+        let searcher = self.reader.searcher();
+        let top_docs = searcher.search(query, &TopDocs::with_limit(limit))?;
 
         // Convert to results
         let mut results = Vec::with_capacity(top_docs.len());
@@ -275,6 +668,71 @@ impl FtsStore {
         })
     }
 
+    /// Number of searchable segments
+    pub fn segment_count(&self) -> usize {
+        self.reader.searcher().segment_readers().len()
+    }
+
+    /// Merge all segments into one and drop deleted documents
+    ///
+    /// Deletions only mark documents; they take disk space and slow queries
+    /// until their segment is merged. Files no longer used by the index are
+    /// removed afterwards. Uncommitted changes are discarded, so commit first.
+    pub fn optimize(&mut self) -> Result<OptimizeStats> {
+        // Background merges started by earlier commits would hold segments
+        if let Some(writer) = self.writer.take() {
+            writer.wait_merging_threads()?;
+        }
+        let searcher = self.reader.searcher();
+        let segments_before = searcher.segment_readers().len();
+        let deleted_documents: u64 =
+            searcher.segment_readers().iter().map(|segment| segment.num_deleted_docs() as u64).sum();
+
+        self.ensure_writer()?;
+        let segment_ids = self.index.searchable_segment_ids()?;
+        let writer = self.writer.as_mut().unwrap();
+        if segment_ids.len() > 1 || deleted_documents > 0 {
+            writer.merge(&segment_ids).wait()?;
+        }
+        writer.garbage_collect_files().wait()?;
+        // Let the merge finish writing before the writer goes away
+        if let Some(writer) = self.writer.take() {
+            writer.wait_merging_threads()?;
+        }
+        self.reader.reload()?;
+
+        Ok(OptimizeStats {
+            segments_before,
+            segments_after: self.segment_count(),
+            deleted_documents,
+        })
+    }
+
+    /// Optimize if the index has more than `MAX_SEGMENTS` segments
+    pub fn merge_if_fragmented(&mut self) -> Result<Option<OptimizeStats>> {
+        if self.segment_count() <= MAX_SEGMENTS {
+            return Ok(None);
+        }
+        self.optimize().map(Some)
+    }
+
+    /// Chunk IDs of every document
+    ///
+    /// An ID appears more than once if a chunk was indexed twice.
+    pub fn chunk_ids(&self) -> Result<Vec<u32>> {
+        let searcher = self.reader.searcher();
+        let addresses = searcher.search(&AllQuery, &DocSetCollector)?;
+
+        let mut ids = Vec::with_capacity(addresses.len());
+        for address in addresses {
+            let doc: TantivyDocument = searcher.doc(address)?;
+            if let Some(id) = doc.get_first(self.chunk_id_field).and_then(|v| v.as_u64()) {
+                ids.push(id as u32);
+            }
+        }
+        Ok(ids)
+    }
+
     /// Clear the entire index
     pub fn clear(&mut self) -> Result<()> {
         self.ensure_writer()?;
@@ -292,6 +750,15 @@ pub struct FtsStats {
     pub num_documents: usize,
 }
 
+/// What `FtsStore::optimize` did
+#[derive(Debug, Clone, Serialize)]
+pub struct OptimizeStats {
+    pub segments_before: usize,
+    pub segments_after: usize,
+    /// Deleted documents purged from disk
+    pub deleted_documents: u64,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -305,9 +772,9 @@ mod tests {
         let mut store = FtsStore::new(&db_path)?;
 
         // Add some chunks
-        store.add_chunk(1, "fn hello_world() { println!(\"Hello!\"); }", "src/main.rs", Some("hello_world"), "function", &["Hello!".to_string()])?;
-        store.add_chunk(2, "struct UserConfig { name: String, age: u32 }", "src/config.rs", Some("UserConfig"), "struct", &[])?;
-        store.add_chunk(3, "fn process_data(data: Vec<u8>) -> Result<()>", "src/processor.rs", Some("process_data"), "function", &[])?;
+        store.add_chunk(1, "fn hello_world() { println!(\"Hello!\"); }", "src/main.rs", None, Some("hello_world"), None, "function", &["Hello!".to_string()], &[], None)?;
+        store.add_chunk(2, "struct UserConfig { name: String, age: u32 }", "src/config.rs", None, Some("UserConfig"), None, "struct", &[], &[], None)?;
+        store.add_chunk(3, "fn process_data(data: Vec<u8>) -> Result<()>", "src/processor.rs", None, Some("process_data"), None, "function", &[], &[], None)?;
 
         store.commit()?;
 
@@ -329,6 +796,75 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_fts_search_filtered() -> Result<()> {
+        let dir = tempdir()?;
+        let mut store = FtsStore::new(dir.path())?;
+        store.add_chunk(1, "fn parse_config() {}", "src/config.rs", None, Some("parse_config"), None, "Function", &[], &[], None)?;
+        store.add_chunk(2, "struct Parse { config: Config }", "src/config.rs", None, Some("Parse"), None, "Struct", &[], &[], None)?;
+        let tools_tags = ["tools".to_string(), "generated".to_string()];
+        store.add_chunk(3, "def parse_config(): pass", "tools/config.py", None, Some("parse_config"), None, "Function", &[], &tools_tags, Some("tools"))?;
+        store.commit()?;
+
+        let ids = |filter: &FtsFilter| -> Result<Vec<u32>> {
+            let mut ids: Vec<u32> =
+                store.search_filtered("parse_config", filter, 10)?.iter().map(|r| r.chunk_id).collect();
+            ids.sort();
+            Ok(ids)
+        };
+        assert_eq!(ids(&FtsFilter::default())?, [1, 2, 3]);
+        assert_eq!(ids(&FtsFilter { kinds: vec!["Function".to_string()], ..Default::default() })?, [1, 3]);
+        let rust = FtsFilter { path_patterns: vec![vec![r".*\.rs".to_string()]], ..Default::default() };
+        assert_eq!(ids(&rust)?, [1, 2]);
+        assert_eq!(ids(&FtsFilter { kinds: vec!["Function".to_string()], ..rust.clone() })?, [1]);
+        let tools = FtsFilter { path_patterns: vec![vec!["tools/.*".to_string()], vec![r".*\.rs".to_string()]], ..Default::default() };
+        assert!(ids(&tools)?.is_empty());
+
+        let tagged = |tags: &[&str], not_tags: &[&str]| FtsFilter {
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            not_tags: not_tags.iter().map(|tag| tag.to_string()).collect(),
+            ..Default::default()
+        };
+        assert_eq!(ids(&tagged(&["api", "tools"], &[]))?, [3]);
+        assert_eq!(ids(&tagged(&[], &["generated"]))?, [1, 2]);
+        assert!(ids(&tagged(&["tools"], &["generated"]))?.is_empty());
+
+        let packages = |packages: &[&str]| FtsFilter {
+            packages: packages.iter().map(|package| package.to_string()).collect(),
+            ..Default::default()
+        };
+        assert_eq!(ids(&packages(&["tools", "web"]))?, [3]);
+        assert!(ids(&packages(&["web"]))?.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_fts_filter_under() -> Result<()> {
+        let dir = tempdir()?;
+        let mut store = FtsStore::new(dir.path())?;
+        store.add_chunk(1, "fn retry_upload() {}", "./src/api/upload.rs", None, None, None, "Function", &[], &[], None)?;
+        store.add_chunk(2, "fn retry_upload() {}", "src/api_v2/upload.rs", None, None, None, "Function", &[], &[], None)?;
+        store.add_chunk(3, "fn retry_upload() {}", "src/main.rs", None, None, None, "Function", &[], &[], None)?;
+        store.commit()?;
+
+        let under = |dir: &str| -> Result<Vec<u32>> {
+            let filter = FtsFilter { under: Some(dir.to_string()), ..Default::default() };
+            let mut ids: Vec<u32> =
+                store.search_filtered("retry_upload", &filter, 10)?.iter().map(|r| r.chunk_id).collect();
+            ids.sort();
+            Ok(ids)
+        };
+        // Whole components only: src/api doesn't take in src/api_v2
+        assert_eq!(under("src/api")?, [1]);
+        assert_eq!(under("./src/api/")?, [1]);
+        assert_eq!(under("src")?, [1, 2, 3]);
+        assert_eq!(under("src/main.rs")?, [3]);
+        assert!(under("tests")?.is_empty());
+
+        Ok(())
+    }
+
     #[test]
     fn test_fts_delete() -> Result<()> {
         let dir = tempdir()?;
@@ -336,8 +872,8 @@ mod tests {
 
         let mut store = FtsStore::new(&db_path)?;
 
-        store.add_chunk(1, "test content one", "file1.rs", None, "block", &[])?;
-        store.add_chunk(2, "test content two", "file2.rs", None, "block", &[])?;
+        store.add_chunk(1, "test content one", "file1.rs", None, None, None, "block", &[], &[], None)?;
+        store.add_chunk(2, "test content two", "file2.rs", None, None, None, "block", &[], &[], None)?;
         store.commit()?;
 
         // Should find both
@@ -356,6 +892,118 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_fts_optimize() -> Result<()> {
+        let dir = tempdir()?;
+        let mut store = FtsStore::new(dir.path())?;
+        // One commit per file, like a watched project
+        for file in 0..4 {
+            for id in [file * 2 + 1, file * 2 + 2] {
+                store.add_chunk(id, "retry the upload", &format!("src/file{}.rs", file), None, None, None, "Function", &[], &[], None)?;
+            }
+            store.commit()?;
+        }
+        store.delete_chunk(2)?;
+        store.commit()?;
+        assert!(store.segment_count() > 1);
+
+        let stats = store.optimize()?;
+        assert_eq!(stats.segments_after, 1);
+        assert_eq!(stats.deleted_documents, 1);
+        let mut ids: Vec<u32> = store.search("upload", 10)?.iter().map(|r| r.chunk_id).collect();
+        ids.sort();
+        assert_eq!(ids, [1, 3, 4, 5, 6, 7, 8]);
+        assert!(store.merge_if_fragmented()?.is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_fts_search_name() -> Result<()> {
+        let dir = tempdir()?;
+        let mut store = FtsStore::new(dir.path())?;
+        store.add_chunk(1, "fn parse() {}", "src/a.rs", Some("parse"), Some("fn parse()"), None, "Function", &[], &[], None)?;
+        store.add_chunk(2, "class Parse: pass", "tools/b.py", Some("Parse"), Some("class Parse"), None, "Class", &[], &[], None)?;
+        store.add_chunk(3, "fn parse_config() {}", "src/c.rs", Some("parse_config"), None, None, "Function", &[], &[], None)?;
+        store.commit()?;
+
+        let ids = |results: Vec<FtsResult>| -> Vec<u32> { results.iter().map(|r| r.chunk_id).collect() };
+        let all = FtsFilter::default();
+        // Matching case first, then ignoring it; names aren't split into words
+        assert_eq!(ids(store.search_name("Parse", &all, 10)?), [2, 1]);
+        assert_eq!(ids(store.search_name("parse", &all, 10)?), [1, 2]);
+        assert!(store.search_name("config", &all, 10)?.is_empty());
+        let mut prefixed = ids(store.search_name_prefix("PARSE", &all, 10)?);
+        prefixed.sort();
+        assert_eq!(prefixed, [1, 2, 3]);
+        let rust = FtsFilter { path_patterns: vec![vec![r".*\.rs".to_string()]], ..Default::default() };
+        assert_eq!(ids(store.search_name("Parse", &rust, 10)?), [1]);
+        let refined = FtsFilter { chunk_ids: Some(vec![2, 3]), ..Default::default() };
+        let mut prefixed = ids(store.search_name_prefix("parse", &refined, 10)?);
+        prefixed.sort();
+        assert_eq!(prefixed, [2, 3]);
+        let nothing = FtsFilter { chunk_ids: Some(Vec::new()), ..Default::default() };
+        assert!(store.search_name("parse", &nothing, 10)?.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_fts_docstring_boost() -> Result<()> {
+        let dir = tempdir()?;
+        let mut store = FtsStore::new(dir.path())?;
+        store.add_chunk(1, "// retry the upload later\nfn schedule() {}", "src/a.rs", None, None, None, "Function", &[], &[], None)?;
+        store.add_chunk(
+            2,
+            "fn send_chunks() {}",
+            "src/b.rs",
+            None,
+            None,
+            Some("/// Retry the upload when the connection drops"),
+            "Function",
+            &[],
+            &[],
+            None,
+        )?;
+        store.commit()?;
+
+        // The docstring match ranks first; docs alone are enough to match
+        let ids: Vec<u32> = store.search("retry upload", 10)?.iter().map(|r| r.chunk_id).collect();
+        assert_eq!(ids, [2, 1]);
+        assert_eq!(store.search("connection drops", 10)?[0].chunk_id, 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_fts_analyzer_options() -> Result<()> {
+        let add = |store: &mut FtsStore| -> Result<()> {
+            store.add_chunk(1, "Parses the café menu", "src/menu.rs", None, Some("fn parse_menu_items()"), None, "Function", &[], &[], None)?;
+            store.commit()
+        };
+
+        let dir = tempdir()?;
+        let options = FtsConfig { stemming: true, ascii_folding: true, identifier_ngrams: true, ..Default::default() };
+        add(&mut FtsStore::with_analyzer(dir.path(), &options)?)?;
+
+        // Reopening applies the recorded analyzer, whatever is asked for
+        let store = FtsStore::open_readonly(dir.path())?;
+        assert_eq!(store.analyzer(), &options);
+        assert_eq!(store.search("parsing cafe", 10)?.len(), 1);
+        assert_eq!(store.search_signatures("menu_item", 10)?.len(), 1);
+        assert_eq!(FtsStore::new(dir.path())?.analyzer(), &options);
+
+        // The default analyzer matches whole words as written
+        let dir = tempdir()?;
+        add(&mut FtsStore::new(dir.path())?)?;
+        let store = FtsStore::open_readonly(dir.path())?;
+        assert!(store.search("parsing", 10)?.is_empty());
+        assert!(store.search_signatures("menu_item", 10)?.is_empty());
+        assert_eq!(store.search("PARSES café", 10)?.len(), 1);
+
+        Ok(())
+    }
+
     #[test]
     fn test_fts_string_literals() -> Result<()> {
         let dir = tempdir()?;
@@ -367,18 +1015,26 @@ mod tests {
         store.add_chunk(
             1, 
             "requestHeaders = [(\"API-VERSION\", \"2\")]", 
-            "src/api.rs", 
+            "src/api.rs",
+            None, 
+            None,
             None, 
             "block",
             &["API-VERSION".to_string(), "2".to_string()],
+            &[],
+            None,
         )?;
         store.add_chunk(
             2, 
             "const version = \"1.0\";", 
-            "src/version.rs", 
+            "src/version.rs",
+            None, 
+            None,
             None, 
             "block",
             &["1.0".to_string()],
+            &[],
+            None,
         )?;
         store.commit()?;
 
@@ -388,4 +1044,41 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_fts_search_signatures() -> Result<()> {
+        let dir = tempdir()?;
+        let db_path = dir.path().to_path_buf();
+
+        let mut store = FtsStore::new(&db_path)?;
+
+        store.add_chunk(1, "fn validate_token(t: &str) -> bool { true }", "src/auth.rs", None, Some("fn validate_token(t: &str) -> bool"), None, "function", &[], &[], None)?;
+        store.add_chunk(2, "fn login() { validate_token(\"x\"); }", "src/login.rs", None, Some("fn login()"), None, "function", &[], &[], None)?;
+        store.commit()?;
+
+        // The call site mentions the name, but only the definition matches
+        let results = store.search_signatures("validate_token", 10)?;
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].chunk_id, 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_fts_chunk_ids() -> Result<()> {
+        let dir = tempdir()?;
+        let db_path = dir.path().to_path_buf();
+
+        let mut store = FtsStore::new(&db_path)?;
+        store.add_chunk(1, "fn a() {}", "a.rs", None, None, None, "function", &[], &[], None)?;
+        store.add_chunk(2, "fn b() {}", "b.rs", None, None, None, "function", &[], &[], None)?;
+        store.add_chunk(2, "fn b() {}", "b.rs", None, None, None, "function", &[], &[], None)?;
+        store.commit()?;
+
+        let mut ids = store.chunk_ids()?;
+        ids.sort();
+        assert_eq!(ids, [1, 2, 2]);
+
+        Ok(())
+    }
 }
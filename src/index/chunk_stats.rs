@@ -0,0 +1,174 @@
+use colored::Colorize;
+
+use crate::embed::MAX_EMBED_TOKENS;
+
+/// Chunks with fewer estimated tokens than this carry little signal on their own
+const TINY_CHUNK_TOKENS: usize = 32;
+
+/// Upper bounds (exclusive) of the token histogram buckets; the last bucket is open-ended
+const TOKEN_BUCKETS: &[usize] = &[TINY_CHUNK_TOKENS, 64, 128, 256, MAX_EMBED_TOKENS];
+
+/// Share of truncated or tiny chunks above which `stats --chunks` suggests retuning
+const TUNING_HINT_RATIO: f64 = 0.1;
+
+/// Summary of one size measure across all chunks
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Distribution {
+    pub min: usize,
+    pub p50: usize,
+    pub p90: usize,
+    pub p99: usize,
+    pub max: usize,
+    pub mean: f64,
+}
+
+impl Distribution {
+    fn from_values(mut values: Vec<usize>) -> Self {
+        if values.is_empty() {
+            return Self::default();
+        }
+        values.sort_unstable();
+
+        let percentile = |p: usize| values[(values.len() - 1) * p / 100];
+        Self {
+            min: values[0],
+            p50: percentile(50),
+            p90: percentile(90),
+            p99: percentile(99),
+            max: values[values.len() - 1],
+            mean: values.iter().sum::<usize>() as f64 / values.len() as f64,
+        }
+    }
+}
+
+/// Chunk size distribution for `demongrep stats --chunks`
+#[derive(Debug, Clone, Default)]
+pub struct ChunkSizeStats {
+    pub chunks: usize,
+    pub chars: Distribution,
+    pub tokens: Distribution,
+    /// Chunks longer than the model's input and therefore truncated when embedded
+    pub truncated: usize,
+    /// Chunks under `TINY_CHUNK_TOKENS`
+    pub tiny: usize,
+    /// (label, count) per token bucket
+    pub histogram: Vec<(String, usize)>,
+}
+
+impl ChunkSizeStats {
+    /// Summarize (chars, estimated tokens) pairs as returned by `VectorStore::chunk_sizes`
+    pub fn from_sizes(sizes: &[(usize, usize)]) -> Self {
+        let tokens: Vec<usize> = sizes.iter().map(|&(_, t)| t).collect();
+
+        let mut histogram: Vec<(String, usize)> = Vec::with_capacity(TOKEN_BUCKETS.len() + 1);
+        let mut lower = 0;
+        for &upper in TOKEN_BUCKETS {
+            let count = tokens.iter().filter(|&&t| t >= lower && t < upper).count();
+            histogram.push((format!("{}-{}", lower, upper - 1), count));
+            lower = upper;
+        }
+        let count = tokens.iter().filter(|&&t| t >= lower).count();
+        histogram.push((format!("{}+", lower), count));
+
+        Self {
+            chunks: sizes.len(),
+            chars: Distribution::from_values(sizes.iter().map(|&(c, _)| c).collect()),
+            truncated: tokens.iter().filter(|&&t| t > MAX_EMBED_TOKENS).count(),
+            tiny: tokens.iter().filter(|&&t| t < TINY_CHUNK_TOKENS).count(),
+            tokens: Distribution::from_values(tokens),
+            histogram,
+        }
+    }
+
+    fn ratio(&self, count: usize) -> f64 {
+        if self.chunks == 0 {
+            0.0
+        } else {
+            count as f64 / self.chunks as f64
+        }
+    }
+
+    /// Print the distribution, histogram and tuning hints
+    pub fn print(&self) {
+        println!("\n{}", "Chunk sizes:".bright_green());
+        if self.chunks == 0 {
+            println!("   No chunks");
+            return;
+        }
+
+        let row = |label: &str, d: &Distribution| {
+            println!(
+                "   {:<7} min {}, median {}, p90 {}, p99 {}, max {}, mean {:.0}",
+                label, d.min, d.p50, d.p90, d.p99, d.max, d.mean
+            );
+        };
+        row("Chars:", &self.chars);
+        row("Tokens:", &self.tokens);
+        println!("   (tokens are estimated; the model reads at most {})", MAX_EMBED_TOKENS);
+
+        println!("\n   {:<10} {:>8}", "Tokens", "Chunks");
+        let widest = self.histogram.iter().map(|(_, n)| *n).max().unwrap_or(0).max(1);
+        for (label, count) in &self.histogram {
+            let bar = "█".repeat(count * 30 / widest);
+            println!("   {:<10} {:>8}  {}", label, count, bar.dimmed());
+        }
+
+        println!();
+        println!(
+            "   Truncated (>{} tokens): {} ({:.1}%)",
+            MAX_EMBED_TOKENS,
+            self.truncated,
+            self.ratio(self.truncated) * 100.0
+        );
+        println!(
+            "   Tiny (<{} tokens): {} ({:.1}%)",
+            TINY_CHUNK_TOKENS,
+            self.tiny,
+            self.ratio(self.tiny) * 100.0
+        );
+
+        if self.ratio(self.truncated) > TUNING_HINT_RATIO {
+            println!(
+                "   {}",
+                "⚠️  Many chunks are cut off when embedded; lower indexing.max_chunk_lines / max_chunk_chars".yellow()
+            );
+        }
+        if self.ratio(self.tiny) > TUNING_HINT_RATIO {
+            println!(
+                "   {}",
+                "⚠️  Many chunks are tiny; try indexing.gap_strategy = \"merge\" or larger chunk limits".yellow()
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chunk_size_stats() {
+        let sizes = [(40, 10), (400, 100), (800, 200), (4000, 1000)];
+        let stats = ChunkSizeStats::from_sizes(&sizes);
+
+        assert_eq!(stats.chunks, 4);
+        assert_eq!(stats.tokens.min, 10);
+        assert_eq!(stats.tokens.max, 1000);
+        assert_eq!(stats.tokens.p50, 100);
+        assert_eq!(stats.chars.mean, 1310.0);
+        assert_eq!(stats.truncated, 1);
+        assert_eq!(stats.tiny, 1);
+
+        assert_eq!(stats.histogram.len(), TOKEN_BUCKETS.len() + 1);
+        assert_eq!(stats.histogram[0], ("0-31".to_string(), 1));
+        assert_eq!(stats.histogram.last().unwrap(), &("512+".to_string(), 1));
+        assert_eq!(stats.histogram.iter().map(|(_, n)| n).sum::<usize>(), 4);
+    }
+
+    #[test]
+    fn test_empty_sizes() {
+        let stats = ChunkSizeStats::from_sizes(&[]);
+        assert_eq!(stats.chunks, 0);
+        assert_eq!(stats.tokens, Distribution::default());
+    }
+}
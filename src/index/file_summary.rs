@@ -0,0 +1,144 @@
+//! File summaries for coarse-to-fine search (`search --coarse-to-fine`)
+//!
+//! Broad questions ("how is billing wired up?") match whole files better
+//! than any one chunk: the answer is spread over a module's definitions, and
+//! each chunk only holds a piece of it. Every indexed file therefore also
+//! gets one embedded summary document, made of its path, the symbols it
+//! defines and the first sentence of their doc comments. A coarse-to-fine
+//! search ranks files by these summaries first and then ranks chunks only
+//! within the best files. Summaries are rebuilt from the stored chunks,
+//! so they follow the file's content hash rather than being part of the
+//! chunking pipeline.
+
+use anyhow::Result;
+
+use crate::chunker::{Chunk, ChunkKind};
+use crate::embed::{clean_docstring, EmbeddingService};
+use crate::vectordb::{ChunkMetadata, FileVector, VectorStore};
+
+/// Longest summary; the embedding model reads about this much anyway
+const MAX_SUMMARY_CHARS: usize = 2000;
+
+/// Symbol of a stored chunk, qualified by its parents (`Server.start`)
+fn qualified_name(context: &str) -> Option<String> {
+    Chunk::name_from_context(context)?;
+    let names: Vec<&str> = context
+        .split(" > ")
+        .filter(|crumb| !crumb.starts_with("File: "))
+        .filter_map(|crumb| crumb.split_once(": ").map(|(_, name)| name))
+        .collect();
+    Some(names.join("."))
+}
+
+/// The summary document of the file at `path` with these chunks
+///
+/// Symbols come first, so a long file's symbol list survives the cut to
+/// `MAX_SUMMARY_CHARS`; doc comments fill what is left.
+pub fn file_summary(path: &str, chunks: &[ChunkMetadata]) -> String {
+    let mut symbols: Vec<String> = Vec::new();
+    let mut docs: Vec<String> = Vec::new();
+    for chunk in chunks {
+        let Some(name) = chunk.context.as_deref().and_then(qualified_name) else {
+            continue;
+        };
+        if symbols.contains(&name) {
+            continue;
+        }
+        if let Some(doc) = chunk.docstring.as_deref().map(clean_docstring) {
+            let sentence = doc.split_inclusive(". ").next().unwrap_or(&doc).trim();
+            if !sentence.is_empty() {
+                docs.push(format!("{}: {}", name, sentence));
+            }
+        }
+        symbols.push(name);
+    }
+
+    let mut summary = format!("File: {}", path.trim_start_matches("./"));
+    if !symbols.is_empty() {
+        summary.push_str(&format!("\nSymbols: {}", symbols.join(", ")));
+    }
+    for doc in docs {
+        if summary.len() + doc.len() + 1 > MAX_SUMMARY_CHARS {
+            break;
+        }
+        summary.push('\n');
+        summary.push_str(&doc);
+    }
+    if summary.len() > MAX_SUMMARY_CHARS {
+        let mut end = MAX_SUMMARY_CHARS;
+        while !summary.is_char_boundary(end) {
+            end -= 1;
+        }
+        summary.truncate(end);
+    }
+    summary
+}
+
+/// Embed the summaries of files that are new or changed since they were summarized
+///
+/// Returns the number of files summarized.
+pub fn refresh_file_vectors(store: &mut VectorStore, embedding_service: &mut EmbeddingService) -> Result<usize> {
+    let mut hashes = Vec::new();
+    let mut documents = Vec::new();
+    for (path, meta) in store.stale_file_vectors()? {
+        let mut chunks = Vec::with_capacity(meta.chunk_ids.len());
+        for id in &meta.chunk_ids {
+            chunks.extend(store.get_chunk(*id)?);
+        }
+        if chunks.is_empty() {
+            continue;
+        }
+        let summary = file_summary(&path, &chunks);
+        documents.push(Chunk::new(summary, 0, 0, ChunkKind::Anchor, path.clone()));
+        hashes.push((path, meta.hash));
+    }
+    if documents.is_empty() {
+        return Ok(0);
+    }
+
+    let embedded = embedding_service.embed_chunks(documents)?;
+    let vectors: Vec<(String, FileVector)> = hashes
+        .into_iter()
+        .zip(embedded)
+        .map(|((path, hash), embedded)| (path, FileVector { hash, embedding: embedded.embedding }))
+        .collect();
+    store.put_file_vectors(&vectors)?;
+    Ok(vectors.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk(context: &str, docstring: Option<&str>) -> ChunkMetadata {
+        ChunkMetadata {
+            context: Some(context.to_string()),
+            docstring: docstring.map(str::to_string),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_file_summary() {
+        let chunks = vec![
+            chunk("File: src/billing.rs", None),
+            chunk(
+                "File: src/billing.rs > Struct: Invoice",
+                Some("/// An invoice for one billing period. Amounts are in cents."),
+            ),
+            chunk("File: src/billing.rs > Impl: Invoice > Method: total", Some("/// Sum of all line items")),
+            chunk("File: src/billing.rs > Function: round_cents", None),
+        ];
+        assert_eq!(
+            file_summary("./src/billing.rs", &chunks),
+            "File: src/billing.rs\n\
+             Symbols: Invoice, Invoice.total, round_cents\n\
+             Invoice: An invoice for one billing period.\n\
+             Invoice.total: Sum of all line items"
+        );
+
+        let many: Vec<ChunkMetadata> =
+            (0..500).map(|i| chunk(&format!("File: a.rs > Function: handler_{}", i), Some("/// Does things"))).collect();
+        assert!(file_summary("a.rs", &many).len() <= MAX_SUMMARY_CHARS);
+    }
+}
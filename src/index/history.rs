@@ -0,0 +1,122 @@
+//! Commit history as a search namespace (`index --with-history`)
+//!
+//! Code says what a program does, commit messages say why it changed. With
+//! `--with-history N`, the last N commit messages are embedded together with
+//! the files each commit touched, and `search --in history` ranks them
+//! instead of code, so "why was the retry logic changed" finds the commit
+//! that changed it. Commits live apart from chunks and never show up in code
+//! search. Re-running the command embeds only commits that are new since the
+//! last run and drops the ones that fell out of the window.
+
+use anyhow::{bail, Result};
+use std::path::Path;
+
+use super::staleness::git;
+use crate::chunker::{Chunk, ChunkKind};
+use crate::embed::EmbeddingService;
+use crate::vectordb::{CommitRecord, VectorStore};
+
+/// Files listed in a commit's document; the rest are only counted
+const MAX_DOCUMENT_FILES: usize = 20;
+
+/// Separators `git log` is asked to print around commits and fields
+const COMMIT_SEPARATOR: char = '\x1e';
+const FIELD_SEPARATOR: char = '\x1f';
+
+/// Commits (without embeddings) from `git log --name-only` in the format below
+fn parse_log(output: &str) -> Vec<CommitRecord> {
+    output
+        .split(COMMIT_SEPARATOR)
+        .filter_map(|entry| {
+            let mut fields = entry.splitn(5, FIELD_SEPARATOR);
+            let hash = fields.next()?.trim().to_string();
+            let author = fields.next()?.trim().to_string();
+            let timestamp = fields.next()?.trim().parse().ok()?;
+            let message = fields.next()?.trim().to_string();
+            let files = fields.next().unwrap_or("").lines().map(str::trim).filter(|l| !l.is_empty()).map(String::from);
+            Some(CommitRecord { hash, author, timestamp, message, files: files.collect(), embedding: Vec::new() })
+        })
+        .collect()
+}
+
+/// The last `count` commits of the repository at `root`, newest first
+///
+/// Merge commits are left out: their messages name branches, not changes.
+fn recent_commits(root: &Path, count: usize) -> Result<Vec<CommitRecord>> {
+    let format = format!("--format={c}%H{f}%an{f}%at{f}%B{f}", c = COMMIT_SEPARATOR, f = FIELD_SEPARATOR);
+    let Some(output) = git(root, &["log", "--no-merges", "--name-only", &format, "-n", &count.to_string()]) else {
+        bail!("Could not read the git history of {}", root.display());
+    };
+    Ok(parse_log(&output))
+}
+
+/// The text embedded for a commit: its message, then the files it touched
+fn commit_document(commit: &CommitRecord) -> String {
+    let mut document = commit.message.clone();
+    if !commit.files.is_empty() {
+        let shown: Vec<&str> = commit.files.iter().take(MAX_DOCUMENT_FILES).map(String::as_str).collect();
+        document.push_str(&format!("\n\nFiles: {}", shown.join(", ")));
+        if commit.files.len() > MAX_DOCUMENT_FILES {
+            document.push_str(&format!(" and {} more", commit.files.len() - MAX_DOCUMENT_FILES));
+        }
+    }
+    document
+}
+
+/// Bring the history namespace to the last `count` commits of `root`
+///
+/// Returns the number of commits embedded and the number in the namespace.
+pub fn index_history(
+    store: &mut VectorStore,
+    embedding_service: &mut EmbeddingService,
+    root: &Path,
+    count: usize,
+) -> Result<(usize, usize)> {
+    let commits = recent_commits(root, count)?;
+    let stored = store.commit_hashes()?;
+
+    let (mut new, kept): (Vec<CommitRecord>, Vec<CommitRecord>) =
+        commits.into_iter().partition(|commit| !stored.contains(&commit.hash));
+    let total = new.len() + kept.len();
+    let removed: Vec<String> = stored
+        .into_iter()
+        .filter(|hash| !kept.iter().any(|commit| &commit.hash == hash))
+        .collect();
+
+    if !new.is_empty() {
+        let documents: Vec<Chunk> = new
+            .iter()
+            .map(|commit| Chunk::new(commit_document(commit), 0, 0, ChunkKind::Anchor, commit.hash.clone()))
+            .collect();
+        for (commit, embedded) in new.iter_mut().zip(embedding_service.embed_chunks(documents)?) {
+            commit.embedding = embedded.embedding;
+        }
+    }
+    store.update_commits(&new, &removed)?;
+    Ok((new.len(), total))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_log() {
+        let output = "\x1eabc123\x1fAda\x1f1760000000\x1fFix retry backoff overflow\n\nThe delay doubled past u32::MAX.\n\x1f\n\nsrc/net/retry.rs\nsrc/net/client.rs\n\
+                      \x1edef456\x1fGrace\x1f1750000000\x1fBump version\n\x1f\n";
+        let commits = parse_log(output);
+        assert_eq!(commits.len(), 2);
+        assert_eq!(commits[0].hash, "abc123");
+        assert_eq!(commits[0].author, "Ada");
+        assert_eq!(commits[0].timestamp, 1760000000);
+        assert_eq!(commits[0].message, "Fix retry backoff overflow\n\nThe delay doubled past u32::MAX.");
+        assert_eq!(commits[0].files, ["src/net/retry.rs", "src/net/client.rs"]);
+        assert!(commits[1].files.is_empty());
+
+        assert_eq!(
+            commit_document(&commits[0]),
+            "Fix retry backoff overflow\n\nThe delay doubled past u32::MAX.\n\nFiles: src/net/retry.rs, src/net/client.rs"
+        );
+        assert_eq!(commit_document(&commits[1]), "Bump version");
+    }
+}
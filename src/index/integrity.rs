@@ -0,0 +1,236 @@
+//! Cross-store integrity check and repair (`demongrep doctor [--repair]`)
+//!
+//! A database is several stores that must agree on chunk IDs: the LMDB chunk
+//! metadata (the source of truth), the arroy vectors, the tantivy documents,
+//! and the file metadata (the LMDB table, plus `file_meta.json` when `serve`
+//! maintains the index). An interrupted index or a crash between commits can
+//! leave them out of step. Repair rebuilds the FTS index from the chunk
+//! metadata and prunes vectors whose chunk is gone; chunks without a vector
+//! need their files re-embedded, which only `demongrep index` can do.
+
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap};
+use std::path::Path;
+
+use crate::cache::FileMetaStore;
+use crate::chunker::Chunk;
+use crate::fts::FtsStore;
+use crate::vectordb::VectorStore;
+
+/// IDs listed in the report are capped so a badly broken index stays readable
+const MAX_LISTED_IDS: usize = 20;
+
+/// Disagreements between the stores of one database
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct IntegrityReport {
+    pub chunks: usize,
+    /// Items in the vector index (None if it was never built)
+    pub vectors: Option<usize>,
+    /// Documents in the FTS index (None if it is missing or unreadable)
+    pub fts_documents: Option<usize>,
+    /// Chunks with no vector
+    pub missing_vectors: Vec<u32>,
+    /// Vectors whose chunk is gone
+    pub dangling_vectors: Vec<u32>,
+    /// Chunks with no FTS document
+    pub missing_fts: Vec<u32>,
+    /// FTS documents whose chunk is gone
+    pub orphan_fts: Vec<u32>,
+    /// Chunks with more than one FTS document
+    pub duplicate_fts: Vec<u32>,
+    /// Chunk IDs in file metadata whose chunk is gone
+    pub dangling_file_refs: Vec<u32>,
+    /// Chunks that no file metadata entry points to
+    pub untracked_chunks: Vec<u32>,
+}
+
+/// Compare the chunk IDs each store holds
+///
+/// `tracked` is every chunk ID listed in file metadata. Each list in the
+/// report is sorted.
+pub fn compare_ids(chunks: &[u32], vectors: Option<&[u32]>, fts: Option<&[u32]>, tracked: &[u32]) -> IntegrityReport {
+    let chunk_set: BTreeSet<u32> = chunks.iter().copied().collect();
+    let mut report = IntegrityReport {
+        chunks: chunk_set.len(),
+        vectors: vectors.map(|v| v.len()),
+        fts_documents: fts.map(|f| f.len()),
+        ..Default::default()
+    };
+
+    if let Some(vectors) = vectors {
+        let vector_set: BTreeSet<u32> = vectors.iter().copied().collect();
+        report.missing_vectors = chunk_set.difference(&vector_set).copied().collect();
+        report.dangling_vectors = vector_set.difference(&chunk_set).copied().collect();
+    }
+
+    if let Some(fts) = fts {
+        let mut counts: HashMap<u32, usize> = HashMap::new();
+        for id in fts {
+            *counts.entry(*id).or_default() += 1;
+        }
+        report.missing_fts = chunk_set.iter().filter(|id| !counts.contains_key(id)).copied().collect();
+        let mut orphans: Vec<u32> = counts.keys().filter(|id| !chunk_set.contains(id)).copied().collect();
+        orphans.sort_unstable();
+        report.orphan_fts = orphans;
+        let mut duplicates: Vec<u32> = counts.iter().filter(|(_, n)| **n > 1).map(|(id, _)| *id).collect();
+        duplicates.sort_unstable();
+        report.duplicate_fts = duplicates;
+    }
+
+    let tracked_set: BTreeSet<u32> = tracked.iter().copied().collect();
+    report.dangling_file_refs = tracked_set.difference(&chunk_set).copied().collect();
+    report.untracked_chunks = chunk_set.difference(&tracked_set).copied().collect();
+
+    report
+}
+
+fn id_list(ids: &[u32]) -> String {
+    let mut listed: Vec<String> = ids.iter().take(MAX_LISTED_IDS).map(|id| id.to_string()).collect();
+    if ids.len() > MAX_LISTED_IDS {
+        listed.push(format!("… {} more", ids.len() - MAX_LISTED_IDS));
+    }
+    listed.join(", ")
+}
+
+impl IntegrityReport {
+    /// Whether `repair` would rebuild the FTS index
+    pub fn fts_needs_rebuild(&self) -> bool {
+        self.fts_documents.is_none()
+            || !self.missing_fts.is_empty()
+            || !self.orphan_fts.is_empty()
+            || !self.duplicate_fts.is_empty()
+    }
+
+    /// Whether `repair` can fix every problem found
+    pub fn repairable(&self) -> bool {
+        self.missing_vectors.is_empty() && self.dangling_file_refs.is_empty() && self.untracked_chunks.is_empty()
+    }
+
+    /// Human-readable list of inconsistencies (empty if the stores agree)
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        let mut list = |ids: &[u32], what: &str| {
+            if !ids.is_empty() {
+                problems.push(format!("{} {}: {}", ids.len(), what, id_list(ids)));
+            }
+        };
+
+        list(&self.missing_vectors, "chunks without a vector");
+        list(&self.dangling_vectors, "dangling vectors");
+        list(&self.missing_fts, "chunks missing from the FTS index");
+        list(&self.orphan_fts, "orphaned FTS documents");
+        list(&self.duplicate_fts, "chunks indexed more than once in FTS");
+        list(&self.dangling_file_refs, "file metadata references to missing chunks");
+        list(&self.untracked_chunks, "chunks not tracked in file metadata");
+
+        if self.vectors.is_none() && self.chunks > 0 {
+            problems.push("Vector index was never built".to_string());
+        }
+        if self.fts_documents.is_none() {
+            problems.push("FTS index is missing or unreadable".to_string());
+        }
+        problems
+    }
+
+    pub fn is_ok(&self) -> bool {
+        self.problems().is_empty()
+    }
+}
+
+/// Cross-check the stores of the database at `db_path`
+pub fn check_integrity(db_path: &Path, store: &VectorStore) -> Result<IntegrityReport> {
+    let chunks = store.chunk_ids()?;
+    let vectors = store.vector_ids()?;
+    let fts = FtsStore::open_readonly(db_path).and_then(|fts| fts.chunk_ids()).ok();
+
+    let mut tracked: Vec<u32> = store.tracked_chunk_ids()?.into_iter().flat_map(|(_, ids)| ids).collect();
+    if let Some(file_meta) = FileMetaStore::load(db_path)? {
+        tracked.extend(file_meta.tracked_chunk_ids().flat_map(|(_, ids)| ids.iter().copied()));
+    }
+
+    Ok(compare_ids(&chunks, vectors.as_deref(), fts.as_deref(), &tracked))
+}
+
+/// Fix what can be fixed from the stored chunk metadata
+///
+/// Returns a description of each action taken.
+pub fn repair(db_path: &Path, store: &mut VectorStore, report: &IntegrityReport) -> Result<Vec<String>> {
+    let mut actions = Vec::new();
+
+    if !report.dangling_vectors.is_empty() || (report.vectors.is_none() && report.chunks > 0) {
+        store.delete_chunks(&report.dangling_vectors)?;
+        store.build_index()?;
+        if report.dangling_vectors.is_empty() {
+            actions.push("Built the vector index".to_string());
+        } else {
+            actions.push(format!("Pruned {} dangling vectors", report.dangling_vectors.len()));
+        }
+    }
+
+    if report.fts_needs_rebuild() {
+        let chunks = rebuild_fts(db_path, store)?;
+        actions.push(format!("Rebuilt the FTS index from {} chunks", chunks));
+    }
+
+    Ok(actions)
+}
+
+/// Replace the FTS index of the database at `db_path` with one built from the stored chunks
+///
+/// Returns the number of chunks indexed.
+pub fn rebuild_fts(db_path: &Path, store: &VectorStore) -> Result<usize> {
+    let mut fts = FtsStore::new(db_path)?;
+    fts.clear()?;
+    let chunks = store.all_chunks()?;
+    for (id, chunk) in &chunks {
+        fts.add_chunk(
+            *id,
+            &chunk.content,
+            &chunk.path,
+            chunk.context.as_deref().and_then(Chunk::name_from_context),
+            chunk.signature.as_deref(),
+            chunk.docstring.as_deref(),
+            &chunk.kind,
+            &Chunk::extract_string_literals(&chunk.content),
+            &chunk.tags,
+            chunk.package.as_deref(),
+        )?;
+    }
+    fts.commit()?;
+    Ok(chunks.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compare_ids() {
+        let report = compare_ids(&[1, 2, 3, 4], Some(&[1, 2, 3, 9]), Some(&[1, 2, 2, 3, 7]), &[1, 2, 3, 8]);
+        assert_eq!(report.chunks, 4);
+        assert_eq!(report.missing_vectors, [4]);
+        assert_eq!(report.dangling_vectors, [9]);
+        assert_eq!(report.missing_fts, [4]);
+        assert_eq!(report.orphan_fts, [7]);
+        assert_eq!(report.duplicate_fts, [2]);
+        assert_eq!(report.dangling_file_refs, [8]);
+        assert_eq!(report.untracked_chunks, [4]);
+        assert!(report.fts_needs_rebuild());
+        assert!(!report.repairable());
+        assert_eq!(report.problems().len(), 7);
+
+        let healthy = compare_ids(&[1, 2], Some(&[1, 2]), Some(&[2, 1]), &[1, 2]);
+        assert!(healthy.is_ok(), "{:?}", healthy.problems());
+
+        let no_fts = compare_ids(&[1], Some(&[1]), None, &[1]);
+        assert!(no_fts.fts_needs_rebuild());
+        assert!(no_fts.repairable());
+    }
+
+    #[test]
+    fn test_id_list_is_capped() {
+        let ids: Vec<u32> = (0..25).collect();
+        assert!(id_list(&ids).ends_with("19, … 5 more"));
+    }
+}
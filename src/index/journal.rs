@@ -0,0 +1,159 @@
+//! Write-ahead journal for incremental updates
+//!
+//! Re-indexing a file touches stores that can't share a transaction: the
+//! vectors and chunks in LMDB, the tantivy full-text index, and the file
+//! metadata (the LMDB table, plus `file_meta.json` when `serve` keeps one).
+//! Before writing any of them an update records `update.journal`: the files
+//! it re-indexes or removes, the chunk IDs it deletes, and the first chunk
+//! ID it will insert. The journal is removed once every store is updated.
+//!
+//! A journal found on disk means an update was interrupted. The next writer
+//! rolls it back to a consistent state before doing anything else: the old
+//! chunks and any new ones (IDs are handed out in order, so everything from
+//! the recorded first ID on) are deleted from both stores, and the files
+//! lose their metadata so the following sync indexes them again.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::cache::FileMetaStore;
+use crate::fts::FtsStore;
+use crate::vectordb::VectorStore;
+
+/// Name of the journal file inside a database directory
+pub const JOURNAL_FILE: &str = "update.journal";
+
+/// An update in progress
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Journal {
+    /// Files being re-indexed or removed
+    pub files: Vec<PathBuf>,
+    /// Chunk IDs being deleted
+    pub deleted_ids: Vec<u32>,
+    /// First chunk ID the update inserts
+    pub first_new_id: u32,
+}
+
+impl Journal {
+    /// Record the update before touching any store
+    pub fn begin(&self, db_path: &Path) -> Result<()> {
+        // Write, sync, then rename, so a crash never leaves half a journal
+        let tmp_path = db_path.join(format!("{}.tmp", JOURNAL_FILE));
+        let mut file = std::fs::File::create(&tmp_path)?;
+        file.write_all(serde_json::to_string(self)?.as_bytes())?;
+        file.sync_all()?;
+        std::fs::rename(&tmp_path, db_path.join(JOURNAL_FILE))?;
+        Ok(())
+    }
+
+    /// Mark the update complete
+    pub fn finish(db_path: &Path) -> Result<()> {
+        match std::fs::remove_file(db_path.join(JOURNAL_FILE)) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    fn read(db_path: &Path) -> Result<Option<Self>> {
+        match std::fs::read_to_string(db_path.join(JOURNAL_FILE)) {
+            Ok(content) => Ok(Some(serde_json::from_str(&content)?)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+/// Roll back an interrupted update of the database at `db_path`, if any
+///
+/// Call with the write lock held. Returns the number of files rolled back.
+pub fn recover_update(db_path: &Path, store: &mut VectorStore) -> Result<Option<usize>> {
+    let Some(journal) = Journal::read(db_path)? else {
+        return Ok(None);
+    };
+    crate::warn_print!(
+        "⚠️  Rolling back an interrupted update of {} file(s) in {}",
+        journal.files.len(),
+        db_path.display()
+    );
+
+    let mut ids = journal.deleted_ids.clone();
+    ids.extend(store.chunk_ids()?.into_iter().filter(|&id| id >= journal.first_new_id));
+    store.delete_chunks(&ids)?;
+
+    let mut fts_store = FtsStore::new(db_path)?;
+    // The full-text index may hold new chunks the vector store never got
+    let fts_new = fts_store.chunk_ids()?.into_iter().filter(|&id| id >= journal.first_new_id);
+    for id in ids.iter().copied().chain(fts_new) {
+        fts_store.delete_chunk(id)?;
+    }
+    fts_store.commit()?;
+
+    let mut file_meta = FileMetaStore::load(db_path)?;
+    for path in &journal.files {
+        store.remove_file_metadata(path)?;
+        if let Some(file_meta) = file_meta.as_mut() {
+            file_meta.remove_file(path);
+        }
+    }
+    if let Some(file_meta) = file_meta {
+        file_meta.save(db_path)?;
+    }
+
+    store.build_index()?;
+    Journal::finish(db_path)?;
+    Ok(Some(journal.files.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunker::{Chunk, ChunkKind};
+    use crate::embed::EmbeddedChunk;
+    use tempfile::tempdir;
+
+    fn chunk(path: &Path, name: &str) -> EmbeddedChunk {
+        EmbeddedChunk::new(
+            Chunk::new(
+                format!("fn {}() {{}}", name),
+                0,
+                1,
+                ChunkKind::Function,
+                path.to_string_lossy().to_string(),
+            ),
+            vec![1.0, 0.0, 0.0, 0.0],
+        )
+    }
+
+    #[test]
+    fn test_recover_interrupted_update() {
+        let dir = tempdir().unwrap();
+        let db_path = dir.path().join(".demongrep.db");
+        let kept = dir.path().join("kept.rs");
+        let edited = dir.path().join("edited.rs");
+        std::fs::write(&kept, "fn kept() {}").unwrap();
+        std::fs::write(&edited, "fn edited() {}").unwrap();
+
+        let mut store = VectorStore::new(&db_path, 4).unwrap();
+        let ids = store.insert_chunks_with_ids(vec![chunk(&kept, "kept"), chunk(&edited, "old")]).unwrap();
+        store.update_file_metadata(&kept, vec![ids[0]]).unwrap();
+        store.update_file_metadata(&edited, vec![ids[1]]).unwrap();
+        store.build_index().unwrap();
+
+        // Crash after inserting the new chunk, before the metadata was updated
+        let journal = Journal { files: vec![edited.clone()], deleted_ids: vec![ids[1]], first_new_id: store.next_id() };
+        journal.begin(&db_path).unwrap();
+        store.insert_chunks_with_ids(vec![chunk(&edited, "new")]).unwrap();
+
+        assert_eq!(recover_update(&db_path, &mut store).unwrap(), Some(1));
+        assert_eq!(store.chunk_ids().unwrap(), vec![ids[0]]);
+        assert!(!db_path.join(JOURNAL_FILE).exists());
+        // The edited file has no metadata left, so the next sync indexes it
+        assert!(store.check_file_needs_reindex(&edited).unwrap().0);
+        assert!(!store.check_file_needs_reindex(&kept).unwrap().0);
+
+        // Nothing to roll back the second time
+        assert_eq!(recover_update(&db_path, &mut store).unwrap(), None);
+    }
+}
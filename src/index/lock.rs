@@ -0,0 +1,205 @@
+//! Per-database write lock
+//!
+//! LMDB and tantivy cope with readers alongside a writer, but not with two
+//! processes updating the same database: `index` running while `serve`
+//! re-indexes a changed file can leave vectors, file metadata and the
+//! full-text index disagreeing. Every writer (`index`, `search --sync`,
+//! `serve`, the daemon, MCP syncs) takes `write.lock` in the database
+//! directory first. The file records who holds it, so a second writer fails
+//! straight away with the holder's name and pid. A lock left behind by a
+//! process that no longer runs is taken over.
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// Name of the lock file inside a database directory
+pub const LOCK_FILE: &str = "write.lock";
+
+/// A lock file younger than this that can't be parsed is still being written
+const WRITE_GRACE: Duration = Duration::from_secs(2);
+
+/// Whether a process with this pid exists
+#[cfg(unix)]
+pub fn is_process_running(pid: u32) -> bool {
+    if pid == 0 {
+        return false;
+    }
+    // SAFETY: signal 0 only checks that the process exists
+    let result = unsafe { libc::kill(pid as libc::pid_t, 0) };
+    // EPERM: it exists but belongs to another user
+    result == 0 || std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+/// Whether a process with this pid exists
+#[cfg(not(unix))]
+pub fn is_process_running(pid: u32) -> bool {
+    std::process::Command::new("tasklist")
+        .args(["/FI", &format!("PID eq {}", pid), "/NH"])
+        .output()
+        .map(|output| String::from_utf8_lossy(&output.stdout).contains(&pid.to_string()))
+        .unwrap_or(false)
+}
+
+/// Contents of a lock file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LockHolder {
+    pub pid: u32,
+    /// Command holding the lock, e.g. "serve"
+    pub command: String,
+    /// RFC 3339
+    pub acquired_at: String,
+}
+
+enum LockState {
+    Free,
+    Held(LockHolder),
+    /// Left by a process that has exited
+    Stale,
+}
+
+fn lock_state(path: &Path) -> LockState {
+    let Ok(content) = std::fs::read_to_string(path) else {
+        return LockState::Free;
+    };
+    match serde_json::from_str::<LockHolder>(&content) {
+        // Our own pid can only be a leftover whose pid was reused (pid 1 in containers)
+        Ok(holder) if holder.pid != std::process::id() && is_process_running(holder.pid) => LockState::Held(holder),
+        Ok(_) => LockState::Stale,
+        Err(_) => {
+            let age = std::fs::metadata(path)
+                .and_then(|m| m.modified())
+                .ok()
+                .and_then(|modified| SystemTime::now().duration_since(modified).ok());
+            match age {
+                Some(age) if age < WRITE_GRACE => LockState::Held(LockHolder {
+                    pid: 0,
+                    command: "another demongrep process".to_string(),
+                    acquired_at: String::new(),
+                }),
+                _ => LockState::Stale,
+            }
+        }
+    }
+}
+
+/// Exclusive right to write one database, released on drop
+#[derive(Debug)]
+pub struct WriteLock {
+    path: PathBuf,
+}
+
+impl WriteLock {
+    /// Take the lock on the database at `db_path` for `command`
+    ///
+    /// Fails without waiting when another live process holds it.
+    pub fn acquire(db_path: &Path, command: &str) -> Result<Self> {
+        std::fs::create_dir_all(db_path)?;
+        let path = db_path.join(LOCK_FILE);
+        let holder = LockHolder {
+            pid: std::process::id(),
+            command: command.to_string(),
+            acquired_at: chrono::Utc::now().to_rfc3339(),
+        };
+
+        // Second attempt after removing a stale lock
+        for _ in 0..2 {
+            match std::fs::OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(mut file) => {
+                    file.write_all(serde_json::to_string(&holder)?.as_bytes())?;
+                    return Ok(Self { path });
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => match lock_state(&path) {
+                    LockState::Held(other) => return Err(busy_error(db_path, &other)),
+                    LockState::Stale => {
+                        tracing::debug!("Removing stale lock {}", path.display());
+                        let _ = std::fs::remove_file(&path);
+                    }
+                    LockState::Free => {}
+                },
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Err(anyhow!("Could not take the write lock on {}", db_path.display()))
+    }
+
+    /// The live process holding the lock on `db_path`, if any
+    pub fn holder(db_path: &Path) -> Option<LockHolder> {
+        match lock_state(&db_path.join(LOCK_FILE)) {
+            LockState::Held(holder) => Some(holder),
+            _ => None,
+        }
+    }
+}
+
+impl Drop for WriteLock {
+    fn drop(&mut self) {
+        // Gone already if the database directory was removed while locked
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+fn busy_error(db_path: &Path, holder: &LockHolder) -> anyhow::Error {
+    if holder.pid == 0 {
+        return anyhow!("Database {} is locked by {}; try again", db_path.display(), holder.command);
+    }
+    anyhow!(
+        "Database {} is being written by `demongrep {}` (pid {}, since {}); stop it or wait for it to finish",
+        db_path.display(),
+        holder.command,
+        holder.pid,
+        holder.acquired_at
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_is_process_running() {
+        assert!(is_process_running(std::process::id()));
+        assert!(!is_process_running(999_999_999));
+    }
+
+    #[test]
+    fn test_write_lock_released_on_drop() {
+        let dir = tempdir().unwrap();
+        let lock = WriteLock::acquire(dir.path(), "index").unwrap();
+        assert!(dir.path().join(LOCK_FILE).exists());
+        drop(lock);
+        assert!(!dir.path().join(LOCK_FILE).exists());
+        WriteLock::acquire(dir.path(), "index").unwrap();
+    }
+
+    #[test]
+    fn test_stale_lock_is_taken_over() {
+        let dir = tempdir().unwrap();
+        let stale = LockHolder { pid: 999_999_999, command: "serve".to_string(), acquired_at: String::new() };
+        std::fs::write(dir.path().join(LOCK_FILE), serde_json::to_string(&stale).unwrap()).unwrap();
+        assert!(WriteLock::holder(dir.path()).is_none());
+        WriteLock::acquire(dir.path(), "index").unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_live_lock_fails_fast() {
+        let dir = tempdir().unwrap();
+        // The test runner's parent is alive for the whole test
+        let live = LockHolder {
+            pid: std::os::unix::process::parent_id(),
+            command: "serve".to_string(),
+            acquired_at: String::new(),
+        };
+        std::fs::write(dir.path().join(LOCK_FILE), serde_json::to_string(&live).unwrap()).unwrap();
+
+        assert_eq!(WriteLock::holder(dir.path()).unwrap().command, "serve");
+        let err = WriteLock::acquire(dir.path(), "index").unwrap_err();
+        assert!(err.to_string().contains("`demongrep serve`"));
+        assert!(dir.path().join(LOCK_FILE).exists());
+    }
+}
@@ -4,33 +4,139 @@ use std::hash::{Hash, Hasher};
 use colored::Colorize;
 use indicatif::{ProgressBar, ProgressStyle};
 use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
 use std::time::Instant;
 
 use crate::chunker::SemanticChunker;
+use crate::config::Config;
 use crate::database::DatabaseManager;
 use crate::embed::{EmbeddingService, ModelType};
 use crate::file::FileWalker;
 use crate::fts::FtsStore;
-use crate::vectordb::VectorStore;
+use crate::vectordb::{StoreOptions, VectorStore};
+use revision::Snapshot;
+
+mod chunk_stats;
+mod file_summary;
+mod history;
+mod integrity;
+mod journal;
+mod lock;
+mod optimize;
+mod revision;
+mod staleness;
+mod stores;
+mod sync;
+mod verify;
+
+pub use chunk_stats::ChunkSizeStats;
+pub use file_summary::refresh_file_vectors;
+pub use history::index_history;
+pub use integrity::{check_integrity, rebuild_fts, repair};
+pub use journal::{recover_update, Journal};
+pub use lock::{is_process_running, WriteLock, LOCK_FILE};
+pub use optimize::{optimize, recorded_dimensions};
+pub use staleness::{check_staleness, git_head, record_fingerprint};
+pub use stores::{dir_size, global_stores, prune_global_stores, GlobalStore, StoreInventory};
+pub use sync::{apply_changes, pending_changes, PendingChanges};
+pub use verify::{check_store, verify};
+
+/// Named store selected with the global `--store` flag
+static STORE: OnceLock<String> = OnceLock::new();
+
+/// Select a named store for this process (e.g. "docs" vs "code")
+///
+/// Named stores live in their own database directories, so each can use a
+/// different model and settings.
+pub fn set_store(name: &str) -> Result<()> {
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        return Err(anyhow::anyhow!(
+            "Invalid store name '{}': use letters, digits, '-' and '_'",
+            name
+        ));
+    }
+    let _ = STORE.set(name.to_string());
+    Ok(())
+}
+
+/// Name of the selected store (None for the default store)
+pub fn store_name() -> Option<&'static str> {
+    STORE.get().map(|s| s.as_str())
+}
+
+/// Local database directory for a project in the selected store
+pub fn local_db_path(project_root: &Path) -> PathBuf {
+    local_db_path_for_store(project_root, store_name())
+}
+
+/// Per-project file name in the selected store: a hash of the root, plus `-<store>`
+pub fn store_dir_name(project_root: &Path) -> String {
+    let mut hasher = DefaultHasher::new();
+    project_root.hash(&mut hasher);
+    let hash = hasher.finish();
+
+    match store_name() {
+        Some(name) => format!("{:x}-{}", hash, name),
+        None => format!("{:x}", hash),
+    }
+}
+
+/// Global database directory for a project in the selected store
+pub fn global_db_path(project_root: &Path) -> Option<PathBuf> {
+    let home = dirs::home_dir()?;
+    Some(home.join(".demongrep").join("stores").join(store_dir_name(project_root)))
+}
+
+/// Whether a database path is a project-local database (in any store)
+pub fn is_local_db(db_path: &Path) -> bool {
+    db_path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .map(|n| n.starts_with(".demongrep") && n.ends_with(".db"))
+        .unwrap_or(false)
+}
+
+/// Key for a project in projects.json (named stores get a `#store` suffix)
+fn mapping_key(project_path: &Path) -> String {
+    match store_name() {
+        Some(name) => format!("{}#{}", project_path.to_string_lossy(), name),
+        None => project_path.to_string_lossy().to_string(),
+    }
+}
+
+/// Split a projects.json key into project root and store name
+fn parse_mapping_key(key: &str) -> (PathBuf, Option<&str>) {
+    match key.rsplit_once('#') {
+        Some((project, store))
+            if !store.is_empty()
+                && store.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') =>
+        {
+            (PathBuf::from(project), Some(store))
+        }
+        _ => (PathBuf::from(key), None),
+    }
+}
+
+/// Local database path for a project in a specific store
+fn local_db_path_for_store(project_root: &Path, store: Option<&str>) -> PathBuf {
+    match store {
+        Some(name) => project_root.join(format!(".demongrep.{}.db", name)),
+        None => project_root.join(".demongrep.db"),
+    }
+}
 
 /// Get the database path for indexing
-fn get_index_db_path(path: Option<PathBuf>, global: bool) -> Result<PathBuf> {
+pub fn get_index_db_path(path: Option<PathBuf>, global: bool) -> Result<PathBuf> {
     let project_path = path.unwrap_or_else(|| PathBuf::from("."));
     let canonical_path = project_path.canonicalize()?;
 
     if global {
         // Global mode: use home directory with project hash
-        let home = dirs::home_dir().ok_or_else(|| anyhow::anyhow!("Could not find home directory"))?;
-        
-        // Create hash of canonical path
-        let mut hasher = DefaultHasher::new();
-        canonical_path.hash(&mut hasher);
-        let hash = hasher.finish();
-        
-        let global_base = home.join(".demongrep").join("stores");
-        std::fs::create_dir_all(&global_base)?;
-        
-        let db_path = global_base.join(format!("{:x}", hash));
+        let db_path = global_db_path(&canonical_path)
+            .ok_or_else(|| anyhow::anyhow!("Could not find home directory"))?;
+        if let Some(global_base) = db_path.parent() {
+            std::fs::create_dir_all(global_base)?;
+        }
         
         // Save project mapping for later reference
         save_project_mapping(&canonical_path, &db_path)?;
@@ -38,7 +144,7 @@ fn get_index_db_path(path: Option<PathBuf>, global: bool) -> Result<PathBuf> {
         Ok(db_path)
     } else {
         // Local mode: use project directory
-        Ok(canonical_path.join(".demongrep.db"))
+        Ok(local_db_path(&canonical_path))
     }
 }
 
@@ -50,18 +156,13 @@ pub fn get_search_db_paths(path: Option<PathBuf>) -> Result<Vec<PathBuf>> {
     let canonical_path = project_path.canonicalize()?;
     
     // 1. Check local database
-    let local_db = canonical_path.join(".demongrep.db");
+    let local_db = local_db_path(&canonical_path);
     if local_db.exists() {
         paths.push(local_db);
     }
     
     // 2. Check global database
-    if let Some(home) = dirs::home_dir() {
-        let mut hasher = DefaultHasher::new();
-        canonical_path.hash(&mut hasher);
-        let hash = hasher.finish();
-        
-        let global_db = home.join(".demongrep").join("stores").join(format!("{:x}", hash));
+    if let Some(global_db) = global_db_path(&canonical_path) {
         if global_db.exists() {
             paths.push(global_db);
         }
@@ -87,7 +188,7 @@ fn save_project_mapping(project_path: &Path, db_path: &Path) -> Result<()> {
     
     // Add new mapping
     mappings.insert(
-        project_path.to_string_lossy().to_string(),
+        mapping_key(project_path),
         db_path.to_string_lossy().to_string()
     );
     
@@ -97,6 +198,42 @@ fn save_project_mapping(project_path: &Path, db_path: &Path) -> Result<()> {
     Ok(())
 }
 
+/// Get every registered project with its databases (from projects.json)
+///
+/// Returns (project root, database path) pairs. Each project contributes its
+/// global store and, if present, the local database of the same store.
+pub fn get_all_project_db_paths() -> Result<Vec<(PathBuf, PathBuf)>> {
+    let home = dirs::home_dir().ok_or_else(|| anyhow::anyhow!("Could not find home directory"))?;
+    let mapping_file = home.join(".demongrep").join("projects.json");
+
+    if !mapping_file.exists() {
+        return Ok(Vec::new());
+    }
+
+    let content = std::fs::read_to_string(&mapping_file)?;
+    let mappings: std::collections::HashMap<String, String> = serde_json::from_str(&content)?;
+
+    let mut projects: Vec<_> = mappings.into_iter().collect();
+    projects.sort();
+
+    let mut paths = Vec::new();
+    for (key, db_path_str) in projects {
+        let (project_root, store) = parse_mapping_key(&key);
+
+        let local_db = local_db_path_for_store(&project_root, store);
+        if local_db.exists() {
+            paths.push((project_root.clone(), local_db));
+        }
+
+        let db_path = PathBuf::from(&db_path_str);
+        if db_path.exists() {
+            paths.push((project_root, db_path));
+        }
+    }
+
+    Ok(paths)
+}
+
 /// Find databases for a project by name (searches in projects.json)
 fn find_project_databases(project_name: &str) -> Result<Vec<PathBuf>> {
     let home = dirs::home_dir().ok_or_else(|| anyhow::anyhow!("Could not find home directory"))?;
@@ -128,9 +265,9 @@ fn find_project_databases(project_name: &str) -> Result<Vec<PathBuf>> {
             }
             
             // Also check for local database at project path
-            let project_pb = PathBuf::from(&project_path);
+            let (project_pb, store) = parse_mapping_key(&project_path);
             if project_pb.exists() {
-                let local_db = project_pb.join(".demongrep.db");
+                let local_db = local_db_path_for_store(&project_pb, store);
                 if local_db.exists() {
                     found_paths.push(local_db);
                 }
@@ -199,21 +336,42 @@ fn remove_from_project_mapping(project_name: &str) -> Result<()> {
     Ok(())
 }
 
+/// Print what `index_history` did
+fn report_history((embedded, total): (usize, usize)) {
+    println!("✅ Embedded {} new commit messages ({} in history)", embedded, total);
+}
+
+/// What `index` builds and how (`demongrep index` flags)
+#[derive(Debug, Clone, Default)]
+pub struct IndexOptions {
+    /// Report what would be indexed without writing anything
+    pub dry_run: bool,
+    /// `--force`; accepted for compatibility, changed files are found either way
+    pub force: bool,
+    /// Build the global store instead of one in the project
+    pub global: bool,
+    /// Embedding model, or the default one
+    pub model: Option<ModelType>,
+    /// Verify this many sampled chunks after indexing
+    pub verify_samples: Option<usize>,
+    /// Also embed the messages of up to this many commits
+    pub history: Option<usize>,
+    /// Index the files of this revision instead of the working tree
+    pub rev: Option<String>,
+}
+
 /// Index a repository
-pub async fn index(path: Option<PathBuf>, dry_run: bool, _force: bool, global: bool, model: Option<ModelType>) -> Result<()> {
+pub async fn index(path: Option<PathBuf>, options: IndexOptions, config: &Config) -> Result<()> {
+    let IndexOptions { dry_run, global, model, verify_samples, history, rev, .. } = options;
     let project_path = path.clone().unwrap_or_else(|| PathBuf::from("."));
     let canonical_path = project_path.canonicalize()?;
+    if history.is_some() && git_head(&canonical_path).is_none() {
+        return Err(anyhow::anyhow!("--with-history needs a git repository, {} is not one", project_path.display()));
+    }
     
     // Check for existing databases (local and global)
-    let local_db_path = canonical_path.join(".demongrep.db");
-    let global_db_path = if let Some(home) = dirs::home_dir() {
-        let mut hasher = DefaultHasher::new();
-        canonical_path.hash(&mut hasher);
-        let hash = hasher.finish();
-        Some(home.join(".demongrep").join("stores").join(format!("{:x}", hash)))
-    } else {
-        None
-    };
+    let local_db_path = local_db_path(&canonical_path);
+    let global_db_path = global_db_path(&canonical_path);
     
     let local_exists = local_db_path.exists();
     let global_exists = global_db_path.as_ref().map(|p| p.exists()).unwrap_or(false);
@@ -253,16 +411,45 @@ pub async fn index(path: Option<PathBuf>, dry_run: bool, _force: bool, global: b
     let db_path = get_index_db_path(Some(canonical_path.clone()), global)?;
     let model_type = model.unwrap_or_default();
 
+    // A revision's files come from git, not the working tree, and a pinned
+    // index has nothing to update incrementally
+    let snapshot = match &rev {
+        Some(rev) => {
+            if db_path.exists() {
+                return Err(anyhow::anyhow!(
+                    "{} already exists and --rev builds a new index. Run `demongrep clear` first, \
+                     or keep the revision in its own store: `demongrep --store {} index --rev {}`",
+                    db_path.display(),
+                    rev,
+                    rev
+                ));
+            }
+            Some(Snapshot::checkout(&canonical_path, rev)?)
+        }
+        None => None,
+    };
+    // Snapshot files are indexed under their place in the project
+    let indexed_path = |path: &Path| match &snapshot {
+        Some(snapshot) => snapshot.project_path(&project_path, path),
+        None => path.to_path_buf(),
+    };
+
     println!("{}", "🚀 Demongrep Indexer".bright_cyan().bold());
     println!("{}", "=".repeat(60));
     println!("📂 Project: {}", project_path.display());
     println!("💾 Database: {}", db_path.display());
+    if let Some(name) = store_name() {
+        println!("🏷️  Store: {}", name);
+    }
     if global {
         println!("🌍 Mode: Global (shared across workspaces)");
     } else {
         println!("📍 Mode: Local (project-specific)");
     }
     println!("🧠 Model: {} ({} dims)", model_type.name(), model_type.dimensions());
+    if let (Some(rev), Some(snapshot)) = (&rev, &snapshot) {
+        println!("📌 Revision: {} ({})", rev, &snapshot.commit[..12]);
+    }
 
     if dry_run {
         println!("\n{}", "🔍 DRY RUN MODE".bright_yellow());
@@ -282,7 +469,8 @@ pub async fn index(path: Option<PathBuf>, dry_run: bool, _force: bool, global: b
     println!("{}", "-".repeat(60));
 
     let start = Instant::now();
-    let walker = FileWalker::new(project_path.clone());
+    let source_root = snapshot.as_ref().map_or_else(|| project_path.clone(), |s| s.root().to_path_buf());
+    let walker = FileWalker::new(source_root).with_excludes(&config.indexing.excludes);
     let (files, stats) = walker.walk()?;
     let discovery_duration = start.elapsed();
 
@@ -301,8 +489,10 @@ pub async fn index(path: Option<PathBuf>, dry_run: bool, _force: bool, global: b
         return Ok(());
     }
 
+    let _write_lock = WriteLock::acquire(&db_path, "index")?;
+
     // Open or create database
-    let mut store = VectorStore::new(&db_path, model_type.dimensions())?;
+    let mut store = VectorStore::with_options(&db_path, model_type.dimensions(), &StoreOptions::from_config(config))?;
     
     // Check database metadata for model changes
     if is_incremental {
@@ -314,6 +504,20 @@ pub async fn index(path: Option<PathBuf>, dry_run: bool, _force: bool, global: b
             println!("\n   Run {} first", "demongrep clear".bright_cyan());
             return Err(anyhow::anyhow!("Model mismatch - clear database first"));
         }
+        recover_update(&db_path, &mut store)?;
+        // Sharding is chosen when an index is created; asking for it later needs a fresh index
+        let shards = config.indexing.shards;
+        if shards > 1 && store.chunk_count()? > 0 && store.shard_count() != shards {
+            println!(
+                "{}",
+                format!(
+                    "⚠️  The index has {} shard(s); --shards/indexing.shards ({}) applies to new indexes. Run `demongrep clear` first to reshard.",
+                    store.shard_count(),
+                    shards
+                )
+                .yellow()
+            );
+        }
     }
     
     // Determine which files need indexing
@@ -354,6 +558,14 @@ pub async fn index(path: Option<PathBuf>, dry_run: bool, _force: bool, global: b
         
         if files_to_index.is_empty() && files_to_delete.is_empty() {
             println!("\n{}", "✅ Database is up to date! No changes detected.".green());
+            if let Some(commits) = history {
+                let mut embedding_service = EmbeddingService::with_model(model_type)?;
+                report_history(index_history(&mut store, &mut embedding_service, &canonical_path, commits)?);
+            }
+            if let Some(samples) = verify_samples {
+                drop(store);
+                verify(&db_path, model_type.dimensions(), samples)?;
+            }
             return Ok(());
         }
     } else {
@@ -366,7 +578,7 @@ pub async fn index(path: Option<PathBuf>, dry_run: bool, _force: bool, global: b
     println!("{}", "-".repeat(60));
 
     let start = Instant::now();
-    let mut chunker = SemanticChunker::new(100, 2000, 10);
+    let mut chunker = SemanticChunker::from_config(&config.indexing).with_project_root(&project_path);
     let mut all_chunks = Vec::new();
 
     let pb = ProgressBar::new(files_to_index.len() as u64);
@@ -391,7 +603,7 @@ pub async fn index(path: Option<PathBuf>, dry_run: bool, _force: bool, global: b
             }
         };
 
-        let chunks = chunker.chunk_semantic(file.language, &file.path, &source_code)?;
+        let chunks = chunker.chunk_semantic(file.language, &indexed_path(&file.path), &source_code)?;
         all_chunks.extend(chunks);
 
         pb.inc(1);
@@ -405,6 +617,19 @@ pub async fn index(path: Option<PathBuf>, dry_run: bool, _force: bool, global: b
     let chunking_duration = start.elapsed();
 
     println!("✅ Created {} chunks in {:?}", all_chunks.len(), chunking_duration);
+    if !all_chunks.is_empty() {
+        let by_kind: Vec<String> = crate::chunker::count_by_kind(&all_chunks)
+            .into_iter()
+            .map(|(kind, count)| format!("{:?} {}", kind, count))
+            .collect();
+        println!("   By kind: {}", by_kind.join(", "));
+
+        let gaps = chunker.gap_stats();
+        println!(
+            "   Gaps ({:?}): {} kept, {} merged, {} dropped",
+            config.indexing.gap_strategy, gaps.kept, gaps.merged, gaps.dropped
+        );
+    }
 
     // Phase 3: Embedding Generation
     println!("\n{}", "Phase 3: Embedding Generation".bright_cyan());
@@ -420,6 +645,7 @@ pub async fn index(path: Option<PathBuf>, dry_run: bool, _force: bool, global: b
         vec![]
     } else {
         println!("\n🔄 Generating embeddings for {} chunks...", all_chunks.len());
+        store.attach_notes(&mut all_chunks)?;
         let chunks = embedding_service.embed_chunks(all_chunks)?;
         println!("✅ Generated {} embeddings in {:?}", chunks.len(), start.elapsed());
         println!("   Average: {:?} per chunk", start.elapsed() / chunks.len() as u32);
@@ -443,6 +669,22 @@ pub async fn index(path: Option<PathBuf>, dry_run: bool, _force: bool, global: b
         println!("✅ Database ready (newly created)");
     }
 
+    // Journal the update so an interrupted run is rolled back by the next one
+    Journal {
+        files: files_to_index
+            .iter()
+            .map(|(file, _)| indexed_path(&file.path))
+            .chain(files_to_delete.iter().map(|(path, _)| path.clone()))
+            .collect(),
+        deleted_ids: files_to_index
+            .iter()
+            .flat_map(|(_, ids)| ids.iter().copied())
+            .chain(files_to_delete.iter().flat_map(|(_, ids)| ids.iter().copied()))
+            .collect(),
+        first_new_id: store.next_id(),
+    }
+    .begin(&db_path)?;
+
     // Delete old chunks from changed/deleted files
     if is_incremental {
         let mut chunks_to_delete = Vec::new();
@@ -479,7 +721,15 @@ pub async fn index(path: Option<PathBuf>, dry_run: bool, _force: bool, global: b
 
     // Phase 4b: FTS Index
     println!("\n🔄 Updating full-text search index...");
-    let mut fts_store = FtsStore::new(&db_path)?;
+    let mut fts_store = FtsStore::with_analyzer(&db_path, &config.fts)?;
+    if fts_store.analyzer() != &config.fts {
+        println!(
+            "   {}",
+            "⚠️  [fts] options differ from the ones this index was built with; they apply after \
+             `demongrep clear` and `demongrep index`"
+                .yellow()
+        );
+    }
 
     // Delete old FTS entries
     if is_incremental {
@@ -506,12 +756,17 @@ pub async fn index(path: Option<PathBuf>, dry_run: bool, _force: bool, global: b
             *chunk_id,
             &chunk.chunk.content,
             &chunk.chunk.path,
+            chunk.chunk.name.as_deref(),
             chunk.chunk.signature.as_deref(),
+            chunk.chunk.docstring.as_deref(),
             &format!("{:?}", chunk.chunk.kind),
             &chunk.chunk.string_literals,
+            &chunk.chunk.tags,
+            chunk.chunk.package.as_deref(),
         )?;
     }
     fts_store.commit()?;
+    fts_store.merge_if_fragmented()?;
 
     let fts_stats = fts_store.stats()?;
     println!("✅ FTS index updated ({} documents)", fts_stats.num_documents);
@@ -534,14 +789,27 @@ pub async fn index(path: Option<PathBuf>, dry_run: bool, _force: bool, global: b
     
     // Update metadata for changed files
     for (file, _) in &files_to_index {
-        let chunk_ids_for_file = file_chunks.get(&file.path).cloned().unwrap_or_default();
-        store.update_file_metadata(&file.path, chunk_ids_for_file)?;
+        let path = indexed_path(&file.path);
+        let chunk_ids_for_file = file_chunks.get(&path).cloned().unwrap_or_default();
+        store.update_file_metadata_from(&path, &file.path, chunk_ids_for_file)?;
     }
     
     // Remove metadata for deleted files
     for (path, _) in &files_to_delete {
         store.remove_file_metadata(&path)?;
     }
+
+    // File summaries are built from the stored chunks, so they come last
+    if config.indexing.file_summaries {
+        let summarized = refresh_file_vectors(&mut store, &mut embedding_service)?;
+        if summarized > 0 {
+            println!("✅ Embedded {} file summaries", summarized);
+        }
+    }
+    if let Some(commits) = history {
+        report_history(index_history(&mut store, &mut embedding_service, &canonical_path, commits)?);
+    }
+    Journal::finish(&db_path)?;
     
     // Save database metadata
     store.save_db_metadata(
@@ -553,12 +821,20 @@ pub async fn index(path: Option<PathBuf>, dry_run: bool, _force: bool, global: b
     println!("✅ File metadata saved");
 
     // Save model metadata (for backwards compatibility with tools that read metadata.json)
-    let metadata = serde_json::json!({
+    let mut metadata = serde_json::json!({
         "model_short_name": embedding_service.model_short_name(),
         "model_name": embedding_service.model_name(),
         "dimensions": embedding_service.dimensions(),
         "indexed_at": chrono::Utc::now().to_rfc3339(),
+        "git_head": git_head(&canonical_path),
+        "trees": store.n_trees()?,
+        "shards": store.shard_count(),
+        "search_quality": config.search.search_quality,
     });
+    if let (Some(rev), Some(snapshot)) = (&rev, &snapshot) {
+        metadata["git_rev"] = rev.clone().into();
+        metadata["git_head"] = snapshot.commit.clone().into();
+    }
     std::fs::write(
         db_path.join("metadata.json"),
         serde_json::to_string_pretty(&metadata)?
@@ -573,6 +849,10 @@ pub async fn index(path: Option<PathBuf>, dry_run: bool, _force: bool, global: b
     println!("   Total files: {}", db_stats.total_files);
     println!("   Indexed: {}", if db_stats.indexed { "✅ Yes" } else { "❌ No" });
     println!("   Dimensions: {}", db_stats.dimensions);
+    println!("   Trees: {}", store.n_trees()?);
+    if store.shard_count() > 1 {
+        println!("   Shards: {}", store.shard_count());
+    }
 
     // Calculate database size
     let mut total_size = 0u64;
@@ -592,6 +872,12 @@ pub async fn index(path: Option<PathBuf>, dry_run: bool, _force: bool, global: b
     println!("   Vector storage:      {:?}", storage_duration);
     println!("   {}", format!("Total:               {:?}", total_duration).bold());
 
+    if let Some(samples) = verify_samples {
+        drop(store);
+        drop(fts_store);
+        verify(&db_path, embedding_service.dimensions(), samples)?;
+    }
+
     println!("\n{}", "✨ Indexing complete!".bright_green().bold());
     println!("   Run {} to search your codebase", "demongrep search <query>".bright_cyan());
 
@@ -612,7 +898,7 @@ pub async fn list() -> Result<()> {
     } else {
         println!("\n{}", "Current Directory:".bright_green());
         for db_path in &db_paths {
-            let db_type = if db_path.ends_with(".demongrep.db") { "Local" } else { "Global" };
+            let db_type = if is_local_db(db_path) { "Local" } else { "Global" };
             println!("\n   {} Database:", db_type);
             print_repo_stats(&current_dir, db_path)?;
         }
@@ -645,7 +931,7 @@ pub async fn list() -> Result<()> {
 }
 
 /// Show statistics about the vector database - REFACTORED to use DatabaseManager
-pub async fn stats(path: Option<PathBuf>) -> Result<()> {
+pub async fn stats(path: Option<PathBuf>, chunks: bool) -> Result<()> {
     // Load all databases using DatabaseManager
     let db_manager = match DatabaseManager::load(path) {
         Ok(manager) => manager,
@@ -674,6 +960,33 @@ pub async fn stats(path: Option<PathBuf>) -> Result<()> {
     println!("   Total files: {}", combined.total_files);
     println!("   Indexed: {}", if combined.indexed { "✅ Yes" } else { "❌ No" });
     println!("   Dimensions: {}", combined.dimensions);
+    for database in db_manager.databases() {
+        let name = database.db_type.name().to_lowercase();
+        let trees = database.store().n_trees()?;
+        if trees > 0 {
+            println!("   Trees ({}): {}", name, trees);
+        }
+        let shards = database.store().shard_count();
+        if shards > 1 {
+            println!("   Shards ({}): {}", name, shards);
+        }
+        let pending = database.store().pending_vectors()?;
+        if pending > 0 {
+            println!("   Vector changes not yet in the index ({}): {} (folded in by `demongrep optimize`)", name, pending);
+        }
+    }
+    for database in db_manager.databases() {
+        if let Some((rev, commit)) = database.revision() {
+            println!("   Revision: {} ({})", rev, commit.get(..12).unwrap_or(&commit));
+        }
+    }
+    let mut commits = 0;
+    for database in db_manager.databases() {
+        commits += database.store().commit_hashes()?.len();
+    }
+    if commits > 0 {
+        println!("   Commit history: {} commits", commits);
+    }
 
     // Show breakdown if both databases exist
     if db_manager.database_count() > 1 {
@@ -686,6 +999,30 @@ pub async fn stats(path: Option<PathBuf>) -> Result<()> {
         }
     }
 
+    // Show the package breakdown in monorepos (nothing for indexes without packages)
+    let mut packages: std::collections::BTreeMap<String, (usize, usize)> = Default::default();
+    for database in db_manager.databases() {
+        for (package, (chunks, files)) in database.store().package_counts()? {
+            let counts = packages.entry(package).or_default();
+            counts.0 += chunks;
+            counts.1 += files;
+        }
+    }
+    if !packages.is_empty() {
+        let mut packages: Vec<_> = packages.into_iter().collect();
+        // Largest first; the map already ordered equal sizes by name
+        packages.sort_by_key(|(_, (chunks, _))| std::cmp::Reverse(*chunks));
+        let width = packages.iter().map(|(package, _)| package.chars().count()).max().unwrap_or(0);
+        let packaged: usize = packages.iter().map(|(_, (chunks, _))| chunks).sum();
+        println!("\n{}", "Packages:".bright_green());
+        for (package, (chunks, files)) in &packages {
+            println!("   📦 {:<width$}  {} chunks from {} files", package, chunks, files, width = width);
+        }
+        if packaged < combined.total_chunks {
+            println!("   {}", format!("{} chunks outside any package", combined.total_chunks - packaged).dimmed());
+        }
+    }
+
     // Calculate total database size
     let mut total_size = 0u64;
     for db_path in db_manager.database_paths() {
@@ -701,6 +1038,14 @@ pub async fn stats(path: Option<PathBuf>) -> Result<()> {
         println!("   Average per chunk: {:.2} KB", (total_size as f64 / combined.total_chunks as f64) / 1024.0);
     }
 
+    if chunks {
+        let mut sizes = Vec::new();
+        for database in db_manager.databases() {
+            sizes.extend(database.store().chunk_sizes()?);
+        }
+        ChunkSizeStats::from_sizes(&sizes).print();
+    }
+
     Ok(())
 }
 
@@ -727,7 +1072,7 @@ pub async fn clear(path: Option<PathBuf>, yes: bool, project: Option<String>) ->
     println!("{}", "=".repeat(60));
     
     for db_path in &db_paths {
-        let db_type = if db_path.ends_with(".demongrep.db") { "Local" } else { "Global" };
+        let db_type = if is_local_db(db_path) { "Local" } else { "Global" };
         println!("💾 {} Database: {}", db_type, db_path.display());
     }
 
@@ -746,15 +1091,21 @@ pub async fn clear(path: Option<PathBuf>, yes: bool, project: Option<String>) ->
         }
     }
 
+    // Take every lock before deleting anything
+    let _write_locks = db_paths
+        .iter()
+        .map(|db_path| WriteLock::acquire(db_path, "clear"))
+        .collect::<Result<Vec<_>>>()?;
+
     // Track which paths we're deleting for cleanup
     let mut deleted_global_dbs = Vec::new();
     
     for db_path in db_paths {
-        let db_type = if db_path.ends_with(".demongrep.db") { "Local" } else { "Global" };
+        let db_type = if is_local_db(&db_path) { "Local" } else { "Global" };
         println!("\n🔄 Removing {} database...", db_type);
         
         // Track global databases for projects.json cleanup
-        if !db_path.ends_with(".demongrep.db") {
+        if !is_local_db(&db_path) {
             deleted_global_dbs.push(db_path.clone());
         }
         
@@ -800,3 +1151,23 @@ fn print_repo_stats(_repo_path: &Path, db_path: &Path) -> Result<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_mapping_key() {
+        assert_eq!(parse_mapping_key("/work/app"), (PathBuf::from("/work/app"), None));
+        assert_eq!(parse_mapping_key("/work/app#docs"), (PathBuf::from("/work/app"), Some("docs")));
+        // A '#' in the directory name is not a store suffix
+        assert_eq!(parse_mapping_key("/work/c#/lib"), (PathBuf::from("/work/c#/lib"), None));
+    }
+
+    #[test]
+    fn test_is_local_db() {
+        assert!(is_local_db(Path::new("/work/app/.demongrep.db")));
+        assert!(is_local_db(Path::new("/work/app/.demongrep.docs.db")));
+        assert!(!is_local_db(Path::new("/home/me/.demongrep/stores/abc123-docs")));
+    }
+}
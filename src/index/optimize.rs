@@ -0,0 +1,82 @@
+//! Index maintenance (`demongrep optimize`)
+//!
+//! Tantivy writes a new segment on every commit and only marks deleted
+//! documents. A project re-indexed file by file, as `serve` and the daemon
+//! do, ends up with many small segments full of dead documents, and every
+//! query has to visit each of them. Optimizing merges them into one and
+//! drops the deleted documents. Syncs and watched updates already merge
+//! once an index passes `MAX_SEGMENTS`; this runs it on demand.
+//!
+//! The vector index works the same way: changes since it was built sit in a
+//! delta segment that every search scans, until there are enough of them
+//! to rebuild. Optimizing folds them in now.
+
+use anyhow::Result;
+use colored::Colorize;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use super::{dir_size, get_search_db_paths, is_local_db, WriteLock};
+use crate::config::Config;
+use crate::fts::FtsStore;
+use crate::vectordb::{StoreOptions, VectorStore};
+
+fn mb(bytes: u64) -> f64 {
+    bytes as f64 / (1024.0 * 1024.0)
+}
+
+/// Embedding dimensions recorded in a database's metadata.json
+pub fn recorded_dimensions(db_path: &Path) -> Option<usize> {
+    let content = std::fs::read_to_string(db_path.join("metadata.json")).ok()?;
+    let json: serde_json::Value = serde_json::from_str(&content).ok()?;
+    json.get("dimensions")?.as_u64().map(|dimensions| dimensions as usize)
+}
+
+/// Fold pending vector changes into the vector index of the database at `db_path`
+fn compact_vectors(db_path: &Path, options: &StoreOptions) -> Result<()> {
+    let Some(dimensions) = recorded_dimensions(db_path) else {
+        return Ok(());
+    };
+    let start = Instant::now();
+    let mut store = VectorStore::with_options(db_path, dimensions, options)?;
+    let folded = store.compact_index()?;
+    if folded > 0 {
+        println!("   Vector changes folded into the index: {} ({:?})", folded, start.elapsed());
+    }
+    Ok(())
+}
+
+/// Merge the FTS segments and fold in pending vector changes of every database for the project
+pub async fn optimize(path: Option<PathBuf>, config: &Config) -> Result<()> {
+    let db_paths = get_search_db_paths(path)?;
+    if db_paths.is_empty() {
+        println!("{}", "❌ No database found!".red());
+        println!("   Run {} first", "demongrep index".bright_cyan());
+        return Ok(());
+    }
+
+    for db_path in &db_paths {
+        let db_type = if is_local_db(db_path) { "Local" } else { "Global" };
+        let fts_path = db_path.join("fts");
+        let _lock = WriteLock::acquire(db_path, "optimize")?;
+        println!("{} {} database: {}", "🧹 Optimizing".bright_cyan(), db_type, db_path.display());
+        compact_vectors(db_path, &StoreOptions::from_config(config))?;
+        if !fts_path.join("meta.json").exists() {
+            println!("{}", format!("⚠️  {} database has no full-text index, skipping", db_type).yellow());
+            continue;
+        }
+
+        let start = Instant::now();
+        let size_before = dir_size(&fts_path);
+        let mut fts_store = FtsStore::new(db_path)?;
+        let stats = fts_store.optimize()?;
+        drop(fts_store);
+        let size_after = dir_size(&fts_path);
+
+        println!("   Segments: {} → {}", stats.segments_before, stats.segments_after);
+        println!("   Deleted documents purged: {}", stats.deleted_documents);
+        println!("   Size: {:.1} MB → {:.1} MB", mb(size_before), mb(size_after));
+        println!("{}", format!("✅ Done in {:?}", start.elapsed()).green());
+    }
+    Ok(())
+}
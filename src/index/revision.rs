@@ -0,0 +1,131 @@
+//! Indexing a git revision instead of the working tree (`index --rev`)
+//!
+//! CI indexes release tags so that searches see exactly what shipped, not
+//! whatever the build checkout happens to contain. The files of the revision
+//! are read from the git object store and written to a temporary snapshot,
+//! which the usual pipeline then indexes; chunks and file metadata are
+//! stored under the project's own paths, so results look like those of a
+//! working-tree index. The revision is recorded in `metadata.json`, and an
+//! index pinned to one is never reported as stale.
+
+use anyhow::{anyhow, Result};
+use git2::{ObjectType, Repository, TreeWalkMode, TreeWalkResult};
+use std::path::{Path, PathBuf};
+use tempfile::TempDir;
+
+/// Git's file mode for symbolic links, which are not indexed
+const SYMLINK_MODE: i32 = 0o120000;
+
+/// The files of one revision, written out below a temporary directory
+pub struct Snapshot {
+    dir: TempDir,
+    /// The commit the revision resolved to
+    pub commit: String,
+}
+
+impl Snapshot {
+    /// Write the files of `rev` under `project` (part of a git repository) to a temporary directory
+    ///
+    /// Only the part of the tree below `project` is written, so a project in
+    /// a subdirectory of a monorepo snapshots just that subdirectory.
+    pub fn checkout(project: &Path, rev: &str) -> Result<Self> {
+        let repo = Repository::discover(project)?;
+        let workdir = repo.workdir().ok_or_else(|| anyhow!("--rev needs a repository with a working tree"))?;
+        let prefix = project.canonicalize()?.strip_prefix(workdir.canonicalize()?)?.to_path_buf();
+        let commit = repo
+            .revparse_single(rev)
+            .and_then(|object| object.peel_to_commit())
+            .map_err(|e| anyhow!("Unknown revision '{}': {}", rev, e.message()))?;
+
+        let dir = tempfile::tempdir()?;
+        let mut error = None;
+        let walked = commit.tree()?.walk(TreeWalkMode::PreOrder, |parent, entry| {
+            // Submodules are other repositories; symlinks may point anywhere
+            if entry.kind() != Some(ObjectType::Blob) || entry.filemode() == SYMLINK_MODE {
+                return TreeWalkResult::Ok;
+            }
+            let Some(name) = entry.name() else {
+                return TreeWalkResult::Ok;
+            };
+            let path = Path::new(parent).join(name);
+            let Ok(relative) = path.strip_prefix(&prefix) else {
+                return TreeWalkResult::Ok;
+            };
+            let target = dir.path().join(relative);
+            let written = repo.find_blob(entry.id()).map_err(anyhow::Error::from).and_then(|blob| {
+                std::fs::create_dir_all(target.parent().unwrap_or(dir.path()))?;
+                std::fs::write(&target, blob.content())?;
+                Ok(())
+            });
+            match written {
+                Ok(()) => TreeWalkResult::Ok,
+                Err(e) => {
+                    error = Some(e);
+                    TreeWalkResult::Abort
+                }
+            }
+        });
+        // An aborted walk fails too; the write error says why
+        if let Some(e) = error {
+            return Err(e);
+        }
+        walked?;
+        Ok(Self { dir, commit: commit.id().to_string() })
+    }
+
+    /// Directory the snapshot is written to
+    pub fn root(&self) -> &Path {
+        self.dir.path()
+    }
+
+    /// Where a snapshot file lives in the project, the path it is indexed under
+    pub fn project_path(&self, project: &Path, file: &Path) -> PathBuf {
+        match file.strip_prefix(self.dir.path()) {
+            Ok(relative) => project.join(relative),
+            Err(_) => file.to_path_buf(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process::Command;
+
+    fn git(dir: &Path, args: &[&str]) {
+        let status = Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+            .args(args)
+            .status()
+            .unwrap();
+        assert!(status.success());
+    }
+
+    #[test]
+    fn test_checkout() {
+        let repo = tempfile::tempdir().unwrap();
+        let root = repo.path();
+        git(root, &["init", "-q"]);
+        std::fs::create_dir_all(root.join("app/src")).unwrap();
+        std::fs::write(root.join("app/src/lib.rs"), "fn shipped() {}").unwrap();
+        std::fs::write(root.join("README.md"), "outside the project").unwrap();
+        git(root, &["add", "."]);
+        git(root, &["commit", "-q", "-m", "v1"]);
+        git(root, &["tag", "v1.0.0"]);
+        std::fs::write(root.join("app/src/lib.rs"), "fn unreleased() {}").unwrap();
+        std::fs::write(root.join("app/src/new.rs"), "fn untracked() {}").unwrap();
+
+        let project = root.join("app");
+        let snapshot = Snapshot::checkout(&project, "v1.0.0").unwrap();
+        assert_eq!(snapshot.commit.len(), 40);
+        let file = snapshot.root().join("src/lib.rs");
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "fn shipped() {}");
+        assert!(!snapshot.root().join("src/new.rs").exists());
+        assert!(!snapshot.root().join("README.md").exists());
+        assert_eq!(snapshot.project_path(Path::new("."), &file), Path::new("./src/lib.rs"));
+
+        assert!(Snapshot::checkout(&project, "v9.9.9").is_err());
+    }
+}
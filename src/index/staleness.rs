@@ -0,0 +1,155 @@
+//! Stale index detection (`demongrep search`)
+//!
+//! Before searching, a cheap fingerprint of the project is compared with
+//! the index: files modified since the database was last written, and
+//! commits made since the git HEAD recorded when it was indexed. Either can
+//! be off (a file touched without changes counts), so the result only
+//! decides whether to warn or to run an automatic sync.
+
+use anyhow::Result;
+use std::path::Path;
+use std::time::SystemTime;
+
+use crate::config::IndexingConfig;
+use crate::file::{FileInfo, FileWalker};
+
+/// How far an index is behind the working tree
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Staleness {
+    /// Files modified or added since the index was last written
+    pub files_changed: usize,
+    /// Commits since the recorded HEAD (None outside git or when unknown)
+    pub commits_behind: Option<usize>,
+}
+
+impl Staleness {
+    /// "3 commits / 12 files" or "12 files"
+    pub fn describe(&self) -> String {
+        let files = format!("{} file{}", self.files_changed, if self.files_changed == 1 { "" } else { "s" });
+        match self.commits_behind {
+            Some(commits) if commits > 0 => {
+                format!("{} commit{} / {}", commits, if commits == 1 { "" } else { "s" }, files)
+            }
+            _ => files,
+        }
+    }
+}
+
+/// Output of a git command run in `root`, None if it failed
+pub(super) fn git(root: &Path, args: &[&str]) -> Option<String> {
+    std::process::Command::new("git")
+        .arg("-C")
+        .arg(root)
+        .args(args)
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Commit checked out in `root`, if it is a git repository
+pub fn git_head(root: &Path) -> Option<String> {
+    git(root, &["rev-parse", "HEAD"])
+}
+
+fn commits_since(root: &Path, commit: &str) -> Option<usize> {
+    git(root, &["rev-list", "--count", &format!("{}..HEAD", commit)])?.parse().ok()
+}
+
+fn read_metadata(db_path: &Path) -> Option<serde_json::Value> {
+    serde_json::from_str(&std::fs::read_to_string(db_path.join("metadata.json")).ok()?).ok()
+}
+
+/// When the database was last written: indexed, synced or updated by `serve`
+fn last_written(db_path: &Path, metadata: Option<&serde_json::Value>) -> Option<SystemTime> {
+    let indexed_at = metadata
+        .and_then(|m| m.get("indexed_at"))
+        .and_then(|v| v.as_str())
+        .and_then(|s| chrono::DateTime::parse_from_rfc3339(s).ok())
+        .map(SystemTime::from);
+    let lmdb_write = std::fs::metadata(db_path.join("data.mdb")).and_then(|m| m.modified()).ok();
+    indexed_at.max(lmdb_write)
+}
+
+/// Number of files modified after `since`
+pub fn changed_since(files: &[FileInfo], since: SystemTime) -> usize {
+    files
+        .iter()
+        .filter(|file| {
+            std::fs::metadata(&file.path)
+                .and_then(|m| m.modified())
+                .map(|modified| modified > since)
+                .unwrap_or(false)
+        })
+        .count()
+}
+
+/// Compare the project at `root` with the database at `db_path`
+///
+/// Returns None when the index looks current, or when there is nothing to
+/// compare against.
+pub fn check_staleness(root: &Path, db_path: &Path, config: &IndexingConfig) -> Result<Option<Staleness>> {
+    let metadata = read_metadata(db_path);
+    // Pinned to a revision (`index --rev`): the working tree is not what it indexed
+    if metadata.as_ref().and_then(|m| m.get("git_rev")).is_some_and(|rev| !rev.is_null()) {
+        return Ok(None);
+    }
+    let Some(since) = last_written(db_path, metadata.as_ref()) else {
+        return Ok(None);
+    };
+
+    let walker = FileWalker::new(root.to_path_buf()).with_excludes(&config.excludes);
+    let (files, _stats) = walker.walk()?;
+    let files_changed = changed_since(&files, since);
+    if files_changed == 0 {
+        return Ok(None);
+    }
+
+    let commits_behind = metadata
+        .as_ref()
+        .and_then(|m| m.get("git_head"))
+        .and_then(|v| v.as_str())
+        .and_then(|head| commits_since(root, head));
+
+    Ok(Some(Staleness { files_changed, commits_behind }))
+}
+
+/// Record the current time and git HEAD in the database's metadata.json
+pub fn record_fingerprint(root: &Path, db_path: &Path) -> Result<()> {
+    let mut metadata = read_metadata(db_path).unwrap_or_else(|| serde_json::json!({}));
+    if let Some(object) = metadata.as_object_mut() {
+        object.insert("indexed_at".to_string(), chrono::Utc::now().to_rfc3339().into());
+        object.insert("git_head".to_string(), git_head(root).into());
+    }
+    std::fs::write(db_path.join("metadata.json"), serde_json::to_string_pretty(&metadata)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file::Language;
+    use std::time::Duration;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_changed_since() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("lib.rs");
+        std::fs::write(&path, "fn main() {}").unwrap();
+        let files = vec![FileInfo { path, language: Language::Rust, size: 12 }];
+
+        let before = SystemTime::now() - Duration::from_secs(60);
+        let after = SystemTime::now() + Duration::from_secs(60);
+        assert_eq!(changed_since(&files, before), 1);
+        assert_eq!(changed_since(&files, after), 0);
+    }
+
+    #[test]
+    fn test_describe() {
+        let stale = Staleness { files_changed: 12, commits_behind: Some(3) };
+        assert_eq!(stale.describe(), "3 commits / 12 files");
+        let stale = Staleness { files_changed: 1, commits_behind: Some(0) };
+        assert_eq!(stale.describe(), "1 file");
+    }
+}
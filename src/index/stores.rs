@@ -0,0 +1,238 @@
+//! Global store inventory and cleanup (`demongrep doctor [--prune]`)
+//!
+//! Global databases live under `~/.demongrep/stores`, one directory per
+//! project and store, and `~/.demongrep/projects.json` maps project roots to
+//! them. Deleting or moving a project leaves its store behind. A store is
+//! orphaned when no registry entry points at it, or when the project it was
+//! built for no longer exists. A registry entry is stale when its store
+//! directory is gone.
+
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use super::parse_mapping_key;
+
+/// Total size of the files under `dir`
+pub fn dir_size(dir: &Path) -> u64 {
+    walkdir::WalkDir::new(dir)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| entry.metadata().ok())
+        .filter(|meta| meta.is_file())
+        .map(|meta| meta.len())
+        .sum()
+}
+
+/// One directory under `~/.demongrep/stores`
+#[derive(Debug, Clone, Serialize)]
+pub struct GlobalStore {
+    pub path: PathBuf,
+    /// Project root from the registry (None if no entry points here)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub project: Option<PathBuf>,
+    /// Named store (`--store`), if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub store: Option<String>,
+    pub size_bytes: u64,
+    /// When the store was last indexed (RFC 3339, from metadata.json)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub indexed_at: Option<String>,
+    pub project_exists: bool,
+}
+
+impl GlobalStore {
+    /// No registry entry points here, or the project is gone
+    pub fn is_orphaned(&self) -> bool {
+        !self.project_exists
+    }
+}
+
+/// Global stores plus registry entries whose store directory is gone
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct StoreInventory {
+    pub stores: Vec<GlobalStore>,
+    /// projects.json keys pointing at missing directories
+    pub stale_entries: Vec<String>,
+}
+
+impl StoreInventory {
+    pub fn orphaned(&self) -> impl Iterator<Item = &GlobalStore> {
+        self.stores.iter().filter(|s| s.is_orphaned())
+    }
+
+    pub fn total_bytes(&self) -> u64 {
+        self.stores.iter().map(|s| s.size_bytes).sum()
+    }
+
+    pub fn orphaned_bytes(&self) -> u64 {
+        self.orphaned().map(|s| s.size_bytes).sum()
+    }
+}
+
+/// What `prune_stores` removed
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PruneSummary {
+    pub stores_removed: usize,
+    pub entries_removed: usize,
+    pub bytes_freed: u64,
+}
+
+fn home_paths() -> Option<(PathBuf, PathBuf)> {
+    let base = dirs::home_dir()?.join(".demongrep");
+    Some((base.join("stores"), base.join("projects.json")))
+}
+
+fn read_mappings(mapping_file: &Path) -> Result<HashMap<String, String>> {
+    if !mapping_file.exists() {
+        return Ok(HashMap::new());
+    }
+    Ok(serde_json::from_str(&std::fs::read_to_string(mapping_file)?)?)
+}
+
+fn indexed_at(db_path: &Path) -> Option<String> {
+    let content = std::fs::read_to_string(db_path.join("metadata.json")).ok()?;
+    let json: serde_json::Value = serde_json::from_str(&content).ok()?;
+    json.get("indexed_at")?.as_str().map(|s| s.to_string())
+}
+
+/// Inventory the stores in `stores_dir` against the registry in `mapping_file`
+pub fn scan_stores(stores_dir: &Path, mapping_file: &Path) -> Result<StoreInventory> {
+    let mappings = read_mappings(mapping_file)?;
+    let by_db: HashMap<PathBuf, &String> = mappings.iter().map(|(key, db)| (PathBuf::from(db), key)).collect();
+
+    let mut inventory = StoreInventory::default();
+    if stores_dir.exists() {
+        for entry in std::fs::read_dir(stores_dir)? {
+            let path = entry?.path();
+            if !path.is_dir() {
+                continue;
+            }
+            let (project, store) = match by_db.get(&path) {
+                Some(key) => {
+                    let (project, store) = parse_mapping_key(key);
+                    (Some(project), store.map(|s| s.to_string()))
+                }
+                None => (None, None),
+            };
+            inventory.stores.push(GlobalStore {
+                size_bytes: dir_size(&path),
+                indexed_at: indexed_at(&path),
+                project_exists: project.as_ref().map(|p| p.exists()).unwrap_or(false),
+                path,
+                project,
+                store,
+            });
+        }
+    }
+    inventory.stores.sort_by_key(|s| std::cmp::Reverse(s.size_bytes));
+
+    let mut stale: Vec<String> = mappings
+        .iter()
+        .filter(|(_, db)| !Path::new(db.as_str()).exists())
+        .map(|(key, _)| key.clone())
+        .collect();
+    stale.sort();
+    inventory.stale_entries = stale;
+
+    Ok(inventory)
+}
+
+/// Inventory `~/.demongrep/stores`
+pub fn global_stores() -> Result<StoreInventory> {
+    match home_paths() {
+        Some((stores_dir, mapping_file)) => scan_stores(&stores_dir, &mapping_file),
+        None => Ok(StoreInventory::default()),
+    }
+}
+
+/// Delete orphaned stores and drop their registry entries, plus stale entries
+pub fn prune_stores(inventory: &StoreInventory, stores_dir: &Path, mapping_file: &Path) -> Result<PruneSummary> {
+    let mut summary = PruneSummary::default();
+    let mut removed: Vec<&Path> = Vec::new();
+
+    for store in inventory.orphaned() {
+        // Never delete anything outside the stores directory
+        if store.path.parent() != Some(stores_dir) {
+            continue;
+        }
+        std::fs::remove_dir_all(&store.path)?;
+        summary.stores_removed += 1;
+        summary.bytes_freed += store.size_bytes;
+        removed.push(&store.path);
+    }
+
+    let mut mappings = read_mappings(mapping_file)?;
+    let before = mappings.len();
+    mappings.retain(|key, db| {
+        !inventory.stale_entries.contains(key) && !removed.iter().any(|path| *path == Path::new(db.as_str()))
+    });
+    summary.entries_removed = before - mappings.len();
+    if summary.entries_removed > 0 {
+        std::fs::write(mapping_file, serde_json::to_string_pretty(&mappings)?)?;
+    }
+
+    Ok(summary)
+}
+
+/// Prune `~/.demongrep/stores` and `projects.json`
+pub fn prune_global_stores(inventory: &StoreInventory) -> Result<PruneSummary> {
+    match home_paths() {
+        Some((stores_dir, mapping_file)) => prune_stores(inventory, &stores_dir, &mapping_file),
+        None => Ok(PruneSummary::default()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_scan_and_prune_stores() {
+        let home = tempdir().unwrap();
+        let stores_dir = home.path().join("stores");
+        let mapping_file = home.path().join("projects.json");
+        let project = home.path().join("project");
+        std::fs::create_dir_all(&project).unwrap();
+
+        let live = stores_dir.join("aaa");
+        let moved = stores_dir.join("bbb-docs");
+        let unregistered = stores_dir.join("ccc");
+        for dir in [&live, &moved, &unregistered] {
+            std::fs::create_dir_all(dir).unwrap();
+            std::fs::write(dir.join("data.mdb"), "12345").unwrap();
+        }
+        std::fs::write(live.join("metadata.json"), r#"{"indexed_at": "2026-01-02T03:04:05Z"}"#).unwrap();
+
+        let mappings: HashMap<String, String> = [
+            (project.to_string_lossy().to_string(), live.to_string_lossy().to_string()),
+            ("/gone/project#docs".to_string(), moved.to_string_lossy().to_string()),
+            ("/other/project".to_string(), stores_dir.join("deleted").to_string_lossy().to_string()),
+        ]
+        .into_iter()
+        .collect();
+        std::fs::write(&mapping_file, serde_json::to_string(&mappings).unwrap()).unwrap();
+
+        let inventory = scan_stores(&stores_dir, &mapping_file).unwrap();
+        assert_eq!(inventory.stores.len(), 3);
+        assert_eq!(inventory.total_bytes(), 15 + 38);
+        let orphaned: Vec<&Path> = inventory.orphaned().map(|s| s.path.as_path()).collect();
+        assert_eq!(orphaned.len(), 2);
+        assert!(orphaned.contains(&moved.as_path()) && orphaned.contains(&unregistered.as_path()));
+        let docs = inventory.stores.iter().find(|s| s.path == moved).unwrap();
+        assert_eq!(docs.store.as_deref(), Some("docs"));
+        let kept = inventory.stores.iter().find(|s| s.path == live).unwrap();
+        assert_eq!(kept.indexed_at.as_deref(), Some("2026-01-02T03:04:05Z"));
+        assert_eq!(inventory.stale_entries, ["/other/project"]);
+
+        let summary = prune_stores(&inventory, &stores_dir, &mapping_file).unwrap();
+        assert_eq!((summary.stores_removed, summary.entries_removed, summary.bytes_freed), (2, 2, 10));
+        assert!(live.exists() && !moved.exists() && !unregistered.exists());
+
+        let remaining = read_mappings(&mapping_file).unwrap();
+        assert_eq!(remaining.len(), 1);
+        assert!(remaining.contains_key(project.to_string_lossy().as_ref()));
+    }
+}
@@ -0,0 +1,165 @@
+use anyhow::Result;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use super::journal::{self, Journal};
+use super::refresh_file_vectors;
+use crate::cache::FileMetaStore;
+use crate::chunker::SemanticChunker;
+use crate::config::IndexingConfig;
+use crate::embed::EmbeddingService;
+use crate::file::{FileInfo, FileWalker};
+use crate::fts::FtsStore;
+use crate::vectordb::VectorStore;
+
+/// Files whose indexed state no longer matches the working tree
+#[derive(Debug, Default)]
+pub struct PendingChanges {
+    /// New or modified files, with the chunk IDs they previously owned
+    pub changed: Vec<(FileInfo, Vec<u32>)>,
+    /// Files removed from disk, with the chunk IDs they owned
+    pub deleted: Vec<(PathBuf, Vec<u32>)>,
+}
+
+impl PendingChanges {
+    /// Number of files that differ from the index
+    pub fn file_count(&self) -> usize {
+        self.changed.len() + self.deleted.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.file_count() == 0
+    }
+}
+
+/// Compare the working tree against a store's file metadata
+///
+/// `root` must be the same path form the index was built with, since file
+/// metadata is keyed by the walker's paths.
+pub fn pending_changes(store: &VectorStore, root: &Path, config: &IndexingConfig) -> Result<PendingChanges> {
+    let walker = FileWalker::new(root.to_path_buf()).with_excludes(&config.excludes);
+    let (files, _stats) = walker.walk()?;
+
+    let mut changes = PendingChanges::default();
+    for file in files {
+        match store.check_file_needs_reindex(&file.path) {
+            Ok((false, _)) => {}
+            Ok((true, old_chunk_ids)) => changes.changed.push((file, old_chunk_ids)),
+            Err(_) => changes.changed.push((file, vec![])),
+        }
+    }
+
+    for (path, chunk_ids) in store.find_deleted_files()? {
+        changes.deleted.push((PathBuf::from(path), chunk_ids));
+    }
+
+    Ok(changes)
+}
+
+/// Re-index the given changes into the vector store, FTS index and file metadata
+///
+/// The update is journaled (see `journal`): if it is interrupted, the next
+/// call rolls it back before applying anything, so the stores never keep
+/// disagreeing. Call with the database's write lock held. Returns the number
+/// of chunks inserted.
+pub fn apply_changes(
+    store: &mut VectorStore,
+    db_path: &Path,
+    changes: &PendingChanges,
+    embedding_service: &mut EmbeddingService,
+    chunker: &mut SemanticChunker,
+) -> Result<usize> {
+    journal::recover_update(db_path, store)?;
+    if changes.is_empty() {
+        return Ok(0);
+    }
+
+    // Chunk and embed the changed files before writing anything
+    let mut all_chunks = Vec::new();
+    for (file, _) in &changes.changed {
+        let Ok(source_code) = std::fs::read_to_string(&file.path) else {
+            continue;
+        };
+        all_chunks.extend(chunker.chunk_semantic(file.language, &file.path, &source_code)?);
+    }
+
+    store.attach_notes(&mut all_chunks)?;
+    let embedded_chunks = embedding_service.embed_chunks(all_chunks)?;
+
+    // Drop everything the changed and deleted files used to own
+    let stale_ids: Vec<u32> = changes
+        .changed
+        .iter()
+        .flat_map(|(_, ids)| ids.iter().copied())
+        .chain(changes.deleted.iter().flat_map(|(_, ids)| ids.iter().copied()))
+        .collect();
+    let journal = Journal {
+        files: changes
+            .changed
+            .iter()
+            .map(|(file, _)| file.path.clone())
+            .chain(changes.deleted.iter().map(|(path, _)| path.clone()))
+            .collect(),
+        deleted_ids: stale_ids.clone(),
+        first_new_id: store.next_id(),
+    };
+    journal.begin(db_path)?;
+
+    let mut fts_store = FtsStore::new(db_path)?;
+    store.delete_chunks(&stale_ids)?;
+    for id in &stale_ids {
+        fts_store.delete_chunk(*id)?;
+    }
+
+    let chunk_ids = store.insert_chunks_with_ids(embedded_chunks.clone())?;
+
+    let mut file_chunks: HashMap<PathBuf, Vec<u32>> = HashMap::new();
+    for (chunk, chunk_id) in embedded_chunks.iter().zip(chunk_ids.iter()) {
+        fts_store.add_chunk(
+            *chunk_id,
+            &chunk.chunk.content,
+            &chunk.chunk.path,
+            chunk.chunk.name.as_deref(),
+            chunk.chunk.signature.as_deref(),
+            chunk.chunk.docstring.as_deref(),
+            &format!("{:?}", chunk.chunk.kind),
+            &chunk.chunk.string_literals,
+            &chunk.chunk.tags,
+            chunk.chunk.package.as_deref(),
+        )?;
+        file_chunks
+            .entry(PathBuf::from(&chunk.chunk.path))
+            .or_default()
+            .push(*chunk_id);
+    }
+    fts_store.commit()?;
+    fts_store.merge_if_fragmented()?;
+
+    store.build_index()?;
+
+    // `serve` tracks files in file_meta.json; keep it in step when present
+    let mut file_meta = FileMetaStore::load(db_path)?;
+    for (file, _) in &changes.changed {
+        let ids = file_chunks.remove(&file.path).unwrap_or_default();
+        if let Some(file_meta) = file_meta.as_mut() {
+            file_meta.update_file(&file.path, ids.clone())?;
+        }
+        store.update_file_metadata(&file.path, ids)?;
+    }
+    for (path, _) in &changes.deleted {
+        if let Some(file_meta) = file_meta.as_mut() {
+            file_meta.remove_file(path);
+        }
+        store.remove_file_metadata(path)?;
+    }
+    if let Some(file_meta) = file_meta {
+        file_meta.save(db_path)?;
+    }
+    // Indexes built with file summaries keep them in step
+    if store.has_file_vectors()? {
+        refresh_file_vectors(store, embedding_service)?;
+    }
+
+    Journal::finish(db_path)?;
+    Ok(chunk_ids.len())
+}
@@ -0,0 +1,238 @@
+use anyhow::Result;
+use colored::Colorize;
+use rand::seq::SliceRandom;
+use std::path::Path;
+
+use crate::fts::FtsStore;
+use crate::vectordb::{StoreOptions, VectorStore};
+
+/// Number of neighbours a sampled chunk must appear in when queried with its own vector
+const SELF_CHECK_K: usize = 10;
+
+/// Minimum fraction of sampled chunks that must find themselves
+const MIN_SELF_RECALL: f32 = 0.9;
+
+/// Outcome of a post-index verification pass
+#[derive(Debug, Clone, Default)]
+pub struct VerifyReport {
+    /// Chunks in the vector store metadata table
+    pub total_chunks: usize,
+    /// Documents in the FTS index (None if the FTS index could not be opened)
+    pub fts_documents: Option<usize>,
+    /// Number of chunks sampled for the self-recall check
+    pub sampled: usize,
+    /// Sampled chunks that have metadata but no stored vector
+    pub missing_vectors: Vec<u32>,
+    /// Sampled chunks that were not returned when searching with their own vector
+    pub recall_misses: Vec<u32>,
+}
+
+impl VerifyReport {
+    /// Fraction of sampled chunks that were retrieved by their own vector
+    pub fn self_recall(&self) -> f32 {
+        if self.sampled == 0 {
+            return 1.0;
+        }
+        let hits = self.sampled - self.missing_vectors.len() - self.recall_misses.len();
+        hits as f32 / self.sampled as f32
+    }
+
+    /// Human-readable list of inconsistencies (empty if the index is healthy)
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+
+        match self.fts_documents {
+            Some(docs) if docs != self.total_chunks => problems.push(format!(
+                "FTS document count ({}) does not match vector store chunk count ({})",
+                docs, self.total_chunks
+            )),
+            None => problems.push("FTS index is missing or unreadable".to_string()),
+            _ => {}
+        }
+
+        if !self.missing_vectors.is_empty() {
+            problems.push(format!(
+                "{} of {} sampled chunks have no stored vector",
+                self.missing_vectors.len(),
+                self.sampled
+            ));
+        }
+
+        if self.self_recall() < MIN_SELF_RECALL {
+            problems.push(format!(
+                "Self-recall@{} is {:.1}% (expected at least {:.0}%)",
+                SELF_CHECK_K,
+                self.self_recall() * 100.0,
+                MIN_SELF_RECALL * 100.0
+            ));
+        }
+
+        problems
+    }
+
+    pub fn is_ok(&self) -> bool {
+        self.problems().is_empty()
+    }
+}
+
+/// Sample chunks and check that each one is retrievable using its own vector
+pub fn check_store(store: &VectorStore, fts_documents: Option<usize>, sample_size: usize) -> Result<VerifyReport> {
+    let ids = store.chunk_ids()?;
+    let mut rng = rand::thread_rng();
+    let sample: Vec<u32> = ids.choose_multiple(&mut rng, sample_size).copied().collect();
+
+    let mut report = VerifyReport {
+        total_chunks: ids.len(),
+        fts_documents,
+        sampled: sample.len(),
+        ..Default::default()
+    };
+
+    for id in sample {
+        let Some(vector) = store.get_vector(id)? else {
+            report.missing_vectors.push(id);
+            continue;
+        };
+
+        let results = store.search(&vector, SELF_CHECK_K)?;
+        if !results.iter().any(|r| r.id == id) {
+            report.recall_misses.push(id);
+        }
+    }
+
+    Ok(report)
+}
+
+/// Store options that send every search through the ANN index
+///
+/// Small stores are otherwise searched exactly, so the self-recall check
+/// would never touch the index it is meant to verify.
+fn ann_options() -> StoreOptions {
+    StoreOptions {
+        exact_threshold: 0,
+        ..StoreOptions::default()
+    }
+}
+
+/// Run the verification pass against a database and print diagnostics
+///
+/// Returns an error if the index is internally inconsistent.
+pub fn verify(db_path: &Path, dimensions: usize, sample_size: usize) -> Result<VerifyReport> {
+    println!("\n{}", "🔎 Verifying index...".bright_cyan());
+
+    let store = VectorStore::with_options(db_path, dimensions, &ann_options())?;
+    let fts_documents = FtsStore::open_readonly(db_path)
+        .and_then(|fts| fts.stats())
+        .map(|stats| stats.num_documents)
+        .ok();
+
+    let report = check_store(&store, fts_documents, sample_size)?;
+
+    println!("   Chunks: {}", report.total_chunks);
+    match report.fts_documents {
+        Some(docs) => println!("   FTS documents: {}", docs),
+        None => println!("   FTS documents: {}", "unavailable".red()),
+    }
+    println!(
+        "   Self-recall@{}: {:.1}% ({} sampled)",
+        SELF_CHECK_K,
+        report.self_recall() * 100.0,
+        report.sampled
+    );
+
+    let problems = report.problems();
+    if problems.is_empty() {
+        println!("{}", "✅ Index verified".green());
+        return Ok(report);
+    }
+
+    println!("\n{}", "❌ Index verification failed".red().bold());
+    for problem in &problems {
+        println!("   • {}", problem);
+    }
+    for id in report.missing_vectors.iter().chain(report.recall_misses.iter()).take(10) {
+        if let Some(chunk) = store.get_chunk(*id)? {
+            println!(
+                "     chunk {} {}:{}-{}",
+                id,
+                chunk.path,
+                chunk.start_line + 1,
+                chunk.end_line
+            );
+        }
+    }
+    println!(
+        "\n   Run {} to rebuild the index from scratch",
+        "demongrep clear && demongrep index".bright_cyan()
+    );
+
+    Err(anyhow::anyhow!("Index verification failed: {}", problems.join("; ")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunker::{Chunk, ChunkKind};
+    use crate::embed::EmbeddedChunk;
+    use tempfile::tempdir;
+
+    fn chunk(name: &str, embedding: Vec<f32>) -> EmbeddedChunk {
+        EmbeddedChunk::new(
+            Chunk::new(
+                format!("fn {}() {{}}", name),
+                0,
+                1,
+                ChunkKind::Function,
+                format!("{}.rs", name),
+            ),
+            embedding,
+        )
+    }
+
+    #[test]
+    fn test_healthy_store_passes() {
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("test.db");
+        let mut store = VectorStore::with_options(&db_path, 4, &ann_options()).unwrap();
+        store
+            .insert_chunks(vec![
+                chunk("a", vec![1.0, 0.0, 0.0, 0.0]),
+                chunk("b", vec![0.0, 1.0, 0.0, 0.0]),
+                chunk("c", vec![0.0, 0.0, 1.0, 0.0]),
+            ])
+            .unwrap();
+        store.build_index().unwrap();
+        assert!(!store.searches_exactly().unwrap());
+
+        let report = check_store(&store, Some(3), 10).unwrap();
+        assert_eq!(report.sampled, 3);
+        assert!(report.is_ok(), "{:?}", report.problems());
+    }
+
+    #[test]
+    fn test_fts_count_mismatch_reported() {
+        let temp_dir = tempdir().unwrap();
+        let mut store = VectorStore::new(&temp_dir.path().join("test.db"), 4).unwrap();
+        store
+            .insert_chunks(vec![chunk("a", vec![1.0, 0.0, 0.0, 0.0])])
+            .unwrap();
+        store.build_index().unwrap();
+
+        let report = check_store(&store, Some(0), 10).unwrap();
+        assert!(!report.is_ok());
+        assert!(report.problems()[0].contains("FTS document count"));
+    }
+
+    #[test]
+    fn test_unbuilt_index_reports_missing_vectors() {
+        let temp_dir = tempdir().unwrap();
+        let mut store = VectorStore::new(&temp_dir.path().join("test.db"), 4).unwrap();
+        store
+            .insert_chunks(vec![chunk("a", vec![1.0, 0.0, 0.0, 0.0])])
+            .unwrap();
+
+        let report = check_store(&store, Some(1), 10).unwrap();
+        assert_eq!(report.missing_vectors.len(), 1);
+        assert!(!report.is_ok());
+    }
+}
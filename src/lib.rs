@@ -11,12 +11,17 @@ pub mod index;
 pub mod search;
 pub mod watch;
 pub mod server;
+pub mod daemon;
 pub mod bench;
+pub mod bundle;
 pub mod file;
 pub mod fts;
 pub mod mcp;
+pub mod scip;
+pub mod dataset;
 pub mod output;
 pub mod database;  // NEW: Add database module
+pub mod diagnostics;
 
 // Re-export commonly used types
 pub use config::Config;
@@ -12,12 +12,17 @@ mod index;
 mod search;
 mod watch;
 mod server;
+mod daemon;
 mod bench;
+mod bundle;
 mod file;
 mod fts;
 mod mcp;
+mod scip;
+mod dataset;
 mod output;
 mod database;  // NEW: Centralized database management
+mod diagnostics;
 
 use anyhow::Result;
 use tracing::info;
@@ -30,19 +35,25 @@ async fn main() -> Result<()> {
     let is_quiet = args.iter().any(|a| a == "-q" || a == "--quiet");
     let is_json = args.iter().any(|a| a == "--json");
 
-    // Skip tracing in quiet mode or JSON output
-    if !is_quiet && !is_json {
-        // Initialize tracing
-        tracing_subscriber::registry()
-            .with(
-                tracing_subscriber::EnvFilter::try_from_default_env()
-                    .unwrap_or_else(|_| "demongrep=info".into()),
-            )
-            .with(tracing_subscriber::fmt::layer())
-            .init();
+    // Console logging is skipped in quiet mode or JSON output; recent lines are
+    // always kept in memory for crash reports
+    let console = !is_quiet && !is_json;
+    tracing_subscriber::registry()
+        .with(
+            tracing_subscriber::EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| "demongrep=info".into()),
+        )
+        .with(console.then(tracing_subscriber::fmt::layer))
+        .with(
+            tracing_subscriber::fmt::layer()
+                .with_ansi(false)
+                .with_writer(|| diagnostics::LogCapture),
+        )
+        .init();
 
-        info!("Starting demongrep v{}", env!("CARGO_PKG_VERSION"));
-    }
+    info!("Starting demongrep v{}", env!("CARGO_PKG_VERSION"));
+
+    diagnostics::install_panic_hook();
 
     // Parse CLI and execute command
     cli::run().await
@@ -4,29 +4,72 @@
 //! allowing AI assistants like Claude to search codebases during conversations.
 //!
 //! **Now supports dual-database search**: Searches both local and global databases automatically.
+//!
+//! Before answering, tools check whether the index is stale and apply the
+//! configured refresh policy (`[mcp] refresh` in the config file).
+//!
+//! Search hits can be expanded through resources: `demongrep://chunk/{id}`
+//! returns a whole indexed chunk and `demongrep://file/{path}?start=&end=`
+//! reads lines of a project file, so agents need no separate file-read tool.
 
 use anyhow::Result;
 use rmcp::{
     handler::server::router::tool::ToolRouter,
     handler::server::wrapper::Parameters,
-    model::{CallToolResult, Content, ServerCapabilities, ServerInfo},
+    model::{
+        AnnotateAble, CallToolResult, Content, ListResourceTemplatesResult, PaginatedRequestParam,
+        RawResourceTemplate, ReadResourceRequestParam, ReadResourceResult, ResourceContents, ResourceTemplate,
+        ServerCapabilities, ServerInfo,
+    },
     schemars::JsonSchema,
-    tool, tool_handler, tool_router, ErrorData as McpError, ServerHandler,
+    service::RequestContext,
+    tool, tool_handler, tool_router, ErrorData as McpError, RoleServer, ServerHandler,
 };
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
-use std::sync::Mutex;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
 
+use crate::chunker::{Chunk, ChunkKind, SemanticChunker};
+use crate::config::{Config, RefreshPolicy};
 use crate::database::DatabaseManager;  // NEW: Use DatabaseManager
-use crate::embed::EmbeddingService;
+use crate::embed::{count_tokens, EmbeddingService};
+use crate::file::FileWalker;
+use crate::fts::{FtsFilter, FtsResult, FtsStore};
+use crate::index::{apply_changes, pending_changes, PendingChanges, WriteLock};
+use crate::rerank::{rerank_results, PathWeights, RerankerCache};
+use crate::search::result_tokens;
+use crate::vectordb::StoreOptions;
+
+/// Files listed by name in an index update response; the rest are only counted
+const MAX_LISTED_FILES: usize = 100;
+
+/// Candidates fetched per `find_similar` result, to survive dropping the source file
+const SIMILAR_OVERFETCH: usize = 4;
+
+/// Signature matches fetched per `lookup_symbol` result, to survive the kind filter
+const SYMBOL_OVERFETCH: usize = 4;
 
+/// Fewest lines worth returning of a result cut to fit max_tokens
+const MIN_TRUNCATED_LINES: usize = 3;
+
+/// Resource URI templates (RFC 6570)
+const CHUNK_RESOURCE: &str = "demongrep://chunk/{id}{?database}";
+const FILE_RESOURCE: &str = "demongrep://file/{+path}{?start,end}";
 
 /// Demongrep MCP service with dual-database support via DatabaseManager
 pub struct DemongrepService {
     tool_router: ToolRouter<DemongrepService>,
-    db_manager: DatabaseManager,  // NEW: Replaced db_paths with DatabaseManager
+    db_manager: RwLock<DatabaseManager>,  // Write-locked only while auto-syncing
     // Lazily initialized on first search
     embedding_service: Mutex<Option<EmbeddingService>>,
+    /// Cross-encoder kept across searches, loaded on first rerank
+    reranker: Arc<RerankerCache>,
+    /// Project root, in the same form the index was built with
+    project_root: PathBuf,
+    config: Config,
+    /// Time and stale file count of the last staleness check
+    last_check: Mutex<Option<(Instant, usize)>>,
 }
 
 impl std::fmt::Debug for DemongrepService {
@@ -41,11 +84,33 @@ impl std::fmt::Debug for DemongrepService {
 
 #[derive(Debug, Deserialize, JsonSchema)]
 pub struct SemanticSearchRequest {
-    /// The search query (natural language or code snippet)
+    /// The search query (natural language or code snippet). May include filters
+    /// like `lang:rust`, `kind:fn` and `path:src/`, and "quoted phrases"
     pub query: String,
 
     /// Maximum number of results to return (default: 10)
     pub limit: Option<usize>,
+
+    /// Rerank results with a cross-encoder for better accuracy (slower; default from config)
+    pub rerank: Option<bool>,
+
+    /// Approximate token budget for the results; lower-ranked results are cut at line
+    /// boundaries or left out to fit (default: no budget)
+    pub max_tokens: Option<usize>,
+
+    /// Only search these chunks: the `id`s of an earlier semantic_search, to narrow
+    /// its results with a new query instead of searching the whole index
+    pub previous_result_ids: Option<Vec<u32>>,
+
+    /// Only return chunks with one of these project tags (e.g. "api"; see `tags` in results)
+    pub tags: Option<Vec<String>>,
+
+    /// Leave out chunks with any of these project tags (e.g. "generated", "tests")
+    pub not_tags: Option<Vec<String>>,
+
+    /// Only return chunks from one of these packages, by the name in their Cargo.toml,
+    /// package.json, go.mod or pyproject.toml (see `package` in results)
+    pub packages: Option<Vec<String>>,
 }
 
 #[derive(Debug, Deserialize, JsonSchema)]
@@ -54,13 +119,59 @@ pub struct GetFileChunksRequest {
     pub path: String,
 }
 
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct LookupSymbolRequest {
+    /// Symbol name, e.g. "validate_token" or "UserConfig" (words of a signature also work)
+    pub name: String,
+
+    /// Only return definitions of this kind (e.g. "function", "struct", "class")
+    pub kind: Option<String>,
+
+    /// Match every symbol whose name starts with `name`, ignoring case (default: false)
+    pub prefix: Option<bool>,
+
+    /// Maximum number of definitions to return (default: 10)
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct FindSimilarRequest {
+    /// Code to find look-alikes of (alternatively give path with start_line/end_line)
+    pub code: Option<String>,
+
+    /// File the code is in (relative to project root); matches in this file are excluded
+    pub path: Option<String>,
+
+    /// First line of the code in path (1-based; default: 1)
+    pub start_line: Option<usize>,
+
+    /// Last line of the code in path (inclusive; default: end of file)
+    pub end_line: Option<usize>,
+
+    /// Maximum number of results to return (default: 10)
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct IndexUpdateRequest {
+    /// File or directory to limit the update to (relative to project root; default: whole project)
+    pub path: Option<String>,
+
+    /// Only report which files would be re-indexed or removed, without changing the index
+    pub dry_run: Option<bool>,
+}
+
 #[derive(Debug, Serialize)]
 pub struct SearchResultItem {
+    /// Chunk ID within its database, for `demongrep://chunk/{id}`
+    pub id: u32,
     pub path: String,
     pub start_line: usize,
     pub end_line: usize,
     pub kind: String,
     pub content: String,
+    /// LLM prompt tokens in `content`
+    pub tokens: usize,
     pub score: f32,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub signature: Option<String>,
@@ -70,6 +181,94 @@ pub struct SearchResultItem {
     pub context_next: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub database: Option<String>,
+    /// Team notes attached with `demongrep note`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub notes: Vec<String>,
+    /// Project tags from `indexing.tags`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Package the file belongs to
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub package: Option<String>,
+    /// Content was cut to fit max_tokens (end_line is where it stops)
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub truncated: bool,
+}
+
+/// Where a symbol is defined, without the code
+#[derive(Debug, Serialize)]
+pub struct SymbolLocation {
+    /// Chunk ID within its database, for `demongrep://chunk/{id}`
+    pub id: u32,
+    pub path: String,
+    pub start_line: usize,
+    pub end_line: usize,
+    pub kind: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+    /// Whether the signature declares exactly this name (listed first)
+    pub exact: bool,
+    /// BM25 score of the signature match
+    pub score: f32,
+    pub database: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SemanticSearchResponse {
+    pub results: Vec<SearchResultItem>,
+    pub diagnostics: SearchDiagnostics,
+}
+
+/// How a search was answered, for debugging slow or low-quality tool calls
+#[derive(Debug, Serialize)]
+pub struct SearchDiagnostics {
+    pub timing: SearchTiming,
+    pub databases: Vec<DatabaseInfo>,
+    pub model: String,
+    pub reranked: bool,
+    /// Whether changed files were re-indexed before searching
+    pub refreshed: bool,
+    /// Results left out to fit max_tokens (absent without a budget)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub omitted: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SearchTiming {
+    pub refresh_ms: u64,
+    pub model_load_ms: u64,
+    pub embed_ms: u64,
+    pub search_ms: u64,
+    pub rerank_ms: u64,
+    pub total_ms: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DatabaseInfo {
+    #[serde(rename = "type")]
+    pub db_type: String,
+    pub path: String,
+    pub chunks: usize,
+    /// When the database was last indexed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub indexed_at: Option<String>,
+    pub format_version: u32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct IndexUpdateResponse {
+    pub dry_run: bool,
+    /// Files (re-)indexed, or that would be on a dry run
+    pub files_indexed: usize,
+    /// Files removed from the index because they no longer exist
+    pub files_removed: usize,
+    /// Chunks written (0 on a dry run)
+    pub chunks_indexed: usize,
+    /// Up to the first 100 indexed file paths
+    pub indexed: Vec<String>,
+    /// Up to the first 100 removed file paths
+    pub removed: Vec<String>,
+    pub took_ms: u64,
 }
 
 #[derive(Debug, Serialize)]
@@ -85,6 +284,9 @@ pub struct IndexStatusResponse {
     pub dimensions: usize,
     pub databases: Vec<String>,
     pub databases_available: usize,
+    /// Files changed since the last index (None if the refresh policy is off)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stale_files: Option<usize>,
 }
 
 // === Tool Router Implementation ===
@@ -92,11 +294,15 @@ pub struct IndexStatusResponse {
 #[tool_router]
 impl DemongrepService {
     /// Create a new DemongrepService with DatabaseManager
-    pub fn new(db_manager: DatabaseManager) -> Result<Self> {
+    pub fn new(db_manager: DatabaseManager, project_root: PathBuf, config: Config) -> Result<Self> {
         Ok(Self {
             tool_router: Self::tool_router(),
-            db_manager,
+            db_manager: RwLock::new(db_manager),
             embedding_service: Mutex::new(None),
+            reranker: Arc::new(RerankerCache::from_idle_secs(config.search.reranker_idle_secs)),
+            project_root,
+            config,
+            last_check: Mutex::new(None),
         })
     }
 
@@ -104,19 +310,218 @@ impl DemongrepService {
     fn get_embedding_service(&self) -> Result<std::sync::MutexGuard<'_, Option<EmbeddingService>>> {
         let mut guard = self.embedding_service.lock().unwrap();
         if guard.is_none() {
-            *guard = Some(EmbeddingService::with_model(self.db_manager.model_type())?);
+            let model_type = self.db_manager.read().unwrap().model_type();
+            *guard = Some(EmbeddingService::with_model(model_type)?);
         }
         Ok(guard)
     }
 
-    #[tool(description = "Search the codebase using semantic similarity. Searches both local and global databases. Returns code chunks that are semantically similar to the query.")]
+    /// Count files that changed since the last index, re-checking at most once per interval
+    fn stale_file_count(&self) -> Result<usize> {
+        let interval = Duration::from_secs(self.config.mcp.check_interval_secs);
+        if let Some((checked_at, count)) = *self.last_check.lock().unwrap() {
+            if checked_at.elapsed() < interval {
+                return Ok(count);
+            }
+        }
+
+        let db_manager = self.db_manager.read().unwrap();
+        let mut count = 0;
+        for database in db_manager.databases() {
+            count += pending_changes(database.store(), &self.project_root, &self.config.indexing)?.file_count();
+        }
+
+        *self.last_check.lock().unwrap() = Some((Instant::now(), count));
+        Ok(count)
+    }
+
+    /// Re-index changed files in every loaded database
+    fn sync_databases(&self) -> Result<usize> {
+        let mut service_guard = self.get_embedding_service()?;
+        let service = service_guard.as_mut().unwrap();
+        let mut chunker = SemanticChunker::from_config(&self.config.indexing).with_project_root(&self.project_root);
+
+        let mut db_manager = self.db_manager.write().unwrap();
+        let mut synced = 0;
+        for database in db_manager.databases_mut() {
+            let changes = pending_changes(database.store(), &self.project_root, &self.config.indexing)?;
+            if changes.is_empty() {
+                continue;
+            }
+            let db_path = database.path.clone();
+            let _write_lock = WriteLock::acquire(&db_path, "mcp")?;
+            apply_changes(database.store_mut(), &db_path, &changes, service, &mut chunker)?;
+            synced += changes.file_count();
+        }
+
+        *self.last_check.lock().unwrap() = Some((Instant::now(), 0));
+        Ok(synced)
+    }
+
+    /// Changes to apply to one database for an `index_project` or `sync_project` call
+    ///
+    /// Only files under `scope` are included. With `full`, every file in scope
+    /// is re-indexed, not just the ones that changed.
+    fn scoped_changes(&self, store: &crate::vectordb::VectorStore, scope: &Path, full: bool) -> Result<PendingChanges> {
+        let mut changes = pending_changes(store, &self.project_root, &self.config.indexing)?;
+        changes.changed.retain(|(file, _)| file.path.starts_with(scope));
+        changes.deleted.retain(|(path, _)| path.starts_with(scope));
+
+        if full {
+            let walker = FileWalker::new(self.project_root.clone()).with_excludes(&self.config.indexing.excludes);
+            let (files, _stats) = walker.walk()?;
+            changes.changed = files
+                .into_iter()
+                .filter(|file| file.path.starts_with(scope))
+                .map(|file| {
+                    let old_ids = store.file_chunk_ids(&file.path.to_string_lossy())?;
+                    Ok((file, old_ids))
+                })
+                .collect::<Result<_>>()?;
+        }
+
+        Ok(changes)
+    }
+
+    /// Re-index files under `path` in every loaded database, or report what would change
+    fn update_index(&self, request: IndexUpdateRequest, full: bool) -> Result<IndexUpdateResponse> {
+        let start = Instant::now();
+        let dry_run = request.dry_run.unwrap_or(false);
+        let scope = match request.path.as_deref().map(|p| p.trim_start_matches("./")) {
+            Some(path) if !path.is_empty() && path != "." => self.project_root.join(path),
+            _ => self.project_root.clone(),
+        };
+
+        let mut indexed = std::collections::BTreeSet::new();
+        let mut removed = std::collections::BTreeSet::new();
+        let mut chunks_indexed = 0;
+
+        if dry_run {
+            let db_manager = self.db_manager.read().unwrap();
+            for database in db_manager.databases() {
+                let changes = self.scoped_changes(database.store(), &scope, full)?;
+                indexed.extend(changes.changed.iter().map(|(file, _)| file.path.display().to_string()));
+                removed.extend(changes.deleted.iter().map(|(path, _)| path.display().to_string()));
+            }
+        } else {
+            // Same lock order as `sync_databases`: embedding service, then databases
+            let mut service_guard = self.get_embedding_service()?;
+            let service = service_guard.as_mut().unwrap();
+            let mut chunker = SemanticChunker::from_config(&self.config.indexing).with_project_root(&self.project_root);
+
+            let mut db_manager = self.db_manager.write().unwrap();
+            for database in db_manager.databases_mut() {
+                let changes = self.scoped_changes(database.store(), &scope, full)?;
+                let db_path = database.path.clone();
+                let _write_lock = WriteLock::acquire(&db_path, "mcp")?;
+                chunks_indexed += apply_changes(database.store_mut(), &db_path, &changes, service, &mut chunker)?;
+                indexed.extend(changes.changed.iter().map(|(file, _)| file.path.display().to_string()));
+                removed.extend(changes.deleted.iter().map(|(path, _)| path.display().to_string()));
+            }
+
+            // Force a fresh staleness check on the next request
+            *self.last_check.lock().unwrap() = None;
+        }
+
+        Ok(IndexUpdateResponse {
+            dry_run,
+            files_indexed: indexed.len(),
+            files_removed: removed.len(),
+            chunks_indexed,
+            indexed: indexed.into_iter().take(MAX_LISTED_FILES).collect(),
+            removed: removed.into_iter().take(MAX_LISTED_FILES).collect(),
+            took_ms: start.elapsed().as_millis() as u64,
+        })
+    }
+
+    /// A chunk by ID as JSON, from the named database or the first one that has it
+    fn read_chunk(&self, id: u32, database: Option<&str>) -> Result<String> {
+        let db_manager = self.db_manager.read().unwrap();
+        for db in db_manager.databases() {
+            if database.is_some_and(|name| !db.db_type.name().eq_ignore_ascii_case(name)) {
+                continue;
+            }
+            if let Some(result) = db.store().get_chunk_as_result(id)? {
+                let mut item = to_result_item(&db_manager, result);
+                item.database = Some(db.db_type.name().to_lowercase());
+                return Ok(serde_json::to_string_pretty(&item)?);
+            }
+        }
+        anyhow::bail!("No chunk with id {} (ids change when files are re-indexed; search again)", id)
+    }
+
+    /// Apply the refresh policy before answering a request
+    ///
+    /// Returns a note to prepend to the tool output, if any.
+    fn refresh_index(&self) -> Option<String> {
+        self.refresh_index_inner().0
+    }
+
+    /// Like `refresh_index`, but also reports whether a sync actually ran
+    fn refresh_index_inner(&self) -> (Option<String>, bool) {
+        let policy = self.config.mcp.refresh;
+        if policy == RefreshPolicy::Off {
+            return (None, false);
+        }
+
+        let stale = match self.stale_file_count() {
+            Ok(0) => return (None, false),
+            Ok(count) => count,
+            Err(e) => return (Some(format!("Warning: could not check index freshness: {}", e)), false),
+        };
+
+        if policy == RefreshPolicy::Auto && stale <= self.config.mcp.auto_sync_max_files {
+            return match self.sync_databases() {
+                Ok(synced) => (
+                    Some(format!("Index refreshed: re-indexed {} changed file(s) before answering.", synced)),
+                    true,
+                ),
+                Err(e) => (
+                    Some(format!(
+                        "Warning: stale index ({} changed file(s)) and auto-sync failed: {}. Results may be outdated.",
+                        stale, e
+                    )),
+                    false,
+                ),
+            };
+        }
+
+        (
+            Some(format!(
+                "Warning: stale index. {} file(s) changed since the last index, so results may not match the code on disk. \
+                 Run `demongrep index` to update.",
+                stale
+            )),
+            false,
+        )
+    }
+
+    #[tool(description = "Search the codebase using semantic similarity. Searches both local and global databases. Returns code chunks that are semantically similar to the query, plus diagnostics (per-stage timings, databases searched, index version).")]
     async fn semantic_search(
         &self,
         Parameters(request): Parameters<SemanticSearchRequest>,
     ) -> Result<CallToolResult, McpError> {
         let limit = request.limit.unwrap_or(10);
+        let total_start = Instant::now();
+
+        let mut parsed = match crate::search::ParsedQuery::parse(&request.query) {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                return Ok(CallToolResult::success(vec![Content::text(format!("Error parsing query: {}", e))]));
+            }
+        };
+        parsed.filters.chunk_ids = request.previous_result_ids;
+        parsed.filters.tags = request.tags.unwrap_or_default();
+        parsed.filters.not_tags = request.not_tags.unwrap_or_default();
+        parsed.filters.packages = request.packages.unwrap_or_default();
+        let query = parsed.text.as_str();
+
+        let start = Instant::now();
+        let (note, refreshed) = self.refresh_index_inner();
+        let refresh_duration = start.elapsed();
 
         // Get embedding service and embed query
+        let start = Instant::now();
         let mut service_guard = match self.get_embedding_service() {
             Ok(g) => g,
             Err(e) => {
@@ -127,8 +532,11 @@ impl DemongrepService {
             }
         };
 
+        let model_load_duration = start.elapsed();
+
+        let start = Instant::now();
         let service = service_guard.as_mut().unwrap();
-        let query_embedding = match service.embed_query(&request.query) {
+        let query_embedding = match service.embed_query(query) {
             Ok(e) => e,
             Err(e) => {
                 return Ok(CallToolResult::success(vec![Content::text(format!(
@@ -138,8 +546,17 @@ impl DemongrepService {
             }
         };
 
+        drop(service_guard);
+        let embed_duration = start.elapsed();
+
+        // Fetch extra candidates for the reranker to reorder
+        let rerank = request.rerank.unwrap_or(self.config.search.rerank);
+        let candidates = if rerank { self.config.search.rerank_top.max(limit) } else { limit };
+
         // Search across all databases using DatabaseManager
-        let results = match self.db_manager.search_all(&query_embedding, limit) {
+        let start = Instant::now();
+        let db_manager = self.db_manager.read().unwrap();
+        let mut results = match db_manager.search_all_filtered(&query_embedding, candidates, &parsed.filters) {
             Ok(r) => r,
             Err(e) => {
                 return Ok(CallToolResult::success(vec![Content::text(format!(
@@ -149,42 +566,91 @@ impl DemongrepService {
             }
         };
 
-        if results.is_empty() {
-            return Ok(CallToolResult::success(vec![Content::text(
-                "No results found for the query.",
-            )]));
+        crate::rerank::apply_path_weights(&mut results, &PathWeights::new(&self.config.search.path_weights));
+        crate::rerank::apply_kind_weights(
+            &mut results,
+            query,
+            self.config.search.imports_weight,
+            self.config.search.module_docs_boost,
+        );
+        let search_duration = start.elapsed();
+
+        let start = Instant::now();
+        if rerank && !results.is_empty() {
+            results = match self.reranker.with_reranker(|reranker| rerank_results(reranker, query, results)) {
+                Ok(r) => r,
+                Err(e) => {
+                    return Ok(CallToolResult::success(vec![Content::text(format!(
+                        "Error reranking: {}",
+                        e
+                    ))]));
+                }
+            };
         }
+        if let Some(ref command) = self.config.search.transform_command {
+            results = match crate::search::transform_results(command, query, results) {
+                Ok(r) => r,
+                Err(e) => {
+                    return Ok(CallToolResult::success(vec![Content::text(format!(
+                        "Error transforming results: {:#}",
+                        e
+                    ))]));
+                }
+            };
+        }
+        results.truncate(limit);
+        let rerank_duration = start.elapsed();
 
         // Convert to response format
         let items: Vec<SearchResultItem> = results
             .into_iter()
-            .map(|r| {
-                // Determine which database this came from based on path
-                let database = self.db_manager.databases()
-                    .iter()
-                    .find(|db| r.path.starts_with(db.path.to_str().unwrap_or("")))
-                    .map(|db| match db.db_type {
-                        crate::database::DatabaseType::Local => "local".to_string(),
-                        crate::database::DatabaseType::Global => "global".to_string(),
-                    });
-
-                SearchResultItem {
-                    path: r.path,
-                    start_line: r.start_line,
-                    end_line: r.end_line,
-                    kind: r.kind,
-                    content: r.content,
-                    score: r.score,
-                    signature: r.signature,
-                    context_prev: r.context_prev,
-                    context_next: r.context_next,
-                    database,
-                }
+            .map(|r| to_result_item(&db_manager, r))
+            .collect();
+        let (items, omitted) = match request.max_tokens {
+            Some(max_tokens) => {
+                let (packed, omitted) = pack_results(items, max_tokens);
+                (packed, Some(omitted))
+            }
+            None => (items, None),
+        };
+
+        let databases = db_manager
+            .databases()
+            .iter()
+            .map(|db| DatabaseInfo {
+                db_type: db.db_type.name().to_lowercase(),
+                path: db.path.display().to_string(),
+                chunks: db.store().stats().map(|s| s.total_chunks).unwrap_or(0),
+                indexed_at: db.indexed_at(),
+                format_version: db
+                    .store()
+                    .get_db_metadata(db_manager.model_type().name(), db_manager.dimensions())
+                    .map(|m| m.version)
+                    .unwrap_or(0),
             })
             .collect();
 
-        let json = serde_json::to_string_pretty(&items).unwrap_or_else(|_| "[]".to_string());
-        Ok(CallToolResult::success(vec![Content::text(json)]))
+        let response = SemanticSearchResponse {
+            results: items,
+            diagnostics: SearchDiagnostics {
+                timing: SearchTiming {
+                    refresh_ms: refresh_duration.as_millis() as u64,
+                    model_load_ms: model_load_duration.as_millis() as u64,
+                    embed_ms: embed_duration.as_millis() as u64,
+                    search_ms: search_duration.as_millis() as u64,
+                    rerank_ms: rerank_duration.as_millis() as u64,
+                    total_ms: total_start.elapsed().as_millis() as u64,
+                },
+                databases,
+                model: db_manager.model_type().short_name().to_string(),
+                reranked: rerank,
+                refreshed,
+                omitted,
+            },
+        };
+
+        let json = serde_json::to_string_pretty(&response).unwrap_or_else(|_| "{}".to_string());
+        Ok(with_note(note, json))
     }
 
     #[tool(description = "Get all indexed chunks from a specific file. Searches across all databases. Useful for understanding the structure of a file.")]
@@ -192,10 +658,12 @@ impl DemongrepService {
         &self,
         Parameters(request): Parameters<GetFileChunksRequest>,
     ) -> Result<CallToolResult, McpError> {
+        let note = self.refresh_index();
         let mut all_file_chunks: Vec<SearchResultItem> = Vec::new();
 
         // Search across all databases
-        for database in self.db_manager.databases() {
+        let db_manager = self.db_manager.read().unwrap();
+        for database in db_manager.databases() {
             let store = database.store();
             
             let stats = match store.stats() {
@@ -216,17 +684,31 @@ impl DemongrepService {
                             crate::database::DatabaseType::Global => "global",
                         };
 
+                        let notes = store
+                            .get_notes(&chunk.hash)
+                            .map(|notes| notes.into_iter().map(|n| n.text).collect())
+                            .unwrap_or_default();
                         all_file_chunks.push(SearchResultItem {
+                            id,
                             path: chunk.path,
                             start_line: chunk.start_line,
                             end_line: chunk.end_line,
                             kind: chunk.kind,
+                            tokens: if chunk.prompt_tokens > 0 {
+                                chunk.prompt_tokens
+                            } else {
+                                count_tokens(&chunk.content)
+                            },
                             content: chunk.content,
                             score: 1.0,
                             signature: chunk.signature,
                             context_prev: chunk.context_prev,
                             context_next: chunk.context_next,
                             database: Some(db_type.to_string()),
+                            notes,
+                            tags: chunk.tags,
+                            package: chunk.package,
+                            truncated: false,
                         });
                     }
                 }
@@ -237,21 +719,183 @@ impl DemongrepService {
         all_file_chunks.sort_by_key(|c| c.start_line);
 
         if all_file_chunks.is_empty() {
-            return Ok(CallToolResult::success(vec![Content::text(format!(
-                "No chunks found for file: {}",
-                request.path
-            ))]));
+            return Ok(with_note(note, format!("No chunks found for file: {}", request.path)));
         }
 
         let json =
             serde_json::to_string_pretty(&all_file_chunks).unwrap_or_else(|_| "[]".to_string());
-        Ok(CallToolResult::success(vec![Content::text(json)]))
+        Ok(with_note(note, json))
+    }
+
+    #[tool(description = "Find where a symbol (function, method, type, ...) is defined by its name, or by searching only signatures. Cheaper and more precise than semantic_search when you know the name; set prefix to list every symbol starting with it. Returns locations without code (read demongrep://chunk/{id} for the code).")]
+    async fn lookup_symbol(
+        &self,
+        Parameters(request): Parameters<LookupSymbolRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        let note = self.refresh_index();
+        let limit = request.limit.unwrap_or(10);
+
+        let db_manager = self.db_manager.read().unwrap();
+        let mut locations: Vec<SymbolLocation> = Vec::new();
+        let mut searched = 0;
+        for database in db_manager.databases() {
+            let Ok(fts_store) = FtsStore::open_readonly(&database.path) else {
+                continue;
+            };
+            searched += 1;
+
+            let (named, hits) = match symbol_hits(&fts_store, &request, limit * SYMBOL_OVERFETCH) {
+                Ok(hits) => hits,
+                Err(e) => {
+                    return Ok(CallToolResult::success(vec![Content::text(format!(
+                        "Error looking up symbol: {}",
+                        e
+                    ))]));
+                }
+            };
+            for hit in hits {
+                let Ok(Some(chunk)) = database.store().get_chunk(hit.chunk_id) else {
+                    continue;
+                };
+                if request.kind.as_ref().is_some_and(|kind| !chunk.kind.eq_ignore_ascii_case(kind)) {
+                    continue;
+                }
+                locations.push(SymbolLocation {
+                    id: hit.chunk_id,
+                    exact: named.contains(&hit.chunk_id)
+                        || chunk.signature.as_deref().is_some_and(|sig| declares_name(sig, &request.name)),
+                    path: chunk.path,
+                    start_line: chunk.start_line,
+                    end_line: chunk.end_line,
+                    kind: chunk.kind,
+                    signature: chunk.signature,
+                    score: hit.score,
+                    database: database.db_type.name().to_lowercase(),
+                });
+            }
+        }
+
+        if searched == 0 {
+            return Ok(with_note(
+                note,
+                "No full-text index found; run `demongrep index` to build one".to_string(),
+            ));
+        }
+        if locations.is_empty() {
+            return Ok(with_note(note, format!("No definitions found for: {}", request.name)));
+        }
+
+        locations.sort_by(|a, b| b.exact.cmp(&a.exact).then(b.score.total_cmp(&a.score)));
+        locations.truncate(limit);
+
+        let json = serde_json::to_string_pretty(&locations).unwrap_or_else(|_| "[]".to_string());
+        Ok(with_note(note, json))
+    }
+
+    #[tool(description = "Find indexed code similar to a snippet or to a line range of a file, excluding matches in that same file. Use it to check whether something like this has already been implemented elsewhere before writing it.")]
+    async fn find_similar(
+        &self,
+        Parameters(request): Parameters<FindSimilarRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        let note = self.refresh_index();
+        let limit = request.limit.unwrap_or(10);
+
+        let code = match similar_source(&self.project_root, &request) {
+            Ok(code) => code,
+            Err(e) => return Ok(CallToolResult::success(vec![Content::text(format!("Error: {}", e))])),
+        };
+
+        // Embed as a document, the same way indexed chunks were embedded
+        let embedding = {
+            let mut service_guard = match self.get_embedding_service() {
+                Ok(g) => g,
+                Err(e) => {
+                    return Ok(CallToolResult::success(vec![Content::text(format!(
+                        "Error initializing embedding service: {}",
+                        e
+                    ))]));
+                }
+            };
+            let lines = code.lines().count();
+            let chunk = Chunk::new(code, 0, lines, ChunkKind::Block, request.path.clone().unwrap_or_default());
+            match service_guard.as_mut().unwrap().embed_chunk(chunk) {
+                Ok(embedded) => embedded.embedding,
+                Err(e) => {
+                    return Ok(CallToolResult::success(vec![Content::text(format!(
+                        "Error embedding code: {}",
+                        e
+                    ))]));
+                }
+            }
+        };
+
+        let db_manager = self.db_manager.read().unwrap();
+        // Over-fetch: the source file's own chunks are usually the closest matches
+        let mut results = match db_manager.search_all(&embedding, limit * SIMILAR_OVERFETCH) {
+            Ok(r) => r,
+            Err(e) => {
+                return Ok(CallToolResult::success(vec![Content::text(format!(
+                    "Error searching: {}",
+                    e
+                ))]));
+            }
+        };
+        if let Some(ref source) = request.path {
+            let source = source.trim_start_matches("./");
+            results.retain(|r| r.path.trim_start_matches("./") != source);
+        }
+        results.truncate(limit);
+
+        let items: Vec<SearchResultItem> = results
+            .into_iter()
+            .map(|r| to_result_item(&db_manager, r))
+            .collect();
+        if items.is_empty() {
+            return Ok(with_note(note, "No similar code found".to_string()));
+        }
+
+        let json = serde_json::to_string_pretty(&items).unwrap_or_else(|_| "[]".to_string());
+        Ok(with_note(note, json))
+    }
+
+    #[tool(description = "Re-index only the files that changed on disk since the last index (new, modified or deleted), optionally limited to a path. Use this when search results look stale or after editing files. Set dry_run to list the files without re-indexing.")]
+    async fn sync_project(
+        &self,
+        Parameters(request): Parameters<IndexUpdateRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        match self.update_index(request, false) {
+            Ok(response) => {
+                let json = serde_json::to_string_pretty(&response).unwrap_or_else(|_| "{}".to_string());
+                Ok(CallToolResult::success(vec![Content::text(json)]))
+            }
+            Err(e) => Ok(CallToolResult::success(vec![Content::text(format!("Error syncing index: {}", e))])),
+        }
+    }
+
+    #[tool(description = "Re-index every file in the project or under a path, whether or not it changed. Slower than sync_project; use it when results for unchanged files look wrong. Set dry_run to list the files without re-indexing.")]
+    async fn index_project(
+        &self,
+        Parameters(request): Parameters<IndexUpdateRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        match self.update_index(request, true) {
+            Ok(response) => {
+                let json = serde_json::to_string_pretty(&response).unwrap_or_else(|_| "{}".to_string());
+                Ok(CallToolResult::success(vec![Content::text(json)]))
+            }
+            Err(e) => Ok(CallToolResult::success(vec![Content::text(format!("Error indexing: {}", e))])),
+        }
     }
 
     #[tool(description = "Get the status of the semantic search index including model info and statistics from all databases.")]
     async fn index_status(&self) -> Result<CallToolResult, McpError> {
+        let stale_files = match self.config.mcp.refresh {
+            RefreshPolicy::Off => None,
+            _ => self.stale_file_count().ok(),
+        };
+
         // Use DatabaseManager for stats - MUCH SIMPLER!
-        let stats = match self.db_manager.combined_stats() {
+        let db_manager = self.db_manager.read().unwrap();
+        let stats = match db_manager.combined_stats() {
             Ok(s) => s,
             Err(e) => {
                 return Ok(CallToolResult::success(vec![Content::text(format!(
@@ -269,10 +913,11 @@ impl DemongrepService {
             local_files: stats.local_files,
             global_chunks: stats.global_chunks,
             global_files: stats.global_files,
-            model: self.db_manager.model_type().short_name().to_string(),
+            model: db_manager.model_type().short_name().to_string(),
             dimensions: stats.dimensions,
-            databases: self.db_manager.database_paths().iter().map(|p| p.display().to_string()).collect(),
-            databases_available: self.db_manager.database_count(),
+            databases: db_manager.database_paths().iter().map(|p| p.display().to_string()).collect(),
+            databases_available: db_manager.database_count(),
+            stale_files,
         };
 
         let json = serde_json::to_string_pretty(&response).unwrap_or_else(|_| "{}".to_string());
@@ -280,13 +925,236 @@ impl DemongrepService {
     }
 }
 
+/// Convert a search hit to the tool response format
+fn to_result_item(db_manager: &DatabaseManager, r: crate::vectordb::SearchResult) -> SearchResultItem {
+    // Determine which database this came from based on path
+    let database = db_manager.databases()
+        .iter()
+        .find(|db| r.path.starts_with(db.path.to_str().unwrap_or("")))
+        .map(|db| match db.db_type {
+            crate::database::DatabaseType::Local => "local".to_string(),
+            crate::database::DatabaseType::Global => "global".to_string(),
+        });
+
+    SearchResultItem {
+        tokens: result_tokens(&r),
+        id: r.id,
+        path: r.path,
+        start_line: r.start_line,
+        end_line: r.end_line,
+        kind: r.kind,
+        content: r.content,
+        score: r.score,
+        signature: r.signature,
+        context_prev: r.context_prev,
+        context_next: r.context_next,
+        database,
+        notes: r.notes,
+        tags: r.tags,
+        package: r.package,
+        truncated: false,
+    }
+}
+
+/// Tokens of a result as it appears in the response, counted like `search --format context` counts them
+fn item_tokens(item: &SearchResultItem) -> usize {
+    count_tokens(&serde_json::to_string(item).unwrap_or_default())
+}
+
+/// Keep results in rank order while they fit in `max_tokens`
+///
+/// A result that doesn't fit first loses its context lines, then is cut at a
+/// line boundary to the remaining budget. Results that can't keep even
+/// `MIN_TRUNCATED_LINES` lines are left out; later, smaller ones may still
+/// fit. Returns the kept results and how many were left out.
+fn pack_results(items: Vec<SearchResultItem>, max_tokens: usize) -> (Vec<SearchResultItem>, usize) {
+    let mut packed = Vec::new();
+    let mut used = 0;
+    let mut omitted = 0;
+
+    for mut item in items {
+        let remaining = max_tokens.saturating_sub(used);
+        if item_tokens(&item) > remaining {
+            item.context_prev = None;
+            item.context_next = None;
+        }
+        if item_tokens(&item) > remaining && !truncate_to_fit(&mut item, remaining) {
+            omitted += 1;
+            continue;
+        }
+        used += item_tokens(&item);
+        packed.push(item);
+    }
+
+    (packed, omitted)
+}
+
+/// Cut a result's content to the most whole lines that fit in `budget` tokens
+///
+/// Returns false (leaving the item unusable) when fewer than
+/// `MIN_TRUNCATED_LINES` lines fit.
+fn truncate_to_fit(item: &mut SearchResultItem, budget: usize) -> bool {
+    let content = std::mem::take(&mut item.content);
+    let lines: Vec<&str> = content.lines().collect();
+    item.truncated = true;
+
+    // Largest line count that fits
+    let (mut low, mut high) = (0, lines.len());
+    while low < high {
+        let mid = (low + high).div_ceil(2);
+        item.content = lines[..mid].join("\n");
+        if item_tokens(item) <= budget {
+            low = mid;
+        } else {
+            high = mid - 1;
+        }
+    }
+    if low < MIN_TRUNCATED_LINES {
+        return false;
+    }
+
+    item.content = lines[..low].join("\n");
+    item.tokens = count_tokens(&item.content);
+    item.end_line = item.start_line + low;
+    true
+}
+
+/// Definitions for a `lookup_symbol` request: by name, then by signature
+///
+/// Also returns the IDs whose name is exactly the one asked for. The
+/// signature search covers indexes without the name fields.
+fn symbol_hits(
+    fts_store: &FtsStore,
+    request: &LookupSymbolRequest,
+    limit: usize,
+) -> Result<(Vec<u32>, Vec<FtsResult>)> {
+    let name = request.name.rsplit([':', '.']).next().unwrap_or(&request.name).trim();
+    let (named, mut hits) = if request.prefix.unwrap_or(false) {
+        (Vec::new(), fts_store.search_name_prefix(name, &FtsFilter::default(), limit)?)
+    } else {
+        let hits = fts_store.search_name(name, &FtsFilter::default(), limit)?;
+        (hits.iter().map(|hit| hit.chunk_id).collect(), hits)
+    };
+    for hit in fts_store.search_signatures(&request.name, limit)? {
+        if !hits.iter().any(|known| known.chunk_id == hit.chunk_id) {
+            hits.push(hit);
+        }
+    }
+    Ok((named, hits))
+}
+
+/// Whether a signature declares `name` as a whole identifier
+///
+/// Qualified names (`Type::method`, `pkg.func`) are matched by their last segment.
+fn declares_name(signature: &str, name: &str) -> bool {
+    let name = name.rsplit([':', '.']).next().unwrap_or(name).trim();
+    !name.is_empty()
+        && signature
+            .split(|c: char| !(c.is_alphanumeric() || c == '_' || c == '$'))
+            .any(|word| word == name)
+}
+
+/// The code a `find_similar` request is about: the snippet, or the line range read from disk
+fn similar_source(project_root: &Path, request: &FindSimilarRequest) -> Result<String> {
+    if let Some(ref code) = request.code {
+        if code.trim().is_empty() {
+            anyhow::bail!("code is empty");
+        }
+        return Ok(code.clone());
+    }
+
+    let Some(ref path) = request.path else {
+        anyhow::bail!("Give either code or path (with optional start_line/end_line)");
+    };
+    read_lines(project_root, path, request.start_line, request.end_line)
+}
+
+/// Lines `start..=end` (1-based; default: the whole file) of a file inside the project
+fn read_lines(project_root: &Path, path: &str, start: Option<usize>, end: Option<usize>) -> Result<String> {
+    let file = project_root.join(path.trim_start_matches("./"));
+    let inside = match (project_root.canonicalize(), file.canonicalize()) {
+        (Ok(root), Ok(file)) => file.starts_with(root),
+        _ => false,
+    };
+    if !inside {
+        anyhow::bail!("{} is not a file in the project", path);
+    }
+    let content = std::fs::read_to_string(&file).map_err(|e| anyhow::anyhow!("Could not read {}: {}", path, e))?;
+
+    let lines: Vec<&str> = content.lines().collect();
+    let start = start.unwrap_or(1).max(1);
+    let end = end.unwrap_or(lines.len()).min(lines.len());
+    if start > end {
+        anyhow::bail!("Empty line range {}-{} in {} ({} lines)", start, end, path, lines.len());
+    }
+    Ok(lines[start - 1..end].join("\n"))
+}
+
+/// A resource named by a `demongrep://` URI
+#[derive(Debug, PartialEq)]
+enum ResourceRef {
+    /// An indexed chunk, optionally in a given database ("local" or "global")
+    Chunk { id: u32, database: Option<String> },
+    /// Lines of a project file (1-based, inclusive)
+    File { path: String, start: Option<usize>, end: Option<usize> },
+}
+
+fn parse_resource_uri(uri: &str) -> Result<ResourceRef> {
+    let rest = uri
+        .strip_prefix("demongrep://")
+        .ok_or_else(|| anyhow::anyhow!("Not a demongrep:// URI: {}", uri))?;
+    let (rest, query) = rest.split_once('?').unwrap_or((rest, ""));
+    let param = |name: &str| {
+        query
+            .split('&')
+            .filter_map(|pair| pair.split_once('='))
+            .find(|(key, value)| *key == name && !value.is_empty())
+            .map(|(_, value)| value)
+    };
+    let line = |name: &str| -> Result<Option<usize>> {
+        param(name)
+            .map(|value| value.parse().map_err(|_| anyhow::anyhow!("Invalid {} line '{}' in {}", name, value, uri)))
+            .transpose()
+    };
+
+    if let Some(id) = rest.strip_prefix("chunk/") {
+        let id = id.parse().map_err(|_| anyhow::anyhow!("Invalid chunk id '{}' in {}", id, uri))?;
+        Ok(ResourceRef::Chunk { id, database: param("database").map(str::to_string) })
+    } else if let Some(path) = rest.strip_prefix("file/").filter(|path| !path.is_empty()) {
+        Ok(ResourceRef::File { path: path.to_string(), start: line("start")?, end: line("end")? })
+    } else {
+        anyhow::bail!("Unknown resource {}; expected {} or {}", uri, CHUNK_RESOURCE, FILE_RESOURCE)
+    }
+}
+
+fn resource_template(uri_template: &str, name: &str, description: &str, mime_type: &str) -> ResourceTemplate {
+    RawResourceTemplate {
+        uri_template: uri_template.to_string(),
+        name: name.to_string(),
+        title: None,
+        description: Some(description.to_string()),
+        mime_type: Some(mime_type.to_string()),
+    }
+    .no_annotation()
+}
+
+/// Build a tool result, prepending a freshness note when there is one
+fn with_note(note: Option<String>, body: String) -> CallToolResult {
+    let mut content = Vec::new();
+    if let Some(note) = note {
+        content.push(Content::text(note));
+    }
+    content.push(Content::text(body));
+    CallToolResult::success(content)
+}
+
 // === Server Handler Implementation ===
 
 #[tool_handler]
 impl ServerHandler for DemongrepService {
     fn get_info(&self) -> ServerInfo {
         ServerInfo {
-            capabilities: ServerCapabilities::builder().enable_tools().build(),
+            capabilities: ServerCapabilities::builder().enable_tools().enable_resources().build(),
             server_info: rmcp::model::Implementation {
                 name: "demongrep".to_string(),
                 version: env!("CARGO_PKG_VERSION").to_string(),
@@ -297,21 +1165,71 @@ impl ServerHandler for DemongrepService {
             instructions: Some(
                 "Demongrep is a semantic code search tool with dual-database support. \
                  Use semantic_search to find code by meaning (searches both local and global databases), \
+                 lookup_symbol to find where a known name is defined, \
                  get_file_chunks to see all chunks in a file, and index_status \
-                 to check if the index is ready and see stats from all databases."
+                 to check if the index is ready and see stats from all databases. \
+                 Before writing new code, find_similar shows whether something like it already exists. \
+                 If results look stale, call sync_project to re-index changed files, \
+                 or index_project to re-index everything under a path. \
+                 Read demongrep://chunk/{id} for a whole chunk from a result, or \
+                 demongrep://file/{path}?start=&end= for the lines around it."
                     .to_string(),
             ),
             ..Default::default()
         }
     }
+
+    async fn list_resource_templates(
+        &self,
+        _request: Option<PaginatedRequestParam>,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ListResourceTemplatesResult, McpError> {
+        Ok(ListResourceTemplatesResult::with_all_items(vec![
+            resource_template(
+                CHUNK_RESOURCE,
+                "chunk",
+                "An indexed chunk by the id in a search result, as JSON (database: local or global; default: first with that id)",
+                "application/json",
+            ),
+            resource_template(
+                FILE_RESOURCE,
+                "file",
+                "Lines start..=end (1-based; default: whole file) of a file, relative to the project root",
+                "text/plain",
+            ),
+        ]))
+    }
+
+    async fn read_resource(
+        &self,
+        request: ReadResourceRequestParam,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ReadResourceResult, McpError> {
+        let resource = parse_resource_uri(&request.uri).map_err(|e| McpError::invalid_params(e.to_string(), None))?;
+        let text = match resource {
+            ResourceRef::Chunk { id, database } => self.read_chunk(id, database.as_deref()),
+            ResourceRef::File { path, start, end } => read_lines(&self.project_root, &path, start, end),
+        }
+        .map_err(|e| McpError::resource_not_found(e.to_string(), None))?;
+
+        Ok(ReadResourceResult {
+            contents: vec![ResourceContents::text(text, request.uri)],
+        })
+    }
 }
 
 /// Run the MCP server using stdio transport with DatabaseManager
-pub async fn run_mcp_server(path: Option<PathBuf>) -> Result<()> {
+pub async fn run_mcp_server(path: Option<PathBuf>, config: &Config) -> Result<()> {
     use rmcp::{transport::stdio, ServiceExt};
 
+    // stdout carries the MCP protocol, so keep progress output off it
+    crate::output::set_quiet(true);
+
+    // Same root form as `demongrep index`, so file metadata keys line up
+    let project_root = path.clone().unwrap_or_else(|| PathBuf::from("."));
+
     // Use DatabaseManager to load all databases
-    let db_manager = match DatabaseManager::load(path) {
+    let db_manager = match DatabaseManager::load_with_options(path, &StoreOptions::from_config(config)) {
         Ok(manager) => manager,
         Err(_) => {
             eprintln!("Error: No databases found!");
@@ -332,7 +1250,8 @@ pub async fn run_mcp_server(path: Option<PathBuf>) -> Result<()> {
         );
     }
 
-    let service = DemongrepService::new(db_manager)?;
+    let service = DemongrepService::new(db_manager, project_root, config.clone())?;
+    service.reranker.spawn_idle_unloader();
 
     // Serve using stdio transport
     let server = service.serve(stdio()).await?;
@@ -344,3 +1263,101 @@ pub async fn run_mcp_server(path: Option<PathBuf>) -> Result<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_resource_uri() {
+        assert_eq!(
+            parse_resource_uri("demongrep://chunk/42").unwrap(),
+            ResourceRef::Chunk { id: 42, database: None }
+        );
+        assert_eq!(
+            parse_resource_uri("demongrep://chunk/7?database=global").unwrap(),
+            ResourceRef::Chunk { id: 7, database: Some("global".to_string()) }
+        );
+        assert_eq!(
+            parse_resource_uri("demongrep://file/src/auth/token.rs?start=10&end=").unwrap(),
+            ResourceRef::File { path: "src/auth/token.rs".to_string(), start: Some(10), end: None }
+        );
+        assert!(parse_resource_uri("demongrep://chunk/abc").is_err());
+        assert!(parse_resource_uri("demongrep://file/a.rs?start=x").is_err());
+        assert!(parse_resource_uri("demongrep://file/").is_err());
+        assert!(parse_resource_uri("file:///etc/passwd").is_err());
+    }
+
+    fn item(path: &str, lines: usize) -> SearchResultItem {
+        SearchResultItem {
+            id: 0,
+            path: path.to_string(),
+            start_line: 10,
+            end_line: 10 + lines,
+            kind: "Function".to_string(),
+            content: (0..lines).map(|i| format!("let value_{} = compute({});", i, i)).collect::<Vec<_>>().join("\n"),
+            tokens: 0,
+            score: 0.9,
+            signature: None,
+            context_prev: Some("// before".to_string()),
+            context_next: None,
+            database: None,
+            notes: vec![],
+            tags: vec![],
+            package: None,
+            truncated: false,
+        }
+    }
+
+    #[test]
+    fn test_pack_results() {
+        let small = item_tokens(&item("a.rs", 5));
+        let big = item_tokens(&item("b.rs", 200));
+
+        // Everything fits: nothing changes
+        let (packed, omitted) = pack_results(vec![item("a.rs", 5), item("b.rs", 200)], small + big);
+        assert_eq!((packed.len(), omitted), (2, 0));
+        assert!(!packed[1].truncated);
+
+        // The big result is cut at a line boundary to the remaining budget
+        let (packed, omitted) = pack_results(vec![item("a.rs", 5), item("b.rs", 200)], small + big / 2);
+        assert_eq!((packed.len(), omitted), (2, 0));
+        let cut = &packed[1];
+        assert!(cut.truncated);
+        assert!(cut.context_prev.is_none());
+        let kept = cut.content.lines().count();
+        assert!((MIN_TRUNCATED_LINES..200).contains(&kept));
+        assert_eq!(cut.end_line, cut.start_line + kept);
+        assert!(packed.iter().map(item_tokens).sum::<usize>() <= small + big / 2);
+
+        // No room for even a few lines of the big one; the tiny one after it still fits
+        let tiny = item_tokens(&item("a.rs", 1));
+        let (packed, omitted) = pack_results(vec![item("b.rs", 200), item("a.rs", 1)], tiny);
+        assert_eq!((packed.len(), omitted), (1, 1));
+        assert_eq!(packed[0].path, "a.rs");
+    }
+
+    #[test]
+    fn test_declares_name() {
+        assert!(declares_name("pub fn validate_token(t: &str) -> bool", "validate_token"));
+        assert!(declares_name("impl UserConfig", "crate::config::UserConfig"));
+        assert!(declares_name("def save(self)", "Model.save"));
+        assert!(!declares_name("pub fn validate_token_v2()", "validate_token"));
+        assert!(!declares_name("fn login()", ""));
+    }
+
+    #[test]
+    fn test_read_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("project");
+        std::fs::create_dir(&root).unwrap();
+        std::fs::write(root.join("a.rs"), "one\ntwo\nthree\n").unwrap();
+        std::fs::write(dir.path().join("secret"), "outside").unwrap();
+
+        assert_eq!(read_lines(&root, "a.rs", Some(2), None).unwrap(), "two\nthree");
+        assert_eq!(read_lines(&root, "./a.rs", None, Some(1)).unwrap(), "one");
+        assert!(read_lines(&root, "a.rs", Some(3), Some(2)).is_err());
+        assert!(read_lines(&root, "../secret", None, None).is_err());
+        assert!(read_lines(&root, "missing.rs", None, None).is_err());
+    }
+}
@@ -0,0 +1,132 @@
+//! Reranker model cache for long-running processes
+//!
+//! Loading the cross-encoder takes a second or more, so `serve` and `mcp`
+//! keep one instance across queries instead of constructing a
+//! `NeuralReranker` per search. The model is loaded on first use and dropped
+//! again after `search.reranker_idle_secs` without a query.
+
+use anyhow::Result;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use super::NeuralReranker;
+
+/// How often the idle unloader checks at most
+const MAX_IDLE_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+struct LoadedReranker {
+    reranker: NeuralReranker,
+    last_used: Instant,
+}
+
+/// Lazily loaded, shared reranker with idle unload
+pub struct RerankerCache {
+    loaded: Mutex<Option<LoadedReranker>>,
+    /// None keeps the model loaded for the life of the process
+    idle_timeout: Option<Duration>,
+}
+
+impl RerankerCache {
+    pub fn new(idle_timeout: Option<Duration>) -> Self {
+        Self {
+            loaded: Mutex::new(None),
+            idle_timeout,
+        }
+    }
+
+    /// Cache that unloads after `secs` idle seconds (0 = never unload)
+    pub fn from_idle_secs(secs: u64) -> Self {
+        Self::new((secs > 0).then(|| Duration::from_secs(secs)))
+    }
+
+    /// Run `f` with the reranker, loading the model first if needed
+    pub fn with_reranker<T>(&self, f: impl FnOnce(&mut NeuralReranker) -> Result<T>) -> Result<T> {
+        let mut loaded = self.loaded.lock().unwrap();
+        if loaded.is_none() {
+            *loaded = Some(LoadedReranker {
+                reranker: NeuralReranker::new()?,
+                last_used: Instant::now(),
+            });
+        }
+
+        let entry = loaded.as_mut().unwrap();
+        entry.last_used = Instant::now();
+        f(&mut entry.reranker)
+    }
+
+    /// Whether the model is currently in memory
+    pub fn is_loaded(&self) -> bool {
+        self.loaded.lock().unwrap().is_some()
+    }
+
+    /// Drop the model if it has not been used within the idle timeout
+    ///
+    /// Returns whether it was unloaded.
+    pub fn unload_if_idle(&self) -> bool {
+        let Some(timeout) = self.idle_timeout else {
+            return false;
+        };
+
+        let mut loaded = self.loaded.lock().unwrap();
+        match loaded.as_ref() {
+            Some(entry) if is_idle(entry.last_used, Instant::now(), timeout) => {
+                *loaded = None;
+                tracing::info!("Unloaded idle reranker model");
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Periodically unload the model while it is idle
+    ///
+    /// Does nothing without an idle timeout. The task stops once the cache
+    /// is dropped. Must be called from within a tokio runtime.
+    pub fn spawn_idle_unloader(self: &Arc<Self>) {
+        let Some(timeout) = self.idle_timeout else {
+            return;
+        };
+
+        let cache = Arc::downgrade(self);
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(timeout.min(MAX_IDLE_CHECK_INTERVAL));
+            loop {
+                interval.tick().await;
+                let Some(cache) = cache.upgrade() else {
+                    break;
+                };
+                cache.unload_if_idle();
+            }
+        });
+    }
+}
+
+/// Whether a model last used at `last_used` has been idle for `timeout` by `now`
+fn is_idle(last_used: Instant, now: Instant, timeout: Duration) -> bool {
+    now.saturating_duration_since(last_used) >= timeout
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_idle() {
+        let start = Instant::now();
+        let timeout = Duration::from_secs(600);
+        assert!(!is_idle(start, start, timeout));
+        assert!(!is_idle(start, start + Duration::from_secs(599), timeout));
+        assert!(is_idle(start, start + timeout, timeout));
+    }
+
+    #[test]
+    fn test_unload_without_model() {
+        let cache = RerankerCache::from_idle_secs(0);
+        assert!(cache.idle_timeout.is_none());
+        assert!(!cache.is_loaded());
+        assert!(!cache.unload_if_idle());
+
+        let cache = RerankerCache::from_idle_secs(1);
+        assert!(!cache.unload_if_idle());
+    }
+}
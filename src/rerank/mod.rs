@@ -1,26 +1,86 @@
 //! Reranking and result fusion strategies
 //!
-//! Provides RRF (Reciprocal Rank Fusion) for combining vector and FTS results,
-//! and neural reranking using cross-encoder models for improved accuracy.
+//! Provides RRF (Reciprocal Rank Fusion) and weighted score blending for
+//! combining vector and FTS results, and neural reranking using cross-encoder
+//! models for improved accuracy.
 
+mod cache;
 mod neural;
+mod path_weights;
 
+use anyhow::Result;
+use serde::Serialize;
 use std::collections::HashMap;
 
 use crate::fts::FtsResult;
 use crate::vectordb::SearchResult;
 
-pub use neural::NeuralReranker;
+pub use cache::RerankerCache;
+pub use neural::{reranker_model_code, NeuralReranker, DEFAULT_RERANKER};
+pub use path_weights::{apply_path_weights, PathWeights};
 
 /// Default RRF k parameter (per osgrep reference)
 pub const DEFAULT_RRF_K: f32 = 20.0;
 
+/// Default weight of the vector score in weighted fusion (BM25 gets the rest)
+pub const DEFAULT_FUSION_ALPHA: f32 = 0.5;
+
+/// Default score multiplier for import blocks, which rarely answer a query
+pub const DEFAULT_IMPORTS_WEIGHT: f32 = 0.5;
+
+/// Default score multiplier for module docs on "what does this module do" queries
+pub const DEFAULT_MODULE_DOCS_BOOST: f32 = 1.5;
+
+/// Score multiplier for chunks under a dependency root with `--prefer-project`
+pub const DEPENDENCY_WEIGHT: f32 = 0.5;
+
+/// Score multiplier for declaration-only chunks with `--prefer-project`
+pub const DECLARATION_WEIGHT: f32 = 0.7;
+
+/// Default extra weight for a file modified just now with `--boost-recent`
+pub const DEFAULT_RECENCY_BOOST: f32 = 0.25;
+
+/// Default age in days at which `--boost-recent` gives half its boost
+pub const DEFAULT_RECENCY_HALF_LIFE_DAYS: f32 = 30.0;
+
+/// Recency multipliers below this are too small to mention
+const MIN_RECENCY_FACTOR: f32 = 1.005;
+
+/// Directory names that hold third-party code
+const DEPENDENCY_DIRS: &[&str] = &[
+    "node_modules",
+    "bower_components",
+    "vendor",
+    "third_party",
+    "third-party",
+    "external",
+    "site-packages",
+    ".venv",
+    "venv",
+    "Pods",
+];
+
+/// Multi-component dependency roots (package manager caches)
+const DEPENDENCY_ROOTS: &[&str] = &[".cargo/registry/", "go/pkg/mod/", ".m2/repository/", ".gradle/caches/"];
+
+/// Files that only declare types, never implement them
+const DECLARATION_SUFFIXES: &[&str] = &[".d.ts", ".d.mts", ".d.cts", ".pyi"];
+
+/// Line prefixes of comments and docstrings, skipped when looking for a body
+const COMMENT_PREFIXES: &[&str] = &["//", "/*", "*", "#", "\"\"\"", "'''"];
+
+/// Words that mark a query as asking about a module as a whole
+const MODULE_WORDS: &[&str] = &["module", "package", "crate", "library", "file", "component"];
+
+/// Words that mark a query as asking for an overview or purpose
+const OVERVIEW_WORDS: &[&str] = &["what", "purpose", "overview", "responsible", "describe", "explain", "does"];
+
 /// Fused search result combining vector and FTS scores
 #[derive(Debug, Clone)]
 pub struct FusedResult {
     /// Chunk ID
     pub chunk_id: u32,
-    /// Combined RRF score
+    /// Combined score (RRF, weighted blend, or the single retriever's score)
     pub rrf_score: f32,
     /// Original vector similarity score (if present)
     pub vector_score: Option<f32>,
@@ -91,6 +151,103 @@ pub fn rrf_fusion(
     results
 }
 
+/// Min-max normalize scores to [0, 1]; equal scores all become 1
+fn normalize_scores(scores: impl Iterator<Item = f32> + Clone) -> impl Fn(f32) -> f32 {
+    let min = scores.clone().fold(f32::INFINITY, f32::min);
+    let max = scores.fold(f32::NEG_INFINITY, f32::max);
+    move |score| if max > min { (score - min) / (max - min) } else { 1.0 }
+}
+
+/// Weighted fusion: blend min-max normalized vector and BM25 scores
+///
+/// score = alpha × vector + (1 − alpha) × bm25, each normalized to [0, 1]
+/// within its own result list; a chunk missing from one list gets 0 for it.
+/// Unlike RRF this keeps score magnitudes, so a far-ahead BM25 hit or a
+/// near-tie in vector similarity shows up in the fused ranking.
+pub fn weighted_fusion(vector_results: &[SearchResult], fts_results: &[FtsResult], alpha: f32) -> Vec<FusedResult> {
+    let vector_norm = normalize_scores(vector_results.iter().map(|r| r.score));
+    let fts_norm = normalize_scores(fts_results.iter().map(|r| r.score));
+    let mut fused: HashMap<u32, FusedResult> = HashMap::new();
+
+    for (rank, result) in vector_results.iter().enumerate() {
+        let entry = fused.entry(result.id).or_insert_with(|| FusedResult {
+            chunk_id: result.id,
+            rrf_score: 0.0,
+            vector_score: None,
+            fts_score: None,
+            vector_rank: None,
+            fts_rank: None,
+        });
+        entry.rrf_score += alpha * vector_norm(result.score);
+        entry.vector_score = Some(result.score);
+        entry.vector_rank = Some(rank + 1);
+    }
+
+    for (rank, result) in fts_results.iter().enumerate() {
+        let entry = fused.entry(result.chunk_id).or_insert_with(|| FusedResult {
+            chunk_id: result.chunk_id,
+            rrf_score: 0.0,
+            vector_score: None,
+            fts_score: None,
+            vector_rank: None,
+            fts_rank: None,
+        });
+        entry.rrf_score += (1.0 - alpha) * fts_norm(result.score);
+        entry.fts_score = Some(result.score);
+        entry.fts_rank = Some(rank + 1);
+    }
+
+    let mut results: Vec<FusedResult> = fused.into_values().collect();
+    results.sort_by(|a, b| b.rrf_score.partial_cmp(&a.rrf_score).unwrap_or(std::cmp::Ordering::Equal));
+    results
+}
+
+/// How hybrid search combines the vector and full-text rankings
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Fusion {
+    /// Reciprocal Rank Fusion with constant `k`
+    Rrf { k: f32 },
+    /// Normalized score blend, `alpha` being the vector weight
+    Weighted { alpha: f32 },
+}
+
+impl Fusion {
+    pub fn fuse(&self, vector_results: &[SearchResult], fts_results: &[FtsResult]) -> Vec<FusedResult> {
+        match *self {
+            Fusion::Rrf { k } => rrf_fusion(vector_results, fts_results, k),
+            Fusion::Weighted { alpha } => weighted_fusion(vector_results, fts_results, alpha),
+        }
+    }
+}
+
+/// Move the chunks in `first` to the top of a fused ranking, in their order
+///
+/// They get at least the best fused score, so the stable sorts applied
+/// later keep them ahead of the rest. Chunks fusion didn't return are added
+/// without retriever scores.
+pub fn promote(fused: Vec<FusedResult>, first: &[FtsResult]) -> Vec<FusedResult> {
+    let best = fused.iter().map(|r| r.rrf_score).fold(0.0, f32::max);
+    let mut rest = fused;
+    let mut promoted = Vec::with_capacity(first.len() + rest.len());
+    for hit in first {
+        let mut result = match rest.iter().position(|r| r.chunk_id == hit.chunk_id) {
+            Some(idx) => rest.remove(idx),
+            None => FusedResult {
+                chunk_id: hit.chunk_id,
+                rrf_score: 0.0,
+                vector_score: None,
+                fts_score: None,
+                vector_rank: None,
+                fts_rank: None,
+            },
+        };
+        result.rrf_score = result.rrf_score.max(best);
+        promoted.push(result);
+    }
+    promoted.extend(rest);
+    promoted
+}
+
 /// Simple vector-only pass-through (no fusion)
 pub fn vector_only(vector_results: &[SearchResult]) -> Vec<FusedResult> {
     vector_results
@@ -107,6 +264,190 @@ pub fn vector_only(vector_results: &[SearchResult]) -> Vec<FusedResult> {
         .collect()
 }
 
+/// Simple FTS-only pass-through (no fusion), scored by BM25
+pub fn fts_only(fts_results: &[FtsResult]) -> Vec<FusedResult> {
+    fts_results
+        .iter()
+        .enumerate()
+        .map(|(rank, result)| FusedResult {
+            chunk_id: result.chunk_id,
+            rrf_score: result.score,
+            vector_score: None,
+            fts_score: Some(result.score),
+            vector_rank: None,
+            fts_rank: Some(rank + 1),
+        })
+        .collect()
+}
+
+/// A score multiplier applied to a result, and why
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Boost {
+    pub reason: String,
+    pub factor: f32,
+}
+
+impl Boost {
+    pub fn new(reason: impl Into<String>, factor: f32) -> Self {
+        Self { reason: reason.into(), factor }
+    }
+}
+
+/// Multiplier for a result's kind: imports always, module docs on overview queries
+fn kind_boost(kind: &str, overview: bool, imports_weight: f32, module_docs_boost: f32) -> Option<Boost> {
+    match kind {
+        "Imports" => Some(Boost::new("imports", imports_weight)),
+        "ModuleDocs" if overview => Some(Boost::new("module docs", module_docs_boost)),
+        _ => None,
+    }
+}
+
+/// Multipliers `--prefer-project` applies to a result
+fn project_boosts(result: &SearchResult) -> Vec<Boost> {
+    let mut boosts = Vec::new();
+    if is_dependency_path(&result.path) {
+        boosts.push(Boost::new("dependency", DEPENDENCY_WEIGHT));
+    }
+    if is_declaration_only(result) {
+        boosts.push(Boost::new("declaration only", DECLARATION_WEIGHT));
+    }
+    boosts
+}
+
+/// Every multiplier `search` applies to a result's score (for `--explain`)
+pub fn score_boosts(
+    result: &SearchResult,
+    query: &str,
+    imports_weight: f32,
+    module_docs_boost: f32,
+    prefer_project: bool,
+) -> Vec<Boost> {
+    let overview = is_module_overview_query(query);
+    let mut boosts: Vec<Boost> = kind_boost(&result.kind, overview, imports_weight, module_docs_boost)
+        .into_iter()
+        .collect();
+    if prefer_project {
+        boosts.extend(project_boosts(result));
+    }
+    boosts.retain(|boost| boost.factor != 1.0);
+    boosts
+}
+
+/// Multiplier `--boost-recent` gives a chunk from a file modified at `modified`
+///
+/// `1 + boost` for a file modified at `now`, halving the extra weight every
+/// `half_life_days`. None for boosts too small to matter, which includes
+/// files whose modification time couldn't be read (recorded as 0).
+pub fn recency_boost(modified: u64, now: u64, boost: f32, half_life_days: f32) -> Option<Boost> {
+    if boost <= 0.0 || half_life_days <= 0.0 {
+        return None;
+    }
+    let age_days = now.saturating_sub(modified) as f32 / 86_400.0;
+    let factor = 1.0 + boost * 0.5f32.powf(age_days / half_life_days);
+    (factor >= MIN_RECENCY_FACTOR).then(|| Boost::new("recently modified", factor))
+}
+
+/// Whether a query asks what a module is for (e.g. "what does this module do")
+pub fn is_module_overview_query(query: &str) -> bool {
+    let query = query.to_lowercase();
+    let words: Vec<&str> = query.split(|c: char| !c.is_alphanumeric()).collect();
+    let has_any = |set: &[&str]| words.iter().any(|w| set.contains(w));
+
+    (has_any(MODULE_WORDS) && has_any(OVERVIEW_WORDS)) || words.contains(&"overview")
+}
+
+/// Re-weight scores by chunk kind and re-sort
+///
+/// Imports are down-ranked on every query, or dropped when `imports_weight`
+/// is 0; module docs are boosted only when the query asks what a module does.
+pub fn apply_kind_weights(results: &mut Vec<SearchResult>, query: &str, imports_weight: f32, module_docs_boost: f32) {
+    if imports_weight <= 0.0 {
+        results.retain(|result| result.kind != "Imports");
+    }
+    let overview = is_module_overview_query(query);
+
+    for result in results.iter_mut() {
+        if let Some(boost) = kind_boost(&result.kind, overview, imports_weight, module_docs_boost) {
+            result.score *= boost.factor;
+        }
+    }
+
+    results.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
+}
+
+/// Whether a path lies under a vendored or installed dependency
+pub fn is_dependency_path(path: &str) -> bool {
+    let path = path.replace('\\', "/");
+    path.split('/').any(|component| DEPENDENCY_DIRS.contains(&component))
+        || DEPENDENCY_ROOTS.iter().any(|root| path.contains(root))
+}
+
+/// Whether a chunk declares something without implementing it
+///
+/// Covers declaration files (`.d.ts`, `.pyi`) and bodiless chunks such as C
+/// prototypes, trait method signatures and `...` stubs.
+pub fn is_declaration_only(result: &SearchResult) -> bool {
+    if DECLARATION_SUFFIXES.iter().any(|suffix| result.path.ends_with(suffix)) {
+        return true;
+    }
+    if result.kind == "Imports" {
+        return false;
+    }
+
+    let code: Vec<&str> = result
+        .content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !COMMENT_PREFIXES.iter().any(|prefix| line.starts_with(prefix)))
+        .collect();
+    let Some(last) = code.last() else {
+        return false;
+    };
+
+    let bodiless = last.ends_with(';') && code.iter().all(|line| !line.contains('{') && !line.contains('='));
+    let stub = last.ends_with(": ...") || *last == "...";
+    bodiless || stub
+}
+
+/// Down-rank dependency code and declarations so the project's own implementations come first, and re-sort
+pub fn apply_project_preference(results: &mut [SearchResult]) {
+    for result in results.iter_mut() {
+        for boost in project_boosts(result) {
+            result.score *= boost.factor;
+        }
+    }
+
+    results.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
+}
+
+/// Reorder results with the cross-encoder, blending its scores with the current ones
+pub fn rerank_results(reranker: &mut NeuralReranker, query: &str, results: Vec<SearchResult>) -> Result<Vec<SearchResult>> {
+    Ok(rerank_results_with_scores(reranker, query, results)?
+        .into_iter()
+        .map(|(result, _)| result)
+        .collect())
+}
+
+/// Like `rerank_results`, also returning each result's cross-encoder score in [0, 1]
+pub fn rerank_results_with_scores(
+    reranker: &mut NeuralReranker,
+    query: &str,
+    results: Vec<SearchResult>,
+) -> Result<Vec<(SearchResult, f32)>> {
+    let documents: Vec<String> = results.iter().map(|r| r.content.clone()).collect();
+    let scores: Vec<f32> = results.iter().map(|r| r.score).collect();
+    let reranked = reranker.rerank_and_blend(query, &documents, &scores)?;
+
+    Ok(reranked
+        .into_iter()
+        .map(|(idx, score, rerank_score)| {
+            let mut result = results[idx].clone();
+            result.score = score;
+            (result, rerank_score)
+        })
+        .collect())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -127,6 +468,16 @@ mod tests {
             score,
             context_prev: None,
             context_next: None,
+            notes: Vec::new(),
+            simhash: 0,
+            also_in: Vec::new(),
+            tokens: 0,
+            via: None,
+            tags: Vec::new(),
+            package: None,
+            stale: false,
+            match_lines: Vec::new(),
+            database: None,
         }
     }
 
@@ -191,6 +542,108 @@ mod tests {
         assert!((result.rrf_score - expected).abs() < 0.0001);
     }
 
+    #[test]
+    fn test_weighted_fusion() {
+        let vector_results = vec![make_vector_result(1, 0.9), make_vector_result(2, 0.5), make_vector_result(3, 0.4)];
+        // ID 3 is far ahead in BM25, ID 4 only matched text
+        let fts_results = vec![make_fts_result(3, 30.0), make_fts_result(1, 6.0), make_fts_result(4, 5.0)];
+
+        // RRF puts ID 1 first (ranks 1 and 2); the blend sees how far ahead ID 3 is in BM25
+        assert_eq!(rrf_fusion(&vector_results, &fts_results, 20.0)[0].chunk_id, 1);
+        let fused = weighted_fusion(&vector_results, &fts_results, 0.3);
+        let ids: Vec<u32> = fused.iter().map(|r| r.chunk_id).collect();
+        assert_eq!(ids, vec![3, 1, 2, 4]);
+        // 0.3 × 0 + 0.7 × 1 for ID 3; 0.3 × 1 + 0.7 × 0.04 for ID 1
+        assert!((fused[0].rrf_score - 0.7).abs() < 1e-6);
+        assert!((fused[1].rrf_score - 0.328).abs() < 1e-6);
+
+        // alpha = 1 is vector order; text-only hits get nothing
+        let fused = Fusion::Weighted { alpha: 1.0 }.fuse(&vector_results, &fts_results);
+        let ids: Vec<u32> = fused.iter().map(|r| r.chunk_id).collect();
+        assert_eq!(&ids[..2], [1, 2]);
+        assert_eq!(fused.iter().find(|r| r.chunk_id == 4).unwrap().rrf_score, 0.0);
+    }
+
+    #[test]
+    fn test_promote() {
+        let vector_results = vec![make_vector_result(1, 0.9), make_vector_result(2, 0.5)];
+        let fused = rrf_fusion(&vector_results, &[make_fts_result(2, 8.0)], 20.0);
+        let best = fused[0].rrf_score;
+
+        let promoted = promote(fused, &[make_fts_result(5, 1.0), make_fts_result(1, 1.0)]);
+        let ids: Vec<u32> = promoted.iter().map(|r| r.chunk_id).collect();
+        assert_eq!(ids, [5, 1, 2]);
+        assert_eq!(promoted[0].rrf_score, best);
+        assert_eq!(promoted[1].vector_rank, Some(1));
+    }
+
+    #[test]
+    fn test_apply_kind_weights() {
+        let mut imports = make_vector_result(1, 0.9);
+        imports.kind = "Imports".to_string();
+        let mut docs = make_vector_result(2, 0.7);
+        docs.kind = "ModuleDocs".to_string();
+        let function = make_vector_result(3, 0.8);
+
+        let mut results = vec![imports.clone(), docs.clone(), function.clone()];
+        apply_kind_weights(&mut results, "parse config", 0.5, 1.5);
+        let ids: Vec<u32> = results.iter().map(|r| r.id).collect();
+        assert_eq!(ids, vec![3, 2, 1]);
+
+        let mut results = vec![imports.clone(), docs.clone(), function.clone()];
+        apply_kind_weights(&mut results, "what does this module do?", 0.5, 1.5);
+        assert_eq!(results[0].id, 2);
+
+        // A weight of 0 filters imports out instead of zeroing their score
+        let mut results = vec![imports, docs, function];
+        apply_kind_weights(&mut results, "serde", 0.0, 1.5);
+        let ids: Vec<u32> = results.iter().map(|r| r.id).collect();
+        assert_eq!(ids, vec![3, 2]);
+    }
+
+    #[test]
+    fn test_apply_project_preference() {
+        let mut vendored = make_vector_result(1, 0.9);
+        vendored.path = "node_modules/@types/node/fs.d.ts".to_string();
+        let mut prototype = make_vector_result(2, 0.8);
+        prototype.path = "include/parser.h".to_string();
+        prototype.content = "/* Parse a config file */\nint parse_config(const char *path);".to_string();
+        let mut implementation = make_vector_result(3, 0.6);
+        implementation.content = "int parse_config(const char *path) {\n    return 0;\n}".to_string();
+
+        assert!(is_dependency_path("vendor/github.com/pkg/errors/errors.go"));
+        assert!(is_dependency_path("/home/me/.cargo/registry/src/serde-1.0/lib.rs"));
+        assert!(!is_dependency_path("src/vendors.rs"));
+        assert!(is_declaration_only(&prototype));
+        assert!(!is_declaration_only(&implementation));
+
+        let mut results = vec![vendored, prototype, implementation];
+        apply_project_preference(&mut results);
+        let ids: Vec<u32> = results.iter().map(|r| r.id).collect();
+        assert_eq!(ids, vec![3, 2, 1]);
+    }
+
+    #[test]
+    fn test_recency_boost() {
+        let now = 1_700_000_000;
+        let day = 86_400;
+        assert_eq!(recency_boost(now, now, 0.25, 30.0).unwrap().factor, 1.25);
+        let month_old = recency_boost(now - 30 * day, now, 0.25, 30.0).unwrap();
+        assert!((month_old.factor - 1.125).abs() < 1e-4);
+        // Years old, unknown time, or turned off
+        assert!(recency_boost(now - 1000 * day, now, 0.25, 30.0).is_none());
+        assert!(recency_boost(0, now, 0.25, 30.0).is_none());
+        assert!(recency_boost(now, now, 0.0, 30.0).is_none());
+    }
+
+    #[test]
+    fn test_is_module_overview_query() {
+        assert!(is_module_overview_query("What does this module do?"));
+        assert!(is_module_overview_query("purpose of the auth package"));
+        assert!(is_module_overview_query("overview"));
+        assert!(!is_module_overview_query("where do we parse tokens"));
+    }
+
     #[test]
     fn test_vector_only() {
         let vector_results = vec![
@@ -3,6 +3,7 @@
 //! Provides second-pass reranking using fastembed's TextRerank
 //! with the Jina Reranker v1 Turbo model for improved accuracy.
 
+use crate::embed::{is_offline, is_repo_file_cached, offline_error};
 use crate::info_print;
 use anyhow::Result;
 use fastembed::{RerankInitOptions, RerankerModel, TextRerank};
@@ -16,6 +17,26 @@ pub const DEFAULT_RERANK_TOP: usize = 50;
 pub const RERANK_WEIGHT: f32 = 0.575;
 pub const RRF_WEIGHT: f32 = 0.425;
 
+/// Reranker model loaded for `--rerank`
+pub const DEFAULT_RERANKER: RerankerModel = RerankerModel::JINARerankerV1TurboEn;
+
+/// Hugging Face repo a reranker model is downloaded from
+pub fn reranker_model_code(model: &RerankerModel) -> Option<String> {
+    TextRerank::list_supported_models()
+        .into_iter()
+        .find(|info| info.model == *model)
+        .map(|info| info.model_code)
+}
+
+/// Whether a reranker model's files are already in the local model cache
+pub fn is_reranker_cached(model: &RerankerModel) -> bool {
+    TextRerank::list_supported_models()
+        .into_iter()
+        .find(|info| info.model == *model)
+        .map(|info| is_repo_file_cached(&info.model_code, &info.model_file))
+        .unwrap_or(false)
+}
+
 /// Neural reranker using cross-encoder model
 pub struct NeuralReranker {
     reranker: TextRerank,
@@ -25,11 +46,15 @@ pub struct NeuralReranker {
 impl NeuralReranker {
     /// Create a new neural reranker with the default Jina model
     pub fn new() -> Result<Self> {
-        Self::with_model(RerankerModel::JINARerankerV1TurboEn)
+        Self::with_model(DEFAULT_RERANKER)
     }
 
     /// Create a neural reranker with a specific model
     pub fn with_model(model: RerankerModel) -> Result<Self> {
+        if is_offline() && !is_reranker_cached(&model) {
+            return Err(offline_error("the neural reranker", ""));
+        }
+
         let model_name = model.to_string();
         info_print!("Loading reranker model: {}", model_name);
 
@@ -81,12 +106,15 @@ impl NeuralReranker {
     /// Rerank and blend scores with existing RRF scores
     ///
     /// Uses weighted blending: final_score = RERANK_WEIGHT * rerank_score + RRF_WEIGHT * rrf_score
+    ///
+    /// Returns Vec of (original_index, blended_score, normalized_rerank_score)
+    /// sorted by blended score descending
     pub fn rerank_and_blend(
         &mut self,
         query: &str,
         documents: &[String],
         rrf_scores: &[f32],
-    ) -> Result<Vec<(usize, f32)>> {
+    ) -> Result<Vec<(usize, f32, f32)>> {
         if documents.is_empty() {
             return Ok(vec![]);
         }
@@ -108,12 +136,12 @@ impl NeuralReranker {
         let rrf_range = (rrf_max - rrf_min).max(0.0001); // Avoid division by zero
 
         // Blend scores
-        let mut blended: Vec<(usize, f32)> = normalized
+        let mut blended: Vec<(usize, f32, f32)> = normalized
             .into_iter()
             .map(|(idx, rerank_norm)| {
                 let rrf_norm = (rrf_scores[idx] - rrf_min) / rrf_range;
                 let blended_score = RERANK_WEIGHT * rerank_norm + RRF_WEIGHT * rrf_norm;
-                (idx, blended_score)
+                (idx, blended_score, rerank_norm)
             })
             .collect();
 
@@ -0,0 +1,125 @@
+//! Project-specific score multipliers by path (`search.path_weights`)
+//!
+//! Some projects know where their answers live: production code should
+//! outrank fixtures, and generated code is rarely what anyone searches for.
+//! `path_weights` maps gitignore-style patterns to multipliers, above 1 to
+//! boost and below 1 to penalize, so such code still shows up but lower.
+//! A path matching several patterns gets all of their multipliers.
+
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+use super::Boost;
+use crate::vectordb::SearchResult;
+
+struct PathRule {
+    pattern: String,
+    matcher: Gitignore,
+    weight: f32,
+}
+
+/// Compiled `path_weights` patterns
+pub struct PathWeights {
+    rules: Vec<PathRule>,
+    /// Absolute result paths are matched relative to this directory
+    root: Option<PathBuf>,
+}
+
+impl PathWeights {
+    /// Compile the patterns, skipping invalid ones and non-positive weights
+    pub fn new(weights: &BTreeMap<String, f32>) -> Self {
+        let mut rules = Vec::new();
+        for (pattern, &weight) in weights {
+            if weight <= 0.0 {
+                warn!("Ignoring path weight {} for '{}': weights must be positive", weight, pattern);
+                continue;
+            }
+            let mut builder = GitignoreBuilder::new("");
+            let added = builder.add_line(None, pattern).map(|_| ());
+            let matcher = added.and_then(|_| builder.build());
+            match matcher {
+                Ok(matcher) => rules.push(PathRule { pattern: pattern.clone(), matcher, weight }),
+                Err(e) => warn!("Invalid path weight pattern '{}': {}", pattern, e),
+            }
+        }
+        let root = std::env::current_dir().ok().and_then(|dir| dir.canonicalize().ok());
+        Self { rules, root }
+    }
+
+    /// Match absolute paths relative to `root` instead of the working directory
+    pub fn with_root(mut self, root: &Path) -> Self {
+        self.root = Some(root.to_path_buf());
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Multipliers for a result path, one per matching pattern
+    pub fn boosts(&self, path: &str) -> Vec<Boost> {
+        let path = Path::new(path.trim_start_matches("./"));
+        let relative = if path.is_absolute() {
+            match self.root.as_deref().and_then(|root| path.strip_prefix(root).ok()) {
+                Some(relative) => relative,
+                // Outside the project: patterns are relative to it
+                None => return Vec::new(),
+            }
+        } else {
+            path
+        };
+
+        self.rules
+            .iter()
+            .filter(|rule| rule.matcher.matched_path_or_any_parents(relative, false).is_ignore())
+            .map(|rule| Boost::new(format!("path {}", rule.pattern), rule.weight))
+            .collect()
+    }
+}
+
+/// Multiply each result's score by the weights of the patterns its path matches
+///
+/// Does not re-sort; the kind weights that follow do.
+pub fn apply_path_weights(results: &mut [SearchResult], weights: &PathWeights) {
+    if weights.is_empty() {
+        return;
+    }
+    for result in results.iter_mut() {
+        for boost in weights.boosts(&result.path) {
+            result.score *= boost.factor;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_path_weights() {
+        let config = BTreeMap::from([
+            ("src/**".to_string(), 1.2),
+            ("**/tests/**".to_string(), 0.5),
+            ("*.generated.ts".to_string(), 0.0),
+        ]);
+        let weights = PathWeights::new(&config);
+
+        let factors = |path: &str| weights.boosts(path).iter().map(|b| b.factor).collect::<Vec<_>>();
+        assert_eq!(factors("./src/lib.rs"), [1.2]);
+        assert_eq!(factors("crates/core/tests/fixtures.rs"), [0.5]);
+        assert_eq!(factors("src/parser/tests/cases.rs"), [0.5, 1.2]);
+        assert!(factors("build.rs").is_empty());
+        // A zero weight would filter, which is what excludes are for
+        assert!(factors("api/client.generated.ts").is_empty());
+
+        let mut results = vec![
+            SearchResult { path: "src/lib.rs".to_string(), score: 0.5, ..Default::default() },
+            SearchResult { path: "tests/lib.rs".to_string(), score: 0.8, ..Default::default() },
+        ];
+        apply_path_weights(&mut results, &weights);
+        assert!((results[0].score - 0.6).abs() < 1e-6);
+        assert!((results[1].score - 0.4).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,286 @@
+//! SCIP export of the definitions found while chunking (`demongrep scip`)
+//!
+//! The chunker already parses every file with tree-sitter to find
+//! functions, types and methods, so the same pass can feed code-navigation
+//! tools. The output is a SCIP index (Sourcegraph's protobuf format) with a
+//! definition occurrence and symbol information for every named chunk: the
+//! signature and doc comment as documentation, the chunk's lines as the
+//! enclosing range. References are not resolved, so the index is a symbol
+//! table for "go to definition" and symbol search, not full navigation.
+//!
+//! Symbols are global (`demongrep . . . src/`pool.rs`/Pool#acquire().`):
+//! the file path as namespaces, then the breadcrumbs of the definition.
+
+use anyhow::{Context, Result};
+use colored::Colorize;
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::time::Instant;
+
+use crate::chunker::{Chunk, SemanticChunker};
+use crate::config::Config;
+use crate::file::{FileWalker, Language};
+
+/// Scheme and (empty) package of every symbol
+const SYMBOL_PREFIX: &str = "demongrep . . . ";
+
+/// `SymbolRole.Definition`
+const DEFINITION_ROLE: i32 = 1;
+
+/// `TextEncoding.UTF8`
+const UTF8_TEXT: i32 = 1;
+
+/// `PositionEncoding.UTF8CodeUnitOffsetFromLineStart`
+const UTF8_POSITIONS: i32 = 1;
+
+/// Protobuf encoder for the few SCIP messages written here
+#[derive(Default)]
+struct Message(Vec<u8>);
+
+impl Message {
+    fn varint(&mut self, mut value: u64) {
+        while value >= 0x80 {
+            self.0.push(value as u8 | 0x80);
+            value >>= 7;
+        }
+        self.0.push(value as u8);
+    }
+
+    fn key(&mut self, field: u32, wire_type: u8) {
+        self.varint(u64::from(field) << 3 | u64::from(wire_type));
+    }
+
+    fn bytes(&mut self, field: u32, bytes: &[u8]) {
+        self.key(field, 2);
+        self.varint(bytes.len() as u64);
+        self.0.extend_from_slice(bytes);
+    }
+
+    /// Proto3 leaves out zero values
+    fn int32(&mut self, field: u32, value: i32) {
+        if value != 0 {
+            self.key(field, 0);
+            self.varint(value as i64 as u64);
+        }
+    }
+
+    fn string(&mut self, field: u32, value: &str) {
+        if !value.is_empty() {
+            self.bytes(field, value.as_bytes());
+        }
+    }
+
+    fn message(&mut self, field: u32, message: Message) {
+        self.bytes(field, &message.0);
+    }
+
+    fn packed_int32(&mut self, field: u32, values: &[i32]) {
+        let mut packed = Message::default();
+        for &value in values {
+            packed.varint(value as i64 as u64);
+        }
+        self.bytes(field, &packed.0);
+    }
+}
+
+/// A name as a SCIP identifier: as is when simple, otherwise in backticks
+fn escape_identifier(name: &str) -> String {
+    if !name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || matches!(c, '_' | '+' | '-' | '$')) {
+        name.to_string()
+    } else {
+        format!("`{}`", name.replace('`', "``"))
+    }
+}
+
+/// SCIP descriptor for a breadcrumb like "Method: acquire" (None without a name)
+fn descriptor(label: &str) -> Option<String> {
+    let (kind, name) = label.split_once(": ")?;
+    let name = escape_identifier(name);
+    Some(match kind {
+        "Function" | "Method" => format!("{}().", name),
+        "Module" | "Mod" => format!("{}/", name),
+        "Class" | "Struct" | "Enum" | "Trait" | "Interface" | "Impl" | "Type" | "TypeAlias" => format!("{}#", name),
+        _ => format!("{}.", name),
+    })
+}
+
+/// Global symbol of a definition at `relative_path` with these breadcrumbs
+///
+/// None when the chunk or one of its parents has no name.
+pub fn chunk_symbol(relative_path: &str, context: &[String]) -> Option<String> {
+    let mut symbol = SYMBOL_PREFIX.to_string();
+    for part in relative_path.split('/').filter(|part| !part.is_empty() && *part != ".") {
+        symbol.push_str(&escape_identifier(part));
+        symbol.push('/');
+    }
+    let mut labels = context.iter().filter(|label| !label.starts_with("File: ")).peekable();
+    labels.peek()?;
+    for label in labels {
+        symbol.push_str(&descriptor(label)?);
+    }
+    Some(symbol)
+}
+
+/// Line within `content` and byte column of the first whole-word `name`
+fn find_name(content: &str, name: &str) -> Option<(usize, usize)> {
+    let is_word = |c: Option<char>| c.is_some_and(|c| c.is_alphanumeric() || c == '_');
+    if name.is_empty() {
+        return None;
+    }
+    for (line_idx, line) in content.lines().enumerate() {
+        let mut from = 0;
+        while let Some(pos) = line[from..].find(name) {
+            let start = from + pos;
+            let end = start + name.len();
+            if !is_word(line[..start].chars().next_back()) && !is_word(line[end..].chars().next()) {
+                return Some((line_idx, start));
+            }
+            from = end;
+        }
+    }
+    None
+}
+
+/// Language name as SCIP spells it
+fn scip_language(language: Language) -> &'static str {
+    match language {
+        Language::Cpp => "CPP",
+        Language::CSharp => "CSharp",
+        Language::Shell => "ShellScript",
+        _ => language.name(),
+    }
+}
+
+/// The SCIP document for one file; returns it with the number of symbols
+fn document(relative_path: &str, language: Language, chunks: &[Chunk]) -> (Message, usize) {
+    let mut doc = Message::default();
+    doc.string(1, relative_path);
+    doc.string(4, scip_language(language));
+    doc.int32(6, UTF8_POSITIONS);
+
+    let mut seen = HashSet::new();
+    for chunk in chunks {
+        let Some(name) = chunk.name.as_deref() else {
+            continue;
+        };
+        let Some(symbol) = chunk_symbol(relative_path, &chunk.context) else {
+            continue;
+        };
+        // Overloads share a symbol; the first definition stands for all of them
+        if !seen.insert(symbol.clone()) {
+            continue;
+        }
+
+        let range = match find_name(&chunk.content, name) {
+            Some((line, column)) => [chunk.start_line + line, column, column + name.len()],
+            None => [chunk.start_line, 0, 0],
+        };
+        let mut occurrence = Message::default();
+        occurrence.packed_int32(1, &range.map(|n| n as i32));
+        occurrence.string(2, &symbol);
+        occurrence.int32(3, DEFINITION_ROLE);
+        occurrence.packed_int32(7, &[chunk.start_line as i32, 0, chunk.end_line as i32, 0]);
+        doc.message(2, occurrence);
+
+        let mut info = Message::default();
+        info.string(1, &symbol);
+        if let Some(ref signature) = chunk.signature {
+            info.string(3, &format!("```{}\n{}\n```", language.fence_tag(), signature));
+        }
+        if let Some(ref docstring) = chunk.docstring {
+            info.string(3, docstring);
+        }
+        info.string(6, name);
+        doc.message(3, info);
+    }
+    (doc, seen.len())
+}
+
+/// Chunk the project at `path` and write its definitions to `output` as SCIP
+pub async fn export_scip(path: Option<PathBuf>, output: PathBuf, config: &Config) -> Result<()> {
+    let root = path.unwrap_or_else(|| PathBuf::from(".")).canonicalize()?;
+    let start = Instant::now();
+    println!("{} {}", "🔍 Parsing".bright_cyan(), root.display());
+
+    let walker = FileWalker::new(root.clone()).with_excludes(&config.indexing.excludes);
+    let (files, _stats) = walker.walk()?;
+    let mut chunker = SemanticChunker::from_config(&config.indexing);
+
+    let mut index = Message::default();
+    let mut metadata = Message::default();
+    let mut tool = Message::default();
+    tool.string(1, "demongrep");
+    tool.string(2, env!("CARGO_PKG_VERSION"));
+    metadata.message(2, tool);
+    metadata.string(3, &format!("file://{}", root.to_string_lossy().replace('\\', "/")));
+    metadata.int32(4, UTF8_TEXT);
+    index.message(1, metadata);
+
+    let mut documents = 0;
+    let mut symbols = 0;
+    for file in &files {
+        let Ok(source) = std::fs::read_to_string(&file.path) else {
+            continue;
+        };
+        let chunks = chunker.chunk_semantic(file.language, &file.path, &source)?;
+        let relative = file.path.strip_prefix(&root).unwrap_or(&file.path);
+        let relative = relative.to_string_lossy().replace('\\', "/");
+        let (doc, count) = document(&relative, file.language, &chunks);
+        if count > 0 {
+            index.message(2, doc);
+            documents += 1;
+            symbols += count;
+        }
+    }
+
+    std::fs::write(&output, &index.0).with_context(|| format!("Failed to write {}", output.display()))?;
+    println!("   Files: {} ({} with definitions)", files.len(), documents);
+    println!("   Symbols: {}", symbols);
+    println!(
+        "{}",
+        format!("✅ Wrote {} ({} KB) in {:?}", output.display(), index.0.len() / 1024, start.elapsed()).green()
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunker::ChunkKind;
+
+    #[test]
+    fn test_chunk_symbol() {
+        let context = |labels: &[&str]| labels.iter().map(|label| label.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            chunk_symbol("src/pool.rs", &context(&["File: src/pool.rs", "Impl: Pool", "Method: acquire"])).unwrap(),
+            "demongrep . . . src/`pool.rs`/Pool#acquire()."
+        );
+        assert_eq!(
+            chunk_symbol("app.py", &context(&["File: app.py", "Const: MAX_SIZE"])).unwrap(),
+            "demongrep . . . `app.py`/MAX_SIZE."
+        );
+        // Gap chunks and unnamed parents have no symbol
+        assert_eq!(chunk_symbol("app.py", &context(&["File: app.py"])), None);
+        assert_eq!(chunk_symbol("a.rs", &context(&["File: a.rs", "Impl", "Method: new"])), None);
+        assert_eq!(escape_identifier("Display for Pool"), "`Display for Pool`");
+    }
+
+    #[test]
+    fn test_document() {
+        assert_eq!(find_name("/// Get a pool\npub fn pool_size() -> usize", "pool_size"), Some((1, 7)));
+        assert_eq!(find_name("fn pools()", "pool"), None);
+
+        let mut chunk = Chunk::new("fn main() {}".to_string(), 4, 5, ChunkKind::Function, "a.rs".to_string());
+        chunk.context = vec!["File: a.rs".to_string(), "Function: main".to_string()];
+        chunk.name = Some("main".to_string());
+        let gap = Chunk::new("use std::io;".to_string(), 0, 1, ChunkKind::Imports, "a.rs".to_string());
+        let (doc, symbols) = document("a.rs", Language::Rust, &[gap, chunk.clone(), chunk]);
+        assert_eq!(symbols, 1);
+
+        // relative_path, then the occurrence with its range [4, 3, 7]
+        let mut expected = Message::default();
+        expected.string(1, "a.rs");
+        assert!(doc.0.starts_with(&expected.0));
+        assert!(doc.0.windows(5).any(|w| w == [0x0a, 3, 4, 3, 7]));
+    }
+}
@@ -0,0 +1,81 @@
+//! Search results as LLM context (`demongrep ask "question"`)
+//!
+//! Automates the usual manual workflow of searching, picking the relevant
+//! hits and pasting them into a chat: the top results go through the same
+//! packer as `search --format context` (repeats dropped, the rest fitted into
+//! a token budget in rank order and grouped by file). Only the context goes
+//! to stdout, so it can be piped straight into another tool.
+
+use anyhow::Result;
+use std::path::PathBuf;
+
+use super::context::pack_context;
+use super::{rank_results, retain_under, transform_results, ParsedQuery};
+use crate::config::Config;
+use crate::database::DatabaseManager;
+use crate::embed::{count_tokens, EmbeddingService};
+use crate::rerank::NeuralReranker;
+use crate::vectordb::StoreOptions;
+
+/// Search and print the top results as a token-budgeted context block
+#[allow(clippy::too_many_arguments)]
+pub async fn ask(
+    query: &str,
+    budget: usize,
+    max_results: usize,
+    filter_path: Option<String>,
+    rerank: bool,
+    rerank_top: usize,
+    prefer_project: bool,
+    path: Option<PathBuf>,
+    config: &Config,
+) -> Result<()> {
+    // stdout carries only the context
+    crate::output::set_quiet(true);
+
+    let mut parsed = ParsedQuery::parse(query)?;
+    parsed.filters.under = filter_path.clone();
+    let query = parsed.text.as_str();
+    let db_manager = DatabaseManager::load_with_options(path, &StoreOptions::from_config(config))?;
+    let mut embedding_service = EmbeddingService::with_model(db_manager.model_type())?;
+    let query_embedding = embedding_service.embed_query(query)?;
+
+    let mut reranker = if rerank {
+        NeuralReranker::new()
+            .map_err(|e| eprintln!("⚠️  Reranking skipped: {}", e))
+            .ok()
+    } else {
+        None
+    };
+    let mut results = rank_results(
+        &db_manager,
+        &parsed,
+        &query_embedding,
+        max_results,
+        reranker.as_mut(),
+        rerank_top,
+        prefer_project,
+        config,
+    )?;
+    if let Some(ref filter) = filter_path {
+        retain_under(&mut results, filter);
+    }
+    if let Some(ref command) = config.search.transform_command {
+        match transform_results(command, query, results.clone()) {
+            Ok(transformed) => results = transformed,
+            Err(e) => eprintln!("⚠️  Result transform skipped: {:#}", e),
+        }
+    }
+    results.truncate(max_results);
+
+    let total = results.len();
+    let (context, included) = pack_context(query, results, budget);
+    if included == 0 {
+        eprintln!("No matches fit in {} tokens. Try a larger --budget or run `demongrep index`", budget);
+        return Ok(());
+    }
+
+    print!("{}", context);
+    eprintln!("{} of {} results, {} tokens", included, total, count_tokens(&context));
+    Ok(())
+}
@@ -0,0 +1,221 @@
+//! Many queries in one run (`search --queries-file` / `-e`)
+//!
+//! Evaluating a query set, or an agent asking a dozen questions up front,
+//! would otherwise start the binary and load the model once per query. In
+//! batch mode the model loads once, every query is embedded in one call,
+//! and the results come back grouped by query. Ranking is the hybrid
+//! ranking `ask` and `serve` use.
+
+use anyhow::{bail, Context, Result};
+use colored::Colorize;
+use serde::Serialize;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use super::{
+    cap_total_tokens, duplicates, markdown_results, merge, print_result, rank_results, retain_under,
+    transform_results, JsonOutput, JsonResult, OutputFormat, ParsedQuery, CONTENT_LINES,
+};
+use crate::config::Config;
+use crate::database::DatabaseManager;
+use crate::embed::EmbeddingService;
+use crate::rerank::NeuralReranker;
+use crate::vectordb::{SearchResult, StoreOptions};
+
+/// Queries from a file, one per line; blank lines and `#` comments are skipped
+pub fn parse_queries(text: &str) -> Vec<String> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect()
+}
+
+/// Read a queries file (`-` for stdin)
+pub fn read_queries_file(path: &Path) -> Result<Vec<String>> {
+    let text = if path == Path::new("-") {
+        let mut text = String::new();
+        std::io::stdin().read_to_string(&mut text).context("Failed to read queries from stdin")?;
+        text
+    } else {
+        std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?
+    };
+    Ok(parse_queries(&text))
+}
+
+#[derive(Serialize)]
+struct BatchOutput {
+    queries: Vec<JsonOutput>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    timing: Option<BatchTiming>,
+}
+
+#[derive(Serialize)]
+struct BatchTiming {
+    total_ms: u64,
+    model_load_ms: u64,
+    /// All queries, embedded together
+    embed_ms: u64,
+    search_ms: u64,
+}
+
+/// Search every query and print the results grouped by query
+#[allow(clippy::too_many_arguments)]
+pub async fn batch_search(
+    queries: &[String],
+    max_results: usize,
+    content: bool,
+    scores: bool,
+    format: OutputFormat,
+    path: Option<PathBuf>,
+    filter_path: Option<String>,
+    rerank: bool,
+    rerank_top: usize,
+    prefer_project: bool,
+    collapse_duplicates: bool,
+    max_total_tokens: Option<usize>,
+    config: &Config,
+) -> Result<()> {
+    if queries.is_empty() {
+        bail!("No queries to search");
+    }
+    if matches!(format, OutputFormat::Sarif | OutputFormat::Context) {
+        bail!("--format sarif and --format context take a single query");
+    }
+    let total_start = Instant::now();
+    let mut parsed = queries.iter().map(|query| ParsedQuery::parse(query)).collect::<Result<Vec<_>>>()?;
+    for query in &mut parsed {
+        query.filters.under = filter_path.clone();
+    }
+    let texts: Vec<String> = parsed.iter().map(|query| query.text.clone()).collect();
+
+    let db_manager = DatabaseManager::load_with_options(path, &StoreOptions::from_config(config))?;
+    let start = Instant::now();
+    let mut embedding_service = EmbeddingService::with_model(db_manager.model_type())?;
+    let model_load_duration = start.elapsed();
+
+    let start = Instant::now();
+    let query_embeddings = embedding_service.embed_queries(&texts)?;
+    let embed_duration = start.elapsed();
+
+    let mut reranker = if rerank {
+        NeuralReranker::new()
+            .map_err(|e| eprintln!("{}", format!("⚠️  Could not load reranker: {}", e).yellow()))
+            .ok()
+    } else {
+        None
+    };
+
+    let start = Instant::now();
+    let mut grouped: Vec<(&str, Vec<SearchResult>)> = Vec::with_capacity(queries.len());
+    for ((query, parsed), query_embedding) in queries.iter().zip(&parsed).zip(&query_embeddings) {
+        let mut results = rank_results(
+            &db_manager,
+            parsed,
+            query_embedding,
+            max_results,
+            reranker.as_mut(),
+            rerank_top,
+            prefer_project,
+            config,
+        )?;
+        merge::merge_split_parts(&mut results);
+        if let Some(ref filter) = filter_path {
+            retain_under(&mut results, filter);
+        }
+        if collapse_duplicates {
+            duplicates::collapse_near_duplicates(&mut results);
+        }
+        if let Some(ref command) = config.search.transform_command {
+            match transform_results(command, &parsed.text, results.clone()) {
+                Ok(transformed) => results = transformed,
+                Err(e) => eprintln!("{}", format!("⚠️  Result transform skipped: {:#}", e).yellow()),
+            }
+        }
+        results.truncate(max_results);
+        if let Some(max_total_tokens) = max_total_tokens {
+            cap_total_tokens(&mut results, max_total_tokens);
+        }
+        grouped.push((query.as_str(), results));
+    }
+    let search_duration = start.elapsed();
+
+    let json_output = |query: &str, results: &[SearchResult]| JsonOutput {
+        query: query.to_string(),
+        results: results.iter().map(|r| JsonResult::new(r, None)).collect(),
+        timing: None,
+    };
+    match format {
+        OutputFormat::Json => {
+            let timing = scores.then(|| BatchTiming {
+                total_ms: total_start.elapsed().as_millis() as u64,
+                model_load_ms: model_load_duration.as_millis() as u64,
+                embed_ms: embed_duration.as_millis() as u64,
+                search_ms: search_duration.as_millis() as u64,
+            });
+            let output = BatchOutput {
+                queries: grouped.iter().map(|(query, results)| json_output(query, results)).collect(),
+                timing,
+            };
+            println!("{}", serde_json::to_string(&output)?);
+        }
+        OutputFormat::Jsonl => {
+            // One line per query
+            for (query, results) in &grouped {
+                println!("{}", serde_json::to_string(&json_output(query, results))?);
+            }
+        }
+        OutputFormat::Markdown => {
+            let sections: Vec<String> =
+                grouped.iter().map(|(query, results)| markdown_results(query, results, &[])).collect();
+            print!("{}", sections.join("\n"));
+        }
+        OutputFormat::Text | OutputFormat::Sarif | OutputFormat::Context => {
+            let content_lines = content.then_some(CONTENT_LINES);
+            for (query, results) in &grouped {
+                println!("{}", "=".repeat(60));
+                println!(
+                    "{} \"{}\" ({} result{})",
+                    "🔍".bright_cyan(),
+                    query.bright_yellow(),
+                    results.len(),
+                    if results.len() == 1 { "" } else { "s" }
+                );
+                if results.is_empty() {
+                    println!("{}", "No matches found.".dimmed());
+                }
+                for result in results {
+                    print_result(result, true, content_lines, scores, None, None)?;
+                }
+                println!();
+            }
+            if scores {
+                println!(
+                    "{}",
+                    format!(
+                        "{} queries in {:?} (model load {:?}, embedding {:?}, search {:?})",
+                        queries.len(),
+                        total_start.elapsed(),
+                        model_load_duration,
+                        embed_duration,
+                        search_duration
+                    )
+                    .dimmed()
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_queries() {
+        let text = "# auth questions\nwhere are tokens validated?\n\n  session expiry  \n#skip\nRetryPolicy\n";
+        assert_eq!(parse_queries(text), ["where are tokens validated?", "session expiry", "RetryPolicy"]);
+    }
+}
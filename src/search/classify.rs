@@ -0,0 +1,136 @@
+//! Lightweight query classifier
+//!
+//! A single exact identifier (`parse_config`, `HashMap::new`) is better served
+//! by keyword search than by embeddings, while a natural-language question is
+//! better served by embeddings alone. `classify_query` picks the pipeline that
+//! fits; `search --auto` uses it, otherwise it only drives a hint.
+
+/// Words that open a natural-language question
+const QUESTION_WORDS: &[&str] = &[
+    "how", "where", "what", "why", "which", "when", "who", "does", "is", "are", "can", "find", "show",
+];
+
+/// Descriptive phrases of at least this many words go to vector search
+const SEMANTIC_MIN_WORDS: usize = 4;
+
+/// Retrieval pipeline for a query
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pipeline {
+    /// Full-text (BM25) search only
+    Lexical,
+    /// Vector and full-text search fused with RRF
+    Hybrid,
+    /// Vector search only
+    Semantic,
+}
+
+impl Pipeline {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Pipeline::Lexical => "lexical (FTS only)",
+            Pipeline::Hybrid => "hybrid (vector + FTS)",
+            Pipeline::Semantic => "semantic (vector only)",
+        }
+    }
+}
+
+/// The pipeline a query should use, and why
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QueryClass {
+    pub pipeline: Pipeline,
+    pub reason: &'static str,
+}
+
+impl QueryClass {
+    fn new(pipeline: Pipeline, reason: &'static str) -> Self {
+        Self { pipeline, reason }
+    }
+}
+
+/// Whether a word looks like a code identifier rather than an English word
+///
+/// Matches snake_case, camelCase/PascalCase, CONSTANTS, paths (`a::b`, `a.b`)
+/// and calls (`foo()`).
+pub fn is_identifier_like(word: &str) -> bool {
+    let call = word.ends_with("()");
+    let word = word.strip_suffix("()").unwrap_or(word);
+    if !word.chars().any(|c| c.is_alphabetic())
+        || !word.chars().all(|c| c.is_alphanumeric() || "_:.$".contains(c))
+    {
+        return false;
+    }
+
+    let chars: Vec<char> = word.chars().collect();
+    let camel = chars.windows(2).any(|w| w[0].is_lowercase() && w[1].is_uppercase());
+    let constant = chars.len() > 1 && chars.iter().all(|c| !c.is_lowercase());
+    let separated = word.contains('_') || word.contains("::") || word.trim_matches('.').contains('.');
+
+    call || camel || constant || separated
+}
+
+/// Pick the retrieval pipeline for a query
+pub fn classify_query(query: &str) -> QueryClass {
+    let query = query.trim();
+
+    let quoted = query.len() >= 2
+        && ((query.starts_with('"') && query.ends_with('"')) || (query.starts_with('`') && query.ends_with('`')));
+    if quoted {
+        return QueryClass::new(Pipeline::Lexical, "quoted exact string");
+    }
+
+    let words: Vec<&str> = query.split_whitespace().collect();
+    match words.as_slice() {
+        [] => return QueryClass::new(Pipeline::Hybrid, "empty query"),
+        [word] if is_identifier_like(word) => {
+            return QueryClass::new(Pipeline::Lexical, "single exact identifier");
+        }
+        [_] => return QueryClass::new(Pipeline::Hybrid, "single word: could be a name or a concept"),
+        _ => {}
+    }
+
+    let first = words[0].to_lowercase();
+    if query.ends_with('?') || QUESTION_WORDS.contains(&first.as_str()) {
+        return QueryClass::new(Pipeline::Semantic, "natural-language question");
+    }
+
+    if words.iter().any(|w| is_identifier_like(w)) {
+        return QueryClass::new(Pipeline::Hybrid, "mixes identifiers and words");
+    }
+
+    if words.len() >= SEMANTIC_MIN_WORDS {
+        return QueryClass::new(Pipeline::Semantic, "descriptive phrase");
+    }
+
+    QueryClass::new(Pipeline::Hybrid, "short phrase")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_identifier_like() {
+        for word in ["parse_config", "parseConfig", "HashMap", "MAX_SIZE", "std::fs", "self.client", "run()"] {
+            assert!(is_identifier_like(word), "{}", word);
+        }
+        for word in ["authentication", "Server", "error?", "42", "a"] {
+            assert!(!is_identifier_like(word), "{}", word);
+        }
+    }
+
+    #[test]
+    fn test_classify_query() {
+        let pipeline = |q: &str| classify_query(q).pipeline;
+
+        assert_eq!(pipeline("parse_config"), Pipeline::Lexical);
+        assert_eq!(pipeline("HashMap::new"), Pipeline::Lexical);
+        assert_eq!(pipeline("\"connection refused\""), Pipeline::Lexical);
+
+        assert_eq!(pipeline("where do we handle authentication?"), Pipeline::Semantic);
+        assert_eq!(pipeline("retry failed uploads with exponential backoff"), Pipeline::Semantic);
+
+        assert_eq!(pipeline("authentication"), Pipeline::Hybrid);
+        assert_eq!(pipeline("error handling"), Pipeline::Hybrid);
+        assert_eq!(pipeline("callers of parse_config in the server"), Pipeline::Hybrid);
+    }
+}
@@ -0,0 +1,187 @@
+//! Search results as one prompt-ready block (`search --format context`)
+//!
+//! What agents otherwise do by hand with `--json`: drop repeats, fit the
+//! best results into a token budget and paste them as fenced code under
+//! their paths. Results are picked in rank order, so the budget goes to the
+//! best matches, and printed grouped by file in line order, so code from one
+//! file reads top to bottom. Tokens are counted with GPT-4's BPE tokenizer
+//! rather than estimated; other models' tokenizers come out close on code.
+
+use std::collections::BTreeMap;
+
+use super::fenced_code;
+use crate::embed::count_tokens;
+use crate::vectordb::SearchResult;
+
+/// Token budget of `--format context` without `--budget-tokens`
+pub const DEFAULT_BUDGET_TOKENS: usize = 6000;
+
+/// LLM prompt tokens in a result's code: counted at index time, or now when unknown (such as results a transform command built)
+pub fn result_tokens(result: &SearchResult) -> usize {
+    if result.tokens > 0 {
+        result.tokens
+    } else {
+        count_tokens(&result.content)
+    }
+}
+
+/// Keep results, in rank order, while their code fits in `max_total` tokens
+///
+/// A result too large for what is left is dropped; smaller ones after it
+/// may still fit.
+pub fn cap_total_tokens(results: &mut Vec<SearchResult>, max_total: usize) {
+    let mut used = 0;
+    results.retain(|result| {
+        let tokens = result_tokens(result);
+        if used + tokens > max_total {
+            return false;
+        }
+        used += tokens;
+        true
+    });
+}
+
+/// Drop results that repeat one already ranked higher
+///
+/// A result is a repeat when it overlaps the lines of a kept result in the
+/// same file, or has exactly the same code (copies in vendored or generated
+/// files, for example).
+fn dedup_context(results: Vec<SearchResult>) -> Vec<SearchResult> {
+    let mut kept: Vec<SearchResult> = Vec::new();
+    for result in results {
+        let repeat = kept.iter().any(|k| {
+            (k.path == result.path && k.start_line < result.end_line && result.start_line < k.end_line)
+                || k.content.trim() == result.content.trim()
+        });
+        if !repeat {
+            kept.push(result);
+        }
+    }
+    kept
+}
+
+/// Header for a file's results
+fn file_header(path: &str) -> String {
+    format!("\n## {}\n", path)
+}
+
+/// One result: its lines and signature, how it was reached, notes, then the code
+fn result_block(result: &SearchResult) -> String {
+    let mut block = format!("\n### Lines {}-{}", result.start_line, result.end_line);
+    if let Some(ref signature) = result.signature {
+        block.push_str(&format!(": `{}`", signature.replace('`', "'")));
+    }
+    block.push('\n');
+    if let Some(ref via) = result.via {
+        block.push_str(&format!("> Definition {}\n", via));
+    }
+    for note in &result.notes {
+        block.push_str(&format!("> Note: {}\n", note));
+    }
+    block.push_str(&fenced_code(&result.path, &result.content));
+    block
+}
+
+/// The context block for `results` and how many of them it holds
+///
+/// Results that repeat a higher-ranked one are dropped; the rest are taken
+/// in rank order while they fit in `budget` tokens, skipping any that
+/// don't so smaller ones further down can still use the room.
+pub fn pack_context(query: &str, results: Vec<SearchResult>, budget: usize) -> (String, usize) {
+    let title = format!("# Code context for: {}\n", query);
+    let mut used = count_tokens(&title);
+    let mut files: BTreeMap<&str, Vec<(usize, String)>> = BTreeMap::new();
+    let results = dedup_context(results);
+
+    for result in &results {
+        let block = result_block(result);
+        let mut tokens = count_tokens(&block);
+        if !files.contains_key(result.path.as_str()) {
+            tokens += count_tokens(&file_header(&result.path));
+        }
+        if used + tokens > budget {
+            continue;
+        }
+        used += tokens;
+        files.entry(&result.path).or_default().push((result.start_line, block));
+    }
+
+    let mut context = title;
+    let mut included = 0;
+    for (path, mut blocks) in files {
+        blocks.sort_by_key(|(start_line, _)| *start_line);
+        context.push_str(&file_header(path));
+        for (_, block) in blocks {
+            context.push_str(&block);
+            included += 1;
+        }
+    }
+    (context, included)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(path: &str, start_line: usize, end_line: usize, content: &str) -> SearchResult {
+        SearchResult {
+            path: path.to_string(),
+            start_line,
+            end_line,
+            content: content.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_dedup_context() {
+        let results = dedup_context(vec![
+            result("src/auth.rs", 10, 20, "fn validate() {}"),
+            // Overlaps the first result
+            result("src/auth.rs", 15, 30, "fn inner() {}"),
+            // Same code in another file
+            result("vendor/auth.rs", 10, 20, "fn validate() {}"),
+            result("src/token.rs", 1, 5, "fn refresh() {}"),
+        ]);
+        let paths: Vec<&str> = results.iter().map(|r| r.path.as_str()).collect();
+        assert_eq!(paths, ["src/auth.rs", "src/token.rs"]);
+    }
+
+    #[test]
+    fn test_pack_context() {
+        let results = vec![
+            result("src/token.rs", 40, 50, "fn refresh() {}"),
+            result("src/big.rs", 0, 400, &"let x = compute_value(42);\n".repeat(400)),
+            result("src/auth.rs", 10, 20, "fn validate() {}"),
+            result("src/token.rs", 1, 5, "fn parse() {}"),
+            // Repeats the first result
+            result("vendor/token.rs", 0, 10, "fn refresh() {}"),
+        ];
+        let (context, included) = pack_context("token validation", results, 200);
+        assert_eq!(included, 3);
+        assert!(count_tokens(&context) <= 200);
+        // The big block doesn't fit; the rest are grouped by file in line order
+        assert_eq!(
+            context,
+            "# Code context for: token validation\n\n\
+             ## src/auth.rs\n\n### Lines 10-20\n```rust\nfn validate() {}\n```\n\n\
+             ## src/token.rs\n\n### Lines 1-5\n```rust\nfn parse() {}\n```\n\n\
+             ### Lines 40-50\n```rust\nfn refresh() {}\n```\n"
+        );
+
+        let (_, included) = pack_context("token validation", vec![result("src/a.rs", 0, 1, "fn a() {}")], 5);
+        assert_eq!(included, 0);
+    }
+
+    #[test]
+    fn test_cap_total_tokens() {
+        let sized = |path: &str, tokens| SearchResult { tokens, ..result(path, 0, 1, "") };
+        let mut results = vec![sized("a.rs", 300), sized("b.rs", 800), sized("c.rs", 150), sized("d.rs", 100)];
+        cap_total_tokens(&mut results, 500);
+        let paths: Vec<&str> = results.iter().map(|r| r.path.as_str()).collect();
+        assert_eq!(paths, ["a.rs", "c.rs"]);
+
+        // Without a stored count the code is counted
+        assert_eq!(result_tokens(&result("a.rs", 0, 1, "fn validate_token() {}")), 5);
+    }
+}
@@ -0,0 +1,111 @@
+//! Aggregate counts instead of results (`search --count`, `--count-by`)
+//!
+//! Some questions are about spread, not about any one match: how many places
+//! read feature flags, which modules handle retries. `--count` prints how
+//! many results there are and in how many files; `--count-by` groups them
+//! by file, language or chunk kind. Counts are over the results a search
+//! would show, so `--max-results` sets how deep they look.
+
+use anyhow::Result;
+use colored::Colorize;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use super::OutputFormat;
+use crate::file::Language;
+use crate::vectordb::SearchResult;
+
+/// What `search --count-by` groups results by
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum CountBy {
+    /// File path
+    Path,
+    /// Language of the file
+    Lang,
+    /// Chunk kind (Function, Class, ...)
+    Kind,
+}
+
+#[derive(Serialize)]
+struct CountOutput<'a> {
+    query: &'a str,
+    total: usize,
+    files: usize,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    counts: Vec<GroupCount>,
+}
+
+#[derive(Serialize)]
+struct GroupCount {
+    key: String,
+    count: usize,
+}
+
+/// Results per group, largest first, then by name
+fn group_counts(results: &[SearchResult], by: CountBy) -> Vec<(String, usize)> {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for result in results {
+        let key = match by {
+            CountBy::Path => result.path.clone(),
+            CountBy::Lang => Language::from_path(Path::new(&result.path)).name().to_string(),
+            CountBy::Kind => result.kind.clone(),
+        };
+        *counts.entry(key).or_default() += 1;
+    }
+    let mut counts: Vec<(String, usize)> = counts.into_iter().collect();
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    counts
+}
+
+/// Print the number of results, grouped with `by`, in place of the results
+pub fn print_counts(query: &str, results: &[SearchResult], by: Option<CountBy>, format: OutputFormat) -> Result<()> {
+    let files = results.iter().map(|r| r.path.as_str()).collect::<HashSet<_>>().len();
+    let counts = by.map(|by| group_counts(results, by)).unwrap_or_default();
+
+    if matches!(format, OutputFormat::Json | OutputFormat::Jsonl) {
+        let output = CountOutput {
+            query,
+            total: results.len(),
+            files,
+            counts: counts.into_iter().map(|(key, count)| GroupCount { key, count }).collect(),
+        };
+        println!("{}", serde_json::to_string(&output)?);
+        return Ok(());
+    }
+
+    println!(
+        "{} result{} in {} file{}",
+        results.len(),
+        if results.len() == 1 { "" } else { "s" },
+        files,
+        if files == 1 { "" } else { "s" }
+    );
+    let width = counts.first().map(|(_, count)| count.to_string().len()).unwrap_or(0);
+    for (key, count) in &counts {
+        println!("  {}  {}", format!("{:>width$}", count, width = width).bold(), key);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_group_counts() {
+        let result = |path: &str, kind: &str| SearchResult { path: path.to_string(), kind: kind.to_string(), ..Default::default() };
+        let results = vec![
+            result("src/flags.rs", "Function"),
+            result("web/flags.ts", "Function"),
+            result("src/flags.rs", "Struct"),
+            result("src/api.rs", "Function"),
+        ];
+        assert_eq!(
+            group_counts(&results, CountBy::Path),
+            [("src/flags.rs".to_string(), 2), ("src/api.rs".to_string(), 1), ("web/flags.ts".to_string(), 1)]
+        );
+        assert_eq!(group_counts(&results, CountBy::Lang), [("Rust".to_string(), 3), ("TypeScript".to_string(), 1)]);
+        assert_eq!(group_counts(&results, CountBy::Kind), [("Function".to_string(), 3), ("Struct".to_string(), 1)]);
+    }
+}
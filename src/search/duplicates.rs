@@ -0,0 +1,73 @@
+//! Collapsing near-duplicate results
+//!
+//! Vendored libraries, generated clients and copy-pasted helpers put the
+//! same code under several paths, and every copy matches the same queries.
+//! A result that repeats a better-ranked one from another file, exactly or
+//! by near-duplicate fingerprint, is folded into it; the kept result lists
+//! where else the code lives.
+
+use crate::chunker::is_near_duplicate;
+use crate::vectordb::SearchResult;
+
+/// Fold results that duplicate a better-ranked result in another file into it
+///
+/// Expects results in rank order. Copies within one file are left alone.
+pub fn collapse_near_duplicates(results: &mut Vec<SearchResult>) {
+    let mut kept: Vec<SearchResult> = Vec::with_capacity(results.len());
+    for result in results.drain(..) {
+        let original = kept.iter_mut().find(|kept| {
+            kept.path != result.path
+                && (kept.hash == result.hash || is_near_duplicate(kept.simhash, result.simhash))
+        });
+        match original {
+            Some(original) => {
+                if !original.also_in.contains(&result.path) {
+                    original.also_in.push(result.path);
+                }
+            }
+            None => kept.push(result),
+        }
+    }
+    *results = kept;
+}
+
+/// "also in 2 other files: a.rs, b.rs", listing at most `shown` paths
+pub fn describe_also_in(also_in: &[String], shown: usize) -> String {
+    let files = if also_in.len() == 1 { "file" } else { "files" };
+    let mut listed = also_in.iter().take(shown).cloned().collect::<Vec<_>>().join(", ");
+    if also_in.len() > shown {
+        listed.push_str(&format!(" and {} more", also_in.len() - shown));
+    }
+    format!("also in {} other {}: {}", also_in.len(), files, listed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(path: &str, hash: &str, simhash: u64) -> SearchResult {
+        SearchResult { path: path.to_string(), hash: hash.to_string(), simhash, ..Default::default() }
+    }
+
+    #[test]
+    fn test_collapse_near_duplicates() {
+        let mut results = vec![
+            result("src/retry.rs", "a", 0b1111_0000),
+            result("vendor/retry/lib.rs", "b", 0b1111_0001),
+            result("src/config.rs", "c", 0xffff_0000_0000),
+            result("third_party/retry.rs", "a", 0),
+            result("src/retry.rs", "d", 0b1111_0000),
+        ];
+        collapse_near_duplicates(&mut results);
+
+        let paths: Vec<&str> = results.iter().map(|r| r.path.as_str()).collect();
+        assert_eq!(paths, ["src/retry.rs", "src/config.rs", "src/retry.rs"]);
+        assert_eq!(results[0].also_in, ["vendor/retry/lib.rs", "third_party/retry.rs"]);
+        assert!(results[1].also_in.is_empty());
+
+        assert_eq!(
+            describe_also_in(&results[0].also_in, 1),
+            "also in 2 other files: vendor/retry/lib.rs and 1 more"
+        );
+    }
+}
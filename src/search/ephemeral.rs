@@ -0,0 +1,187 @@
+//! Searching a directory that has no index (`search --ephemeral`)
+//!
+//! An index pays off for a project searched again and again, not for a
+//! one-off look into a vendored dependency or a scratch checkout. With
+//! `--ephemeral` the directory is walked, chunked and embedded in memory,
+//! searched by vector similarity and forgotten; nothing is written to disk.
+//! Every chunk is embedded on every run, so the directory may hold at most
+//! `MAX_EPHEMERAL_CHUNKS` chunks.
+
+use anyhow::{bail, Result};
+use colored::Colorize;
+use std::path::PathBuf;
+use std::time::Instant;
+
+use super::{
+    cap_total_tokens, duplicates, markdown_results, merge, print_context, print_result, write_jsonl, JsonOutput,
+    JsonResult, OutputFormat, ParsedQuery, CONTENT_LINES,
+};
+use crate::chunker::{simhash, SemanticChunker};
+use crate::config::Config;
+use crate::embed::{count_tokens, EmbeddedChunk, EmbeddingService, ModelType};
+use crate::file::FileWalker;
+use crate::rerank::apply_kind_weights;
+use crate::vectordb::SearchResult;
+
+/// Most chunks an ephemeral search embeds
+pub const MAX_EPHEMERAL_CHUNKS: usize = 3000;
+
+/// Results for every chunk, best match first
+fn rank_chunks(embedded: Vec<EmbeddedChunk>, query_embedding: &[f32]) -> Vec<SearchResult> {
+    let mut results: Vec<SearchResult> = embedded
+        .into_iter()
+        .enumerate()
+        .map(|(idx, embedded)| {
+            let score = embedded.similarity_to(query_embedding);
+            let chunk = embedded.chunk;
+            SearchResult {
+                id: idx as u32,
+                simhash: simhash(&chunk.content),
+                tokens: count_tokens(&chunk.content),
+                content: chunk.content,
+                path: chunk.path,
+                start_line: chunk.start_line,
+                end_line: chunk.end_line,
+                kind: format!("{:?}", chunk.kind),
+                signature: chunk.signature,
+                docstring: chunk.docstring,
+                context: (!chunk.context.is_empty()).then(|| chunk.context.join(" > ")),
+                hash: chunk.hash,
+                distance: 1.0 - score,
+                score,
+                context_prev: chunk.context_prev,
+                context_next: chunk.context_next,
+                tags: chunk.tags,
+                package: chunk.package,
+                ..Default::default()
+            }
+        })
+        .collect();
+    results.sort_by(|a, b| b.score.total_cmp(&a.score));
+    results
+}
+
+/// Walk, chunk and embed `path` in memory and search it
+#[allow(clippy::too_many_arguments)]
+pub async fn ephemeral_search(
+    query: &str,
+    max_results: usize,
+    content: bool,
+    scores: bool,
+    format: OutputFormat,
+    path: Option<PathBuf>,
+    filter_path: Option<String>,
+    tags: Vec<String>,
+    not_tags: Vec<String>,
+    packages: Vec<String>,
+    model_type: ModelType,
+    collapse_duplicates: bool,
+    budget_tokens: usize,
+    max_total_tokens: Option<usize>,
+    config: &Config,
+) -> Result<()> {
+    if format == OutputFormat::Sarif {
+        bail!("--format sarif needs an index");
+    }
+    let start = Instant::now();
+    let mut parsed = ParsedQuery::parse(query)?;
+    parsed.filters.under = filter_path;
+    parsed.filters.tags = tags;
+    parsed.filters.not_tags = not_tags;
+    parsed.filters.packages = packages;
+
+    let root = path.unwrap_or_else(|| PathBuf::from("."));
+    let walker = FileWalker::new(root.clone()).with_excludes(&config.indexing.excludes);
+    let (files, _stats) = walker.walk()?;
+    let mut chunker = SemanticChunker::from_config(&config.indexing).with_project_root(&root);
+    let mut chunks = Vec::new();
+    for file in &files {
+        let Ok(source) = std::fs::read_to_string(&file.path) else {
+            continue;
+        };
+        for chunk in chunker.chunk_semantic(file.language, &file.path, &source)? {
+            let kind = format!("{:?}", chunk.kind);
+            if parsed.filters.matches(&chunk.path, &kind, &chunk.tags, chunk.package.as_deref()) {
+                chunks.push(chunk);
+            }
+        }
+        if chunks.len() > MAX_EPHEMERAL_CHUNKS {
+            bail!(
+                "{} has more than {} chunks, too many to embed on every search. Index it with `demongrep index {}`",
+                root.display(),
+                MAX_EPHEMERAL_CHUNKS,
+                root.display()
+            );
+        }
+    }
+    let chunk_count = chunks.len();
+
+    let mut embedding_service = EmbeddingService::with_model(model_type)?;
+    let query_embedding = embedding_service.embed_query(&parsed.text)?;
+    let mut results = rank_chunks(embedding_service.embed_chunks(chunks)?, &query_embedding);
+
+    apply_kind_weights(&mut results, &parsed.text, config.search.imports_weight, config.search.module_docs_boost);
+    merge::merge_split_parts(&mut results);
+    if collapse_duplicates {
+        duplicates::collapse_near_duplicates(&mut results);
+    }
+    results.truncate(max_results);
+    if let Some(max_total_tokens) = max_total_tokens {
+        cap_total_tokens(&mut results, max_total_tokens);
+    }
+
+    match format {
+        OutputFormat::Json => {
+            let output = JsonOutput {
+                query: query.to_string(),
+                results: results.iter().map(|r| JsonResult::new(r, None)).collect(),
+                timing: None,
+            };
+            println!("{}", serde_json::to_string(&output)?);
+        }
+        OutputFormat::Jsonl => write_jsonl(&results, &[], |_| None)?,
+        OutputFormat::Markdown => print!("{}", markdown_results(query, &results, &[])),
+        OutputFormat::Context => print_context(query, results, budget_tokens)?,
+        OutputFormat::Text | OutputFormat::Sarif => {
+            println!(
+                "{}",
+                format!(
+                    "🔍 Searched {} chunks from {} files in memory (not indexed) in {:?}",
+                    chunk_count,
+                    files.len(),
+                    start.elapsed()
+                )
+                .dimmed()
+            );
+            if results.is_empty() {
+                println!("{}", "No matches found.".dimmed());
+            }
+            let content_lines = content.then_some(CONTENT_LINES);
+            for result in &results {
+                print_result(result, true, content_lines, scores, None, None)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunker::{Chunk, ChunkKind};
+
+    #[test]
+    fn test_rank_chunks() {
+        let embedded = |name: &str, embedding: Vec<f32>| {
+            let mut chunk = Chunk::new(format!("fn {}() {{}}", name), 0, 1, ChunkKind::Function, "a.rs".to_string());
+            chunk.context = vec!["File: a.rs".to_string(), format!("Function: {}", name)];
+            EmbeddedChunk::new(chunk, embedding)
+        };
+        let results = rank_chunks(vec![embedded("far", vec![0.0, 1.0]), embedded("near", vec![1.0, 0.1])], &[1.0, 0.0]);
+
+        assert_eq!(results[0].content, "fn near() {}");
+        assert_eq!(results[0].kind, "Function");
+        assert_eq!(results[0].context.as_deref(), Some("File: a.rs > Function: near"));
+        assert!(results[0].score > 0.99 && results[1].score.abs() < 1e-6);
+    }
+}
@@ -0,0 +1,99 @@
+//! Whole-symbol expansion of search results (`search --expand`)
+//!
+//! Chunks are cut at `max_chunk_lines`, so a hit in a long function often
+//! shows its middle without the signature or the end the user needs. With
+//! `--expand`, each result's file is read again and parsed, and the result
+//! grows to the smallest function, class or other definition enclosing its
+//! stored lines. Results outside any definition, in languages without a
+//! grammar, or in files that can't be read are shown as indexed.
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use crate::chunker::CodeParser;
+use crate::embed::count_tokens;
+use crate::file::Language;
+use crate::vectordb::SearchResult;
+
+/// Where a result's file is: as stored, or relative to the searched project
+pub(super) fn locate(path: &str, project: Option<&Path>) -> Option<PathBuf> {
+    let stored = PathBuf::from(path);
+    if stored.is_file() {
+        return Some(stored);
+    }
+    project.map(|project| project.join(path)).filter(|joined| joined.is_file())
+}
+
+/// Grow a result to its enclosing definition, returning whether one was found
+pub fn expand_result(result: &mut SearchResult, parser: &mut CodeParser, project: Option<&Path>) -> bool {
+    let Some(file) = locate(&result.path, project) else {
+        return false;
+    };
+    let Ok(source) = std::fs::read_to_string(&file) else {
+        return false;
+    };
+    let Ok(parsed) = parser.parse(Language::from_path(&file), &source) else {
+        return false;
+    };
+    let Some((start_line, end_line)) = parsed.enclosing_definition(result.start_line, result.end_line) else {
+        return false;
+    };
+
+    let lines: Vec<&str> = source.lines().collect();
+    result.content = lines[start_line..end_line.min(lines.len())].join("\n");
+    result.tokens = count_tokens(&result.content);
+    if (start_line, end_line) != (result.start_line, result.end_line) {
+        // The stored neighbours no longer border the result
+        result.context_prev = None;
+        result.context_next = None;
+        result.start_line = start_line;
+        result.end_line = end_line;
+    }
+    true
+}
+
+/// Expand every result, dropping any that expand to a symbol an earlier result already shows
+pub fn expand_results(results: &mut Vec<SearchResult>, project: Option<&Path>) {
+    let mut parser = CodeParser::new();
+    for result in results.iter_mut() {
+        expand_result(result, &mut parser, project);
+    }
+
+    let mut seen = HashSet::new();
+    results.retain(|result| seen.insert((result.path.clone(), result.start_line, result.end_line)));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_expand_results() {
+        let dir = tempdir().unwrap();
+        let source = "fn long() {\n    let a = 1;\n    let b = 2;\n    let c = 3;\n}\n\nfn short() {}\n";
+        std::fs::write(dir.path().join("lib.rs"), source).unwrap();
+
+        let part = |start_line, end_line| SearchResult {
+            path: "lib.rs".to_string(),
+            start_line,
+            end_line,
+            content: "// [Part 2/2] fn long()\n    let c = 3;\n}".to_string(),
+            ..Default::default()
+        };
+        // Two parts of the same function expand to it once
+        let mut results = vec![part(2, 5), part(1, 3), part(6, 7)];
+        expand_results(&mut results, Some(dir.path()));
+
+        assert_eq!(results.len(), 2);
+        assert_eq!((results[0].start_line, results[0].end_line), (0, 5));
+        assert_eq!(results[0].content, "fn long() {\n    let a = 1;\n    let b = 2;\n    let c = 3;\n}");
+        assert_eq!((results[1].start_line, results[1].end_line), (6, 7));
+        assert_eq!(results[1].content, "fn short() {}");
+
+        // Not on disk: left as indexed
+        let mut missing = vec![SearchResult { path: "gone.rs".to_string(), ..part(2, 5) }];
+        expand_results(&mut missing, Some(dir.path()));
+        assert_eq!(missing[0].start_line, 2);
+    }
+}
@@ -0,0 +1,193 @@
+//! Score explanations (`search --explain`)
+//!
+//! A result's score passes through several stages: vector and full-text
+//! retrieval, rank fusion, kind and project multipliers and, with
+//! `--rerank`, the cross-encoder. `--explain` keeps what each stage
+//! contributed so a surprising ranking can be traced back to its cause.
+
+use serde::Serialize;
+use std::collections::HashMap;
+
+use crate::rerank::{Boost, Fusion, FusedResult};
+use crate::vectordb::SearchResult;
+
+/// What one retriever's ranking added to the fused score
+#[derive(Debug, Clone, Serialize)]
+pub struct RrfContribution {
+    /// The RRF constant: each ranking adds 1 / (k + rank)
+    pub k: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vector: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fts: Option<f32>,
+    pub total: f32,
+}
+
+/// The weighted blend of normalized retriever scores (`--fusion weighted`)
+#[derive(Debug, Clone, Serialize)]
+pub struct BlendContribution {
+    /// Weight of the normalized vector score; the text score gets 1 - alpha
+    pub alpha: f32,
+    pub total: f32,
+}
+
+/// How a result was scored
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Explanation {
+    /// Position among the vector hits (1-based)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vector_rank: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vector_distance: Option<f32>,
+    /// Position among the full-text hits (1-based)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fts_rank: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bm25: Option<f32>,
+    /// Rank fusion (hybrid search only)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rrf: Option<RrfContribution>,
+    /// Weighted fusion (hybrid search with `--fusion weighted` only)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub blend: Option<BlendContribution>,
+    /// Multipliers for the result's kind, location and age
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub boosts: Vec<Boost>,
+    /// Cross-encoder relevance in [0, 1], blended with the fused score
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rerank_score: Option<f32>,
+}
+
+/// Explanations by result, keyed by path and chunk ID
+///
+/// The key survives deduplication and merging of split parts, which keep
+/// the path and the ID of the best-ranked chunk.
+pub type Explanations = HashMap<(String, u32), Explanation>;
+
+pub fn key(result: &SearchResult) -> (String, u32) {
+    (result.path.clone(), result.id)
+}
+
+/// What retrieval and fusion contributed to a resolved result
+///
+/// `fusion` is None when the score isn't fused (vector-only or lexical search).
+pub fn retrieval(fused: &FusedResult, result: &SearchResult, fusion: Option<Fusion>) -> Explanation {
+    let contribution = |rank: Option<usize>, k: f32| rank.map(|rank| 1.0 / (k + rank as f32));
+    Explanation {
+        vector_rank: fused.vector_rank,
+        vector_distance: fused.vector_rank.map(|_| result.distance),
+        fts_rank: fused.fts_rank,
+        bm25: fused.fts_score,
+        rrf: match fusion {
+            Some(Fusion::Rrf { k }) => Some(RrfContribution {
+                k,
+                vector: contribution(fused.vector_rank, k),
+                fts: contribution(fused.fts_rank, k),
+                total: fused.rrf_score,
+            }),
+            _ => None,
+        },
+        blend: match fusion {
+            Some(Fusion::Weighted { alpha }) => Some(BlendContribution { alpha, total: fused.rrf_score }),
+            _ => None,
+        },
+        ..Default::default()
+    }
+}
+
+/// Terminal lines for an explanation
+pub fn describe(explanation: &Explanation) -> Vec<String> {
+    let mut lines = Vec::new();
+
+    let mut sources = Vec::new();
+    if let Some(rank) = explanation.vector_rank {
+        match explanation.vector_distance {
+            Some(distance) => sources.push(format!("vector #{} (distance {:.3})", rank, distance)),
+            None => sources.push(format!("vector #{}", rank)),
+        }
+    }
+    if let Some(rank) = explanation.fts_rank {
+        match explanation.bm25 {
+            Some(bm25) => sources.push(format!("text #{} (BM25 {:.2})", rank, bm25)),
+            None => sources.push(format!("text #{}", rank)),
+        }
+    }
+    if !sources.is_empty() {
+        lines.push(format!("Matched: {}", sources.join(" · ")));
+    }
+
+    if let Some(rrf) = &explanation.rrf {
+        let parts: Vec<String> = [("vector", rrf.vector), ("text", rrf.fts)]
+            .iter()
+            .filter_map(|(name, value)| value.map(|value| format!("{} {:.4}", name, value)))
+            .collect();
+        lines.push(format!("RRF:     {:.4} = {} (k = {})", rrf.total, parts.join(" + "), rrf.k));
+    }
+
+    if let Some(blend) = &explanation.blend {
+        lines.push(format!(
+            "Blend:   {:.4} = {:.2} × vector + {:.2} × text (normalized)",
+            blend.total,
+            blend.alpha,
+            1.0 - blend.alpha
+        ));
+    }
+
+    if !explanation.boosts.is_empty() {
+        let boosts: Vec<String> = explanation
+            .boosts
+            .iter()
+            .map(|boost| format!("×{:.2} {}", boost.factor, boost.reason))
+            .collect();
+        lines.push(format!("Boosts:  {}", boosts.join(" · ")));
+    }
+
+    if let Some(score) = explanation.rerank_score {
+        lines.push(format!("Rerank:  {:.3}", score));
+    }
+
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_explain_hybrid_result() {
+        let fused = FusedResult {
+            chunk_id: 7,
+            rrf_score: 1.0 / 23.0 + 1.0 / 21.0,
+            vector_score: Some(0.8),
+            fts_score: Some(7.25),
+            vector_rank: Some(3),
+            fts_rank: Some(1),
+        };
+        let result = SearchResult { id: 7, path: "src/lib.rs".to_string(), distance: 0.4, ..Default::default() };
+
+        let mut explanation = retrieval(&fused, &result, Some(Fusion::Rrf { k: 20.0 }));
+        let rrf = explanation.rrf.as_ref().unwrap();
+        assert!((rrf.vector.unwrap() - 1.0 / 23.0).abs() < 1e-6);
+        assert!((rrf.fts.unwrap() - 1.0 / 21.0).abs() < 1e-6);
+
+        explanation.boosts.push(Boost::new("imports", 0.5));
+        assert_eq!(
+            describe(&explanation),
+            [
+                "Matched: vector #3 (distance 0.400) · text #1 (BM25 7.25)",
+                "RRF:     0.0911 = vector 0.0435 + text 0.0476 (k = 20)",
+                "Boosts:  ×0.50 imports",
+            ]
+        );
+
+        let explanation = retrieval(&fused, &result, Some(Fusion::Weighted { alpha: 0.75 }));
+        assert!(explanation.rrf.is_none());
+        assert_eq!(describe(&explanation)[1], "Blend:   0.0911 = 0.75 × vector + 0.25 × text (normalized)");
+
+        // Vector-only search has no fusion and no text match
+        let fused = FusedResult { fts_score: None, fts_rank: None, ..fused };
+        let explanation = retrieval(&fused, &result, None);
+        assert!(explanation.rrf.is_none());
+        assert_eq!(describe(&explanation), ["Matched: vector #3 (distance 0.400)"]);
+    }
+}
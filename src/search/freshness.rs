@@ -0,0 +1,149 @@
+//! Stale result detection (`search --live`)
+//!
+//! In a watched project the index trails the editor by a debounce at best,
+//! so a result may show code that has since changed or moved. Before results
+//! are printed, each file is hashed again and compared with the hash stored
+//! when it was indexed; results from changed files are flagged. With
+//! `--live`, flagged results show the file as it is now: a chunk found
+//! verbatim elsewhere in the file follows it there and is fresh again, any
+//! other keeps its lines, re-read from disk, and stays flagged because it was
+//! ranked on its old content.
+
+use anyhow::Result;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use super::expand::locate;
+use crate::embed::count_tokens;
+use crate::vectordb::{SearchResult, VectorStore};
+
+/// Whether the file behind each result changed since it was indexed, by stored path
+fn changed_files(
+    results: &[SearchResult],
+    origins: &HashMap<(u32, String), PathBuf>,
+    dimensions: usize,
+    project: Option<&Path>,
+) -> Result<HashMap<String, bool>> {
+    let mut stores: HashMap<PathBuf, Option<VectorStore>> = HashMap::new();
+    let mut changed = HashMap::new();
+    for result in results {
+        if changed.contains_key(&result.path) {
+            continue;
+        }
+        let Some(db_path) = origins.get(&(result.id, result.path.clone())) else {
+            continue;
+        };
+        let store = stores.entry(db_path.clone()).or_insert_with(|| VectorStore::new(db_path, dimensions).ok());
+        let Some(store) = store else {
+            continue;
+        };
+        // Stored paths differ from shown ones with --all-projects
+        let Some(stored_path) = store.get_chunk(result.id)?.map(|meta| meta.path) else {
+            continue;
+        };
+        let Some(indexed_hash) = store.indexed_file_hash(&stored_path)? else {
+            continue;
+        };
+        let current_hash = locate(&result.path, project).and_then(|file| VectorStore::compute_file_hash(&file).ok());
+        changed.insert(result.path.clone(), current_hash.as_deref() != Some(indexed_hash.as_str()));
+    }
+    Ok(changed)
+}
+
+/// Show a stale result's file as it is now, returning whether it is fresh again
+fn refresh(result: &mut SearchResult, source: &str) -> bool {
+    let lines: Vec<&str> = source.lines().collect();
+    // Split parts start with a "// [Part 2/3] fn name()" header that isn't in the file
+    let stored: Vec<&str> =
+        result.content.lines().skip_while(|line| line.trim_start().starts_with("// [Part ")).collect();
+    if !stored.is_empty() {
+        if let Some(start) = lines.windows(stored.len()).position(|window| window == stored.as_slice()) {
+            result.start_line = start;
+            result.end_line = start + stored.len();
+            return true;
+        }
+    }
+
+    let end = result.end_line.min(lines.len());
+    let start = result.start_line.min(end);
+    result.content = lines[start..end].join("\n");
+    result.tokens = count_tokens(&result.content);
+    result.context_prev = None;
+    result.context_next = None;
+    false
+}
+
+/// Flag results whose files changed since indexing, refreshing them with `live`
+///
+/// Returns the number of results left stale.
+pub fn check_freshness(
+    results: &mut [SearchResult],
+    origins: &HashMap<(u32, String), PathBuf>,
+    dimensions: usize,
+    project: Option<&Path>,
+    live: bool,
+) -> Result<usize> {
+    let changed = changed_files(results, origins, dimensions, project)?;
+    let mut stale = 0;
+    for result in results.iter_mut() {
+        if !changed.get(&result.path).copied().unwrap_or(false) {
+            continue;
+        }
+        result.stale = true;
+        if live {
+            let source = locate(&result.path, project).and_then(|file| std::fs::read_to_string(file).ok());
+            if let Some(source) = source {
+                result.stale = !refresh(result, &source);
+            }
+        }
+        stale += usize::from(result.stale);
+    }
+    Ok(stale)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunker::{Chunk, ChunkKind};
+    use crate::embed::EmbeddedChunk;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_check_freshness() {
+        let dir = tempdir().unwrap();
+        let file = dir.path().join("lib.rs");
+        let db_path = dir.path().join("test.db");
+        let path = file.to_string_lossy().to_string();
+        std::fs::write(&file, "fn a() {}\nfn b() {}\nfn c() {}\n").unwrap();
+
+        let mut store = VectorStore::new(&db_path, 2).unwrap();
+        let chunks = ["fn a() {}", "fn b() {}", "fn c() {}"]
+            .iter()
+            .enumerate()
+            .map(|(line, content)| {
+                let chunk = Chunk::new(content.to_string(), line, line + 1, ChunkKind::Function, path.clone());
+                EmbeddedChunk::new(chunk, vec![1.0, 0.0])
+            })
+            .collect();
+        let ids = store.insert_chunks_with_ids(chunks).unwrap();
+        store.update_file_metadata(&file, ids.clone()).unwrap();
+        let indexed: Vec<SearchResult> = ids.iter().map(|&id| store.get_chunk_as_result(id).unwrap().unwrap()).collect();
+        drop(store);
+        let origins: HashMap<(u32, String), PathBuf> = ids.iter().map(|&id| ((id, path.clone()), db_path.clone())).collect();
+
+        let mut fresh = indexed.clone();
+        assert_eq!(check_freshness(&mut fresh, &origins, 2, None, false).unwrap(), 0);
+        assert!(fresh.iter().all(|r| !r.stale));
+
+        // b moved down a line, c was edited
+        std::fs::write(&file, "fn a() {}\n\nfn b() {}\nfn c(x: u8) {}\n").unwrap();
+        let mut flagged = indexed.clone();
+        assert_eq!(check_freshness(&mut flagged, &origins, 2, None, false).unwrap(), 3);
+        assert_eq!(flagged[2].content, "fn c() {}");
+
+        let mut live = indexed;
+        assert_eq!(check_freshness(&mut live, &origins, 2, None, true).unwrap(), 1);
+        assert_eq!((live[1].start_line, live[1].stale), (2, false));
+        assert_eq!((live[2].content.as_str(), live[2].stale), ("fn b() {}", true));
+    }
+}
@@ -0,0 +1,84 @@
+//! Syntax highlighting for code shown in the terminal
+//!
+//! Uses syntect's bundled grammars and themes, loaded once on first use.
+//! Highlighting follows the same switch as the rest of the colored output,
+//! so `--no-color`, `NO_COLOR` and `CLICOLOR=0` turn it off.
+
+use std::path::Path;
+use std::sync::OnceLock;
+use syntect::easy::HighlightLines;
+use syntect::highlighting::{Theme, ThemeSet};
+use syntect::parsing::{SyntaxReference, SyntaxSet};
+use syntect::util::{as_24_bit_terminal_escaped, LinesWithEndings};
+
+/// Bundled theme used for code
+const THEME: &str = "base16-ocean.dark";
+
+struct Highlighter {
+    syntaxes: SyntaxSet,
+    theme: Theme,
+}
+
+static HIGHLIGHTER: OnceLock<Highlighter> = OnceLock::new();
+
+fn highlighter() -> &'static Highlighter {
+    HIGHLIGHTER.get_or_init(|| {
+        let mut themes = ThemeSet::load_defaults();
+        Highlighter {
+            syntaxes: SyntaxSet::load_defaults_newlines(),
+            theme: themes.themes.remove(THEME).unwrap_or_default(),
+        }
+    })
+}
+
+/// Grammar for a file, by extension
+///
+/// The bundled set has no TypeScript grammar; JavaScript's is close enough.
+fn syntax_for<'a>(syntaxes: &'a SyntaxSet, path: &str) -> Option<&'a SyntaxReference> {
+    let extension = Path::new(path).extension()?.to_str()?;
+    let extension = match extension {
+        "ts" | "mts" | "cts" | "tsx" => "js",
+        other => other,
+    };
+    syntaxes.find_syntax_by_extension(extension)
+}
+
+/// `code` as terminal-colored lines, or None when color is off or the language is unknown
+pub fn highlight_lines(code: &str, path: &str) -> Option<Vec<String>> {
+    if !colored::control::SHOULD_COLORIZE.should_colorize() {
+        return None;
+    }
+
+    let highlighter = highlighter();
+    let syntax = syntax_for(&highlighter.syntaxes, path)?;
+    let mut lines = HighlightLines::new(syntax, &highlighter.theme);
+
+    let mut highlighted = Vec::new();
+    for line in LinesWithEndings::from(code) {
+        let ranges = lines.highlight_line(line, &highlighter.syntaxes).ok()?;
+        let escaped = as_24_bit_terminal_escaped(&ranges, false);
+        highlighted.push(format!("{}\x1b[0m", escaped.trim_end_matches(['\n', '\r'])));
+    }
+    Some(highlighted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_highlight_lines() {
+        colored::control::set_override(true);
+        let lines = highlight_lines("fn main() {\n    let x = 1;\n}\n", "src/main.rs").unwrap();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].contains("\x1b[38;2;"));
+        assert!(lines.iter().all(|l| !l.contains('\n')));
+
+        // Unknown languages are left to the plain output
+        assert!(highlight_lines("data", "notes.unknownext").is_none());
+
+        colored::control::set_override(false);
+        assert!(highlight_lines("fn main() {}", "src/main.rs").is_none());
+        colored::control::unset_override();
+    }
+}
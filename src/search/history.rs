@@ -0,0 +1,145 @@
+//! Searching commit messages (`search --in history`)
+//!
+//! Ranks the commits embedded by `index --with-history` instead of code, for
+//! questions about why something changed rather than where it is. Each hit
+//! shows the commit's subject, author, date and the files it touched; the
+//! full message is shown with `--content`.
+
+use anyhow::{bail, Result};
+use colored::Colorize;
+use serde::Serialize;
+use std::path::PathBuf;
+use std::time::Instant;
+
+use super::OutputFormat;
+use crate::database::DatabaseManager;
+use crate::embed::EmbeddingService;
+use crate::vectordb::CommitRecord;
+
+/// Files listed under a commit in text output
+const SHOWN_FILES: usize = 5;
+
+/// A commit as printed by `--format json` and `jsonl`
+#[derive(Serialize)]
+struct HistoryResult<'a> {
+    hash: &'a str,
+    author: &'a str,
+    /// RFC 3339
+    date: String,
+    message: &'a str,
+    files: &'a [String],
+    score: f32,
+}
+
+impl<'a> HistoryResult<'a> {
+    fn new(commit: &'a CommitRecord, score: f32) -> Self {
+        Self {
+            hash: &commit.hash,
+            author: &commit.author,
+            date: commit_date(commit).to_rfc3339(),
+            message: &commit.message,
+            files: &commit.files,
+            score,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct HistoryOutput<'a> {
+    query: &'a str,
+    results: Vec<HistoryResult<'a>>,
+}
+
+fn commit_date(commit: &CommitRecord) -> chrono::DateTime<chrono::Utc> {
+    chrono::DateTime::from_timestamp(commit.timestamp as i64, 0).unwrap_or_default()
+}
+
+/// "src/a.rs, src/b.rs and 3 more"
+fn describe_files(files: &[String]) -> String {
+    let mut described = files.iter().take(SHOWN_FILES).cloned().collect::<Vec<_>>().join(", ");
+    if files.len() > SHOWN_FILES {
+        described.push_str(&format!(" and {} more", files.len() - SHOWN_FILES));
+    }
+    described
+}
+
+/// Rank the indexed commit messages by similarity to `query` and print the best
+pub async fn history_search(
+    query: &str,
+    max_results: usize,
+    content: bool,
+    scores: bool,
+    format: OutputFormat,
+    path: Option<PathBuf>,
+) -> Result<()> {
+    if !matches!(format, OutputFormat::Text | OutputFormat::Json | OutputFormat::Jsonl) {
+        bail!("--in history prints text, json or jsonl");
+    }
+    let start = Instant::now();
+    let db_manager = DatabaseManager::load(path)?;
+    let mut embedding_service = EmbeddingService::with_model(db_manager.model_type())?;
+    let query_embedding = embedding_service.embed_query(query)?;
+
+    let mut commits: Vec<(CommitRecord, f32)> = Vec::new();
+    let mut searched = 0;
+    for database in db_manager.databases() {
+        let store = database.store();
+        searched += store.commit_hashes()?.len();
+        commits.extend(store.rank_commits(&query_embedding, max_results)?);
+    }
+    if searched == 0 {
+        bail!("The index has no commit history. Add it with `demongrep index --with-history 500`");
+    }
+    commits.sort_by(|a, b| b.1.total_cmp(&a.1));
+    commits.truncate(max_results);
+
+    match format {
+        OutputFormat::Json => {
+            let output = HistoryOutput {
+                query,
+                results: commits.iter().map(|(commit, score)| HistoryResult::new(commit, *score)).collect(),
+            };
+            println!("{}", serde_json::to_string(&output)?);
+        }
+        OutputFormat::Jsonl => {
+            for (commit, score) in &commits {
+                println!("{}", serde_json::to_string(&HistoryResult::new(commit, *score))?);
+            }
+        }
+        _ => {
+            println!("{}", format!("📜 Searched {} commits in {:?}", searched, start.elapsed()).dimmed());
+            for (rank, (commit, score)) in commits.iter().enumerate() {
+                let subject = commit.message.lines().next().unwrap_or_default();
+                let short_hash = &commit.hash[..commit.hash.len().min(10)];
+                print!("\n{}. {} {}", rank + 1, short_hash.yellow(), subject.bold());
+                if scores {
+                    print!(" {}", format!("({:.3})", score).dimmed());
+                }
+                println!();
+                println!("   {} • {}", commit.author, commit_date(commit).format("%Y-%m-%d"));
+                if !commit.files.is_empty() {
+                    println!("   {}", describe_files(&commit.files).cyan());
+                }
+                if content {
+                    let body = commit.message.lines().skip(1).skip_while(|line| line.trim().is_empty());
+                    for line in body {
+                        println!("   {}", line.dimmed());
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_describe_files() {
+        let files: Vec<String> = (1..=7).map(|i| format!("src/{}.rs", i)).collect();
+        assert_eq!(describe_files(&files[..2]), "src/1.rs, src/2.rs");
+        assert_eq!(describe_files(&files), "src/1.rs, src/2.rs, src/3.rs, src/4.rs, src/5.rs and 2 more");
+    }
+}
@@ -0,0 +1,155 @@
+//! Following calls out of the results (`search --hops 2`)
+//!
+//! A function is rarely understood on its own: the helpers it calls say
+//! what it actually does. With `--hops 2`, the names called in the top
+//! results are looked up in the symbol index and their definitions are
+//! added after the results, each labeled with the call that led to it.
+//! `--hops 3` follows the calls in those definitions as well. Names are
+//! matched exactly, without type information, so a call to a common method
+//! name resolves to whichever definition of that name the index holds.
+
+use anyhow::Result;
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+
+use crate::chunker::Chunk;
+use crate::fts::{FtsFilter, FtsStore};
+use crate::vectordb::{SearchResult, VectorStore};
+
+/// Results of each hop whose calls are followed
+const SOURCES_PER_HOP: usize = 5;
+
+/// Calls followed per result, in order of appearance
+const CALLS_PER_RESULT: usize = 8;
+
+/// Most definitions all hops together add
+pub const MAX_REFERENCED: usize = 10;
+
+/// Words followed by `(` that aren't calls of a definition in the project
+const NOT_CALLS: &[&str] = &[
+    "if", "for", "while", "match", "return", "switch", "catch", "sizeof", "typeof", "fn", "def", "function", "func",
+    "new", "super", "self", "this", "and", "or", "not", "in", "await", "yield", "lambda", "with", "elif", "print",
+    "Some", "Ok", "Err", "Box", "Vec", "String",
+];
+
+/// Keywords after which a name followed by `(` is being defined, not called
+const DEFINERS: &[&str] = &["fn", "def", "function", "func", "class", "struct", "impl"];
+
+/// Names called in `content`, first occurrence first
+///
+/// A call is a name followed by `(`, optionally after spaces; the last
+/// segment of `a.b(` and `a::b(` is the name. Macros (`name!(`), keywords,
+/// definitions and one- or two-letter names are left out.
+pub fn called_names(content: &str) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    let chars: Vec<char> = content.chars().collect();
+    let mut previous_word = String::new();
+    let mut i = 0;
+    while i < chars.len() {
+        if !(chars[i].is_alphabetic() || chars[i] == '_') {
+            i += 1;
+            continue;
+        }
+        let start = i;
+        while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+            i += 1;
+        }
+        let word: String = chars[start..i].iter().collect();
+        let mut next = i;
+        while next < chars.len() && chars[next] == ' ' {
+            next += 1;
+        }
+        let is_call = chars.get(next) == Some(&'(')
+            && word.chars().count() > 2
+            && !NOT_CALLS.contains(&word.as_str())
+            && !DEFINERS.contains(&previous_word.as_str());
+        if is_call && !names.contains(&word) {
+            names.push(word.clone());
+        }
+        previous_word = word;
+    }
+    names
+}
+
+/// A database's stores, opened once for all lookups in it
+struct Database {
+    store: VectorStore,
+    fts_store: FtsStore,
+}
+
+/// Definitions of what the top `results` call, `hops - 1` levels deep
+///
+/// `database_of` gives the database a result came from; definitions are
+/// looked up in the same database. Results already shown are not repeated.
+pub fn follow_calls(
+    results: &[SearchResult],
+    database_of: impl Fn(&SearchResult) -> Option<PathBuf>,
+    hops: usize,
+    dimensions: usize,
+) -> Result<Vec<SearchResult>> {
+    let mut seen: HashSet<(String, usize)> = results.iter().map(|r| (r.path.clone(), r.start_line)).collect();
+    let mut databases: HashMap<PathBuf, Option<Database>> = HashMap::new();
+    let mut sources: Vec<(SearchResult, PathBuf)> = results
+        .iter()
+        .filter_map(|result| Some((result.clone(), database_of(result)?)))
+        .take(SOURCES_PER_HOP)
+        .collect();
+    let mut referenced = Vec::new();
+
+    for _ in 1..hops {
+        let mut next = Vec::new();
+        for (source, db_path) in &sources {
+            let database = databases.entry(db_path.clone()).or_insert_with(|| {
+                let store = VectorStore::new(db_path, dimensions).ok()?;
+                let fts_store = FtsStore::open_readonly(db_path).ok()?;
+                Some(Database { store, fts_store })
+            });
+            let Some(database) = database else {
+                continue;
+            };
+            for name in called_names(&source.content).into_iter().take(CALLS_PER_RESULT) {
+                if referenced.len() >= MAX_REFERENCED {
+                    return Ok(referenced);
+                }
+                let Some(hit) = database.fts_store.search_name(&name, &FtsFilter::default(), 1)?.into_iter().next()
+                else {
+                    continue;
+                };
+                let Some(mut definition) = database.store.get_chunk_as_result(hit.chunk_id)? else {
+                    continue;
+                };
+                // Case-insensitive matches are other symbols
+                if definition.context.as_deref().and_then(Chunk::name_from_context) != Some(name.as_str()) {
+                    continue;
+                }
+                if !seen.insert((definition.path.clone(), definition.start_line)) {
+                    continue;
+                }
+                definition.via = Some(format!("called as {} in {}:{}", name, source.path, source.start_line));
+                definition.database = source.database.clone();
+                next.push((definition.clone(), db_path.clone()));
+                referenced.push(definition);
+            }
+        }
+        sources = next;
+    }
+    Ok(referenced)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_called_names() {
+        let content = "pub fn refresh(token: &str) -> Result<Session> {\n\
+                       if expired(token) {\n\
+                       return Err(anyhow!(\"expired\"));\n\
+                       }\n\
+                       let claims = jwt::decode (token)?;\n\
+                       self.store.save_session(&claims).map(Session::from)\n\
+                       }";
+        assert_eq!(called_names(content), ["expired", "decode", "save_session", "map"]);
+        assert!(called_names("def handler(request):\n    return f(x)").is_empty());
+    }
+}
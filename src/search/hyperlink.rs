@@ -0,0 +1,83 @@
+//! Clickable result paths (OSC 8 terminal hyperlinks)
+//!
+//! Modern terminals (iTerm2, WezTerm, kitty, GNOME Terminal, Windows
+//! Terminal, ...) turn `ESC ] 8 ; ; URI ESC \ text ESC ] 8 ; ; ESC \` into a
+//! link; terminals without support print just the text. Links are only
+//! written to a color terminal, so piped and `--no-color` output stays plain.
+//! The URI is either a `file://` URL or an editor URI that also carries the
+//! line, like `vscode://file/path:line`.
+
+use std::path::Path;
+use std::sync::OnceLock;
+
+use crate::config::UriScheme;
+
+/// Scheme for result links, set once when links are enabled
+static SCHEME: OnceLock<UriScheme> = OnceLock::new();
+
+/// Link result paths with `scheme` if stdout is a color terminal
+///
+/// Must be called before the pager takes over stdout.
+pub fn enable_hyperlinks(scheme: UriScheme) {
+    use std::io::IsTerminal;
+
+    let dumb = std::env::var("TERM").is_ok_and(|term| term == "dumb");
+    let colored = colored::control::SHOULD_COLORIZE.should_colorize();
+    if scheme != UriScheme::None && !dumb && colored && std::io::stdout().is_terminal() {
+        let _ = SCHEME.set(scheme);
+    }
+}
+
+/// Percent-encode a path for use in a URI, keeping `/` and unreserved characters
+fn encode_path(path: &str) -> String {
+    let mut encoded = String::with_capacity(path.len());
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => encoded.push(byte as char),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+/// URI opening `path` (absolute) at `line` (1-based), or None for `UriScheme::None`
+pub fn uri(scheme: UriScheme, path: &Path, line: usize) -> Option<String> {
+    // Windows paths become /C:/... like in file URLs
+    let path = path.to_string_lossy().replace('\\', "/");
+    let path = if path.starts_with('/') { path } else { format!("/{}", path) };
+    match scheme {
+        UriScheme::File => Some(format!("file://{}", encode_path(&path))),
+        UriScheme::Vscode => Some(format!("vscode://file{}:{}", encode_path(&path), line)),
+        UriScheme::None => None,
+    }
+}
+
+/// Wrap `text` in a link to a result's file at `line` (0-based), if links are on
+///
+/// Files that no longer exist are not linked.
+pub fn link(text: &str, path: &str, line: usize) -> String {
+    let target = SCHEME
+        .get()
+        .and_then(|&scheme| Some((scheme, std::fs::canonicalize(path).ok()?)))
+        .and_then(|(scheme, file)| uri(scheme, &file, line + 1));
+    match target {
+        Some(uri) => format!("\x1b]8;;{}\x1b\\{}\x1b]8;;\x1b\\", uri, text),
+        None => text.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_uri() {
+        let path = Path::new("/home/me/my project/src/lib.rs");
+        assert_eq!(uri(UriScheme::File, path, 12).unwrap(), "file:///home/me/my%20project/src/lib.rs");
+        assert_eq!(uri(UriScheme::Vscode, path, 12).unwrap(), "vscode://file/home/me/my%20project/src/lib.rs:12");
+        assert_eq!(uri(UriScheme::None, path, 12), None);
+
+        // Links are off unless enabled on a terminal
+        assert_eq!(link("src/lib.rs", "src/lib.rs", 0), "src/lib.rs");
+    }
+}
@@ -0,0 +1,180 @@
+//! Best-matching lines within results
+//!
+//! A chunk can run to 70 lines, and the user wants the one that answers the
+//! query. Each line of a shown result is scored by how many of the query's
+//! words it contains, with identifiers split into words (`retryDelay`
+//! matches "retry delay"), and the best lines are marked with an arrow in
+//! text output and listed in `match_lines` in JSON. With
+//! `search.embed_match_lines`, the lines of the first few results are also
+//! embedded and scored against the query embedding, which finds lines that
+//! match by meaning rather than by words.
+
+use anyhow::Result;
+use std::collections::HashSet;
+
+use crate::chunker::{Chunk, ChunkKind};
+use crate::embed::EmbeddingService;
+use crate::vectordb::SearchResult;
+
+/// Most lines marked per result
+const MAX_MATCH_LINES: usize = 3;
+
+/// Results whose lines are embedded with `search.embed_match_lines`
+const EMBEDDED_RESULTS: usize = 3;
+
+/// Weight of one matched query word against line-to-query similarity (0-1)
+const TERM_WEIGHT: f32 = 0.2;
+
+/// Query words that say nothing about the code
+const STOP_WORDS: &[&str] = &[
+    "a", "an", "and", "are", "does", "for", "how", "in", "is", "of", "on", "or", "the", "to", "what", "where",
+    "which", "who", "why", "with",
+];
+
+/// Lowercase words of `text`, with identifiers split at `_` and at lower-to-upper case changes
+fn words(text: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    for token in text.split(|c: char| !c.is_alphanumeric()) {
+        let mut word = String::new();
+        let mut previous: Option<char> = None;
+        for c in token.chars() {
+            let boundary = previous.is_some_and(|p| p.is_lowercase() && c.is_uppercase());
+            if boundary && !word.is_empty() {
+                words.push(std::mem::take(&mut word).to_lowercase());
+            }
+            word.push(c);
+            previous = Some(c);
+        }
+        if !word.is_empty() {
+            words.push(word.to_lowercase());
+        }
+    }
+    words
+}
+
+/// Whether a line word matches a query word, allowing plurals and other endings
+fn matches(line_word: &str, query_word: &str) -> bool {
+    line_word == query_word
+        || (query_word.len() >= 4 && line_word.starts_with(query_word))
+        || (line_word.len() >= 4 && query_word.starts_with(line_word))
+}
+
+/// The query's distinct meaningful words
+fn query_words(query: &str) -> Vec<String> {
+    let mut seen = HashSet::new();
+    words(query)
+        .into_iter()
+        .filter(|word| word.len() > 1 && !STOP_WORDS.contains(&word.as_str()))
+        .filter(|word| seen.insert(word.clone()))
+        .collect()
+}
+
+/// Number of distinct query words a line contains
+fn term_score(line: &str, query_words: &[String]) -> usize {
+    let line_words = words(line);
+    query_words.iter().filter(|query_word| line_words.iter().any(|word| matches(word, query_word))).count()
+}
+
+/// Lines of a result's content as they are in the file, with their line numbers
+///
+/// Split parts begin with a "// [Part 2/3]" header that is not in the file.
+fn file_lines(result: &SearchResult) -> Vec<(usize, &str)> {
+    let mut lines = result.content.lines().peekable();
+    if lines.peek().is_some_and(|line| line.trim_start().starts_with("// [Part ")) {
+        lines.next();
+    }
+    lines.enumerate().map(|(offset, line)| (result.start_line + offset, line)).collect()
+}
+
+/// The best of `scored` lines: at most `MAX_MATCH_LINES`, all scoring as high as the best
+fn best_lines(mut scored: Vec<(usize, f32)>) -> Vec<usize> {
+    scored.retain(|(_, score)| *score > 0.0);
+    scored.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+    let Some(&(_, best)) = scored.first() else {
+        return Vec::new();
+    };
+    // Close seconds count; far weaker lines only add noise
+    let mut lines: Vec<usize> = scored
+        .into_iter()
+        .take_while(|(_, score)| *score >= best * 0.75)
+        .take(MAX_MATCH_LINES)
+        .map(|(line, _)| line)
+        .collect();
+    lines.sort_unstable();
+    lines
+}
+
+/// Mark the lines of each result that best match the query by its words
+pub fn mark_match_lines(results: &mut [SearchResult], query: &str) {
+    let query_words = query_words(query);
+    if query_words.is_empty() {
+        return;
+    }
+    for result in results.iter_mut() {
+        let scored = file_lines(result)
+            .into_iter()
+            .map(|(line, text)| (line, term_score(text, &query_words) as f32))
+            .collect();
+        result.match_lines = best_lines(scored);
+    }
+}
+
+/// Mark the best lines of the first `EMBEDDED_RESULTS` results by embedding each line
+///
+/// Line similarity to the query counts first; matched query words add to it.
+pub fn embed_match_lines(
+    results: &mut [SearchResult],
+    query: &str,
+    query_embedding: &[f32],
+    embedding_service: &mut EmbeddingService,
+) -> Result<()> {
+    let query_words = query_words(query);
+    for result in results.iter_mut().take(EMBEDDED_RESULTS) {
+        let lines: Vec<(usize, String)> = file_lines(result)
+            .into_iter()
+            .filter(|(_, text)| text.chars().filter(|c| c.is_alphanumeric()).count() >= 3)
+            .map(|(line, text)| (line, text.trim().to_string()))
+            .collect();
+        if lines.is_empty() {
+            continue;
+        }
+        let chunks = lines
+            .iter()
+            .map(|(line, text)| Chunk::new(text.clone(), *line, *line + 1, ChunkKind::Block, result.path.clone()))
+            .collect();
+        let embedded = embedding_service.embed_chunks(chunks)?;
+        let scored = lines
+            .iter()
+            .zip(&embedded)
+            .map(|((line, text), embedded)| {
+                let terms = term_score(text, &query_words) as f32;
+                (*line, embedded.similarity_to(query_embedding).max(0.0) + TERM_WEIGHT * terms)
+            })
+            .collect();
+        result.match_lines = best_lines(scored);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_words() {
+        assert_eq!(words("retryDelay = MAX_RETRIES * 2"), ["retry", "delay", "max", "retries", "2"]);
+        assert_eq!(query_words("How is the retry delay computed?"), ["retry", "delay", "computed"]);
+    }
+
+    #[test]
+    fn test_mark_match_lines() {
+        let content = "// [Part 2/2] fn connect()\n    let socket = open()?;\n    let delay = backoff.retry_delay(attempt);\n    sleep(delay);\n}";
+        let mut results = vec![SearchResult { content: content.to_string(), start_line: 40, end_line: 44, ..Default::default() }];
+        mark_match_lines(&mut results, "retry delay");
+        // The header line is not in the file: line 41 is `let delay = ...`
+        assert_eq!(results[0].match_lines, [41]);
+
+        mark_match_lines(&mut results, "unrelated words");
+        assert!(results[0].match_lines.is_empty());
+    }
+}
@@ -0,0 +1,172 @@
+//! Merging the parts of split definitions in search results
+//!
+//! The chunker splits an oversized definition into overlapping parts and
+//! marks each with a `// [Part i/n] <signature>` header. When several parts
+//! of one definition match a query they would take several result slots, so
+//! contiguous parts are merged into one result spanning their lines.
+
+use std::collections::HashMap;
+
+use crate::embed::count_tokens;
+use crate::vectordb::SearchResult;
+
+/// Part number and part count of a split chunk, plus its signature
+fn split_part(content: &str) -> Option<(usize, usize, &str)> {
+    let header = content.lines().next()?.strip_prefix("// [Part ")?;
+    let (numbers, signature) = header.split_once("] ")?;
+    let (index, total) = numbers.split_once('/')?;
+    Some((index.parse().ok()?, total.parse().ok()?, signature))
+}
+
+/// Content of a split chunk without its header line
+fn part_body(content: &str) -> &str {
+    content.split_once('\n').map(|(_, body)| body).unwrap_or("")
+}
+
+/// Merge a run of overlapping parts (sorted by start line) into the best-ranked one
+fn merge_run(results: &mut [SearchResult], run: &[usize], signature: &str, total: usize, removed: &mut [bool]) {
+    if run.len() < 2 {
+        return;
+    }
+
+    let mut lines: Vec<&str> = Vec::new();
+    let mut covered_to = results[run[0]].start_line;
+    let mut notes: Vec<String> = Vec::new();
+    for &i in run {
+        let part = &results[i];
+        // Parts overlap; skip the lines the previous parts already cover
+        let skip = covered_to.saturating_sub(part.start_line);
+        lines.extend(part_body(&part.content).lines().skip(skip));
+        covered_to = covered_to.max(part.end_line);
+        for note in &part.notes {
+            if !notes.contains(note) {
+                notes.push(note.clone());
+            }
+        }
+    }
+
+    let first = &results[run[0]];
+    let last = &results[run[run.len() - 1]];
+    let first_part = split_part(&first.content).map(|(index, _, _)| index).unwrap_or(1);
+    let last_part = split_part(&last.content).map(|(index, _, _)| index).unwrap_or(total);
+    let body = lines.join("\n");
+    let content = if first_part == 1 && last_part == total {
+        body
+    } else {
+        format!("// [Parts {}-{}/{}] {}\n{}", first_part, last_part, total, signature, body)
+    };
+    let start_line = first.start_line;
+    let docstring = first.docstring.clone();
+    let context_prev = first.context_prev.clone();
+    let context_next = last.context_next.clone();
+    let best = run.iter().map(|&i| results[i].score).fold(f32::MIN, f32::max);
+    let nearest = run.iter().map(|&i| results[i].distance).fold(f32::MAX, f32::min);
+
+    // The merged result takes the slot of the highest-ranked part
+    let slot = *run.iter().min().unwrap();
+    let merged = &mut results[slot];
+    merged.tokens = count_tokens(&content);
+    merged.content = content;
+    merged.start_line = start_line;
+    merged.end_line = covered_to;
+    merged.docstring = docstring;
+    merged.context_prev = context_prev;
+    merged.context_next = context_next;
+    merged.score = best;
+    merged.distance = nearest;
+    merged.notes = notes;
+
+    for &i in run {
+        removed[i] = i != slot;
+    }
+}
+
+/// Merge contiguous parts of the same split definition into one result
+///
+/// The merged result keeps the position and score of its best-ranked part
+/// and shows the full line range. Parts with a gap between them stay apart.
+pub fn merge_split_parts(results: &mut Vec<SearchResult>) {
+    let mut groups: HashMap<(String, String, usize), Vec<usize>> = HashMap::new();
+    for (i, result) in results.iter().enumerate() {
+        if let Some((_, total, signature)) = split_part(&result.content) {
+            groups
+                .entry((result.path.clone(), signature.to_string(), total))
+                .or_default()
+                .push(i);
+        }
+    }
+
+    let mut removed = vec![false; results.len()];
+    for ((_, signature, total), mut members) in groups {
+        if members.len() < 2 {
+            continue;
+        }
+        members.sort_by_key(|&i| results[i].start_line);
+
+        let mut run = vec![members[0]];
+        let mut run_end = results[members[0]].end_line;
+        for &i in &members[1..] {
+            if results[i].start_line <= run_end {
+                run.push(i);
+            } else {
+                merge_run(results, &run, &signature, total, &mut removed);
+                run = vec![i];
+            }
+            run_end = run_end.max(results[i].end_line);
+        }
+        merge_run(results, &run, &signature, total, &mut removed);
+    }
+
+    let mut index = 0;
+    results.retain(|_| {
+        let keep = !removed[index];
+        index += 1;
+        keep
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn part(index: usize, total: usize, start_line: usize, end_line: usize, score: f32) -> SearchResult {
+        let body: Vec<String> = (start_line..end_line).map(|line| format!("line {}", line)).collect();
+        SearchResult {
+            path: "src/big.rs".to_string(),
+            start_line,
+            end_line,
+            kind: "Function".to_string(),
+            content: format!("// [Part {}/{}] fn big()\n{}", index, total, body.join("\n")),
+            score,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_merge_split_parts() {
+        let other = SearchResult {
+            path: "src/other.rs".to_string(),
+            content: "fn other() {}".to_string(),
+            score: 0.9,
+            ..Default::default()
+        };
+        // Parts 1 and 2 overlap by a line; part 4 is not contiguous with them
+        let mut results = vec![other, part(2, 4, 13, 17, 0.7), part(4, 4, 25, 29, 0.6), part(1, 4, 10, 14, 0.5)];
+        merge_split_parts(&mut results);
+
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].path, "src/other.rs");
+        let merged = &results[1];
+        assert_eq!((merged.start_line, merged.end_line, merged.score), (10, 17, 0.7));
+        let expected: Vec<String> = (10..17).map(|line| format!("line {}", line)).collect();
+        assert_eq!(merged.content, format!("// [Parts 1-2/4] fn big()\n{}", expected.join("\n")));
+        assert_eq!(results[2].start_line, 25);
+
+        // Every part of the definition: shown whole, without a header
+        let mut results = vec![part(2, 2, 13, 17, 0.7), part(1, 2, 10, 14, 0.5)];
+        merge_split_parts(&mut results);
+        assert_eq!(results.len(), 1);
+        assert!(results[0].content.starts_with("line 10\n"));
+        assert!(results[0].content.ends_with("line 16"));
+    }
+}
@@ -1,17 +1,255 @@
-use anyhow::Result;
+use anyhow::{bail, Result};
 use colored::Colorize;
 use serde::Serialize;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::time::{Duration, Instant};
 
-use crate::cache::FileMetaStore;
 use crate::chunker::SemanticChunker;
+use crate::config::Config;
+use crate::database::DatabaseManager;
 use crate::embed::{EmbeddingService, ModelType};
-use crate::file::FileWalker;
 use crate::fts::FtsStore;
-use crate::index::get_search_db_paths;
-use crate::rerank::{rrf_fusion, vector_only, FusedResult, NeuralReranker};
-use crate::vectordb::VectorStore;
+use crate::index::{
+    apply_changes, check_staleness, get_all_project_db_paths, get_search_db_paths, pending_changes, record_fingerprint,
+    recover_update, WriteLock,
+};
+use crate::rerank::{
+    apply_kind_weights, apply_path_weights, apply_project_preference, fts_only, promote, rerank_results,
+    rerank_results_with_scores, recency_boost, score_boosts, vector_only, Fusion, FusedResult, NeuralReranker, PathWeights,
+};
+use crate::vectordb::{StoreOptions, VectorStore};
+
+mod ask;
+mod batch;
+mod classify;
+mod context;
+mod count;
+mod duplicates;
+mod ephemeral;
+mod expand;
+mod explain;
+mod freshness;
+mod highlight;
+mod history;
+mod hops;
+mod hyperlink;
+mod match_lines;
+mod merge;
+mod open;
+mod parents;
+mod refine;
+mod remote;
+mod sarif;
+mod stdin;
+mod syntax;
+mod transform;
+mod watch;
+mod within;
+
+pub use ask::ask;
+pub use batch::{batch_search, read_queries_file};
+pub use classify::{classify_query, Pipeline};
+pub use context::{cap_total_tokens, result_tokens, DEFAULT_BUDGET_TOKENS};
+pub use count::CountBy;
+pub use ephemeral::ephemeral_search;
+pub use history::history_search;
+pub use hyperlink::enable_hyperlinks;
+pub use refine::LastSearch;
+pub use remote::remote_search;
+pub use stdin::read_stdin_query;
+pub use syntax::{ParsedQuery, QueryFilters};
+pub use transform::transform_results;
+pub use watch::watch_search;
+pub use within::within;
+
+/// Smallest adaptive retrieval depth
+pub const MIN_RETRIEVAL_DEPTH: usize = 50;
+
+/// Largest adaptive retrieval depth (also the reference depth in `bench search`)
+pub const MAX_RETRIEVAL_DEPTH: usize = 1000;
+
+/// Adaptive depth fetches at least this many candidates per wanted result
+const DEPTH_PER_RESULT: usize = 4;
+
+/// Candidates each retriever fetches in hybrid/lexical mode
+///
+/// Scales with the number of results wanted (max_results, or rerank_top when
+/// reranking) and with the square root of the index size, so small indexes
+/// aren't over-fetched and large ones still give fusion enough overlap.
+pub fn adaptive_depth(wanted: usize, total_chunks: usize) -> usize {
+    let by_results = wanted * DEPTH_PER_RESULT;
+    let by_size = 2 * (total_chunks as f64).sqrt() as usize;
+
+    by_results
+        .max(by_size)
+        .clamp(MIN_RETRIEVAL_DEPTH, MAX_RETRIEVAL_DEPTH)
+        .min(total_chunks.max(wanted))
+}
+
+/// Fetch `depth` candidates per retriever from one database and fuse them per `pipeline`
+///
+/// Returns the vector hits alongside the fused ranking so callers can map
+/// chunk IDs back to results without another lookup. Without an FTS index,
+/// hybrid and lexical fall back to vector-only. Lexical search with an FTS
+/// index skips the vector search, so `query_embedding` may then be empty.
+/// `query` is the full-text query; both retrievers only return chunks that
+/// pass `filters`. When the query is a single name, definitions with that
+/// name come first, ahead of whatever fusion ranked highest.
+#[allow(clippy::too_many_arguments)]
+pub fn retrieve(
+    store: &VectorStore,
+    fts_store: Option<&FtsStore>,
+    query: &str,
+    query_embedding: &[f32],
+    pipeline: Pipeline,
+    depth: usize,
+    fusion: Fusion,
+    filters: &QueryFilters,
+) -> Result<(Vec<crate::vectordb::SearchResult>, Vec<FusedResult>)> {
+    let vector_results = if pipeline == Pipeline::Lexical && fts_store.is_some() {
+        Vec::new()
+    } else {
+        vector_search(store, query_embedding, depth, filters)?
+    };
+
+    let fts_filter = filters.fts_filter();
+    let fused_results = match (pipeline, fts_store) {
+        (Pipeline::Semantic, _) | (_, None) => vector_only(&vector_results),
+        (Pipeline::Lexical, Some(fts_store)) => fts_only(&fts_store.search_filtered(query, &fts_filter, depth)?),
+        (Pipeline::Hybrid, Some(fts_store)) => {
+            fusion.fuse(&vector_results, &fts_store.search_filtered(query, &fts_filter, depth)?)
+        }
+    };
+    let fused_results = match fts_store {
+        Some(fts_store) if pipeline != Pipeline::Semantic && is_symbol_name(query) => {
+            promote(fused_results, &fts_store.search_name(query, &fts_filter, depth)?)
+        }
+        _ => fused_results,
+    };
+
+    Ok((vector_results, fused_results))
+}
+
+/// The `limit` nearest chunks that pass `filters`
+///
+/// A refined search scores the earlier results exactly; there are few of
+/// them, and the approximate index would miss most of them.
+pub fn vector_search(
+    store: &VectorStore,
+    query_embedding: &[f32],
+    limit: usize,
+    filters: &QueryFilters,
+) -> Result<Vec<crate::vectordb::SearchResult>> {
+    if let Some(ref chunk_ids) = filters.chunk_ids {
+        let mut results = store.score_chunks(query_embedding, chunk_ids)?;
+        results.retain(|result| filters.matches(&result.path, &result.kind, &result.tags, result.package.as_deref()));
+        results.truncate(limit);
+        Ok(results)
+    } else if filters.is_empty() {
+        store.search(query_embedding, limit)
+    } else {
+        store.search_filtered(query_embedding, limit, &|path, kind, tags, package| {
+            filters.matches(path, kind, tags, package)
+        })
+    }
+}
+
+/// Whether a query is one word that could name a symbol (`parse_config`, `Pool`, `$emit`)
+fn is_symbol_name(query: &str) -> bool {
+    !query.is_empty() && query.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '$')
+}
+
+/// How `demongrep search` prints its results
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum OutputFormat {
+    /// Colored terminal output
+    Text,
+    /// JSON for agents (same as --json)
+    Json,
+    /// A Markdown document for PRs and LLM prompts
+    Markdown,
+    /// One JSON result per line, flushed as it is written
+    Jsonl,
+    /// A SARIF 2.1.0 log for code-scanning tools
+    Sarif,
+    /// One prompt-ready block of code, packed to --budget-tokens
+    Context,
+}
+
+/// What `demongrep search` searches
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum SearchScope {
+    /// Code chunks
+    Code,
+    /// Commit messages embedded with `index --with-history`
+    History,
+}
+
+/// Hybrid search over every loaded database, ranked the way `search` ranks
+///
+/// Fuses vector and full-text hits per database, then applies the kind
+/// weights, the project preference and, given a reranker, neural reranking
+/// of the top `rerank_top`. Returns every candidate in rank order (at least
+/// `wanted` when the index has them); `--filter-path`, the transform hook and
+/// truncation are left to the caller. `query_embedding` is the embedding of
+/// `query.text`.
+#[allow(clippy::too_many_arguments)]
+pub fn rank_results(
+    db_manager: &DatabaseManager,
+    query: &ParsedQuery,
+    query_embedding: &[f32],
+    wanted: usize,
+    reranker: Option<&mut NeuralReranker>,
+    rerank_top: usize,
+    prefer_project: bool,
+    config: &Config,
+) -> Result<Vec<crate::vectordb::SearchResult>> {
+    let candidates = if reranker.is_some() { rerank_top.max(wanted) } else { wanted };
+
+    let mut all_results = Vec::new();
+    for database in db_manager.databases() {
+        let store = database.store();
+        let depth = config
+            .search
+            .retrieval_depth
+            .unwrap_or_else(|| adaptive_depth(candidates, store.chunk_count().unwrap_or(0)));
+        let fts_store = FtsStore::open_readonly(&database.path).ok();
+        let (vector_results, fused) = retrieve(
+            store,
+            fts_store.as_ref(),
+            &query.fts,
+            query_embedding,
+            Pipeline::Hybrid,
+            depth,
+            config.search.hybrid_fusion(),
+            &query.filters,
+        )?;
+        let label = database.db_type.name().to_lowercase();
+        all_results.extend(
+            resolve_fused(store, &vector_results, &fused, candidates)
+                .into_iter()
+                .map(|result| crate::vectordb::SearchResult { database: Some(label.clone()), ..result }),
+        );
+    }
+
+    let mut results = dedup_results(all_results);
+    apply_path_weights(&mut results, &PathWeights::new(&config.search.path_weights));
+    apply_kind_weights(
+        &mut results,
+        &query.text,
+        config.search.imports_weight,
+        config.search.module_docs_boost,
+    );
+    if prefer_project {
+        apply_project_preference(&mut results);
+    }
+    if let Some(reranker) = reranker {
+        if !results.is_empty() {
+            results = rerank_results(reranker, &query.text, results)?;
+        }
+    }
+    Ok(results)
+}
 
 /// JSON output format for search results
 #[derive(Serialize)]
@@ -29,6 +267,8 @@ struct JsonResult {
     end_line: usize,
     kind: String,
     content: String,
+    /// LLM prompt tokens in `content`
+    tokens: usize,
     score: f32,
     #[serde(skip_serializing_if = "Option::is_none")]
     signature: Option<String>,
@@ -36,6 +276,65 @@ struct JsonResult {
     context_prev: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     context_next: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    project: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    notes: Vec<String>,
+    /// Tags from `indexing.tags`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tags: Vec<String>,
+    /// Package the file belongs to
+    #[serde(skip_serializing_if = "Option::is_none")]
+    package: Option<String>,
+    /// Other files with a near-duplicate of this result
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    also_in: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    explain: Option<explain::Explanation>,
+    /// The call that led to this definition (`--hops`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    via: Option<String>,
+    /// The file changed since the result was indexed
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    stale: bool,
+    /// Lines that best match the query
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    match_lines: Vec<usize>,
+    /// Database the result was found in: "local" or "global"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    database: Option<String>,
+}
+
+impl JsonResult {
+    fn new(r: &crate::vectordb::SearchResult, project: Option<String>) -> Self {
+        Self {
+            path: r.path.clone(),
+            start_line: r.start_line,
+            end_line: r.end_line,
+            kind: r.kind.clone(),
+            content: r.content.clone(),
+            tokens: context::result_tokens(r),
+            score: r.score,
+            signature: r.signature.clone(),
+            context_prev: r.context_prev.clone(),
+            context_next: r.context_next.clone(),
+            project,
+            notes: r.notes.clone(),
+            tags: r.tags.clone(),
+            package: r.package.clone(),
+            also_in: r.also_in.clone(),
+            explain: None,
+            via: r.via.clone(),
+            stale: r.stale,
+            match_lines: r.match_lines.clone(),
+            database: r.database.clone(),
+        }
+    }
+
+    fn with_explanation(mut self, explanation: Option<explain::Explanation>) -> Self {
+        self.explain = explanation;
+        self
+    }
 }
 
 #[derive(Serialize)]
@@ -45,6 +344,11 @@ struct JsonTiming {
     search_ms: u64,
     #[serde(skip_serializing_if = "Option::is_none")]
     rerank_ms: Option<u64>,
+    /// Query embedding cache lookups (absent when the cache is off)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    query_cache_hits: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    query_cache_misses: Option<usize>,
 }
 
 
@@ -62,7 +366,238 @@ fn read_metadata(db_path: &PathBuf) -> Option<(String, usize)> {
     None
 }
 
+/// Candidates each retriever fetches for one database
+///
+/// Vector-only search needs no fusion headroom; otherwise an explicit
+/// `--retrieval-depth` wins over the adaptive depth.
+fn depth_for(
+    pipeline: Pipeline,
+    retrieval_depth: Option<usize>,
+    max_results: usize,
+    wanted: usize,
+    total_chunks: usize,
+) -> usize {
+    if pipeline == Pipeline::Semantic {
+        max_results
+    } else if let Some(depth) = retrieval_depth {
+        depth.max(1)
+    } else {
+        adaptive_depth(wanted, total_chunks)
+    }
+}
+
+/// Turn the top `take` fused hits into full results carrying the fused score
+fn resolve_fused(
+    store: &VectorStore,
+    vector_results: &[crate::vectordb::SearchResult],
+    fused_results: &[FusedResult],
+    take: usize,
+) -> Vec<crate::vectordb::SearchResult> {
+    let chunk_id_to_result: std::collections::HashMap<u32, &crate::vectordb::SearchResult> =
+        vector_results.iter().map(|r| (r.id, r)).collect();
+
+    fused_results
+        .iter()
+        .take(take)
+        .filter_map(|fused| {
+            let mut result = match chunk_id_to_result.get(&fused.chunk_id) {
+                Some(result) => (*result).clone(),
+                None => store.get_chunk_as_result(fused.chunk_id).ok().flatten()?,
+            };
+            result.score = fused.rrf_score;
+            Some(result)
+        })
+        .collect()
+}
+
+/// Deduplicate results by (path, start_line, end_line), keeping the highest score
+fn dedup_results(all_results: Vec<crate::vectordb::SearchResult>) -> Vec<crate::vectordb::SearchResult> {
+    let mut seen: std::collections::HashMap<(String, usize, usize), usize> = std::collections::HashMap::new();
+    let mut results: Vec<crate::vectordb::SearchResult> = Vec::new();
+
+    for result in all_results {
+        let key = (result.path.clone(), result.start_line, result.end_line);
+        if let Some(&idx) = seen.get(&key) {
+            // Already have this result, keep the one with higher score
+            if result.score > results[idx].score {
+                results[idx] = result;
+            }
+        } else {
+            seen.insert(key, results.len());
+            results.push(result);
+        }
+    }
+
+    results
+}
+
+/// "local" or "global", as results are attributed in JSON and with --scores
+fn database_label(db_path: &Path) -> &'static str {
+    if crate::index::is_local_db(db_path) {
+        "local"
+    } else {
+        "global"
+    }
+}
+
+/// Whether `path` is the file `dir` or under the directory `dir` (`./` optional)
+///
+/// Whole components only, like the FTS path facet: `src/api` doesn't
+/// include `src/api_v2`.
+fn is_under(path: &str, dir: &str) -> bool {
+    let path = path.trim_start_matches("./");
+    let dir = dir.trim_start_matches("./").trim_end_matches('/');
+    dir.is_empty() || dir == "." || path == dir || path.strip_prefix(dir).is_some_and(|rest| rest.starts_with('/'))
+}
+
+/// Keep only results under `filter` (a directory or file, `./` optional)
+fn retain_under(results: &mut Vec<crate::vectordb::SearchResult>, filter: &str) {
+    results.retain(|r| is_under(&r.path, filter));
+}
+
+/// A database to search, plus the project it belongs to in --all-projects mode
+struct SearchTarget {
+    db_path: PathBuf,
+    project_root: Option<PathBuf>,
+}
+
+/// A fenced Markdown code block, tagged with the language of `path`
+///
+/// The fence is longer than any backtick run in the code, so code that
+/// itself contains fences can't close the block early.
+fn fenced_code(path: &str, code: &str) -> String {
+    let mut longest = 0;
+    let mut run = 0;
+    for c in code.chars() {
+        run = if c == '`' { run + 1 } else { 0 };
+        longest = longest.max(run);
+    }
+    let fence = "`".repeat(longest.max(2) + 1);
+    let lang = crate::file::Language::from_path(std::path::Path::new(path)).fence_tag();
+    format!("{}{}\n{}\n{}\n", fence, lang, code.trim_end(), fence)
+}
+
+/// Write one JSON object per result and line, flushing each line
+///
+/// A consumer that stops reading early (`| head -n 3`) ends the output
+/// quietly instead of with a broken pipe error.
+fn write_jsonl(
+    results: &[crate::vectordb::SearchResult],
+    projects: &[PathBuf],
+    explain: impl Fn(&crate::vectordb::SearchResult) -> Option<explain::Explanation>,
+) -> Result<()> {
+    use std::io::Write;
+
+    let mut stdout = std::io::stdout().lock();
+    for result in results {
+        let json = JsonResult::new(result, project_label(projects, &result.path)).with_explanation(explain(result));
+        let line = serde_json::to_string(&json)?;
+        match writeln!(stdout, "{}", line).and_then(|_| stdout.flush()) {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::BrokenPipe => return Ok(()),
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok(())
+}
+
+/// Results as a Markdown document: a header per result, then its code
+fn markdown_results(query: &str, results: &[crate::vectordb::SearchResult], projects: &[PathBuf]) -> String {
+    let mut out = format!("# Search results for \"{}\"\n\n", query);
+    if results.is_empty() {
+        out.push_str("No matches found.\n");
+        return out;
+    }
+
+    for result in results {
+        let project = project_label(projects, &result.path)
+            .map(|p| format!("[{}] ", p))
+            .unwrap_or_default();
+        out.push_str(&format!(
+            "## {}`{}:{}-{}`\n\n",
+            project, result.path, result.start_line, result.end_line
+        ));
+
+        let mut details = vec![result.kind.clone()];
+        if let Some(ref signature) = result.signature {
+            details.push(format!("`{}`", signature.replace('`', "'")));
+        }
+        details.push(format!("score {:.3}", result.score));
+        out.push_str(&details.join(" · "));
+        out.push_str("\n\n");
+
+        for note in &result.notes {
+            out.push_str(&format!("> 📝 {}\n\n", note));
+        }
+        if !result.also_in.is_empty() {
+            out.push_str(&format!("_{}_\n\n", duplicates::describe_also_in(&result.also_in, usize::MAX)));
+        }
+        if let Some(ref via) = result.via {
+            out.push_str(&format!("_🔗 {}_\n\n", via));
+        }
+        out.push_str(&fenced_code(&result.path, &result.content));
+        out.push('\n');
+    }
+    out
+}
+
+/// Print results as a context block for an LLM, with a summary on stderr
+fn print_context(query: &str, results: Vec<crate::vectordb::SearchResult>, budget_tokens: usize) -> Result<()> {
+    let total = results.len();
+    let (context, included) = context::pack_context(query, results, budget_tokens);
+    if included == 0 {
+        eprintln!("No matches fit in {} tokens. Try a larger --budget-tokens", budget_tokens);
+        return Ok(());
+    }
+    print!("{}", context);
+    eprintln!("{} of {} results, {} tokens", included, total, crate::embed::count_tokens(&context));
+    Ok(())
+}
+
+/// Make a result path absolute relative to its project root
+fn absolutize(project_root: &std::path::Path, path: &str) -> String {
+    let relative = std::path::Path::new(path);
+    if relative.is_absolute() {
+        return path.to_string();
+    }
+    project_root
+        .join(relative.strip_prefix("./").unwrap_or(relative))
+        .to_string_lossy()
+        .to_string()
+}
+
+/// Name of the project (longest matching root) a result path belongs to
+fn project_label(projects: &[PathBuf], path: &str) -> Option<String> {
+    projects
+        .iter()
+        .filter(|root| std::path::Path::new(path).starts_with(root))
+        .max_by_key(|root| root.as_os_str().len())
+        .map(|root| {
+            root.file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_else(|| root.display().to_string())
+        })
+}
+
 /// Search the codebase (searches both local and global databases)
+///
+/// With `all_projects`, every project registered in `~/.demongrep/projects.json`
+/// is searched instead, and result paths are made absolute. With `auto`, the
+/// query classifier picks lexical, hybrid or vector-only search. With
+/// `text_only`, only the full-text index is searched and the embedding model
+/// is never loaded. With `boost_recent`, results from recently modified files rank higher. With
+/// `collapse_duplicates`, copies of a result in other files are folded into
+/// it. With `expand`, each result grows to its whole enclosing function or
+/// class, re-read from disk. With `explain`, each result shows how its score
+/// came about. With `open`, the chosen result is opened in the editor instead
+/// of being printed (`Some(None)` asks which one). With `count`, only the
+/// number of results is printed, grouped when it names a `CountBy`. With `hops` above 1, the
+/// definitions of what the top results call are shown after them. With
+/// `coarse_to_fine`, chunks are only ranked within the `top_files` files
+/// whose summaries best match the query. With `return_parents`, each result
+/// is replaced by the impl block or class it belongs to. Results from files
+/// changed since indexing are flagged; with `live`, they show the file as it
+/// is now.
 #[allow(clippy::too_many_arguments)]
 pub async fn search(
     query: &str,
@@ -72,148 +607,373 @@ pub async fn search(
     scores: bool,
     compact: bool,
     sync: bool,
-    json: bool,
+    format: OutputFormat,
     path: Option<PathBuf>,
     filter_path: Option<String>,
+    tags: Vec<String>,
+    not_tags: Vec<String>,
+    packages: Vec<String>,
     model_override: Option<ModelType>,
     vector_only_mode: bool,
-    rrf_k: f32,
+    auto: bool,
+    text_only: bool,
+    verbose: bool,
+    retrieval_depth: Option<usize>,
+    fusion: Fusion,
     rerank: bool,
     rerank_top: usize,
+    prefer_project: bool,
+    all_projects: bool,
+    auto_sync: bool,
+    boost_recent: bool,
+    collapse_duplicates: bool,
+    expand: bool,
+    explain: bool,
+    open: Option<Option<usize>>,
+    count: Option<Option<CountBy>>,
+    refine: bool,
+    coarse_to_fine: bool,
+    top_files: usize,
+    return_parents: bool,
+    live: bool,
+    budget_tokens: usize,
+    max_total_tokens: Option<usize>,
+    hops: usize,
+    config: &Config,
 ) -> Result<()> {
-    // Get all database paths (local + global)
-    let db_paths = get_search_db_paths(path.clone())?;
+    // Only the results go to stdout in JSON and Markdown output
+    let machine_output = format != OutputFormat::Text;
+
+    // lang:, kind: and path: filters and "phrases" come out of the query text;
+    // --filter-path joins them so both retrievers spend their depth under it
+    let mut parsed = ParsedQuery::parse(query)?;
+    parsed.filters.under = filter_path.clone();
+    parsed.filters.tags = tags;
+    parsed.filters.not_tags = not_tags;
+    parsed.filters.packages = packages;
+    let full_query = query;
+    let (query, fts_query, filters) = (parsed.text.as_str(), parsed.fts.as_str(), &parsed.filters);
+
+    // Get all database paths (local + global, or every registered project)
+    let targets: Vec<SearchTarget> = if all_projects {
+        get_all_project_db_paths()?
+            .into_iter()
+            .map(|(project_root, db_path)| SearchTarget { db_path, project_root: Some(project_root) })
+            .collect()
+    } else {
+        get_search_db_paths(path.clone())?
+            .into_iter()
+            .map(|db_path| SearchTarget { db_path, project_root: None })
+            .collect()
+    };
     
-    if db_paths.is_empty() {
+    if targets.is_empty() {
         println!("{}", "❌ No database found!".red());
-        println!("   Run {} or {} first", 
-            "demongrep index".bright_cyan(),
-            "demongrep index --global".bright_cyan()
-        );
+        if all_projects {
+            println!("   No projects registered in ~/.demongrep/projects.json");
+            println!("   Run {} in a project to register it", "demongrep index --global".bright_cyan());
+        } else {
+            println!("   Run {} or {} first", 
+                "demongrep index".bright_cyan(),
+                "demongrep index --global".bright_cyan()
+            );
+            println!("   or search without an index: {}", "demongrep search --ephemeral".bright_cyan());
+        }
         return Ok(());
     }
     
     // Show which databases we're searching (unless in JSON mode)
-    if !json && db_paths.len() > 1 {
+    if !machine_output && targets.len() > 1 {
         println!("{}", "🔍 Searching in multiple databases...".dimmed());
-        for db_path in &db_paths {
-            let db_type = if db_path.ends_with(".demongrep.db") { "Local" } else { "Global" };
-            println!("   {} {}", db_type, db_path.display().to_string().dimmed());
+        for target in &targets {
+            let db_type = if crate::index::is_local_db(&target.db_path) { "Local" } else { "Global" };
+            match &target.project_root {
+                Some(root) => println!("   {} {} {}", db_type, root.display(), target.db_path.display().to_string().dimmed()),
+                None => println!("   {} {}", db_type, target.db_path.display().to_string().dimmed()),
+            }
         }
         println!();
     }
 
+    let projects: Vec<PathBuf> = targets.iter().filter_map(|t| t.project_root.clone()).collect();
+
+    // --refine searches only the chunks the last search showed
+    let previous = if refine { Some(LastSearch::load(&config.data_dir)?) } else { None };
+    if let Some(ref previous) = previous {
+        if targets.iter().all(|target| previous.chunk_ids(&target.db_path).is_empty()) {
+            bail!("The last search (\"{}\") found nothing in these databases to refine", previous.query);
+        }
+        if !machine_output {
+            println!(
+                "{}",
+                format!("🔎 Refining {} results of \"{}\"", previous.len(), previous.query).dimmed()
+            );
+        }
+    }
+    // Database of each candidate, to record where the shown results came from
+    let mut origins: std::collections::HashMap<(u32, String), PathBuf> = std::collections::HashMap::new();
+
+    let class = classify_query(query);
+    let pipeline = if text_only {
+        Pipeline::Lexical
+    } else if auto {
+        class.pipeline
+    } else if vector_only_mode {
+        Pipeline::Semantic
+    } else {
+        Pipeline::Hybrid
+    };
+    if verbose && !machine_output {
+        println!(
+            "{}",
+            format!(
+                "🧭 Query classified as {} ({}); using {} search",
+                class.pipeline.name(),
+                class.reason,
+                pipeline.name()
+            )
+            .dimmed()
+        );
+    }
+    if !auto && !text_only && !machine_output && class.pipeline == Pipeline::Lexical {
+        crate::warn_print!(
+            "{}",
+            format!(
+                "💡 \"{}\" looks like an exact identifier; keyword search usually finds it better. Try --auto or --text-only",
+                query
+            )
+            .yellow()
+        );
+    }
+
     // Collect all results from all databases
     let mut all_results: Vec<crate::vectordb::SearchResult> = Vec::new();
     let mut total_embed_duration = Duration::ZERO;
     let mut total_search_duration = Duration::ZERO;
     let mut total_load_duration = Duration::ZERO;
     let mut model_load_duration = Duration::ZERO;
+    let mut explanations = explain::Explanations::new();
+    let path_weights = PathWeights::new(&config.search.path_weights);
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
     
-    // We'll use the first database's model/dimensions, or override
-    let (model_type, dimensions) = if let Some(override_model) = model_override {
-        (override_model, override_model.dimensions())
-    } else if let Some((model_name, dims)) = read_metadata(&db_paths[0]) {
-        if let Some(mt) = ModelType::from_str(&model_name) {
-            (mt, dims)
+    // Resolve the model a database was indexed with, or the override
+    let resolve_model = |db_path: &PathBuf| -> (ModelType, usize) {
+        if let Some(override_model) = model_override {
+            (override_model, override_model.dimensions())
+        } else if let Some((model_name, dims)) = read_metadata(db_path) {
+            if let Some(mt) = ModelType::from_str(&model_name) {
+                (mt, dims)
+            } else {
+                eprintln!("{}", "⚠️  Unknown model in metadata, using default".yellow());
+                (ModelType::default(), 384)
+            }
         } else {
-            eprintln!("{}", "⚠️  Unknown model in metadata, using default".yellow());
             (ModelType::default(), 384)
         }
-    } else {
-        (ModelType::default(), 384)
     };
-    
-    // Initialize embedding service once (shared across all databases)
-    let start = Instant::now();
-    let mut embedding_service = EmbeddingService::with_model(model_type)?;
-    model_load_duration = start.elapsed();
-    
-    // Embed query once
-    let start = Instant::now();
-    let query_embedding = embedding_service.embed_query(query)?;
-    total_embed_duration = start.elapsed();
+
+    // We'll use the first database's model/dimensions for all local/global
+    // databases. Registered projects may differ, so each resolves its own.
+    let (default_model, default_dimensions) = resolve_model(&targets[0].db_path);
+
+    // Embed the query once per distinct model
+    let mut query_embeddings: std::collections::HashMap<ModelType, Vec<f32>> = std::collections::HashMap::new();
     
     // Search in each database
-    for db_path in db_paths {
+    for target in targets {
+        let db_path = target.db_path;
+        let (model_type, dimensions) = if all_projects {
+            resolve_model(&db_path)
+        } else {
+            (default_model, default_dimensions)
+        };
+
+        // Text-only search never needs the model
+        if !text_only && !query_embeddings.contains_key(&model_type) {
+            // A cached query embedding skips loading the model at all
+            let start = Instant::now();
+            let cache = crate::embed::query_cache();
+            let embedding = match cache.and_then(|cache| cache.get(model_type, query)) {
+                Some(embedding) => {
+                    total_embed_duration += start.elapsed();
+                    embedding
+                }
+                None => {
+                    let mut embedding_service = EmbeddingService::with_model(model_type)?;
+                    model_load_duration += start.elapsed();
+
+                    let start = Instant::now();
+                    let embedding = embedding_service.embed_query_uncached(query)?;
+                    if let Some(cache) = cache {
+                        if let Err(e) = cache.put(model_type, query, &embedding) {
+                            tracing::debug!("Query cache write failed: {}", e);
+                        }
+                    }
+                    total_embed_duration += start.elapsed();
+                    embedding
+                }
+            };
+            query_embeddings.insert(model_type, embedding);
+        }
+        let query_embedding = query_embeddings.get(&model_type).map(Vec::as_slice).unwrap_or_default();
 
         // Perform sync if requested
-        if sync {
-            if !json {
-                let db_type: &str = if db_path.ends_with(".demongrep.db") { "Local" } else { "Global" };
+        // Otherwise check whether the index is behind the working tree
+        // (skipped when nothing would act on the answer)
+        let stale = if sync || all_projects || (!auto_sync && crate::output::is_quiet()) {
+            None
+        } else {
+            let root = path.clone().unwrap_or_else(|| PathBuf::from("."));
+            check_staleness(&root, &db_path, &config.indexing).unwrap_or(None)
+        };
+
+        // Another writer (`serve`, the daemon) is already catching up
+        let writer = if auto_sync && !sync && stale.is_some() { WriteLock::holder(&db_path) } else { None };
+        if let Some(writer) = writer {
+            crate::warn_print!(
+                "{}",
+                format!("⏳ Index is being updated by `demongrep {}` (pid {})", writer.command, writer.pid).dimmed()
+            );
+        } else if sync || (auto_sync && stale.is_some()) {
+            if !machine_output {
+                let db_type: &str = if crate::index::is_local_db(&db_path) { "Local" } else { "Global" };
                 println!("{}", format!("🔄 Syncing {} database...", db_type).yellow());
             }
-            sync_database(&db_path, model_type)?;
+            sync_database(&db_path, model_type, config)?;
+        } else if let Some(stale) = stale {
+            crate::warn_print!(
+                "{}",
+                format!("⚠️  Index is {} behind, run with --sync to update", stale.describe()).yellow()
+            );
         }
         
         // Load this database
         let start = Instant::now();
-        let store = VectorStore::new(&db_path, dimensions)?;
+        let store = VectorStore::with_options(&db_path, dimensions, &StoreOptions::from_config(config))?;
         total_load_duration += start.elapsed();
         
         // Search in this database
         let start = Instant::now();
-        let retrieval_limit = if vector_only_mode { max_results } else { 200 };
-        let vector_results = store.search(&query_embedding, retrieval_limit)?;
+        let wanted = if rerank { rerank_top.max(max_results) } else { max_results };
+        let depth = depth_for(pipeline, retrieval_depth, max_results, wanted, store.chunk_count()?);
+        if verbose && !machine_output {
+            println!("{}", format!("   Retrieval depth: {}", depth).dimmed());
+            if pipeline != Pipeline::Lexical && store.searches_exactly()? {
+                println!("{}", "   Exact vector search (every stored vector is scored)".dimmed());
+            }
+        }
 
-        let fused_results: Vec<FusedResult> = if vector_only_mode {
-            vector_only(&vector_results)
+        let fts_store = if pipeline == Pipeline::Semantic {
+            None
         } else {
-            match FtsStore::open_readonly(&db_path) {
-                Ok(fts_store) => {
-                    let fts_results = fts_store.search(query, retrieval_limit)?;
-                    rrf_fusion(&vector_results, &fts_results, rrf_k)
-                }
-                Err(_) => {
-                    if !json {
-                        eprintln!("{}", "⚠️  FTS index not found, using vector-only search".yellow());
+            let fts_store = FtsStore::open_readonly(&db_path).ok();
+            if fts_store.is_none() && text_only {
+                bail!(
+                    "--text-only needs the full-text index, which {} doesn't have. Run `demongrep clear` and `demongrep index` to rebuild it",
+                    db_path.display()
+                );
+            }
+            if fts_store.is_none() && !machine_output {
+                eprintln!("{}", "⚠️  FTS index not found, using vector-only search".yellow());
+            }
+            fts_store
+        };
+        let mut narrowed = filters.clone();
+        if let Some(ref previous) = previous {
+            narrowed.chunk_ids = Some(previous.chunk_ids(&db_path));
+        }
+        // --coarse-to-fine: rank files by their summaries, then chunks in the best files only
+        if coarse_to_fine && !query_embedding.is_empty() {
+            if store.has_file_vectors()? {
+                let files = store.rank_files(query_embedding, top_files)?;
+                if verbose && !machine_output {
+                    for (file, score) in &files {
+                        println!("{}", format!("   📄 {:.3} {}", score, file).dimmed());
                     }
-                    vector_only(&vector_results)
                 }
+                let paths: Vec<String> = files.into_iter().map(|(file, _)| file).collect();
+                let mut ids = store.files_chunk_ids(&paths)?;
+                if let Some(ref refined) = narrowed.chunk_ids {
+                    ids.retain(|id| refined.contains(id));
+                }
+                narrowed.chunk_ids = Some(ids);
+            } else {
+                crate::warn_print!(
+                    "{}",
+                    format!(
+                        "⚠️  {} has no file summaries, searching all chunks. Run `demongrep index` to add them",
+                        db_path.display()
+                    )
+                    .yellow()
+                );
             }
-        };
+        }
+        let filters = &narrowed;
+        let (vector_results, fused_results) =
+            retrieve(&store, fts_store.as_ref(), fts_query, query_embedding, pipeline, depth, fusion, filters)?;
+        // Scores are only fused when both retrievers ran
+        let fused_with = (pipeline == Pipeline::Hybrid && fts_store.is_some()).then_some(fusion);
+        let fused_by_id: std::collections::HashMap<u32, &FusedResult> =
+            if explain { fused_results.iter().map(|f| (f.chunk_id, f)).collect() } else { Default::default() };
         
         // Map fused results back to full SearchResult
-        let chunk_id_to_result: std::collections::HashMap<u32, &crate::vectordb::SearchResult> =
-            vector_results.iter().map(|r| (r.id, r)).collect();
-        
         let take_count = if rerank { rerank_top.min(fused_results.len()) } else { max_results };
-        
-        for fused in fused_results.iter().take(take_count) {
-            if let Some(result) = chunk_id_to_result.get(&fused.chunk_id) {
-                let mut r = (*result).clone();
-                r.score = fused.rrf_score;
-                all_results.push(r);
-            } else {
-                if let Ok(Some(mut result)) = store.get_chunk_as_result(fused.chunk_id) {
-                    result.score = fused.rrf_score;
-                    all_results.push(result);
+        for mut result in resolve_fused(&store, &vector_results, &fused_results, take_count) {
+            let mut explanation =
+                fused_by_id.get(&result.id).map(|fused| explain::retrieval(fused, &result, fused_with));
+            // Patterns are relative to the project, so they apply before paths are made absolute
+            for boost in path_weights.boosts(&result.path) {
+                result.score *= boost.factor;
+                if let Some(explanation) = explanation.as_mut() {
+                    explanation.boosts.push(boost);
+                }
+            }
+            if let Some(ref root) = target.project_root {
+                result.path = absolutize(root, &result.path);
+            }
+            // Modification times are per chunk, so the boost is applied while the store is open
+            if boost_recent {
+                let modified = store.get_chunk(result.id)?.map(|meta| meta.modified).unwrap_or(0);
+                let boost = recency_boost(
+                    modified,
+                    now,
+                    config.search.recency_boost,
+                    config.search.recency_half_life_days,
+                );
+                if let Some(boost) = boost {
+                    result.score *= boost.factor;
+                    if let Some(explanation) = explanation.as_mut() {
+                        explanation.boosts.push(boost);
+                    }
                 }
             }
+            if let Some(explanation) = explanation {
+                explanations.insert(explain::key(&result), explanation);
+            }
+            result.database = Some(database_label(&db_path).to_string());
+            origins.insert((result.id, result.path.clone()), db_path.clone());
+            all_results.push(result);
         }
         
         total_search_duration += start.elapsed();
     }
     
     // Deduplicate results by (path, start_line, end_line) and keep highest score
-    let mut seen: std::collections::HashMap<(String, usize, usize), usize> = std::collections::HashMap::new();
-    let mut results: Vec<crate::vectordb::SearchResult> = Vec::new();
-    
-    for result in all_results {
-        let key = (result.path.clone(), result.start_line, result.end_line);
-        if let Some(&idx) = seen.get(&key) {
-            // Already have this result, keep the one with higher score
-            if result.score > results[idx].score {
-                results[idx] = result;
-            }
-        } else {
-            seen.insert(key, results.len());
-            results.push(result);
-        }
+    let mut results = dedup_results(all_results);
+
+    // Down-rank imports, boost module docs for overview queries (also sorts by score)
+    apply_kind_weights(
+        &mut results,
+        query,
+        config.search.imports_weight,
+        config.search.module_docs_boost,
+    );
+    if prefer_project {
+        apply_project_preference(&mut results);
     }
-    
-    // Sort by score
-    results.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap());
 
     // Neural reranking (if enabled)
     let mut rerank_duration = Duration::ZERO;
@@ -221,30 +981,30 @@ pub async fn search(
         let start = Instant::now();
         match NeuralReranker::new() {
             Ok(mut reranker) => {
-                let documents: Vec<String> = results.iter().map(|r| r.content.clone()).collect();
-                let rrf_scores: Vec<f32> = results.iter().map(|r| r.score).collect();
-                match reranker.rerank_and_blend(query, &documents, &rrf_scores) {
+                match rerank_results_with_scores(&mut reranker, query, results.clone()) {
                     Ok(reranked) => {
-                        let mut reordered: Vec<crate::vectordb::SearchResult> = Vec::with_capacity(results.len());
-                        for (idx, score) in reranked {
-                            let mut result = results[idx].clone();
-                            result.score = score;
-                            reordered.push(result);
-                        }
-                        results = reordered;
-                        if !json {
+                        results = reranked
+                            .into_iter()
+                            .map(|(result, rerank_score)| {
+                                if let Some(explanation) = explanations.get_mut(&explain::key(&result)) {
+                                    explanation.rerank_score = Some(rerank_score);
+                                }
+                                result
+                            })
+                            .collect();
+                        if !machine_output {
                             println!("{}", "✅ Neural reranking applied".green());
                         }
                     }
                     Err(e) => {
-                        if !json {
+                        if !machine_output {
                             eprintln!("{}", format!("⚠️  Reranking failed: {}", e).yellow());
                         }
                     }
                 }
             }
             Err(e) => {
-                if !json {
+                if !machine_output {
                     eprintln!("{}", format!("⚠️  Could not load reranker: {}", e).yellow());
                 }
             }
@@ -252,33 +1012,123 @@ pub async fn search(
         rerank_duration = start.elapsed();
     }
 
+    // One result per split definition instead of one per matching part
+    merge::merge_split_parts(&mut results);
+
+    // Retrieved at the method level, answered at the class level
+    if return_parents {
+        let replaced = parents::replace_with_parents(&mut results, &mut origins, default_dimensions)?;
+        if verbose && !machine_output {
+            println!("{}", format!("Replaced {} results with their enclosing definitions", replaced).dimmed());
+        }
+    }
+
     // Filter by path if specified
     if let Some(ref filter) = filter_path {
-        let filter_normalized = filter.trim_start_matches("./");
-        results.retain(|r| {
-            let path_normalized = r.path.trim_start_matches("./");
-            path_normalized.starts_with(filter_normalized)
-        });
+        retain_under(&mut results, filter);
+    }
+
+    // Vendored and generated copies take one slot instead of one each
+    if collapse_duplicates {
+        duplicates::collapse_near_duplicates(&mut results);
+    }
+
+    // Org-specific rules see every candidate before the cut
+    if let Some(ref command) = config.search.transform_command {
+        match transform_results(command, query, results.clone()) {
+            Ok(transformed) => results = transformed,
+            Err(e) => eprintln!("{}", format!("⚠️  Result transform skipped: {:#}", e).yellow()),
+        }
     }
 
     // Truncate to max_results after reranking and filtering
     results.truncate(max_results);
+    if let Some(max_total_tokens) = max_total_tokens {
+        cap_total_tokens(&mut results, max_total_tokens);
+    }
+
+    // What --refine narrows next time
+    let mut last_search = LastSearch::new(full_query);
+    for result in &results {
+        if let Some(db_path) = origins.get(&(result.id, result.path.clone())) {
+            last_search.add(db_path, result);
+        }
+    }
+    if let Err(e) = last_search.save(&config.data_dir) {
+        tracing::debug!("Could not record the search for --refine: {}", e);
+    }
+
+    if let Some(by) = count {
+        return count::print_counts(full_query, &results, by, format);
+    }
+
+    // The index may trail the files, most of all in watched projects
+    let stale = freshness::check_freshness(&mut results, &origins, default_dimensions, path.as_deref(), live)?;
+    if stale > 0 && !machine_output {
+        let hint = if live { "--sync reindexes them" } else { "--live shows them as they are now" };
+        crate::warn_print!(
+            "{}",
+            format!("⚠️  {} result(s) come from files changed since indexing; {}", stale, hint).yellow()
+        );
+    }
+
+    // Definitions of what the results call, looked up before expansion changes their content
+    let referenced = if hops > 1 {
+        hops::follow_calls(&results, |r| origins.get(&(r.id, r.path.clone())).cloned(), hops, default_dimensions)?
+    } else {
+        Vec::new()
+    };
+
+    // Only the shown results are worth re-parsing
+    if expand {
+        expand::expand_results(&mut results, path.as_deref());
+    }
+
+    // Point at the lines that answer the query, in the content as shown
+    match_lines::mark_match_lines(&mut results, query);
+    if config.search.embed_match_lines {
+        if let Some(query_embedding) = query_embeddings.get(&default_model) {
+            let mut embedding_service = EmbeddingService::with_model(default_model)?;
+            match_lines::embed_match_lines(&mut results, query, query_embedding, &mut embedding_service)?;
+        }
+    }
+
+    // Expanded results are shown whole
+    let content_lines = if expand { Some(usize::MAX) } else { content.then_some(CONTENT_LINES) };
+
+    let query_cache_stats = if scores { crate::embed::query_cache().map(|cache| cache.stats()) } else { None };
+
+    // Multipliers are derived from the final result, so merged parts get theirs too
+    let explain_result = |result: &crate::vectordb::SearchResult| -> Option<explain::Explanation> {
+        if !explain {
+            return None;
+        }
+        let mut explanation = explanations.get(&explain::key(result)).cloned().unwrap_or_default();
+        explanation.boosts.extend(score_boosts(
+            result,
+            query,
+            config.search.imports_weight,
+            config.search.module_docs_boost,
+            prefer_project,
+        ));
+        Some(explanation)
+    };
+
+    if let Some(n) = open {
+        return open::open_result(&results, n, path.as_deref());
+    }
+
+    // SARIF findings are matches only; other machine formats list the definitions after them
+    let mut referenced = referenced;
+    if machine_output && format != OutputFormat::Sarif {
+        results.append(&mut referenced);
+    }
 
     // Output results
-    if json {
+    if format == OutputFormat::Json {
         let json_results: Vec<JsonResult> = results
             .iter()
-            .map(|r| JsonResult {
-                path: r.path.clone(),
-                start_line: r.start_line,
-                end_line: r.end_line,
-                kind: r.kind.clone(),
-                content: r.content.clone(),
-                score: r.score,
-                signature: r.signature.clone(),
-                context_prev: r.context_prev.clone(),
-                context_next: r.context_next.clone(),
-            })
+            .map(|r| JsonResult::new(r, project_label(&projects, &r.path)).with_explanation(explain_result(r)))
             .collect();
 
         let timing = if scores {
@@ -287,13 +1137,15 @@ pub async fn search(
                 embed_ms: total_embed_duration.as_millis() as u64,
                 search_ms: total_search_duration.as_millis() as u64,
                 rerank_ms: if rerank { Some(rerank_duration.as_millis() as u64) } else { None },
+                query_cache_hits: query_cache_stats.as_ref().map(|s| s.hits),
+                query_cache_misses: query_cache_stats.as_ref().map(|s| s.misses),
             })
         } else {
             None
         };
 
         let output = JsonOutput {
-            query: query.to_string(),
+            query: full_query.to_string(),
             results: json_results,
             timing,
         };
@@ -302,6 +1154,24 @@ pub async fn search(
         return Ok(());
     }
 
+    if format == OutputFormat::Jsonl {
+        return write_jsonl(&results, &projects, explain_result);
+    }
+
+    if format == OutputFormat::Sarif {
+        println!("{}", serde_json::to_string_pretty(&sarif::sarif_report(full_query, &results))?);
+        return Ok(());
+    }
+
+    if format == OutputFormat::Markdown {
+        print!("{}", markdown_results(full_query, &results, &projects));
+        return Ok(());
+    }
+
+    if format == OutputFormat::Context {
+        return print_context(full_query, results, budget_tokens);
+    }
+
     if compact {
         // Show only file paths (like grep -l)
         let mut seen_files = std::collections::HashSet::new();
@@ -317,7 +1187,7 @@ pub async fn search(
     // Standard output
     println!("{}", "🔍 Search Results".bright_cyan().bold());
     println!("{}", "=".repeat(60));
-    println!("Query: \"{}\"", query.bright_yellow());
+    println!("Query: \"{}\"", full_query.bright_yellow());
     println!("Found {} results", results.len());
     println!();
 
@@ -326,6 +1196,16 @@ pub async fn search(
         println!("   Database load: {:?}", total_load_duration);
         println!("   Model load:    {:?}", model_load_duration);
         println!("   Query embed:   {:?}", total_embed_duration);
+        if let Some(ref stats) = query_cache_stats {
+            println!(
+                "   Query cache:   {} hit{}, {} miss{} ({} cached queries)",
+                stats.hits,
+                if stats.hits == 1 { "" } else { "s" },
+                stats.misses,
+                if stats.misses == 1 { "" } else { "es" },
+                stats.size
+            );
+        }
         println!("   Search:        {:?}", total_search_duration);
         if rerank {
             println!("   Reranking:     {:?}", rerank_duration);
@@ -364,13 +1244,35 @@ pub async fn search(
             file_results.truncate(per_file);
 
             for (idx, result) in file_results.iter().enumerate() {
-                print_result(result, idx == 0, content, scores)?;
+                print_result(
+                    result,
+                    idx == 0,
+                    content_lines,
+                    scores || explain,
+                    project_label(&projects, &result.path),
+                    explain_result(result).as_ref(),
+                )?;
             }
         }
     } else {
         // Show all results
         for result in &results {
-            print_result(result, true, content, scores)?;
+            print_result(
+                result,
+                true,
+                content_lines,
+                scores || explain,
+                project_label(&projects, &result.path),
+                explain_result(result).as_ref(),
+            )?;
+        }
+    }
+
+    if !referenced.is_empty() {
+        println!("{}", "🔗 Definitions called by these results".bright_cyan().bold());
+        println!();
+        for result in &referenced {
+            print_result(result, true, content_lines, false, None, None)?;
         }
     }
 
@@ -378,108 +1280,110 @@ pub async fn search(
 }
 
 /// Sync database by re-indexing changed files
-fn sync_database(db_path: &PathBuf, model_type: ModelType) -> Result<()> {
+///
+/// Goes through `apply_changes`, so the vector store, FTS index and file
+/// metadata are updated together.
+fn sync_database(db_path: &Path, model_type: ModelType, config: &Config) -> Result<()> {
     let project_path = db_path.parent().unwrap_or(std::path::Path::new("."));
+    let _write_lock = WriteLock::acquire(db_path, "search --sync")?;
 
-    // Load file metadata store
-    let mut file_meta = FileMetaStore::load_or_create(db_path, model_type.short_name(), model_type.dimensions())?;
-
-    // Walk the file system
-    let walker = FileWalker::new(project_path.to_path_buf());
-    let (files, _stats) = walker.walk()?;
-
-    // Initialize services
-    let mut embedding_service = EmbeddingService::with_model(model_type)?;
-    let mut chunker = SemanticChunker::new(100, 2000, 10);
-    let mut store = VectorStore::new(db_path, model_type.dimensions())?;
-
-    let mut changes = 0;
-
-    // Check for changed files
-    for file in &files {
-        let (needs_reindex, old_chunk_ids) = file_meta.check_file(&file.path)?;
-
-        if !needs_reindex {
-            continue;
-        }
-
-        changes += 1;
-        println!("  📝 {}", file.path.display());
-
-        // Delete old chunks
-        if !old_chunk_ids.is_empty() {
-            store.delete_chunks(&old_chunk_ids)?;
-        }
-
-        // Read and chunk file
-        let source_code = match std::fs::read_to_string(&file.path) {
-            Ok(content) => content,
-            Err(_) => continue,
-        };
-
-        let chunks = chunker.chunk_semantic(file.language, &file.path, &source_code)?;
+    let mut store = VectorStore::with_options(db_path, model_type.dimensions(), &StoreOptions::from_config(config))?;
+    recover_update(db_path, &mut store)?;
+    let changes = pending_changes(&store, project_path, &config.indexing)?;
 
-        if chunks.is_empty() {
-            file_meta.update_file(&file.path, vec![])?;
-            continue;
+    if changes.is_empty() {
+        println!("  ✅ Already up to date");
+    } else {
+        for (file, _) in &changes.changed {
+            println!("  📝 {}", file.path.display());
         }
-
-        // Embed and insert
-        let embedded_chunks = embedding_service.embed_chunks(chunks)?;
-        let chunk_ids = store.insert_chunks_with_ids(embedded_chunks)?;
-        file_meta.update_file(&file.path, chunk_ids)?;
-    }
-
-    // Check for deleted files
-    let deleted_files = file_meta.find_deleted_files();
-    for (path, chunk_ids) in &deleted_files {
-        changes += 1;
-        println!("  🗑️  {} (deleted)", path);
-        if !chunk_ids.is_empty() {
-            store.delete_chunks(chunk_ids)?;
+        for (path, _) in &changes.deleted {
+            println!("  🗑️  {} (deleted)", path.display());
         }
-        file_meta.remove_file(std::path::Path::new(path));
-    }
 
-    // Rebuild index if changes were made
-    if changes > 0 {
+        let mut embedding_service = EmbeddingService::with_model(model_type)?;
+        let mut chunker = SemanticChunker::from_config(&config.indexing).with_project_root(project_path);
         println!("  🔨 Rebuilding index...");
-        store.build_index()?;
-        file_meta.save(db_path)?;
-        println!("  ✅ {} file(s) synced", changes);
-    } else {
-        println!("  ✅ Already up to date");
+        apply_changes(&mut store, db_path, &changes, &mut embedding_service, &mut chunker)?;
+        println!("  ✅ {} file(s) synced", changes.file_count());
     }
+    record_fingerprint(project_path, db_path)?;
 
     Ok(())
 }
 
+/// Content lines shown per result with `--content`
+const CONTENT_LINES: usize = 10;
+
+/// Print one result; `content_lines` is None for a one-line snippet
+/// Indexes into `result.content` of its `match_lines`
+///
+/// Split parts start with a header line that is not in the file.
+fn marked_content_lines(result: &crate::vectordb::SearchResult) -> Vec<usize> {
+    let header = usize::from(result.content.trim_start().starts_with("// [Part "));
+    result
+        .match_lines
+        .iter()
+        .filter_map(|&line| (line + header).checked_sub(result.start_line))
+        .collect()
+}
+
 fn print_result(
     result: &crate::vectordb::SearchResult,
     show_file: bool,
-    show_content: bool,
+    content_lines: Option<usize>,
     show_scores: bool,
+    project: Option<String>,
+    explanation: Option<&explain::Explanation>,
 ) -> Result<()> {
     if show_file {
         println!("{}", "─".repeat(60));
-        let file_display = format!("📄 {}", result.path);
-        println!("{}", file_display.bright_green());
+        let file_display = format!("📄 {}", result.path).bright_green().to_string();
+        let file_display = hyperlink::link(&file_display, &result.path, result.start_line);
+        match project {
+            Some(project) => println!("{} {}", format!("[{}]", project).bright_magenta(), file_display),
+            None => println!("{}", file_display),
+        }
     }
 
-    // Show location and kind
-    let location = format!(
+    // Show location, kind, package and tags
+    let mut location = format!(
         "   Lines {}-{} • {}",
         result.start_line,
         result.end_line,
         result.kind
     );
-    println!("{}", location.dimmed());
+    if let Some(package) = &result.package {
+        location.push_str(&format!(" • 📦 {}", package));
+    }
+    if !result.tags.is_empty() {
+        location.push_str(&format!(" • {}", result.tags.join(", ")));
+    }
+    println!("{}", hyperlink::link(&location.dimmed().to_string(), &result.path, result.start_line));
+    if result.stale {
+        println!("   {}", "⚠️  File changed since indexing; this may not match it".yellow());
+    }
+
+    // Show the call that led here (--hops)
+    if let Some(via) = &result.via {
+        println!("   {}", format!("🔗 {}", via).dimmed());
+    }
 
     // Show signature if available
     if let Some(sig) = &result.signature {
         println!("   {}", sig.bright_cyan());
     }
 
+    // Show team notes
+    for note in &result.notes {
+        println!("   📝 {}", note.yellow());
+    }
+
+    // Show where else the code lives
+    if !result.also_in.is_empty() {
+        println!("   {}", format!("↳ {}", duplicates::describe_also_in(&result.also_in, 3)).dimmed());
+    }
+
     // Show score if requested
     if show_scores {
         let score_color = if result.score > 0.8 {
@@ -490,7 +1394,10 @@ fn print_result(
             "red"
         };
 
-        let score_text = format!("   Score: {:.3}", result.score);
+        let mut score_text = format!("   Score: {:.3}", result.score);
+        if let Some(ref database) = result.database {
+            score_text.push_str(&format!(" ({})", database));
+        }
         println!("{}", match score_color {
             "green" => score_text.green(),
             "yellow" => score_text.yellow(),
@@ -498,13 +1405,20 @@ fn print_result(
         });
     }
 
+    // Show how the score came about (--explain)
+    if let Some(explanation) = explanation {
+        for line in explain::describe(explanation) {
+            println!("   {}", line.dimmed());
+        }
+    }
+
     // Show context if available
     if let Some(ctx) = &result.context {
         println!("   Context: {}", ctx.dimmed());
     }
 
     // Show content if requested
-    if show_content {
+    if let Some(max_lines) = content_lines {
         // Show context before (if available)
         if let Some(ctx_prev) = &result.context_prev {
             println!("\n   {}:", "Context (before)".dimmed());
@@ -514,10 +1428,33 @@ fn print_result(
         }
 
         println!("\n   {}:", "Content".bright_yellow());
-        for line in result.content.lines().take(10) {
-            println!("   │ {}", line.dimmed());
+        let lines: Vec<&str> = result.content.lines().collect();
+        let marked = marked_content_lines(result);
+        // Start a few lines above the best match when it would be cut off
+        let skip = match marked.first() {
+            Some(&first) if first >= max_lines => first - 2,
+            _ => 0,
+        };
+        let shown: Vec<&str> = lines.iter().skip(skip).take(max_lines).copied().collect();
+        let gutter = |index: usize| {
+            if marked.contains(&(skip + index)) { "→".bright_yellow().bold() } else { "│".normal() }
+        };
+        if skip > 0 {
+            println!("   │ {}", "...".dimmed());
+        }
+        match highlight::highlight_lines(&shown.join("\n"), &result.path) {
+            Some(highlighted) => {
+                for (index, line) in highlighted.iter().enumerate() {
+                    println!("   {} {}", gutter(index), line);
+                }
+            }
+            None => {
+                for (index, line) in shown.iter().enumerate() {
+                    println!("   {} {}", gutter(index), line.dimmed());
+                }
+            }
         }
-        if result.content.lines().count() > 10 {
+        if lines.len().saturating_sub(skip) > max_lines {
             println!("   │ {}", "...".dimmed());
         }
 
@@ -528,6 +1465,16 @@ fn print_result(
                 println!("   │ {}", line.bright_black());
             }
         }
+    } else if let Some((&line, &index)) = result.match_lines.first().zip(marked_content_lines(result).first()) {
+        // Show the best-matching line
+        let text = result.content.lines().nth(index).unwrap_or_default().trim();
+        let text: String = if text.chars().count() > 100 {
+            format!("{}...", text.chars().take(100).collect::<String>())
+        } else {
+            text.to_string()
+        };
+        let shown = format!("{} {}", format!("→ {}:", line).bright_yellow(), text.dimmed());
+        println!("   {}", hyperlink::link(&shown, &result.path, line));
     } else {
         // Show a snippet
         let snippet: String = result
@@ -550,3 +1497,83 @@ fn print_result(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_adaptive_depth() {
+        // Default max_results on a mid-sized index matches the old fixed depth
+        assert_eq!(adaptive_depth(25, 10_000), 200);
+        // Small limits on small indexes stay at the floor
+        assert_eq!(adaptive_depth(5, 400), MIN_RETRIEVAL_DEPTH);
+        // Never more than the index holds, never more than the ceiling
+        assert_eq!(adaptive_depth(25, 30), 30);
+        assert_eq!(adaptive_depth(25, 10_000_000), MAX_RETRIEVAL_DEPTH);
+        // Reranking many candidates scales the depth with them
+        assert_eq!(adaptive_depth(100, 10_000), 400);
+    }
+
+    #[test]
+    fn test_is_symbol_name() {
+        for query in ["parse_config", "Pool", "$emit", "utf8"] {
+            assert!(is_symbol_name(query), "{}", query);
+        }
+        for query in ["", "parse config", "HashMap::new", "\"Pool\"", "a.b"] {
+            assert!(!is_symbol_name(query), "{}", query);
+        }
+    }
+
+    #[test]
+    fn test_dedup_results_keeps_database() {
+        let result = |score: f32, database: &str| crate::vectordb::SearchResult {
+            path: "src/lib.rs".to_string(),
+            start_line: 1,
+            end_line: 9,
+            score,
+            database: Some(database.to_string()),
+            ..Default::default()
+        };
+        let results = dedup_results(vec![result(0.4, "local"), result(0.7, "global")]);
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].database.as_deref(), Some("global"));
+    }
+
+    #[test]
+    fn test_is_under() {
+        assert!(is_under("./src/api/user.rs", "src/api"));
+        assert!(is_under("src/api/user.rs", "./src/api/"));
+        assert!(is_under("src/api/user.rs", "src/api/user.rs"));
+        assert!(is_under("src/api/user.rs", "."));
+        assert!(!is_under("src/api_v2/user.rs", "src/api"));
+        assert!(!is_under("tests/api/user.rs", "src"));
+    }
+
+    #[test]
+    fn test_markdown_results() {
+        let results = vec![crate::vectordb::SearchResult {
+            path: "src/auth.rs".to_string(),
+            start_line: 10,
+            end_line: 12,
+            kind: "Function".to_string(),
+            signature: Some("fn check(token: &str) -> bool".to_string()),
+            content: "fn check(token: &str) -> bool {\n    !token.is_empty()\n}\n".to_string(),
+            score: 0.8123,
+            notes: vec!["Called on every request".to_string()],
+            ..Default::default()
+        }];
+
+        let markdown = markdown_results("token check", &results, &[]);
+        assert!(markdown.starts_with("# Search results for \"token check\"\n"));
+        assert!(markdown.contains("## `src/auth.rs:10-12`\n"));
+        assert!(markdown.contains("Function · `fn check(token: &str) -> bool` · score 0.812\n"));
+        assert!(markdown.contains("> 📝 Called on every request\n"));
+        assert!(markdown.contains("```rust\nfn check(token: &str) -> bool {\n    !token.is_empty()\n}\n```\n"));
+
+        // Code containing a fence gets a longer one
+        let block = fenced_code("README.md", "```sh\nls\n```");
+        assert!(block.starts_with("````markdown\n"));
+        assert!(block.ends_with("\n````\n"));
+    }
+}
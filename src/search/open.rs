@@ -0,0 +1,135 @@
+//! Opening a result in the user's editor (`search --open [n]`)
+//!
+//! The editor is `$VISUAL`, then `$EDITOR`, then `vi`. Editors disagree on
+//! how to say "at this line", so the arguments are picked by the editor's
+//! name: `+line file` for vi-style editors, `--goto file:line` for VS Code
+//! and its forks, `file:line` for Helix, Sublime and Zed. Without `n`, the
+//! results are listed and the user picks one.
+
+use anyhow::{bail, Result};
+use colored::Colorize;
+use std::io::{self, IsTerminal, Write};
+use std::path::Path;
+use std::process::Command;
+
+use super::expand::locate;
+use crate::vectordb::SearchResult;
+
+/// Editor command: `$VISUAL`, then `$EDITOR`, then `vi`
+fn editor_command() -> String {
+    ["VISUAL", "EDITOR"]
+        .iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find(|command| !command.trim().is_empty())
+        .unwrap_or_else(|| "vi".to_string())
+}
+
+/// Arguments that open `file` at `line` (1-based) in `editor`
+///
+/// `editor` is the command as configured; only its program name matters.
+pub fn editor_args(editor: &str, file: &Path, line: usize) -> Vec<String> {
+    let program = editor.split_whitespace().next().unwrap_or(editor);
+    let name = Path::new(program)
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or(program)
+        .to_lowercase();
+    let file = file.display().to_string();
+    match name.as_str() {
+        "code" | "code-insiders" | "codium" | "vscodium" | "cursor" | "windsurf" => {
+            vec!["--goto".to_string(), format!("{}:{}", file, line)]
+        }
+        "hx" | "helix" | "subl" | "sublime_text" | "zed" | "zeditor" => vec![format!("{}:{}", file, line)],
+        "idea" | "pycharm" | "goland" | "webstorm" | "clion" | "rustrover" | "phpstorm" => {
+            vec!["--line".to_string(), line.to_string(), file]
+        }
+        // vi, vim, nvim, nano, emacs, micro, kak, joe, ... all take +line
+        _ => vec![format!("+{}", line), file],
+    }
+}
+
+/// Ask which result to open; Enter picks the first
+fn prompt_choice(results: &[SearchResult]) -> Result<Option<usize>> {
+    let width = results.len().to_string().len();
+    for (idx, result) in results.iter().enumerate() {
+        let label = result.signature.as_deref().unwrap_or(&result.kind);
+        eprintln!(
+            "{:>width$}. {} {}",
+            idx + 1,
+            format!("{}:{}", result.path, result.start_line + 1).bright_green(),
+            label.dimmed(),
+            width = width
+        );
+    }
+    eprint!("Open which result? [1-{}, Enter = 1, q = quit]: ", results.len());
+    io::stderr().flush()?;
+
+    let mut input = String::new();
+    io::stdin().read_line(&mut input)?;
+    let input = input.trim();
+    if input.eq_ignore_ascii_case("q") {
+        return Ok(None);
+    }
+    if input.is_empty() {
+        return Ok(Some(1));
+    }
+    match input.parse::<usize>() {
+        Ok(n) => Ok(Some(n)),
+        Err(_) => bail!("Not a result number: {}", input),
+    }
+}
+
+/// Open result `n` (1-based) in the editor, prompting for it when None
+///
+/// Without a terminal to prompt on, the first result is opened.
+pub fn open_result(results: &[SearchResult], n: Option<usize>, project: Option<&Path>) -> Result<()> {
+    if results.is_empty() {
+        bail!("No matches found, nothing to open");
+    }
+    let n = match n {
+        Some(n) => n,
+        None if io::stdin().is_terminal() => match prompt_choice(results)? {
+            Some(n) => n,
+            None => return Ok(()),
+        },
+        None => 1,
+    };
+    if n == 0 || n > results.len() {
+        bail!("No result {}: there are {} (numbered from 1)", n, results.len());
+    }
+
+    let result = &results[n - 1];
+    let Some(file) = locate(&result.path, project) else {
+        bail!("{} is no longer on disk", result.path);
+    };
+    let editor = editor_command();
+    let args = editor_args(&editor, &file, result.start_line + 1);
+
+    // Through the shell, like git, so the editor command may carry its own flags
+    let status = if cfg!(unix) {
+        Command::new("sh").arg("-c").arg(format!("{} \"$@\"", editor)).arg(&editor).args(&args).status()
+    } else {
+        let mut words = editor.split_whitespace();
+        Command::new(words.next().unwrap_or("vi")).args(words).args(&args).status()
+    };
+    match status {
+        Ok(status) if status.success() => Ok(()),
+        Ok(status) => bail!("Editor '{}' exited with {}", editor, status),
+        Err(e) => bail!("Failed to start editor '{}': {}", editor, e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_editor_args() {
+        let file = Path::new("src/lib.rs");
+        assert_eq!(editor_args("nvim", file, 12), ["+12", "src/lib.rs"]);
+        assert_eq!(editor_args("/usr/bin/vim -u NONE", file, 12), ["+12", "src/lib.rs"]);
+        assert_eq!(editor_args("code --wait", file, 12), ["--goto", "src/lib.rs:12"]);
+        assert_eq!(editor_args("hx", file, 12), ["src/lib.rs:12"]);
+        assert_eq!(editor_args("idea", file, 12), ["--line", "12", "src/lib.rs"]);
+    }
+}
@@ -0,0 +1,125 @@
+//! Returning enclosing definitions (`search --return-parents`)
+//!
+//! Small chunks match precisely, large ones answer better: a method matches
+//! the query, but an LLM asked about it needs the impl block or class around
+//! it. Each chunk records the chunk of its enclosing definition at index
+//! time, so retrieval can stay at the method level and `--return-parents`
+//! swaps each result for its parent afterwards. Several methods of one class
+//! become one result, at the rank of the best of them.
+
+use anyhow::Result;
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+
+use crate::chunker::Chunk;
+use crate::vectordb::{SearchResult, VectorStore};
+
+/// Replace each result with the definition enclosing it, keeping its rank
+///
+/// `origins` maps results to the database they came from; parents are read
+/// from the same database and added to it. Results without a parent, or
+/// from indexes that predate parent links, are kept as they are. Returns
+/// how many results were replaced.
+pub fn replace_with_parents(
+    results: &mut Vec<SearchResult>,
+    origins: &mut HashMap<(u32, String), PathBuf>,
+    dimensions: usize,
+) -> Result<usize> {
+    let mut stores: HashMap<PathBuf, Option<VectorStore>> = HashMap::new();
+    let mut seen: HashSet<(String, usize)> = HashSet::new();
+    let mut replaced = 0;
+    let mut parents = Vec::with_capacity(results.len());
+
+    for result in results.drain(..) {
+        let parent = match origins.get(&(result.id, result.path.clone())) {
+            Some(db_path) => {
+                let store = stores.entry(db_path.clone()).or_insert_with(|| VectorStore::new(db_path, dimensions).ok());
+                match store {
+                    Some(store) => parent_of(store, &result)?.map(|parent| (parent, db_path.clone())),
+                    None => None,
+                }
+            }
+            None => None,
+        };
+        let result = match parent {
+            Some((parent, db_path)) => {
+                origins.insert((parent.id, parent.path.clone()), db_path);
+                replaced += 1;
+                parent
+            }
+            None => result,
+        };
+        // Siblings share a parent; the best-ranked one placed it
+        if seen.insert((result.path.clone(), result.start_line)) {
+            parents.push(result);
+        }
+    }
+    *results = parents;
+    Ok(replaced)
+}
+
+/// The enclosing definition of `result`, ranked and labeled in its place
+fn parent_of(store: &VectorStore, result: &SearchResult) -> Result<Option<SearchResult>> {
+    let Some(parent_id) = store.get_chunk(result.id)?.and_then(|meta| meta.parent_id) else {
+        return Ok(None);
+    };
+    let Some(mut parent) = store.get_chunk_as_result(parent_id)? else {
+        return Ok(None);
+    };
+    let name = result.context.as_deref().and_then(Chunk::name_from_context).unwrap_or(&result.kind);
+    // Paths may have been made absolute (--all-projects)
+    parent.path = result.path.clone();
+    parent.score = result.score;
+    parent.distance = result.distance;
+    parent.database = result.database.clone();
+    parent.via = Some(format!("containing the match {} (lines {}-{})", name, result.start_line, result.end_line));
+    Ok(Some(parent))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunker::ChunkKind;
+    use crate::embed::EmbeddedChunk;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_replace_with_parents() {
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("test.db");
+        let mut store = VectorStore::new(&db_path, 2).unwrap();
+        let chunk = |context: &[&str], kind, start_line, end_line| {
+            let mut chunk = Chunk::new(format!("// {}", start_line), start_line, end_line, kind, "a.rs".to_string());
+            chunk.context = context.iter().map(|crumb| crumb.to_string()).collect();
+            EmbeddedChunk::new(chunk, vec![1.0, 0.0])
+        };
+        store
+            .insert_chunks(vec![
+                chunk(&["File: a.rs", "Impl: Pool"], ChunkKind::Impl, 0, 30),
+                chunk(&["File: a.rs", "Impl: Pool", "Method: acquire"], ChunkKind::Method, 2, 10),
+                chunk(&["File: a.rs", "Impl: Pool", "Method: release"], ChunkKind::Method, 11, 20),
+                chunk(&["File: a.rs", "Function: main"], ChunkKind::Function, 31, 40),
+            ])
+            .unwrap();
+        drop(store);
+
+        let store = VectorStore::new(&db_path, 2).unwrap();
+        let mut results: Vec<SearchResult> = [2, 3, 1]
+            .iter()
+            .enumerate()
+            .map(|(rank, &id)| SearchResult { score: 1.0 - rank as f32 / 10.0, ..store.get_chunk_as_result(id).unwrap().unwrap() })
+            .collect();
+        drop(store);
+        let mut origins: HashMap<(u32, String), PathBuf> =
+            results.iter().map(|r| ((r.id, r.path.clone()), db_path.clone())).collect();
+
+        assert_eq!(replace_with_parents(&mut results, &mut origins, 2).unwrap(), 2);
+        // Both methods became the impl block, at the rank of release
+        let ids: Vec<u32> = results.iter().map(|r| r.id).collect();
+        assert_eq!(ids, [0, 3]);
+        assert_eq!(results[0].score, 1.0);
+        assert_eq!(results[0].via.as_deref(), Some("containing the match release (lines 11-20)"));
+        assert!(results[1].via.is_none());
+        assert!(origins.contains_key(&(0, "a.rs".to_string())));
+    }
+}
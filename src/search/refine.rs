@@ -0,0 +1,115 @@
+//! Narrowing the previous search (`search --refine`)
+//!
+//! A first query often finds the right area but not the right chunk. Every
+//! search records the chunks it showed, per database, in the data
+//! directory; with `--refine` the next query searches only those chunks, so
+//! "retry logic" followed by `--refine "exponential backoff"` ranks the
+//! retry results by the second query instead of searching everything again.
+//! The server and MCP take the IDs directly (`previous_result_ids`).
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::vectordb::SearchResult;
+
+/// File in the data directory holding the last search
+const LAST_SEARCH_FILE: &str = "last_search.json";
+
+/// The results of the last search, as chunk IDs per database
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct LastSearch {
+    pub query: String,
+    /// Database path to the IDs of its chunks among the results
+    databases: BTreeMap<String, Vec<u32>>,
+}
+
+/// Key for a database, the same however its path was spelled
+fn database_key(db_path: &Path) -> String {
+    db_path.canonicalize().unwrap_or_else(|_| db_path.to_path_buf()).to_string_lossy().to_string()
+}
+
+impl LastSearch {
+    pub fn new(query: &str) -> Self {
+        Self { query: query.to_string(), databases: BTreeMap::new() }
+    }
+
+    /// Record a shown result from the database at `db_path`
+    pub fn add(&mut self, db_path: &Path, result: &SearchResult) {
+        let ids = self.databases.entry(database_key(db_path)).or_default();
+        if !ids.contains(&result.id) {
+            ids.push(result.id);
+        }
+    }
+
+    /// IDs of the results from the database at `db_path` (none if it had no results)
+    pub fn chunk_ids(&self, db_path: &Path) -> Vec<u32> {
+        self.databases.get(&database_key(db_path)).cloned().unwrap_or_default()
+    }
+
+    /// Number of recorded results
+    pub fn len(&self) -> usize {
+        self.databases.values().map(Vec::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The last search recorded in `data_dir`
+    pub fn load(data_dir: &Path) -> Result<Self> {
+        let path = data_dir.join(LAST_SEARCH_FILE);
+        if !path.exists() {
+            bail!("No earlier search to refine; run a search without --refine first");
+        }
+        let text = std::fs::read_to_string(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+        serde_json::from_str(&text).with_context(|| format!("Failed to parse {}", path.display()))
+    }
+
+    /// Replace the recorded search; a search without results leaves it alone
+    pub fn save(&self, data_dir: &Path) -> Result<()> {
+        if self.is_empty() {
+            return Ok(());
+        }
+        std::fs::create_dir_all(data_dir)?;
+        std::fs::write(data_dir.join(LAST_SEARCH_FILE), serde_json::to_string(self)?)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_last_search() -> Result<()> {
+        let dir = tempdir()?;
+        assert!(LastSearch::load(dir.path()).is_err());
+
+        let local = dir.path().join("local");
+        let global = dir.path().join("global");
+        std::fs::create_dir_all(&local)?;
+        std::fs::create_dir_all(&global)?;
+        let result = |id| SearchResult { id, ..Default::default() };
+        let mut last = LastSearch::new("retry logic");
+        last.add(&local, &result(4));
+        last.add(&local, &result(9));
+        last.add(&local, &result(4));
+        last.add(&global, &result(4));
+        last.save(dir.path())?;
+
+        let loaded = LastSearch::load(dir.path())?;
+        assert_eq!(loaded, last);
+        assert_eq!(loaded.len(), 3);
+        // Paths are compared canonicalized
+        assert_eq!(loaded.chunk_ids(&dir.path().join("global/../local")), [4, 9]);
+        assert!(loaded.chunk_ids(&dir.path().join("other")).is_empty());
+
+        // Empty searches don't replace it
+        LastSearch::new("nothing").save(dir.path())?;
+        assert_eq!(LastSearch::load(dir.path())?.query, "retry logic");
+        Ok(())
+    }
+}
@@ -0,0 +1,322 @@
+//! Searching through a running server (`search --remote`)
+//!
+//! A local search loads the embedding model before it can embed the query,
+//! which takes seconds; `serve` keeps the model loaded. With `--remote URL`
+//! the query is sent to that server's `POST /search` and only the results
+//! come back. `--remote` without a URL finds the server `serve` announced in
+//! the project's database (see `server::address`), and `--remote unix:PATH`
+//! talks to a server started with `serve --socket PATH`. The server ranks
+//! with its own settings, so options that change how a local search ranks or
+//! what it shows beyond the results are not available.
+
+use anyhow::{anyhow, bail, Result};
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use super::{
+    cap_total_tokens, markdown_results, print_context, print_result, write_jsonl, JsonOutput, JsonResult,
+    OutputFormat, CONTENT_LINES,
+};
+use crate::config::Config;
+use crate::embed::count_tokens;
+use crate::index::get_search_db_paths;
+use crate::server::ServerAddress;
+use crate::vectordb::SearchResult;
+
+/// How long to wait for the server to answer
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Body of `POST /search`
+#[derive(Serialize)]
+struct RemoteRequest<'a> {
+    query: &'a str,
+    limit: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    path: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    rerank: Option<bool>,
+    tags: &'a [String],
+    not_tags: &'a [String],
+    packages: &'a [String],
+    full_content: bool,
+}
+
+#[derive(Deserialize)]
+struct RemoteResponse {
+    results: Vec<RemoteResult>,
+    took_ms: u64,
+    #[serde(default)]
+    reranked: bool,
+}
+
+#[derive(Deserialize)]
+struct RemoteResult {
+    id: u32,
+    path: String,
+    content: String,
+    start_line: usize,
+    end_line: usize,
+    kind: String,
+    score: f32,
+    database: String,
+    #[serde(default)]
+    notes: Vec<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    package: Option<String>,
+}
+
+impl From<RemoteResult> for SearchResult {
+    fn from(r: RemoteResult) -> Self {
+        SearchResult {
+            id: r.id,
+            tokens: count_tokens(&r.content),
+            content: r.content,
+            path: r.path,
+            start_line: r.start_line,
+            end_line: r.end_line,
+            kind: r.kind,
+            distance: 1.0 - r.score,
+            score: r.score,
+            notes: r.notes,
+            tags: r.tags,
+            package: r.package,
+            database: Some(r.database),
+            ..Default::default()
+        }
+    }
+}
+
+/// URL of the server announced in one of the project's databases
+fn discover(path: Option<PathBuf>) -> Result<String> {
+    get_search_db_paths(path)?
+        .into_iter()
+        .find_map(|db_path| ServerAddress::find(&db_path))
+        .map(|address| address.url)
+        .ok_or_else(|| anyhow!("No running `demongrep serve` found for this project. Start one, or pass --remote URL"))
+}
+
+/// POST `body` to `/search` over TCP, returning the status and response body
+fn post_http(base_url: &str, body: &str, api_key: Option<&str>) -> Result<(u16, String)> {
+    let url = format!("{}/search", base_url.trim_end_matches('/'));
+    let agent = ureq::AgentBuilder::new().timeout(REQUEST_TIMEOUT).build();
+    let mut call = agent.post(&url).set("Content-Type", "application/json");
+    if let Some(key) = api_key {
+        call = call.set("Authorization", &format!("Bearer {}", key));
+    }
+    let response = match call.send_string(body) {
+        Ok(response) | Err(ureq::Error::Status(_, response)) => response,
+        Err(ureq::Error::Transport(e)) => bail!("Could not reach {}: {}", base_url, e),
+    };
+    let status = response.status();
+    let mut text = String::new();
+    response.into_reader().read_to_string(&mut text)?;
+    Ok((status, text))
+}
+
+/// POST `body` to `/search` over the Unix socket at `socket`, returning the status and response body
+#[cfg(unix)]
+fn post_unix(socket: &Path, body: &str, api_key: Option<&str>) -> Result<(u16, String)> {
+    use std::io::Write;
+    use std::os::unix::net::UnixStream;
+
+    let mut stream =
+        UnixStream::connect(socket).map_err(|e| anyhow!("Could not reach {}: {}", socket.display(), e))?;
+    stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+    let mut request = format!(
+        "POST /search HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n",
+        body.len()
+    );
+    if let Some(key) = api_key {
+        request.push_str(&format!("Authorization: Bearer {}\r\n", key));
+    }
+    request.push_str("\r\n");
+    request.push_str(body);
+    stream.write_all(request.as_bytes())?;
+
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response)?;
+    parse_response(&response)
+}
+
+#[cfg(not(unix))]
+fn post_unix(_socket: &Path, _body: &str, _api_key: Option<&str>) -> Result<(u16, String)> {
+    bail!("Unix domain sockets are not supported on this platform")
+}
+
+/// Position of the first occurrence of `needle` in `haystack`
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|window| window == needle)
+}
+
+/// Status and body of a raw HTTP/1.1 response read until the server closed the connection
+fn parse_response(response: &[u8]) -> Result<(u16, String)> {
+    let malformed = || anyhow!("Malformed response from the server");
+    let head_end = find(response, b"\r\n\r\n").ok_or_else(malformed)?;
+    let head = String::from_utf8_lossy(&response[..head_end]);
+    let body = &response[head_end + 4..];
+    let mut lines = head.lines();
+    let status = lines
+        .next()
+        .and_then(|line| line.split_whitespace().nth(1))
+        .and_then(|code| code.parse().ok())
+        .ok_or_else(malformed)?;
+    let chunked = lines.any(|line| {
+        line.split_once(':').is_some_and(|(name, value)| {
+            name.trim().eq_ignore_ascii_case("transfer-encoding") && value.trim().eq_ignore_ascii_case("chunked")
+        })
+    });
+    if !chunked {
+        return Ok((status, String::from_utf8_lossy(body).into_owned()));
+    }
+
+    // Each chunk is its size in hex, CRLF, the data and CRLF; a size of 0 ends the body.
+    // Chunks split the body at byte offsets, so it is only decoded once reassembled
+    let truncated = || anyhow!("Truncated response from the server");
+    let mut decoded = Vec::new();
+    let mut rest = body;
+    while let Some(line_end) = find(rest, b"\r\n") {
+        let size_line = std::str::from_utf8(&rest[..line_end]).map_err(|_| malformed())?;
+        let size = usize::from_str_radix(size_line.split(';').next().unwrap_or("").trim(), 16)?;
+        if size == 0 {
+            break;
+        }
+        let after = &rest[line_end + 2..];
+        let data = after.get(..size).ok_or_else(truncated)?;
+        decoded.extend_from_slice(data);
+        rest = after[size..].strip_prefix(b"\r\n").ok_or_else(truncated)?;
+    }
+    Ok((status, String::from_utf8_lossy(&decoded).into_owned()))
+}
+
+/// Send the search to `POST /search` on the server at `base_url` (`http(s)://...` or `unix:PATH`)
+fn post_search(base_url: &str, request: &RemoteRequest, api_key: Option<&str>) -> Result<RemoteResponse> {
+    let body = serde_json::to_string(request)?;
+    let (status, text) = match base_url.strip_prefix("unix:") {
+        Some(socket) => post_unix(Path::new(socket), &body, api_key)?,
+        None => post_http(base_url, &body, api_key)?,
+    };
+    match status {
+        200 => Ok(serde_json::from_str(&text)?),
+        401 => bail!("{} needs an API key; set DEMONGREP_API_KEY or serve.api_key", base_url),
+        code => bail!("{} answered {}: {}", base_url, code, text.trim()),
+    }
+}
+
+/// Search through the server at `url`, or the one running for the project
+#[allow(clippy::too_many_arguments)]
+pub async fn remote_search(
+    query: &str,
+    url: Option<String>,
+    max_results: usize,
+    content: bool,
+    scores: bool,
+    format: OutputFormat,
+    path: Option<PathBuf>,
+    filter_path: Option<String>,
+    tags: Vec<String>,
+    not_tags: Vec<String>,
+    packages: Vec<String>,
+    rerank: Option<bool>,
+    budget_tokens: usize,
+    max_total_tokens: Option<usize>,
+    config: &Config,
+) -> Result<()> {
+    if format == OutputFormat::Sarif {
+        bail!("--format sarif needs a local search");
+    }
+    let start = Instant::now();
+    let url = match url {
+        Some(url) => url,
+        None => discover(path)?,
+    };
+    let request = RemoteRequest {
+        query,
+        limit: max_results,
+        path: filter_path.as_deref(),
+        rerank,
+        tags: &tags,
+        not_tags: &not_tags,
+        packages: &packages,
+        full_content: true,
+    };
+    let response = post_search(&url, &request, config.serve.resolved_api_key().as_deref())?;
+    let mut results: Vec<SearchResult> = response.results.into_iter().map(SearchResult::from).collect();
+    if let Some(max_total_tokens) = max_total_tokens {
+        cap_total_tokens(&mut results, max_total_tokens);
+    }
+
+    match format {
+        OutputFormat::Json => {
+            let output = JsonOutput {
+                query: query.to_string(),
+                results: results.iter().map(|r| JsonResult::new(r, None)).collect(),
+                timing: None,
+            };
+            println!("{}", serde_json::to_string(&output)?);
+        }
+        OutputFormat::Jsonl => write_jsonl(&results, &[], |_| None)?,
+        OutputFormat::Markdown => print!("{}", markdown_results(query, &results, &[])),
+        OutputFormat::Context => print_context(query, results, budget_tokens)?,
+        OutputFormat::Text | OutputFormat::Sarif => {
+            let reranked = if response.reranked { ", reranked" } else { "" };
+            println!(
+                "{}",
+                format!(
+                    "🌐 Searched by {} in {} ms{} ({:?} in total)",
+                    url,
+                    response.took_ms,
+                    reranked,
+                    start.elapsed()
+                )
+                .dimmed()
+            );
+            if results.is_empty() {
+                println!("{}", "No matches found.".dimmed());
+            }
+            let content_lines = content.then_some(CONTENT_LINES);
+            for result in &results {
+                print_result(result, true, content_lines, scores, None, None)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_remote_result() {
+        let body = r#"{"results": [{"id": 7, "path": "src/auth.rs", "content": "fn login() {}", "start_line": 3,
+            "end_line": 4, "kind": "Function", "score": 0.8, "database": "local", "tags": ["api"]}],
+            "query": "login", "took_ms": 12, "databases_searched": 1, "reranked": false}"#;
+        let response: RemoteResponse = serde_json::from_str(body).unwrap();
+        assert_eq!(response.took_ms, 12);
+        let result = SearchResult::from(response.results.into_iter().next().unwrap());
+        assert_eq!((result.id, result.path.as_str(), result.start_line), (7, "src/auth.rs", 3));
+        assert_eq!(result.database.as_deref(), Some("local"));
+        assert_eq!(result.tags, ["api"]);
+        assert!(result.notes.is_empty() && result.tokens > 0);
+    }
+
+    #[test]
+    fn test_parse_response() {
+        let plain = b"HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: 2\r\n\r\n{}";
+        assert_eq!(parse_response(plain).unwrap(), (200, "{}".to_string()));
+
+        let chunked = b"HTTP/1.1 400 Bad Request\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nQuery\r\n9\r\n too long\r\n0\r\n\r\n";
+        assert_eq!(parse_response(chunked).unwrap(), (400, "Query too long".to_string()));
+
+        // "é" is split across the two chunks
+        let split = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n2\r\nr\xc3\r\n2\r\n\xa9s\r\n0\r\n\r\n";
+        assert_eq!(parse_response(split).unwrap(), (200, "rés".to_string()));
+
+        assert!(parse_response(b"garbage").is_err());
+    }
+}
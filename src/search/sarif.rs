@@ -0,0 +1,113 @@
+//! SARIF output for code-scanning tools (`search --format sarif`)
+//!
+//! Each result becomes a SARIF 2.1.0 result whose rule is the query itself,
+//! so a semantic query like "places we build SQL from user input" can be
+//! uploaded to GitHub code scanning and reviewed like any other alert.
+
+use serde_json::{json, Value};
+
+use crate::vectordb::SearchResult;
+
+/// Artifact URI for a result path: relative paths as-is, absolute ones as file URIs
+fn artifact_uri(path: &str) -> String {
+    let path = path.replace('\\', "/");
+    let path = path.trim_start_matches("./");
+    if path.starts_with('/') {
+        format!("file://{}", path)
+    } else if path.as_bytes().get(1) == Some(&b':') {
+        // Windows drive path
+        format!("file:///{}", path)
+    } else {
+        path.to_string()
+    }
+}
+
+/// A SARIF log with one run holding every result
+pub(super) fn sarif_report(query: &str, results: &[SearchResult]) -> Value {
+    let sarif_results: Vec<Value> = results
+        .iter()
+        .map(|result| {
+            let mut message = format!("Matches \"{}\" (score {:.3})", query, result.score);
+            if let Some(ref signature) = result.signature {
+                message.push_str(&format!(": {}", signature));
+            }
+            // Chunk rows are 0-based with an exclusive end; SARIF lines are 1-based and inclusive
+            let start_line = result.start_line + 1;
+            let end_line = result.end_line.max(start_line);
+
+            json!({
+                "ruleId": query,
+                "ruleIndex": 0,
+                "level": "note",
+                "message": { "text": message },
+                "locations": [{
+                    "physicalLocation": {
+                        "artifactLocation": { "uri": artifact_uri(&result.path) },
+                        "region": { "startLine": start_line, "endLine": end_line },
+                    }
+                }],
+                "partialFingerprints": { "demongrepChunkHash/v1": result.hash },
+                "properties": {
+                    "score": result.score,
+                    "kind": result.kind,
+                    "notes": result.notes,
+                },
+            })
+        })
+        .collect();
+
+    json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "demongrep",
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "informationUri": env!("CARGO_PKG_REPOSITORY"),
+                    "rules": [{
+                        "id": query,
+                        "shortDescription": { "text": query },
+                        "defaultConfiguration": { "level": "note" },
+                    }],
+                }
+            },
+            "results": sarif_results,
+        }]
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sarif_report() {
+        let results = vec![SearchResult {
+            path: "./src/db.rs".to_string(),
+            start_line: 9,
+            end_line: 20,
+            kind: "Function".to_string(),
+            signature: Some("fn raw_query(sql: String)".to_string()),
+            hash: "abc123".to_string(),
+            score: 0.5,
+            ..Default::default()
+        }];
+
+        let report = sarif_report("SQL built from user input", &results);
+        assert_eq!(report["version"], "2.1.0");
+        let run = &report["runs"][0];
+        assert_eq!(run["tool"]["driver"]["rules"][0]["id"], "SQL built from user input");
+
+        let result = &run["results"][0];
+        assert_eq!(result["ruleId"], "SQL built from user input");
+        let location = &result["locations"][0]["physicalLocation"];
+        assert_eq!(location["artifactLocation"]["uri"], "src/db.rs");
+        assert_eq!(location["region"]["startLine"], 10);
+        assert_eq!(location["region"]["endLine"], 20);
+        assert_eq!(result["partialFingerprints"]["demongrepChunkHash/v1"], "abc123");
+
+        assert_eq!(artifact_uri("/home/me/project/a.rs"), "file:///home/me/project/a.rs");
+        assert_eq!(artifact_uri("C:\\repo\\a.rs"), "file:///C:/repo/a.rs");
+    }
+}
@@ -0,0 +1,106 @@
+//! Queries read from stdin (`demongrep search -`)
+//!
+//! Piping in an error message or a stack trace is the quickest way to ask
+//! "where does this come from?". Such input is long and repetitive, and
+//! the model only looks at its first few hundred tokens, so it is condensed
+//! first: memory addresses and "... 12 more" lines are dropped, repeated
+//! lines (recursive frames) are kept once, and the rest is cut to a token
+//! budget from the top, where the error message and innermost frames are.
+
+use anyhow::{bail, Context, Result};
+use std::collections::HashSet;
+use std::io::Read;
+
+use crate::embed::estimate_tokens;
+
+/// Longest query, in estimated tokens, kept from stdin
+pub const MAX_QUERY_TOKENS: usize = 256;
+
+/// Whether a word is a memory address like `0x7ffd5e8c3a10`
+fn is_address(word: &str) -> bool {
+    let word = word.trim_matches(|c: char| !c.is_ascii_alphanumeric());
+    word.strip_prefix("0x")
+        .is_some_and(|hex| hex.len() >= 6 && hex.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+/// One line without addresses, whitespace collapsed
+fn clean_line(line: &str) -> String {
+    line.split_whitespace().filter(|word| !is_address(word)).collect::<Vec<_>>().join(" ")
+}
+
+/// Elided-frame markers ("... 12 more", "[... 3 frames omitted]")
+fn is_elision(line: &str) -> bool {
+    let line = line.trim_start_matches(['[', '(']);
+    line.starts_with("...") && line.split_whitespace().any(|word| word.chars().all(|c| c.is_ascii_digit()))
+}
+
+/// Condense multi-line input into a query of at most `max_tokens` estimated tokens
+pub fn condense_query(text: &str, max_tokens: usize) -> String {
+    let mut seen = HashSet::new();
+    let mut kept: Vec<String> = Vec::new();
+    let mut tokens = 0;
+
+    for line in text.lines() {
+        let line = clean_line(line);
+        if line.is_empty() || is_elision(&line) || !seen.insert(line.clone()) {
+            continue;
+        }
+        let cost = estimate_tokens(&line);
+        if tokens + cost > max_tokens {
+            if kept.is_empty() {
+                // A single huge line: keep its first words
+                let mut words = Vec::new();
+                for word in line.split_whitespace() {
+                    tokens += estimate_tokens(word);
+                    if tokens > max_tokens {
+                        break;
+                    }
+                    words.push(word);
+                }
+                kept.push(words.join(" "));
+            }
+            break;
+        }
+        tokens += cost;
+        kept.push(line);
+    }
+
+    kept.join("\n")
+}
+
+/// Read the query from stdin and condense it
+pub fn read_stdin_query() -> Result<String> {
+    let mut text = String::new();
+    std::io::stdin().read_to_string(&mut text).context("Failed to read the query from stdin")?;
+    let query = condense_query(&text, MAX_QUERY_TOKENS);
+    if query.is_empty() {
+        bail!("No query on stdin");
+    }
+    Ok(query)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_condense_query() {
+        let trace = "thread 'main' panicked at src/pool.rs:88:14:\n\
+            connection pool exhausted (0x7ffd5e8c3a10)\n\n\
+            \tat Pool.acquire(Pool.java:42)\n\
+            \tat Pool.acquire(Pool.java:42)\n\
+            \t... 12 more\n\
+            \tat Server.handle(Server.java:7)\n";
+        assert_eq!(
+            condense_query(trace, MAX_QUERY_TOKENS),
+            "thread 'main' panicked at src/pool.rs:88:14:\nconnection pool exhausted\n\
+             at Pool.acquire(Pool.java:42)\nat Server.handle(Server.java:7)"
+        );
+
+        // Cut from the bottom; a single long line keeps its first words
+        let frames: String = (0..100).map(|i| format!("frame {} in handler\n", i)).collect();
+        assert_eq!(condense_query(&frames, 20), "frame 0 in handler\nframe 1 in handler");
+        assert_eq!(condense_query(&"word ".repeat(1000), 20), "word word word word word word");
+        assert_eq!(condense_query("  \n\n", 20), "");
+    }
+}
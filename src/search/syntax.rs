@@ -0,0 +1,223 @@
+//! Inline filters in the query string
+//!
+//! `lang:rust kind:fn path:src/ "exact phrase" loose terms` lets agents and
+//! scripts put constraints into the one string parameter they have. The
+//! filters narrow both retrievers: the vector search only scores matching
+//! chunks and the full-text query requires them, so a narrow filter still
+//! fills the result list. Quoted phrases must appear verbatim in full-text
+//! matches and are plain words for the embedding. A `word:word` token with
+//! any other key (`std::io`, `http://`) is left in the query as written.
+
+use anyhow::{bail, Result};
+use std::path::Path;
+
+use super::is_under;
+use crate::chunker::ChunkKind;
+use crate::file::Language;
+use crate::fts::{escape_regex, FtsFilter};
+
+/// Filters parsed from a query; each kind of filter matches any of its values
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct QueryFilters {
+    pub languages: Vec<Language>,
+    pub kinds: Vec<ChunkKind>,
+    /// Path prefixes, `./` optional
+    pub paths: Vec<String>,
+    /// Directory (or file) from `--filter-path`
+    pub under: Option<String>,
+    /// Chunks of an earlier search being refined; IDs are per database
+    pub chunk_ids: Option<Vec<u32>>,
+    /// Tags from `--tag`; a chunk needs one of them
+    pub tags: Vec<String>,
+    /// Tags from `--not-tag`; a chunk may have none of them
+    pub not_tags: Vec<String>,
+    /// Packages from `--package`; a chunk must belong to one of them
+    pub packages: Vec<String>,
+}
+
+impl QueryFilters {
+    pub fn is_empty(&self) -> bool {
+        self.languages.is_empty()
+            && self.kinds.is_empty()
+            && self.paths.is_empty()
+            && self.under.is_none()
+            && self.chunk_ids.is_none()
+            && self.tags.is_empty()
+            && self.not_tags.is_empty()
+            && self.packages.is_empty()
+    }
+
+    /// Whether a chunk with this path, stored kind, tags and package passes every filter but `chunk_ids`
+    pub fn matches(&self, path: &str, kind: &str, tags: &[String], package: Option<&str>) -> bool {
+        let path = path.trim_start_matches("./");
+        let prefixed = |prefix: &String| path.starts_with(prefix.trim_start_matches("./"));
+        (self.languages.is_empty() || self.languages.contains(&Language::from_path(Path::new(path))))
+            && (self.kinds.is_empty() || self.kinds.iter().any(|k| format!("{:?}", k) == kind))
+            && (self.paths.is_empty() || self.paths.iter().any(prefixed))
+            && self.under.as_deref().is_none_or(|dir| is_under(path, dir))
+            && (self.tags.is_empty() || self.tags.iter().any(|tag| tags.contains(tag)))
+            && !self.not_tags.iter().any(|tag| tags.contains(tag))
+            && (self.packages.is_empty() || self.packages.iter().any(|p| Some(p.as_str()) == package))
+    }
+
+    /// The same filters for the full-text index
+    pub fn fts_filter(&self) -> FtsFilter {
+        let mut path_patterns = Vec::new();
+        if !self.paths.is_empty() {
+            let prefixes = self
+                .paths
+                .iter()
+                .map(|prefix| format!(r"(\./)?{}.*", escape_regex(prefix.trim_start_matches("./"))));
+            path_patterns.push(prefixes.collect());
+        }
+        if !self.languages.is_empty() {
+            let extensions: Vec<&str> =
+                self.languages.iter().flat_map(|lang| lang.extensions().iter().copied()).collect();
+            path_patterns.push(vec![format!(r".*\.({})", extensions.join("|"))]);
+        }
+        FtsFilter {
+            kinds: self.kinds.iter().map(|kind| format!("{:?}", kind)).collect(),
+            path_patterns,
+            under: self.under.clone(),
+            chunk_ids: self.chunk_ids.clone(),
+            tags: self.tags.clone(),
+            not_tags: self.not_tags.clone(),
+            packages: self.packages.clone(),
+        }
+    }
+}
+
+/// A query split into what to search for and how to filter
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ParsedQuery {
+    /// Terms and phrase words, for the embedding, classifier and reranker
+    pub text: String,
+    /// Terms and quoted phrases, for the full-text index
+    pub fts: String,
+    pub filters: QueryFilters,
+}
+
+/// Split on whitespace, keeping double-quoted runs (also after `key:`) together
+fn tokenize(query: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+    for c in query.chars() {
+        if c == '"' {
+            in_quotes = !in_quotes;
+            current.push(c);
+        } else if c.is_whitespace() && !in_quotes {
+            if !current.is_empty() {
+                tokens.push(std::mem::take(&mut current));
+            }
+        } else {
+            current.push(c);
+        }
+    }
+    if !current.is_empty() {
+        tokens.push(current);
+    }
+    tokens
+}
+
+impl ParsedQuery {
+    /// Parse `lang:`, `kind:` and `path:` filters and quoted phrases out of a query
+    ///
+    /// Values may be comma-separated (`lang:rust,go`). Fails on an unknown
+    /// language or kind, and on a query that is nothing but filters.
+    pub fn parse(query: &str) -> Result<Self> {
+        let mut parsed = ParsedQuery::default();
+        let mut text = Vec::new();
+        let mut fts = Vec::new();
+
+        for token in tokenize(query) {
+            let filter = token
+                .split_once(':')
+                .map(|(key, value)| (key.to_lowercase(), value.trim_matches('"')))
+                .filter(|(key, value)| {
+                    matches!(key.as_str(), "lang" | "language" | "kind" | "path") && !value.is_empty()
+                });
+            if let Some((key, values)) = filter {
+                for value in values.split(',').filter(|value| !value.is_empty()) {
+                    match key.as_str() {
+                        "kind" => match ChunkKind::from_name(value) {
+                            Some(kind) => parsed.filters.kinds.push(kind),
+                            None => bail!("Unknown kind in {} (try fn, method, class, struct, trait, ...)", token),
+                        },
+                        "path" => parsed.filters.paths.push(value.to_string()),
+                        _ => match Language::from_name(value) {
+                            Some(lang) => parsed.filters.languages.push(lang),
+                            None => bail!("Unknown language in {}", token),
+                        },
+                    }
+                }
+            } else if token.starts_with('"') {
+                let phrase = token.trim_matches('"').trim();
+                if !phrase.is_empty() {
+                    text.push(phrase.to_string());
+                    fts.push(format!("\"{}\"", phrase));
+                }
+            } else {
+                text.push(token.clone());
+                fts.push(token);
+            }
+        }
+
+        if text.is_empty() && !parsed.filters.is_empty() {
+            bail!("The query has filters but nothing to search for");
+        }
+        parsed.text = text.join(" ");
+        parsed.fts = fts.join(" ");
+        Ok(parsed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_query() {
+        let parsed = ParsedQuery::parse(r#"lang:rust kind:fn path:src/ "connection pool" retry std::io"#).unwrap();
+        assert_eq!(parsed.text, "connection pool retry std::io");
+        assert_eq!(parsed.fts, r#""connection pool" retry std::io"#);
+        assert_eq!(parsed.filters.languages, [Language::Rust]);
+        assert_eq!(parsed.filters.kinds, [ChunkKind::Function]);
+        assert_eq!(parsed.filters.paths, ["src/"]);
+
+        assert!(parsed.filters.matches("./src/pool.rs", "Function", &[], None));
+        assert!(!parsed.filters.matches("./src/pool.py", "Function", &[], None));
+        assert!(!parsed.filters.matches("./src/pool.rs", "Struct", &[], None));
+        assert!(!parsed.filters.matches("./tests/pool.rs", "Function", &[], None));
+
+        // --tag needs one of its tags, --not-tag none of its tags
+        let filters = QueryFilters {
+            tags: vec!["api".to_string()],
+            not_tags: vec!["generated".to_string()],
+            ..Default::default()
+        };
+        let tags = |tags: &[&str]| tags.iter().map(|tag| tag.to_string()).collect::<Vec<_>>();
+        assert!(filters.matches("src/api/user.rs", "Function", &tags(&["api", "tests"]), None));
+        assert!(!filters.matches("src/api/user.pb.go", "Function", &tags(&["api", "generated"]), None));
+        assert!(!filters.matches("src/db.rs", "Function", &[], None));
+
+        // --package needs the chunk's package to be one of them
+        let filters = QueryFilters { packages: vec!["core".to_string(), "web".to_string()], ..Default::default() };
+        assert!(filters.matches("crates/core/src/pool.rs", "Function", &[], Some("core")));
+        assert!(!filters.matches("crates/cli/src/main.rs", "Function", &[], Some("cli")));
+        assert!(!filters.matches("build.rs", "Function", &[], None));
+
+        let fts = parsed.filters.fts_filter();
+        assert_eq!(fts.kinds, ["Function"]);
+        assert_eq!(fts.path_patterns, [vec![r"(\./)?src/.*".to_string()], vec![r".*\.(rs)".to_string()]]);
+
+        // Several values, quoted values, plain queries
+        let parsed = ParsedQuery::parse(r#"lang:py,go path:"my dir/" handler"#).unwrap();
+        assert_eq!(parsed.filters.languages, [Language::Python, Language::Go]);
+        assert_eq!(parsed.filters.paths, ["my dir/"]);
+        assert_eq!(ParsedQuery::parse("where is auth handled").unwrap().fts, "where is auth handled");
+
+        assert!(ParsedQuery::parse("lang:klingon parser").is_err());
+        assert!(ParsedQuery::parse("kind:fn").is_err());
+    }
+}
@@ -0,0 +1,119 @@
+//! Post-search result transformer (`search.transform_command`)
+//!
+//! An escape hatch for org-specific ranking rules: the configured command
+//! receives `{"query": ..., "results": [...]}` as JSON on stdin and prints
+//! the results to keep, in order, either as a bare array or in the same
+//! envelope. It can drop, reorder or enrich results (for example by adding
+//! `notes`); fields it leaves out of a result take their defaults.
+
+use anyhow::{anyhow, bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use crate::vectordb::SearchResult;
+
+#[derive(Serialize)]
+struct TransformInput<'a> {
+    query: &'a str,
+    results: &'a [SearchResult],
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum TransformOutput {
+    Results(Vec<SearchResult>),
+    Envelope { results: Vec<SearchResult> },
+}
+
+/// Shell invocation for a command line
+fn shell(command: &str) -> Command {
+    if cfg!(windows) {
+        let mut cmd = Command::new("cmd");
+        cmd.args(["/C", command]);
+        cmd
+    } else {
+        let mut cmd = Command::new("sh");
+        cmd.args(["-c", command]);
+        cmd
+    }
+}
+
+/// Pass results through the transform command and return what it prints
+pub fn transform_results(command: &str, query: &str, results: Vec<SearchResult>) -> Result<Vec<SearchResult>> {
+    let input = serde_json::to_vec(&TransformInput {
+        query,
+        results: &results,
+    })?;
+
+    let mut child = shell(command)
+        .env("DEMONGREP_QUERY", query)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit())
+        .spawn()
+        .with_context(|| format!("Could not run transform command '{}'", command))?;
+
+    // Write from another thread so a command that prints before reading
+    // all of its input cannot deadlock against us
+    let mut stdin = child.stdin.take().ok_or_else(|| anyhow!("Transform command has no stdin"))?;
+    let writer = std::thread::spawn(move || stdin.write_all(&input));
+
+    let output = child.wait_with_output()?;
+    // A command that ignores its input closes the pipe early; that is fine
+    let _ = writer.join();
+
+    if !output.status.success() {
+        bail!("Transform command '{}' failed with {}", command, output.status);
+    }
+    parse_output(&output.stdout)
+}
+
+fn parse_output(stdout: &[u8]) -> Result<Vec<SearchResult>> {
+    let output: TransformOutput =
+        serde_json::from_slice(stdout).context("Transform command did not print a JSON result list")?;
+    Ok(match output {
+        TransformOutput::Results(results) | TransformOutput::Envelope { results } => results,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_output() {
+        let bare = parse_output(br#"[{"path": "src/a.rs", "start_line": 3, "notes": ["owned by auth team"]}]"#).unwrap();
+        assert_eq!(bare.len(), 1);
+        assert_eq!(bare[0].path, "src/a.rs");
+        assert_eq!(bare[0].start_line, 3);
+        assert_eq!(bare[0].notes, vec!["owned by auth team"]);
+
+        let envelope = parse_output(br#"{"query": "q", "results": []}"#).unwrap();
+        assert!(envelope.is_empty());
+
+        assert!(parse_output(b"not json").is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_transform_results() {
+        let results = vec![
+            SearchResult {
+                path: "vendor/lib.rs".to_string(),
+                ..Default::default()
+            },
+            SearchResult {
+                path: "src/lib.rs".to_string(),
+                ..Default::default()
+            },
+        ];
+
+        // Round-trips unchanged through cat
+        let same = transform_results("cat", "query", results.clone()).unwrap();
+        assert_eq!(same.len(), 2);
+        assert_eq!(same[1].path, "src/lib.rs");
+
+        assert!(transform_results("exit 3", "query", results).is_err());
+    }
+}
@@ -0,0 +1,356 @@
+//! Search result watch mode (`demongrep search --watch`)
+//!
+//! Keeps the embedding model and query embedding in memory, re-runs the
+//! query whenever the index on disk changes (for example when `demongrep
+//! serve` reindexes a file you just saved, or after `demongrep index`) or
+//! every `--interval` seconds, and prints how the results moved since the
+//! previous run.
+
+use anyhow::Result;
+use colored::Colorize;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime};
+
+use super::{
+    classify_query, dedup_results, depth_for, read_metadata, resolve_fused, retain_under, retrieve, ParsedQuery,
+    Pipeline,
+};
+use crate::config::Config;
+use crate::embed::{EmbeddingService, ModelType};
+use crate::fts::FtsStore;
+use crate::index::get_search_db_paths;
+use crate::rerank::{
+    apply_kind_weights, apply_path_weights, apply_project_preference, rerank_results, Fusion, NeuralReranker,
+    PathWeights,
+};
+use crate::vectordb::{SearchResult, StoreOptions, VectorStore};
+
+/// How often the index files are checked for changes
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Identity of a result across runs
+///
+/// Line numbers shift as code above a chunk is edited, so a chunk is
+/// identified by its file and signature (or first line) instead.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ResultKey {
+    pub path: String,
+    pub label: String,
+}
+
+impl ResultKey {
+    pub fn of(result: &SearchResult) -> Self {
+        let label = result.signature.clone().unwrap_or_else(|| {
+            result
+                .content
+                .lines()
+                .map(str::trim)
+                .find(|line| !line.is_empty())
+                .unwrap_or_default()
+                .to_string()
+        });
+        Self {
+            path: result.path.clone(),
+            label,
+        }
+    }
+}
+
+/// How one result changed between two runs (ranks are 1-based)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResultChange {
+    /// New in the results at `rank`
+    Added { rank: usize },
+    /// Was at `from`, now at `to`
+    Moved { from: usize, to: usize },
+    /// Was at `rank`, no longer in the results
+    Removed { rank: usize },
+}
+
+/// Compare two rankings: entries for the new ranking in order, then removals
+pub fn diff_results(old: &[ResultKey], new: &[ResultKey]) -> Vec<ResultChange> {
+    let old_ranks: HashMap<&ResultKey, usize> = old.iter().enumerate().map(|(i, k)| (k, i + 1)).collect();
+    let new_ranks: HashMap<&ResultKey, usize> = new.iter().enumerate().map(|(i, k)| (k, i + 1)).collect();
+
+    let mut changes: Vec<ResultChange> = new
+        .iter()
+        .enumerate()
+        .filter_map(|(i, key)| match old_ranks.get(key) {
+            None => Some(ResultChange::Added { rank: i + 1 }),
+            Some(&from) if from != i + 1 => Some(ResultChange::Moved { from, to: i + 1 }),
+            Some(_) => None,
+        })
+        .collect();
+
+    changes.extend(
+        old.iter()
+            .enumerate()
+            .filter(|(_, key)| !new_ranks.contains_key(key))
+            .map(|(i, _)| ResultChange::Removed { rank: i + 1 }),
+    );
+
+    changes
+}
+
+/// Modification time and size of every file under the databases
+///
+/// Any write to the vector store, FTS index or metadata changes this.
+fn index_fingerprint(db_paths: &[PathBuf]) -> Vec<(PathBuf, Option<SystemTime>, u64)> {
+    let mut files: Vec<(PathBuf, Option<SystemTime>, u64)> = db_paths
+        .iter()
+        .flat_map(|db_path| walkdir::WalkDir::new(db_path).into_iter().flatten())
+        .filter(|entry| entry.file_type().is_file())
+        .filter_map(|entry| {
+            let metadata = entry.metadata().ok()?;
+            Some((entry.into_path(), metadata.modified().ok(), metadata.len()))
+        })
+        .collect();
+    files.sort();
+    files
+}
+
+/// Run a query repeatedly, printing result changes until interrupted
+#[allow(clippy::too_many_arguments)]
+pub async fn watch_search(
+    query: &str,
+    max_results: usize,
+    path: Option<PathBuf>,
+    filter_path: Option<String>,
+    model_override: Option<ModelType>,
+    vector_only_mode: bool,
+    auto: bool,
+    retrieval_depth: Option<usize>,
+    fusion: Fusion,
+    rerank: bool,
+    rerank_top: usize,
+    prefer_project: bool,
+    interval: Option<Duration>,
+    config: &Config,
+) -> Result<()> {
+    let db_paths = get_search_db_paths(path)?;
+    if db_paths.is_empty() {
+        println!("{}", "❌ No database found!".red());
+        println!(
+            "   Run {} or {} first",
+            "demongrep index".bright_cyan(),
+            "demongrep index --global".bright_cyan()
+        );
+        return Ok(());
+    }
+
+    let mut parsed = ParsedQuery::parse(query)?;
+    parsed.filters.under = filter_path.clone();
+    let full_query = query;
+    let query = parsed.text.as_str();
+    let model_type = model_override
+        .or_else(|| read_metadata(&db_paths[0]).and_then(|(name, _)| ModelType::from_str(&name)))
+        .unwrap_or_default();
+    let pipeline = if auto {
+        classify_query(query).pipeline
+    } else if vector_only_mode {
+        Pipeline::Semantic
+    } else {
+        Pipeline::Hybrid
+    };
+
+    // Load models once; only the index is re-read on each run
+    let mut embedding_service = EmbeddingService::with_model(model_type)?;
+    let query_embedding = embedding_service.embed_query(query)?;
+    let mut reranker = if rerank {
+        match NeuralReranker::new() {
+            Ok(reranker) => Some(reranker),
+            Err(e) => {
+                eprintln!("{}", format!("⚠️  Could not load reranker: {}", e).yellow());
+                None
+            }
+        }
+    } else {
+        None
+    };
+
+    let path_weights = PathWeights::new(&config.search.path_weights);
+    let run_query = |reranker: &mut Option<NeuralReranker>| -> Result<Vec<SearchResult>> {
+        let mut all_results = Vec::new();
+        for db_path in &db_paths {
+            let store = VectorStore::with_options(db_path, model_type.dimensions(), &StoreOptions::from_config(config))?;
+            let wanted = if reranker.is_some() { rerank_top.max(max_results) } else { max_results };
+            let depth = depth_for(pipeline, retrieval_depth, max_results, wanted, store.chunk_count()?);
+            let fts_store = if pipeline == Pipeline::Semantic {
+                None
+            } else {
+                FtsStore::open_readonly(db_path).ok()
+            };
+            let (vector_results, fused_results) = retrieve(
+                &store,
+                fts_store.as_ref(),
+                &parsed.fts,
+                &query_embedding,
+                pipeline,
+                depth,
+                fusion,
+                &parsed.filters,
+            )?;
+            all_results.extend(resolve_fused(&store, &vector_results, &fused_results, wanted));
+        }
+
+        let mut results = dedup_results(all_results);
+        apply_path_weights(&mut results, &path_weights);
+        apply_kind_weights(&mut results, query, config.search.imports_weight, config.search.module_docs_boost);
+        if prefer_project {
+            apply_project_preference(&mut results);
+        }
+        if let Some(reranker) = reranker.as_mut() {
+            results = rerank_results(reranker, query, results)?;
+        }
+        if let Some(ref filter) = filter_path {
+            retain_under(&mut results, filter);
+        }
+        results.truncate(max_results);
+        Ok(results)
+    };
+
+    println!(
+        "{} {} {}",
+        "👀 Watching results for".bright_cyan().bold(),
+        format!("\"{}\"", full_query).bright_yellow(),
+        "(Ctrl+C to stop)".dimmed()
+    );
+    match interval {
+        Some(interval) => println!(
+            "{}",
+            format!("   Re-running when the index changes and every {}s", interval.as_secs()).dimmed()
+        ),
+        None => println!("{}", "   Re-running when the index changes".dimmed()),
+    }
+
+    let mut previous: Option<Vec<SearchResult>> = None;
+    let mut fingerprint = index_fingerprint(&db_paths);
+    let mut reason = "initial";
+
+    loop {
+        let last_run = Instant::now();
+        match run_query(&mut reranker) {
+            Ok(results) => {
+                print_run(reason, previous.as_deref(), &results);
+                previous = Some(results);
+            }
+            // The index may be mid-write; the next change triggers a retry
+            Err(e) => eprintln!("{}", format!("⚠️  Search failed: {}", e).yellow()),
+        }
+
+        // Wait for the index to change (and settle) or the interval to pass
+        reason = loop {
+            tokio::select! {
+                _ = tokio::time::sleep(POLL_INTERVAL) => {}
+                _ = tokio::signal::ctrl_c() => return Ok(()),
+            }
+
+            let current = index_fingerprint(&db_paths);
+            if current != fingerprint {
+                // Let a reindex finish writing before searching
+                fingerprint = current;
+                loop {
+                    tokio::time::sleep(POLL_INTERVAL).await;
+                    let settled = index_fingerprint(&db_paths);
+                    if settled == fingerprint {
+                        break;
+                    }
+                    fingerprint = settled;
+                }
+                break "index changed";
+            }
+            if interval.is_some_and(|interval| last_run.elapsed() >= interval) {
+                break "interval";
+            }
+        };
+    }
+}
+
+/// Location and label of a result for one output line
+fn describe(result: &SearchResult) -> String {
+    format!(
+        "{}:{}-{}  {}",
+        result.path,
+        result.start_line,
+        result.end_line,
+        ResultKey::of(result).label.dimmed()
+    )
+}
+
+fn print_run(reason: &str, previous: Option<&[SearchResult]>, results: &[SearchResult]) {
+    println!(
+        "\n{}",
+        format!("── {} · {} ──", chrono::Local::now().format("%H:%M:%S"), reason).bright_cyan()
+    );
+
+    let Some(previous) = previous else {
+        if results.is_empty() {
+            println!("{}", "   No matches yet".dimmed());
+        }
+        for (i, result) in results.iter().enumerate() {
+            println!("   {:>3}. {}", i + 1, describe(result));
+        }
+        return;
+    };
+
+    let old_keys: Vec<ResultKey> = previous.iter().map(ResultKey::of).collect();
+    let new_keys: Vec<ResultKey> = results.iter().map(ResultKey::of).collect();
+    let changes = diff_results(&old_keys, &new_keys);
+    if changes.is_empty() {
+        println!("{}", "   No changes".dimmed());
+        return;
+    }
+
+    for change in changes {
+        match change {
+            ResultChange::Added { rank } => {
+                println!("   {} {:>3}. {}", "+".green().bold(), rank, describe(&results[rank - 1]))
+            }
+            ResultChange::Moved { from, to } => {
+                let arrow = if to < from { "↑".green() } else { "↓".yellow() };
+                println!(
+                    "   {} {:>3}. {} {}",
+                    arrow,
+                    to,
+                    describe(&results[to - 1]),
+                    format!("(was #{})", from).dimmed()
+                )
+            }
+            ResultChange::Removed { rank } => println!(
+                "   {} {} {}",
+                "-".red().bold(),
+                describe(&previous[rank - 1]),
+                format!("(was #{})", rank).dimmed()
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(label: &str) -> ResultKey {
+        ResultKey {
+            path: "src/lib.rs".to_string(),
+            label: label.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_diff_results() {
+        let old = [key("a"), key("b"), key("c")];
+        let new = [key("b"), key("d"), key("c")];
+
+        assert_eq!(
+            diff_results(&old, &new),
+            vec![
+                ResultChange::Moved { from: 2, to: 1 },
+                ResultChange::Added { rank: 2 },
+                ResultChange::Removed { rank: 1 },
+            ]
+        );
+        assert!(diff_results(&new, &new).is_empty());
+    }
+}
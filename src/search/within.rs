@@ -0,0 +1,119 @@
+//! Search inside a single file (`demongrep within <file> "query"`)
+//!
+//! A semantic Ctrl-F for files too large to skim. Every chunk of the file is
+//! scored against the query exactly instead of through the approximate
+//! index, so nothing in the file is missed, and the full-text side is
+//! restricted to the file with a path term. The two rankings are fused with
+//! RRF as in a normal hybrid search.
+
+use anyhow::{bail, Result};
+use colored::Colorize;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use super::{print_result, resolve_fused, JsonOutput, JsonResult, CONTENT_LINES};
+use crate::config::Config;
+use crate::database::DatabaseManager;
+use crate::embed::EmbeddingService;
+use crate::fts::FtsStore;
+use crate::rerank::vector_only;
+use crate::vectordb::StoreOptions;
+
+/// Path as given on the command line, relative to the working directory when possible
+fn relative_to_cwd(file: &Path) -> String {
+    let relative = std::env::current_dir()
+        .ok()
+        .and_then(|cwd| cwd.canonicalize().ok())
+        .and_then(|cwd| file.canonicalize().ok()?.strip_prefix(cwd).ok().map(Path::to_path_buf))
+        .unwrap_or_else(|| file.to_path_buf());
+    relative.to_string_lossy().to_string()
+}
+
+/// Search one file's chunks and print the best matches
+#[allow(clippy::too_many_arguments)]
+pub async fn within(
+    file: PathBuf,
+    query: &str,
+    max_results: usize,
+    content: bool,
+    scores: bool,
+    json: bool,
+    path: Option<PathBuf>,
+    config: &Config,
+) -> Result<()> {
+    if json {
+        crate::output::set_quiet(true);
+    }
+
+    let db_manager = DatabaseManager::load_with_options(path, &StoreOptions::from_config(config))?;
+    let wanted = relative_to_cwd(&file);
+    let given = file.to_string_lossy().to_string();
+
+    // The first database (local before global) that indexed the file
+    let mut found = None;
+    for database in db_manager.databases() {
+        for candidate in [wanted.as_str(), given.as_str()] {
+            if let Some(indexed) = database.store().indexed_path(candidate)? {
+                found = Some((database, indexed));
+                break;
+            }
+        }
+        if found.is_some() {
+            break;
+        }
+    }
+    let Some((database, indexed_path)) = found else {
+        bail!(
+            "{} is not in the index. Run `demongrep index` if the file is new, or check that it is not excluded",
+            file.display()
+        );
+    };
+
+    let store = database.store();
+    let chunk_ids = store.file_chunk_ids(&indexed_path)?;
+
+    let start = Instant::now();
+    let mut embedding_service = EmbeddingService::with_model(db_manager.model_type())?;
+    let query_embedding = embedding_service.embed_query(query)?;
+
+    // Exact vector scores for every chunk of the file, plus BM25 within the file
+    let vector_results = store.score_chunks(&query_embedding, &chunk_ids)?;
+    let fused = match FtsStore::open_readonly(&database.path) {
+        Ok(fts_store) => {
+            let fts_results = fts_store.search_in_path(query, &indexed_path, chunk_ids.len().max(1))?;
+            config.search.hybrid_fusion().fuse(&vector_results, &fts_results)
+        }
+        Err(_) => vector_only(&vector_results),
+    };
+    let results = resolve_fused(store, &vector_results, &fused, max_results);
+    let elapsed = start.elapsed();
+
+    if json {
+        let output = JsonOutput {
+            query: query.to_string(),
+            results: results.iter().map(|r| JsonResult::new(r, None)).collect(),
+            timing: None,
+        };
+        println!("{}", serde_json::to_string(&output)?);
+        return Ok(());
+    }
+
+    println!("{}", format!("🔍 {}", indexed_path).bright_cyan().bold());
+    println!("{}", "=".repeat(60));
+    println!("Query: \"{}\"", query.bright_yellow());
+    println!(
+        "{}",
+        format!("{} of {} chunks matched in {:?}", results.len(), chunk_ids.len(), elapsed).dimmed()
+    );
+    println!();
+
+    if results.is_empty() {
+        println!("{}", "No matches found.".dimmed());
+        return Ok(());
+    }
+    for result in &results {
+        print_result(result, false, content.then_some(CONTENT_LINES), scores, None, None)?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,95 @@
+//! Where a running `serve` can be reached (`search --remote`)
+//!
+//! `serve` writes `server.json` into the database directory it watches, with
+//! its pid and URL, and removes it on exit. `search --remote` without a URL
+//! looks for the file in the databases it would search and sends the query
+//! to that server. A file left behind by a server that crashed names a pid
+//! that no longer runs and is ignored.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::index::is_process_running;
+
+/// Name of the address file inside a database directory
+pub const ADDRESS_FILE: &str = "server.json";
+
+/// Contents of an address file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ServerAddress {
+    pub pid: u32,
+    /// Base URL clients connect to, e.g. "http://127.0.0.1:4444"
+    pub url: String,
+}
+
+impl ServerAddress {
+    /// Announce that this process serves the database at `db_path` on `url`
+    pub fn publish(db_path: &Path, url: &str) -> Result<AddressFile> {
+        let path = db_path.join(ADDRESS_FILE);
+        let address = Self { pid: std::process::id(), url: url.to_string() };
+        std::fs::write(&path, serde_json::to_string(&address)?)?;
+        Ok(AddressFile { path })
+    }
+
+    /// The live server announced in the database at `db_path`, if any
+    pub fn find(db_path: &Path) -> Option<Self> {
+        let content = std::fs::read_to_string(db_path.join(ADDRESS_FILE)).ok()?;
+        let address: Self = serde_json::from_str(&content).ok()?;
+        is_process_running(address.pid).then_some(address)
+    }
+}
+
+/// This process's address file, removed on drop
+#[derive(Debug)]
+pub struct AddressFile {
+    path: PathBuf,
+}
+
+impl Drop for AddressFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// URL a client on this machine connects to for a server bound to `host`
+///
+/// Wildcard addresses accept connections but can't be connected to, so
+/// they are replaced with the loopback address.
+pub fn local_url(host: &str, port: u16, tls: bool) -> String {
+    let scheme = if tls { "https" } else { "http" };
+    let host = match host {
+        "0.0.0.0" => "127.0.0.1",
+        "::" | "[::]" => "[::1]",
+        host if host.contains(':') && !host.starts_with('[') => return format!("{}://[{}]:{}", scheme, host, port),
+        host => host,
+    };
+    format!("{}://{}:{}", scheme, host, port)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_publish_and_find() {
+        let dir = tempdir().unwrap();
+        assert_eq!(ServerAddress::find(dir.path()), None);
+
+        let file = ServerAddress::publish(dir.path(), "http://127.0.0.1:4444").unwrap();
+        let found = ServerAddress::find(dir.path()).unwrap();
+        assert_eq!((found.pid, found.url.as_str()), (std::process::id(), "http://127.0.0.1:4444"));
+
+        drop(file);
+        assert!(!dir.path().join(ADDRESS_FILE).exists());
+    }
+
+    #[test]
+    fn test_local_url() {
+        assert_eq!(local_url("0.0.0.0", 4444, false), "http://127.0.0.1:4444");
+        assert_eq!(local_url("::", 4444, true), "https://[::1]:4444");
+        assert_eq!(local_url("::1", 4444, false), "http://[::1]:4444");
+        assert_eq!(local_url("localhost", 80, false), "http://localhost:80");
+    }
+}
@@ -0,0 +1,99 @@
+//! Per-client rate limiting for `demongrep serve`
+//!
+//! Every search embeds its query while holding the shared embedding service,
+//! so one runaway client can starve all the others. Each client IP gets a
+//! token bucket: `burst` requests up front, refilled at `per_minute`. The API
+//! key is shared by the whole team, so the IP address is what tells clients
+//! apart.
+
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Buckets kept before full (idle) ones are dropped
+const MAX_TRACKED_CLIENTS: usize = 10_000;
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+/// Token bucket rate limiter keyed by client IP
+pub struct RateLimiter {
+    per_sec: f64,
+    burst: f64,
+    buckets: Mutex<HashMap<IpAddr, Bucket>>,
+}
+
+impl RateLimiter {
+    /// Limiter allowing `per_minute` requests per client (None when 0 = unlimited)
+    pub fn new(per_minute: u32, burst: u32) -> Option<Self> {
+        (per_minute > 0).then(|| Self {
+            per_sec: per_minute as f64 / 60.0,
+            burst: burst.max(1) as f64,
+            buckets: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Take one request from the client's bucket
+    ///
+    /// Returns how long to wait before retrying when the bucket is empty.
+    pub fn check(&self, client: IpAddr) -> Result<(), Duration> {
+        self.check_at(client, Instant::now())
+    }
+
+    fn check_at(&self, client: IpAddr, now: Instant) -> Result<(), Duration> {
+        let mut buckets = self.buckets.lock().unwrap();
+        if buckets.len() >= MAX_TRACKED_CLIENTS && !buckets.contains_key(&client) {
+            let (per_sec, burst) = (self.per_sec, self.burst);
+            buckets.retain(|_, bucket| refilled(bucket, now, per_sec) < burst);
+        }
+
+        let bucket = buckets.entry(client).or_insert(Bucket {
+            tokens: self.burst,
+            updated: now,
+        });
+        bucket.tokens = refilled(bucket, now, self.per_sec).min(self.burst);
+        bucket.updated = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - bucket.tokens) / self.per_sec))
+        }
+    }
+}
+
+/// Tokens in a bucket at `now`, before capping at the burst size
+fn refilled(bucket: &Bucket, now: Instant, per_sec: f64) -> f64 {
+    bucket.tokens + now.saturating_duration_since(bucket.updated).as_secs_f64() * per_sec
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate_limiter() {
+        assert!(RateLimiter::new(0, 10).is_none());
+
+        let limiter = RateLimiter::new(60, 2).unwrap();
+        let a: IpAddr = "10.0.0.1".parse().unwrap();
+        let b: IpAddr = "10.0.0.2".parse().unwrap();
+        let start = Instant::now();
+
+        assert!(limiter.check_at(a, start).is_ok());
+        assert!(limiter.check_at(a, start).is_ok());
+        let retry = limiter.check_at(a, start).unwrap_err();
+        assert_eq!(retry.as_secs(), 1);
+
+        // Other clients have their own bucket
+        assert!(limiter.check_at(b, start).is_ok());
+
+        // One request per second refills
+        assert!(limiter.check_at(a, start + Duration::from_secs(1)).is_ok());
+        assert!(limiter.check_at(a, start + Duration::from_secs(1)).is_err());
+    }
+}
@@ -2,6 +2,9 @@
 
 mod store;
 
-pub use store::{SearchResult, StoreStats, VectorStore};
+pub use store::{
+    rebase_path, ChunkFilter, ChunkMetadata, ChunkNote, CommitRecord, FileVector, SearchResult, StoreOptions,
+    StoreStats, VectorStore, DEFAULT_EXACT_THRESHOLD, DEFAULT_SEARCH_QUALITY, MAX_SHARDS,
+};
 
 // Re-export for advanced usage
@@ -1,4 +1,6 @@
-use crate::embed::EmbeddedChunk;
+use crate::chunker::{parent_indices, simhash, Chunk};
+use crate::config::Config;
+use crate::embed::{count_tokens, embedding_text, estimate_tokens, EmbeddedChunk};
 use crate::info_print;
 use anyhow::{anyhow, Result};
 use arroy::distances::Cosine;
@@ -10,12 +12,119 @@ use rand::rngs::StdRng;
 use rand::SeedableRng;
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::num::NonZeroUsize;
+use rayon::prelude::*;
 use std::path::{Path, PathBuf};
 use std::time::SystemTime;
 
+/// Filtered searches score up to this many matching chunks exactly
+const MAX_EXACT_CANDIDATES: usize = 50_000;
+
+/// Stores with at most this many chunks are searched exactly unless configured otherwise
+pub const DEFAULT_EXACT_THRESHOLD: usize = 10_000;
+
+/// Candidates the approximate search explores per result and tree unless configured otherwise
+pub const DEFAULT_SEARCH_QUALITY: usize = 15;
+
+/// Key of the shard count in the layout database
+const SHARDS_KEY: &str = "shards";
+
+/// Most shards a vector store can be split into
+pub const MAX_SHARDS: usize = 256;
+
+/// How a store is built and searched (`indexing` and `search` config)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StoreOptions {
+    /// Shards a new store is split into (`indexing.shards`, `index --shards`)
+    ///
+    /// Each shard is built and searched on its own, so rebuild time and
+    /// memory are bounded by the shard rather than the whole store. Existing
+    /// stores keep the shard count they were created with.
+    pub shards: usize,
+    /// Trees `build_index` builds (`indexing.trees`, `index --trees`); None lets arroy choose by index size
+    ///
+    /// More trees raise recall at the cost of build time, memory and disk.
+    pub trees: Option<usize>,
+    /// Multiplier of `limit * n_trees` for the approximate search's `search_k` (`search.search_quality`)
+    ///
+    /// Higher values raise recall at the cost of search time.
+    pub search_quality: usize,
+    /// Chunk count up to which `search` scans every vector (`search.exact_threshold`; 0 never, `usize::MAX` always)
+    pub exact_threshold: usize,
+}
+
+impl Default for StoreOptions {
+    fn default() -> Self {
+        Self {
+            shards: 1,
+            trees: None,
+            search_quality: DEFAULT_SEARCH_QUALITY,
+            exact_threshold: DEFAULT_EXACT_THRESHOLD,
+        }
+    }
+}
+
+impl StoreOptions {
+    /// Options as the `indexing` and `search` config (with CLI overrides applied) set them
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            shards: config.indexing.shards,
+            trees: config.indexing.trees,
+            search_quality: config.search.search_quality,
+            exact_threshold: config.search.exact_threshold,
+        }
+    }
+}
+
+/// Shard a chunk of `path` is put in when it is inserted
+///
+/// By path hash, so the chunks of a file stay together. The shard is
+/// recorded per chunk (`VectorStore::chunk_shards`) rather than recomputed,
+/// as paths change when a store is moved to another checkout.
+fn shard_of(path: &str, shards: u16) -> u16 {
+    if shards <= 1 {
+        return 0;
+    }
+    let digest = Sha256::digest(path.as_bytes());
+    let hash = u64::from_le_bytes(digest[..8].try_into().expect("digest has 32 bytes"));
+    (hash % u64::from(shards)) as u16
+}
+
+/// `path` moved from under the directory `from` to under `to`, or None if it isn't under `from`
+///
+/// `from` only matches whole path components, so `/work/app` does not
+/// rebase `/work/app2/main.rs`.
+pub fn rebase_path(path: &str, from: &str, to: &str) -> Option<String> {
+    let rest = path.strip_prefix(from)?;
+    let under = rest.is_empty()
+        || from.ends_with(['/', '\\'])
+        || rest.starts_with(['/', '\\']);
+    under.then(|| format!("{}{}", to, rest))
+}
+
+/// Vector changes `build_index` keeps beside a built index before rebuilding it: at least this many...
+const MIN_DELTA: usize = 2_000;
+
+/// ...or one in this many of the indexed vectors, whichever is more
+const DELTA_DIVISOR: usize = 20;
+
+/// Beyond that, the approximate search fetches this many times the limit before filtering
+const FILTERED_OVERFETCH: usize = 8;
+
+/// Whether `search_filtered` keeps a chunk, given its path, kind, tags and package
+pub type ChunkFilter<'a> = dyn Fn(&str, &str, &[String], Option<&str>) -> bool + 'a;
+
+/// Version of the stored record layout (`DbMetadata::version`)
+///
+/// Records are bincode, which can't skip or default fields, so adding a
+/// field to `ChunkMetadata` makes older stores unreadable: bump this with
+/// it, and `VectorStore::new` asks for a reindex instead of failing to
+/// decode. Version 1 is the layout before chunk sizes were recorded.
+pub const FORMAT_VERSION: u32 = 2;
+
 /// Chunk metadata stored in the database
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ChunkMetadata {
     pub content: String,
     pub path: String,
@@ -32,6 +141,22 @@ pub struct ChunkMetadata {
     /// Lines of code immediately after this chunk (for context)
     #[serde(default)]
     pub context_next: Option<String>,
+    /// Characters in `content`
+    pub char_count: usize,
+    /// Estimated tokens in the text that was embedded (see `estimate_tokens`)
+    pub token_count: usize,
+    /// Modification time of the file when the chunk was indexed (Unix seconds, 0 if unknown)
+    pub modified: u64,
+    /// Near-duplicate fingerprint of `content` (see `chunker::simhash`, 0 if unknown)
+    pub simhash: u64,
+    /// LLM prompt tokens in `content` (see `count_tokens`, 0 if unknown)
+    pub prompt_tokens: usize,
+    /// Tags from `indexing.tags` (see `PathTags`)
+    pub tags: Vec<String>,
+    /// Package the file belongs to (see `PackageFinder`)
+    pub package: Option<String>,
+    /// ID of the enclosing definition's chunk: a method's impl block or class (see `parent_indices`)
+    pub parent_id: Option<u32>,
 }
 
 /// File metadata for incremental indexing
@@ -49,6 +174,40 @@ pub struct FileMeta {
     pub chunk_ids: Vec<u32>,
 }
 
+/// Embedding of a file's summary (see `index::file_summary`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileVector {
+    /// `FileMeta::hash` of the file when it was summarized
+    pub hash: String,
+    pub embedding: Vec<f32>,
+}
+
+/// An embedded commit message (see `index::history`)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CommitRecord {
+    pub hash: String,
+    pub author: String,
+    /// Unix timestamp of the commit
+    pub timestamp: u64,
+    /// Full message, subject first
+    pub message: String,
+    /// Files the commit touched
+    pub files: Vec<String>,
+    pub embedding: Vec<f32>,
+}
+
+/// Team note attached to a chunk with `demongrep note`
+///
+/// Notes are keyed by chunk content hash, so they survive reindexing and
+/// moving code around, but not editing the chunk itself.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChunkNote {
+    pub text: String,
+    pub author: Option<String>,
+    /// Unix timestamp
+    pub created_at: u64,
+}
+
 /// Database metadata for model tracking
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DbMetadata {
@@ -63,7 +222,7 @@ pub struct DbMetadata {
 }
 
 impl ChunkMetadata {
-    fn from_embedded_chunk(chunk: &EmbeddedChunk) -> Self {
+    fn from_embedded_chunk(chunk: &EmbeddedChunk, modified: u64, parent_id: Option<u32>) -> Self {
         Self {
             content: chunk.chunk.content.clone(),
             path: chunk.chunk.path.clone(),
@@ -80,14 +239,38 @@ impl ChunkMetadata {
             hash: chunk.chunk.hash.clone(),
             context_prev: chunk.chunk.context_prev.clone(),
             context_next: chunk.chunk.context_next.clone(),
+            char_count: chunk.chunk.content.chars().count(),
+            token_count: estimate_tokens(&embedding_text(&chunk.chunk)),
+            modified,
+            simhash: simhash(&chunk.chunk.content),
+            prompt_tokens: count_tokens(&chunk.chunk.content),
+            tags: chunk.chunk.tags.clone(),
+            package: chunk.chunk.package.clone(),
+            parent_id,
         }
     }
 }
 
+/// Modification times of the files chunks come from, looked up once per file
+#[derive(Default)]
+struct FileTimes(HashMap<String, u64>);
+
+impl FileTimes {
+    fn get(&mut self, path: &str) -> u64 {
+        *self
+            .0
+            .entry(path.to_string())
+            .or_insert_with(|| VectorStore::get_file_mtime(Path::new(path)).unwrap_or(0))
+    }
+}
+
 /// Vector database using arroy + heed (LMDB)
 ///
 /// Single-file database with:
-/// - Vector search via arroy (ANN with random projections)
+/// - Vector search via arroy (ANN with random projections), split into
+///   shards by path hash that are built and searched independently
+/// - A delta segment of vectors changed since the arroy index was built,
+///   searched exactly beside it until `build_index` folds it in
 /// - Metadata storage via heed (LMDB)
 /// - File metadata for incremental indexing
 /// - ACID transactions
@@ -97,19 +280,43 @@ pub struct VectorStore {
     vectors: ArroyDatabase<Cosine>,
     chunks: Database<U32<BigEndian>, SerdeBincode<ChunkMetadata>>,
     file_metadata: Database<Str, SerdeBincode<FileMeta>>,
+    /// File summary embeddings by path, for coarse-to-fine search
+    file_vectors: Database<Str, SerdeBincode<FileVector>>,
+    /// Commit messages by hash, searched apart from code (`search --in history`)
+    commits: Database<Str, SerdeBincode<CommitRecord>>,
     db_metadata: Database<Str, SerdeBincode<DbMetadata>>,
+    /// Team notes by chunk hash (kept across `clear`)
+    notes: Database<Str, SerdeBincode<Vec<ChunkNote>>>,
+    /// Vectors added since the arroy index was built
+    delta: Database<U32<BigEndian>, SerdeBincode<Vec<f32>>>,
+    /// Chunks deleted since the arroy index was built, whose vectors it still holds
+    tombstones: Database<U32<BigEndian>, Unit>,
+    /// Store-wide settings fixed at creation, such as the shard count
+    layout: Database<Str, U32<BigEndian>>,
+    /// Shard of each chunk's vector, kept until the vector leaves the arroy index (only in sharded stores)
+    chunk_shards: Database<U32<BigEndian>, U16<BigEndian>>,
+    /// Number of arroy indexes the vectors are split into
+    shards: u16,
+    options: StoreOptions,
     next_id: u32,
     dimensions: usize,
     indexed: bool,
 }
 
 impl VectorStore {
-    /// Create or open a vector store
+    /// Create or open a vector store with the default options
     ///
     /// # Arguments
     /// * `db_path` - Path to the database directory (e.g., ".demongrep.db")
     /// * `dimensions` - Dimensionality of embeddings (e.g., 384, 768)
     pub fn new(db_path: &Path, dimensions: usize) -> Result<Self> {
+        Self::with_options(db_path, dimensions, &StoreOptions::default())
+    }
+
+    /// Create or open a vector store built and searched as `options` says
+    ///
+    /// An existing store keeps the shard count it was created with.
+    pub fn with_options(db_path: &Path, dimensions: usize, options: &StoreOptions) -> Result<Self> {
         info_print!("📦 Opening vector database at: {}", db_path.display());
 
         // Create database directory (LMDB expects a directory, not a file)
@@ -119,7 +326,7 @@ impl VectorStore {
         let env = unsafe {
             EnvOpenOptions::new()
                 .map_size(10 * 1024 * 1024 * 1024) // 10GB max
-                .max_dbs(10)
+                .max_dbs(12)
                 .open(db_path)?
         };
 
@@ -131,11 +338,55 @@ impl VectorStore {
             env.create_database(&mut wtxn, Some("chunks"))?;
         let file_metadata: Database<Str, SerdeBincode<FileMeta>> =
             env.create_database(&mut wtxn, Some("file_metadata"))?;
+        let file_vectors: Database<Str, SerdeBincode<FileVector>> =
+            env.create_database(&mut wtxn, Some("file_vectors"))?;
+        let commits: Database<Str, SerdeBincode<CommitRecord>> = env.create_database(&mut wtxn, Some("commits"))?;
         let db_metadata: Database<Str, SerdeBincode<DbMetadata>> =
             env.create_database(&mut wtxn, Some("db_metadata"))?;
+        let notes: Database<Str, SerdeBincode<Vec<ChunkNote>>> =
+            env.create_database(&mut wtxn, Some("notes"))?;
+        let delta: Database<U32<BigEndian>, SerdeBincode<Vec<f32>>> =
+            env.create_database(&mut wtxn, Some("delta_vectors"))?;
+        let tombstones: Database<U32<BigEndian>, Unit> = env.create_database(&mut wtxn, Some("tombstones"))?;
+        let layout: Database<Str, U32<BigEndian>> = env.create_database(&mut wtxn, Some("layout"))?;
+        let chunk_shards: Database<U32<BigEndian>, U16<BigEndian>> =
+            env.create_database(&mut wtxn, Some("chunk_shards"))?;
+
+        if let Some(meta) = db_metadata.get(&wtxn, "metadata")? {
+            if meta.version != FORMAT_VERSION {
+                return Err(anyhow!(
+                    "{} was indexed by a different version of demongrep (format {}, expected {}) - reindex required: run `demongrep clear`, then `demongrep index`",
+                    db_path.display(),
+                    meta.version,
+                    FORMAT_VERSION
+                ));
+            }
+        }
 
-        // Get the next ID by counting existing chunks
-        let next_id = chunks.len(&wtxn)? as u32;
+        // Continue after the highest ID still in use. Counting chunks would
+        // reuse the IDs of deleted ones and overwrite live chunks, and a
+        // deleted chunk's vector stays in the arroy index under its ID until
+        // the tombstone is folded in
+        let next_id = [
+            chunks.last(&wtxn)?.map(|(id, _)| id),
+            delta.last(&wtxn)?.map(|(id, _)| id),
+            tombstones.last(&wtxn)?.map(|(id, _)| id),
+        ]
+        .into_iter()
+        .flatten()
+        .max()
+        .map_or(0, |id| id + 1);
+
+        // The shard count is fixed once a store has chunks; stores from before sharding have one
+        let shards = match layout.get(&wtxn, SHARDS_KEY)? {
+            Some(shards) if next_id > 0 => shards as u16,
+            _ if next_id > 0 => 1,
+            _ => {
+                let shards = options.shards.clamp(1, MAX_SHARDS) as u16;
+                layout.put(&mut wtxn, SHARDS_KEY, &u32::from(shards))?;
+                shards
+            }
+        };
 
         wtxn.commit()?;
 
@@ -154,7 +405,16 @@ impl VectorStore {
             vectors,
             chunks,
             file_metadata,
+            file_vectors,
+            commits,
             db_metadata,
+            notes,
+            delta,
+            tombstones,
+            layout,
+            chunk_shards,
+            shards,
+            options: *options,
             next_id,
             dimensions,
             indexed,
@@ -169,12 +429,31 @@ impl VectorStore {
             return Ok(0);
         }
 
-        println!("📊 Inserting {} chunks...", chunks.len());
+        info_print!("📊 Inserting {} chunks...", chunks.len());
+
+        self.put_chunks(&chunks)?;
+
+        info_print!("✅ Inserted {} chunks (IDs: {}-{})",
+            chunks.len(),
+            self.next_id - chunks.len() as u32,
+            self.next_id - 1
+        );
 
+        Ok(chunks.len())
+    }
+
+    /// Store chunks under new IDs, returning the first one
+    ///
+    /// Once the arroy index is built, vectors go to the delta segment, which
+    /// searches read right away; before that they go to arroy and wait for
+    /// `build_index`.
+    fn put_chunks(&mut self, chunks: &[EmbeddedChunk]) -> Result<u32> {
         let mut wtxn = self.env.write_txn()?;
-        let writer = Writer::new(self.vectors, 0, self.dimensions);
+        let mut file_times = FileTimes::default();
+        let start_id = self.next_id;
+        let parents = parent_indices(chunks.iter().map(|chunk| &chunk.chunk));
 
-        for chunk in &chunks {
+        for (chunk, parent) in chunks.iter().zip(parents) {
             let id = self.next_id;
 
             // Check embedding dimensions
@@ -186,52 +465,136 @@ impl VectorStore {
                 ));
             }
 
-            // Add vector to arroy
-            writer.add_item(&mut wtxn, id, &chunk.embedding)?;
+            // A reused ID must not stay hidden, nor have its new vector deleted by `rebuild_index`
+            self.tombstones.delete(&mut wtxn, &id)?;
+
+            let shard = shard_of(&chunk.chunk.path, self.shards);
+            if self.shards > 1 {
+                self.chunk_shards.put(&mut wtxn, &id, &shard)?;
+            }
+            if self.indexed {
+                self.delta.put(&mut wtxn, &id, &chunk.embedding)?;
+            } else {
+                Writer::new(self.vectors, shard, self.dimensions).add_item(&mut wtxn, id, &chunk.embedding)?;
+            }
 
             // Store metadata
-            let metadata = ChunkMetadata::from_embedded_chunk(chunk);
+            let parent_id = parent.map(|offset| start_id + offset as u32);
+            let metadata = ChunkMetadata::from_embedded_chunk(chunk, file_times.get(&chunk.chunk.path), parent_id);
             self.chunks.put(&mut wtxn, &id, &metadata)?;
 
             self.next_id += 1;
         }
 
         wtxn.commit()?;
-
-        // Mark as not indexed (need to rebuild index after inserts)
-        self.indexed = false;
-
-        println!("✅ Inserted {} chunks (IDs: {}-{})",
-            chunks.len(),
-            self.next_id - chunks.len() as u32,
-            self.next_id - 1
-        );
-
-        Ok(chunks.len())
+        Ok(start_id)
     }
 
     /// Build the vector index
     ///
-    /// Must be called after inserting chunks and before searching
+    /// Must be called after inserting chunks and before searching. Once the
+    /// index is built, later changes stay in the delta segment until they
+    /// outgrow `MIN_DELTA` or one in `DELTA_DIVISOR` indexed vectors, so a
+    /// small sync doesn't rebuild the whole index.
     pub fn build_index(&mut self) -> Result<()> {
-        println!("🔨 Building vector index...");
+        if self.indexed {
+            let pending = self.pending_vectors()?;
+            let indexed = self.chunk_count()?.saturating_sub(pending);
+            if pending <= MIN_DELTA.max(indexed / DELTA_DIVISOR) {
+                info_print!("✅ {} vector changes kept in the delta segment", pending);
+                return Ok(());
+            }
+        }
+        self.rebuild_index()
+    }
 
-        let mut wtxn = self.env.write_txn()?;
-        let writer = Writer::new(self.vectors, 0, self.dimensions);
+    /// Fold the delta segment into the vector index, returning the number of changes folded
+    ///
+    /// Does nothing when the index is built and has no pending changes.
+    pub fn compact_index(&mut self) -> Result<usize> {
+        let pending = self.pending_vectors()?;
+        if self.indexed && pending == 0 {
+            return Ok(0);
+        }
+        self.rebuild_index()?;
+        Ok(pending)
+    }
 
-        let mut rng = StdRng::seed_from_u64(rand::random());
-        writer.builder(&mut rng).build(&mut wtxn)?;
+    /// Move the delta segment into arroy and build the shards it changed
+    ///
+    /// The first build builds every shard, empty ones included, so each can
+    /// be opened for searching.
+    fn rebuild_index(&mut self) -> Result<()> {
+        info_print!("🔨 Building vector index...");
+
+        let mut wtxn = self.env.write_txn()?;
+        let mut changed: BTreeSet<u16> = if self.indexed { BTreeSet::new() } else { (0..self.shards).collect() };
+        let delta: Vec<(u32, Vec<f32>)> = self.delta.iter(&wtxn)?.collect::<heed::Result<_>>()?;
+        for (id, vector) in &delta {
+            let shard = self.chunk_shard(&wtxn, *id)?;
+            Writer::new(self.vectors, shard, self.dimensions).add_item(&mut wtxn, *id, vector)?;
+            changed.insert(shard);
+        }
+        let tombstones: Vec<u32> =
+            self.tombstones.iter(&wtxn)?.map(|item| item.map(|(id, _)| id)).collect::<heed::Result<_>>()?;
+        for id in tombstones {
+            let shard = self.chunk_shard(&wtxn, id)?;
+            if Writer::new(self.vectors, shard, self.dimensions).del_item(&mut wtxn, id)? {
+                changed.insert(shard);
+            }
+            self.chunk_shards.delete(&mut wtxn, &id)?;
+        }
+        self.delta.clear(&mut wtxn)?;
+        self.tombstones.clear(&mut wtxn)?;
+
+        for &shard in &changed {
+            let writer = Writer::new(self.vectors, shard, self.dimensions);
+            let mut rng = StdRng::seed_from_u64(rand::random());
+            let mut builder = writer.builder(&mut rng);
+            if let Some(trees) = self.options.trees.filter(|&trees| trees > 0) {
+                builder.n_trees(trees);
+            }
+            builder.build(&mut wtxn)?;
+        }
 
         wtxn.commit()?;
 
         self.indexed = true;
 
-        println!("✅ Index built successfully");
+        if self.shards > 1 {
+            info_print!("✅ Index built successfully ({} of {} shards)", changed.len(), self.shards);
+        } else {
+            info_print!("✅ Index built successfully");
+        }
         Ok(())
     }
 
+    /// Whether `search` scans every vector instead of searching the approximate index
+    pub fn searches_exactly(&self) -> Result<bool> {
+        Ok(self.chunk_count()? <= self.options.exact_threshold)
+    }
+
+    /// Number of arroy indexes the vectors are split into
+    pub fn shard_count(&self) -> usize {
+        usize::from(self.shards)
+    }
+
+    /// Shard holding the vector of chunk `id`
+    fn chunk_shard(&self, rtxn: &heed::RoTxn, id: u32) -> Result<u16> {
+        Ok(self.chunk_shards.get(rtxn, &id)?.unwrap_or(0))
+    }
+
+    /// Readers of every shard of the arroy index
+    fn readers<'t>(&self, rtxn: &'t heed::RoTxn) -> Result<Vec<Reader<'t, Cosine>>> {
+        (0..self.shards).map(|shard| Ok(Reader::open(rtxn, shard, self.vectors)?)).collect()
+    }
+
     /// Search for similar chunks
     ///
+    /// Stores small enough for `searches_exactly` are scanned in full,
+    /// larger ones are searched through the approximate index and the delta
+    /// segment beside it.
+    ///
     /// # Arguments
     /// * `query_embedding` - The query vector
     /// * `limit` - Maximum number of results to return
@@ -252,27 +615,74 @@ impl VectorStore {
                 "Index not built. Call build_index() after inserting chunks."
             ));
         }
+        if self.searches_exactly()? {
+            return self.search_exact(query_embedding, limit);
+        }
 
-        let rtxn = self.env.read_txn()?;
-        let reader = Reader::open(&rtxn, 0, self.vectors)?;
-
-        // Perform ANN search with quality boost
-        let mut query = reader.nns(limit);
+        let delta_ids = self.nearest_delta(query_embedding, limit)?;
+        let results = self.search_index(query_embedding, limit)?;
+        if delta_ids.is_empty() {
+            return Ok(results);
+        }
+        // Rescore both by exact similarity so they rank on one scale
+        let ids: Vec<u32> = results.iter().map(|result| result.id).chain(delta_ids).collect();
+        let mut results = self.score_chunks(query_embedding, &ids)?;
+        results.truncate(limit);
+        Ok(results)
+    }
 
-        // Improve search quality by exploring more candidates
-        if let Some(n_trees) = NonZeroUsize::new(reader.n_trees()) {
-            if let Some(search_k) = NonZeroUsize::new(limit * n_trees.get() * 15) {
-                query.search_k(search_k);
-            }
+    /// IDs of the `limit` chunks in the delta segment nearest to the query
+    fn nearest_delta(&self, query_embedding: &[f32], limit: usize) -> Result<Vec<u32>> {
+        let rtxn = self.env.read_txn()?;
+        let mut scored = Vec::new();
+        for item in self.delta.iter(&rtxn)? {
+            let (id, vector) = item?;
+            scored.push((id, cosine_similarity(query_embedding, &vector)));
         }
+        scored.sort_by(|a, b| b.1.total_cmp(&a.1));
+        Ok(scored.into_iter().take(limit).map(|(id, _)| id).collect())
+    }
 
-        let results = query.by_vector(&rtxn, query_embedding)?;
+    /// The `limit` chunks nearest to the query in the arroy index, leaving out deleted ones
+    ///
+    /// Shards are searched in parallel, each in its own read transaction, and
+    /// their nearest chunks merged by distance.
+    fn search_index(&self, query_embedding: &[f32], limit: usize) -> Result<Vec<SearchResult>> {
+        // Chunks deleted since the index was built still take places among the nearest
+        let fetch = limit + self.tombstones.len(&self.env.read_txn()?)? as usize;
+        let quality = self.options.search_quality.max(1);
+
+        let per_shard: Vec<Vec<(ItemId, f32)>> = (0..self.shards)
+            .into_par_iter()
+            .map(|shard| -> Result<Vec<(ItemId, f32)>> {
+                let rtxn = self.env.read_txn()?;
+                let reader = Reader::open(&rtxn, shard, self.vectors)?;
+
+                // Perform ANN search with quality boost
+                let mut query = reader.nns(fetch);
+
+                // Improve search quality by exploring more candidates
+                if let Some(n_trees) = NonZeroUsize::new(reader.n_trees()) {
+                    let search_k = fetch.saturating_mul(n_trees.get()).saturating_mul(quality);
+                    if let Some(search_k) = NonZeroUsize::new(search_k) {
+                        query.search_k(search_k);
+                    }
+                }
+
+                Ok(query.by_vector(&rtxn, query_embedding)?)
+            })
+            .collect::<Result<_>>()?;
+        let mut results: Vec<(ItemId, f32)> = per_shard.into_iter().flatten().collect();
+        results.sort_by(|a, b| a.1.total_cmp(&b.1));
+        results.truncate(fetch);
 
         // Fetch metadata for each result
+        let rtxn = self.env.read_txn()?;
         let mut search_results = Vec::new();
 
         for (id, distance) in results {
             if let Some(metadata) = self.chunks.get(&rtxn, &id)? {
+                let notes = self.note_texts(&rtxn, &metadata.hash)?;
                 search_results.push(SearchResult {
                     id,
                     content: metadata.content,
@@ -288,13 +698,101 @@ impl VectorStore {
                     score: 1.0 - distance, // Convert distance to similarity score
                     context_prev: metadata.context_prev,
                     context_next: metadata.context_next,
+                    notes,
+                    simhash: metadata.simhash,
+                    also_in: Vec::new(),
+                    tokens: metadata.prompt_tokens,
+                    via: None,
+                    tags: metadata.tags,
+                    package: metadata.package,
+                    stale: false,
+                    match_lines: Vec::new(),
+                    database: None,
                 });
             }
         }
 
+        search_results.truncate(limit);
         Ok(search_results)
     }
 
+    /// The `limit` chunks nearest to the query, by cosine similarity to every stored vector
+    ///
+    /// What the approximate index estimates, at the cost of a full scan: the
+    /// vectors are read in one transaction and scored in parallel.
+    pub fn search_exact(&self, query_embedding: &[f32], limit: usize) -> Result<Vec<SearchResult>> {
+        if !self.indexed {
+            return Ok(Vec::new());
+        }
+        let vectors = {
+            let rtxn = self.env.read_txn()?;
+            let readers = self.readers(&rtxn)?;
+            let mut vectors = Vec::with_capacity(self.chunks.len(&rtxn)? as usize);
+            for item in self.chunks.iter(&rtxn)? {
+                let (id, _) = item?;
+                if let Some(vector) = self.item_vector(&rtxn, &readers, id)? {
+                    vectors.push((id, vector));
+                }
+            }
+            vectors
+        };
+
+        let mut scored: Vec<(u32, f32)> = vectors
+            .par_iter()
+            .map(|(id, vector)| (*id, cosine_similarity(query_embedding, vector)))
+            .collect();
+        scored.sort_by(|a, b| b.1.total_cmp(&a.1));
+        scored.truncate(limit);
+        let ids: Vec<u32> = scored.into_iter().map(|(id, _)| id).collect();
+        self.score_chunks(query_embedding, &ids)
+    }
+
+    /// Stored vector of the chunk `id`, from the delta segment or the arroy index
+    fn item_vector(&self, rtxn: &heed::RoTxn, readers: &[Reader<Cosine>], id: u32) -> Result<Option<Vec<f32>>> {
+        if let Some(vector) = self.delta.get(rtxn, &id)? {
+            return Ok(Some(vector));
+        }
+        Ok(readers[usize::from(self.chunk_shard(rtxn, id)?)].item_vector(rtxn, id)?)
+    }
+
+    /// Call `f` with every chunk that has a vector, in ID order
+    ///
+    /// Reads in one transaction without loading the whole store, for exports.
+    pub fn for_each_vector(&self, mut f: impl FnMut(u32, ChunkMetadata, Vec<f32>) -> Result<()>) -> Result<()> {
+        if !self.indexed {
+            return Ok(());
+        }
+        let rtxn = self.env.read_txn()?;
+        let readers = self.readers(&rtxn)?;
+        for item in self.chunks.iter(&rtxn)? {
+            let (id, metadata) = item?;
+            if let Some(vector) = self.item_vector(&rtxn, &readers, id)? {
+                f(id, metadata, vector)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Number of stored chunks (cheaper than `stats`, which also counts files)
+    pub fn chunk_count(&self) -> Result<usize> {
+        let rtxn = self.env.read_txn()?;
+        Ok(self.chunks.len(&rtxn)? as usize)
+    }
+
+    /// Number of trees in the approximate index, over all shards (0 before it is built)
+    pub fn n_trees(&self) -> Result<usize> {
+        let rtxn = self.env.read_txn()?;
+        Ok((0..self.shards)
+            .map(|shard| Reader::open(&rtxn, shard, self.vectors).map(|reader| reader.n_trees()).unwrap_or(0))
+            .sum())
+    }
+
+    /// Vector changes not yet folded into the approximate index: added vectors plus deleted ones
+    pub fn pending_vectors(&self) -> Result<usize> {
+        let rtxn = self.env.read_txn()?;
+        Ok((self.delta.len(&rtxn)? + self.tombstones.len(&rtxn)?) as usize)
+    }
+
     /// Get statistics about the vector store
     pub fn stats(&self) -> Result<StoreStats> {
         let rtxn = self.env.read_txn()?;
@@ -325,13 +823,29 @@ impl VectorStore {
         }
 
         let mut wtxn = self.env.write_txn()?;
-        let writer = Writer::new(self.vectors, 0, self.dimensions);
 
         let mut deleted = 0;
         for &id in chunk_ids {
-            // Delete from vector database
-            if writer.del_item(&mut wtxn, id).is_ok() {
+            // Delete from vector database. A built index keeps its vectors: a
+            // tombstone hides them until `build_index` folds the deletion in,
+            // which needs the chunk's shard until then
+            if self.indexed {
+                // A tombstone for an unknown ID would never be folded away
+                if self.chunks.get(&wtxn, &id)?.is_none() {
+                    continue;
+                }
+                if self.delta.delete(&mut wtxn, &id)? {
+                    self.chunk_shards.delete(&mut wtxn, &id)?;
+                } else {
+                    self.tombstones.put(&mut wtxn, &id, &())?;
+                }
                 deleted += 1;
+            } else {
+                let shard = self.chunk_shard(&wtxn, id)?;
+                if Writer::new(self.vectors, shard, self.dimensions).del_item(&mut wtxn, id)? {
+                    deleted += 1;
+                }
+                self.chunk_shards.delete(&mut wtxn, &id)?;
             }
             // Delete from metadata
             self.chunks.delete(&mut wtxn, &id)?;
@@ -339,11 +853,6 @@ impl VectorStore {
 
         wtxn.commit()?;
 
-        // Mark as needing re-index
-        if deleted > 0 {
-            self.indexed = false;
-        }
-
         Ok(deleted)
     }
 
@@ -351,7 +860,19 @@ impl VectorStore {
     ///
     /// Returns the IDs of deleted chunks
     pub fn delete_file_chunks(&mut self, file_path: &str) -> Result<Vec<u32>> {
-        // First, find all chunk IDs for this file
+        let chunk_ids = self.file_chunk_ids(file_path)?;
+        if chunk_ids.is_empty() {
+            return Ok(vec![]);
+        }
+
+        // Delete the chunks
+        self.delete_chunks(&chunk_ids)?;
+
+        Ok(chunk_ids)
+    }
+
+    /// IDs of all chunks stored for a file
+    pub fn file_chunk_ids(&self, file_path: &str) -> Result<Vec<u32>> {
         let rtxn = self.env.read_txn()?;
         let mut chunk_ids = Vec::new();
 
@@ -361,14 +882,6 @@ impl VectorStore {
                 chunk_ids.push(id);
             }
         }
-        drop(rtxn);
-
-        if chunk_ids.is_empty() {
-            return Ok(vec![]);
-        }
-
-        // Delete the chunks
-        self.delete_chunks(&chunk_ids)?;
 
         Ok(chunk_ids)
     }
@@ -381,36 +894,14 @@ impl VectorStore {
             return Ok(vec![]);
         }
 
-        let start_id = self.next_id;
-        let mut wtxn = self.env.write_txn()?;
-        let writer = Writer::new(self.vectors, 0, self.dimensions);
-
-        for chunk in &chunks {
-            let id = self.next_id;
-
-            if chunk.embedding.len() != self.dimensions {
-                return Err(anyhow!(
-                    "Embedding dimension mismatch: expected {}, got {}",
-                    self.dimensions,
-                    chunk.embedding.len()
-                ));
-            }
-
-            writer.add_item(&mut wtxn, id, &chunk.embedding)?;
-            let metadata = ChunkMetadata::from_embedded_chunk(chunk);
-            self.chunks.put(&mut wtxn, &id, &metadata)?;
-
-            self.next_id += 1;
-        }
-
-        wtxn.commit()?;
-        self.indexed = false;
-
+        let start_id = self.put_chunks(&chunks)?;
         let ids: Vec<u32> = (start_id..self.next_id).collect();
         Ok(ids)
     }
 
     /// Clear all data from the database
+    ///
+    /// Notes are kept: they are keyed by content and reattach on reindex.
     pub fn clear(&mut self) -> Result<()> {
         println!("🗑️  Clearing database...");
 
@@ -420,7 +911,16 @@ impl VectorStore {
         self.chunks.clear(&mut wtxn)?;
         self.vectors.clear(&mut wtxn)?;
         self.file_metadata.clear(&mut wtxn)?;
+        self.file_vectors.clear(&mut wtxn)?;
+        self.commits.clear(&mut wtxn)?;
         self.db_metadata.clear(&mut wtxn)?;
+        self.delta.clear(&mut wtxn)?;
+        self.tombstones.clear(&mut wtxn)?;
+        self.chunk_shards.clear(&mut wtxn)?;
+
+        // An empty store takes the configured shard count again
+        self.shards = self.options.shards.clamp(1, MAX_SHARDS) as u16;
+        self.layout.put(&mut wtxn, SHARDS_KEY, &u32::from(self.shards))?;
 
         wtxn.commit()?;
 
@@ -441,6 +941,7 @@ impl VectorStore {
     pub fn get_chunk_as_result(&self, id: u32) -> Result<Option<SearchResult>> {
         let rtxn = self.env.read_txn()?;
         if let Some(meta) = self.chunks.get(&rtxn, &id)? {
+            let notes = self.note_texts(&rtxn, &meta.hash)?;
             Ok(Some(SearchResult {
                 id,
                 content: meta.content,
@@ -456,12 +957,86 @@ impl VectorStore {
                 score: 0.0, // Will be set by caller
                 context_prev: meta.context_prev,
                 context_next: meta.context_next,
+                notes,
+                simhash: meta.simhash,
+                also_in: Vec::new(),
+                tokens: meta.prompt_tokens,
+                via: None,
+                tags: meta.tags,
+                package: meta.package,
+                stale: false,
+                match_lines: Vec::new(),
+                database: None,
             }))
         } else {
             Ok(None)
         }
     }
 
+    /// ID the next inserted chunk will get
+    pub fn next_id(&self) -> u32 {
+        self.next_id
+    }
+
+    /// List the IDs of all stored chunks
+    pub fn chunk_ids(&self) -> Result<Vec<u32>> {
+        let rtxn = self.env.read_txn()?;
+        let mut ids = Vec::new();
+        for result in self.chunks.iter(&rtxn)? {
+            let (id, _) = result?;
+            ids.push(id);
+        }
+        Ok(ids)
+    }
+
+    /// All stored chunks with their IDs, in ID order
+    pub fn all_chunks(&self) -> Result<Vec<(u32, ChunkMetadata)>> {
+        let rtxn = self.env.read_txn()?;
+        let mut chunks = Vec::new();
+        for result in self.chunks.iter(&rtxn)? {
+            let (id, metadata) = result?;
+            chunks.push((id, metadata));
+        }
+        Ok(chunks)
+    }
+
+    /// IDs of all items in the vector index, with the delta segment applied
+    ///
+    /// Returns None if the index was never built.
+    pub fn vector_ids(&self) -> Result<Option<Vec<u32>>> {
+        if !self.indexed {
+            return Ok(None);
+        }
+        let rtxn = self.env.read_txn()?;
+        let readers = self.readers(&rtxn)?;
+        let mut ids = readers[0].item_ids().clone();
+        for reader in &readers[1..] {
+            ids |= reader.item_ids();
+        }
+        for item in self.tombstones.iter(&rtxn)? {
+            ids.remove(item?.0);
+        }
+        for item in self.delta.iter(&rtxn)? {
+            ids.insert(item?.0);
+        }
+        Ok(Some(ids.iter().collect()))
+    }
+
+    /// Get the stored embedding for a chunk
+    ///
+    /// Returns None if the chunk has no vector (or the index was never built).
+    pub fn get_vector(&self, id: u32) -> Result<Option<Vec<f32>>> {
+        if !self.indexed {
+            return Ok(None);
+        }
+        let rtxn = self.env.read_txn()?;
+        if self.chunks.get(&rtxn, &id)?.is_none() {
+            return Ok(None);
+        }
+        let readers = self.readers(&rtxn)?;
+        self.item_vector(&rtxn, &readers, id)
+    }
+
     /// Get the database file size in bytes
     pub fn db_size(&self) -> Result<u64> {
         let info = self.env.info();
@@ -475,7 +1050,11 @@ impl VectorStore {
 }
 
 /// Search result with metadata
-#[derive(Debug, Clone)]
+///
+/// Serialized for `search.transform_command`; fields missing from its
+/// output take their defaults.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct SearchResult {
     pub id: ItemId,
     pub content: String,
@@ -493,6 +1072,54 @@ pub struct SearchResult {
     pub context_prev: Option<String>,
     /// Lines of code immediately after this chunk (for context)
     pub context_next: Option<String>,
+    /// Team notes attached with `demongrep note`
+    pub notes: Vec<String>,
+    /// Near-duplicate fingerprint of `content` (0 if unknown)
+    pub simhash: u64,
+    /// Other files holding a near-duplicate of this result, collapsed into it
+    pub also_in: Vec<String>,
+    /// LLM prompt tokens in `content` (0 if unknown)
+    pub tokens: usize,
+    /// Why a result that didn't match the query was added (`--hops`)
+    pub via: Option<String>,
+    /// Tags from `indexing.tags`
+    pub tags: Vec<String>,
+    /// Package the file belongs to
+    pub package: Option<String>,
+    /// The file changed since this result was indexed (see `search::freshness`)
+    pub stale: bool,
+    /// Lines that best match the query, in file line numbers (see `search::match_lines`)
+    pub match_lines: Vec<usize>,
+    /// Database the result was found in: "local" or "global"
+    pub database: Option<String>,
+}
+
+/// Lanes summed separately by `dot`, so the compiler can keep them in one SIMD register
+const DOT_LANES: usize = 8;
+
+/// Dot product of two vectors of the same length
+fn dot(a: &[f32], b: &[f32]) -> f32 {
+    let mut sums = [0.0f32; DOT_LANES];
+    let (a_chunks, b_chunks) = (a.chunks_exact(DOT_LANES), b.chunks_exact(DOT_LANES));
+    let tail: f32 = a_chunks.remainder().iter().zip(b_chunks.remainder()).map(|(x, y)| x * y).sum();
+    for (xs, ys) in a_chunks.zip(b_chunks) {
+        for (sum, (x, y)) in sums.iter_mut().zip(xs.iter().zip(ys)) {
+            *sum += x * y;
+        }
+    }
+    sums.iter().sum::<f32>() + tail
+}
+
+/// Cosine similarity of two vectors (0 when either is all zeros)
+fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    let dot_ab = dot(a, b);
+    let norm_a = dot(a, a).sqrt();
+    let norm_b = dot(b, b).sqrt();
+    if norm_a == 0.0 || norm_b == 0.0 {
+        0.0
+    } else {
+        dot_ab / (norm_a * norm_b)
+    }
 }
 
 /// Statistics about the vector store
@@ -508,7 +1135,7 @@ impl VectorStore {
     // ========== File Metadata Methods for Incremental Indexing ==========
     
     /// Compute SHA256 hash of file content
-    fn compute_file_hash(path: &Path) -> Result<String> {
+    pub fn compute_file_hash(path: &Path) -> Result<String> {
         let content = std::fs::read(path)?;
         let mut hasher = Sha256::new();
         hasher.update(&content);
@@ -556,10 +1183,15 @@ impl VectorStore {
 
     /// Update metadata for a file after indexing
     pub fn update_file_metadata(&mut self, path: &Path, chunk_ids: Vec<u32>) -> Result<()> {
+        self.update_file_metadata_from(path, path, chunk_ids)
+    }
+
+    /// Update metadata for `path`, reading its contents from `source` (a snapshot of it, see `index --rev`)
+    pub fn update_file_metadata_from(&mut self, path: &Path, source: &Path, chunk_ids: Vec<u32>) -> Result<()> {
         let path_str = path.to_string_lossy().to_string();
-        let hash = Self::compute_file_hash(path)?;
-        let mtime = Self::get_file_mtime(path)?;
-        let size = std::fs::metadata(path)?.len();
+        let hash = Self::compute_file_hash(source)?;
+        let mtime = Self::get_file_mtime(source)?;
+        let size = std::fs::metadata(source)?.len();
 
         let meta = FileMeta {
             hash,
@@ -586,11 +1218,114 @@ impl VectorStore {
             .map(|meta| meta.chunk_ids.clone());
         
         self.file_metadata.delete(&mut wtxn, &path_str)?;
+        self.file_vectors.delete(&mut wtxn, &path_str)?;
         wtxn.commit()?;
 
         Ok(chunk_ids)
     }
 
+    /// Files whose summary embedding is missing or older than their content
+    pub fn stale_file_vectors(&self) -> Result<Vec<(String, FileMeta)>> {
+        let rtxn = self.env.read_txn()?;
+        let mut stale = Vec::new();
+        for item in self.file_metadata.iter(&rtxn)? {
+            let (path, meta) = item?;
+            if self.file_vectors.get(&rtxn, path)?.is_none_or(|vector| vector.hash != meta.hash) {
+                stale.push((path.to_string(), meta));
+            }
+        }
+        Ok(stale)
+    }
+
+    /// Store summary embeddings by file path
+    pub fn put_file_vectors(&mut self, vectors: &[(String, FileVector)]) -> Result<()> {
+        let mut wtxn = self.env.write_txn()?;
+        for (path, vector) in vectors {
+            self.file_vectors.put(&mut wtxn, path, vector)?;
+        }
+        wtxn.commit()?;
+        Ok(())
+    }
+
+    /// Whether any file summary has been embedded
+    pub fn has_file_vectors(&self) -> Result<bool> {
+        let rtxn = self.env.read_txn()?;
+        Ok(!self.file_vectors.is_empty(&rtxn)?)
+    }
+
+    /// The `limit` files whose summaries are most similar to the query, best first
+    ///
+    /// Files are scored exactly; a project has far fewer files than chunks.
+    pub fn rank_files(&self, query_embedding: &[f32], limit: usize) -> Result<Vec<(String, f32)>> {
+        let rtxn = self.env.read_txn()?;
+        let mut files = Vec::new();
+        for item in self.file_vectors.iter(&rtxn)? {
+            let (path, vector) = item?;
+            files.push((path.to_string(), cosine_similarity(query_embedding, &vector.embedding)));
+        }
+        files.sort_by(|a, b| b.1.total_cmp(&a.1));
+        files.truncate(limit);
+        Ok(files)
+    }
+
+    /// IDs of the chunks stored for these files, from their file metadata
+    pub fn files_chunk_ids(&self, paths: &[String]) -> Result<Vec<u32>> {
+        let rtxn = self.env.read_txn()?;
+        let mut ids = Vec::new();
+        for path in paths {
+            if let Some(meta) = self.file_metadata.get(&rtxn, path)? {
+                ids.extend(meta.chunk_ids);
+            }
+        }
+        Ok(ids)
+    }
+
+    /// Hashes of the commits in the history namespace
+    pub fn commit_hashes(&self) -> Result<HashSet<String>> {
+        let rtxn = self.env.read_txn()?;
+        let mut hashes = HashSet::new();
+        for item in self.commits.iter(&rtxn)? {
+            hashes.insert(item?.0.to_string());
+        }
+        Ok(hashes)
+    }
+
+    /// Add embedded commits and drop the ones in `remove`
+    pub fn update_commits(&mut self, add: &[CommitRecord], remove: &[String]) -> Result<()> {
+        let mut wtxn = self.env.write_txn()?;
+        for commit in add {
+            self.commits.put(&mut wtxn, &commit.hash, commit)?;
+        }
+        for hash in remove {
+            self.commits.delete(&mut wtxn, hash)?;
+        }
+        wtxn.commit()?;
+        Ok(())
+    }
+
+    /// The `limit` commits whose messages are most similar to the query, best first
+    ///
+    /// Scored exactly like file summaries; histories are indexed a few
+    /// hundred commits deep.
+    pub fn rank_commits(&self, query_embedding: &[f32], limit: usize) -> Result<Vec<(CommitRecord, f32)>> {
+        let rtxn = self.env.read_txn()?;
+        let mut commits = Vec::new();
+        for item in self.commits.iter(&rtxn)? {
+            let (_, commit) = item?;
+            let score = cosine_similarity(query_embedding, &commit.embedding);
+            commits.push((commit, score));
+        }
+        commits.sort_by(|a, b| b.1.total_cmp(&a.1));
+        commits.truncate(limit);
+        Ok(commits)
+    }
+
+    /// Hash of the file at `path` (as stored) when it was last indexed
+    pub fn indexed_file_hash(&self, path: &str) -> Result<Option<String>> {
+        let rtxn = self.env.read_txn()?;
+        Ok(self.file_metadata.get(&rtxn, path)?.map(|meta| meta.hash))
+    }
+
     /// Find files that were deleted (exist in metadata but not on disk)
     pub fn find_deleted_files(&self) -> Result<Vec<(String, Vec<u32>)>> {
         let rtxn = self.env.read_txn()?;
@@ -619,7 +1354,7 @@ impl VectorStore {
                     model_name: model_name.to_string(),
                     dimensions,
                     last_full_index: None,
-                    version: 1,
+                    version: FORMAT_VERSION,
                 })
             } else {
                 Ok(meta)
@@ -630,7 +1365,7 @@ impl VectorStore {
                 model_name: model_name.to_string(),
                 dimensions,
                 last_full_index: None,
-                version: 1,
+                version: FORMAT_VERSION,
             })
         }
     }
@@ -641,7 +1376,7 @@ impl VectorStore {
             model_name: model_name.to_string(),
             dimensions,
             last_full_index: None,
-            version: 1,
+            version: FORMAT_VERSION,
         };
 
         if mark_full_index {
@@ -659,6 +1394,277 @@ impl VectorStore {
         Ok(())
     }
 
+    /// Rewrite chunk and file metadata paths under the directory `from` to be under `to`
+    ///
+    /// Used when a database built on one machine is moved to another checkout.
+    /// Returns the number of chunks rewritten.
+    pub fn rebase_paths(&mut self, from: &str, to: &str) -> Result<usize> {
+        let mut wtxn = self.env.write_txn()?;
+
+        let mut chunks = Vec::new();
+        for item in self.chunks.iter(&wtxn)? {
+            let (id, mut meta) = item?;
+            if let Some(path) = rebase_path(&meta.path, from, to) {
+                meta.path = path;
+                chunks.push((id, meta));
+            }
+        }
+        for (id, meta) in &chunks {
+            self.chunks.put(&mut wtxn, id, meta)?;
+        }
+
+        let mut files = Vec::new();
+        for item in self.file_metadata.iter(&wtxn)? {
+            let (path, meta) = item?;
+            if let Some(new_path) = rebase_path(path, from, to) {
+                files.push((path.to_string(), new_path, meta));
+            }
+        }
+        for (path, new_path, meta) in files {
+            self.file_metadata.delete(&mut wtxn, &path)?;
+            self.file_metadata.put(&mut wtxn, &new_path, &meta)?;
+        }
+
+        let mut vectors = Vec::new();
+        for item in self.file_vectors.iter(&wtxn)? {
+            let (path, vector) = item?;
+            if let Some(new_path) = rebase_path(path, from, to) {
+                vectors.push((path.to_string(), new_path, vector));
+            }
+        }
+        for (path, new_path, vector) in vectors {
+            self.file_vectors.delete(&mut wtxn, &path)?;
+            self.file_vectors.put(&mut wtxn, &new_path, &vector)?;
+        }
+
+        wtxn.commit()?;
+        Ok(chunks.len())
+    }
+
+    /// Write a compacted copy of the LMDB environment to `path`
+    ///
+    /// Safe to call while other readers are active.
+    pub fn snapshot_to(&self, path: &Path) -> Result<()> {
+        self.env.copy_to_file(path, heed::CompactionOption::Enabled)?;
+        Ok(())
+    }
+
+    /// Character and estimated token counts of every stored chunk
+    pub fn chunk_sizes(&self) -> Result<Vec<(usize, usize)>> {
+        let rtxn = self.env.read_txn()?;
+        let mut sizes = Vec::new();
+
+        for item in self.chunks.iter(&rtxn)? {
+            let (_id, meta) = item?;
+            sizes.push((meta.char_count, meta.token_count));
+        }
+
+        Ok(sizes)
+    }
+
+    /// Chunks and files of each package, for packages with any chunks
+    ///
+    /// Chunks outside every package are left out.
+    pub fn package_counts(&self) -> Result<BTreeMap<String, (usize, usize)>> {
+        let rtxn = self.env.read_txn()?;
+        let mut files: BTreeMap<String, HashSet<String>> = BTreeMap::new();
+        let mut counts: BTreeMap<String, (usize, usize)> = BTreeMap::new();
+
+        for item in self.chunks.iter(&rtxn)? {
+            let (_id, meta) = item?;
+            if let Some(package) = meta.package {
+                counts.entry(package.clone()).or_default().0 += 1;
+                files.entry(package).or_default().insert(meta.path);
+            }
+        }
+        for (package, paths) in files {
+            counts.entry(package).or_default().1 = paths.len();
+        }
+        Ok(counts)
+    }
+
+    /// Path a file was indexed under, ignoring a leading `./` on either side
+    pub fn indexed_path(&self, file_path: &str) -> Result<Option<String>> {
+        let wanted = file_path.trim_start_matches("./");
+        let rtxn = self.env.read_txn()?;
+        for item in self.file_metadata.iter(&rtxn)? {
+            let (path, _) = item?;
+            if path.trim_start_matches("./") == wanted {
+                return Ok(Some(path.to_string()));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Score the given chunks against a query by exact cosine similarity
+    ///
+    /// For searching a known, small set of chunks (such as one file) without
+    /// going through the approximate index. Results are sorted best first.
+    pub fn score_chunks(&self, query_embedding: &[f32], ids: &[u32]) -> Result<Vec<SearchResult>> {
+        if !self.indexed {
+            return Ok(Vec::new());
+        }
+
+        let rtxn = self.env.read_txn()?;
+        let readers = self.readers(&rtxn)?;
+        let mut results = Vec::with_capacity(ids.len());
+        for &id in ids {
+            let Some(meta) = self.chunks.get(&rtxn, &id)? else {
+                continue;
+            };
+            let Some(vector) = self.item_vector(&rtxn, &readers, id)? else {
+                continue;
+            };
+            let score = cosine_similarity(query_embedding, &vector);
+            let notes = self.note_texts(&rtxn, &meta.hash)?;
+            results.push(SearchResult {
+                id,
+                content: meta.content,
+                path: meta.path,
+                start_line: meta.start_line,
+                end_line: meta.end_line,
+                kind: meta.kind,
+                signature: meta.signature,
+                docstring: meta.docstring,
+                context: meta.context,
+                hash: meta.hash,
+                distance: 1.0 - score,
+                score,
+                context_prev: meta.context_prev,
+                context_next: meta.context_next,
+                notes,
+                simhash: meta.simhash,
+                also_in: Vec::new(),
+                tokens: meta.prompt_tokens,
+                via: None,
+                tags: meta.tags,
+                package: meta.package,
+                stale: false,
+                match_lines: Vec::new(),
+                database: None,
+            });
+        }
+
+        results.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
+        Ok(results)
+    }
+
+    /// Search only chunks whose path, kind, tags and package pass `keep`
+    ///
+    /// When few enough chunks pass, they are scored exactly, so a narrow
+    /// filter still finds `limit` results; otherwise the approximate search
+    /// over-fetches and drops the chunks that don't pass.
+    pub fn search_filtered(
+        &self,
+        query_embedding: &[f32],
+        limit: usize,
+        keep: &ChunkFilter<'_>,
+    ) -> Result<Vec<SearchResult>> {
+        let ids = {
+            let rtxn = self.env.read_txn()?;
+            let mut ids = Vec::new();
+            for result in self.chunks.iter(&rtxn)? {
+                let (id, metadata) = result?;
+                if keep(&metadata.path, &metadata.kind, &metadata.tags, metadata.package.as_deref()) {
+                    ids.push(id);
+                }
+            }
+            ids
+        };
+
+        if ids.len() <= MAX_EXACT_CANDIDATES {
+            let mut results = self.score_chunks(query_embedding, &ids)?;
+            results.truncate(limit);
+            return Ok(results);
+        }
+        let mut results = self.search(query_embedding, limit * FILTERED_OVERFETCH)?;
+        results.retain(|result| keep(&result.path, &result.kind, &result.tags, result.package.as_deref()));
+        results.truncate(limit);
+        Ok(results)
+    }
+
+    /// Innermost chunk of `file_path` covering `line`, with its ID
+    ///
+    /// A leading `./` on either path is ignored.
+    pub fn find_chunk_at(&self, file_path: &str, line: usize) -> Result<Option<(u32, ChunkMetadata)>> {
+        let wanted = file_path.trim_start_matches("./");
+        let rtxn = self.env.read_txn()?;
+        let mut best: Option<(u32, ChunkMetadata)> = None;
+
+        for item in self.chunks.iter(&rtxn)? {
+            let (id, meta) = item?;
+            if meta.path.trim_start_matches("./") != wanted || line < meta.start_line || line > meta.end_line {
+                continue;
+            }
+            let span = meta.end_line - meta.start_line;
+            let narrower = match &best {
+                Some((_, b)) => span < b.end_line - b.start_line,
+                None => true,
+            };
+            if narrower {
+                best = Some((id, meta));
+            }
+        }
+
+        Ok(best)
+    }
+
+    /// Notes attached to a chunk hash
+    pub fn get_notes(&self, hash: &str) -> Result<Vec<ChunkNote>> {
+        let rtxn = self.env.read_txn()?;
+        Ok(self.notes.get(&rtxn, hash)?.unwrap_or_default())
+    }
+
+    /// Attach a note to a chunk hash, returning how many notes it now has
+    pub fn add_note(&mut self, hash: &str, note: ChunkNote) -> Result<usize> {
+        let mut wtxn = self.env.write_txn()?;
+        let mut notes = self.notes.get(&wtxn, hash)?.unwrap_or_default();
+        notes.push(note);
+        self.notes.put(&mut wtxn, hash, &notes)?;
+        wtxn.commit()?;
+        Ok(notes.len())
+    }
+
+    /// Remove every note on a chunk hash, returning how many were removed
+    pub fn remove_notes(&mut self, hash: &str) -> Result<usize> {
+        let mut wtxn = self.env.write_txn()?;
+        let removed = self.notes.get(&wtxn, hash)?.map_or(0, |notes| notes.len());
+        self.notes.delete(&mut wtxn, hash)?;
+        wtxn.commit()?;
+        Ok(removed)
+    }
+
+    /// Copy stored notes onto freshly chunked code so they are embedded with it
+    pub fn attach_notes(&self, chunks: &mut [Chunk]) -> Result<()> {
+        let rtxn = self.env.read_txn()?;
+        if self.notes.is_empty(&rtxn)? {
+            return Ok(());
+        }
+        for chunk in chunks {
+            chunk.notes = self.note_texts(&rtxn, &chunk.hash)?;
+        }
+        Ok(())
+    }
+
+    fn note_texts(&self, rtxn: &heed::RoTxn, hash: &str) -> Result<Vec<String>> {
+        Ok(self
+            .notes
+            .get(rtxn, hash)?
+            .map(|notes| notes.into_iter().map(|n| n.text).collect())
+            .unwrap_or_default())
+    }
+
+    /// Chunk IDs recorded in the file metadata table, by file
+    pub fn tracked_chunk_ids(&self) -> Result<Vec<(String, Vec<u32>)>> {
+        let rtxn = self.env.read_txn()?;
+        let mut tracked = Vec::new();
+        for item in self.file_metadata.iter(&rtxn)? {
+            let (path, meta) = item?;
+            tracked.push((path.to_string(), meta.chunk_ids));
+        }
+        Ok(tracked)
+    }
+
     /// Get file metadata statistics
     pub fn file_metadata_stats(&self) -> Result<(usize, usize, u64)> {
         let rtxn = self.env.read_txn()?;
@@ -684,6 +1690,14 @@ mod tests {
     use crate::embed::EmbeddedChunk;
     use tempfile::tempdir;
 
+    #[test]
+    fn test_rebase_path() {
+        assert_eq!(rebase_path("/ci/app/src/lib.rs", "/ci/app", "/home/me/app").as_deref(), Some("/home/me/app/src/lib.rs"));
+        assert_eq!(rebase_path("/ci/app2/src/lib.rs", "/ci/app", "/home/me/app"), None);
+        assert_eq!(rebase_path("/ci/app", "/ci/app", "/home/me/app").as_deref(), Some("/home/me/app"));
+        assert_eq!(rebase_path("src/lib.rs", "src/", "/home/me/src/").as_deref(), Some("/home/me/src/lib.rs"));
+    }
+
     #[test]
     fn test_vector_store_creation() {
         let temp_dir = tempdir().unwrap();
@@ -843,6 +1857,280 @@ mod tests {
         let metadata = metadata.unwrap();
         assert_eq!(metadata.content, "fn test() {}");
         assert_eq!(metadata.path, "test.rs");
+        assert_eq!(metadata.char_count, 12);
+        assert!(metadata.token_count > 0);
+        assert_eq!(store.chunk_sizes().unwrap(), vec![(12, metadata.token_count)]);
+        // test.rs doesn't exist, so its modification time is unknown
+        assert_eq!(metadata.modified, 0);
+    }
+
+    #[test]
+    fn test_notes() {
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("test.db");
+        let mut store = VectorStore::new(&db_path, 4).unwrap();
+
+        let chunk = |content: &str, start, end, kind| Chunk::new(content.to_string(), start, end, kind, "./auth.rs".to_string());
+        let outer = chunk("impl Auth {\n fn login() {}\n}", 10, 30, ChunkKind::Impl);
+        let inner = chunk("fn login() {}", 12, 14, ChunkKind::Method);
+        let hash = inner.hash.clone();
+        store
+            .insert_chunks(vec![
+                EmbeddedChunk::new(outer, vec![1.0, 0.0, 0.0, 0.0]),
+                EmbeddedChunk::new(inner, vec![0.0, 1.0, 0.0, 0.0]),
+            ])
+            .unwrap();
+
+        let (id, found) = store.find_chunk_at("auth.rs", 13).unwrap().unwrap();
+        assert_eq!(id, 1);
+        assert_eq!(found.hash, hash);
+        assert!(store.find_chunk_at("auth.rs", 40).unwrap().is_none());
+
+        let note = |text: &str| ChunkNote { text: text.to_string(), author: None, created_at: 0 };
+        assert_eq!(store.add_note(&hash, note("Retries are handled by the caller")).unwrap(), 1);
+        assert_eq!(store.add_note(&hash, note("Keep in sync with the mobile client")).unwrap(), 2);
+
+        store.clear().unwrap();
+        let mut rechunked = vec![chunk("fn login() {}", 2, 4, ChunkKind::Method)];
+        store.attach_notes(&mut rechunked).unwrap();
+        assert_eq!(rechunked[0].notes.len(), 2);
+
+        assert_eq!(store.remove_notes(&hash).unwrap(), 2);
+        assert!(store.get_notes(&hash).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_score_chunks() {
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("test.db");
+        let mut store = VectorStore::new(&db_path, 4).unwrap();
+
+        let chunk = |content: &str| Chunk::new(content.to_string(), 1, 2, ChunkKind::Function, "./big.rs".to_string());
+        store
+            .insert_chunks(vec![
+                EmbeddedChunk::new(chunk("fn parse() {}"), vec![1.0, 0.0, 0.0, 0.0]),
+                EmbeddedChunk::new(chunk("fn render() {}"), vec![0.0, 1.0, 0.0, 0.0]),
+                EmbeddedChunk::new(chunk("fn flush() {}"), vec![0.6, 0.8, 0.0, 0.0]),
+            ])
+            .unwrap();
+        store.build_index().unwrap();
+
+        let results = store.score_chunks(&[0.0, 1.0, 0.0, 0.0], &[0, 1, 2]).unwrap();
+        let ids: Vec<u32> = results.iter().map(|r| r.id).collect();
+        assert_eq!(ids, vec![1, 2, 0]);
+        assert!((results[0].score - 1.0).abs() < 1e-6);
+
+        // Only the requested chunks are scored
+        assert_eq!(store.score_chunks(&[0.0, 1.0, 0.0, 0.0], &[0]).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_search_exact() {
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("test.db");
+        let mut store = VectorStore::new(&db_path, 4).unwrap();
+        assert_eq!(store.n_trees().unwrap(), 0);
+
+        let chunk = |content: &str| Chunk::new(content.to_string(), 1, 2, ChunkKind::Function, "lib.rs".to_string());
+        store
+            .insert_chunks(vec![
+                EmbeddedChunk::new(chunk("fn parse() {}"), vec![1.0, 0.0, 0.0, 0.0]),
+                EmbeddedChunk::new(chunk("fn render() {}"), vec![0.0, 1.0, 0.0, 0.0]),
+                EmbeddedChunk::new(chunk("fn flush() {}"), vec![0.6, 0.8, 0.0, 0.0]),
+            ])
+            .unwrap();
+        store.build_index().unwrap();
+        assert!(store.n_trees().unwrap() > 0);
+
+        let results = store.search_exact(&[0.0, 1.0, 0.0, 0.0], 2).unwrap();
+        let ids: Vec<u32> = results.iter().map(|r| r.id).collect();
+        assert_eq!(ids, vec![1, 2]);
+        assert!((results[1].score - 0.8).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_delta_segment() {
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("test.db");
+        let mut store = VectorStore::new(&db_path, 4).unwrap();
+
+        let chunk = |content: &str| Chunk::new(content.to_string(), 1, 2, ChunkKind::Function, "lib.rs".to_string());
+        store
+            .insert_chunks(vec![
+                EmbeddedChunk::new(chunk("fn parse() {}"), vec![1.0, 0.0, 0.0, 0.0]),
+                EmbeddedChunk::new(chunk("fn render() {}"), vec![0.0, 1.0, 0.0, 0.0]),
+            ])
+            .unwrap();
+        store.build_index().unwrap();
+        assert_eq!(store.pending_vectors().unwrap(), 0);
+
+        // Changes after the build are searchable without rebuilding
+        let flush = EmbeddedChunk::new(chunk("fn flush() {}"), vec![0.0, 0.0, 1.0, 0.0]);
+        let ids = store.insert_chunks_with_ids(vec![flush]).unwrap();
+        assert_eq!(store.delete_chunks(&[0, 7]).unwrap(), 1);
+        assert_eq!(store.delete_chunks(&[0]).unwrap(), 0);
+        store.build_index().unwrap();
+        assert!(store.is_indexed());
+        assert_eq!(store.pending_vectors().unwrap(), 2);
+        assert_eq!(store.get_vector(ids[0]).unwrap(), Some(vec![0.0, 0.0, 1.0, 0.0]));
+        assert_eq!(store.vector_ids().unwrap(), Some(vec![1, 2]));
+        let results = store.search_exact(&[0.0, 0.0, 1.0, 0.0], 3).unwrap();
+        assert_eq!(results.iter().map(|r| r.id).collect::<Vec<_>>(), vec![2, 1]);
+
+        // Reopening keeps the delta segment; compacting folds it in
+        drop(store);
+        let mut store = VectorStore::new(&db_path, 4).unwrap();
+        assert_eq!(store.pending_vectors().unwrap(), 2);
+        assert_eq!(store.compact_index().unwrap(), 2);
+        assert_eq!(store.pending_vectors().unwrap(), 0);
+        assert_eq!(store.vector_ids().unwrap(), Some(vec![1, 2]));
+        assert_eq!(store.compact_index().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_shards() {
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("test.db");
+        let options = StoreOptions { shards: 4, ..StoreOptions::default() };
+        let mut store = VectorStore::with_options(&db_path, 2, &options).unwrap();
+        assert_eq!(store.shard_count(), 4);
+
+        let chunks = (0..20)
+            .map(|i| {
+                let chunk = Chunk::new(format!("fn f{}() {{}}", i), 1, 2, ChunkKind::Function, format!("src/f{}.rs", i));
+                EmbeddedChunk::new(chunk, vec![1.0, i as f32])
+            })
+            .collect();
+        store.insert_chunks(chunks).unwrap();
+        store.build_index().unwrap();
+        assert_eq!(store.vector_ids().unwrap().unwrap().len(), 20);
+        assert_eq!(store.get_vector(7).unwrap(), Some(vec![1.0, 7.0]));
+        let results = store.search_index(&[1.0, 19.0], 3).unwrap();
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].id, 19);
+
+        // Vectors are found by their recorded shard after paths move
+        store.rebase_paths("src/", "/checkout/src/").unwrap();
+        assert_eq!(store.get_vector(7).unwrap(), Some(vec![1.0, 7.0]));
+        assert_eq!(store.search_exact(&[1.0, 19.0], 20).unwrap().len(), 20);
+        assert_eq!(store.delete_chunks(&[7]).unwrap(), 1);
+
+        // The shard count stays with the store
+        drop(store);
+        let store = VectorStore::new(&db_path, 2).unwrap();
+        assert_eq!(store.shard_count(), 4);
+        assert_eq!(shard_of("src/f1.rs", 1), 0);
+    }
+
+    #[test]
+    fn test_dot() {
+        // Two full lanes of 8 and a remainder of 3
+        let a: Vec<f32> = (0..19).map(|i| i as f32).collect();
+        let b: Vec<f32> = (0..19).map(|i| (i % 3) as f32).collect();
+        let naive: f32 = a.iter().zip(&b).map(|(x, y)| x * y).sum();
+        assert_eq!(dot(&a, &b), naive);
+        assert_eq!(cosine_similarity(&[0.0, 0.0], &[1.0, 0.0]), 0.0);
+    }
+
+    #[test]
+    fn test_search_filtered() {
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("test.db");
+        let mut store = VectorStore::new(&db_path, 4).unwrap();
+
+        let chunk = |path: &str, kind| Chunk::new("fn f() {}".to_string(), 1, 2, kind, path.to_string());
+        let mut generated = chunk("./src/c.rs", ChunkKind::Struct);
+        generated.tags = vec!["generated".to_string()];
+        generated.package = Some("gen".to_string());
+        store
+            .insert_chunks(vec![
+                EmbeddedChunk::new(chunk("./src/a.rs", ChunkKind::Function), vec![1.0, 0.0, 0.0, 0.0]),
+                EmbeddedChunk::new(chunk("./src/b.py", ChunkKind::Function), vec![0.9, 0.1, 0.0, 0.0]),
+                EmbeddedChunk::new(generated, vec![0.0, 1.0, 0.0, 0.0]),
+            ])
+            .unwrap();
+        store.build_index().unwrap();
+
+        let rust = |path: &str, _kind: &str, _tags: &[String], _package: Option<&str>| path.ends_with(".rs");
+        let ids: Vec<u32> =
+            store.search_filtered(&[1.0, 0.0, 0.0, 0.0], 5, &rust).unwrap().iter().map(|r| r.id).collect();
+        assert_eq!(ids, vec![0, 2]);
+
+        let functions = |_path: &str, kind: &str, _tags: &[String], _package: Option<&str>| kind == "Function";
+        assert_eq!(store.search_filtered(&[0.0, 1.0, 0.0, 0.0], 1, &functions).unwrap()[0].id, 1);
+
+        // Tags and the package are stored with the chunk
+        let handwritten =
+            |_path: &str, _kind: &str, tags: &[String], _package: Option<&str>| !tags.contains(&"generated".to_string());
+        let results = store.search_filtered(&[0.0, 1.0, 0.0, 0.0], 5, &handwritten).unwrap();
+        assert_eq!(results.iter().map(|r| r.id).collect::<Vec<_>>(), vec![1, 0]);
+        let generated = store.get_chunk_as_result(2).unwrap().unwrap();
+        assert_eq!(generated.tags, ["generated"]);
+        assert_eq!(generated.package.as_deref(), Some("gen"));
+        assert_eq!(store.package_counts().unwrap(), BTreeMap::from([("gen".to_string(), (1, 1))]));
+    }
+
+    #[test]
+    fn test_file_vectors() {
+        let temp_dir = tempdir().unwrap();
+        let mut store = VectorStore::new(&temp_dir.path().join("test.db"), 2).unwrap();
+        let billing = temp_dir.path().join("billing.rs");
+        let auth = temp_dir.path().join("auth.rs");
+        std::fs::write(&billing, "fn total() {}").unwrap();
+        std::fs::write(&auth, "fn login() {}").unwrap();
+        store.update_file_metadata(&billing, vec![0, 1]).unwrap();
+        store.update_file_metadata(&auth, vec![2]).unwrap();
+        let path = |file: &Path| file.to_string_lossy().to_string();
+
+        assert!(!store.has_file_vectors().unwrap());
+        let vectors: Vec<(String, FileVector)> = store
+            .stale_file_vectors()
+            .unwrap()
+            .into_iter()
+            .map(|(file, meta)| {
+                let embedding = if file == path(&billing) { vec![1.0, 0.0] } else { vec![0.0, 1.0] };
+                (file, FileVector { hash: meta.hash, embedding })
+            })
+            .collect();
+        assert_eq!(vectors.len(), 2);
+        store.put_file_vectors(&vectors).unwrap();
+        assert!(store.stale_file_vectors().unwrap().is_empty());
+
+        let ranked = store.rank_files(&[0.9, 0.1], 1).unwrap();
+        assert_eq!(ranked.len(), 1);
+        assert_eq!(ranked[0].0, path(&billing));
+        assert_eq!(store.files_chunk_ids(&[path(&billing)]).unwrap(), [0, 1]);
+
+        // An edited file needs a new summary; a removed one loses its summary
+        std::fs::write(&billing, "fn total() -> u64 { 0 }").unwrap();
+        store.update_file_metadata(&billing, vec![3]).unwrap();
+        let stale: Vec<String> = store.stale_file_vectors().unwrap().into_iter().map(|(file, _)| file).collect();
+        assert_eq!(stale, [path(&billing)]);
+        store.remove_file_metadata(&auth).unwrap();
+        assert_eq!(store.rank_files(&[0.0, 1.0], 5).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_commits() {
+        let temp_dir = tempdir().unwrap();
+        let mut store = VectorStore::new(&temp_dir.path().join("test.db"), 2).unwrap();
+        let commit = |hash: &str, embedding: Vec<f32>| CommitRecord {
+            hash: hash.to_string(),
+            message: format!("Commit {}", hash),
+            embedding,
+            ..Default::default()
+        };
+        store.update_commits(&[commit("aaa", vec![1.0, 0.0]), commit("bbb", vec![0.0, 1.0])], &[]).unwrap();
+        assert_eq!(store.commit_hashes().unwrap(), HashSet::from(["aaa".to_string(), "bbb".to_string()]));
+
+        let ranked = store.rank_commits(&[0.2, 0.9], 1).unwrap();
+        assert_eq!(ranked.len(), 1);
+        assert_eq!(ranked[0].0.hash, "bbb");
+
+        store.update_commits(&[], &["bbb".to_string()]).unwrap();
+        assert_eq!(store.rank_commits(&[0.2, 0.9], 5).unwrap()[0].0.hash, "aaa");
+        // Commits are not chunks: code search and file bookkeeping don't see them
+        assert_eq!(store.stats().unwrap().total_chunks, 0);
     }
 
     #[test]
@@ -880,4 +2168,62 @@ mod tests {
             assert!(metadata.is_some());
         }
     }
+
+    #[test]
+    fn test_ids_not_reused_after_reopen() {
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("test.db");
+        let chunk = |name: &str| {
+            EmbeddedChunk::new(
+                Chunk::new(format!("fn {}() {{}}", name), 0, 1, ChunkKind::Function, "test.rs".to_string()),
+                vec![1.0, 0.0, 0.0, 0.0],
+            )
+        };
+
+        let mut store = VectorStore::new(&db_path, 4).unwrap();
+        store.insert_chunks_with_ids(vec![chunk("a"), chunk("b"), chunk("c")]).unwrap();
+        store.delete_chunks(&[0]).unwrap();
+        drop(store);
+
+        // Two chunks left, but ID 2 is taken: the next one must be 3
+        let mut store = VectorStore::new(&db_path, 4).unwrap();
+        assert_eq!(store.next_id(), 3);
+        assert_eq!(store.insert_chunks_with_ids(vec![chunk("d")]).unwrap(), vec![3]);
+        assert!(store.get_chunk(2).unwrap().unwrap().content.contains("fn c()"));
+    }
+
+    #[test]
+    fn test_ids_of_deleted_indexed_chunks_not_reused() {
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("test.db");
+        let chunk = |name: &str, embedding: Vec<f32>| {
+            EmbeddedChunk::new(
+                Chunk::new(format!("fn {}() {{}}", name), 0, 1, ChunkKind::Function, "test.rs".to_string()),
+                embedding,
+            )
+        };
+
+        let mut store = VectorStore::new(&db_path, 4).unwrap();
+        store
+            .insert_chunks_with_ids(vec![
+                chunk("a", vec![1.0, 0.0, 0.0, 0.0]),
+                chunk("b", vec![0.0, 1.0, 0.0, 0.0]),
+                chunk("c", vec![0.0, 0.0, 1.0, 0.0]),
+            ])
+            .unwrap();
+        store.build_index().unwrap();
+        // The top IDs stay in the arroy index behind tombstones
+        store.delete_chunks(&[1, 2]).unwrap();
+        drop(store);
+
+        let mut store = VectorStore::new(&db_path, 4).unwrap();
+        assert_eq!(store.next_id(), 3);
+        assert_eq!(store.insert_chunks_with_ids(vec![chunk("d", vec![0.0, 0.0, 0.0, 1.0])]).unwrap(), vec![3]);
+        store.compact_index().unwrap();
+
+        let results = store.search_exact(&[0.0, 0.0, 0.0, 1.0], 3).unwrap();
+        let ids: Vec<u32> = results.iter().map(|result| result.id).collect();
+        assert_eq!(ids, vec![3, 0]);
+        assert!(results[0].content.contains("fn d()"));
+    }
 }